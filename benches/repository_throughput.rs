@@ -0,0 +1,127 @@
+//! Compare list and search throughput of the RocksDB and in-memory repositories
+//!
+//! Run with `cargo bench --features rocksdb --bench repository_throughput`
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use mcp::adapter::out_adapters::{
+    InMemoryContextRepository, RocksDbContextRepository, SimpleEmbeddingService,
+};
+use mcp::application::{ContextManagementService, ContextSearchService};
+use mcp::domain::{ContextMetadata, SearchOptions, StoreOptions};
+use mcp::ports::in_ports::{ContextManagementPort, ContextSearchPort};
+use mcp::ports::out_ports::ContextRepositoryPort;
+
+const CONTEXTS: usize = 5_000;
+const PAGE_SIZE: usize = 100;
+const SEARCHES: usize = 200;
+
+const TOPICS: [&str; 5] = [
+    "neural networks and machine learning",
+    "rust systems programming and memory safety",
+    "database indexing and query planning",
+    "distributed consensus and replication",
+    "natural language processing with transformers",
+];
+
+struct Timings {
+    store: Duration,
+    list: Duration,
+    search: Duration,
+}
+
+async fn run(repository: Arc<dyn ContextRepositoryPort + Send + Sync>) -> Timings {
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let manager = ContextManagementService::new(
+        repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000,
+        200,
+    );
+    let search = ContextSearchService::new(
+        repository.clone(),
+        embedding_service.clone(),
+        embedding_service,
+        10,
+    );
+
+    let start = Instant::now();
+    for i in 0..CONTEXTS {
+        let topic = TOPICS[i % TOPICS.len()];
+        let metadata = ContextMetadata {
+            source: None,
+            content_type: None,
+            content_hash: None,
+            tags: vec![format!("topic-{}", i % TOPICS.len())],
+            custom: HashMap::new(),
+        };
+        manager
+            .store_context(
+                format!("Document {} about {}", i, topic),
+                metadata,
+                StoreOptions::default(),
+            )
+            .await
+            .unwrap();
+    }
+    let store = start.elapsed();
+
+    let start = Instant::now();
+    let mut offset = 0;
+    loop {
+        let page = repository.list_all(PAGE_SIZE, offset).await.unwrap();
+        if page.is_empty() {
+            break;
+        }
+        offset += page.len();
+    }
+    assert_eq!(offset, CONTEXTS);
+    let list = start.elapsed();
+
+    let start = Instant::now();
+    for i in 0..SEARCHES {
+        search
+            .search(
+                TOPICS[i % TOPICS.len()].to_string(),
+                10,
+                SearchOptions::default(),
+            )
+            .await
+            .unwrap();
+    }
+    let search = start.elapsed();
+
+    Timings {
+        store,
+        list,
+        search,
+    }
+}
+
+fn report(name: &str, timings: &Timings) {
+    println!(
+        "{:<10} store {:>8.1} ctx/s   list {:>10.1} ctx/s   search {:>8.1} queries/s",
+        name,
+        CONTEXTS as f64 / timings.store.as_secs_f64(),
+        CONTEXTS as f64 / timings.list.as_secs_f64(),
+        SEARCHES as f64 / timings.search.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let memory = runtime.block_on(run(Arc::new(InMemoryContextRepository::new())));
+    report("memory", &memory);
+
+    let path = std::env::temp_dir().join(format!("mcp-bench-{}", uuid::Uuid::new_v4()));
+    let rocksdb = runtime.block_on(run(Arc::new(
+        RocksDbContextRepository::open(&path).unwrap(),
+    )));
+    report("rocksdb", &rocksdb);
+
+    let _ = std::fs::remove_dir_all(&path);
+}
@@ -0,0 +1,92 @@
+//! Compare query latency and recall of exact and HNSW vector search over 100k chunks
+//!
+//! Run with `cargo bench --bench vector_index_search`
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use mcp::adapter::out_adapters::InMemoryVectorIndex;
+use mcp::domain::ContextChunk;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use uuid::Uuid;
+
+const CHUNKS: usize = 100_000;
+const CHUNKS_PER_CONTEXT: usize = 10;
+const DIMENSION: usize = 64;
+const QUERIES: usize = 200;
+const LIMIT: usize = 10;
+
+fn random_vector(rng: &mut StdRng) -> Vec<f32> {
+    (0..DIMENSION).map(|_| rng.gen_range(-1.0..1.0)).collect()
+}
+
+fn fill(index: &InMemoryVectorIndex, chunks: &[ContextChunk]) -> Duration {
+    let start = Instant::now();
+    for context in chunks.chunks(CHUNKS_PER_CONTEXT) {
+        index.replace(context, &[]);
+    }
+    start.elapsed()
+}
+
+/// Time every query, returning the ids found for each
+fn query(index: &InMemoryVectorIndex, queries: &[Vec<f32>]) -> (Duration, Vec<HashSet<Uuid>>) {
+    let start = Instant::now();
+    let found = queries
+        .iter()
+        .map(|query| {
+            index
+                .search(query, &[], LIMIT)
+                .unwrap()
+                .into_iter()
+                .map(|(chunk, _)| chunk.chunk_id)
+                .collect()
+        })
+        .collect();
+    (start.elapsed(), found)
+}
+
+fn report(name: &str, build: Duration, search: Duration) {
+    println!(
+        "{:<6} build {:>8.1} chunks/s   search {:>9.1} queries/s   {:>8.3} ms/query",
+        name,
+        CHUNKS as f64 / build.as_secs_f64(),
+        QUERIES as f64 / search.as_secs_f64(),
+        search.as_secs_f64() * 1000.0 / QUERIES as f64,
+    );
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let chunks: Vec<ContextChunk> = (0..CHUNKS)
+        .map(|position| ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id: Uuid::from_u128((position / CHUNKS_PER_CONTEXT) as u128),
+            content: String::new(),
+            position: position % CHUNKS_PER_CONTEXT,
+            embedding: Some(random_vector(&mut rng)),
+        })
+        .collect();
+    let queries: Vec<Vec<f32>> = (0..QUERIES).map(|_| random_vector(&mut rng)).collect();
+
+    let exact = InMemoryVectorIndex::new();
+    let exact_build = fill(&exact, &chunks);
+    let (exact_search, expected) = query(&exact, &queries);
+    report("exact", exact_build, exact_search);
+
+    let hnsw = InMemoryVectorIndex::hnsw(16, 200, 64);
+    let hnsw_build = fill(&hnsw, &chunks);
+    let (hnsw_search, found) = query(&hnsw, &queries);
+    report("hnsw", hnsw_build, hnsw_search);
+
+    let hits: usize = expected
+        .iter()
+        .zip(&found)
+        .map(|(expected, found)| expected.intersection(found).count())
+        .sum();
+    println!(
+        "hnsw recall@{} {:.3}",
+        LIMIT,
+        hits as f64 / (QUERIES * LIMIT) as f64
+    );
+}
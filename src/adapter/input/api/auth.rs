@@ -0,0 +1,394 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use super::handlers::ApiError;
+use crate::config::{AuthMode, JwtAlgorithm, JwtConfig, ServerConfig};
+use crate::domain::{McpError, McpResult};
+
+/// Header carrying the API key as an alternative to a bearer token
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// The API key requests must present
+pub struct ApiKeyAuth {
+    digest: [u8; 32],
+}
+
+impl ApiKeyAuth {
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            digest: Sha256::digest(api_key.as_bytes()).into(),
+        }
+    }
+
+    /// Require the configured key, or `None` if no key is configured
+    pub fn from_config(api_key: Option<&str>) -> Option<Self> {
+        api_key.filter(|key| !key.is_empty()).map(Self::new)
+    }
+
+    /// Whether `presented` is the API key, taking the same time however much of it matches
+    ///
+    /// Both keys are hashed first, so neither their contents nor their lengths show in the
+    /// comparison time.
+    pub fn accepts(&self, presented: &str) -> bool {
+        let presented: [u8; 32] = Sha256::digest(presented.as_bytes()).into();
+        presented
+            .iter()
+            .zip(&self.digest)
+            .fold(0, |difference, (a, b)| difference | (a ^ b))
+            == 0
+    }
+}
+
+/// The caller a bearer token identifies, added to the extensions of the requests it signs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Principal {
+    /// The token's `sub` claim
+    pub subject: String,
+
+    /// Scopes granted by the token's `scope` claim
+    pub scopes: Vec<String>,
+}
+
+/// Claims read from a bearer token; `exp`, `iss` and `aud` are checked while decoding
+#[derive(Debug, Deserialize)]
+struct Claims {
+    sub: String,
+
+    /// Space-separated scopes
+    #[serde(default)]
+    scope: String,
+}
+
+/// Verifies JSON Web Tokens presented as bearer tokens
+pub struct JwtAuth {
+    key: DecodingKey,
+    validation: Validation,
+    required_scope: Option<String>,
+}
+
+impl JwtAuth {
+    /// Accept unexpired tokens signed with `key` and issued by `issuer` for `audience`
+    pub fn new(key: DecodingKey, algorithm: Algorithm, issuer: &str, audience: &str) -> Self {
+        let mut validation = Validation::new(algorithm);
+        validation.set_issuer(&[issuer]);
+        validation.set_audience(&[audience]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        Self {
+            key,
+            validation,
+            required_scope: None,
+        }
+    }
+
+    /// Only accept tokens granting `scope`, rejecting others as forbidden
+    pub fn with_required_scope(mut self, scope: Option<String>) -> Self {
+        self.required_scope = scope;
+        self
+    }
+
+    /// Allow `seconds` of clock skew when checking expiry
+    pub fn with_leeway(mut self, seconds: u64) -> Self {
+        self.validation.leeway = seconds;
+        self
+    }
+
+    /// Verify tokens as configured, failing if the key or a required claim isn't set
+    pub fn from_config(config: &JwtConfig) -> McpResult<Self> {
+        let (key, algorithm) = match config.algorithm {
+            JwtAlgorithm::HS256 => {
+                let secret = config.secret.as_deref().ok_or_else(|| {
+                    McpError::ValidationError("server.jwt.secret is required for HS256".into())
+                })?;
+                (
+                    DecodingKey::from_secret(secret.as_bytes()),
+                    Algorithm::HS256,
+                )
+            }
+            JwtAlgorithm::RS256 => {
+                let path = config.public_key_path.as_deref().ok_or_else(|| {
+                    McpError::ValidationError(
+                        "server.jwt.public_key_path is required for RS256".into(),
+                    )
+                })?;
+                let pem = std::fs::read(path).map_err(|e| {
+                    McpError::ValidationError(format!("Failed to read {}: {}", path, e))
+                })?;
+                let key = DecodingKey::from_rsa_pem(&pem).map_err(|e| {
+                    McpError::ValidationError(format!("Invalid public key in {}: {}", path, e))
+                })?;
+                (key, Algorithm::RS256)
+            }
+        };
+
+        let issuer = config
+            .issuer
+            .as_deref()
+            .ok_or_else(|| McpError::ValidationError("server.jwt.issuer is required".into()))?;
+        let audience = config
+            .audience
+            .as_deref()
+            .ok_or_else(|| McpError::ValidationError("server.jwt.audience is required".into()))?;
+
+        Ok(Self::new(key, algorithm, issuer, audience)
+            .with_required_scope(config.required_scope.clone())
+            .with_leeway(config.leeway_seconds))
+    }
+
+    /// The caller a token identifies, if it is valid and grants the required scope
+    pub fn verify(&self, token: &str) -> McpResult<Principal> {
+        let claims = decode::<Claims>(token, &self.key, &self.validation)
+            .map_err(|err| {
+                let message = match err.kind() {
+                    ErrorKind::ExpiredSignature => "Token has expired".to_string(),
+                    ErrorKind::InvalidAudience => "Token is for another audience".to_string(),
+                    ErrorKind::InvalidIssuer => "Token is from an unknown issuer".to_string(),
+                    _ => format!("Invalid token: {}", err),
+                };
+                McpError::AuthenticationError(message)
+            })?
+            .claims;
+
+        let principal = Principal {
+            subject: claims.sub,
+            scopes: claims.scope.split_whitespace().map(String::from).collect(),
+        };
+        if let Some(scope) = &self.required_scope {
+            if !principal.scopes.contains(scope) {
+                return Err(McpError::AuthorizationError(format!(
+                    "Token lacks the {} scope",
+                    scope
+                )));
+            }
+        }
+        Ok(principal)
+    }
+}
+
+/// How requests to the protected routes authenticate
+pub enum Authenticator {
+    ApiKey(ApiKeyAuth),
+    Jwt(JwtAuth),
+}
+
+impl Authenticator {
+    /// The configured authentication, or `None` if requests needn't authenticate
+    pub fn from_config(config: &ServerConfig) -> McpResult<Option<Self>> {
+        match config.auth {
+            AuthMode::ApiKey => {
+                Ok(ApiKeyAuth::from_config(config.api_key.as_deref()).map(Self::ApiKey))
+            }
+            AuthMode::Jwt => JwtAuth::from_config(&config.jwt).map(|auth| Some(Self::Jwt(auth))),
+        }
+    }
+
+    /// Check the credentials of a request, returning the caller if they identify one
+    pub fn authenticate(&self, headers: &HeaderMap) -> McpResult<Option<Principal>> {
+        match self {
+            Self::ApiKey(auth) => match presented_key(headers) {
+                Some(key) if auth.accepts(key) => Ok(None),
+                Some(_) => Err(McpError::AuthenticationError("Invalid API key".into())),
+                None => Err(McpError::AuthenticationError("Missing API key".into())),
+            },
+            Self::Jwt(auth) => {
+                let token = bearer_token(headers)
+                    .ok_or_else(|| McpError::AuthenticationError("Missing bearer token".into()))?;
+                auth.verify(token).map(Some)
+            }
+        }
+    }
+}
+
+/// The token of an `Authorization: Bearer <token>` header
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// The API key of a request, from `Authorization: Bearer <key>` or `X-Api-Key`
+pub(super) fn presented_key(headers: &HeaderMap) -> Option<&str> {
+    bearer_token(headers).or_else(|| {
+        headers
+            .get(API_KEY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+    })
+}
+
+/// Middleware rejecting requests to the routes it is applied to unless they authenticate,
+/// and recording the caller in the request extensions when the credentials identify one
+pub async fn authenticate(
+    State(auth): State<Arc<Authenticator>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match auth.authenticate(request.headers()) {
+        Ok(principal) => {
+            if let Some(principal) = principal {
+                request.extensions_mut().insert(principal);
+            }
+            next.run(request).await
+        }
+        Err(err @ McpError::AuthenticationError(_)) => {
+            let mut response = ApiError::from(err).into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            response
+        }
+        Err(err) => ApiError::from(err).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    const SECRET: &[u8] = b"test-signing-key";
+
+    fn jwt_auth() -> JwtAuth {
+        JwtAuth::new(
+            DecodingKey::from_secret(SECRET),
+            Algorithm::HS256,
+            "https://issuer.test",
+            "mcp",
+        )
+    }
+
+    /// A token signed with the test key, expiring `expires_in` seconds from now
+    fn token(audience: &str, expires_in: i64, scope: &str) -> String {
+        let claims = json!({
+            "sub": "alice",
+            "iss": "https://issuer.test",
+            "aud": audience,
+            "exp": Utc::now().timestamp() + expires_in,
+            "scope": scope,
+        });
+        encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_only_the_configured_key_is_accepted() {
+        let auth = ApiKeyAuth::new("s3cret");
+        assert!(auth.accepts("s3cret"));
+        assert!(!auth.accepts("s3cre"));
+        assert!(!auth.accepts("s3cret "));
+        assert!(!auth.accepts(""));
+
+        assert!(ApiKeyAuth::from_config(None).is_none());
+        assert!(ApiKeyAuth::from_config(Some("")).is_none());
+    }
+
+    #[test]
+    fn test_key_is_read_from_either_header() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(presented_key(&headers), None);
+
+        headers.insert(API_KEY_HEADER, HeaderValue::from_static("from-header"));
+        assert_eq!(presented_key(&headers), Some("from-header"));
+
+        // A bearer token takes precedence
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Bearer from-bearer"),
+        );
+        assert_eq!(presented_key(&headers), Some("from-bearer"));
+
+        // Other schemes aren't API keys
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::AUTHORIZATION,
+            HeaderValue::from_static("Basic dXNlcjpwYXNz"),
+        );
+        assert_eq!(presented_key(&headers), None);
+    }
+
+    #[test]
+    fn test_valid_token_identifies_the_caller() {
+        let principal = jwt_auth()
+            .verify(&token("mcp", 300, "contexts:read contexts:write"))
+            .unwrap();
+
+        assert_eq!(principal.subject, "alice");
+        assert_eq!(principal.scopes, ["contexts:read", "contexts:write"]);
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let err = jwt_auth().verify(&token("mcp", -3600, "")).unwrap_err();
+        assert!(
+            matches!(err, McpError::AuthenticationError(message) if message.contains("expired"))
+        );
+
+        // Within the leeway an expired token still passes
+        let lenient = jwt_auth().with_leeway(7200);
+        assert!(lenient.verify(&token("mcp", -3600, "")).is_ok());
+    }
+
+    #[test]
+    fn test_token_for_another_audience_is_rejected() {
+        let err = jwt_auth()
+            .verify(&token("other-service", 300, ""))
+            .unwrap_err();
+        assert!(
+            matches!(err, McpError::AuthenticationError(message) if message.contains("audience"))
+        );
+    }
+
+    #[test]
+    fn test_token_signed_with_another_key_is_rejected() {
+        let forged = encode(
+            &Header::new(Algorithm::HS256),
+            &json!({
+                "sub": "mallory",
+                "iss": "https://issuer.test",
+                "aud": "mcp",
+                "exp": Utc::now().timestamp() + 300,
+            }),
+            &EncodingKey::from_secret(b"another-key"),
+        )
+        .unwrap();
+
+        assert!(matches!(
+            jwt_auth().verify(&forged),
+            Err(McpError::AuthenticationError(_))
+        ));
+        assert!(matches!(
+            jwt_auth().verify("not-a-token"),
+            Err(McpError::AuthenticationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_scope_is_forbidden() {
+        let auth = jwt_auth().with_required_scope(Some("contexts:write".to_string()));
+
+        let err = auth
+            .verify(&token("mcp", 300, "contexts:read"))
+            .unwrap_err();
+        assert!(
+            matches!(err, McpError::AuthorizationError(message) if message.contains("contexts:write"))
+        );
+        assert!(auth.verify(&token("mcp", 300, "contexts:write")).is_ok());
+    }
+}
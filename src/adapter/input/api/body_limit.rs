@@ -0,0 +1,57 @@
+use axum::{
+    extract::{multipart::MultipartError, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use http_body_util::LengthLimitError;
+
+use super::handlers::ApiError;
+use crate::domain::McpError;
+
+/// Middleware replacing the plain-text 413 of an oversized request body with the standard
+/// error body
+///
+/// The body limit answers by itself when `Content-Length` is too large, and extractors reject
+/// streamed bodies once they run past it; both responses pass through here.
+pub async fn payload_too_large_as_json(
+    State(limit): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE && !is_json(&response) {
+        return ApiError::from(McpError::PayloadTooLarge(limit)).into_response();
+    }
+    response
+}
+
+/// The error for a request body that couldn't be read to the end, which is a 413 if it ran
+/// past the body limit
+pub fn body_read_error(err: axum::Error, limit: usize) -> McpError {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(&err);
+    while let Some(cause) = source {
+        if cause.is::<LengthLimitError>() {
+            return McpError::PayloadTooLarge(limit);
+        }
+        source = cause.source();
+    }
+    McpError::ValidationError(format!("Failed to read the request body: {}", err))
+}
+
+/// The error for a multipart form that couldn't be read, which is a 413 if it ran past the
+/// body limit
+pub fn multipart_read_error(err: MultipartError, limit: usize) -> McpError {
+    if err.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        return McpError::PayloadTooLarge(limit);
+    }
+    McpError::ValidationError(format!("Invalid multipart form: {}", err.body_text()))
+}
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("application/json"))
+}
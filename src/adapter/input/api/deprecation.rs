@@ -0,0 +1,31 @@
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Header marking a response as coming from a deprecated route
+pub const DEPRECATION_HEADER: &str = "deprecation";
+
+/// Middleware marking every response of an unversioned alias as deprecated
+///
+/// Responses get `Deprecation: true` and a `Link` header naming the same path under
+/// `successor`, the version prefix clients should move to.
+pub async fn deprecated_alias(
+    State(successor): State<&'static str>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let successor_path = format!("{}{}", successor, request.uri().path());
+    let mut response = next.run(request).await;
+
+    let headers = response.headers_mut();
+    headers.insert(DEPRECATION_HEADER, HeaderValue::from_static("true"));
+    if let Ok(link) =
+        HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor_path))
+    {
+        headers.append(header::LINK, link);
+    }
+    response
+}
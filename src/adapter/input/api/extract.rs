@@ -0,0 +1,98 @@
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, PathRejection},
+        FromRequest, FromRequestParts, Json, Path, Query, Request,
+    },
+    http::{request::Parts, StatusCode},
+};
+use serde::de::DeserializeOwned;
+
+use super::handlers::{ApiError, AppState};
+use crate::domain::McpError;
+
+/// JSON request body, rejected with the standard error body instead of axum's plain text
+///
+/// Syntax errors are `MALFORMED_BODY`, a missing JSON content type is
+/// `UNSUPPORTED_MEDIA_TYPE`, and well-formed JSON of the wrong shape, such as a field with a
+/// malformed timestamp, is a `VALIDATION_ERROR`.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T> FromRequest<AppState> for ApiJson<T>
+where
+    T: DeserializeOwned,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(request: Request, state: &AppState) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(request, state).await {
+            Ok(Json(value)) => Ok(Self(value)),
+            Err(rejection) => Err(json_rejection(rejection, state.max_body_bytes)),
+        }
+    }
+}
+
+fn json_rejection(rejection: JsonRejection, limit: usize) -> ApiError {
+    match rejection {
+        JsonRejection::JsonDataError(err) => McpError::ValidationError(err.body_text()).into(),
+        JsonRejection::MissingJsonContentType(err) => ApiError::Rejected {
+            status: StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            code: "UNSUPPORTED_MEDIA_TYPE",
+            message: err.body_text(),
+        },
+        rejection if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE => {
+            McpError::PayloadTooLarge(limit).into()
+        }
+        rejection => ApiError::Rejected {
+            status: StatusCode::BAD_REQUEST,
+            code: "MALFORMED_BODY",
+            message: rejection.body_text(),
+        },
+    }
+}
+
+/// Path parameters, rejected as `INVALID_ID` when a segment doesn't parse, such as an id that
+/// isn't a UUID
+pub struct ApiPath<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiPath<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Path::<T>::from_request_parts(parts, state).await {
+            Ok(Path(value)) => Ok(Self(value)),
+            Err(PathRejection::FailedToDeserializePathParams(err)) => Err(ApiError::Rejected {
+                status: StatusCode::BAD_REQUEST,
+                code: "INVALID_ID",
+                message: err.body_text(),
+            }),
+            // The route has no such parameters, which is a bug rather than a bad request
+            Err(rejection) => Err(McpError::Unknown(rejection.body_text()).into()),
+        }
+    }
+}
+
+/// Query string parameters, rejected as a `VALIDATION_ERROR` when they don't parse
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        match Query::<T>::from_request_parts(parts, state).await {
+            Ok(Query(value)) => Ok(Self(value)),
+            Err(rejection) => Err(McpError::ValidationError(rejection.body_text()).into()),
+        }
+    }
+}
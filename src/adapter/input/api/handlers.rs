@@ -1,24 +1,111 @@
 use axum::{
-    extract::{Json, Path, State},
-    http::StatusCode,
-    response::{IntoResponse, Response},
+    body::Body,
+    extract::{
+        rejection::MultipartRejection,
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Json, Multipart, OriginalUri, State,
+    },
+    http::{
+        header::{ACCEPT, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH, VARY},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
 };
-use std::collections::HashMap;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
 use uuid::Uuid;
 
+use super::auth::Authenticator;
+use super::body_limit::{body_read_error, multipart_read_error};
+use super::extract::{ApiJson, ApiPath, ApiQuery};
 use super::models::{
-    ContextChunkDto, ContextMatchDto, ContextResponse, ErrorResponse, ReferenceRequest,
-    SearchRequest, SearchResponse, StoreContextRequest, UpdateContextRequest,
+    AddRelationRequest, ChildrenParams, ChunkResponse, CollectionResponse, ContextChunkDto,
+    ContextEventDto, ContextMatchDto, ContextPage, ContextResponse, CountResponse,
+    CreateCollectionRequest, DeleteByTagsParams, DeleteByTagsResponse, DeleteContextParams,
+    DeleteContextsRequest, DeleteContextsResponse, DeleteResultDto, DependencyStatusDto,
+    ErrorResponse, EvalDatasetRequest, EvalDatasetResponse, EvalRunRequest, EvalRunResponse,
+    EvalRunsParams, ExportParams, ExportRecord, ExportedChunk, ExportedContext, FieldErrorDto,
+    FormatParams, HealthResponse, ImportParams, ImportResponse, IngestUrlRequest,
+    ListContextsParams, MatchedContextDto, McpMessageParams, MissedEventsDto, OnConflict,
+    ReadinessResponse, ReferenceRequest, RelatedContextDto, RelationParams, RelationResponse,
+    ResponseMode, RevisionResponse, SearchQueryParams, SearchRequest, SearchResponse,
+    ShareContextRequest, ShareLinkResponse, StoreContextRequest, SubscribedDto,
+    SubscriptionRequest, TagCountDto, UpdateCollectionRequest, UpdateContextRequest,
 };
-use crate::domain::{Context, ContextMetadata, ContextReference, McpError};
-use crate::ports::in_ports::{ContextManagementPort, ContextSearchPort};
+use super::rate_limit::{RateLimiter, RouteRateLimits};
+use super::render::{wants_plain_text, ResponseFormat, PLAIN_TEXT_CONTENT_TYPE};
+use super::router::API_V1;
+use super::share::ShareLinkService;
+use crate::adapter::input::mcp::SseTransport;
+use crate::adapter::output::BroadcastEventPublisher;
+use crate::domain::{
+    Collection, Context, ContextChunk, ContextEvent, ContextEventFilter, ContextFilter,
+    ContextMatch, ContextMetadata, ContextReference, ContextRelation, ContextRevision,
+    DeleteOutcome, EvalCase, EvalDataset, EvalRun, FieldErrors, Highlighter, McpError, McpResult,
+    SearchOptions, SearchQuery, StoreOptions, TagMode, TagPolicy, TextQuery, Tokenizer,
+};
+use crate::ports::in_ports::{
+    CollectionPort, ContextManagementPort, ContextSearchPort, EvaluationPort, IngestionPort,
+    ReadinessPort,
+};
+
+/// Header carrying the number of contexts a list request matches before pagination
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Media type of export responses, one JSON record per line
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Type of the frame acknowledging a WebSocket subscription
+pub const SUBSCRIBED_MESSAGE: &str = "subscribed";
+
+/// Name of the event telling an `/events` subscriber it missed events
+pub const MISSED_EVENTS_EVENT: &str = "events.missed";
+
+/// Time between keep-alive comments on an idle `/events` stream
+pub const EVENT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Name of the event telling an MCP client where to post its messages
+pub const MCP_ENDPOINT_EVENT: &str = "endpoint";
+
+/// Name of the events carrying the responses and notifications sent to an MCP client
+pub const MCP_MESSAGE_EVENT: &str = "message";
+
+/// Number of contexts read per page while exporting
+const EXPORT_PAGE_SIZE: usize = 500;
 
 /// Application state shared between handlers
 #[derive(Clone)]
 pub struct AppState {
     pub context_manager: Arc<dyn ContextManagementPort + Send + Sync>,
     pub context_search: Arc<dyn ContextSearchPort + Send + Sync>,
+    pub share_links: Arc<ShareLinkService>,
+    pub share_rate_limiter: Arc<RateLimiter>,
+    pub rate_limits: Option<RouteRateLimits>,
+    pub max_body_bytes: usize,
+    pub max_results: usize,
+    pub max_page_size: usize,
+    pub legacy_routes: bool,
+    pub auth: Option<Arc<Authenticator>>,
+    pub tag_policy: Arc<TagPolicy>,
+    pub highlighter: Arc<Highlighter>,
+    pub tokenizer: Arc<dyn Tokenizer>,
+    pub evaluation: Arc<dyn EvaluationPort + Send + Sync>,
+    pub ingestion: Arc<dyn IngestionPort + Send + Sync>,
+    pub readiness: Arc<dyn ReadinessPort + Send + Sync>,
+    pub collections: Arc<dyn CollectionPort + Send + Sync>,
+    pub events: Arc<BroadcastEventPublisher>,
+    pub mcp: Arc<SseTransport>,
+    pub started_at: Instant,
 }
 
 /// Convert a domain Context to a ContextResponse DTO
@@ -28,129 +115,1638 @@ fn context_to_response(context: &Context) -> ContextResponse {
         content: context.content.clone(),
         source: context.metadata.source.clone(),
         content_type: context.metadata.content_type.clone(),
+        content_hash: context.metadata.content_hash.clone(),
+        tags: context.metadata.tags.clone(),
+        metadata: context.metadata.custom.clone(),
+        created_at: context.created_at.to_rfc3339(),
+        expires_at: context.expires_at.map(|dt| dt.to_rfc3339()),
+        version: context.version,
+        parent_id: context.parent_id,
+        collection_id: context.collection(),
+    }
+}
+
+/// Convert a domain Collection to a CollectionResponse
+fn collection_to_response(collection: Collection) -> CollectionResponse {
+    CollectionResponse {
+        id: collection.id,
+        name: collection.name,
+        description: collection.description,
+        created_at: collection.created_at.to_rfc3339(),
+    }
+}
+
+/// Convert a domain ContextRevision to a RevisionResponse
+fn revision_to_response(revision: ContextRevision) -> RevisionResponse {
+    RevisionResponse {
+        context_id: revision.context_id,
+        version: revision.version,
+        content: revision.content,
+        source: revision.metadata.source,
+        content_type: revision.metadata.content_type,
+        content_hash: revision.metadata.content_hash,
+        tags: revision.metadata.tags,
+        metadata: revision.metadata.custom,
+        expires_at: revision.expires_at.map(|dt| dt.to_rfc3339()),
+        archived_at: revision.archived_at.to_rfc3339(),
+    }
+}
+
+/// Convert a domain Context to the MatchedContextDto a search returns, with or without its content
+fn matched_context(context: &Context, with_content: bool) -> MatchedContextDto {
+    MatchedContextDto {
+        id: context.id,
+        content: with_content.then(|| context.content.clone()),
+        source: context.metadata.source.clone(),
+        content_type: context.metadata.content_type.clone(),
+        content_hash: context.metadata.content_hash.clone(),
         tags: context.metadata.tags.clone(),
         metadata: context.metadata.custom.clone(),
         created_at: context.created_at.to_rfc3339(),
         expires_at: context.expires_at.map(|dt| dt.to_rfc3339()),
+        version: context.version,
+        parent_id: context.parent_id,
+        collection_id: context.collection(),
+    }
+}
+
+/// Convert a domain ContextMatch to a ContextMatchDto holding as much as `mode` asks for,
+/// counting the tokens of its chunks with `tokenizer`
+fn match_to_dto(
+    m: ContextMatch,
+    mode: ResponseMode,
+    snippets: Option<Vec<String>>,
+    tokenizer: &dyn Tokenizer,
+) -> ContextMatchDto {
+    let id = m.context.id;
+    if mode == ResponseMode::IdsOnly {
+        return ContextMatchDto {
+            id,
+            context: None,
+            chunks: None,
+            score: m.score,
+            parent_id: m.context.parent_id,
+            snippets,
+        };
+    }
+
+    let parent_id = m.context.parent_id;
+    let context = matched_context(&m.context, mode == ResponseMode::Full);
+
+    let chunks = m.chunks.map(|chunks| {
+        chunks
+            .into_iter()
+            .map(|chunk| ContextChunkDto {
+                id: chunk.chunk_id,
+                token_count: tokenizer.count_tokens(&chunk.content),
+                content: chunk.content,
+                position: chunk.position,
+            })
+            .collect()
+    });
+
+    ContextMatchDto {
+        id,
+        context: Some(context),
+        chunks,
+        score: m.score,
+        parent_id,
+        snippets,
+    }
+}
+
+/// Convert a domain Context to the record an export writes for it
+fn exported_context(context: &Context) -> ExportedContext {
+    ExportedContext {
+        id: context.id,
+        content: context.content.clone(),
+        source: context.metadata.source.clone(),
+        content_type: context.metadata.content_type.clone(),
+        tags: context.metadata.tags.clone(),
+        metadata: context.metadata.custom.clone(),
+        created_at: context.created_at,
+        expires_at: context.expires_at,
+        version: context.version,
+        relations: context.relations.clone(),
+        parent_id: context.parent_id,
+        collection_id: context.collection_id,
+    }
+}
+
+/// Convert a domain ContextChunk to the record an export writes for it
+fn exported_chunk(chunk: ContextChunk) -> ExportedChunk {
+    ExportedChunk {
+        id: chunk.chunk_id,
+        context_id: chunk.context_id,
+        content: chunk.content,
+        position: chunk.position,
+        embedding: chunk.embedding,
+    }
+}
+
+/// Convert an exported context back to the domain Context it was made from
+fn imported_context(context: ExportedContext) -> Context {
+    Context {
+        id: context.id,
+        content: context.content,
+        metadata: ContextMetadata {
+            source: context.source,
+            content_type: context.content_type,
+            content_hash: None,
+            tags: context.tags,
+            custom: context.metadata,
+        },
+        created_at: context.created_at,
+        expires_at: context.expires_at,
+        version: context.version,
+        deleted_at: None,
+        relations: context.relations,
+        parent_id: context.parent_id,
+        collection_id: context.collection_id,
+    }
+}
+
+/// Convert an exported chunk back to the domain ContextChunk it was made from
+fn imported_chunk(chunk: ExportedChunk) -> ContextChunk {
+    ContextChunk {
+        context_id: chunk.context_id,
+        chunk_id: chunk.id,
+        content: chunk.content,
+        embedding: chunk.embedding,
+        position: chunk.position,
+    }
+}
+
+/// Handler streaming context changes as server-sent events, from the moment the client
+/// connects
+///
+/// Each `context.created`, `context.updated` or `context.deleted` event carries a
+/// [`ContextEventDto`]. A client too slow to keep up gets an `events.missed` event with how
+/// many it missed, after which it should reload what it caches. Comments are sent every
+/// [`EVENT_KEEP_ALIVE`] so idle connections stay open.
+pub async fn context_events(
+    State(state): State<AppState>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let events = stream::unfold(state.events.subscribe(), |mut receiver| async move {
+        let event = match receiver.recv().await {
+            Ok(event) => sse_event(event.kind.name(), &event_to_dto(&event)),
+            Err(RecvError::Lagged(missed)) => {
+                sse_event(MISSED_EVENTS_EVENT, &MissedEventsDto { missed })
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok(event), receiver))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::new().interval(EVENT_KEEP_ALIVE))
+}
+
+/// Handler upgrading to a WebSocket that sends the context changes a client subscribes to
+///
+/// The client's first message is a [`SubscriptionRequest`], answered with a
+/// [`SubscribedDto`] frame; from then on every matching change arrives as a
+/// [`ContextEventDto`] frame. Later messages replace the subscription. A client too slow to
+/// keep up is disconnected with close code 1013 rather than let events pile up.
+pub async fn subscribe_ws(State(state): State<AppState>, ws: WebSocketUpgrade) -> Response {
+    ws.on_upgrade(move |socket| serve_subscription(socket, state))
+}
+
+/// Send a subscriber the events it asks for until either side closes the socket
+async fn serve_subscription(mut socket: WebSocket, state: AppState) {
+    // Nothing is sent until the client says what it wants
+    let (mut filter, mut receiver) = loop {
+        match socket.recv().await {
+            Some(Ok(Message::Text(text))) => {
+                let Some(filter) = subscribe(&mut socket, &state.tag_policy, &text).await else {
+                    return reject(socket).await;
+                };
+                // Subscribed before the acknowledgement, so no later change is missed
+                let receiver = state.events.subscribe();
+                if !acknowledge(&mut socket, &filter).await {
+                    return;
+                }
+                break (filter, receiver);
+            }
+            Some(Ok(Message::Close(_))) => return finish_close(socket).await,
+            Some(Ok(_)) => {}
+            Some(Err(_)) | None => return,
+        }
+    };
+
+    loop {
+        tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let Some(replaced) = subscribe(&mut socket, &state.tag_policy, &text).await else {
+                        return reject(socket).await;
+                    };
+                    if !acknowledge(&mut socket, &replaced).await {
+                        return;
+                    }
+                    filter = replaced;
+                }
+                Some(Ok(Message::Close(_))) => return finish_close(socket).await,
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => return,
+            },
+            event = receiver.recv() => match event {
+                Ok(event) => {
+                    if filter.matches(&event) && !send_json(&mut socket, &event_to_dto(&event)).await {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    let reason = format!("Fell {} events behind", missed);
+                    return close_socket(socket, close_code::AGAIN, reason).await;
+                }
+                Err(RecvError::Closed) => {
+                    return close_socket(socket, close_code::AWAY, "Server shutting down").await;
+                }
+            },
+        }
+    }
+}
+
+/// The filter a subscription message asks for, or `None` after telling the client what's
+/// wrong with it
+async fn subscribe(
+    socket: &mut WebSocket,
+    policy: &TagPolicy,
+    text: &str,
+) -> Option<ContextEventFilter> {
+    match subscription_filter(policy, text) {
+        Ok(filter) => Some(filter),
+        Err(err) => {
+            let message = match err {
+                McpError::ValidationError(message) => message,
+                other => other.to_string(),
+            };
+            let error = ErrorResponse {
+                message,
+                code: "VALIDATION_ERROR".to_string(),
+                details: None,
+                request_id: None,
+            };
+            send_json(socket, &error).await;
+            None
+        }
+    }
+}
+
+/// Confirm a subscription to the client, returning whether it is still there
+async fn acknowledge(socket: &mut WebSocket, filter: &ContextEventFilter) -> bool {
+    let ack = SubscribedDto {
+        kind: SUBSCRIBED_MESSAGE.to_string(),
+        tags: filter.tags.clone(),
+    };
+    send_json(socket, &ack).await
+}
+
+/// Close the socket of a client that sent an invalid subscription
+async fn reject(socket: WebSocket) {
+    close_socket(socket, close_code::POLICY, "Invalid subscription").await
+}
+
+/// Finish a close the client started; reading on after its close frame sends the reply
+async fn finish_close(mut socket: WebSocket) {
+    while let Some(Ok(_)) = socket.recv().await {}
+}
+
+/// The filter a subscription message asks for, with tags normalized like stored tags
+fn subscription_filter(policy: &TagPolicy, text: &str) -> McpResult<ContextEventFilter> {
+    let request: SubscriptionRequest = serde_json::from_str(text)
+        .map_err(|e| McpError::ValidationError(format!("Invalid subscription: {}", e)))?;
+    Ok(ContextEventFilter {
+        tags: policy.normalize_all(request.tags.iter().map(String::as_str))?,
+        tag_mode: request.tag_mode,
+        kinds: request.events,
+    })
+}
+
+/// Send `message` as a JSON text frame, returning whether the client is still there
+async fn send_json(socket: &mut WebSocket, message: &impl serde::Serialize) -> bool {
+    match serde_json::to_string(message) {
+        Ok(text) => socket.send(Message::Text(text)).await.is_ok(),
+        Err(_) => true,
     }
 }
 
+/// Close `socket` with `code` and a short `reason`
+async fn close_socket(mut socket: WebSocket, code: u16, reason: impl Into<Cow<'static, str>>) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+/// Convert a domain ContextEvent to the data sent to subscribers
+fn event_to_dto(event: &ContextEvent) -> ContextEventDto {
+    ContextEventDto {
+        kind: event.kind.name().to_string(),
+        context_id: event.context_id,
+        tags: event.tags.clone(),
+        timestamp: event.at.to_rfc3339(),
+    }
+}
+
+/// A server-sent event named `name` with `data` as its JSON data
+fn sse_event(name: &str, data: &impl serde::Serialize) -> Event {
+    let event = Event::default().event(name);
+    match serde_json::to_string(data) {
+        Ok(data) => event.data(data),
+        Err(_) => event,
+    }
+}
+
+/// Handler opening an MCP session whose responses are streamed as server-sent events
+///
+/// The first event, `endpoint`, holds the URL to post the session's messages to; the response
+/// to each one, and every notification, arrives as a `message` event. Closing the stream ends
+/// the session.
+pub async fn mcp_sse(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (session_id, messages) = state.mcp.connect();
+    // Posted to the same prefix the stream was opened under, versioned or not
+    let endpoint = format!(
+        "{}/messages?sessionId={}",
+        uri.path().trim_end_matches("/sse"),
+        session_id
+    );
+
+    let endpoint =
+        stream::once(async move { Ok(Event::default().event(MCP_ENDPOINT_EVENT).data(endpoint)) });
+    let messages = messages.map(|message| Ok(sse_event(MCP_MESSAGE_EVENT, &message)));
+    Sse::new(endpoint.chain(messages)).keep_alive(KeepAlive::new().interval(EVENT_KEEP_ALIVE))
+}
+
+/// Handler for a JSON-RPC message of an MCP session, accepted with 202 and answered on the
+/// session's stream
+pub async fn mcp_message(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<McpMessageParams>,
+    message: String,
+) -> Result<StatusCode, ApiError> {
+    if !state.mcp.deliver(params.session_id, &message).await {
+        return Err(ApiError::Rejected {
+            status: StatusCode::NOT_FOUND,
+            code: "SESSION_NOT_FOUND",
+            message: format!("MCP session not found: {}", params.session_id),
+        });
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Handler for checking that the server is up, which needs no credentials
+pub async fn health(State(state): State<AppState>) -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "ok".to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        uptime_seconds: state.started_at.elapsed().as_secs(),
+    })
+}
+
+/// Handler for checking that the repository and embedding provider can serve requests,
+/// answering 503 when any of them can't
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let statuses = state.readiness.check_readiness().await;
+    let ready = statuses.iter().all(|status| status.is_healthy());
+
+    let response = ReadinessResponse {
+        status: if ready { "ready" } else { "unavailable" },
+        dependencies: statuses
+            .into_iter()
+            .map(|status| DependencyStatusDto {
+                status: if status.is_healthy() { "ok" } else { "error" },
+                name: status.name,
+                error: status.error,
+            })
+            .collect(),
+    };
+
+    let code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (code, Json(response))
+}
+
 /// Handler for storing a new context
 pub async fn store_context(
     State(state): State<AppState>,
-    Json(request): Json<StoreContextRequest>,
+    ApiJson(request): ApiJson<StoreContextRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
+    // Report every problem with the request at once
+    let mut errors = FieldErrors::default();
+    errors.check(
+        "content",
+        state.context_manager.check_content(&request.content),
+    )?;
+    let tags = errors.check(
+        "tags",
+        state
+            .tag_policy
+            .normalize_context_tags(request.tags.unwrap_or_default()),
+    )?;
+    let expires_at = errors.check(
+        "expires_at",
+        expiry_param(request.expires_at.as_deref(), request.ttl_seconds),
+    )?;
+    errors.into_result()?;
+
     // Prepare metadata from request
     let metadata = ContextMetadata {
         source: request.source,
         content_type: request.content_type,
         content_hash: None,
-        tags: request.tags.unwrap_or_default(),
+        tags: tags.unwrap_or_default(),
         custom: request.metadata.unwrap_or_default(),
     };
 
     // Store context
     let context = state
         .context_manager
-        .store_context(request.content, metadata)
+        .store_context(
+            request.content,
+            metadata,
+            StoreOptions {
+                expires_at: expires_at.flatten(),
+                chunking: request.chunking,
+                on_duplicate: request.on_duplicate,
+                parent_id: request.parent_id,
+                collection_id: request.collection_id,
+            },
+        )
         .await?;
 
     // Return response
     Ok((StatusCode::CREATED, Json(context_to_response(&context))))
 }
 
+/// Handler for storing an uploaded file as a new context
+///
+/// Takes `multipart/form-data` with a `file` part and optional `tags` (comma-separated),
+/// `source`, `content_type`, `collection` (ID or name), `expires_at`, `ttl_seconds` and
+/// `lossy` fields. The file must be
+/// UTF-8 unless `lossy=true`, which replaces invalid bytes instead. The source defaults to
+/// the file name and the content type is guessed from its extension when not given.
+pub async fn upload_context(
+    State(state): State<AppState>,
+    multipart: Result<Multipart, MultipartRejection>,
+) -> Result<impl IntoResponse, ApiError> {
+    let mut multipart = multipart.map_err(|err| McpError::ValidationError(err.body_text()))?;
+    let upload = read_upload(&mut multipart, state.max_body_bytes).await?;
+
+    let mut errors = FieldErrors::default();
+    let content = errors.check("file", upload.content())?;
+    if let Some(content) = &content {
+        errors.check("file", state.context_manager.check_content(content))?;
+    }
+    let tags = errors.check(
+        "tags",
+        match &upload.tags {
+            Some(tags) => state
+                .tag_policy
+                .normalize_context_tags(tags.split(',').map(str::trim).filter(|t| !t.is_empty())),
+            None => Ok(Vec::new()),
+        },
+    )?;
+    let ttl_seconds = errors.check(
+        "ttl_seconds",
+        upload
+            .ttl_seconds
+            .as_deref()
+            .map(|ttl| {
+                ttl.trim().parse::<u64>().map_err(|_| {
+                    McpError::ValidationError(format!(
+                        "ttl_seconds must be a whole number of seconds, got '{}'",
+                        ttl
+                    ))
+                })
+            })
+            .transpose(),
+    )?;
+    let expires_at = errors.check(
+        "expires_at",
+        expiry_param(upload.expires_at.as_deref(), ttl_seconds.flatten()),
+    )?;
+    let collection_id = errors.check(
+        "collection",
+        collection_param(&state, upload.collection.as_deref()).await,
+    )?;
+    errors.into_result()?;
+
+    let content_type = upload.content_type.clone().or_else(|| {
+        upload
+            .file_name
+            .as_deref()
+            .and_then(content_type_for_file)
+            .map(str::to_string)
+    });
+    let metadata = ContextMetadata {
+        source: upload.source.clone().or(upload.file_name.clone()),
+        content_type,
+        content_hash: None,
+        tags: tags.unwrap_or_default(),
+        custom: HashMap::new(),
+    };
+
+    let context = state
+        .context_manager
+        .store_context(
+            content.unwrap_or_default(),
+            metadata,
+            StoreOptions {
+                expires_at: expires_at.flatten(),
+                collection_id: collection_id.flatten(),
+                ..StoreOptions::default()
+            },
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(context_to_response(&context))))
+}
+
+/// The parts of an upload form, read before any of them is checked
+#[derive(Default)]
+struct Upload {
+    file: Option<Bytes>,
+    file_name: Option<String>,
+    tags: Option<String>,
+    source: Option<String>,
+    content_type: Option<String>,
+    collection: Option<String>,
+    expires_at: Option<String>,
+    ttl_seconds: Option<String>,
+    lossy: bool,
+}
+
+impl Upload {
+    /// The uploaded file decoded as UTF-8, lossily if the form asked for it
+    fn content(&self) -> McpResult<String> {
+        let Some(file) = &self.file else {
+            return Err(McpError::ValidationError("file is required".to_string()));
+        };
+        if self.lossy {
+            return Ok(String::from_utf8_lossy(file).into_owned());
+        }
+        String::from_utf8(file.to_vec()).map_err(|err| {
+            McpError::ValidationError(format!(
+                "file is not valid UTF-8 (at byte {}); send lossy=true to replace invalid bytes",
+                err.utf8_error().valid_up_to()
+            ))
+        })
+    }
+}
+
+/// Every field of an upload form; unknown or repeated fields are rejected
+async fn read_upload(multipart: &mut Multipart, limit: usize) -> McpResult<Upload> {
+    let mut upload = Upload::default();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| multipart_read_error(err, limit))?
+    {
+        let name = field.name().unwrap_or_default().to_string();
+        if name == "file" {
+            if upload.file.is_some() {
+                return Err(McpError::ValidationError(
+                    "Only one file can be uploaded at a time".to_string(),
+                ));
+            }
+            upload.file_name = field.file_name().map(str::to_string);
+            upload.file = Some(
+                field
+                    .bytes()
+                    .await
+                    .map_err(|err| multipart_read_error(err, limit))?,
+            );
+            continue;
+        }
+
+        let slot = match name.as_str() {
+            "tags" => &mut upload.tags,
+            "source" => &mut upload.source,
+            "content_type" => &mut upload.content_type,
+            "collection" => &mut upload.collection,
+            "expires_at" => &mut upload.expires_at,
+            "ttl_seconds" => &mut upload.ttl_seconds,
+            "lossy" => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|err| multipart_read_error(err, limit))?;
+                upload.lossy = match value.trim() {
+                    "true" => true,
+                    "false" => false,
+                    other => {
+                        return Err(McpError::ValidationError(format!(
+                            "lossy must be 'true' or 'false', got '{}'",
+                            other
+                        )))
+                    }
+                };
+                continue;
+            }
+            other => {
+                return Err(McpError::ValidationError(format!(
+                    "Unknown form field '{}'",
+                    other
+                )))
+            }
+        };
+        if slot.is_some() {
+            return Err(McpError::ValidationError(format!(
+                "Form field '{}' was given more than once",
+                name
+            )));
+        }
+        let value = field
+            .text()
+            .await
+            .map_err(|err| multipart_read_error(err, limit))?;
+        *slot = Some(value).filter(|value| !value.trim().is_empty());
+    }
+    Ok(upload)
+}
+
+/// The content type of a file going by its extension, for the common text formats and
+/// source files
+fn content_type_for_file(file_name: &str) -> Option<&'static str> {
+    let (_, extension) = file_name.rsplit_once('.')?;
+    let content_type = match extension.to_ascii_lowercase().as_str() {
+        "txt" | "text" | "log" => "text/plain",
+        "md" | "markdown" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "yaml" | "yml" => "application/yaml",
+        "toml" => "application/toml",
+        "rs" => "text/x-rust",
+        "py" => "text/x-python",
+        "go" => "text/x-go",
+        "java" => "text/x-java",
+        "c" | "h" => "text/x-c",
+        "cpp" | "cc" | "hpp" => "text/x-c++",
+        "js" | "mjs" => "text/javascript",
+        "ts" => "text/x-typescript",
+        _ => return None,
+    };
+    Some(content_type)
+}
+
+/// Handler for storing the document at a URL as a new context
+///
+/// The source is the URL and the content type the one the server answered with, or
+/// `text/plain` for HTML reduced to text. A server that fails to answer with the document is
+/// a 502.
+pub async fn ingest_url(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<IngestUrlRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let url = request.url.trim();
+    let mut errors = FieldErrors::default();
+    if url.is_empty() {
+        errors.add("url", "url must not be empty");
+    }
+    let tags = errors.check(
+        "tags",
+        state
+            .tag_policy
+            .normalize_context_tags(request.tags.unwrap_or_default()),
+    )?;
+    errors.into_result()?;
+
+    let document = state
+        .ingestion
+        .fetch_document(url, request.strip_html.unwrap_or(true))
+        .await
+        .map_err(ApiError::Upstream)?;
+
+    let metadata = ContextMetadata {
+        source: Some(url.to_string()),
+        content_type: document.content_type,
+        content_hash: None,
+        tags: tags.unwrap_or_default(),
+        custom: HashMap::new(),
+    };
+    let context = state
+        .context_manager
+        .store_context(document.content, metadata, StoreOptions::default())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(context_to_response(&context))))
+}
+
 /// Handler for retrieving a context by ID
+///
+/// The response carries the context's version hash as a strong `ETag`; a request whose
+/// `If-None-Match` names it gets a 304 with no body. JSON is returned unless the `Accept`
+/// header asks for `text/plain`, which gets just the content.
 pub async fn get_context(
     State(state): State<AppState>,
-    Path(context_id): Path<Uuid>,
-) -> Result<impl IntoResponse, ApiError> {
+    ApiPath(context_id): ApiPath<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
     let context = state.context_manager.get_context(context_id).await?;
-    Ok((StatusCode::OK, Json(context_to_response(&context))))
+
+    let (etag, unchanged) = context_etag(&context, &headers);
+    let response_headers = [(ETAG, etag), (VARY, ACCEPT.to_string())];
+    if unchanged {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    if wants_plain_text(&headers) {
+        return Ok((
+            StatusCode::OK,
+            response_headers,
+            [(CONTENT_TYPE, PLAIN_TEXT_CONTENT_TYPE)],
+            context.content,
+        )
+            .into_response());
+    }
+    Ok((
+        StatusCode::OK,
+        response_headers,
+        Json(context_to_response(&context)),
+    )
+        .into_response())
+}
+
+/// Handler for a context's content exactly as stored (`GET /contexts/:id/raw`)
+///
+/// The content type is the context's own, or plain text if it has none. The `ETag` and
+/// `If-None-Match` handling are those of `GET /contexts/:id`.
+pub async fn get_raw_context(
+    State(state): State<AppState>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let context = state.context_manager.get_context(context_id).await?;
+
+    let (etag, unchanged) = context_etag(&context, &headers);
+    let etag_header = [(ETAG, etag)];
+    if unchanged {
+        return Ok((StatusCode::NOT_MODIFIED, etag_header).into_response());
+    }
+
+    let content_type = context
+        .metadata
+        .content_type
+        .as_deref()
+        .and_then(|content_type| HeaderValue::from_str(content_type).ok())
+        .unwrap_or(HeaderValue::from_static(PLAIN_TEXT_CONTENT_TYPE));
+    Ok((
+        StatusCode::OK,
+        etag_header,
+        [(CONTENT_TYPE, content_type)],
+        context.content,
+    )
+        .into_response())
+}
+
+/// The context's version hash as a strong `ETag`, and whether `If-None-Match` names it
+fn context_etag(context: &Context, headers: &HeaderMap) -> (String, bool) {
+    let etag = format!("\"{}\"", context.version_hash());
+    let unchanged = headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| etag_matches(value, &etag));
+    (etag, unchanged)
+}
+
+/// Whether an `If-None-Match` value lists `etag` or is `*`, comparing weakly as RFC 9110
+/// asks for this header
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.strip_prefix("W/").unwrap_or(candidate) == etag
+    })
+}
+
+/// Handler for retrieving a single chunk by ID
+pub async fn get_chunk(
+    State(state): State<AppState>,
+    ApiPath(chunk_id): ApiPath<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let chunk = state.context_manager.get_chunk(chunk_id).await?;
+
+    Ok(Json(ChunkResponse {
+        id: chunk.chunk_id,
+        context_id: chunk.context_id,
+        content: chunk.content,
+        position: chunk.position,
+    }))
 }
 
 /// Handler for updating a context
+///
+/// With `If-Match: <version>` the update only applies while the context is still at that
+/// version.
 pub async fn update_context(
     State(state): State<AppState>,
-    Path(context_id): Path<Uuid>,
-    Json(request): Json<UpdateContextRequest>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<UpdateContextRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
+    // Report every problem with the request at once
+    let mut errors = FieldErrors::default();
+    let expected_version = errors.check(
+        "If-Match",
+        headers
+            .get(IF_MATCH)
+            .map(|value| expected_version(value.to_str().unwrap_or_default()))
+            .transpose(),
+    )?;
+    errors.check(
+        "content",
+        state.context_manager.check_content(&request.content),
+    )?;
+    let tags = errors.check(
+        "tags",
+        state
+            .tag_policy
+            .normalize_context_tags(request.tags.unwrap_or_default()),
+    )?;
+    let expires_at = errors.check(
+        "expires_at",
+        expiry_param(request.expires_at.as_deref(), request.ttl_seconds),
+    )?;
+    errors.into_result()?;
+
     // Prepare metadata from request
     let metadata = ContextMetadata {
         source: request.source,
         content_type: request.content_type,
         content_hash: None,
-        tags: request.tags.unwrap_or_default(),
+        tags: tags.unwrap_or_default(),
         custom: request.metadata.unwrap_or_default(),
     };
 
     // Update context
     let context = state
         .context_manager
-        .update_context(context_id, request.content, metadata)
+        .update_context(
+            context_id,
+            request.content,
+            metadata,
+            expires_at.flatten(),
+            expected_version.flatten(),
+        )
         .await?;
 
     // Return response
     Ok((StatusCode::OK, Json(context_to_response(&context))))
 }
 
+/// Handler for listing the prior states kept for a context, oldest first
+pub async fn list_revisions(
+    State(state): State<AppState>,
+    ApiPath(context_id): ApiPath<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let revisions = state.context_manager.list_revisions(context_id).await?;
+
+    Ok(Json(
+        revisions
+            .into_iter()
+            .map(revision_to_response)
+            .collect::<Vec<_>>(),
+    ))
+}
+
+/// Handler for retrieving the prior state of a context at a version
+pub async fn get_revision(
+    State(state): State<AppState>,
+    ApiPath((context_id, version)): ApiPath<(Uuid, u64)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let revision = state
+        .context_manager
+        .get_revision(context_id, version)
+        .await?;
+
+    Ok(Json(revision_to_response(revision)))
+}
+
+/// Handler for making a prior state of a context current again
+///
+/// Honours `If-Match` like an update, and keeps the replaced state as a revision.
+pub async fn restore_revision(
+    State(state): State<AppState>,
+    ApiPath((context_id, version)): ApiPath<(Uuid, u64)>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let expected_version = headers
+        .get(IF_MATCH)
+        .map(|value| expected_version(value.to_str().unwrap_or_default()))
+        .transpose()?;
+
+    let context = state
+        .context_manager
+        .restore_revision(context_id, version, expected_version)
+        .await?;
+
+    Ok(Json(context_to_response(&context)))
+}
+
+/// The version an `If-Match` value names, quoted or not
+fn expected_version(if_match: &str) -> McpResult<u64> {
+    let version = if_match.trim();
+    let version = version
+        .strip_prefix('"')
+        .and_then(|version| version.strip_suffix('"'))
+        .unwrap_or(version);
+    version
+        .parse()
+        .map_err(|_| McpError::ValidationError("If-Match must be a context version".to_string()))
+}
+
 /// Handler for deleting a context
+///
+/// The context is only marked deleted, so it can be restored, unless `hard=true` deletes it
+/// for good; soft-deleted contexts can still be deleted for good that way.
 pub async fn delete_context(
     State(state): State<AppState>,
-    Path(context_id): Path<Uuid>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    ApiQuery(params): ApiQuery<DeleteContextParams>,
 ) -> Result<impl IntoResponse, ApiError> {
-    state.context_manager.delete_context(context_id).await?;
+    if params.hard {
+        state.context_manager.delete_context(context_id).await?;
+    } else {
+        state
+            .context_manager
+            .soft_delete_context(context_id)
+            .await?;
+    }
     Ok(StatusCode::NO_CONTENT)
 }
 
-/// Handler for listing contexts
-pub async fn list_contexts(
+/// Handler for relating a context to another
+pub async fn add_relation(
     State(state): State<AppState>,
-    axum::extract::Query(params): axum::extract::Query<HashMap<String, String>>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    ApiJson(request): ApiJson<AddRelationRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
-    // Extract optional parameters
-    let tags = params.get("tags").map(|t| {
-        t.split(',')
-            .map(|s| s.trim().to_string())
-            .collect::<Vec<_>>()
-    });
+    let relation = ContextRelation {
+        target_id: request.target_id,
+        relation_type: request.relation_type.trim().to_string(),
+    };
+    state
+        .context_manager
+        .add_relation(context_id, relation.clone())
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(RelationResponse {
+            target_id: relation.target_id,
+            relation_type: relation.relation_type,
+        }),
+    ))
+}
+
+/// Handler for removing the relations from a context to another, of one type with
+/// `relation_type`
+pub async fn remove_relation(
+    State(state): State<AppState>,
+    ApiPath((context_id, target_id)): ApiPath<(Uuid, Uuid)>,
+    ApiQuery(params): ApiQuery<RelationParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    state
+        .context_manager
+        .remove_relations(context_id, target_id, params.relation_type)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for listing the contexts a context relates to, of one type with `relation_type`
+pub async fn related_contexts(
+    State(state): State<AppState>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    ApiQuery(params): ApiQuery<RelationParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let related = state
+        .context_manager
+        .related_contexts(context_id, params.relation_type)
+        .await?;
 
-    let limit = params
-        .get("limit")
-        .and_then(|l| l.parse::<usize>().ok())
-        .unwrap_or(100);
+    Ok(Json(
+        related
+            .into_iter()
+            .map(|(relation, context)| RelatedContextDto {
+                relation_type: relation.relation_type,
+                context: context_to_response(&context),
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
 
-    let offset = params
-        .get("offset")
-        .and_then(|o| o.parse::<usize>().ok())
-        .unwrap_or(0);
+/// Handler for listing the children of a context, or all its descendants with
+/// `include_descendants=true`
+pub async fn list_children(
+    State(state): State<AppState>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    ApiQuery(params): ApiQuery<ChildrenParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let limit = clamp_limit(params.limit, state.max_page_size);
+    let children = state
+        .context_manager
+        .list_children(
+            context_id,
+            params.include_descendants,
+            limit,
+            params.offset.unwrap_or(0),
+        )
+        .await?;
+
+    Ok(Json(
+        children.iter().map(context_to_response).collect::<Vec<_>>(),
+    ))
+}
+
+/// Handler for bringing back a soft-deleted context
+pub async fn restore_context(
+    State(state): State<AppState>,
+    ApiPath(context_id): ApiPath<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let context = state.context_manager.restore_context(context_id).await?;
+    Ok(Json(context_to_response(&context)))
+}
+
+/// Handler for listing collections, the default collection first
+pub async fn list_collections(
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, ApiError> {
+    let collections = state.collections.list_collections().await?;
+
+    Ok(Json(
+        collections
+            .into_iter()
+            .map(collection_to_response)
+            .collect::<Vec<_>>(),
+    ))
+}
 
-    // List contexts
+/// Handler for retrieving a collection by ID
+pub async fn get_collection(
+    State(state): State<AppState>,
+    ApiPath(collection_id): ApiPath<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let collection = state.collections.get_collection(collection_id).await?;
+    Ok(Json(collection_to_response(collection)))
+}
+
+/// Handler for creating a collection
+pub async fn create_collection(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<CreateCollectionRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let collection = state
+        .collections
+        .create_collection(request.name, request.description)
+        .await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(collection_to_response(collection)),
+    ))
+}
+
+/// Handler for renaming a collection or changing its description
+pub async fn update_collection(
+    State(state): State<AppState>,
+    ApiPath(collection_id): ApiPath<Uuid>,
+    ApiJson(request): ApiJson<UpdateCollectionRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let collection = state
+        .collections
+        .update_collection(collection_id, request.name, request.description)
+        .await?;
+
+    Ok(Json(collection_to_response(collection)))
+}
+
+/// Handler for deleting a collection, which fails while it holds contexts
+pub async fn delete_collection(
+    State(state): State<AppState>,
+    ApiPath(collection_id): ApiPath<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    state.collections.delete_collection(collection_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Handler for deleting several contexts at once
+///
+/// IDs no context has are reported as `not_found` rather than failing the batch.
+pub async fn delete_contexts(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<DeleteContextsRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let outcomes = state.context_manager.delete_contexts(request.ids).await?;
+    let deleted = outcomes
+        .iter()
+        .filter(|(_, outcome)| *outcome == DeleteOutcome::Deleted)
+        .count();
+
+    let results: Vec<DeleteResultDto> = outcomes
+        .into_iter()
+        .map(|(id, outcome)| DeleteResultDto {
+            id,
+            status: match outcome {
+                DeleteOutcome::Deleted => "deleted",
+                DeleteOutcome::NotFound => "not_found",
+                DeleteOutcome::HasChildren => "has_children",
+            }
+            .to_string(),
+        })
+        .collect();
+    Ok(Json(DeleteContextsResponse { deleted, results }))
+}
+
+/// Handler for deleting every context with the given tags
+///
+/// Requires `confirm=true`, so a stray request can't wipe out a set of contexts.
+pub async fn delete_contexts_by_tags(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<DeleteByTagsParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    if !params.confirm {
+        return Err(McpError::ValidationError(
+            "confirm must be true to delete contexts by tag".to_string(),
+        )
+        .into());
+    }
+
+    let tags = tags_param(&state.tag_policy, params.tags.as_deref())?;
+    let deleted = state.context_manager.delete_by_tags(tags).await?;
+    Ok(Json(DeleteByTagsResponse { deleted }))
+}
+
+/// Handler for listing contexts
+///
+/// Returns a bare array unless the request asks for a [`ContextPage`] with `envelope=true`;
+/// either way the total count is also in the `X-Total-Count` header.
+pub async fn list_contexts(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<ListContextsParams>,
+) -> Result<Response, ApiError> {
+    let mut filter = list_filter(&state.tag_policy, &params)?;
+    filter.collection = collection_param(&state, params.collection.as_deref()).await?;
+    let limit = clamp_limit(params.limit, state.max_page_size);
+    let offset = params.offset.unwrap_or(0);
+
+    // List contexts, counting all matches so clients can page through them
+    let total = state.context_manager.count_contexts(filter.clone()).await?;
     let contexts = state
         .context_manager
-        .list_contexts(tags, limit, offset)
+        .list_contexts(filter, limit, offset)
         .await?;
 
     // Convert to responses
-    let responses: Vec<ContextResponse> = contexts.iter().map(context_to_response).collect();
+    let items: Vec<ContextResponse> = contexts.iter().map(context_to_response).collect();
+    let headers = [(TOTAL_COUNT_HEADER, total.to_string())];
+    if !params.envelope {
+        return Ok((StatusCode::OK, headers, Json(items)).into_response());
+    }
 
-    Ok((StatusCode::OK, Json(responses)))
+    let next_offset = offset.saturating_add(limit);
+    let page = ContextPage {
+        items,
+        total,
+        limit,
+        offset,
+        next_offset: (limit > 0 && next_offset < total).then_some(next_offset),
+    };
+    Ok((StatusCode::OK, headers, Json(page)).into_response())
 }
 
-/// Handler for searching contexts
-pub async fn search_contexts(
+/// The number of results to return for a requested `limit`, which defaults to `max` and is
+/// lowered to it when larger
+fn clamp_limit(limit: Option<usize>, max: usize) -> usize {
+    limit.map_or(max, |limit| limit.min(max))
+}
+
+/// Handler for counting contexts, filtered like a list request
+///
+/// Paging parameters are accepted but don't change the count.
+pub async fn count_contexts(
     State(state): State<AppState>,
-    Json(request): Json<SearchRequest>,
+    ApiQuery(params): ApiQuery<ListContextsParams>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let limit = request.limit.unwrap_or(10);
-
-    let search_result = match request.tags {
-        Some(tags) if !tags.is_empty() => {
-            state
-                .context_search
-                .search_with_tags(request.query, tags, limit)
-                .await?
+    let mut filter = list_filter(&state.tag_policy, &params)?;
+    filter.collection = collection_param(&state, params.collection.as_deref()).await?;
+
+    let count = state.context_manager.count_contexts(filter).await?;
+    Ok(Json(CountResponse { count }))
+}
+
+/// Handler for listing the tags in use with how many contexts carry each, most used first
+pub async fn list_tags(State(state): State<AppState>) -> Result<impl IntoResponse, ApiError> {
+    let counts = state.context_manager.tag_counts().await?;
+
+    let tags: Vec<TagCountDto> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCountDto { tag, count })
+        .collect();
+    Ok(Json(tags))
+}
+
+/// Handler for exporting every context as newline-delimited JSON, one [`ExportRecord`] per
+/// line, with each context's chunks after it when asked for with `include_chunks=true`
+///
+/// Contexts are read a page at a time as the response is sent, so memory use doesn't grow
+/// with the number of contexts. If storage fails partway the response ends early.
+pub async fn export_contexts(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<ExportParams>,
+) -> Result<Response, ApiError> {
+    let include_chunks = params.include_chunks;
+    let context_manager = state.context_manager.clone();
+
+    let pages = stream::try_unfold(Some(0), move |offset| {
+        let context_manager = context_manager.clone();
+        async move {
+            let Some(offset) = offset else {
+                return Ok(None);
+            };
+            let page = context_manager
+                .list_contexts(ContextFilter::default(), EXPORT_PAGE_SIZE, offset)
+                .await?;
+
+            let mut lines = Vec::new();
+            for context in &page {
+                write_record(
+                    &mut lines,
+                    &ExportRecord::Context(exported_context(context)),
+                )?;
+                if !include_chunks {
+                    continue;
+                }
+                // Contexts deleted since the page was listed are exported without chunks
+                let chunks = match context_manager.get_chunks(context.id).await {
+                    Ok(chunks) => chunks,
+                    Err(McpError::ContextNotFound(_)) => Vec::new(),
+                    Err(err) => return Err(err),
+                };
+                for chunk in chunks {
+                    write_record(&mut lines, &ExportRecord::Chunk(exported_chunk(chunk)))?;
+                }
+            }
+
+            let next = (page.len() == EXPORT_PAGE_SIZE).then_some(offset + page.len());
+            Ok::<_, McpError>(Some((Bytes::from(lines), next)))
         }
-        _ => state.context_search.search(request.query, limit).await?,
+    })
+    .inspect_err(|err| tracing::error!("Export stopped early: {}", err));
+
+    Ok((
+        [(CONTENT_TYPE, NDJSON_CONTENT_TYPE)],
+        Body::from_stream(pages),
+    )
+        .into_response())
+}
+
+/// Handler for importing an export, recreating its contexts with their original ids,
+/// timestamps and versions, and their chunks' embeddings when the export has them
+///
+/// Chunk records may come before or after their context. Every line is checked before
+/// anything is imported; contexts are then imported one at a time, so storage failing
+/// partway leaves the ones before it imported.
+pub async fn import_contexts(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<ImportParams>,
+    body: Body,
+) -> Result<impl IntoResponse, ApiError> {
+    let (contexts, mut chunks) = read_import(body, state.max_body_bytes).await?;
+
+    if params.on_conflict == OnConflict::Error {
+        for context in &contexts {
+            if state.context_manager.context_exists(context.id).await? {
+                return Err(McpError::ContextAlreadyExists(context.id).into());
+            }
+        }
+    }
+
+    let mut imported = 0;
+    let mut skipped = 0;
+    for context in contexts {
+        if params.on_conflict != OnConflict::Error
+            && state.context_manager.context_exists(context.id).await?
+        {
+            if params.on_conflict == OnConflict::Skip {
+                skipped += 1;
+                continue;
+            }
+            match state.context_manager.delete_context(context.id).await {
+                Ok(()) | Err(McpError::ContextNotFound(_)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let context_chunks = chunks.remove(&context.id).unwrap_or_default();
+        state
+            .context_manager
+            .import_context(context, context_chunks)
+            .await?;
+        imported += 1;
+    }
+
+    Ok(Json(ImportResponse { imported, skipped }))
+}
+
+/// The contexts of an NDJSON import, in the order they came, and their chunks by context
+async fn read_import(
+    body: Body,
+    limit: usize,
+) -> McpResult<(Vec<Context>, HashMap<Uuid, Vec<ContextChunk>>)> {
+    let mut contexts = Vec::new();
+    let mut chunks: HashMap<Uuid, Vec<ContextChunk>> = HashMap::new();
+    let mut add = |line_number: usize, line: &[u8]| -> McpResult<()> {
+        match import_record(line_number, line)? {
+            Some(ExportRecord::Context(context)) => contexts.push(imported_context(context)),
+            Some(ExportRecord::Chunk(chunk)) => chunks
+                .entry(chunk.context_id)
+                .or_default()
+                .push(imported_chunk(chunk)),
+            None => {}
+        }
+        Ok(())
+    };
+
+    // Lines are parsed as they arrive, so only the one being read is buffered
+    let mut stream = body.into_data_stream();
+    let mut pending = Vec::new();
+    let mut line_number = 0;
+    while let Some(data) = stream.next().await {
+        pending.extend_from_slice(&data.map_err(|err| body_read_error(err, limit))?);
+        let mut start = 0;
+        while let Some(end) = pending[start..].iter().position(|&byte| byte == b'\n') {
+            line_number += 1;
+            add(line_number, &pending[start..start + end])?;
+            start += end + 1;
+        }
+        pending.drain(..start);
+    }
+    // The last line needn't end with a newline
+    add(line_number + 1, &pending)?;
+
+    let mut ids = HashSet::new();
+    if let Some(context) = contexts.iter().find(|context| !ids.insert(context.id)) {
+        return Err(McpError::ValidationError(format!(
+            "Context {} appears more than once",
+            context.id
+        )));
+    }
+    if let Some(context_id) = chunks.keys().find(|context_id| !ids.contains(*context_id)) {
+        return Err(McpError::ValidationError(format!(
+            "The import has chunks of context {} but not the context",
+            context_id
+        )));
+    }
+    Ok((contexts, chunks))
+}
+
+/// The record on one line of an import, or `None` for a blank line
+fn import_record(line_number: usize, line: &[u8]) -> McpResult<Option<ExportRecord>> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    serde_json::from_slice(line)
+        .map(Some)
+        .map_err(|e| McpError::ValidationError(format!("Line {}: {}", line_number, e)))
+}
+
+/// Append `record` to `lines` as one line of JSON
+fn write_record(lines: &mut Vec<u8>, record: &ExportRecord) -> McpResult<()> {
+    serde_json::to_writer(&mut *lines, record)
+        .map_err(|e| McpError::SerializationError(e.to_string()))?;
+    lines.push(b'\n');
+    Ok(())
+}
+
+/// The filter of a list request, with tags normalized like stored tags
+///
+/// Every invalid parameter is reported, not just the first.
+fn list_filter(policy: &TagPolicy, params: &ListContextsParams) -> McpResult<ContextFilter> {
+    let mut errors = FieldErrors::default();
+    let tags = errors.check("tags", tags_param(policy, params.tags.as_deref()))?;
+    let tag_mode = errors.check("tag_mode", tag_mode_param(params.tag_mode.as_deref()))?;
+    let exclude_tags = errors.check(
+        "exclude_tags",
+        tags_param(policy, params.exclude_tags.as_deref()),
+    )?;
+    let created_after = errors.check(
+        "created_after",
+        timestamp_param("created_after", params.created_after.as_deref()),
+    )?;
+    let created_before = errors.check(
+        "created_before",
+        timestamp_param("created_before", params.created_before.as_deref()),
+    )?;
+    errors.into_result()?;
+
+    Ok(ContextFilter {
+        tags: tags.unwrap_or_default(),
+        tag_mode: tag_mode.unwrap_or_default(),
+        exclude_tags: exclude_tags.unwrap_or_default(),
+        created_after: created_after.flatten(),
+        created_before: created_before.flatten(),
+        live_at: None,
+        parent_id: None,
+        collection: None,
+        source: None,
+    })
+}
+
+/// The collection a `collection` parameter names by ID or name, if given
+async fn collection_param(state: &AppState, collection: Option<&str>) -> McpResult<Option<Uuid>> {
+    match collection {
+        Some(collection) => state
+            .collections
+            .resolve_collection(collection)
+            .await
+            .map(Some),
+        None => Ok(None),
+    }
+}
+
+/// A comma-separated tag list query parameter; empty entries are dropped
+fn tags_param(policy: &TagPolicy, tags: Option<&str>) -> McpResult<Vec<String>> {
+    match tags {
+        Some(tags) => policy.normalize_all(tags.split(',').map(str::trim)),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// The `tag_mode` query parameter, `all` unless given
+fn tag_mode_param(tag_mode: Option<&str>) -> McpResult<TagMode> {
+    match tag_mode {
+        None | Some("all") => Ok(TagMode::All),
+        Some("any") => Ok(TagMode::Any),
+        Some(other) => Err(McpError::ValidationError(format!(
+            "tag_mode must be 'all' or 'any', got '{}'",
+            other
+        ))),
+    }
+}
+
+/// An RFC 3339 timestamp query parameter, if given
+fn timestamp_param(name: &str, value: Option<&str>) -> McpResult<Option<DateTime<Utc>>> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|time| time.with_timezone(&Utc))
+                .map_err(|_| {
+                    McpError::ValidationError(format!(
+                        "{} must be an RFC 3339 timestamp, got '{}'",
+                        name, value
+                    ))
+                })
+        })
+        .transpose()
+}
+
+/// When a stored or updated context expires, given as a timestamp or as seconds from now
+fn expiry_param(
+    expires_at: Option<&str>,
+    ttl_seconds: Option<u64>,
+) -> McpResult<Option<DateTime<Utc>>> {
+    let now = Utc::now();
+    let expires_at = match (expires_at, ttl_seconds) {
+        (Some(_), Some(_)) => {
+            return Err(McpError::ValidationError(
+                "expires_at and ttl_seconds can't both be set".to_string(),
+            ))
+        }
+        (Some(expires_at), None) => timestamp_param("expires_at", Some(expires_at))?,
+        (None, Some(0)) => {
+            return Err(McpError::ValidationError(
+                "ttl_seconds must be at least 1".to_string(),
+            ))
+        }
+        (None, Some(ttl_seconds)) => Some(
+            i64::try_from(ttl_seconds)
+                .ok()
+                .and_then(chrono::Duration::try_seconds)
+                .and_then(|ttl| now.checked_add_signed(ttl))
+                .ok_or_else(|| McpError::ValidationError("ttl_seconds is too large".to_string()))?,
+        ),
+        (None, None) => None,
+    };
+
+    match expires_at {
+        Some(expires_at) if expires_at <= now => Err(McpError::ValidationError(
+            "expires_at must be in the future".to_string(),
+        )),
+        expires_at => Ok(expires_at),
+    }
+}
+
+/// Handler for searching contexts
+pub async fn search_contexts(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<FormatParams>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<SearchRequest>,
+) -> Result<Response, ApiError> {
+    let format = ResponseFormat::negotiate(params.format.as_deref(), &headers)?;
+    let mut errors = FieldErrors::default();
+    if request
+        .hybrid_alpha
+        .is_some_and(|alpha| !(0.0..=1.0).contains(&alpha))
+    {
+        errors.add("hybrid_alpha", "hybrid_alpha must be between 0 and 1");
+    }
+    if TextQuery::parse(&request.query).is_empty() {
+        errors.add("query", "query has no search text");
+    }
+    errors.into_result()?;
+    let options = SearchOptions {
+        hybrid_alpha: request.hybrid_alpha,
+        fuzzy: request.fuzzy,
+        expand: request.expand,
+        tag_mode: request.tag_mode,
+        collection: collection_param(&state, request.collection.as_deref()).await?,
+        ..SearchOptions::default()
+    };
+    let query = SearchQuery {
+        text: request.query,
+        tags: request.tags.unwrap_or_default(),
+        exclude_tags: request.exclude_tags.unwrap_or_default(),
+        source: request.source,
+        after: request.after,
+        before: request.before,
+        min_score: request.min_score,
+        limit: request.limit,
+    };
+
+    let response = run_search(
+        &state,
+        query,
+        options,
+        request.highlight,
+        request.response_mode,
+    )
+    .await?;
+    Ok(format.render(response))
+}
+
+/// Handler for searching contexts with the query string syntax (`GET /search?q=...`)
+pub async fn search_contexts_by_query(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<SearchQueryParams>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let format = ResponseFormat::negotiate(params.format.as_deref(), &headers)?;
+    let mut query = SearchQuery::parse(&params.q)?;
+    query.limit = query.limit.or(params.limit);
+
+    if query.text.is_empty() {
+        return Err(McpError::ValidationError("query has no search text".to_string()).into());
+    }
+
+    let options = SearchOptions {
+        collection: collection_param(&state, params.collection.as_deref()).await?,
+        ..SearchOptions::default()
+    };
+    let response = run_search(&state, query, options, false, ResponseMode::Full).await?;
+    Ok(format.render(response))
+}
+
+/// Execute a search with its tag filters normalized
+///
+/// With `highlight`, each match carries snippets of its best matching chunk. `mode` picks
+/// how much of each match is returned.
+async fn run_search(
+    state: &AppState,
+    mut query: SearchQuery,
+    options: SearchOptions,
+    highlight: bool,
+    mode: ResponseMode,
+) -> Result<SearchResponse, ApiError> {
+    let limit = clamp_limit(query.limit, state.max_results);
+
+    // Normalize filters the same way stored tags were
+    query.tags = state.tag_policy.normalize_all(&query.tags)?;
+    query.exclude_tags = state.tag_policy.normalize_all(&query.exclude_tags)?;
+
+    // The search service filters the candidates before picking the best `limit`, so filtered
+    // searches fill up to the limit as well
+    let options = query.search_options(options);
+
+    let search_result = if query.tags.is_empty() {
+        state
+            .context_search
+            .search(query.text.clone(), limit, options)
+            .await?
+    } else {
+        state
+            .context_search
+            .search_with_tags(query.text.clone(), query.tags.clone(), limit, options)
+            .await?
     };
 
     // Convert domain model to DTO
@@ -158,40 +1754,35 @@ pub async fn search_contexts(
         .matches
         .into_iter()
         .map(|m| {
-            let context_response = context_to_response(&m.context);
-
-            let chunks = m.chunks.map(|chunks| {
-                chunks
-                    .into_iter()
-                    .map(|chunk| ContextChunkDto {
-                        id: chunk.chunk_id,
-                        content: chunk.content,
-                        position: chunk.position,
-                    })
-                    .collect()
+            // Contexts without chunks are highlighted as a whole
+            let snippets = highlight.then(|| match &m.chunks {
+                Some(chunks) if !chunks.is_empty() => state.highlighter.best_snippets(
+                    chunks.iter().map(|chunk| chunk.content.as_str()),
+                    &query.text,
+                ),
+                _ => state.highlighter.snippets(&m.context.content, &query.text),
             });
 
-            ContextMatchDto {
-                context: context_response,
-                chunks,
-                score: m.score,
-            }
+            match_to_dto(m, mode, snippets, state.tokenizer.as_ref())
         })
         .collect();
 
-    let response = SearchResponse {
+    Ok(SearchResponse {
         matches,
         total_matches: search_result.total_matches,
-    };
-
-    Ok((StatusCode::OK, Json(response)))
+        total_matches_is_lower_bound: search_result.total_matches_is_lower_bound,
+    })
 }
 
 /// Handler for retrieving contexts by reference
 pub async fn retrieve_by_references(
     State(state): State<AppState>,
-    Json(request): Json<ReferenceRequest>,
-) -> Result<impl IntoResponse, ApiError> {
+    ApiQuery(params): ApiQuery<FormatParams>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<ReferenceRequest>,
+) -> Result<Response, ApiError> {
+    let format = ResponseFormat::negotiate(params.format.as_deref(), &headers)?;
+
     // Convert DTOs to domain model
     let references = request
         .references
@@ -212,44 +1803,173 @@ pub async fn retrieve_by_references(
     let matches = search_result
         .matches
         .into_iter()
-        .map(|m| {
-            let context_response = context_to_response(&m.context);
-
-            let chunks = m.chunks.map(|chunks| {
-                chunks
-                    .into_iter()
-                    .map(|chunk| ContextChunkDto {
-                        id: chunk.chunk_id,
-                        content: chunk.content,
-                        position: chunk.position,
-                    })
-                    .collect()
-            });
-
-            ContextMatchDto {
-                context: context_response,
-                chunks,
-                score: m.score,
-            }
-        })
+        .map(|m| match_to_dto(m, request.response_mode, None, state.tokenizer.as_ref()))
         .collect();
 
     let response = SearchResponse {
         matches,
         total_matches: search_result.total_matches,
+        total_matches_is_lower_bound: search_result.total_matches_is_lower_bound,
+    };
+
+    Ok(format.render(response))
+}
+
+/// Handler for creating a signed, read-only sharing link to a context
+pub async fn create_share_link(
+    State(state): State<AppState>,
+    ApiPath(context_id): ApiPath<Uuid>,
+    ApiJson(request): ApiJson<ShareContextRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    // Only share contexts that exist
+    let context = state.context_manager.get_context(context_id).await?;
+
+    let (token, encoded) = state
+        .share_links
+        .issue(context.id, request.ttl_seconds, Utc::now())?;
+
+    let response = ShareLinkResponse {
+        token_id: token.token_id,
+        context_id: token.context_id,
+        url: format!("{}/shared/{}", API_V1, encoded),
+        token: encoded,
+        expires_at: token.expires_at.to_rfc3339(),
     };
 
-    Ok((StatusCode::OK, Json(response)))
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Handler for reading a context through a sharing link
+pub async fn get_shared_context(
+    State(state): State<AppState>,
+    ApiPath(token): ApiPath<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let token = state.share_links.verify(&token, Utc::now())?;
+    let context = state.context_manager.get_context(token.context_id).await?;
+    Ok((StatusCode::OK, Json(context_to_response(&context))))
+}
+
+/// Handler for revoking a sharing link
+pub async fn revoke_share_link(
+    State(state): State<AppState>,
+    ApiPath((context_id, token_id)): ApiPath<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    state.context_manager.get_context(context_id).await?;
+    state.share_links.revoke(context_id, token_id, Utc::now())?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Convert a domain EvalRun to an EvalRunResponse DTO
+fn eval_run_to_response(run: &EvalRun) -> EvalRunResponse {
+    EvalRunResponse {
+        id: run.id,
+        dataset: run.dataset.clone(),
+        label: run.label.clone(),
+        k: run.k,
+        queries: run.queries,
+        recall_at_k: run.metrics.recall_at_k,
+        mrr: run.metrics.mrr,
+        ndcg_at_k: run.metrics.ndcg_at_k,
+        created_at: run.created_at.to_rfc3339(),
+    }
+}
+
+/// Handler for storing a labelled evaluation dataset
+pub async fn store_eval_dataset(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<EvalDatasetRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let cases = request
+        .cases
+        .into_iter()
+        .map(|case| {
+            Ok(EvalCase {
+                query: case.query,
+                expected_ids: case.expected_ids.unwrap_or_default(),
+                // Expected tags have to match stored tags, so normalize them the same way
+                expected_tags: state
+                    .tag_policy
+                    .normalize_all(case.expected_tags.unwrap_or_default())?,
+            })
+        })
+        .collect::<Result<Vec<_>, McpError>>()?;
+
+    let dataset = state
+        .evaluation
+        .save_dataset(EvalDataset {
+            name: request.name,
+            cases,
+        })
+        .await?;
+
+    let response = EvalDatasetResponse {
+        name: dataset.name,
+        cases: dataset.cases.len(),
+    };
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Handler for running an evaluation dataset through the search pipeline
+pub async fn run_eval(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<EvalRunRequest>,
+) -> Result<impl IntoResponse, ApiError> {
+    let run = state
+        .evaluation
+        .run_dataset(&request.dataset, request.k.unwrap_or(10), request.label)
+        .await?;
+    Ok((StatusCode::CREATED, Json(eval_run_to_response(&run))))
+}
+
+/// Handler for listing evaluation runs, oldest first
+pub async fn list_eval_runs(
+    State(state): State<AppState>,
+    ApiQuery(params): ApiQuery<EvalRunsParams>,
+) -> Result<impl IntoResponse, ApiError> {
+    let runs = state
+        .evaluation
+        .list_runs(params.dataset.as_deref())
+        .await?;
+    let responses: Vec<EvalRunResponse> = runs.iter().map(eval_run_to_response).collect();
+    Ok((StatusCode::OK, Json(responses)))
 }
 
 /// Error type for API handlers
 #[derive(Debug)]
-pub struct ApiError(McpError);
+pub enum ApiError {
+    /// The operation the request asked for failed
+    Failed(McpError),
+
+    /// Calling a server the client pointed the request at failed, so an
+    /// `ExternalServiceError` is that server's fault: a 502 rather than a 503
+    Upstream(McpError),
+
+    /// The request couldn't be read, such as a malformed body or path parameter, or names
+    /// something the server doesn't have, such as a closed MCP session
+    Rejected {
+        status: StatusCode,
+        code: &'static str,
+        message: String,
+    },
+}
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
-        // Convert the error to status code, error code and message
-        let (status, error_code, error_message) = match self.0 {
+        let error = match self {
+            ApiError::Rejected {
+                status,
+                code,
+                message,
+            } => return error_response(status, code, message, None),
+            ApiError::Upstream(McpError::ExternalServiceError(message)) => {
+                return error_response(StatusCode::BAD_GATEWAY, "UPSTREAM_ERROR", message, None)
+            }
+            ApiError::Failed(error) | ApiError::Upstream(error) => error,
+        };
+
+        // Convert the error to status code, error code and message, and any field errors
+        let mut details = None;
+        let (status, error_code, error_message) = match error {
             McpError::ContextNotFound(_) => (
                 StatusCode::NOT_FOUND,
                 "CONTEXT_NOT_FOUND",
@@ -262,6 +1982,18 @@ impl IntoResponse for ApiError {
                 "Chunk not found".to_string(),
             ),
 
+            err @ McpError::RelationNotFound { .. } => {
+                (StatusCode::NOT_FOUND, "RELATION_NOT_FOUND", err.to_string())
+            }
+            err @ McpError::RevisionNotFound { .. } => {
+                (StatusCode::NOT_FOUND, "REVISION_NOT_FOUND", err.to_string())
+            }
+            err @ McpError::ShareLinkNotFound { .. } => (
+                StatusCode::NOT_FOUND,
+                "SHARE_LINK_NOT_FOUND",
+                err.to_string(),
+            ),
+
             McpError::InvalidContextReference(msg) => {
                 (StatusCode::BAD_REQUEST, "INVALID_REFERENCE", msg)
             }
@@ -272,8 +2004,48 @@ impl IntoResponse for ApiError {
                 "Context already exists".to_string(),
             ),
 
+            err @ McpError::VersionConflict { .. } => {
+                (StatusCode::CONFLICT, "VERSION_CONFLICT", err.to_string())
+            }
+
+            err @ McpError::HasChildren(_) => {
+                (StatusCode::CONFLICT, "HAS_CHILDREN", err.to_string())
+            }
+
+            err @ McpError::CollectionNotFound(_) => (
+                StatusCode::NOT_FOUND,
+                "COLLECTION_NOT_FOUND",
+                err.to_string(),
+            ),
+            err @ McpError::CollectionExists(_) => {
+                (StatusCode::CONFLICT, "COLLECTION_EXISTS", err.to_string())
+            }
+            err @ McpError::CollectionNotEmpty(_) => (
+                StatusCode::CONFLICT,
+                "COLLECTION_NOT_EMPTY",
+                err.to_string(),
+            ),
+
             McpError::ValidationError(msg) => (StatusCode::BAD_REQUEST, "VALIDATION_ERROR", msg),
 
+            McpError::ValidationFailed(errors) => {
+                let message = errors
+                    .iter()
+                    .map(|error| error.message.as_str())
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                details = Some(
+                    errors
+                        .into_iter()
+                        .map(|error| FieldErrorDto {
+                            field: error.field,
+                            message: error.message,
+                        })
+                        .collect(),
+                );
+                (StatusCode::BAD_REQUEST, "VALIDATION_ERROR", message)
+            }
+
             McpError::AuthenticationError(msg) => (StatusCode::UNAUTHORIZED, "AUTH_ERROR", msg),
 
             McpError::AuthorizationError(msg) => (StatusCode::FORBIDDEN, "FORBIDDEN", msg),
@@ -284,12 +2056,24 @@ impl IntoResponse for ApiError {
                 "Rate limit exceeded".to_string(),
             ),
 
+            McpError::PayloadTooLarge(limit) => (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                "PAYLOAD_TOO_LARGE",
+                format!("Request body exceeds {} bytes", limit),
+            ),
+
             McpError::ContextLimitExceeded => (
                 StatusCode::TOO_MANY_REQUESTS,
                 "CONTEXT_LIMIT",
                 "Context limit exceeded".to_string(),
             ),
 
+            McpError::ExternalServiceError(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "SERVICE_UNAVAILABLE",
+                "A dependency is unavailable, try again later".to_string(),
+            ),
+
             _ => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "INTERNAL_ERROR",
@@ -297,19 +2081,157 @@ impl IntoResponse for ApiError {
             ),
         };
 
-        // Create error response
-        let error_response = ErrorResponse {
-            message: error_message,
-            code: error_code.to_string(),
-        };
-
-        // Return as JSON with appropriate status code
-        (status, Json(error_response)).into_response()
+        error_response(status, error_code, error_message, details)
     }
 }
 
+/// The standard error body with `status`
+fn error_response(
+    status: StatusCode,
+    code: &str,
+    message: String,
+    details: Option<Vec<FieldErrorDto>>,
+) -> Response {
+    let error_response = ErrorResponse {
+        message,
+        code: code.to_string(),
+        details,
+        request_id: None,
+    };
+
+    // Return as JSON with appropriate status code; the request id middleware finds the
+    // error in the extensions and adds the id to the body
+    let mut response = (status, Json(error_response.clone())).into_response();
+    response.extensions_mut().insert(error_response);
+    response
+}
+
 impl From<McpError> for ApiError {
     fn from(err: McpError) -> Self {
-        Self(err)
+        Self::Failed(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::{rejection::QueryRejection, Query};
+    use axum::http::Uri;
+
+    fn params(uri: &str) -> Result<ListContextsParams, QueryRejection> {
+        let uri: Uri = uri.parse().unwrap();
+        Query::<ListContextsParams>::try_from_uri(&uri).map(|Query(params)| params)
+    }
+
+    fn filter(uri: &str) -> McpResult<ContextFilter> {
+        list_filter(&TagPolicy::default(), &params(uri).unwrap())
+    }
+
+    #[test]
+    fn test_list_parameters_are_read_from_the_query_string() {
+        let params =
+            params("/contexts?tags=Rust,%20Kafka&tag_mode=any&limit=10&offset=20").unwrap();
+        assert_eq!(params.limit, Some(10));
+        assert_eq!(params.offset, Some(20));
+
+        let filter = list_filter(&TagPolicy::default(), &params).unwrap();
+        assert_eq!(filter.tags, ["rust", "kafka"]);
+        assert_eq!(filter.tag_mode, TagMode::Any);
+
+        // Nothing given lists everything
+        let everything = self::params("/contexts").unwrap();
+        assert_eq!((everything.limit, everything.offset), (None, None));
+        assert_eq!(
+            list_filter(&TagPolicy::default(), &everything).unwrap(),
+            ContextFilter::default()
+        );
+    }
+
+    #[test]
+    fn test_empty_tags_filter_nothing() {
+        assert!(filter("/contexts?tags=").unwrap().tags.is_empty());
+        assert!(filter("/contexts?tags=,%20,").unwrap().tags.is_empty());
+        assert_eq!(filter("/contexts?tags=a,,b,").unwrap().tags, ["a", "b"]);
+        assert!(filter("/contexts?exclude_tags=")
+            .unwrap()
+            .exclude_tags
+            .is_empty());
+    }
+
+    #[test]
+    fn test_malformed_list_parameters_are_rejected() {
+        assert!(params("/contexts?limit=ten").is_err());
+        assert!(params("/contexts?offset=-1").is_err());
+        assert!(matches!(
+            filter("/contexts?tag_mode=either"),
+            Err(McpError::ValidationError(_))
+        ));
+        assert!(matches!(
+            filter("/contexts?created_after=yesterday"),
+            Err(McpError::ValidationError(message)) if message.contains("created_after")
+        ));
+    }
+
+    #[test]
+    fn test_limits_default_to_and_are_clamped_at_the_maximum() {
+        assert_eq!(clamp_limit(None, 10), 10);
+        assert_eq!(clamp_limit(Some(3), 10), 3);
+        assert_eq!(clamp_limit(Some(1_000_000), 10), 10);
+        assert_eq!(clamp_limit(Some(0), 10), 0);
+    }
+
+    #[tokio::test]
+    async fn test_every_invalid_list_parameter_is_reported() {
+        let Err(err) =
+            filter("/contexts?tag_mode=either&created_after=yesterday&created_before=soon")
+        else {
+            panic!("expected the filter to be rejected");
+        };
+        let McpError::ValidationFailed(errors) = &err else {
+            panic!("expected field errors, got {:?}", err);
+        };
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["tag_mode", "created_after", "created_before"]);
+
+        let response = ApiError::from(err).into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "VALIDATION_ERROR");
+        assert_eq!(body["details"].as_array().unwrap().len(), 3);
+        assert_eq!(body["details"][1]["field"], "created_after");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .contains("created_before must be an RFC 3339 timestamp"));
+    }
+
+    #[tokio::test]
+    async fn test_single_validation_errors_have_no_details() {
+        let response = ApiError::from(McpError::ValidationError(
+            "query has no search text".to_string(),
+        ))
+        .into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["message"], "query has no search text");
+        assert!(body.get("details").is_none());
+    }
+
+    #[test]
+    fn test_upload_content_type_follows_the_file_extension() {
+        assert_eq!(content_type_for_file("notes.md"), Some("text/markdown"));
+        assert_eq!(content_type_for_file("README.TXT"), Some("text/plain"));
+        assert_eq!(
+            content_type_for_file("data.tar.json"),
+            Some("application/json")
+        );
+        assert_eq!(content_type_for_file("src/main.rs"), Some("text/x-rust"));
+        assert_eq!(content_type_for_file("image.png"), None);
+        assert_eq!(content_type_for_file("Makefile"), None);
     }
 }
@@ -1,6 +1,23 @@
+pub mod auth;
+pub mod body_limit;
+pub mod deprecation;
+pub mod extract;
 pub mod handlers;
 pub mod models;
+pub mod rate_limit;
+pub mod render;
+pub mod request_id;
 pub mod router;
+pub mod share;
+pub mod tls;
 
+pub use auth::{ApiKeyAuth, Authenticator, JwtAuth, Principal};
+pub use deprecation::DEPRECATION_HEADER;
 pub use handlers::AppState;
-pub use router::create_router;
+pub use rate_limit::{RateLimiter, RouteRateLimits};
+pub use request_id::{RequestId, REQUEST_ID_HEADER};
+pub use router::{api_routes, create_router, RouterBuilder, API_V1};
+pub use share::ShareLinkService;
+#[cfg(unix)]
+pub use tls::reload_on_sighup;
+pub use tls::tls_from_config;
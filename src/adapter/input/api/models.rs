@@ -1,9 +1,13 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
 
+use crate::domain::service::ChunkingStrategy;
+use crate::domain::{ContextEventKind, ContextRelation, OnDuplicate, TagMode};
+
 /// Request to store a new context
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct StoreContextRequest {
     /// Content to store
     pub content: String,
@@ -19,10 +23,45 @@ pub struct StoreContextRequest {
 
     /// Optional custom metadata
     pub metadata: Option<HashMap<String, String>>,
+
+    /// When the context expires, as an RFC 3339 timestamp (optional)
+    pub expires_at: Option<String>,
+
+    /// Seconds from now until the context expires, instead of `expires_at` (optional)
+    pub ttl_seconds: Option<u64>,
+
+    /// How to split the content into chunks, instead of the configured strategy (optional)
+    pub chunking: Option<ChunkingStrategy>,
+
+    /// What to do when a context with the same content is already stored
+    #[serde(default)]
+    pub on_duplicate: OnDuplicate,
+
+    /// ID of the context this one belongs under (optional)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
+
+    /// ID of the collection to store the context in, its parent's or the default one if
+    /// not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collection_id: Option<Uuid>,
 }
 
-/// Request to update an existing context
+/// Request to store the document at a URL as a new context
 #[derive(Debug, Deserialize)]
+pub struct IngestUrlRequest {
+    /// URL of the document, `http` or `https`
+    pub url: String,
+
+    /// Optional tags for categorization
+    pub tags: Option<Vec<String>>,
+
+    /// Reduce HTML documents to their readable text (default true)
+    pub strip_html: Option<bool>,
+}
+
+/// Request to update an existing context
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct UpdateContextRequest {
     /// New content
     pub content: String,
@@ -38,10 +77,70 @@ pub struct UpdateContextRequest {
 
     /// Optional custom metadata
     pub metadata: Option<HashMap<String, String>>,
+
+    /// When the context expires, as an RFC 3339 timestamp (optional)
+    pub expires_at: Option<String>,
+
+    /// Seconds from now until the context expires, instead of `expires_at` (optional)
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Request to delete several contexts at once
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteContextsRequest {
+    /// IDs of the contexts to delete
+    pub ids: Vec<Uuid>,
+}
+
+/// Response to a batch delete, with one result per requested ID
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteContextsResponse {
+    /// Number of contexts that were deleted
+    pub deleted: usize,
+
+    /// What happened to each ID, in request order
+    pub results: Vec<DeleteResultDto>,
+}
+
+/// Outcome of deleting one context of a batch
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteResultDto {
+    /// ID of the context
+    pub id: Uuid,
+
+    /// `deleted`, `not_found` if no context had the ID, or `has_children` if contexts with
+    /// children can't be deleted
+    pub status: String,
+}
+
+/// Query parameters for deleting a single context
+#[derive(Debug, Default, Deserialize)]
+pub struct DeleteContextParams {
+    /// Delete the context for good instead of marking it deleted, so it can't be restored
+    #[serde(default)]
+    pub hard: bool,
+}
+
+/// Query parameters for deleting contexts by tag
+#[derive(Debug, Deserialize)]
+pub struct DeleteByTagsParams {
+    /// Comma-separated tags the deleted contexts all have
+    pub tags: Option<String>,
+
+    /// Must be `true`, so contexts aren't deleted by accident
+    #[serde(default)]
+    pub confirm: bool,
+}
+
+/// Response to deleting contexts by tag
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteByTagsResponse {
+    /// Number of contexts that were deleted
+    pub deleted: usize,
 }
 
 /// Response containing context information
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextResponse {
     /// Context ID
     pub id: Uuid,
@@ -55,6 +154,10 @@ pub struct ContextResponse {
     /// Content type
     pub content_type: Option<String>,
 
+    /// Hex-encoded SHA-256 of the content
+    #[serde(default)]
+    pub content_hash: Option<String>,
+
     /// Tags
     pub tags: Vec<String>,
 
@@ -66,10 +169,21 @@ pub struct ContextResponse {
 
     /// When the context expires, if applicable
     pub expires_at: Option<String>,
+
+    /// Number of times the context was written, for `If-Match` on updates
+    pub version: u64,
+
+    /// ID of the context this one is part of, if any
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+
+    /// ID of the collection the context is in, the nil UUID for the default one
+    #[serde(default)]
+    pub collection_id: Uuid,
 }
 
 /// Request to search for contexts
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct SearchRequest {
     /// Query string
     pub query: String,
@@ -77,19 +191,229 @@ pub struct SearchRequest {
     /// Optional tags to filter by
     pub tags: Option<Vec<String>>,
 
-    /// Maximum number of results to return
+    /// Whether matches need every tag in `tags` (`all`, the default) or any of them (`any`)
+    #[serde(default)]
+    pub tag_mode: TagMode,
+
+    /// Optional tags to exclude
+    pub exclude_tags: Option<Vec<String>>,
+
+    /// Optional source to filter by
+    pub source: Option<String>,
+
+    /// Only contexts in this collection, by ID or name (optional)
+    pub collection: Option<String>,
+
+    /// Only return contexts created at or after this time (RFC 3339)
+    #[serde(alias = "created_after")]
+    pub after: Option<DateTime<Utc>>,
+
+    /// Only return contexts created before this time (RFC 3339)
+    #[serde(alias = "created_before")]
+    pub before: Option<DateTime<Utc>>,
+
+    /// Minimum relevance score of a match
+    pub min_score: Option<f32>,
+
+    /// Weight of vector similarity against the lexical score, from 0.0 to 1.0 (optional)
+    pub hybrid_alpha: Option<f32>,
+
+    /// Whether query terms also match near misses, such as typos (optional)
+    pub fuzzy: Option<bool>,
+
+    /// Whether synonyms and full forms of query terms are searched for too (optional)
+    pub expand: Option<bool>,
+
+    /// Return highlighted snippets of each match
+    #[serde(default)]
+    pub highlight: bool,
+
+    /// Maximum number of results to return, capped at and defaulting to `context.max_results`
     pub limit: Option<usize>,
+
+    /// How much of each match to return (`full`, `chunks_only`, or `ids_only`)
+    #[serde(default)]
+    pub response_mode: ResponseMode,
 }
 
-/// Request to retrieve contexts by reference
+/// How much of each match a search or reference response includes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseMode {
+    /// The context with its content, and the matched chunks
+    #[default]
+    Full,
+
+    /// The context without its content, and the matched chunks
+    ChunksOnly,
+
+    /// Only the ID and score of each match
+    IdsOnly,
+}
+
+/// Query parameters for listing contexts
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ListContextsParams {
+    /// Comma-separated tags the contexts must have (optional)
+    pub tags: Option<String>,
+
+    /// Whether contexts need every tag (`all`, the default) or any of them (`any`)
+    pub tag_mode: Option<String>,
+
+    /// Comma-separated tags of contexts to leave out (optional)
+    pub exclude_tags: Option<String>,
+
+    /// Only contexts created at or after this RFC 3339 timestamp (optional)
+    pub created_after: Option<String>,
+
+    /// Only contexts created before this RFC 3339 timestamp (optional)
+    pub created_before: Option<String>,
+
+    /// Only contexts in this collection, by ID or name (optional)
+    pub collection: Option<String>,
+
+    /// Maximum number of contexts to return, capped at and defaulting to `context.max_page_size`
+    pub limit: Option<usize>,
+
+    /// Number of matching contexts to skip
+    pub offset: Option<usize>,
+
+    /// Wrap the contexts in a [`ContextPage`] instead of returning a bare array
+    #[serde(default)]
+    pub envelope: bool,
+}
+
+/// Page of a context listing, returned when the request asks for `envelope=true`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContextPage {
+    /// Contexts on this page
+    pub items: Vec<ContextResponse>,
+
+    /// Number of contexts matching the filters, on every page
+    pub total: usize,
+
+    /// Page size the contexts were listed with
+    pub limit: usize,
+
+    /// Number of matching contexts skipped before this page
+    pub offset: usize,
+
+    /// Offset of the next page, if any matches are left after this one
+    pub next_offset: Option<usize>,
+}
+
+/// Number of contexts matching a count request's filters
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CountResponse {
+    /// Number of matching contexts
+    pub count: usize,
+}
+
+/// How many contexts carry a tag
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagCountDto {
+    /// The tag
+    pub tag: String,
+
+    /// Number of contexts with the tag
+    pub count: usize,
+}
+
+/// Data of a context change event sent to `/events` subscribers
+#[derive(Debug, Serialize)]
+pub struct ContextEventDto {
+    /// Name of the event, also sent as the SSE event name, such as `context.created`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// ID of the context that changed
+    pub context_id: Uuid,
+
+    /// Tags of the context, as stored after the change or before its deletion
+    pub tags: Vec<String>,
+
+    /// When it changed
+    pub timestamp: String,
+}
+
+/// Message a `/ws` client sends to say which context changes it wants
+#[derive(Debug, Default, Deserialize)]
+pub struct SubscriptionRequest {
+    /// Tags the changed context must have; none means any context
+    #[serde(default)]
+    pub tags: Vec<String>,
+
+    /// Whether the context needs every tag in `tags` (`all`, the default) or any of them
+    #[serde(default)]
+    pub tag_mode: TagMode,
+
+    /// Kinds of change wanted, `created`, `updated` or `deleted`; none means all of them
+    #[serde(default)]
+    pub events: Vec<ContextEventKind>,
+}
+
+/// Frame acknowledging a `/ws` subscription; matching changes are sent from then on
+#[derive(Debug, Serialize)]
+pub struct SubscribedDto {
+    /// Always `subscribed`
+    #[serde(rename = "type")]
+    pub kind: String,
+
+    /// The subscribed tags, normalized like stored tags
+    pub tags: Vec<String>,
+}
+
+/// Data of the event telling an `/events` subscriber it fell behind and missed events
+#[derive(Debug, Serialize)]
+pub struct MissedEventsDto {
+    /// Number of events the subscriber missed
+    pub missed: u64,
+}
+
+/// Query parameters of a message an MCP client posts on its SSE session
+#[derive(Debug, Deserialize)]
+pub struct McpMessageParams {
+    /// Session the `endpoint` event gave the client
+    #[serde(rename = "sessionId")]
+    pub session_id: Uuid,
+}
+
+/// Query parameters of a search written in the query string syntax
 #[derive(Debug, Deserialize)]
+pub struct SearchQueryParams {
+    /// Query string, e.g. `payments outage tag:runbook -tag:archived`
+    pub q: String,
+
+    /// Maximum number of results, unless the query sets `limit:`
+    pub limit: Option<usize>,
+
+    /// Only contexts in this collection, by ID or name (optional)
+    pub collection: Option<String>,
+
+    /// Response format (`json`, `markdown`, or `xml`), overriding the `Accept` header
+    pub format: Option<String>,
+}
+
+/// Query parameters selecting how a search or reference response is rendered
+#[derive(Debug, Deserialize)]
+pub struct FormatParams {
+    /// Response format (`json`, `markdown`, or `xml`), overriding the `Accept` header
+    pub format: Option<String>,
+}
+
+/// Request to retrieve contexts by reference
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ReferenceRequest {
     /// List of context references to retrieve
     pub references: Vec<ContextReferenceDto>,
+
+    /// How much of each match to return (`full`, `chunks_only`, or `ids_only`)
+    #[serde(default)]
+    pub response_mode: ResponseMode,
 }
 
 /// Data transfer object for context references
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ContextReferenceDto {
     /// Context ID
     pub context_id: Uuid,
@@ -102,30 +426,93 @@ pub struct ContextReferenceDto {
 }
 
 /// Response for search operations
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SearchResponse {
     /// Matched contexts
     pub matches: Vec<ContextMatchDto>,
 
-    /// Total number of matches
+    /// Number of contexts that matched, before the limit was applied
     pub total_matches: usize,
+
+    /// Whether more contexts may match than `total_matches` counts, as searches with a score
+    /// threshold or quoted phrases only count the matches among the chunks most similar to the
+    /// query
+    #[serde(default)]
+    pub total_matches_is_lower_bound: bool,
 }
 
 /// DTO for a context match
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ContextMatchDto {
-    /// The matched context
-    pub context: ContextResponse,
+    /// ID of the matched context
+    pub id: Uuid,
+
+    /// The matched context, unless only IDs were asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<MatchedContextDto>,
 
-    /// The chunks that matched the query, if any
+    /// The chunks that matched the query, if any and unless only IDs were asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub chunks: Option<Vec<ContextChunkDto>>,
 
     /// Relevance score
     pub score: f32,
+
+    /// ID of the matched context's parent, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<Uuid>,
+
+    /// Passages of the best matching chunk with the query terms marked, if requested
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snippets: Option<Vec<String>>,
+}
+
+/// A matched context as searches return it, its content left out of `chunks_only` responses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchedContextDto {
+    /// Context ID
+    pub id: Uuid,
+
+    /// Content, unless only the matched chunks were asked for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+
+    /// Source of the content
+    pub source: Option<String>,
+
+    /// Content type
+    pub content_type: Option<String>,
+
+    /// Hex-encoded SHA-256 of the content
+    #[serde(default)]
+    pub content_hash: Option<String>,
+
+    /// Tags
+    pub tags: Vec<String>,
+
+    /// Additional metadata
+    pub metadata: HashMap<String, String>,
+
+    /// When the context was created
+    pub created_at: String,
+
+    /// When the context expires, if applicable
+    pub expires_at: Option<String>,
+
+    /// Number of times the context was written
+    pub version: u64,
+
+    /// ID of the context this one is part of, if any
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+
+    /// ID of the collection the context is in, the nil UUID for the default one
+    #[serde(default)]
+    pub collection_id: Uuid,
 }
 
 /// DTO for a context chunk
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct ContextChunkDto {
     /// Chunk ID
     pub id: Uuid,
@@ -135,14 +522,434 @@ pub struct ContextChunkDto {
 
     /// Position of this chunk in the original context
     pub position: usize,
+
+    /// Tokens in the content, as counted by the configured tokenizer
+    #[serde(default)]
+    pub token_count: usize,
 }
 
-/// API error response
+/// A single chunk, with the context it belongs to
 #[derive(Debug, Serialize)]
+pub struct ChunkResponse {
+    /// Chunk ID
+    pub id: Uuid,
+
+    /// ID of the context the chunk was cut from
+    pub context_id: Uuid,
+
+    /// Content of this chunk
+    pub content: String,
+
+    /// Position of this chunk in the original context
+    pub position: usize,
+}
+
+/// Request to relate a context to another
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AddRelationRequest {
+    /// ID of the context to relate to
+    pub target_id: Uuid,
+
+    /// What the relation means, such as `summarizes` or `same_thread`
+    pub relation_type: String,
+}
+
+/// A relation from a context to another
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelationResponse {
+    /// ID of the context related to
+    pub target_id: Uuid,
+
+    /// What the relation means
+    pub relation_type: String,
+}
+
+/// Query parameters for removing or following relations
+#[derive(Debug, Default, Deserialize)]
+pub struct RelationParams {
+    /// Only relations of this type
+    pub relation_type: Option<String>,
+}
+
+/// Query parameters for listing the children of a context
+#[derive(Debug, Default, Deserialize)]
+pub struct ChildrenParams {
+    /// Maximum number of contexts to return, capped at and defaulting to `context.max_page_size`
+    pub limit: Option<usize>,
+
+    /// Number of children to skip
+    pub offset: Option<usize>,
+
+    /// List their children too, and theirs, breadth first
+    #[serde(default)]
+    pub include_descendants: bool,
+}
+
+/// A context related to another, with the type of the relation
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelatedContextDto {
+    /// What the relation means
+    pub relation_type: String,
+
+    /// The context related to
+    pub context: ContextResponse,
+}
+
+/// A prior state of a context, kept when an update replaced it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionResponse {
+    /// ID of the context this was a state of
+    pub context_id: Uuid,
+
+    /// Version the context was at in this state
+    pub version: u64,
+
+    /// Content at the time
+    pub content: String,
+
+    /// Source of the content
+    pub source: Option<String>,
+
+    /// Content type
+    pub content_type: Option<String>,
+
+    /// Hex-encoded SHA-256 of the content
+    pub content_hash: Option<String>,
+
+    /// Tags
+    pub tags: Vec<String>,
+
+    /// Additional metadata
+    pub metadata: HashMap<String, String>,
+
+    /// When the context expired in this state, if it did
+    pub expires_at: Option<String>,
+
+    /// When an update replaced this state
+    pub archived_at: String,
+}
+
+/// Request to create a collection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateCollectionRequest {
+    /// Name of the collection, unique among collections
+    pub name: String,
+
+    /// What the collection is for (optional)
+    pub description: Option<String>,
+}
+
+/// Request to rename a collection or change its description
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpdateCollectionRequest {
+    /// New name, unique among collections (optional)
+    pub name: Option<String>,
+
+    /// New description (optional)
+    pub description: Option<String>,
+}
+
+/// Response containing collection information
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionResponse {
+    /// Collection ID, the nil UUID for the default collection
+    pub id: Uuid,
+
+    /// Name of the collection
+    pub name: String,
+
+    /// What the collection is for
+    pub description: Option<String>,
+
+    /// When the collection was created
+    pub created_at: String,
+}
+
+/// Query parameters for exporting contexts
+#[derive(Debug, Default, Deserialize)]
+pub struct ExportParams {
+    /// Also export the chunks of every context, with their embeddings
+    #[serde(default)]
+    pub include_chunks: bool,
+}
+
+/// One line of an export, tagged with its `type` so the lines can be read in any order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExportRecord {
+    /// A context, without its chunks
+    Context(ExportedContext),
+
+    /// A chunk, naming the context it was cut from
+    Chunk(ExportedChunk),
+}
+
+/// A context as exported, with everything needed to recreate it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedContext {
+    /// Context ID
+    pub id: Uuid,
+
+    /// Content
+    pub content: String,
+
+    /// Source of the content
+    pub source: Option<String>,
+
+    /// Content type
+    pub content_type: Option<String>,
+
+    /// Tags
+    pub tags: Vec<String>,
+
+    /// Additional metadata
+    pub metadata: HashMap<String, String>,
+
+    /// When the context was created
+    pub created_at: DateTime<Utc>,
+
+    /// When the context expires, if applicable
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// Number of times the context was written
+    pub version: u64,
+
+    /// Links to other contexts, which an import keeps even if their targets aren't imported
+    #[serde(default)]
+    pub relations: Vec<ContextRelation>,
+
+    /// ID of the context this one is part of, if any
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+
+    /// ID of the collection the context is in, if not the default one
+    #[serde(default)]
+    pub collection_id: Option<Uuid>,
+}
+
+/// A chunk as exported, with its embedding if it has one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportedChunk {
+    /// Chunk ID
+    pub id: Uuid,
+
+    /// ID of the context the chunk was cut from
+    pub context_id: Uuid,
+
+    /// Content of this chunk
+    pub content: String,
+
+    /// Position of this chunk in the original context
+    pub position: usize,
+
+    /// Embedding of this chunk
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// Query parameters for importing contexts
+#[derive(Debug, Default, Deserialize)]
+pub struct ImportParams {
+    /// What to do with a context whose id is already stored
+    #[serde(default)]
+    pub on_conflict: OnConflict,
+}
+
+/// What an import does with a context whose id is already stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// Fail with a 409 before importing anything
+    #[default]
+    Error,
+
+    /// Keep the stored context and leave the imported one out
+    Skip,
+
+    /// Delete the stored context and import the new one in its place
+    Overwrite,
+}
+
+/// Response to an import
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    /// Number of contexts imported
+    pub imported: usize,
+
+    /// Number of contexts left out because their id was already stored
+    pub skipped: usize,
+}
+
+/// API error response
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ErrorResponse {
     /// Error message
     pub message: String,
 
     /// Error code
     pub code: String,
+
+    /// Each problem with the request, when validation found more than one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<FieldErrorDto>>,
+
+    /// Id of the failed request, as in its `X-Request-Id` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// A problem with one field of a request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldErrorDto {
+    /// Name of the field
+    pub field: String,
+
+    /// What is wrong with it
+    pub message: String,
+}
+
+/// Request to create a sharing link for a context
+#[derive(Debug, Deserialize)]
+pub struct ShareContextRequest {
+    /// Optional lifetime of the link in seconds
+    pub ttl_seconds: Option<u64>,
+}
+
+/// Response describing a newly created sharing link
+#[derive(Debug, Serialize)]
+pub struct ShareLinkResponse {
+    /// Identifier of the link, used to revoke it
+    pub token_id: Uuid,
+
+    /// The shared context
+    pub context_id: Uuid,
+
+    /// Signed token granting read access
+    pub token: String,
+
+    /// Path at which the shared context can be read without authentication
+    pub url: String,
+
+    /// When the link expires
+    pub expires_at: String,
+}
+
+/// Request to store a labelled evaluation dataset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalDatasetRequest {
+    /// Name the dataset is run by; an existing dataset with this name is replaced
+    pub name: String,
+
+    /// Labelled queries
+    pub cases: Vec<EvalCaseDto>,
+}
+
+/// A labelled query of an evaluation dataset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalCaseDto {
+    /// Query to search for
+    pub query: String,
+
+    /// Contexts that should be returned
+    pub expected_ids: Option<Vec<Uuid>>,
+
+    /// Tags marking contexts that should be returned
+    pub expected_tags: Option<Vec<String>>,
+}
+
+/// Response describing a stored evaluation dataset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalDatasetResponse {
+    /// Dataset name
+    pub name: String,
+
+    /// Number of labelled queries
+    pub cases: usize,
+}
+
+/// Request to run an evaluation dataset
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalRunRequest {
+    /// Name of the dataset to run
+    pub dataset: String,
+
+    /// Number of results considered per query (defaults to 10)
+    pub k: Option<usize>,
+
+    /// Label of the run (defaults to the server's config fingerprint)
+    pub label: Option<String>,
+}
+
+/// Query parameters for listing evaluation runs
+#[derive(Debug, Deserialize)]
+pub struct EvalRunsParams {
+    /// Only list runs of this dataset
+    pub dataset: Option<String>,
+}
+
+/// Results of an evaluation run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EvalRunResponse {
+    /// Run ID
+    pub id: Uuid,
+
+    /// Dataset that was run
+    pub dataset: String,
+
+    /// Label identifying the configuration of the run
+    pub label: String,
+
+    /// Number of results considered per query
+    pub k: usize,
+
+    /// Number of queries evaluated
+    pub queries: usize,
+
+    /// Mean recall of the top k
+    pub recall_at_k: f64,
+
+    /// Mean reciprocal rank
+    pub mrr: f64,
+
+    /// Mean nDCG of the top k
+    pub ndcg_at_k: f64,
+
+    /// When the run was made
+    pub created_at: String,
+}
+
+/// Liveness of the server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthResponse {
+    /// Always `ok` while the server answers
+    pub status: String,
+
+    /// Version of the running build
+    pub version: String,
+
+    /// Seconds since the server started
+    pub uptime_seconds: u64,
+}
+
+/// Response of the readiness check
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    /// `ready` when every dependency passed its check, `unavailable` otherwise
+    pub status: &'static str,
+
+    /// Status of each dependency
+    pub dependencies: Vec<DependencyStatusDto>,
+}
+
+/// Outcome of checking one dependency
+#[derive(Debug, Serialize)]
+pub struct DependencyStatusDto {
+    /// Name of the dependency, such as `repository` or `embedding`
+    pub name: String,
+
+    /// `ok` or `error`
+    pub status: &'static str,
+
+    /// Why the check failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
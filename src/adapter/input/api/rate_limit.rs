@@ -0,0 +1,332 @@
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::auth::{presented_key, Principal};
+use super::handlers::ApiError;
+use crate::config::{RateLimitConfig, RateLimitKey, RateLimitRule};
+use crate::domain::McpError;
+
+/// Clients a limiter holds buckets for before it first forgets the refilled ones
+const MIN_PRUNE_AT: usize = 1024;
+
+/// A token bucket for a single client
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// The buckets of the clients seen lately
+struct Buckets {
+    by_client: HashMap<String, Bucket>,
+    // Number of buckets at which the refilled ones are forgotten
+    prune_at: usize,
+}
+
+/// Token-bucket rate limiter keyed by client
+pub struct RateLimiter {
+    rate_per_second: f64,
+    burst: f64,
+    key: RateLimitKey,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    pub fn new(rate_per_second: f64, burst: u32) -> Self {
+        Self {
+            rate_per_second,
+            burst: f64::from(burst.max(1)),
+            key: RateLimitKey::Ip,
+            buckets: Mutex::new(Buckets {
+                by_client: HashMap::new(),
+                prune_at: MIN_PRUNE_AT,
+            }),
+        }
+    }
+
+    /// Limiter following `rule`, counting requests against the clients `key` identifies
+    pub fn from_rule(rule: &RateLimitRule, key: RateLimitKey) -> Self {
+        Self::new(rule.per_second, rule.burst).with_key(key)
+    }
+
+    /// Set what identifies the client a request is counted against
+    pub fn with_key(mut self, key: RateLimitKey) -> Self {
+        self.key = key;
+        self
+    }
+
+    /// The client a request is counted against
+    fn key_of(&self, request: &Request) -> String {
+        if self.key == RateLimitKey::ApiKey {
+            // Credentials are hashed so the limiter never holds them
+            let credential = request
+                .extensions()
+                .get::<Principal>()
+                .map(|principal| format!("sub:{}", principal.subject))
+                .or_else(|| {
+                    presented_key(request.headers())
+                        .map(|key| format!("key:{}", hex::encode(Sha256::digest(key))))
+                });
+            if let Some(credential) = credential {
+                return credential;
+            }
+        }
+        client_key(request)
+    }
+
+    /// Take a token for the given client, returning how long to wait if none is available
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Whether the given client has a token left, without taking it
+    pub fn peek(&self, key: &str) -> Result<(), Duration> {
+        self.acquire(key, Instant::now(), false)
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        self.acquire(key, now, true)
+    }
+
+    fn acquire(&self, key: &str, now: Instant, take: bool) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.by_client.len() >= buckets.prune_at {
+            self.prune(&mut buckets, now);
+        }
+
+        let bucket = buckets.by_client.entry(key.to_string()).or_insert(Bucket {
+            tokens: self.burst,
+            updated_at: now,
+        });
+
+        // Refill based on the time elapsed since the last request
+        bucket.tokens = self.refilled(bucket, now);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            if take {
+                bucket.tokens -= 1.0;
+            }
+            Ok(())
+        } else if self.rate_per_second > 0.0 {
+            let missing = 1.0 - bucket.tokens;
+            Err(Duration::from_secs_f64(missing / self.rate_per_second))
+        } else {
+            Err(Duration::from_secs(u64::MAX / 2))
+        }
+    }
+
+    /// The tokens `bucket` holds at `now`
+    fn refilled(&self, bucket: &Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated_at);
+        (bucket.tokens + elapsed.as_secs_f64() * self.rate_per_second).min(self.burst)
+    }
+
+    /// Forget the clients whose buckets have refilled, as a new bucket starts out full anyway
+    ///
+    /// Pruning again only once the map has doubled keeps it amortized constant per request.
+    fn prune(&self, buckets: &mut Buckets, now: Instant) {
+        buckets
+            .by_client
+            .retain(|_, bucket| self.refilled(bucket, now) < self.burst);
+        buckets.prune_at = (buckets.by_client.len() * 2).max(MIN_PRUNE_AT);
+    }
+}
+
+/// Separate limits on the reads, searches and writes of the API, and on the requests each
+/// IP address fails to authenticate
+#[derive(Clone)]
+pub struct RouteRateLimits {
+    pub reads: Arc<RateLimiter>,
+    pub search: Arc<RateLimiter>,
+    pub writes: Arc<RateLimiter>,
+    pub failed_auth: Arc<RateLimiter>,
+}
+
+impl RouteRateLimits {
+    /// The configured limits, or `None` if requests aren't limited
+    pub fn from_config(config: &RateLimitConfig) -> Option<Self> {
+        config.enabled.then(|| Self {
+            reads: Arc::new(RateLimiter::from_rule(&config.reads, config.key)),
+            search: Arc::new(RateLimiter::from_rule(&config.search, config.key)),
+            writes: Arc::new(RateLimiter::from_rule(&config.writes, config.key)),
+            // Requests without valid credentials can only be told apart by address
+            failed_auth: Arc::new(RateLimiter::from_rule(
+                &config.failed_auth,
+                RateLimitKey::Ip,
+            )),
+        })
+    }
+}
+
+/// Identify the client making a request, falling back to a shared key when the
+/// peer address is unavailable
+pub fn client_key(request: &Request) -> String {
+    request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Build the 429 response returned when a client exceeds its rate limit
+pub fn rate_limited_response(retry_after: Duration) -> Response {
+    let mut response = ApiError::from(McpError::RateLimitExceeded).into_response();
+
+    // Round up so clients never retry too early
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+
+    response
+}
+
+/// Middleware enforcing a rate limit on the routes it is applied to
+pub async fn rate_limit(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    match limiter.check(&limiter.key_of(&request)) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => rate_limited_response(retry_after),
+    }
+}
+
+/// Middleware throttling clients by the requests they fail to authenticate
+///
+/// It runs before authentication, as the per-route limits only count requests that pass it:
+/// a client whose failures used up its bucket is refused until the bucket refills.
+pub async fn limit_failed_auth(
+    State(limiter): State<Arc<RateLimiter>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let key = limiter.key_of(&request);
+    if let Err(retry_after) = limiter.peek(&key) {
+        return rate_limited_response(retry_after);
+    }
+
+    let response = next.run(request).await;
+    if response.status() == StatusCode::UNAUTHORIZED {
+        // The failure is counted whether or not it leaves a token for the next request
+        let _ = limiter.check(&key);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_burst_then_refill() {
+        let limiter = RateLimiter::new(2.0, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            assert!(limiter.check_at("client", start).is_ok());
+        }
+
+        let retry_after = limiter.check_at("client", start).unwrap_err();
+        assert!(retry_after <= Duration::from_millis(500));
+
+        // Half a second at two tokens per second refills one token
+        let later = start + Duration::from_millis(500);
+        assert!(limiter.check_at("client", later).is_ok());
+        assert!(limiter.check_at("client", later).is_err());
+    }
+
+    #[test]
+    fn test_clients_are_keyed_by_credential_when_configured() {
+        let request = |api_key: Option<&str>| {
+            let mut request = Request::new(axum::body::Body::empty());
+            request
+                .extensions_mut()
+                .insert(ConnectInfo(SocketAddr::from(([10, 0, 0, 1], 4000))));
+            if let Some(api_key) = api_key {
+                request
+                    .headers_mut()
+                    .insert("x-api-key", HeaderValue::from_str(api_key).unwrap());
+            }
+            request
+        };
+
+        let by_ip = RateLimiter::new(1.0, 1);
+        assert_eq!(by_ip.key_of(&request(Some("a"))), "10.0.0.1");
+
+        let by_key = RateLimiter::new(1.0, 1).with_key(RateLimitKey::ApiKey);
+        assert_ne!(
+            by_key.key_of(&request(Some("a"))),
+            by_key.key_of(&request(Some("b")))
+        );
+        assert!(!by_key
+            .key_of(&request(Some("secret-key")))
+            .contains("secret-key"));
+        assert_eq!(by_key.key_of(&request(None)), "10.0.0.1");
+
+        // A token's subject identifies its client, whichever token it presents
+        let mut signed = request(None);
+        signed.extensions_mut().insert(Principal {
+            subject: "alice".to_string(),
+            scopes: Vec::new(),
+        });
+        assert_eq!(by_key.key_of(&signed), "sub:alice");
+    }
+
+    #[test]
+    fn test_peek_leaves_the_token() {
+        let limiter = RateLimiter::new(1.0, 1);
+        let now = Instant::now();
+
+        assert!(limiter.acquire("client", now, false).is_ok());
+        assert!(limiter.acquire("client", now, false).is_ok());
+        assert!(limiter.check_at("client", now).is_ok());
+        assert!(limiter.acquire("client", now, false).is_err());
+    }
+
+    #[test]
+    fn test_refilled_buckets_are_forgotten() {
+        let limiter = RateLimiter::new(1.0, 2);
+        let start = Instant::now();
+
+        assert!(limiter.check_at("busy", start).is_ok());
+        assert!(limiter.check_at("busy", start).is_ok());
+        for client in 1..MIN_PRUNE_AT {
+            assert!(limiter.check_at(&client.to_string(), start).is_ok());
+        }
+
+        // A second later the map is full and only the busy client's bucket hasn't refilled
+        let later = start + Duration::from_secs(1);
+        assert!(limiter.check_at("new", later).is_ok());
+        {
+            let buckets = limiter.buckets.lock().unwrap();
+            assert_eq!(buckets.by_client.len(), 2);
+            assert!(buckets.by_client.contains_key("busy"));
+            assert_eq!(buckets.prune_at, MIN_PRUNE_AT);
+        }
+
+        // The busy client keeps what it had rather than starting over with a full bucket
+        assert!(limiter.check_at("busy", later).is_ok());
+        assert!(limiter.check_at("busy", later).is_err());
+    }
+
+    #[test]
+    fn test_clients_are_limited_independently() {
+        let limiter = RateLimiter::new(1.0, 1);
+        let now = Instant::now();
+
+        assert!(limiter.check_at("a", now).is_ok());
+        assert!(limiter.check_at("a", now).is_err());
+        assert!(limiter.check_at("b", now).is_ok());
+    }
+}
@@ -0,0 +1,358 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use super::models::{ContextMatchDto, SearchResponse};
+use crate::domain::{McpError, McpResult};
+
+/// Representation a search or reference response is rendered in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseFormat {
+    Json,
+    /// A heading per match with its metadata and fenced content
+    Markdown,
+    /// `<context>` elements, as prompt templates commonly expect
+    Xml,
+}
+
+impl ResponseFormat {
+    /// Pick the format from a `format` query parameter, falling back to the `Accept` header
+    pub fn negotiate(format: Option<&str>, headers: &HeaderMap) -> McpResult<Self> {
+        if let Some(format) = format {
+            return match format.to_ascii_lowercase().as_str() {
+                "json" => Ok(Self::Json),
+                "markdown" | "md" => Ok(Self::Markdown),
+                "xml" => Ok(Self::Xml),
+                other => Err(McpError::ValidationError(format!(
+                    "Unsupported format '{}' (supported: json, markdown, xml)",
+                    other
+                ))),
+            };
+        }
+
+        let accept = headers
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        // The first recognised media type wins; anything else gets JSON
+        for media_type in accept.split(',') {
+            let media_type = media_type.split(';').next().unwrap_or_default().trim();
+            match media_type {
+                "application/json" => return Ok(Self::Json),
+                "text/markdown" => return Ok(Self::Markdown),
+                "application/xml" | "text/xml" => return Ok(Self::Xml),
+                _ => {}
+            }
+        }
+
+        Ok(Self::Json)
+    }
+
+    /// Render a search response in this format
+    pub fn render(self, response: SearchResponse) -> Response {
+        match self {
+            Self::Json => (StatusCode::OK, Json(response)).into_response(),
+            Self::Markdown => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, "text/markdown; charset=utf-8")],
+                render_markdown(&response),
+            )
+                .into_response(),
+            Self::Xml => (
+                StatusCode::OK,
+                [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                render_xml(&response),
+            )
+                .into_response(),
+        }
+    }
+}
+
+/// Content type of a context's content returned as plain text
+pub const PLAIN_TEXT_CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Whether the `Accept` header asks for a context as plain text rather than JSON
+///
+/// As for search formats, the first media type recognised in the header wins.
+pub fn wants_plain_text(headers: &HeaderMap) -> bool {
+    let accept = headers
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    for media_type in accept.split(',') {
+        match media_type.split(';').next().unwrap_or_default().trim() {
+            "text/plain" => return true,
+            "application/json" | "*/*" => return false,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Render matches as markdown, showing matched chunks instead of the full content when present
+pub fn render_markdown(response: &SearchResponse) -> String {
+    let mut out = String::new();
+
+    for (index, m) in response.matches.iter().enumerate() {
+        if index > 0 {
+            out.push('\n');
+        }
+
+        out.push_str(&format!("## {}. Context {}\n\n", index + 1, m.id));
+        if let Some(context) = &m.context {
+            if let Some(source) = &context.source {
+                out.push_str(&format!("- Source: {}\n", source));
+            }
+            if !context.tags.is_empty() {
+                out.push_str(&format!("- Tags: {}\n", context.tags.join(", ")));
+            }
+        }
+        out.push_str(&format!("- Score: {:.2}\n", m.score));
+
+        for content in displayed_content(m) {
+            let fence = fence_for(content);
+            out.push_str(&format!("\n{}\n{}\n{}\n", fence, content, fence));
+        }
+    }
+
+    out
+}
+
+/// Render matches as `<context>` elements inside a `<contexts>` root
+pub fn render_xml(response: &SearchResponse) -> String {
+    let mut out = format!("<contexts total=\"{}\">\n", response.total_matches);
+
+    for m in &response.matches {
+        out.push_str(&format!("  <context id=\"{}\"", m.id));
+        if let Some(context) = &m.context {
+            if let Some(source) = &context.source {
+                out.push_str(&format!(" source=\"{}\"", escape_xml(source)));
+            }
+            if !context.tags.is_empty() {
+                out.push_str(&format!(
+                    " tags=\"{}\"",
+                    escape_xml(&context.tags.join(","))
+                ));
+            }
+        }
+        out.push_str(&format!(" score=\"{:.2}\">\n", m.score));
+
+        match &m.chunks {
+            Some(chunks) if !chunks.is_empty() => {
+                for chunk in chunks {
+                    out.push_str(&format!(
+                        "    <chunk position=\"{}\">{}</chunk>\n",
+                        chunk.position,
+                        escape_xml(&chunk.content)
+                    ));
+                }
+            }
+            _ => {
+                for content in displayed_content(m) {
+                    out.push_str(&format!("    {}\n", escape_xml(content)));
+                }
+            }
+        }
+
+        out.push_str("  </context>\n");
+    }
+
+    out.push_str("</contexts>\n");
+    out
+}
+
+/// The matched chunks if the match has any, otherwise the full context content if it was kept
+fn displayed_content(m: &ContextMatchDto) -> Vec<&str> {
+    match (&m.chunks, &m.context) {
+        (Some(chunks), _) if !chunks.is_empty() => {
+            chunks.iter().map(|chunk| chunk.content.as_str()).collect()
+        }
+        (_, Some(context)) => context.content.as_deref().into_iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// A code fence longer than any backtick run in the content
+fn fence_for(content: &str) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest_run.max(2) + 1)
+}
+
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::input::api::models::{ContextChunkDto, MatchedContextDto};
+    use axum::http::HeaderValue;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn fixed_response() -> SearchResponse {
+        let context =
+            |id: u128, content: &str, source: Option<&str>, tags: &[&str]| MatchedContextDto {
+                id: Uuid::from_u128(id),
+                content: Some(content.to_string()),
+                source: source.map(str::to_string),
+                content_type: None,
+                content_hash: None,
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                metadata: HashMap::new(),
+                created_at: "2024-01-01T00:00:00+00:00".to_string(),
+                expires_at: None,
+                version: 1,
+                parent_id: None,
+                collection_id: Uuid::nil(),
+            };
+
+        SearchResponse {
+            matches: vec![
+                ContextMatchDto {
+                    id: Uuid::from_u128(1),
+                    context: Some(context(
+                        1,
+                        "Restart the <primary> & check ```logs```",
+                        Some("wiki"),
+                        &["runbook", "db"],
+                    )),
+                    chunks: None,
+                    score: 0.875,
+                    parent_id: None,
+                    snippets: None,
+                },
+                ContextMatchDto {
+                    id: Uuid::from_u128(2),
+                    context: Some(context(2, "full content is not shown", None, &[])),
+                    chunks: Some(vec![
+                        ContextChunkDto {
+                            id: Uuid::from_u128(20),
+                            content: "first \"chunk\"".to_string(),
+                            position: 0,
+                            token_count: 3,
+                        },
+                        ContextChunkDto {
+                            id: Uuid::from_u128(21),
+                            content: "second chunk".to_string(),
+                            position: 1,
+                            token_count: 3,
+                        },
+                    ]),
+                    score: 0.5,
+                    parent_id: None,
+                    snippets: None,
+                },
+            ],
+            total_matches: 2,
+            total_matches_is_lower_bound: false,
+        }
+    }
+
+    #[test]
+    fn test_markdown_snapshot() {
+        let expected = "\
+## 1. Context 00000000-0000-0000-0000-000000000001
+
+- Source: wiki
+- Tags: runbook, db
+- Score: 0.88
+
+````
+Restart the <primary> & check ```logs```
+````
+
+## 2. Context 00000000-0000-0000-0000-000000000002
+
+- Score: 0.50
+
+```
+first \"chunk\"
+```
+
+```
+second chunk
+```
+";
+        assert_eq!(render_markdown(&fixed_response()), expected);
+    }
+
+    #[test]
+    fn test_xml_snapshot() {
+        let expected = "\
+<contexts total=\"2\">
+  <context id=\"00000000-0000-0000-0000-000000000001\" source=\"wiki\" tags=\"runbook,db\" score=\"0.88\">
+    Restart the &lt;primary&gt; &amp; check ```logs```
+  </context>
+  <context id=\"00000000-0000-0000-0000-000000000002\" score=\"0.50\">
+    <chunk position=\"0\">first &quot;chunk&quot;</chunk>
+    <chunk position=\"1\">second chunk</chunk>
+  </context>
+</contexts>
+";
+        assert_eq!(render_xml(&fixed_response()), expected);
+    }
+
+    #[test]
+    fn test_ids_only_matches_render_without_content() {
+        let mut response = fixed_response();
+        for m in &mut response.matches {
+            m.context = None;
+            m.chunks = None;
+        }
+
+        let expected = "\
+<contexts total=\"2\">
+  <context id=\"00000000-0000-0000-0000-000000000001\" score=\"0.88\">
+  </context>
+  <context id=\"00000000-0000-0000-0000-000000000002\" score=\"0.50\">
+  </context>
+</contexts>
+";
+        assert_eq!(render_xml(&response), expected);
+        assert!(!render_markdown(&response).contains("```"));
+    }
+
+    #[test]
+    fn test_negotiate_format() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(
+            ResponseFormat::negotiate(None, &headers).unwrap(),
+            ResponseFormat::Json
+        );
+
+        headers.insert(
+            header::ACCEPT,
+            HeaderValue::from_static("text/html, text/markdown;q=0.9"),
+        );
+        assert_eq!(
+            ResponseFormat::negotiate(None, &headers).unwrap(),
+            ResponseFormat::Markdown
+        );
+
+        // The query parameter overrides the header
+        assert_eq!(
+            ResponseFormat::negotiate(Some("XML"), &headers).unwrap(),
+            ResponseFormat::Xml
+        );
+        assert!(matches!(
+            ResponseFormat::negotiate(Some("yaml"), &headers),
+            Err(McpError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,76 @@
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+use super::models::ErrorResponse;
+
+/// Header carrying the id that correlates a request with its logs and error body
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest client-supplied request id kept; longer ones are replaced
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// Id of the request being handled, in the request's extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Middleware tagging every request with an id, taken from `X-Request-Id` or generated
+///
+/// The id is added to the request's extensions and the tracing span its handling logs
+/// under, sent back in the `X-Request-Id` response header, and written into the body of
+/// error responses, which `ApiError` leaves an `ErrorResponse` extension on for this.
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    request.extensions_mut().insert(RequestId(id.clone()));
+
+    let span = tracing::info_span!("request", request_id = %id);
+    let mut response = next.run(request).instrument(span).await;
+
+    if let Some(error) = response.extensions_mut().remove::<ErrorResponse>() {
+        let error = ErrorResponse {
+            request_id: Some(id.clone()),
+            ..error
+        };
+        if let Ok(body) = serde_json::to_vec(&error) {
+            response.headers_mut().remove(header::CONTENT_LENGTH);
+            *response.body_mut() = Body::from(body);
+        }
+    }
+
+    // The id was checked to be visible ASCII or generated, so it's always a valid value
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// Whether a client-supplied id is short, non-empty printable ASCII, safe to log and echo
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_REQUEST_ID_LEN && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_short_printable_ids_are_kept() {
+        assert!(is_valid("req-42"));
+        assert!(is_valid(&Uuid::new_v4().to_string()));
+        assert!(!is_valid(""));
+        assert!(!is_valid("has space"));
+        assert!(!is_valid(&"x".repeat(MAX_REQUEST_ID_LEN + 1)));
+    }
+}
@@ -1,36 +1,204 @@
 use axum::{
+    extract::DefaultBodyLimit,
+    http::HeaderName,
+    middleware,
     routing::{delete, get, post, put},
     Router,
 };
+use std::sync::Arc;
 use tower_http::cors::{Any, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 
+use super::auth::authenticate;
+use super::body_limit::payload_too_large_as_json;
+use super::deprecation::{deprecated_alias, DEPRECATION_HEADER};
 use super::handlers::{
-    delete_context, get_context, list_contexts, retrieve_by_references, search_contexts,
-    store_context, update_context, AppState,
+    add_relation, context_events, count_contexts, create_collection, create_share_link,
+    delete_collection, delete_context, delete_contexts, delete_contexts_by_tags, export_contexts,
+    get_chunk, get_collection, get_context, get_raw_context, get_revision, get_shared_context,
+    health, import_contexts, ingest_url, list_children, list_collections, list_contexts,
+    list_eval_runs, list_revisions, list_tags, mcp_message, mcp_sse, ready, related_contexts,
+    remove_relation, restore_context, restore_revision, retrieve_by_references, revoke_share_link,
+    run_eval, search_contexts, search_contexts_by_query, store_context, store_eval_dataset,
+    subscribe_ws, update_collection, update_context, upload_context, AppState,
 };
+use super::rate_limit::{limit_failed_auth, rate_limit, RateLimiter};
+use super::request_id::{request_id, REQUEST_ID_HEADER};
 
-/// Create the API router with all endpoints
+/// Prefix of the current version of the API
+pub const API_V1: &str = "/v1";
+
+/// Create the API router with all endpoints under `/v1`
+///
+/// When `state.legacy_routes` is set the same endpoints are also served at their unversioned
+/// paths, as deprecated aliases.
 pub fn create_router(state: AppState) -> Router {
-    // Set up CORS
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let routes = api_routes(&state);
+    let mut builder = RouterBuilder::new(state.clone()).with_version(API_V1, routes.clone());
+    if state.legacy_routes {
+        builder = builder.with_deprecated_alias(API_V1, routes);
+    }
+    builder.build()
+}
 
-    // Build the router with all routes
-    Router::new()
-        // Context management
-        .route("/contexts", post(store_context))
+/// Composes route sets into the server's router, each under its version prefix, and wraps
+/// them in the middleware every route shares
+pub struct RouterBuilder {
+    state: AppState,
+    router: Router<AppState>,
+}
+
+impl RouterBuilder {
+    /// Create a builder with no routes
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            router: Router::new(),
+        }
+    }
+
+    /// Serve `routes` under `prefix`, such as `/v1`
+    pub fn with_version(mut self, prefix: &str, routes: Router<AppState>) -> Self {
+        self.router = self.router.nest(prefix, routes);
+        self
+    }
+
+    /// Serve `routes` at their unprefixed paths, marking every response deprecated in favour
+    /// of the same path under `successor`
+    pub fn with_deprecated_alias(
+        mut self,
+        successor: &'static str,
+        routes: Router<AppState>,
+    ) -> Self {
+        self.router = self
+            .router
+            .merge(routes.route_layer(middleware::from_fn_with_state(successor, deprecated_alias)));
+        self
+    }
+
+    /// The router serving every route set added
+    pub fn build(self) -> Router {
+        // Set up CORS
+        let cors = CorsLayer::new()
+            .allow_origin(Any)
+            .allow_methods(Any)
+            .allow_headers(Any)
+            .expose_headers([
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(DEPRECATION_HEADER),
+            ]);
+
+        self.router
+            // Add middleware; the body limit replaces extractors' own 2 MB default
+            .layer(DefaultBodyLimit::disable())
+            .layer(RequestBodyLimitLayer::new(self.state.max_body_bytes))
+            .layer(middleware::from_fn_with_state(
+                self.state.max_body_bytes,
+                payload_too_large_as_json,
+            ))
+            .layer(TraceLayer::new_for_http())
+            .layer(middleware::from_fn(request_id))
+            .layer(cors)
+            .with_state(self.state)
+    }
+}
+
+/// Every endpoint of the API, at its path within a version
+pub fn api_routes(state: &AppState) -> Router<AppState> {
+    // Shared links are readable without credentials, so they get their own rate limit
+    let shared = Router::new()
+        .route("/shared/:token", get(get_shared_context))
+        .route_layer(middleware::from_fn_with_state(
+            state.share_rate_limiter.clone(),
+            rate_limit,
+        ));
+
+    // Reads, searches and writes are rate limited separately, when limits are configured
+    let reads = Router::new()
         .route("/contexts", get(list_contexts))
+        .route("/contexts/count", get(count_contexts))
+        .route("/tags", get(list_tags))
+        .route("/collections", get(list_collections))
+        .route("/collections/:id", get(get_collection))
+        .route("/export", get(export_contexts))
+        .route("/events", get(context_events))
+        .route("/ws", get(subscribe_ws))
+        .route("/mcp/sse", get(mcp_sse))
         .route("/contexts/:id", get(get_context))
-        .route("/contexts/:id", put(update_context))
-        .route("/contexts/:id", delete(delete_context))
-        // Context search
+        .route("/contexts/:id/raw", get(get_raw_context))
+        .route("/contexts/:id/children", get(list_children))
+        .route("/contexts/:id/related", get(related_contexts))
+        .route("/contexts/:id/revisions", get(list_revisions))
+        .route("/contexts/:id/revisions/:version", get(get_revision))
+        .route("/chunks/:chunk_id", get(get_chunk))
+        .route("/admin/eval/runs", get(list_eval_runs));
+    let search = Router::new()
         .route("/search", post(search_contexts))
+        .route("/search", get(search_contexts_by_query))
         .route("/references", post(retrieve_by_references))
-        // Add middleware
-        .layer(TraceLayer::new_for_http())
-        .layer(cors)
-        .with_state(state)
+        .route("/mcp/messages", post(mcp_message))
+        .route("/admin/eval/run", post(run_eval));
+    let writes = Router::new()
+        .route("/contexts", post(store_context))
+        .route("/contexts/upload", post(upload_context))
+        .route("/contexts", delete(delete_contexts_by_tags))
+        .route("/contexts/:id", put(update_context))
+        .route("/contexts/:id", delete(delete_context))
+        .route("/contexts/:id/restore", post(restore_context))
+        .route("/contexts/:id/relations", post(add_relation))
+        .route(
+            "/contexts/:id/relations/:target_id",
+            delete(remove_relation),
+        )
+        .route("/contexts/delete", post(delete_contexts))
+        .route("/collections", post(create_collection))
+        .route("/collections/:id", put(update_collection))
+        .route("/collections/:id", delete(delete_collection))
+        .route("/import", post(import_contexts))
+        .route("/ingest/url", post(ingest_url))
+        .route(
+            "/contexts/:id/revisions/:version/restore",
+            post(restore_revision),
+        )
+        .route("/contexts/:id/share", post(create_share_link))
+        .route("/contexts/:id/share/:token_id", delete(revoke_share_link))
+        .route("/admin/eval/datasets", post(store_eval_dataset));
+    let (reads, search, writes) = match &state.rate_limits {
+        Some(limits) => (
+            limited(reads, limits.reads.clone()),
+            limited(search, limits.search.clone()),
+            limited(writes, limits.writes.clone()),
+        ),
+        None => (reads, search, writes),
+    };
+
+    // Every other route needs credentials, when authentication is configured
+    let mut api = Router::new()
+        .route("/ready", get(ready))
+        .merge(reads)
+        .merge(search)
+        .merge(writes);
+    if let Some(auth) = state.auth.clone() {
+        api = api.route_layer(middleware::from_fn_with_state(auth, authenticate));
+
+        // The route limits only see authenticated requests, so failures are limited before
+        if let Some(limits) = &state.rate_limits {
+            api = api.route_layer(middleware::from_fn_with_state(
+                limits.failed_auth.clone(),
+                limit_failed_auth,
+            ));
+        }
+    }
+
+    // The health check and shared links need no credentials
+    Router::new()
+        .route("/health", get(health))
+        .merge(api)
+        .merge(shared)
+}
+
+/// Apply `limiter` to every route of `router`
+fn limited(router: Router<AppState>, limiter: Arc<RateLimiter>) -> Router<AppState> {
+    router.route_layer(middleware::from_fn_with_state(limiter, rate_limit))
 }
@@ -0,0 +1,457 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::config::ShareConfig;
+use crate::domain::{McpError, McpResult};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The claims carried by a sharing link token
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShareToken {
+    /// Identifier of this particular link, used for revocation
+    pub token_id: Uuid,
+
+    /// The context the link grants read access to
+    pub context_id: Uuid,
+
+    /// When the link stops working
+    pub expires_at: DateTime<Utc>,
+}
+
+/// A link that was issued and hasn't expired, remembered so it can be revoked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IssuedLink {
+    token_id: Uuid,
+    context_id: Uuid,
+    expires_at: DateTime<Utc>,
+    revoked: bool,
+}
+
+/// Issues and verifies signed, expiring, read-only links to individual contexts
+///
+/// Tokens have the form `<token_id>.<context_id>.<expires_unix>.<signature>` where the
+/// signature is an HMAC-SHA256 over the first three parts with the server secret.
+/// Tokens verify on their own; the unexpired links are remembered, in `links_path` if
+/// set, so a link can only be revoked through the context it was issued for.
+pub struct ShareLinkService {
+    secret: Vec<u8>,
+    default_ttl: Duration,
+    max_ttl: Duration,
+    links: Mutex<HashMap<Uuid, IssuedLink>>,
+    links_path: Option<PathBuf>,
+    /// Whether links outlive the process, so revocations kept in memory would be lost
+    durable: bool,
+}
+
+impl ShareLinkService {
+    pub fn new(secret: impl Into<Vec<u8>>, default_ttl: Duration, max_ttl: Duration) -> Self {
+        Self {
+            secret: secret.into(),
+            default_ttl,
+            max_ttl,
+            links: Mutex::new(HashMap::new()),
+            links_path: None,
+            durable: false,
+        }
+    }
+
+    /// Keep the issued links and their revocations in the file at `path`, loading those
+    /// already kept there
+    pub fn with_links_file(mut self, path: impl Into<PathBuf>) -> McpResult<Self> {
+        let path = path.into();
+        let links: Vec<IssuedLink> = match std::fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes).map_err(|err| {
+                McpError::SerializationError(format!(
+                    "Invalid share links file {}: {}",
+                    path.display(),
+                    err
+                ))
+            })?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        self.links = Mutex::new(
+            links
+                .into_iter()
+                .map(|link| (link.token_id, link))
+                .collect(),
+        );
+        self.links_path = Some(path);
+        Ok(self)
+    }
+
+    /// Create the service from configuration, generating a random secret if none is set
+    ///
+    /// Links signed with a configured secret survive a restart, so they can only be revoked
+    /// when `config.links_path` keeps the revocations as well.
+    pub fn from_config(secret: Option<&str>, config: &ShareConfig) -> McpResult<Self> {
+        let (secret, durable) = match secret {
+            Some(secret) => (secret.as_bytes().to_vec(), true),
+            None => {
+                // Links signed with a random secret do not survive a restart
+                let mut random = Uuid::new_v4().as_bytes().to_vec();
+                random.extend_from_slice(Uuid::new_v4().as_bytes());
+                (random, false)
+            }
+        };
+
+        let mut service = Self::new(
+            secret,
+            Duration::seconds(config.default_ttl_seconds as i64),
+            Duration::seconds(config.max_ttl_seconds as i64),
+        );
+        service.durable = durable;
+
+        match &config.links_path {
+            Some(path) => service.with_links_file(path),
+            None => Ok(service),
+        }
+    }
+
+    /// Whether revoked links stay revoked after a restart, or die with it anyway
+    pub fn can_revoke(&self) -> bool {
+        !self.durable || self.links_path.is_some()
+    }
+
+    /// Issue a new link for a context, returning its claims and the encoded token
+    pub fn issue(
+        &self,
+        context_id: Uuid,
+        ttl_seconds: Option<u64>,
+        now: DateTime<Utc>,
+    ) -> McpResult<(ShareToken, String)> {
+        let ttl_seconds = ttl_seconds.unwrap_or(self.default_ttl.num_seconds() as u64);
+
+        if ttl_seconds == 0 {
+            return Err(McpError::ValidationError(
+                "ttl_seconds must be greater than zero".to_string(),
+            ));
+        }
+
+        if ttl_seconds > self.max_ttl.num_seconds() as u64 {
+            return Err(McpError::ValidationError(format!(
+                "ttl_seconds must not exceed {}",
+                self.max_ttl.num_seconds()
+            )));
+        }
+
+        // Whole seconds only, so the claims match what the token encodes
+        let expires_at = Utc
+            .timestamp_opt(now.timestamp() + ttl_seconds as i64, 0)
+            .single()
+            .ok_or_else(|| McpError::ValidationError("ttl_seconds is too large".to_string()))?;
+
+        let token = ShareToken {
+            token_id: Uuid::new_v4(),
+            context_id,
+            expires_at,
+        };
+
+        let mut links = self.links.lock().unwrap();
+        links.retain(|_, link| link.expires_at > now);
+        links.insert(
+            token.token_id,
+            IssuedLink {
+                token_id: token.token_id,
+                context_id,
+                expires_at,
+                revoked: false,
+            },
+        );
+        self.save(&links)?;
+
+        let payload = Self::payload(&token);
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+
+        Ok((token, format!("{}.{}", payload, signature)))
+    }
+
+    /// Verify an encoded token, rejecting tampered, expired, and revoked links
+    pub fn verify(&self, encoded: &str, now: DateTime<Utc>) -> McpResult<ShareToken> {
+        let invalid = || McpError::AuthenticationError("Invalid share token".to_string());
+
+        let (payload, signature) = encoded.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+
+        // Constant-time comparison of the signature
+        self.mac(payload)
+            .verify_slice(&signature)
+            .map_err(|_| invalid())?;
+
+        let mut parts = payload.split('.');
+        let token_id = parts.next().and_then(|p| Uuid::parse_str(p).ok());
+        let context_id = parts.next().and_then(|p| Uuid::parse_str(p).ok());
+        let expires_at = parts
+            .next()
+            .and_then(|p| p.parse::<i64>().ok())
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single());
+
+        let token = match (token_id, context_id, expires_at, parts.next()) {
+            (Some(token_id), Some(context_id), Some(expires_at), None) => ShareToken {
+                token_id,
+                context_id,
+                expires_at,
+            },
+            _ => return Err(invalid()),
+        };
+
+        if now >= token.expires_at {
+            return Err(McpError::AuthenticationError(
+                "Share link has expired".to_string(),
+            ));
+        }
+
+        let revoked = self
+            .links
+            .lock()
+            .unwrap()
+            .get(&token.token_id)
+            .is_some_and(|link| link.revoked);
+        if revoked {
+            return Err(McpError::AuthenticationError(
+                "Share link has been revoked".to_string(),
+            ));
+        }
+
+        Ok(token)
+    }
+
+    /// Revoke a link issued for `context_id` that hasn't expired yet
+    pub fn revoke(&self, context_id: Uuid, token_id: Uuid, now: DateTime<Utc>) -> McpResult<()> {
+        if !self.can_revoke() {
+            return Err(McpError::ValidationError(
+                "Sharing links can't be revoked without server.share.links_path, \
+                 as the revocation would be lost on restart"
+                    .to_string(),
+            ));
+        }
+
+        let mut links = self.links.lock().unwrap();
+        match links.get_mut(&token_id) {
+            Some(link) if link.context_id == context_id && link.expires_at > now => {
+                link.revoked = true;
+            }
+            _ => {
+                return Err(McpError::ShareLinkNotFound {
+                    context_id,
+                    token_id,
+                })
+            }
+        }
+        self.save(&links)
+    }
+
+    /// Write the links to the links file, if there is one, replacing it whole
+    fn save(&self, links: &HashMap<Uuid, IssuedLink>) -> McpResult<()> {
+        let Some(path) = &self.links_path else {
+            return Ok(());
+        };
+
+        let links: Vec<&IssuedLink> = links.values().collect();
+        let bytes = serde_json::to_vec(&links)
+            .map_err(|err| McpError::SerializationError(err.to_string()))?;
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, bytes)?;
+        std::fs::rename(&temporary, path)?;
+        Ok(())
+    }
+
+    fn payload(token: &ShareToken) -> String {
+        format!(
+            "{}.{}.{}",
+            token.token_id,
+            token.context_id,
+            token.expires_at.timestamp()
+        )
+    }
+
+    fn mac(&self, payload: &str) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> ShareLinkService {
+        ShareLinkService::new(
+            "test-secret",
+            Duration::seconds(3600),
+            Duration::seconds(86400),
+        )
+    }
+
+    #[test]
+    fn test_issue_and_verify() {
+        let service = service();
+        let context_id = Uuid::new_v4();
+        let now = Utc::now();
+
+        let (issued, encoded) = service.issue(context_id, None, now).unwrap();
+        let verified = service.verify(&encoded, now).unwrap();
+
+        assert_eq!(verified, issued);
+        assert_eq!(verified.context_id, context_id);
+        assert_eq!(
+            verified.expires_at.timestamp(),
+            (now + Duration::seconds(3600)).timestamp()
+        );
+    }
+
+    #[test]
+    fn test_expired_token_is_rejected() {
+        let service = service();
+        let now = Utc::now();
+
+        let (_, encoded) = service.issue(Uuid::new_v4(), Some(60), now).unwrap();
+
+        assert!(service
+            .verify(&encoded, now + Duration::seconds(59))
+            .is_ok());
+        assert!(matches!(
+            service.verify(&encoded, now + Duration::seconds(61)),
+            Err(McpError::AuthenticationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_revoked_token_is_rejected() {
+        let service = service();
+        let now = Utc::now();
+
+        let (token, encoded) = service.issue(Uuid::new_v4(), None, now).unwrap();
+        service
+            .revoke(token.context_id, token.token_id, now)
+            .unwrap();
+
+        assert!(matches!(
+            service.verify(&encoded, now),
+            Err(McpError::AuthenticationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_links_are_only_revoked_through_their_context() {
+        let service = service();
+        let now = Utc::now();
+
+        let (token, encoded) = service.issue(Uuid::new_v4(), None, now).unwrap();
+
+        assert!(matches!(
+            service.revoke(Uuid::new_v4(), token.token_id, now),
+            Err(McpError::ShareLinkNotFound { .. })
+        ));
+        assert!(matches!(
+            service.revoke(token.context_id, Uuid::new_v4(), now),
+            Err(McpError::ShareLinkNotFound { .. })
+        ));
+        assert!(service.verify(&encoded, now).is_ok());
+    }
+
+    #[test]
+    fn test_revocations_survive_reopening_the_links_file() {
+        let path = std::env::temp_dir().join(format!("mcp-share-links-{}.json", Uuid::new_v4()));
+        let now = Utc::now();
+
+        let original = service().with_links_file(&path).unwrap();
+        let (revoked, revoked_encoded) = original.issue(Uuid::new_v4(), None, now).unwrap();
+        let (_, kept_encoded) = original.issue(Uuid::new_v4(), None, now).unwrap();
+        original
+            .revoke(revoked.context_id, revoked.token_id, now)
+            .unwrap();
+
+        let reopened = service().with_links_file(&path).unwrap();
+        assert!(reopened.verify(&revoked_encoded, now).is_err());
+        assert!(reopened.verify(&kept_encoded, now).is_ok());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_configured_secret_needs_a_links_file_to_revoke() {
+        let mut config = ShareConfig {
+            default_ttl_seconds: 3600,
+            max_ttl_seconds: 86400,
+            rate_limit_per_second: 1.0,
+            rate_limit_burst: 10,
+            links_path: None,
+        };
+        let now = Utc::now();
+
+        // Links signed with a random secret die with the process, revocations with them
+        let ephemeral = ShareLinkService::from_config(None, &config).unwrap();
+        let (token, _) = ephemeral.issue(Uuid::new_v4(), None, now).unwrap();
+        assert!(ephemeral
+            .revoke(token.context_id, token.token_id, now)
+            .is_ok());
+
+        let durable = ShareLinkService::from_config(Some("secret"), &config).unwrap();
+        let (token, _) = durable.issue(Uuid::new_v4(), None, now).unwrap();
+        assert!(matches!(
+            durable.revoke(token.context_id, token.token_id, now),
+            Err(McpError::ValidationError(_))
+        ));
+
+        let path = std::env::temp_dir().join(format!("mcp-share-links-{}.json", Uuid::new_v4()));
+        config.links_path = Some(path.to_string_lossy().into_owned());
+        let durable = ShareLinkService::from_config(Some("secret"), &config).unwrap();
+        let (token, _) = durable.issue(Uuid::new_v4(), None, now).unwrap();
+        assert!(durable
+            .revoke(token.context_id, token.token_id, now)
+            .is_ok());
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_tampered_token_is_rejected() {
+        let service = service();
+        let now = Utc::now();
+
+        let (token, encoded) = service.issue(Uuid::new_v4(), None, now).unwrap();
+
+        // Point the token at a different context while keeping the signature
+        let tampered = encoded.replace(&token.context_id.to_string(), &Uuid::new_v4().to_string());
+        assert!(service.verify(&tampered, now).is_err());
+
+        // Extend the expiry while keeping the signature
+        let tampered = encoded.replace(
+            &token.expires_at.timestamp().to_string(),
+            &(token.expires_at.timestamp() + 1000).to_string(),
+        );
+        assert!(service.verify(&tampered, now).is_err());
+
+        // A token signed with a different secret
+        let other = ShareLinkService::new(
+            "other-secret",
+            Duration::seconds(3600),
+            Duration::seconds(86400),
+        );
+        let (_, foreign) = other.issue(token.context_id, None, now).unwrap();
+        assert!(service.verify(&foreign, now).is_err());
+
+        assert!(service.verify("garbage", now).is_err());
+    }
+
+    #[test]
+    fn test_ttl_is_validated() {
+        let service = service();
+        let now = Utc::now();
+
+        assert!(service.issue(Uuid::new_v4(), Some(0), now).is_err());
+        assert!(service.issue(Uuid::new_v4(), Some(86401), now).is_err());
+        assert!(service.issue(Uuid::new_v4(), Some(86400), now).is_ok());
+    }
+}
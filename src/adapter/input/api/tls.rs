@@ -0,0 +1,188 @@
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::crypto::ring::default_provider;
+use rustls::sign::CertifiedKey;
+use rustls::{Error as TlsError, InconsistentKeys, ServerConfig};
+use std::io::{self, BufRead, BufReader};
+use std::sync::Arc;
+
+use crate::config::TlsConfig;
+use crate::domain::{McpError, McpResult};
+
+/// TLS settings for the server, or `None` if `config` leaves TLS off
+///
+/// Fails if either file can't be read or parsed, or if the key isn't the certificate's.
+pub fn tls_from_config(config: &TlsConfig) -> McpResult<Option<RustlsConfig>> {
+    let Some((cert_path, key_path)) = config.paths()? else {
+        return Ok(None);
+    };
+    let server_config = server_config(cert_path, key_path)?;
+    Ok(Some(RustlsConfig::from_config(Arc::new(server_config))))
+}
+
+/// Re-read the certificate and key into `tls` whenever the process gets a SIGHUP
+///
+/// Connections already open keep their certificate. If the new files are invalid the
+/// error is logged and the server goes on with the certificate it has.
+#[cfg(unix)]
+pub fn reload_on_sighup(tls: RustlsConfig, cert_path: String, key_path: String) -> McpResult<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangups = signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangups.recv().await.is_some() {
+            match server_config(&cert_path, &key_path) {
+                Ok(server_config) => {
+                    tls.reload_from_config(Arc::new(server_config));
+                    tracing::info!("Reloaded the TLS certificate from {}", cert_path);
+                }
+                Err(err) => {
+                    tracing::error!("Keeping the current TLS certificate: {}", err);
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+/// A rustls server config presenting the certificate chain in `cert_path`
+fn server_config(cert_path: &str, key_path: &str) -> McpResult<ServerConfig> {
+    let certs = read_pem(cert_path, |reader| {
+        rustls_pemfile::certs(reader).collect::<io::Result<Vec<_>>>()
+    })?;
+    if certs.is_empty() {
+        return Err(McpError::ValidationError(format!(
+            "No certificate in {}",
+            cert_path
+        )));
+    }
+    let key = read_pem(key_path, rustls_pemfile::private_key)?
+        .ok_or_else(|| McpError::ValidationError(format!("No private key in {}", key_path)))?;
+
+    // Checked here so a key from another certificate gets an error naming both files
+    let provider = Arc::new(default_provider());
+    let signing_key = provider
+        .key_provider
+        .load_private_key(key.clone_key())
+        .map_err(|e| {
+            McpError::ValidationError(format!("Invalid private key in {}: {}", key_path, e))
+        })?;
+    if let Err(TlsError::InconsistentKeys(InconsistentKeys::KeyMismatch)) =
+        CertifiedKey::new(certs.clone(), signing_key).keys_match()
+    {
+        return Err(McpError::ValidationError(format!(
+            "The private key in {} doesn't match the certificate in {}",
+            key_path, cert_path
+        )));
+    }
+
+    let mut config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .and_then(|builder| builder.with_no_client_auth().with_single_cert(certs, key))
+        .map_err(|e| McpError::ValidationError(format!("Invalid TLS configuration: {}", e)))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+/// Parse the PEM file at `path`
+fn read_pem<T>(path: &str, parse: impl FnOnce(&mut dyn BufRead) -> io::Result<T>) -> McpResult<T> {
+    let file = std::fs::File::open(path)
+        .map_err(|e| McpError::ValidationError(format!("Failed to read {}: {}", path, e)))?;
+    parse(&mut BufReader::new(file))
+        .map_err(|e| McpError::ValidationError(format!("Invalid PEM in {}: {}", path, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// PEM files of a self-signed certificate for `localhost`, in a fresh directory
+    struct CertFiles {
+        dir: PathBuf,
+        cert_path: String,
+        key_path: String,
+    }
+
+    impl CertFiles {
+        fn generate() -> Self {
+            let dir = std::env::temp_dir().join(format!("mcp-tls-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            let generated = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+            let cert_path = dir.join("cert.pem");
+            let key_path = dir.join("key.pem");
+            std::fs::write(&cert_path, generated.cert.pem()).unwrap();
+            std::fs::write(&key_path, generated.key_pair.serialize_pem()).unwrap();
+            Self {
+                cert_path: cert_path.to_string_lossy().into_owned(),
+                key_path: key_path.to_string_lossy().into_owned(),
+                dir,
+            }
+        }
+
+        fn config(&self) -> TlsConfig {
+            TlsConfig {
+                cert_path: Some(self.cert_path.clone()),
+                key_path: Some(self.key_path.clone()),
+            }
+        }
+    }
+
+    impl Drop for CertFiles {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    fn error_message(config: &TlsConfig) -> String {
+        match tls_from_config(config) {
+            Err(McpError::ValidationError(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_tls_is_off_without_paths() {
+        assert!(tls_from_config(&TlsConfig::default()).unwrap().is_none());
+
+        let files = CertFiles::generate();
+        let only_cert = TlsConfig {
+            key_path: None,
+            ..files.config()
+        };
+        assert!(error_message(&only_cert).contains("must be set together"));
+    }
+
+    #[test]
+    fn test_matching_certificate_and_key_load() {
+        let files = CertFiles::generate();
+        assert!(tls_from_config(&files.config()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_unreadable_files_are_reported() {
+        let files = CertFiles::generate();
+        let missing = TlsConfig {
+            cert_path: Some(format!("{}.missing", files.cert_path)),
+            ..files.config()
+        };
+        assert!(error_message(&missing).starts_with("Failed to read"));
+
+        // A certificate where the key should be holds no private key
+        let swapped = TlsConfig {
+            key_path: Some(files.cert_path.clone()),
+            ..files.config()
+        };
+        assert!(error_message(&swapped).starts_with("No private key in"));
+    }
+
+    #[test]
+    fn test_key_of_another_certificate_is_rejected() {
+        let files = CertFiles::generate();
+        let other = CertFiles::generate();
+        let mismatched = TlsConfig {
+            key_path: Some(other.key_path.clone()),
+            ..files.config()
+        };
+        assert!(error_message(&mismatched).contains("doesn't match the certificate"));
+    }
+}
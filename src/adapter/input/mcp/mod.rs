@@ -0,0 +1,7 @@
+pub mod protocol;
+pub mod server;
+pub mod sse;
+
+pub use protocol::{Notification, Request, Response, RpcError, ServerMessage};
+pub use server::{McpServer, McpSession, CONTEXT_URI_SCHEME, PROTOCOL_VERSION};
+pub use sse::{SessionStream, SseTransport};
@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::domain::McpError;
+
+/// Version of JSON-RPC every message carries
+pub const JSONRPC_VERSION: &str = "2.0";
+
+/// Code of a message that isn't valid JSON
+pub const PARSE_ERROR: i64 = -32700;
+
+/// Code of a message that isn't a JSON-RPC request
+pub const INVALID_REQUEST: i64 = -32600;
+
+/// Code of a request for a method the server doesn't have
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Code of a request whose parameters don't fit its method
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// Code of a request the server failed to carry out
+pub const INTERNAL_ERROR: i64 = -32603;
+
+/// Code of a `resources/read` naming a resource that doesn't exist
+pub const RESOURCE_NOT_FOUND: i64 = -32002;
+
+/// A JSON-RPC request, or a notification when it has no `id`
+#[derive(Debug, Clone, Deserialize)]
+pub struct Request {
+    pub jsonrpc: String,
+
+    /// Id the response is sent back with, absent for notifications
+    #[serde(default)]
+    pub id: Option<Value>,
+
+    pub method: String,
+
+    #[serde(default)]
+    pub params: Option<Value>,
+}
+
+/// A JSON-RPC response, carrying either a result or an error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub jsonrpc: String,
+
+    /// Id of the request answered, `null` when it couldn't be read
+    pub id: Value,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<RpcError>,
+}
+
+impl Response {
+    /// The response to request `id` with `result`
+    pub fn success(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    /// The response to request `id` with `error`
+    pub fn failure(id: Value, error: RpcError) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            id,
+            result: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// A JSON-RPC notification the server sends without being asked, which gets no response
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub jsonrpc: String,
+    pub method: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl Notification {
+    /// A notification of `method` with `params`
+    pub fn new(method: impl Into<String>, params: Option<Value>) -> Self {
+        Self {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            method: method.into(),
+            params,
+        }
+    }
+}
+
+/// A message the server sends a client: the response to a request, or a notification
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ServerMessage {
+    Response(Response),
+    Notification(Notification),
+}
+
+impl From<Response> for ServerMessage {
+    fn from(response: Response) -> Self {
+        ServerMessage::Response(response)
+    }
+}
+
+impl From<Notification> for ServerMessage {
+    fn from(notification: Notification) -> Self {
+        ServerMessage::Notification(notification)
+    }
+}
+
+/// A JSON-RPC error object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    /// An error with `code` and `message`
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// The error with `data` attached
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Error for a message that isn't valid JSON
+    pub fn parse_error(message: impl Into<String>) -> Self {
+        Self::new(PARSE_ERROR, message)
+    }
+
+    /// Error for a message that isn't a request the server accepts
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(INVALID_REQUEST, message)
+    }
+
+    /// Error for a request naming an unknown `method`
+    pub fn method_not_found(method: &str) -> Self {
+        Self::new(METHOD_NOT_FOUND, format!("Method not found: {}", method))
+    }
+
+    /// Error for parameters that are missing or of the wrong shape
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(INVALID_PARAMS, message)
+    }
+}
+
+impl From<McpError> for RpcError {
+    fn from(err: McpError) -> Self {
+        match err {
+            McpError::ContextNotFound(id) => {
+                Self::new(RESOURCE_NOT_FOUND, format!("Context not found: {}", id))
+            }
+            McpError::ValidationError(msg) => Self::invalid_params(msg),
+            err @ McpError::ValidationFailed(_) => Self::invalid_params(err.to_string()),
+            err => Self::new(INTERNAL_ERROR, err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,1382 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::debug;
+use uuid::Uuid;
+
+use super::protocol::{Notification, Request, Response, RpcError, ServerMessage, JSONRPC_VERSION};
+use crate::adapter::output::BroadcastEventPublisher;
+use crate::domain::{
+    Context, ContextEvent, ContextEventKind, ContextFilter, ContextMetadata, McpResult,
+    PromptTemplate, SearchOptions, StoreOptions, TagPolicy,
+};
+use crate::ports::in_ports::{ContextManagementPort, ContextSearchPort};
+
+/// Revision of the Model Context Protocol the server speaks, unless configured otherwise
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+/// Notification a client sends once it has the `initialize` response
+pub const INITIALIZED_NOTIFICATION: &str = "notifications/initialized";
+
+/// Notification telling a client that a resource it subscribed to changed
+pub const RESOURCE_UPDATED_NOTIFICATION: &str = "notifications/resources/updated";
+
+/// Notification telling a client that contexts were created or deleted
+pub const RESOURCE_LIST_CHANGED_NOTIFICATION: &str = "notifications/resources/list_changed";
+
+/// Prefix of the URIs contexts are exposed as resources under
+pub const CONTEXT_URI_SCHEME: &str = "context://";
+
+/// Resources listed per `resources/list` page
+const RESOURCE_PAGE_SIZE: usize = 100;
+
+/// Model Context Protocol server exposing stored contexts as resources, tools and prompts
+///
+/// Speaks JSON-RPC 2.0, one message per line, as hosts do over stdio. Until a client has
+/// sent `initialize` and then `notifications/initialized` in its [`McpSession`], every
+/// method but `ping` is refused.
+pub struct McpServer {
+    context_manager: Arc<dyn ContextManagementPort + Send + Sync>,
+    context_search: Arc<dyn ContextSearchPort + Send + Sync>,
+    tag_policy: Arc<TagPolicy>,
+    max_results: usize,
+    prompts: Vec<PromptTemplate>,
+    protocol_versions: Vec<String>,
+    events: Option<Arc<BroadcastEventPublisher>>,
+}
+
+/// One client's session with an [`McpServer`], which has to be initialized before use
+///
+/// Its resource subscriptions go away with it.
+#[derive(Debug, Default)]
+pub struct McpSession {
+    state: Mutex<SessionState>,
+    subscriptions: Mutex<HashSet<Uuid>>,
+}
+
+#[derive(Debug, Clone, Default)]
+enum SessionState {
+    /// Waiting for `initialize`
+    #[default]
+    New,
+
+    /// `initialize` was answered, waiting for `notifications/initialized`
+    Initializing(Negotiated),
+
+    Ready(Negotiated),
+}
+
+/// What a client and the server agreed on in `initialize`
+#[derive(Debug, Clone)]
+struct Negotiated {
+    protocol_version: String,
+    client_capabilities: ClientCapabilities,
+}
+
+impl McpSession {
+    /// The protocol revision negotiated, once `initialize` has been answered
+    pub fn protocol_version(&self) -> Option<String> {
+        match &*self.state.lock().unwrap() {
+            SessionState::New => None,
+            SessionState::Initializing(negotiated) | SessionState::Ready(negotiated) => {
+                Some(negotiated.protocol_version.clone())
+            }
+        }
+    }
+
+    /// Whether the client can ask the server for its roots, as it said in `initialize`
+    pub fn client_has_roots(&self) -> bool {
+        match &*self.state.lock().unwrap() {
+            SessionState::New => false,
+            SessionState::Initializing(negotiated) | SessionState::Ready(negotiated) => {
+                negotiated.client_capabilities.roots.is_some()
+            }
+        }
+    }
+
+    /// Whether the handshake is done and requests are answered
+    pub fn is_ready(&self) -> bool {
+        matches!(*self.state.lock().unwrap(), SessionState::Ready(_))
+    }
+
+    /// Whether the client subscribed to the context with `id`
+    pub fn is_subscribed(&self, id: Uuid) -> bool {
+        self.subscriptions.lock().unwrap().contains(&id)
+    }
+
+    /// Take `notifications/initialized`, which only counts after `initialize`
+    fn ready(&self) {
+        let mut state = self.state.lock().unwrap();
+        match &*state {
+            SessionState::Initializing(negotiated) => {
+                *state = SessionState::Ready(negotiated.clone())
+            }
+            SessionState::New => debug!("Ignoring {} before initialize", INITIALIZED_NOTIFICATION),
+            SessionState::Ready(_) => {}
+        }
+    }
+}
+
+impl McpServer {
+    /// Create a server working on contexts through `context_manager` and `context_search`
+    pub fn new(
+        context_manager: Arc<dyn ContextManagementPort + Send + Sync>,
+        context_search: Arc<dyn ContextSearchPort + Send + Sync>,
+    ) -> Self {
+        Self {
+            context_manager,
+            context_search,
+            tag_policy: Arc::new(TagPolicy::default()),
+            max_results: 10,
+            prompts: Vec::new(),
+            protocol_versions: vec![PROTOCOL_VERSION.to_string()],
+            events: None,
+        }
+    }
+
+    /// Normalize the tags of stored contexts and searches with `tag_policy`
+    pub fn with_tag_policy(mut self, tag_policy: Arc<TagPolicy>) -> Self {
+        self.tag_policy = tag_policy;
+        self
+    }
+
+    /// Set the most matches a search returns, which is also its default limit
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    /// Offer `prompts`, filled in with the contexts their searches find
+    pub fn with_prompts(mut self, prompts: Vec<PromptTemplate>) -> Self {
+        self.prompts = prompts;
+        self
+    }
+
+    /// Notify sessions of the context changes published to `events`, letting clients subscribe
+    /// to resources
+    pub fn with_events(mut self, events: Arc<BroadcastEventPublisher>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Accept the protocol revisions in `versions`, most preferred first, instead of just
+    /// [`PROTOCOL_VERSION`]; an empty list keeps the default
+    pub fn with_protocol_versions(mut self, versions: Vec<String>) -> Self {
+        if !versions.is_empty() {
+            self.protocol_versions = versions;
+        }
+        self
+    }
+
+    /// Answer the messages read from `reader` on `writer`, as one session, until `reader` is
+    /// closed
+    ///
+    /// Each line holds one message and blank lines are skipped. Malformed messages get
+    /// JSON-RPC errors; only failing to read or write ends the session early. Notifications
+    /// of context changes are written between the responses.
+    pub async fn serve<R, W>(&self, reader: R, mut writer: W) -> io::Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin,
+    {
+        let session = McpSession::default();
+        let mut events = self.subscribe_events();
+        let mut lines = BufReader::new(reader).lines();
+        loop {
+            let messages: Vec<ServerMessage> = tokio::select! {
+                line = lines.next_line() => match line? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => {
+                        let response = self.handle_message(&session, &line).await;
+                        response.into_iter().map(Into::into).collect()
+                    }
+                    None => break,
+                },
+                event = next_event(&mut events) => {
+                    if matches!(event, Err(RecvError::Closed)) {
+                        events = None;
+                    }
+                    let notifications = self.notifications(&session, event);
+                    notifications.into_iter().map(Into::into).collect()
+                }
+            };
+            for message in messages {
+                let mut line = serde_json::to_vec(&message)?;
+                line.push(b'\n');
+                writer.write_all(&line).await?;
+            }
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// Receive the context changes published from now on, if the server notifies of them
+    pub fn subscribe_events(&self) -> Option<broadcast::Receiver<ContextEvent>> {
+        self.events.as_ref().map(|events| events.subscribe())
+    }
+
+    /// The notifications `session` gets for a context change, or for having missed some
+    ///
+    /// Creating or deleting a context changes the resource list, and updating or deleting one
+    /// updates its resource for the sessions subscribed to it. A session that missed events
+    /// is told everything may have changed.
+    pub fn notifications(
+        &self,
+        session: &McpSession,
+        event: Result<ContextEvent, RecvError>,
+    ) -> Vec<Notification> {
+        if !session.is_ready() {
+            return Vec::new();
+        }
+        let updated = |id: Uuid| {
+            Notification::new(
+                RESOURCE_UPDATED_NOTIFICATION,
+                Some(json!({ "uri": context_uri(id) })),
+            )
+        };
+        let list_changed = || Notification::new(RESOURCE_LIST_CHANGED_NOTIFICATION, None);
+
+        match event {
+            Ok(event) => {
+                let mut notifications = Vec::new();
+                let subscribed = match event.kind {
+                    ContextEventKind::Deleted => session
+                        .subscriptions
+                        .lock()
+                        .unwrap()
+                        .remove(&event.context_id),
+                    _ => session.is_subscribed(event.context_id),
+                };
+                if subscribed && event.kind != ContextEventKind::Created {
+                    notifications.push(updated(event.context_id));
+                }
+                if event.kind != ContextEventKind::Updated {
+                    notifications.push(list_changed());
+                }
+                notifications
+            }
+            Err(RecvError::Lagged(_)) => {
+                let mut notifications: Vec<Notification> = session
+                    .subscriptions
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|id| updated(*id))
+                    .collect();
+                notifications.push(list_changed());
+                notifications
+            }
+            Err(RecvError::Closed) => Vec::new(),
+        }
+    }
+
+    /// The response to a single message of `session`, or `None` if it's a notification
+    pub async fn handle_message(&self, session: &McpSession, message: &str) -> Option<Response> {
+        let message: Value = match serde_json::from_str(message) {
+            Ok(message) => message,
+            Err(err) => {
+                return Some(Response::failure(
+                    Value::Null,
+                    RpcError::parse_error(format!("Invalid JSON: {}", err)),
+                ))
+            }
+        };
+
+        // Batches aren't part of this protocol revision, so an array is an invalid request
+        let id = message.get("id").cloned().unwrap_or(Value::Null);
+        let request: Request = match serde_json::from_value(message) {
+            Ok(request) => request,
+            Err(err) => {
+                return Some(Response::failure(
+                    id,
+                    RpcError::invalid_request(format!("Invalid request: {}", err)),
+                ))
+            }
+        };
+        if request.jsonrpc != JSONRPC_VERSION {
+            return Some(Response::failure(
+                id,
+                RpcError::invalid_request(format!("jsonrpc must be \"{}\"", JSONRPC_VERSION)),
+            ));
+        }
+
+        // Notifications, such as `notifications/initialized`, need no answer
+        let Some(id) = request.id else {
+            debug!(method = %request.method, "Received notification");
+            if request.method == INITIALIZED_NOTIFICATION {
+                session.ready();
+            }
+            return None;
+        };
+        Some(
+            match self.call(session, &request.method, request.params).await {
+                Ok(result) => Response::success(id, result),
+                Err(error) => Response::failure(id, error),
+            },
+        )
+    }
+
+    async fn call(
+        &self,
+        session: &McpSession,
+        method: &str,
+        params: Option<Value>,
+    ) -> Result<Value, RpcError> {
+        match method {
+            "initialize" => return self.initialize(session, params_of(params)?),
+            "ping" => return Ok(json!({})),
+            _ => {}
+        }
+        match &*session.state.lock().unwrap() {
+            SessionState::Ready(_) => {}
+            SessionState::New => {
+                return Err(RpcError::invalid_request(format!(
+                    "{} was sent before initialize",
+                    method
+                )))
+            }
+            SessionState::Initializing(_) => {
+                return Err(RpcError::invalid_request(format!(
+                    "{} was sent before {}",
+                    method, INITIALIZED_NOTIFICATION
+                )))
+            }
+        }
+
+        match method {
+            "tools/list" => Ok(json!({ "tools": tools() })),
+            "tools/call" => self.call_tool(params_of(params)?).await,
+            "resources/list" => self.list_resources(params_of(params)?).await,
+            "resources/templates/list" => Ok(json!({
+                "resourceTemplates": [{
+                    "uriTemplate": format!("{}{{id}}", CONTEXT_URI_SCHEME),
+                    "name": "Context",
+                    "description": "A stored context by its id",
+                }]
+            })),
+            "resources/read" => self.read_resource(params_of(params)?).await,
+            "resources/subscribe" if self.events.is_some() => {
+                self.subscribe(session, params_of(params)?).await
+            }
+            "resources/unsubscribe" if self.events.is_some() => {
+                let ReadParams { uri } = params_of(params)?;
+                session
+                    .subscriptions
+                    .lock()
+                    .unwrap()
+                    .remove(&context_id(&uri)?);
+                Ok(json!({}))
+            }
+            "prompts/list" => {
+                Ok(json!({ "prompts": self.prompts.iter().map(prompt_json).collect::<Vec<_>>() }))
+            }
+            "prompts/get" => self.get_prompt(params_of(params)?).await,
+            _ => Err(RpcError::method_not_found(method)),
+        }
+    }
+
+    /// Agree on a protocol revision with the client and describe what the server offers
+    ///
+    /// A client asking for a revision the server accepts gets it back; one asking for any
+    /// other gets the server's preferred revision, and should disconnect if it can't speak
+    /// that one. A session is only initialized once.
+    fn initialize(
+        &self,
+        session: &McpSession,
+        params: InitializeParams,
+    ) -> Result<Value, RpcError> {
+        if params.protocol_version.trim().is_empty() {
+            return Err(
+                RpcError::invalid_params("protocolVersion must not be empty")
+                    .with_data(json!({ "supported": self.protocol_versions })),
+            );
+        }
+        let protocol_version = if self.protocol_versions.contains(&params.protocol_version) {
+            params.protocol_version.clone()
+        } else {
+            self.protocol_versions[0].clone()
+        };
+
+        {
+            let mut state = session.state.lock().unwrap();
+            if !matches!(*state, SessionState::New) {
+                return Err(RpcError::invalid_request(
+                    "The session is already initialized",
+                ));
+            }
+            *state = SessionState::Initializing(Negotiated {
+                protocol_version: protocol_version.clone(),
+                client_capabilities: params.capabilities,
+            });
+        }
+        debug!(
+            requested = %params.protocol_version,
+            negotiated = %protocol_version,
+            client = ?params.client_info.map(|client| client.name),
+            "Initializing MCP session"
+        );
+
+        // Resources change with the contexts when the server is told of the changes
+        let notifies = self.events.is_some();
+        let mut capabilities = json!({
+            "resources": { "subscribe": notifies, "listChanged": notifies },
+            "tools": { "listChanged": false },
+        });
+        if !self.prompts.is_empty() {
+            capabilities["prompts"] = json!({ "listChanged": false });
+        }
+
+        Ok(json!({
+            "protocolVersion": protocol_version,
+            "capabilities": capabilities,
+            "serverInfo": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+        }))
+    }
+
+    /// Fill in a prompt with its arguments and the contexts its search finds
+    async fn get_prompt(&self, params: GetPromptParams) -> Result<Value, RpcError> {
+        let prompt = self
+            .prompts
+            .iter()
+            .find(|prompt| prompt.name == params.name)
+            .ok_or_else(|| RpcError::invalid_params(format!("Unknown prompt: {}", params.name)))?;
+
+        let contexts = match prompt.search_query(&params.arguments)? {
+            Some(query) if !query.trim().is_empty() => self
+                .context_search
+                .search(
+                    query,
+                    prompt.top_k.min(self.max_results),
+                    SearchOptions::default(),
+                )
+                .await?
+                .matches
+                .into_iter()
+                .map(|m| match m.chunks {
+                    // The chunks that matched, or the whole context without them
+                    Some(chunks) if !chunks.is_empty() => chunks
+                        .into_iter()
+                        .map(|chunk| chunk.content)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    _ => m.context.content,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let messages: Vec<Value> = prompt
+            .render(&params.arguments, &contexts)?
+            .into_iter()
+            .map(|message| {
+                json!({
+                    "role": message.role,
+                    "content": { "type": "text", "text": message.text },
+                })
+            })
+            .collect();
+        let mut result = json!({ "messages": messages });
+        if let Some(description) = &prompt.description {
+            result["description"] = json!(description);
+        }
+        Ok(result)
+    }
+
+    /// Run a tool, reporting its failures in the result so the model sees them
+    async fn call_tool(&self, call: ToolCall) -> Result<Value, RpcError> {
+        let arguments = call.arguments;
+        let outcome = match call.name.as_str() {
+            "store_context" => self.store(params_of(arguments)?).await,
+            "search_contexts" => self.search(params_of(arguments)?).await,
+            "get_context" => {
+                let IdArguments { id } = params_of(arguments)?;
+                self.context_manager
+                    .get_context(id)
+                    .await
+                    .map(|context| context_json(&context))
+            }
+            "delete_context" => {
+                let IdArguments { id } = params_of(arguments)?;
+                self.context_manager
+                    .soft_delete_context(id)
+                    .await
+                    .map(|()| json!({ "deleted": id }))
+            }
+            name => return Err(RpcError::invalid_params(format!("Unknown tool: {}", name))),
+        };
+
+        let (text, is_error) = match outcome {
+            Ok(value) => (
+                serde_json::to_string_pretty(&value).unwrap_or_default(),
+                false,
+            ),
+            Err(err) => (err.to_string(), true),
+        };
+        Ok(json!({
+            "content": [{ "type": "text", "text": text }],
+            "isError": is_error,
+        }))
+    }
+
+    async fn store(&self, arguments: StoreArguments) -> McpResult<Value> {
+        let metadata = ContextMetadata {
+            source: arguments.source,
+            content_type: arguments.content_type,
+            content_hash: None,
+            tags: self.tag_policy.normalize_context_tags(arguments.tags)?,
+            custom: HashMap::new(),
+        };
+        let context = self
+            .context_manager
+            .store_context(arguments.content, metadata, StoreOptions::default())
+            .await?;
+        Ok(context_json(&context))
+    }
+
+    async fn search(&self, arguments: SearchArguments) -> McpResult<Value> {
+        let limit = arguments
+            .limit
+            .map_or(self.max_results, |limit| limit.min(self.max_results));
+        let tags = self.tag_policy.normalize_all(&arguments.tags)?;
+        let result = if tags.is_empty() {
+            self.context_search
+                .search(arguments.query, limit, SearchOptions::default())
+                .await?
+        } else {
+            self.context_search
+                .search_with_tags(arguments.query, tags, limit, SearchOptions::default())
+                .await?
+        };
+
+        let matches: Vec<Value> = result
+            .matches
+            .iter()
+            .map(|m| {
+                let mut context = context_json(&m.context);
+                context["score"] = json!(m.score);
+                context
+            })
+            .collect();
+        Ok(json!({
+            "matches": matches,
+            "total_matches": result.total_matches,
+            "total_matches_is_lower_bound": result.total_matches_is_lower_bound,
+        }))
+    }
+
+    async fn list_resources(&self, params: ListParams) -> Result<Value, RpcError> {
+        let offset = match params.cursor {
+            Some(cursor) => cursor
+                .parse()
+                .map_err(|_| RpcError::invalid_params(format!("Invalid cursor: {}", cursor)))?,
+            None => 0,
+        };
+
+        // Reading one past the page tells whether there's another
+        let contexts = self
+            .context_manager
+            .list_contexts(ContextFilter::default(), RESOURCE_PAGE_SIZE + 1, offset)
+            .await?;
+        let resources: Vec<Value> = contexts
+            .iter()
+            .take(RESOURCE_PAGE_SIZE)
+            .map(|context| {
+                json!({
+                    "uri": context_uri(context.id),
+                    "name": context.metadata.source.clone().unwrap_or_else(|| context.id.to_string()),
+                    "mimeType": mime_type(context),
+                })
+            })
+            .collect();
+
+        let mut result = json!({ "resources": resources });
+        if contexts.len() > RESOURCE_PAGE_SIZE {
+            result["nextCursor"] = json!((offset + RESOURCE_PAGE_SIZE).to_string());
+        }
+        Ok(result)
+    }
+
+    async fn read_resource(&self, params: ReadParams) -> Result<Value, RpcError> {
+        let id = context_id(&params.uri)?;
+        let context = self
+            .context_manager
+            .get_context(id)
+            .await
+            .map_err(|err| RpcError::from(err).with_data(json!({ "uri": params.uri })))?;
+
+        Ok(json!({
+            "contents": [{
+                "uri": params.uri,
+                "mimeType": mime_type(&context),
+                "text": context.content,
+            }]
+        }))
+    }
+
+    /// Notify `session` of changes to a context until it unsubscribes or the context is
+    /// deleted
+    async fn subscribe(&self, session: &McpSession, params: ReadParams) -> Result<Value, RpcError> {
+        let id = context_id(&params.uri)?;
+        self.context_manager
+            .get_context(id)
+            .await
+            .map_err(|err| RpcError::from(err).with_data(json!({ "uri": params.uri })))?;
+        session.subscriptions.lock().unwrap().insert(id);
+        Ok(json!({}))
+    }
+}
+
+/// The next event of `events`, or never when there are none to wait for
+async fn next_event(
+    events: &mut Option<broadcast::Receiver<ContextEvent>>,
+) -> Result<ContextEvent, RecvError> {
+    match events {
+        Some(events) => events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Id of the context `uri` names
+fn context_id(uri: &str) -> Result<Uuid, RpcError> {
+    uri.strip_prefix(CONTEXT_URI_SCHEME)
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| RpcError::invalid_params(format!("Not a context URI: {}", uri)))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    protocol_version: String,
+
+    #[serde(default)]
+    capabilities: ClientCapabilities,
+
+    #[serde(default)]
+    client_info: Option<ClientInfo>,
+}
+
+/// What a client says it can do in `initialize`
+#[derive(Debug, Clone, Default, Deserialize)]
+struct ClientCapabilities {
+    #[serde(default)]
+    roots: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClientInfo {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ToolCall {
+    name: String,
+    #[serde(default)]
+    arguments: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StoreArguments {
+    content: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    source: Option<String>,
+    content_type: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchArguments {
+    query: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    limit: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IdArguments {
+    id: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListParams {
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetPromptParams {
+    name: String,
+    #[serde(default)]
+    arguments: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadParams {
+    uri: String,
+}
+
+/// Parameters of a request as `T`, with absent parameters read as an empty object
+fn params_of<T: DeserializeOwned>(params: Option<Value>) -> Result<T, RpcError> {
+    serde_json::from_value(params.unwrap_or_else(|| json!({})))
+        .map_err(|err| RpcError::invalid_params(format!("Invalid params: {}", err)))
+}
+
+/// The tools `tools/list` describes, with JSON schemas of their arguments
+fn tools() -> Value {
+    let tags = json!({
+        "type": "array",
+        "items": { "type": "string" },
+    });
+    let id = json!({
+        "type": "object",
+        "properties": { "id": { "type": "string", "format": "uuid" } },
+        "required": ["id"],
+    });
+    json!([
+        {
+            "name": "store_context",
+            "description": "Store text as a new context, to be found by later searches",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "content": { "type": "string" },
+                    "tags": tags.clone(),
+                    "source": { "type": "string" },
+                    "content_type": { "type": "string" },
+                },
+                "required": ["content"],
+            },
+        },
+        {
+            "name": "search_contexts",
+            "description": "Find the stored contexts most relevant to a query, optionally only those with every one of the tags",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "tags": tags,
+                    "limit": { "type": "integer", "minimum": 1 },
+                },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "get_context",
+            "description": "Read a stored context by its id",
+            "inputSchema": id.clone(),
+        },
+        {
+            "name": "delete_context",
+            "description": "Delete a stored context by its id; it can be restored until it is purged",
+            "inputSchema": id,
+        },
+    ])
+}
+
+/// A prompt as `prompts/list` describes it, with its arguments but not its messages
+fn prompt_json(prompt: &PromptTemplate) -> Value {
+    let arguments: Vec<Value> = prompt
+        .arguments
+        .iter()
+        .map(|argument| {
+            json!({
+                "name": argument.name,
+                "description": argument.description,
+                "required": argument.required,
+            })
+        })
+        .collect();
+    json!({
+        "name": prompt.name,
+        "description": prompt.description,
+        "arguments": arguments,
+    })
+}
+
+fn context_uri(id: Uuid) -> String {
+    format!("{}{}", CONTEXT_URI_SCHEME, id)
+}
+
+fn mime_type(context: &Context) -> &str {
+    context
+        .metadata
+        .content_type
+        .as_deref()
+        .unwrap_or("text/plain")
+}
+
+/// A context as tools return it
+fn context_json(context: &Context) -> Value {
+    json!({
+        "id": context.id,
+        "uri": context_uri(context.id),
+        "content": context.content,
+        "source": context.metadata.source,
+        "content_type": context.metadata.content_type,
+        "tags": context.metadata.tags,
+        "created_at": context.created_at.to_rfc3339(),
+        "expires_at": context.expires_at.map(|at| at.to_rfc3339()),
+        "version": context.version,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::input::mcp::protocol::{
+        INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR, RESOURCE_NOT_FOUND,
+    };
+    use crate::adapter::output::{InMemoryContextRepository, SimpleEmbeddingService};
+    use crate::application::{ContextManagementService, ContextSearchService};
+    use crate::config::McpConfig;
+    use tokio::io::{DuplexStream, Lines, ReadHalf, WriteHalf};
+    use tokio::task::JoinHandle;
+
+    fn server() -> McpServer {
+        let repository = Arc::new(InMemoryContextRepository::new());
+        let embedding = Arc::new(SimpleEmbeddingService::new(128));
+        McpServer::new(
+            Arc::new(
+                ContextManagementService::new(
+                    repository.clone(),
+                    embedding.clone(),
+                    embedding.clone(),
+                    1000,
+                    200,
+                )
+                .unwrap(),
+            ),
+            Arc::new(ContextSearchService::new(
+                repository,
+                embedding.clone(),
+                embedding,
+                10,
+            )),
+        )
+    }
+
+    /// A client talking to a server over an in-memory stream
+    struct Session {
+        writer: WriteHalf<DuplexStream>,
+        lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+        server: JoinHandle<io::Result<()>>,
+    }
+
+    impl Session {
+        fn start() -> Self {
+            Self::start_with(server())
+        }
+
+        fn start_with(mcp: McpServer) -> Self {
+            let (client, transport) = tokio::io::duplex(64 * 1024);
+            let server = tokio::spawn(async move {
+                let (reader, writer) = tokio::io::split(transport);
+                mcp.serve(reader, writer).await
+            });
+            let (reader, writer) = tokio::io::split(client);
+            Self {
+                writer,
+                lines: BufReader::new(reader).lines(),
+                server,
+            }
+        }
+
+        async fn send(&mut self, message: Value) {
+            let mut line = message.to_string();
+            line.push('\n');
+            self.writer.write_all(line.as_bytes()).await.unwrap();
+        }
+
+        async fn request(&mut self, id: u64, method: &str, params: Value) -> Value {
+            self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+                .await;
+            let line = self.lines.next_line().await.unwrap().unwrap();
+            let response: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(response["id"], id);
+            response
+        }
+
+        async fn initialize(&mut self) -> Value {
+            let response = self
+                .request(
+                    0,
+                    "initialize",
+                    json!({
+                        "protocolVersion": PROTOCOL_VERSION,
+                        "capabilities": {},
+                        "clientInfo": { "name": "test", "version": "1.0" },
+                    }),
+                )
+                .await;
+            self.send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+                .await;
+            response
+        }
+
+        /// The text result of calling tool `name`, parsed as JSON
+        async fn call_tool(&mut self, id: u64, name: &str, arguments: Value) -> Value {
+            let response = self
+                .request(
+                    id,
+                    "tools/call",
+                    json!({ "name": name, "arguments": arguments }),
+                )
+                .await;
+            assert_eq!(response["result"]["isError"], false, "{}", response);
+            let text = response["result"]["content"][0]["text"].as_str().unwrap();
+            serde_json::from_str(text).unwrap()
+        }
+
+        /// Close the client's side, ending the session
+        async fn shutdown(mut self) {
+            self.writer.shutdown().await.unwrap();
+            drop(self.writer);
+            self.server.await.unwrap().unwrap();
+            assert!(self.lines.next_line().await.unwrap().is_none());
+        }
+    }
+
+    fn error_code(response: &Option<Response>) -> i64 {
+        response.as_ref().unwrap().error.as_ref().unwrap().code
+    }
+
+    /// The response to an `initialize` asking for `protocol_version`
+    async fn initialize(
+        server: &McpServer,
+        session: &McpSession,
+        protocol_version: &str,
+    ) -> Response {
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": protocol_version,
+                "capabilities": { "roots": { "listChanged": true } },
+                "clientInfo": { "name": "test", "version": "1.0" },
+            },
+        });
+        server
+            .handle_message(session, &message.to_string())
+            .await
+            .unwrap()
+    }
+
+    async fn send_initialized(server: &McpServer, session: &McpSession) {
+        let notification = r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#;
+        assert!(server.handle_message(session, notification).await.is_none());
+    }
+
+    /// A session that has completed the handshake with `server`
+    async fn initialized_session(server: &McpServer) -> McpSession {
+        let session = McpSession::default();
+        initialize(server, &session, PROTOCOL_VERSION).await;
+        send_initialized(server, &session).await;
+        session
+    }
+
+    #[tokio::test]
+    async fn test_a_session_initializes_calls_tools_and_reads_resources() {
+        let mut session = Session::start();
+
+        let initialized = session
+            .request(
+                1,
+                "initialize",
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": {},
+                    "clientInfo": { "name": "test", "version": "1.0" },
+                }),
+            )
+            .await;
+        assert_eq!(initialized["jsonrpc"], "2.0");
+        assert_eq!(initialized["result"]["protocolVersion"], PROTOCOL_VERSION);
+        assert!(initialized["result"]["capabilities"]["tools"].is_object());
+        session
+            .send(json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }))
+            .await;
+
+        let ping = session.request(2, "ping", json!({})).await;
+        assert_eq!(ping["result"], json!({}));
+
+        let tools = session.request(3, "tools/list", json!({})).await;
+        let names: Vec<&str> = tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "store_context",
+                "search_contexts",
+                "get_context",
+                "delete_context"
+            ]
+        );
+
+        let stored = session
+            .call_tool(
+                4,
+                "store_context",
+                json!({ "content": "Promote the replica when the primary fails", "tags": ["Runbook"] }),
+            )
+            .await;
+        assert_eq!(stored["tags"], json!(["runbook"]));
+        let id = stored["id"].as_str().unwrap().to_string();
+
+        let found = session
+            .call_tool(5, "search_contexts", json!({ "query": "replica primary" }))
+            .await;
+        assert_eq!(found["matches"][0]["id"], id.as_str());
+        assert_eq!(found["total_matches"], 1);
+
+        let resources = session.request(6, "resources/list", json!({})).await;
+        let uri = format!("context://{}", id);
+        assert_eq!(resources["result"]["resources"][0]["uri"], uri.as_str());
+        assert!(resources["result"].get("nextCursor").is_none());
+
+        let read = session
+            .request(7, "resources/read", json!({ "uri": uri }))
+            .await;
+        assert_eq!(
+            read["result"]["contents"][0]["text"],
+            "Promote the replica when the primary fails"
+        );
+        assert_eq!(read["result"]["contents"][0]["mimeType"], "text/plain");
+
+        let deleted = session
+            .call_tool(8, "delete_context", json!({ "id": id }))
+            .await;
+        assert_eq!(deleted["deleted"], id.as_str());
+
+        session.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn test_protocol_errors_are_json_rpc_errors() {
+        let server = server();
+        let session = McpSession::default();
+
+        let response = server.handle_message(&session, "{\"jsonrpc\": ").await;
+        assert_eq!(error_code(&response), PARSE_ERROR);
+        assert_eq!(response.unwrap().id, Value::Null);
+
+        let response = server
+            .handle_message(&session, r#"[{"jsonrpc": "2.0", "id": 1}]"#)
+            .await;
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+        let response = server
+            .handle_message(&session, r#"{"jsonrpc": "1.0", "id": 1, "method": "ping"}"#)
+            .await;
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+
+        // Nothing but ping is answered before initialize
+        let response = server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/list"}"#,
+            )
+            .await;
+        assert_eq!(error_code(&response), INVALID_REQUEST);
+        let response = server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "id": 3, "method": "initialize"}"#,
+            )
+            .await;
+        assert_eq!(error_code(&response), INVALID_PARAMS);
+        assert!(session.protocol_version().is_none());
+
+        let session = initialized_session(&server).await;
+        let response = server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "id": 5, "method": "sampling/createMessage"}"#,
+            )
+            .await;
+        assert_eq!(error_code(&response), METHOD_NOT_FOUND);
+        let response = server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "id": 6, "method": "tools/call", "params": {"name": "rm"}}"#,
+            )
+            .await;
+        assert_eq!(error_code(&response), INVALID_PARAMS);
+        let response = server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "id": 7, "method": "tools/call", "params": {"name": "get_context", "arguments": {"id": "not-a-uuid"}}}"#,
+            )
+            .await;
+        assert_eq!(error_code(&response), INVALID_PARAMS);
+
+        let missing = format!("context://{}", Uuid::new_v4());
+        let response = server
+            .handle_message(&session, &format!(
+                r#"{{"jsonrpc": "2.0", "id": 8, "method": "resources/read", "params": {{"uri": "{}"}}}}"#,
+                missing
+            ))
+            .await;
+        assert_eq!(error_code(&response), RESOURCE_NOT_FOUND);
+        assert_eq!(
+            response.unwrap().error.unwrap().data.unwrap()["uri"],
+            missing.as_str()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requests_before_the_handshake_completes_are_refused() {
+        let server = server();
+        let session = McpSession::default();
+        let list = r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/list"}"#;
+
+        // The notification doesn't count before initialize
+        send_initialized(&server, &session).await;
+        let response = server.handle_message(&session, list).await.unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, INVALID_REQUEST);
+        assert_eq!(error.message, "tools/list was sent before initialize");
+
+        let response = initialize(&server, &session, PROTOCOL_VERSION).await;
+        assert!(response.result.is_some());
+        assert!(!session.is_ready());
+        assert!(session.client_has_roots());
+        let response = server.handle_message(&session, list).await.unwrap();
+        let error = response.error.unwrap();
+        assert_eq!(error.code, INVALID_REQUEST);
+        assert_eq!(
+            error.message,
+            "tools/list was sent before notifications/initialized"
+        );
+        let ping = r#"{"jsonrpc": "2.0", "id": 3, "method": "ping"}"#;
+        assert!(server
+            .handle_message(&session, ping)
+            .await
+            .unwrap()
+            .result
+            .is_some());
+
+        send_initialized(&server, &session).await;
+        assert!(session.is_ready());
+        let response = server.handle_message(&session, list).await.unwrap();
+        assert!(response.result.is_some());
+
+        // A session is only initialized once
+        let response = initialize(&server, &session, PROTOCOL_VERSION).await;
+        assert_eq!(response.error.unwrap().code, INVALID_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_protocol_versions_are_negotiated() {
+        let server = server()
+            .with_protocol_versions(vec!["2025-03-26".to_string(), PROTOCOL_VERSION.to_string()]);
+
+        // A revision the server accepts is agreed on
+        let session = McpSession::default();
+        let result = initialize(&server, &session, PROTOCOL_VERSION)
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(
+            session.protocol_version().as_deref(),
+            Some(PROTOCOL_VERSION)
+        );
+        assert_eq!(result["serverInfo"]["name"], env!("CARGO_PKG_NAME"));
+        assert_eq!(result["serverInfo"]["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(result["capabilities"]["resources"]["subscribe"], false);
+        assert!(result["capabilities"]["tools"].is_object());
+        assert!(result["capabilities"].get("prompts").is_none());
+
+        // Any other gets the preferred one, which the client may still speak
+        let session = McpSession::default();
+        let result = initialize(&server, &session, "2099-01-01")
+            .await
+            .result
+            .unwrap();
+        assert_eq!(result["protocolVersion"], "2025-03-26");
+        assert_eq!(session.protocol_version().as_deref(), Some("2025-03-26"));
+
+        let session = McpSession::default();
+        let error = initialize(&server, &session, " ").await.error.unwrap();
+        assert_eq!(error.code, INVALID_PARAMS);
+        assert_eq!(
+            error.data.unwrap()["supported"],
+            json!(["2025-03-26", PROTOCOL_VERSION])
+        );
+        assert!(session.protocol_version().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribed_sessions_are_notified_of_their_contexts() {
+        let server = server().with_events(Arc::new(BroadcastEventPublisher::new(8)));
+        let session = initialized_session(&server).await;
+        let (watched, other) = (Uuid::new_v4(), Uuid::new_v4());
+        session.subscriptions.lock().unwrap().insert(watched);
+        let methods = |event| {
+            server
+                .notifications(&session, event)
+                .into_iter()
+                .map(|notification| notification.method)
+                .collect::<Vec<_>>()
+        };
+        let event = |kind, id| Ok(ContextEvent::now(kind, id, Vec::new()));
+
+        assert_eq!(
+            methods(event(ContextEventKind::Updated, watched)),
+            [RESOURCE_UPDATED_NOTIFICATION]
+        );
+        assert!(methods(event(ContextEventKind::Updated, other)).is_empty());
+        assert_eq!(
+            methods(event(ContextEventKind::Created, other)),
+            [RESOURCE_LIST_CHANGED_NOTIFICATION]
+        );
+
+        // Missed events may have changed anything
+        assert_eq!(
+            methods(Err(RecvError::Lagged(3))),
+            [
+                RESOURCE_UPDATED_NOTIFICATION,
+                RESOURCE_LIST_CHANGED_NOTIFICATION
+            ]
+        );
+
+        // Deleting a context ends its subscription
+        assert_eq!(
+            methods(event(ContextEventKind::Deleted, watched)),
+            [
+                RESOURCE_UPDATED_NOTIFICATION,
+                RESOURCE_LIST_CHANGED_NOTIFICATION
+            ]
+        );
+        assert!(!session.is_subscribed(watched));
+
+        // Sessions still in the handshake get nothing
+        let session = McpSession::default();
+        assert!(server
+            .notifications(&session, event(ContextEventKind::Created, other))
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tool_failures_are_reported_in_the_result() {
+        let server = server();
+        let session = initialized_session(&server).await;
+
+        let response = server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {"name": "store_context", "arguments": {"content": "   "}}}"#,
+            )
+            .await
+            .unwrap();
+        let result = response.result.unwrap();
+        assert_eq!(result["isError"], true);
+        assert!(result["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .contains("content"));
+
+        // Notifications get no response, even for unknown methods
+        assert!(server
+            .handle_message(
+                &session,
+                r#"{"jsonrpc": "2.0", "method": "notifications/cancelled"}"#
+            )
+            .await
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_prompts_are_filled_in_with_the_contexts_found() {
+        let path = std::env::temp_dir().join(format!("mcp-prompts-{}.toml", Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            r#"
+            [[prompts]]
+            name = "answer_with_context"
+            description = "Answer a question from the stored contexts"
+            search = "{{query}}"
+            top_k = 2
+
+            [[prompts.arguments]]
+            name = "query"
+            description = "The question to answer"
+            required = true
+
+            [[prompts.messages]]
+            role = "user"
+            text = "Use these notes:\n\n{{contexts}}\n\nQuestion: {{query}}"
+            "#,
+        )
+        .unwrap();
+        let prompts = McpConfig {
+            prompts_path: Some(path.to_string_lossy().into_owned()),
+        }
+        .prompts();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut session = Session::start_with(server().with_prompts(prompts.unwrap()));
+        let initialized = session.initialize().await;
+        assert!(initialized["result"]["capabilities"]["prompts"].is_object());
+        session
+            .call_tool(
+                1,
+                "store_context",
+                json!({ "content": "Promote the replica when the primary fails" }),
+            )
+            .await;
+
+        let listed = session.request(2, "prompts/list", json!({})).await;
+        let prompt = &listed["result"]["prompts"][0];
+        assert_eq!(prompt["name"], "answer_with_context");
+        assert_eq!(prompt["arguments"][0]["name"], "query");
+        assert_eq!(prompt["arguments"][0]["required"], true);
+        assert!(prompt.get("messages").is_none());
+
+        let got = session
+            .request(
+                3,
+                "prompts/get",
+                json!({
+                    "name": "answer_with_context",
+                    "arguments": { "query": "replica failover" },
+                }),
+            )
+            .await;
+        let message = &got["result"]["messages"][0];
+        assert_eq!(message["role"], "user");
+        assert_eq!(
+            message["content"]["text"],
+            "Use these notes:\n\nPromote the replica when the primary fails\n\nQuestion: replica failover"
+        );
+
+        let missing = session
+            .request(
+                4,
+                "prompts/get",
+                json!({ "name": "answer_with_context", "arguments": {} }),
+            )
+            .await;
+        assert_eq!(missing["error"]["code"], INVALID_PARAMS);
+        assert!(missing["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("query"));
+        let unknown = session
+            .request(5, "prompts/get", json!({ "name": "summarize" }))
+            .await;
+        assert_eq!(unknown["error"]["code"], INVALID_PARAMS);
+
+        session.shutdown().await;
+    }
+}
@@ -0,0 +1,202 @@
+use futures::Stream;
+use std::collections::HashMap;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::protocol::ServerMessage;
+use super::server::{McpServer, McpSession};
+use crate::domain::ContextEvent;
+
+/// Responses held for a session whose client is slow to read its stream
+const SESSION_BUFFER: usize = 32;
+
+/// Sessions of MCP clients connected over HTTP, each answered on its own event stream
+///
+/// A client connects to get a session id and the stream its responses arrive on, then
+/// posts its messages with the id. Messages go through the same [`McpServer`] as over stdio.
+/// Dropping the stream, as happens when the client disconnects, ends the session and its
+/// resource subscriptions.
+pub struct SseTransport {
+    server: Arc<McpServer>,
+    sessions: Mutex<HashMap<Uuid, OpenSession>>,
+}
+
+#[derive(Clone)]
+struct OpenSession {
+    session: Arc<McpSession>,
+    responses: mpsc::Sender<ServerMessage>,
+}
+
+impl SseTransport {
+    /// Create a transport answering messages with `server`
+    pub fn new(server: Arc<McpServer>) -> Self {
+        Self {
+            server,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Open a session, returning its id and the stream of its responses and notifications
+    pub fn connect(self: &Arc<Self>) -> (Uuid, SessionStream) {
+        let id = Uuid::new_v4();
+        let (responses, receiver) = mpsc::channel(SESSION_BUFFER);
+        let open = OpenSession {
+            session: Arc::new(McpSession::default()),
+            responses,
+        };
+        if let Some(events) = self.server.subscribe_events() {
+            tokio::spawn(notify(self.server.clone(), open.clone(), events));
+        }
+        self.sessions.lock().unwrap().insert(id, open);
+        let stream = SessionStream {
+            id,
+            receiver,
+            transport: self.clone(),
+        };
+        (id, stream)
+    }
+
+    /// Handle a message of session `id`, sending its response, if any, on the session's
+    /// stream
+    ///
+    /// Returns `false` if there's no such session.
+    pub async fn deliver(&self, id: Uuid, message: &str) -> bool {
+        let Some(open) = self.sessions.lock().unwrap().get(&id).cloned() else {
+            return false;
+        };
+        if let Some(response) = self.server.handle_message(&open.session, message).await {
+            // A stream closed in the meantime has ended the session, so the response is dropped
+            let _ = open.responses.send(response.into()).await;
+        }
+        true
+    }
+
+    /// Number of sessions open
+    pub fn session_count(&self) -> usize {
+        self.sessions.lock().unwrap().len()
+    }
+}
+
+/// Send `open` the notifications of the context changes in `events` until its stream closes
+async fn notify(
+    server: Arc<McpServer>,
+    open: OpenSession,
+    mut events: broadcast::Receiver<ContextEvent>,
+) {
+    loop {
+        let event = tokio::select! {
+            _ = open.responses.closed() => return,
+            event = events.recv() => event,
+        };
+        let closed = matches!(event, Err(RecvError::Closed));
+        for notification in server.notifications(&open.session, event) {
+            if open.responses.send(notification.into()).await.is_err() {
+                return;
+            }
+        }
+        if closed {
+            return;
+        }
+    }
+}
+
+/// Responses and notifications of one session, which ends when this is dropped
+pub struct SessionStream {
+    id: Uuid,
+    receiver: mpsc::Receiver<ServerMessage>,
+    transport: Arc<SseTransport>,
+}
+
+impl Stream for SessionStream {
+    type Item = ServerMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<ServerMessage>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for SessionStream {
+    fn drop(&mut self) {
+        self.transport.sessions.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::input::mcp::protocol::Response;
+    use crate::adapter::output::{InMemoryContextRepository, SimpleEmbeddingService};
+    use crate::application::{ContextManagementService, ContextSearchService};
+    use futures::StreamExt;
+
+    fn transport() -> Arc<SseTransport> {
+        let repository = Arc::new(InMemoryContextRepository::new());
+        let embedding = Arc::new(SimpleEmbeddingService::new(128));
+        let server = McpServer::new(
+            Arc::new(
+                ContextManagementService::new(
+                    repository.clone(),
+                    embedding.clone(),
+                    embedding.clone(),
+                    1000,
+                    200,
+                )
+                .unwrap(),
+            ),
+            Arc::new(ContextSearchService::new(
+                repository,
+                embedding.clone(),
+                embedding,
+                10,
+            )),
+        );
+        Arc::new(SseTransport::new(Arc::new(server)))
+    }
+
+    fn response(message: Option<ServerMessage>) -> Response {
+        match message {
+            Some(ServerMessage::Response(response)) => response,
+            other => panic!("expected a response, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_answered_separately_until_their_stream_is_dropped() {
+        let transport = transport();
+        let (first, mut first_stream) = transport.connect();
+        let (second, mut second_stream) = transport.connect();
+        assert_eq!(transport.session_count(), 2);
+
+        // Initializing one session leaves the other uninitialized
+        assert!(
+            transport
+                .deliver(
+                    first,
+                    r#"{"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"protocolVersion": "2024-11-05"}}"#,
+                )
+                .await
+        );
+        assert!(response(first_stream.next().await).result.is_some());
+        assert!(
+            transport
+                .deliver(
+                    first,
+                    r#"{"jsonrpc": "2.0", "method": "notifications/initialized"}"#
+                )
+                .await
+        );
+        let list = r#"{"jsonrpc": "2.0", "id": 2, "method": "tools/list"}"#;
+        assert!(transport.deliver(first, list).await);
+        assert!(response(first_stream.next().await).result.is_some());
+        assert!(transport.deliver(second, list).await);
+        assert!(response(second_stream.next().await).error.is_some());
+
+        drop(first_stream);
+        assert_eq!(transport.session_count(), 1);
+        assert!(!transport.deliver(first, list).await);
+    }
+}
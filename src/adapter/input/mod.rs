@@ -1,4 +1,12 @@
 pub mod api;
+pub mod mcp;
 
-pub use api::create_router;
+#[cfg(unix)]
+pub use api::reload_on_sighup;
 pub use api::AppState;
+pub use api::{api_routes, create_router, RouterBuilder, API_V1};
+pub use api::{
+    tls_from_config, ApiKeyAuth, Authenticator, JwtAuth, RateLimiter, RouteRateLimits,
+    ShareLinkService,
+};
+pub use mcp::{McpServer, SseTransport};
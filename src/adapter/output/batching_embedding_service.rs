@@ -0,0 +1,153 @@
+use async_trait::async_trait;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use std::sync::Arc;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service that splits large requests into batches embedded concurrently
+pub struct BatchingEmbeddingService {
+    inner: Arc<dyn EmbeddingPort + Send + Sync>,
+    batch_size: usize,
+    max_concurrency: usize,
+}
+
+impl BatchingEmbeddingService {
+    /// Embed `batch_size` texts per inner call, with at most `max_concurrency` calls in flight
+    pub fn new(
+        inner: Arc<dyn EmbeddingPort + Send + Sync>,
+        batch_size: usize,
+        max_concurrency: usize,
+    ) -> Self {
+        Self {
+            inner,
+            batch_size: batch_size.max(1),
+            max_concurrency: max_concurrency.max(1),
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for BatchingEmbeddingService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        // A single batch goes straight through, keeping the inner service's error
+        if texts.len() <= self.batch_size {
+            return self.inner.embed_texts(texts).await;
+        }
+
+        let batches: Vec<&[String]> = texts.chunks(self.batch_size).collect();
+        let total = batches.len();
+
+        let mut embedded: Vec<(usize, Vec<Vec<f32>>)> =
+            stream::iter(batches.into_iter().enumerate())
+                .map(|(number, batch)| async move {
+                    self.inner
+                        .embed_texts(batch)
+                        .await
+                        .map(|embeddings| (number, embeddings))
+                        .map_err(|err| {
+                            McpError::EmbeddingError(format!(
+                                "Embedding batch {} of {} failed: {}",
+                                number + 1,
+                                total,
+                                err
+                            ))
+                        })
+                })
+                .buffer_unordered(self.max_concurrency)
+                .try_collect()
+                .await?;
+
+        // Batches finish in any order
+        embedded.sort_by_key(|(number, _)| *number);
+        Ok(embedded
+            .into_iter()
+            .flat_map(|(_, embeddings)| embeddings)
+            .collect())
+    }
+
+    async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>> {
+        self.inner.embed_query(query).await
+    }
+
+    async fn check_health(&self) -> McpResult<()> {
+        self.inner.check_health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    /// Embeds texts as the number they end with, recording how many calls overlap
+    #[derive(Default)]
+    struct TrackingEmbeddingService {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl EmbeddingPort for TrackingEmbeddingService {
+        async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+
+            let numbers: Vec<f32> = texts.iter().map(|text| number(text)).collect();
+
+            // Later batches finish first
+            let delay = 50u64.saturating_sub(numbers[0] as u64);
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+            if texts.iter().any(|text| text == "fail") {
+                return Err(McpError::ExternalServiceError("provider down".to_string()));
+            }
+            Ok(numbers.into_iter().map(|number| vec![number]).collect())
+        }
+    }
+
+    fn number(text: &str) -> f32 {
+        text.rsplit(' ').next().unwrap().parse().unwrap_or(0.0)
+    }
+
+    fn texts(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|position| format!("chunk {}", position))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_order_is_preserved_and_concurrency_bounded() {
+        let inner = Arc::new(TrackingEmbeddingService::default());
+        let service = BatchingEmbeddingService::new(inner.clone(), 4, 3);
+
+        let embedded = service.embed_texts(&texts(40)).await.unwrap();
+
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 10);
+        assert!(inner.max_in_flight.load(Ordering::SeqCst) <= 3);
+        assert!(inner.max_in_flight.load(Ordering::SeqCst) > 1);
+
+        let expected: Vec<Vec<f32>> = (0..40).map(|number| vec![number as f32]).collect();
+        assert_eq!(embedded, expected);
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_fails_the_call() {
+        let inner = Arc::new(TrackingEmbeddingService::default());
+        let service = BatchingEmbeddingService::new(inner, 4, 2);
+
+        let mut input = texts(12);
+        input[5] = "fail".to_string();
+
+        let err = service.embed_texts(&input).await.unwrap_err();
+        assert!(
+            matches!(&err, McpError::EmbeddingError(message) if message.contains("batch 2 of 3")),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use tokio::sync::broadcast;
+
+use crate::domain::ContextEvent;
+use crate::ports::out_ports::EventPublisherPort;
+
+/// Publishes context events to every subscriber over a broadcast channel
+///
+/// A subscriber that falls more than `capacity` events behind misses the oldest ones and is
+/// told how many it missed.
+pub struct BroadcastEventPublisher {
+    sender: broadcast::Sender<ContextEvent>,
+}
+
+impl BroadcastEventPublisher {
+    /// A publisher buffering up to `capacity` events per subscriber, which must be at least 1
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Receive the events published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<ContextEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl EventPublisherPort for BroadcastEventPublisher {
+    fn publish(&self, event: ContextEvent) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ContextEventKind;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_subscribers_get_events_published_after_they_subscribe() {
+        let publisher = BroadcastEventPublisher::new(8);
+        let id = Uuid::new_v4();
+
+        // Nobody is listening yet, so this one is dropped
+        publisher.publish(ContextEvent::now(ContextEventKind::Created, id, Vec::new()));
+
+        let mut receiver = publisher.subscribe();
+        publisher.publish(ContextEvent::now(ContextEventKind::Updated, id, Vec::new()));
+        publisher.publish(ContextEvent::now(ContextEventKind::Deleted, id, Vec::new()));
+
+        assert_eq!(
+            receiver.recv().await.unwrap().kind,
+            ContextEventKind::Updated
+        );
+        assert_eq!(
+            receiver.recv().await.unwrap().kind,
+            ContextEventKind::Deleted
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+}
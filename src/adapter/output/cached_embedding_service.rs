@@ -0,0 +1,263 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service that remembers the embedding of every text it has seen
+///
+/// Queries are embedded by the inner service every time; they rarely repeat.
+pub struct CachedEmbeddingService {
+    inner: Arc<dyn EmbeddingPort + Send + Sync>,
+    cache: Mutex<LruCache>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// Point-in-time copy of the cache counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EmbeddingCacheStats {
+    /// Texts embedded without calling the inner service
+    pub hits: u64,
+
+    /// Texts sent to the inner service
+    pub misses: u64,
+
+    /// Embeddings currently cached
+    pub entries: usize,
+}
+
+/// Embeddings by content hash, evicting the least recently used once full
+struct LruCache {
+    capacity: usize,
+    entries: HashMap<String, (Vec<f32>, u64)>,
+    recency: BTreeMap<u64, String>,
+    clock: u64,
+}
+
+impl LruCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Vec<f32>> {
+        let (embedding, last_used) = self.entries.get_mut(key)?;
+        self.recency.remove(last_used);
+        self.clock += 1;
+        *last_used = self.clock;
+        self.recency.insert(self.clock, key.to_string());
+        Some(embedding.clone())
+    }
+
+    fn insert(&mut self, key: String, embedding: Vec<f32>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some((_, last_used)) = self.entries.remove(&key) {
+            self.recency.remove(&last_used);
+        }
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        self.clock += 1;
+        self.recency.insert(self.clock, key.clone());
+        self.entries.insert(key, (embedding, self.clock));
+    }
+}
+
+/// Cache key of a text
+fn content_key(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
+impl CachedEmbeddingService {
+    /// Cache up to `max_entries` embeddings of `inner`
+    pub fn new(inner: Arc<dyn EmbeddingPort + Send + Sync>, max_entries: usize) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(LruCache::new(max_entries)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> EmbeddingCacheStats {
+        EmbeddingCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: self.cache.lock().unwrap().entries.len(),
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for CachedEmbeddingService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let keys: Vec<String> = texts.iter().map(|text| content_key(text)).collect();
+
+        let mut embeddings: HashMap<&str, Vec<f32>> = HashMap::new();
+        {
+            let mut cache = self.cache.lock().unwrap();
+            for key in &keys {
+                if let Some(embedding) = cache.get(key) {
+                    embeddings.insert(key, embedding);
+                }
+            }
+        }
+
+        // Text repeated within the batch is only embedded once
+        let mut pending = HashSet::new();
+        let (missed_keys, missed_texts): (Vec<&str>, Vec<String>) = keys
+            .iter()
+            .zip(texts)
+            .filter(|(key, _)| !embeddings.contains_key(key.as_str()) && pending.insert(*key))
+            .map(|(key, text)| (key.as_str(), text.clone()))
+            .unzip();
+
+        self.misses
+            .fetch_add(missed_texts.len() as u64, Ordering::Relaxed);
+        self.hits
+            .fetch_add((texts.len() - missed_texts.len()) as u64, Ordering::Relaxed);
+
+        if !missed_texts.is_empty() {
+            let embedded = self.inner.embed_texts(&missed_texts).await?;
+            if embedded.len() != missed_texts.len() {
+                return Err(McpError::EmbeddingError(format!(
+                    "Expected {} embeddings, received {}",
+                    missed_texts.len(),
+                    embedded.len()
+                )));
+            }
+
+            let mut cache = self.cache.lock().unwrap();
+            for (key, embedding) in missed_keys.into_iter().zip(embedded) {
+                cache.insert(key.to_string(), embedding.clone());
+                embeddings.insert(key, embedding);
+            }
+        }
+
+        Ok(keys
+            .iter()
+            .map(|key| embeddings[key.as_str()].clone())
+            .collect())
+    }
+
+    async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>> {
+        self.inner.embed_query(query).await
+    }
+
+    async fn check_health(&self) -> McpResult<()> {
+        self.inner.check_health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockall::mock;
+
+    mock! {
+        EmbeddingService {}
+        #[async_trait]
+        impl EmbeddingPort for EmbeddingService {
+            async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>>;
+            async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>>;
+        }
+    }
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(|text| text.to_string()).collect()
+    }
+
+    /// Embeds each text as its length
+    fn embed_lengths(texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| vec![text.len() as f32]).collect())
+    }
+
+    fn cached(inner: MockEmbeddingService, max_entries: usize) -> CachedEmbeddingService {
+        CachedEmbeddingService::new(Arc::new(inner), max_entries)
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_content_is_embedded_once() {
+        let mut inner = MockEmbeddingService::new();
+        inner
+            .expect_embed_texts()
+            .withf(|texts| texts.len() == 1 && texts[0] == "same text")
+            .times(1)
+            .returning(embed_lengths);
+
+        let service = cached(inner, 10);
+
+        // Within one batch and across calls
+        let first = service
+            .embed_texts(&texts(&["same text", "same text"]))
+            .await
+            .unwrap();
+        let second = service.embed_texts(&texts(&["same text"])).await.unwrap();
+
+        assert_eq!(first[1], [9.0]);
+        assert_eq!(second[0], [9.0]);
+        assert_eq!(
+            service.stats(),
+            EmbeddingCacheStats {
+                hits: 2,
+                misses: 1,
+                entries: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_least_recently_used_entry_is_evicted() {
+        let mut inner = MockEmbeddingService::new();
+        for (content, times) in [("a", 1), ("bb", 2), ("ccc", 1)] {
+            inner
+                .expect_embed_texts()
+                .withf(move |texts| texts[0] == content)
+                .times(times)
+                .returning(embed_lengths);
+        }
+
+        let service = cached(inner, 2);
+        for content in ["a", "bb", "a", "ccc", "a", "bb"] {
+            service.embed_texts(&texts(&[content])).await.unwrap();
+        }
+
+        // "bb" was evicted when "ccc" came in, since "a" had just been used
+        let stats = service.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 4, 2));
+    }
+
+    #[tokio::test]
+    async fn test_queries_are_not_cached() {
+        let mut inner = MockEmbeddingService::new();
+        inner
+            .expect_embed_query()
+            .times(2)
+            .returning(|query| Ok(vec![query.len() as f32]));
+
+        let service = cached(inner, 10);
+        service.embed_query("text").await.unwrap();
+        assert_eq!(service.embed_query("text").await.unwrap(), [4.0]);
+        assert_eq!(service.stats().entries, 0);
+    }
+}
@@ -0,0 +1,301 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Largest number of texts the embed endpoint accepts in one request
+pub const COHERE_MAX_BATCH_SIZE: usize = 96;
+
+/// Embedding service backed by the Cohere embed API
+///
+/// Texts are embedded as `search_document` and queries as `search_query`, as the v3 models
+/// expect.
+pub struct CohereEmbeddingService {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+    dimension: usize,
+    batch_size: usize,
+}
+
+/// What the embedded text is used for
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum InputType {
+    SearchDocument,
+    SearchQuery,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    texts: &'a [String],
+    input_type: InputType,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl CohereEmbeddingService {
+    /// Create a service calling `{api_base}/embed` that expects `dimension`-long vectors
+    pub fn new(
+        api_key: impl Into<String>,
+        model: impl Into<String>,
+        api_base: &str,
+        dimension: usize,
+    ) -> McpResult<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| McpError::ExternalServiceError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            endpoint: format!("{}/embed", api_base.trim_end_matches('/')),
+            api_key: api_key.into(),
+            model: model.into(),
+            dimension,
+            batch_size: COHERE_MAX_BATCH_SIZE,
+        })
+    }
+
+    /// Send at most `batch_size` texts per request, capped at the provider's limit
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.clamp(1, COHERE_MAX_BATCH_SIZE);
+        self
+    }
+
+    /// Embed texts, splitting them into as many requests as the batch size requires
+    async fn embed_batches(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> McpResult<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(self.batch_size) {
+            embeddings.extend(self.request_embeddings(batch, input_type).await?);
+        }
+        Ok(embeddings)
+    }
+
+    async fn request_embeddings(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> McpResult<Vec<Vec<f32>>> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&EmbedRequest {
+                model: &self.model,
+                texts,
+                input_type,
+            })
+            .send()
+            .await
+            .map_err(|e| {
+                McpError::ExternalServiceError(format!("Embedding request failed: {}", e))
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(classify_error(status, &body));
+        }
+
+        let response: EmbedResponse = response
+            .json()
+            .await
+            .map_err(|e| McpError::EmbeddingError(format!("Invalid embedding response: {}", e)))?;
+
+        if response.embeddings.len() != texts.len() {
+            return Err(McpError::EmbeddingError(format!(
+                "Expected {} embeddings, received {}",
+                texts.len(),
+                response.embeddings.len()
+            )));
+        }
+        if let Some(embedding) = response
+            .embeddings
+            .iter()
+            .find(|embedding| embedding.len() != self.dimension)
+        {
+            return Err(McpError::EmbeddingError(format!(
+                "Model {} returned {}-dimensional embeddings, expected {}",
+                self.model,
+                embedding.len(),
+                self.dimension
+            )));
+        }
+
+        Ok(response.embeddings)
+    }
+}
+
+/// Map an error response to an error; rate limits become `RateLimitExceeded`
+fn classify_error(status: StatusCode, body: &str) -> McpError {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return McpError::RateLimitExceeded;
+    }
+
+    let message = serde_json::from_str::<ErrorResponse>(body)
+        .map(|response| response.message)
+        .unwrap_or_else(|_| body.to_string());
+    let message = format!("Embedding API returned {}: {}", status, message);
+
+    if status.is_server_error() {
+        McpError::ExternalServiceError(message)
+    } else {
+        McpError::EmbeddingError(message)
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for CohereEmbeddingService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        self.embed_batches(texts, InputType::SearchDocument).await
+    }
+
+    async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>> {
+        self.embed_batches(&[query.to_string()], InputType::SearchQuery)
+            .await?
+            .pop()
+            .ok_or_else(|| McpError::EmbeddingError("No embedding returned for the query".into()))
+    }
+
+    /// Embed a one-word query, which exercises the endpoint, credentials and model
+    async fn check_health(&self) -> McpResult<()> {
+        self.embed_query("ping").await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn service(server: &MockServer) -> CohereEmbeddingService {
+        CohereEmbeddingService::new("test-key", "embed-english-v3.0", &server.uri(), 2).unwrap()
+    }
+
+    fn texts(count: usize) -> Vec<String> {
+        (0..count)
+            .map(|position| format!("chunk {}", position))
+            .collect()
+    }
+
+    fn embeddings(vectors: &[[f32; 2]]) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "id": "test",
+            "embeddings": vectors,
+            "meta": { "api_version": { "version": "1" } },
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_texts_are_split_into_batches() {
+        let server = MockServer::start().await;
+
+        let batches = [
+            (vec!["chunk 0", "chunk 1"], vec![[1.0, 0.0], [0.9, 0.1]]),
+            (vec!["chunk 2", "chunk 3"], vec![[0.5, 0.5], [0.1, 0.9]]),
+            (vec!["chunk 4"], vec![[0.0, 1.0]]),
+        ];
+        for (texts, vectors) in &batches {
+            Mock::given(method("POST"))
+                .and(path("/embed"))
+                .and(header("authorization", "Bearer test-key"))
+                .and(body_partial_json(json!({
+                    "model": "embed-english-v3.0",
+                    "texts": texts,
+                    "input_type": "search_document",
+                })))
+                .respond_with(embeddings(vectors))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(path("/embed"))
+            .and(body_partial_json(json!({
+                "texts": ["query"],
+                "input_type": "search_query",
+            })))
+            .respond_with(embeddings(&[[0.0, 1.0]]))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let service = service(&server).with_batch_size(2);
+        let embedded = service.embed_texts(&texts(5)).await.unwrap();
+
+        assert_eq!(embedded.len(), 5);
+        assert_eq!(embedded[2], [0.5, 0.5]);
+        assert_eq!(embedded[4], [0.0, 1.0]);
+
+        assert_eq!(service.embed_query("query").await.unwrap(), [0.0, 1.0]);
+    }
+
+    #[tokio::test]
+    async fn test_error_mapping() {
+        let server = MockServer::start().await;
+
+        for (query, status) in [("limited", 429), ("unavailable", 503), ("invalid", 400)] {
+            Mock::given(method("POST"))
+                .and(path("/embed"))
+                .and(body_partial_json(json!({ "texts": [query] })))
+                .respond_with(
+                    ResponseTemplate::new(status).set_body_json(json!({ "message": query })),
+                )
+                .mount(&server)
+                .await;
+        }
+
+        let service = service(&server);
+        assert!(matches!(
+            service.embed_query("limited").await,
+            Err(McpError::RateLimitExceeded)
+        ));
+        assert!(matches!(
+            service.embed_query("unavailable").await,
+            Err(McpError::ExternalServiceError(_))
+        ));
+        assert!(matches!(
+            service.embed_query("invalid").await,
+            Err(McpError::EmbeddingError(message)) if message.contains("invalid")
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unexpected_dimension_is_rejected() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/embed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "embeddings": [[0.1, 0.2, 0.3]],
+            })))
+            .mount(&server)
+            .await;
+
+        let result = service(&server).embed_texts(&texts(1)).await;
+        assert!(matches!(
+            result,
+            Err(McpError::EmbeddingError(message)) if message.contains("3-dimensional")
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::domain::{ContextMatch, McpError, McpResult};
+use crate::ports::out_ports::RerankerPort;
+
+/// Reranker backed by a hosted cross-encoder with a Cohere Rerank-style API
+///
+/// Each candidate is sent as the content of its context, and scored by the relevance the API
+/// returns for it.
+pub struct CohereReranker {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Debug, Serialize)]
+struct RerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: Vec<&'a str>,
+    top_n: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    message: String,
+}
+
+impl CohereReranker {
+    /// Create a reranker calling `{api_base}/rerank`
+    pub fn new(
+        api_key: impl Into<String>,
+        model: impl Into<String>,
+        api_base: &str,
+    ) -> McpResult<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| McpError::ExternalServiceError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            endpoint: format!("{}/rerank", api_base.trim_end_matches('/')),
+            api_key: api_key.into(),
+            model: model.into(),
+        })
+    }
+}
+
+/// Map an error response to an error; rate limits become `RateLimitExceeded`
+fn classify_error(status: StatusCode, body: &str) -> McpError {
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        return McpError::RateLimitExceeded;
+    }
+
+    let message = serde_json::from_str::<ErrorResponse>(body)
+        .map(|response| response.message)
+        .unwrap_or_else(|_| body.to_string());
+    McpError::ExternalServiceError(format!("Rerank API returned {}: {}", status, message))
+}
+
+#[async_trait]
+impl RerankerPort for CohereReranker {
+    async fn rerank(
+        &self,
+        query: &str,
+        candidates: Vec<ContextMatch>,
+        top_k: usize,
+    ) -> McpResult<Vec<ContextMatch>> {
+        if candidates.is_empty() || top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&RerankRequest {
+                model: &self.model,
+                query,
+                documents: candidates
+                    .iter()
+                    .map(|candidate| candidate.context.content.as_str())
+                    .collect(),
+                top_n: top_k.min(candidates.len()),
+            })
+            .send()
+            .await
+            .map_err(|e| McpError::ExternalServiceError(format!("Rerank request failed: {}", e)))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(classify_error(status, &body));
+        }
+
+        let response: RerankResponse = response.json().await.map_err(|e| {
+            McpError::ExternalServiceError(format!("Invalid rerank response: {}", e))
+        })?;
+
+        // Results refer to candidates by position; each is taken at most once
+        let mut candidates: Vec<Option<ContextMatch>> = candidates.into_iter().map(Some).collect();
+        let mut reranked = Vec::with_capacity(response.results.len());
+        for result in response.results {
+            let Some(mut candidate) = candidates.get_mut(result.index).and_then(Option::take)
+            else {
+                return Err(McpError::ExternalServiceError(format!(
+                    "Rerank response refers to unknown or repeated document {}",
+                    result.index
+                )));
+            };
+            candidate.score = result.relevance_score;
+            reranked.push(candidate);
+        }
+
+        reranked.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        reranked.truncate(top_k);
+        Ok(reranked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Context, ContextMetadata};
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn reranker(server: &MockServer) -> CohereReranker {
+        CohereReranker::new("test-key", "rerank-english-v3.0", &server.uri()).unwrap()
+    }
+
+    fn candidates(contents: &[&str]) -> Vec<ContextMatch> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(position, content)| ContextMatch {
+                context: Context::new(*content, ContextMetadata::default()),
+                chunks: None,
+                score: 1.0 - position as f32 / 10.0,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_candidates_take_the_reranked_order_and_scores() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/rerank"))
+            .and(header("authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "rerank-english-v3.0",
+                "query": "kafka lag",
+                "documents": ["alpha", "kafka consumer lag", "beta"],
+                "top_n": 2,
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "results": [
+                    { "index": 1, "relevance_score": 0.97 },
+                    { "index": 2, "relevance_score": 0.12 },
+                ],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let reranked = reranker(&server)
+            .rerank(
+                "kafka lag",
+                candidates(&["alpha", "kafka consumer lag", "beta"]),
+                2,
+            )
+            .await
+            .unwrap();
+
+        let contents: Vec<(&str, f32)> = reranked
+            .iter()
+            .map(|candidate| (candidate.context.content.as_str(), candidate.score))
+            .collect();
+        assert_eq!(contents, [("kafka consumer lag", 0.97), ("beta", 0.12)]);
+    }
+
+    #[tokio::test]
+    async fn test_error_mapping() {
+        let server = MockServer::start().await;
+        for (query, response) in [
+            ("limited", ResponseTemplate::new(429)),
+            (
+                "unavailable",
+                ResponseTemplate::new(503).set_body_json(json!({ "message": "down" })),
+            ),
+            (
+                "unknown",
+                ResponseTemplate::new(200).set_body_json(json!({
+                    "results": [{ "index": 5, "relevance_score": 0.5 }],
+                })),
+            ),
+        ] {
+            Mock::given(method("POST"))
+                .and(path("/rerank"))
+                .and(body_partial_json(json!({ "query": query })))
+                .respond_with(response)
+                .mount(&server)
+                .await;
+        }
+
+        let reranker = reranker(&server);
+        let rerank = |query| reranker.rerank(query, candidates(&["alpha"]), 1);
+        assert!(matches!(
+            rerank("limited").await,
+            Err(McpError::RateLimitExceeded)
+        ));
+        assert!(matches!(
+            rerank("unavailable").await,
+            Err(McpError::ExternalServiceError(message)) if message.contains("down")
+        ));
+        assert!(matches!(
+            rerank("unknown").await,
+            Err(McpError::ExternalServiceError(message)) if message.contains("document 5")
+        ));
+    }
+}
@@ -0,0 +1,191 @@
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::config::ExpansionConfig;
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::QueryExpansionPort;
+
+/// Query expansion from a fixed dictionary of terms and their alternatives, such as
+/// `k8s = ["kubernetes"]`
+///
+/// Terms are looked up by the lowercased words of the query; an alternative may span several
+/// words.
+pub struct DictionaryQueryExpander {
+    synonyms: HashMap<String, Vec<String>>,
+}
+
+impl DictionaryQueryExpander {
+    pub fn new(synonyms: HashMap<String, Vec<String>>) -> Self {
+        Self {
+            synonyms: synonyms
+                .into_iter()
+                .map(|(term, alternatives)| {
+                    let alternatives = alternatives
+                        .iter()
+                        .map(|alternative| alternative.trim().to_lowercase())
+                        .filter(|alternative| !alternative.is_empty())
+                        .collect();
+                    (term.trim().to_lowercase(), alternatives)
+                })
+                .collect(),
+        }
+    }
+
+    /// Dictionary in `expansion.synonyms_path`, if one is configured
+    ///
+    /// Fails if expansion is enabled without one.
+    pub fn from_config(config: &ExpansionConfig) -> McpResult<Option<Self>> {
+        match &config.synonyms_path {
+            Some(path) => Self::open(path).map(Some),
+            None if config.enabled => Err(McpError::ValidationError(
+                "context.expansion.synonyms_path is required when expansion is enabled".to_string(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Load the dictionary from a TOML or JSON file, told apart by its extension
+    pub fn open(path: impl AsRef<Path>) -> McpResult<Self> {
+        let path = path.as_ref();
+        if !path.is_file() {
+            return Err(McpError::ValidationError(format!(
+                "Synonyms file {} does not exist",
+                path.display()
+            )));
+        }
+
+        let synonyms = ::config::Config::builder()
+            .add_source(::config::File::from(path))
+            .build()
+            .and_then(|synonyms| synonyms.try_deserialize::<HashMap<String, Vec<String>>>())
+            .map_err(|e| {
+                McpError::SerializationError(format!(
+                    "Invalid synonyms file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        Ok(Self::new(synonyms))
+    }
+}
+
+/// Lowercased alphanumeric words of a text
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[async_trait]
+impl QueryExpansionPort for DictionaryQueryExpander {
+    async fn expand(&self, query: &str) -> McpResult<Vec<String>> {
+        let query_words = words(query);
+        let mut seen: HashSet<&str> = query_words.iter().map(String::as_str).collect();
+
+        let mut expansions = Vec::new();
+        for word in &query_words {
+            for alternative in self.synonyms.get(word).into_iter().flatten() {
+                if seen.insert(alternative) {
+                    expansions.push(alternative.clone());
+                }
+            }
+        }
+        Ok(expansions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use uuid::Uuid;
+
+    /// Write a synonyms file that is removed once dropped
+    struct SynonymsFile(std::path::PathBuf);
+
+    impl SynonymsFile {
+        fn new(extension: &str, contents: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("mcp-synonyms-{}.{}", Uuid::new_v4(), extension));
+            fs::write(&path, contents).unwrap();
+            Self(path)
+        }
+    }
+
+    impl Drop for SynonymsFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_abbreviations_expand_to_full_forms() {
+        let file = SynonymsFile::new(
+            "toml",
+            r#"
+            k8s = ["Kubernetes"]
+            ci = ["continuous integration"]
+            pg = ["postgres", "postgresql"]
+            "#,
+        );
+        let expander = DictionaryQueryExpander::open(&file.0).unwrap();
+
+        assert_eq!(
+            expander.expand("K8s upgrade broke CI").await.unwrap(),
+            ["kubernetes", "continuous integration"]
+        );
+        assert!(expander
+            .expand("nothing to expand")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_terms_already_in_the_query_are_left_out() {
+        let file = SynonymsFile::new("json", r#"{ "pg": ["postgres", "postgresql"] }"#);
+        let expander = DictionaryQueryExpander::open(&file.0).unwrap();
+
+        assert_eq!(
+            expander.expand("pg postgres pg").await.unwrap(),
+            ["postgresql"]
+        );
+    }
+
+    #[test]
+    fn test_enabled_expansion_requires_a_synonyms_file() {
+        let mut config = crate::config::AppConfig::load_defaults()
+            .unwrap()
+            .context
+            .expansion;
+        assert!(DictionaryQueryExpander::from_config(&config)
+            .unwrap()
+            .is_none());
+
+        config.enabled = true;
+        assert!(DictionaryQueryExpander::from_config(&config).is_err());
+
+        let file = SynonymsFile::new("toml", r#"k8s = ["kubernetes"]"#);
+        config.synonyms_path = Some(file.0.to_string_lossy().into_owned());
+        assert!(DictionaryQueryExpander::from_config(&config)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_missing_or_malformed_files_are_rejected() {
+        let missing = std::env::temp_dir().join(format!("mcp-synonyms-{}.toml", Uuid::new_v4()));
+        assert!(matches!(
+            DictionaryQueryExpander::open(&missing),
+            Err(McpError::ValidationError(_))
+        ));
+
+        let file = SynonymsFile::new("toml", "k8s = 'kubernetes' = [");
+        assert!(matches!(
+            DictionaryQueryExpander::open(&file.0),
+            Err(McpError::SerializationError(_))
+        ));
+    }
+}
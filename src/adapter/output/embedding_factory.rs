@@ -0,0 +1,279 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use super::{
+    BatchingEmbeddingService, CachedEmbeddingService, CohereEmbeddingService,
+    HuggingFaceEmbeddingService, InMemoryVectorIndex, OllamaEmbeddingService,
+    OpenAiEmbeddingService, RetryingEmbeddingService, SimpleEmbeddingService,
+    TfIdfEmbeddingService,
+};
+use crate::config::{AppConfig, EmbeddingConfig, EmbeddingProvider, VectorStoreBackend};
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::{EmbeddingPort, VectorStorePort};
+
+#[cfg(feature = "fastembed")]
+use super::FastEmbedService;
+#[cfg(feature = "lancedb")]
+use super::LanceVectorStore;
+
+/// Embedding providers available in this build
+pub fn supported_providers() -> Vec<&'static str> {
+    let mut providers = vec![
+        EmbeddingProvider::Simple,
+        EmbeddingProvider::TfIdf,
+        EmbeddingProvider::OpenAi,
+        EmbeddingProvider::Cohere,
+        EmbeddingProvider::HuggingFace,
+        EmbeddingProvider::Ollama,
+    ];
+    if cfg!(feature = "fastembed") {
+        providers.push(EmbeddingProvider::FastEmbed);
+    }
+    providers.iter().map(EmbeddingProvider::as_str).collect()
+}
+
+/// Embedding service and the vector store its chunk embeddings are kept in
+pub struct EmbeddingBackend {
+    pub embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    pub vector_store: Arc<dyn VectorStorePort + Send + Sync>,
+}
+
+impl EmbeddingBackend {
+    /// A service that also stores what it embeds
+    fn combined<T>(service: T) -> Self
+    where
+        T: EmbeddingPort + VectorStorePort + Send + Sync + 'static,
+    {
+        let service = Arc::new(service);
+        Self {
+            embedding_service: service.clone(),
+            vector_store: service,
+        }
+    }
+
+    /// A remote or in-process model, storing chunks in the store `embedding.vector_store`
+    /// selects
+    async fn model<T: EmbeddingPort + Send + Sync + 'static>(
+        service: T,
+        config: &EmbeddingConfig,
+    ) -> McpResult<Self> {
+        Ok(Self {
+            embedding_service: decorate(service, config),
+            vector_store: create_vector_store(config).await?,
+        })
+    }
+}
+
+/// Create the embedding service selected by `embedding.provider`, with its vector store
+///
+/// Fails if a setting the provider needs is missing. Models are loaded here, so a broken model
+/// fails at startup rather than on the first request.
+pub async fn create_embedding_backend(config: &AppConfig) -> McpResult<EmbeddingBackend> {
+    let config = &config.embedding;
+
+    match config.provider {
+        EmbeddingProvider::Simple => Ok(EmbeddingBackend {
+            embedding_service: Arc::new(SimpleEmbeddingService::new(config.dimension)),
+            vector_store: create_vector_store(config).await?,
+        }),
+        EmbeddingProvider::TfIdf => match &config.tfidf.path {
+            Some(path) => {
+                info!("Loading TF-IDF vocabulary from {}", path);
+                Ok(EmbeddingBackend::combined(TfIdfEmbeddingService::open(
+                    config.dimension,
+                    path,
+                )?))
+            }
+            None => Ok(EmbeddingBackend::combined(TfIdfEmbeddingService::new(
+                config.dimension,
+            ))),
+        },
+        EmbeddingProvider::OpenAi => {
+            let openai = &config.openai;
+            let api_key = required(&openai.api_key, "openai.api_key", config.provider)?;
+
+            info!("Embedding with OpenAI model {}", openai.model);
+            let service = OpenAiEmbeddingService::new(api_key, &openai.model, &openai.api_base)?;
+            EmbeddingBackend::model(service, config).await
+        }
+        EmbeddingProvider::Cohere => {
+            let cohere = &config.cohere;
+            let api_key = required(&cohere.api_key, "cohere.api_key", config.provider)?;
+
+            info!("Embedding with Cohere model {}", cohere.model);
+            let service = CohereEmbeddingService::new(
+                api_key,
+                &cohere.model,
+                &cohere.api_base,
+                config.dimension,
+            )?
+            .with_batch_size(cohere.batch_size);
+            EmbeddingBackend::model(service, config).await
+        }
+        EmbeddingProvider::HuggingFace => {
+            let huggingface = &config.huggingface;
+            let api_key = required(&huggingface.api_key, "huggingface.api_key", config.provider)?;
+
+            info!("Embedding with Hugging Face model {}", huggingface.model);
+            let service = HuggingFaceEmbeddingService::new(
+                api_key,
+                &huggingface.model,
+                &huggingface.api_base,
+            )?
+            .with_retries(huggingface.max_retries, Duration::from_secs(2));
+            EmbeddingBackend::model(service, config).await
+        }
+        EmbeddingProvider::Ollama => {
+            let ollama = &config.ollama;
+
+            info!(
+                "Embedding with Ollama model {} at {}",
+                ollama.model, ollama.api_base
+            );
+            let service = OllamaEmbeddingService::new(&ollama.model, &ollama.api_base)?;
+            EmbeddingBackend::model(service, config).await
+        }
+        #[cfg(feature = "fastembed")]
+        EmbeddingProvider::FastEmbed => {
+            let fastembed = &config.fastembed;
+
+            info!(
+                "Loading embedding model {} from {}",
+                fastembed.model, fastembed.model_path
+            );
+            let service = FastEmbedService::load(&fastembed.model, &fastembed.model_path)?;
+            EmbeddingBackend::model(service, config).await
+        }
+        #[cfg(not(feature = "fastembed"))]
+        EmbeddingProvider::FastEmbed => Err(McpError::ValidationError(format!(
+            "The fastembed provider requires building with --features fastembed (supported: {})",
+            supported_providers().join(", ")
+        ))),
+    }
+}
+
+/// Create the vector store selected by `embedding.vector_store.backend`
+async fn create_vector_store(
+    config: &EmbeddingConfig,
+) -> McpResult<Arc<dyn VectorStorePort + Send + Sync>> {
+    match config.vector_store.backend {
+        VectorStoreBackend::Memory => Ok(Arc::new(InMemoryVectorIndex::from_config(&config.index))),
+        #[cfg(feature = "lancedb")]
+        VectorStoreBackend::Lance => {
+            let path = &config.vector_store.lance_path;
+            info!("Opening LanceDB vector store at {}", path);
+            Ok(Arc::new(
+                LanceVectorStore::open(path, config.dimension).await?,
+            ))
+        }
+        #[cfg(not(feature = "lancedb"))]
+        VectorStoreBackend::Lance => Err(McpError::ValidationError(
+            "The lance vector store requires building with --features lancedb".to_string(),
+        )),
+    }
+}
+
+/// Retry, batch, and cache the embeddings of a service
+///
+/// Only services whose vectors depend on nothing but the text can be cached, which rules out
+/// `tfidf`.
+fn decorate<T: EmbeddingPort + Send + Sync + 'static>(
+    service: T,
+    config: &EmbeddingConfig,
+) -> Arc<dyn EmbeddingPort + Send + Sync> {
+    let service = RetryingEmbeddingService::new(
+        service,
+        config.max_attempts,
+        Duration::from_millis(config.retry_base_delay_ms),
+    );
+    let service: Arc<dyn EmbeddingPort + Send + Sync> = Arc::new(BatchingEmbeddingService::new(
+        Arc::new(service),
+        config.batch_size,
+        config.max_concurrency,
+    ));
+
+    if config.cache_size == 0 {
+        return service;
+    }
+    Arc::new(CachedEmbeddingService::new(service, config.cache_size))
+}
+
+/// The value of a setting the provider can't run without
+fn required<'a>(
+    value: &'a Option<String>,
+    name: &str,
+    provider: EmbeddingProvider,
+) -> McpResult<&'a str> {
+    value.as_deref().ok_or_else(|| {
+        McpError::ValidationError(format!(
+            "embedding.{} is required for the {} provider",
+            name, provider
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_remote_providers_require_api_key() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        assert_eq!(config.embedding.provider, EmbeddingProvider::Simple);
+        assert!(create_embedding_backend(&config).await.is_ok());
+
+        config.embedding.provider = EmbeddingProvider::OpenAi;
+        let Err(McpError::ValidationError(message)) = create_embedding_backend(&config).await
+        else {
+            panic!("expected a validation error");
+        };
+        assert!(message.contains("embedding.openai.api_key"));
+
+        config.embedding.openai.api_key = Some("sk-test".to_string());
+        assert!(create_embedding_backend(&config).await.is_ok());
+
+        config.embedding.provider = EmbeddingProvider::Cohere;
+        assert!(create_embedding_backend(&config).await.is_err());
+        config.embedding.cohere.api_key = Some("co-test".to_string());
+        assert!(create_embedding_backend(&config).await.is_ok());
+
+        config.embedding.provider = EmbeddingProvider::HuggingFace;
+        assert!(create_embedding_backend(&config).await.is_err());
+        config.embedding.huggingface.api_key = Some("hf-test".to_string());
+        assert!(create_embedding_backend(&config).await.is_ok());
+
+        // Local providers need no credentials
+        for provider in [EmbeddingProvider::TfIdf, EmbeddingProvider::Ollama] {
+            config.embedding.provider = provider;
+            assert!(create_embedding_backend(&config).await.is_ok());
+        }
+    }
+
+    #[cfg(not(feature = "lancedb"))]
+    #[tokio::test]
+    async fn test_lance_store_requires_the_feature() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.embedding.vector_store.backend = VectorStoreBackend::Lance;
+
+        let Err(McpError::ValidationError(message)) = create_embedding_backend(&config).await
+        else {
+            panic!("expected a validation error");
+        };
+        assert!(message.contains("--features lancedb"));
+    }
+
+    #[test]
+    fn test_unknown_provider_is_rejected_when_loading() {
+        let err = ::config::Config::builder()
+            .set_override("dimension", 8)
+            .unwrap()
+            .set_override("provider", "word2vec")
+            .unwrap()
+            .build()
+            .unwrap()
+            .try_deserialize::<crate::config::EmbeddingConfig>()
+            .unwrap_err();
+        assert!(err.to_string().contains("word2vec"), "{}", err);
+    }
+}
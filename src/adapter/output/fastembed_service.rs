@@ -0,0 +1,65 @@
+use async_trait::async_trait;
+use fastembed::{InitOptions, TextEmbedding};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service running a sentence-transformer ONNX model in process
+pub struct FastEmbedService {
+    model: Arc<TextEmbedding>,
+}
+
+impl FastEmbedService {
+    /// Load a model by its code (e.g. `sentence-transformers/all-MiniLM-L6-v2`)
+    ///
+    /// The model files are read from `cache_dir`, and downloaded there first if missing.
+    pub fn load(model_code: &str, cache_dir: impl Into<PathBuf>) -> McpResult<Self> {
+        let supported = TextEmbedding::list_supported_models();
+        let Some(info) = supported
+            .iter()
+            .find(|info| info.model_code.eq_ignore_ascii_case(model_code))
+        else {
+            let codes: Vec<&str> = supported
+                .iter()
+                .map(|info| info.model_code.as_str())
+                .collect();
+            return Err(McpError::ValidationError(format!(
+                "Unsupported fastembed model '{}' (supported: {})",
+                model_code,
+                codes.join(", ")
+            )));
+        };
+
+        let options = InitOptions::new(info.model.clone())
+            .with_cache_dir(cache_dir.into())
+            .with_show_download_progress(false);
+        let model = TextEmbedding::try_new(options).map_err(|e| {
+            McpError::EmbeddingError(format!("Failed to load model {}: {}", model_code, e))
+        })?;
+
+        Ok(Self {
+            model: Arc::new(model),
+        })
+    }
+
+    /// Embed texts on the blocking pool, since inference is CPU bound
+    async fn embed(&self, texts: Vec<String>) -> McpResult<Vec<Vec<f32>>> {
+        let model = self.model.clone();
+        tokio::task::spawn_blocking(move || model.embed(texts, None))
+            .await
+            .map_err(|e| McpError::EmbeddingError(e.to_string()))?
+            .map_err(|e| McpError::EmbeddingError(e.to_string()))
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for FastEmbedService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.embed(texts.to_vec()).await
+    }
+}
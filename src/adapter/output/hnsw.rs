@@ -0,0 +1,368 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Deleted nodes tolerated before the graph is rebuilt, whatever its size
+const MIN_DELETED_BEFORE_REBUILD: usize = 64;
+
+/// Highest layer a node can be assigned to
+const MAX_LEVEL: usize = 16;
+
+/// Hierarchical navigable small world graph for approximate cosine similarity search
+///
+/// Vectors are normalized on insert, so similarity is a dot product. Removed nodes stay in the
+/// graph as tombstones that keep it connected, until they outnumber the live ones and the
+/// graph is rebuilt from those.
+pub(super) struct HnswGraph {
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    level_factor: f64,
+    nodes: Vec<Node>,
+    ids: HashMap<Uuid, usize>,
+    entry: Option<usize>,
+    max_level: usize,
+    deleted: usize,
+    rng: u64,
+}
+
+struct Node {
+    id: Uuid,
+    vector: Vec<f32>,
+    /// Linked nodes on each layer up to the node's level
+    neighbors: Vec<Vec<usize>>,
+    deleted: bool,
+}
+
+/// Similarity to the query and index of a node, ordered by similarity
+#[derive(Clone, Copy, PartialEq)]
+struct Scored(f32, usize);
+
+impl Eq for Scored {}
+
+impl PartialOrd for Scored {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Scored {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(self.1.cmp(&other.1))
+    }
+}
+
+impl HnswGraph {
+    /// Link each node to `m` others per layer, searching `ef_construction` candidates when
+    /// linking and `ef_search` when querying
+    pub(super) fn new(m: usize, ef_construction: usize, ef_search: usize) -> Self {
+        let m = m.max(2);
+        Self {
+            m,
+            ef_construction: ef_construction.max(1),
+            ef_search: ef_search.max(1),
+            level_factor: 1.0 / (m as f64).ln(),
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            entry: None,
+            max_level: 0,
+            deleted: 0,
+            rng: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Number of live vectors
+    pub(super) fn len(&self) -> usize {
+        self.nodes.len() - self.deleted
+    }
+
+    /// Length of the indexed vectors, if any are indexed
+    pub(super) fn dimension(&self) -> Option<usize> {
+        self.nodes
+            .iter()
+            .find(|node| !node.deleted)
+            .map(|node| node.vector.len())
+    }
+
+    /// Index a vector under `id`, replacing the one indexed under it before
+    pub(super) fn insert(&mut self, id: Uuid, vector: &[f32]) {
+        self.remove(id);
+
+        let level = self.random_level();
+        let index = self.nodes.len();
+        self.nodes.push(Node {
+            id,
+            vector: normalized(vector),
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+        self.ids.insert(id, index);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(index);
+            self.max_level = level;
+            return;
+        };
+
+        let query = self.nodes[index].vector.clone();
+        let mut nearest = vec![entry];
+        for layer in (level + 1..=self.max_level).rev() {
+            nearest = vec![self.search_layer(&query, &nearest, 1, layer)[0].1];
+        }
+
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&query, &nearest, self.ef_construction, layer);
+            let neighbors: Vec<usize> = candidates
+                .iter()
+                .map(|candidate| candidate.1)
+                .filter(|&candidate| candidate != index)
+                .take(self.m)
+                .collect();
+
+            for &neighbor in &neighbors {
+                self.link(neighbor, index, layer);
+            }
+            self.nodes[index].neighbors[layer] = neighbors;
+            nearest = candidates
+                .into_iter()
+                .map(|candidate| candidate.1)
+                .collect();
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry = Some(index);
+        }
+    }
+
+    /// Stop returning the vector indexed under `id`
+    pub(super) fn remove(&mut self, id: Uuid) {
+        let Some(index) = self.ids.remove(&id) else {
+            return;
+        };
+        self.nodes[index].deleted = true;
+        self.deleted += 1;
+
+        if self.deleted > self.len().max(MIN_DELETED_BEFORE_REBUILD) {
+            self.rebuild();
+        }
+    }
+
+    /// Ids of about the `limit` vectors most similar to `query`, with their similarity, best
+    /// first
+    pub(super) fn search(&self, query: &[f32], limit: usize) -> Vec<(Uuid, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let query = normalized(query);
+        let mut nearest = vec![entry];
+        for layer in (1..=self.max_level).rev() {
+            nearest = vec![self.search_layer(&query, &nearest, 1, layer)[0].1];
+        }
+
+        // Widen the search by the share of tombstones, which are visited but not returned
+        let ef = self.ef_search.max(limit) * self.nodes.len() / self.len().max(1);
+        self.search_layer(&query, &nearest, ef, 0)
+            .into_iter()
+            .filter(|candidate| !self.nodes[candidate.1].deleted)
+            .take(limit)
+            .map(|candidate| (self.nodes[candidate.1].id, candidate.0))
+            .collect()
+    }
+
+    /// The `ef` nodes of a layer most similar to `query` reachable from `entry`, best first
+    fn search_layer(&self, query: &[f32], entry: &[usize], ef: usize, layer: usize) -> Vec<Scored> {
+        let mut visited: HashSet<usize> = entry.iter().copied().collect();
+        let mut candidates = BinaryHeap::new();
+        let mut found = BinaryHeap::new();
+        for &index in entry {
+            let scored = Scored(self.similarity(query, index), index);
+            candidates.push(scored);
+            found.push(Reverse(scored));
+        }
+
+        while let Some(candidate) = candidates.pop() {
+            let Reverse(worst) = *found.peek().expect("entry points are always found");
+            if found.len() >= ef && candidate.0 < worst.0 {
+                break;
+            }
+
+            for &neighbor in &self.nodes[candidate.1].neighbors[layer] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+
+                let scored = Scored(self.similarity(query, neighbor), neighbor);
+                let Reverse(worst) = *found.peek().expect("entry points are always found");
+                if found.len() < ef || scored.0 > worst.0 {
+                    candidates.push(scored);
+                    found.push(Reverse(scored));
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(scored)| scored)
+            .collect()
+    }
+
+    /// Link `from` to `to` on a layer, keeping only the closest links once there are too many
+    fn link(&mut self, from: usize, to: usize, layer: usize) {
+        let max_links = if layer == 0 { 2 * self.m } else { self.m };
+        let mut links = std::mem::take(&mut self.nodes[from].neighbors[layer]);
+        links.push(to);
+
+        if links.len() > max_links {
+            let origin = &self.nodes[from].vector;
+            let mut scored: Vec<Scored> = links
+                .iter()
+                .map(|&link| Scored(dot(origin, &self.nodes[link].vector), link))
+                .collect();
+            scored.sort_by(|a, b| b.cmp(a));
+            links = scored
+                .into_iter()
+                .take(max_links)
+                .map(|scored| scored.1)
+                .collect();
+        }
+
+        self.nodes[from].neighbors[layer] = links;
+    }
+
+    fn similarity(&self, query: &[f32], index: usize) -> f32 {
+        dot(query, &self.nodes[index].vector)
+    }
+
+    /// Rebuild the graph from the live nodes, dropping the tombstones
+    fn rebuild(&mut self) {
+        let nodes = std::mem::take(&mut self.nodes);
+        self.ids.clear();
+        self.entry = None;
+        self.max_level = 0;
+        self.deleted = 0;
+
+        for node in nodes.into_iter().filter(|node| !node.deleted) {
+            self.insert(node.id, &node.vector);
+        }
+    }
+
+    /// Layer of a new node, exponentially less likely the higher it is
+    fn random_level(&mut self) -> usize {
+        // splitmix64, seeded the same for every graph so builds are reproducible
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+
+        let uniform = (z >> 11) as f64 / (1u64 << 53) as f64;
+        let level = -(1.0 - uniform).ln() * self.level_factor;
+        (level as usize).min(MAX_LEVEL)
+    }
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// The vector scaled to unit length, or unchanged if it's all zeros
+fn normalized(vector: &[f32]) -> Vec<f32> {
+    let magnitude = dot(vector, vector).sqrt();
+    if magnitude > 0.0 {
+        vector.iter().map(|x| x / magnitude).collect()
+    } else {
+        vector.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_vectors(rng: &mut StdRng, count: usize, dimension: usize) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|_| (0..dimension).map(|_| rng.gen_range(-1.0..1.0)).collect())
+            .collect()
+    }
+
+    /// Ids of the `limit` vectors most similar to `query` by exhaustive search
+    fn exact(vectors: &[(Uuid, Vec<f32>)], query: &[f32], limit: usize) -> Vec<Uuid> {
+        let query = normalized(query);
+        let mut scored: Vec<(Uuid, f32)> = vectors
+            .iter()
+            .map(|(id, vector)| (*id, dot(&query, &normalized(vector))))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(limit).map(|(id, _)| id).collect()
+    }
+
+    #[test]
+    fn test_recall_at_10_against_exact_search() {
+        let mut rng = StdRng::seed_from_u64(42);
+        let vectors: Vec<(Uuid, Vec<f32>)> = random_vectors(&mut rng, 2_000, 32)
+            .into_iter()
+            .map(|vector| (Uuid::new_v4(), vector))
+            .collect();
+
+        let mut graph = HnswGraph::new(16, 100, 64);
+        for (id, vector) in &vectors {
+            graph.insert(*id, vector);
+        }
+        assert_eq!(graph.len(), vectors.len());
+
+        let queries = random_vectors(&mut rng, 50, 32);
+        let mut found = 0;
+        for query in &queries {
+            let expected: HashSet<Uuid> = exact(&vectors, query, 10).into_iter().collect();
+            found += graph
+                .search(query, 10)
+                .iter()
+                .filter(|(id, _)| expected.contains(id))
+                .count();
+        }
+
+        let recall = found as f64 / (queries.len() * 10) as f64;
+        assert!(recall >= 0.9, "recall@10 was {}", recall);
+    }
+
+    #[test]
+    fn test_removed_vectors_are_not_returned() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let vectors: Vec<(Uuid, Vec<f32>)> = random_vectors(&mut rng, 500, 8)
+            .into_iter()
+            .map(|vector| (Uuid::new_v4(), vector))
+            .collect();
+
+        let mut graph = HnswGraph::new(8, 50, 32);
+        for (id, vector) in &vectors {
+            graph.insert(*id, vector);
+        }
+
+        // Removing most of them rebuilds the graph along the way
+        let (removed, kept) = vectors.split_at(400);
+        for (id, _) in removed {
+            graph.remove(*id);
+        }
+        assert_eq!(graph.len(), kept.len());
+        assert!(graph.nodes.len() < vectors.len());
+
+        for (id, vector) in kept.iter().take(20) {
+            let results = graph.search(vector, 5);
+            assert_eq!(results[0].0, *id);
+            assert!(results
+                .iter()
+                .all(|(id, _)| !removed.iter().any(|(gone, _)| gone == id)));
+        }
+    }
+}
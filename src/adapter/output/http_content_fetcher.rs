@@ -0,0 +1,362 @@
+use async_trait::async_trait;
+use reqwest::header::{CONTENT_TYPE, LOCATION};
+use reqwest::redirect::Policy;
+use reqwest::{Client, Url};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+use crate::config::IngestConfig;
+use crate::domain::{FetchedContent, McpError, McpResult};
+use crate::ports::out_ports::ContentFetcherPort;
+
+/// Fetcher downloading documents over HTTP(S), guarded against reaching internal services
+///
+/// Only `http` and `https` URLs are fetched, and unless private addresses are allowed, hosts
+/// resolving to loopback, private, link-local or otherwise internal addresses are refused.
+/// Each host is resolved once and connected to at the addresses that were checked, and
+/// redirects are followed by hand so every hop is checked the same way.
+pub struct HttpContentFetcher {
+    timeout: Duration,
+    max_bytes: usize,
+    max_redirects: usize,
+    allow_private_addresses: bool,
+}
+
+impl HttpContentFetcher {
+    /// Create a fetcher giving up after `timeout` and on documents over `max_bytes`
+    pub fn new(timeout: Duration, max_bytes: usize) -> Self {
+        Self {
+            timeout,
+            max_bytes,
+            max_redirects: 5,
+            allow_private_addresses: false,
+        }
+    }
+
+    /// Create the fetcher described by `config`
+    pub fn from_config(config: &IngestConfig) -> Self {
+        Self::new(
+            Duration::from_secs(config.timeout_seconds),
+            config.max_bytes,
+        )
+        .with_max_redirects(config.max_redirects)
+        .with_private_addresses(config.allow_private_addresses)
+    }
+
+    /// Set how many redirects are followed before giving up
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Set whether hosts on private and loopback addresses may be fetched from
+    pub fn with_private_addresses(mut self, allow: bool) -> Self {
+        self.allow_private_addresses = allow;
+        self
+    }
+
+    async fn fetch_following_redirects(&self, url: &str) -> McpResult<FetchedContent> {
+        let mut url = Url::parse(url)
+            .map_err(|e| McpError::ValidationError(format!("url is invalid: {}", e)))?;
+
+        for _ in 0..=self.max_redirects {
+            let client = self.client_for(&url).await?;
+            let response = client
+                .get(url.clone())
+                .send()
+                .await
+                .map_err(|e| fetch_error(&url, e))?;
+
+            let status = response.status();
+            if status.is_redirection() {
+                let location = response
+                    .headers()
+                    .get(LOCATION)
+                    .and_then(|value| value.to_str().ok())
+                    .ok_or_else(|| {
+                        McpError::ExternalServiceError(format!(
+                            "{} redirected without a location",
+                            url
+                        ))
+                    })?;
+                url = url.join(location).map_err(|e| {
+                    McpError::ExternalServiceError(format!(
+                        "{} redirected to an invalid location: {}",
+                        url, e
+                    ))
+                })?;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(McpError::ExternalServiceError(format!(
+                    "{} returned {}",
+                    url, status
+                )));
+            }
+
+            let (content_type, charset) = response
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(parse_content_type)
+                .unwrap_or_default();
+            let body = self.read_body(&url, response).await?;
+            return Ok(FetchedContent {
+                url: url.to_string(),
+                content_type,
+                charset,
+                body,
+            });
+        }
+
+        Err(McpError::ExternalServiceError(format!(
+            "{} redirected more than {} times",
+            url, self.max_redirects
+        )))
+    }
+
+    /// A client connecting only to the checked addresses of `url`'s host
+    async fn client_for(&self, url: &Url) -> McpResult<Client> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(McpError::ValidationError(format!(
+                "url must be http or https, got '{}'",
+                url.scheme()
+            )));
+        }
+        let host = url
+            .host_str()
+            .ok_or_else(|| McpError::ValidationError("url has no host".to_string()))?;
+        let port = url.port_or_known_default().unwrap_or(80);
+
+        // An IP address literal is bracketed in IPv6 URLs
+        let addresses: Vec<SocketAddr> = match host
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+        {
+            Ok(ip) => vec![SocketAddr::new(ip, port)],
+            Err(_) => tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| {
+                    McpError::ExternalServiceError(format!("Failed to resolve {}: {}", host, e))
+                })?
+                .collect(),
+        };
+        if addresses.is_empty() {
+            return Err(McpError::ExternalServiceError(format!(
+                "{} has no addresses",
+                host
+            )));
+        }
+        if !self.allow_private_addresses && addresses.iter().any(|address| !is_public(address.ip()))
+        {
+            return Err(McpError::ValidationError(format!(
+                "url host {} is on a private address",
+                host
+            )));
+        }
+
+        Client::builder()
+            .timeout(self.timeout)
+            .redirect(Policy::none())
+            .resolve_to_addrs(host, &addresses)
+            .build()
+            .map_err(|e| McpError::ExternalServiceError(e.to_string()))
+    }
+
+    /// The body of `response`, refused once it's larger than the limit
+    async fn read_body(&self, url: &Url, mut response: reqwest::Response) -> McpResult<Vec<u8>> {
+        let too_large = || {
+            McpError::ValidationError(format!(
+                "url document is larger than {} bytes",
+                self.max_bytes
+            ))
+        };
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_bytes as u64)
+        {
+            return Err(too_large());
+        }
+
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(|e| fetch_error(url, e))? {
+            if body.len() + chunk.len() > self.max_bytes {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+#[async_trait]
+impl ContentFetcherPort for HttpContentFetcher {
+    async fn fetch(&self, url: &str) -> McpResult<FetchedContent> {
+        tokio::time::timeout(self.timeout, self.fetch_following_redirects(url))
+            .await
+            .unwrap_or_else(|_| {
+                Err(McpError::ExternalServiceError(format!(
+                    "{} didn't answer within {:?}",
+                    url, self.timeout
+                )))
+            })
+    }
+}
+
+fn fetch_error(url: &Url, err: reqwest::Error) -> McpError {
+    McpError::ExternalServiceError(format!("Failed to fetch {}: {}", url, err))
+}
+
+/// The media type and charset of a `Content-Type` header, both lowercased
+fn parse_content_type(header: &str) -> (Option<String>, Option<String>) {
+    let mut parts = header.split(';').map(str::trim);
+    let media_type = parts
+        .next()
+        .filter(|media_type| !media_type.is_empty())
+        .map(str::to_ascii_lowercase);
+    let charset = parts
+        .filter_map(|parameter| parameter.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_ascii_lowercase());
+    (media_type, charset)
+}
+
+/// Whether `ip` is on the public internet, rather than loopback, private, link-local,
+/// shared, multicast or reserved for documentation
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || first == 0
+                || (first == 100 && second & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// A fetcher allowed to reach the mock server, which listens on loopback
+    fn fetcher() -> HttpContentFetcher {
+        HttpContentFetcher::new(Duration::from_secs(5), 1024).with_private_addresses(true)
+    }
+
+    fn validation_message(result: McpResult<FetchedContent>) -> String {
+        match result {
+            Err(McpError::ValidationError(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_documents_are_fetched_through_redirects() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/old"))
+            .respond_with(ResponseTemplate::new(301).insert_header("location", "/page"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/page"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "text/HTML; charset=\"ISO-8859-1\"")
+                    .set_body_bytes(b"<p>caf\xe9</p>".to_vec()),
+            )
+            .mount(&server)
+            .await;
+
+        let fetched = fetcher()
+            .fetch(&format!("{}/old", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(fetched.url, format!("{}/page", server.uri()));
+        assert_eq!(fetched.content_type.as_deref(), Some("text/html"));
+        assert_eq!(fetched.charset.as_deref(), Some("iso-8859-1"));
+        assert_eq!(fetched.body, b"<p>caf\xe9</p>");
+    }
+
+    #[tokio::test]
+    async fn test_failures_and_oversized_documents_are_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/large"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("x".repeat(1025)))
+            .mount(&server)
+            .await;
+
+        let missing = fetcher().fetch(&format!("{}/missing", server.uri())).await;
+        assert!(matches!(
+            missing,
+            Err(McpError::ExternalServiceError(message)) if message.contains("404")
+        ));
+
+        let large = fetcher().fetch(&format!("{}/large", server.uri())).await;
+        assert!(validation_message(large).contains("larger than 1024 bytes"));
+    }
+
+    #[tokio::test]
+    async fn test_internal_addresses_and_other_schemes_are_refused() {
+        let server = MockServer::start().await;
+        let guarded = HttpContentFetcher::new(Duration::from_secs(5), 1024);
+
+        let loopback = guarded.fetch(&format!("{}/page", server.uri())).await;
+        assert!(validation_message(loopback).contains("private address"));
+        let localhost = guarded.fetch("http://localhost:9/").await;
+        assert!(validation_message(localhost).contains("private address"));
+        let metadata = guarded
+            .fetch("http://169.254.169.254/latest/meta-data")
+            .await;
+        assert!(validation_message(metadata).contains("private address"));
+
+        let file = guarded.fetch("file:///etc/passwd").await;
+        assert!(validation_message(file).contains("http or https"));
+        assert!(validation_message(guarded.fetch("not a url").await).starts_with("url is invalid"));
+    }
+
+    #[test]
+    fn test_only_public_addresses_are_public() {
+        for public in [
+            "93.184.216.34",
+            "2606:2800:220:1::1",
+            "::ffff:93.184.216.34",
+        ] {
+            assert!(is_public(public.parse().unwrap()), "{}", public);
+        }
+        for internal in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(internal.parse().unwrap()), "{}", internal);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service backed by the Hugging Face Inference API `feature-extraction` pipeline
+///
+/// Models that aren't warm answer with a 503 while they load; those requests are retried a
+/// bounded number of times.
+pub struct HuggingFaceEmbeddingService {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+#[derive(Debug, Serialize)]
+struct FeatureExtractionRequest<'a> {
+    inputs: &'a [String],
+}
+
+/// Sentence models return one vector per input, token models one vector per input token
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum FeatureExtractionResponse {
+    Pooled(Vec<Vec<f32>>),
+    PerToken(Vec<Vec<Vec<f32>>>),
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+    estimated_time: Option<f32>,
+}
+
+impl HuggingFaceEmbeddingService {
+    /// Create a service calling the feature-extraction pipeline of `model` under `api_base`
+    pub fn new(
+        api_key: impl Into<String>,
+        model: impl Into<String>,
+        api_base: &str,
+    ) -> McpResult<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| McpError::ExternalServiceError(e.to_string()))?;
+        let model = model.into();
+
+        Ok(Self {
+            client,
+            endpoint: format!(
+                "{}/pipeline/feature-extraction/{}",
+                api_base.trim_end_matches('/'),
+                model
+            ),
+            api_key: api_key.into(),
+            model,
+            max_retries: 3,
+            retry_backoff: Duration::from_secs(2),
+        })
+    }
+
+    /// Retry a loading model up to `max_retries` times, doubling `backoff` after each
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.retry_backoff = backoff;
+        self
+    }
+
+    /// Embed a batch of texts in one request, waiting for the model if it's still loading
+    async fn embed_inputs(&self, inputs: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        let mut backoff = self.retry_backoff;
+        let mut attempt = 0;
+
+        loop {
+            match self.request_embeddings(inputs).await? {
+                Ok(embeddings) => return Ok(embeddings),
+                Err(loading) if attempt < self.max_retries => {
+                    attempt += 1;
+                    warn!(
+                        "Model {} is loading ({}), retrying in {:?} ({}/{})",
+                        self.model, loading, backoff, attempt, self.max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(loading) => {
+                    return Err(McpError::ExternalServiceError(format!(
+                        "Model {} did not finish loading after {} retries: {}",
+                        self.model, self.max_retries, loading
+                    )))
+                }
+            }
+        }
+    }
+
+    /// Request embeddings, with the inner error describing a model that is still loading
+    async fn request_embeddings(
+        &self,
+        inputs: &[String],
+    ) -> McpResult<Result<Vec<Vec<f32>>, String>> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&FeatureExtractionRequest { inputs })
+            .send()
+            .await
+            .map_err(|e| {
+                McpError::ExternalServiceError(format!("Embedding request failed: {}", e))
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let detail = serde_json::from_str::<ErrorResponse>(&body).ok();
+
+            if status == StatusCode::SERVICE_UNAVAILABLE {
+                if let Some(ErrorResponse {
+                    error,
+                    estimated_time: Some(estimated_time),
+                }) = detail
+                {
+                    return Ok(Err(format!("{}, estimated {:.0}s", error, estimated_time)));
+                }
+            }
+
+            let message = detail.map(|detail| detail.error).unwrap_or(body);
+            return Err(classify_error(status, message));
+        }
+
+        let response: FeatureExtractionResponse = response
+            .json()
+            .await
+            .map_err(|e| McpError::EmbeddingError(format!("Invalid embedding response: {}", e)))?;
+
+        let embeddings: Vec<Vec<f32>> = match response {
+            FeatureExtractionResponse::Pooled(embeddings) => embeddings,
+            FeatureExtractionResponse::PerToken(tokens) => {
+                tokens.iter().map(|tokens| mean_pool(tokens)).collect()
+            }
+        };
+
+        if embeddings.len() != inputs.len() {
+            return Err(McpError::EmbeddingError(format!(
+                "Expected {} embeddings, received {}",
+                inputs.len(),
+                embeddings.len()
+            )));
+        }
+        Ok(Ok(embeddings))
+    }
+}
+
+/// Map an error response to an error; server failures are retryable
+fn classify_error(status: StatusCode, message: String) -> McpError {
+    let message = format!("Embedding API returned {}: {}", status, message);
+    if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+        McpError::ExternalServiceError(message)
+    } else {
+        McpError::EmbeddingError(message)
+    }
+}
+
+/// Average of per-token vectors, giving one vector for the whole input
+fn mean_pool(tokens: &[Vec<f32>]) -> Vec<f32> {
+    let Some(first) = tokens.first() else {
+        return Vec::new();
+    };
+
+    let mut pooled = vec![0.0; first.len()];
+    for token in tokens {
+        for (sum, value) in pooled.iter_mut().zip(token) {
+            *sum += value;
+        }
+    }
+    for sum in &mut pooled {
+        *sum /= tokens.len() as f32;
+    }
+    pooled
+}
+
+#[async_trait]
+impl EmbeddingPort for HuggingFaceEmbeddingService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.embed_inputs(texts).await
+    }
+
+    /// Embed a one-word query, which exercises the endpoint, credentials and model
+    async fn check_health(&self) -> McpResult<()> {
+        self.embed_query("ping").await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const MODEL_PATH: &str = "/pipeline/feature-extraction/sentence-transformers/all-MiniLM-L6-v2";
+
+    fn service(server: &MockServer) -> HuggingFaceEmbeddingService {
+        HuggingFaceEmbeddingService::new(
+            "hf-test",
+            "sentence-transformers/all-MiniLM-L6-v2",
+            &server.uri(),
+        )
+        .unwrap()
+        .with_retries(2, Duration::from_millis(1))
+    }
+
+    fn loading() -> ResponseTemplate {
+        ResponseTemplate::new(503).set_body_json(json!({
+            "error": "Model sentence-transformers/all-MiniLM-L6-v2 is currently loading",
+            "estimated_time": 20.0,
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_loading_model_is_retried() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(MODEL_PATH))
+            .respond_with(loading())
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path(MODEL_PATH))
+            .and(header("authorization", "Bearer hf-test"))
+            .and(body_partial_json(
+                json!({ "inputs": ["restart the database"] }),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([[1.0, 0.0]])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let embedded = service(&server)
+            .embed_query("restart the database")
+            .await
+            .unwrap();
+        assert_eq!(embedded, [1.0, 0.0]);
+    }
+
+    #[tokio::test]
+    async fn test_model_that_keeps_loading_is_external_service_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(MODEL_PATH))
+            .respond_with(loading())
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let err = service(&server).embed_query("query").await.unwrap_err();
+        assert!(
+            matches!(&err, McpError::ExternalServiceError(message) if message.contains("loading")),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_per_token_vectors_are_mean_pooled() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(MODEL_PATH))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!([[[1.0, 0.0], [0.0, 1.0], [0.5, 0.5]], [[0.0, 2.0]],])),
+            )
+            .mount(&server)
+            .await;
+
+        let texts = ["three tokens here".to_string(), "one".to_string()];
+        let embedded = service(&server).embed_texts(&texts).await.unwrap();
+        assert_eq!(embedded, [vec![0.5, 0.5], vec![0.0, 2.0]]);
+    }
+
+    #[tokio::test]
+    async fn test_other_failures_are_not_retried() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path(MODEL_PATH))
+            .respond_with(
+                ResponseTemplate::new(400).set_body_json(json!({ "error": "Invalid token" })),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let err = service(&server).embed_query("query").await.unwrap_err();
+        assert!(
+            matches!(err, McpError::EmbeddingError(message) if message.contains("Invalid token"))
+        );
+    }
+}
@@ -0,0 +1,428 @@
+use arrow_array::builder::{ListBuilder, StringBuilder};
+use arrow_array::types::Float32Type;
+use arrow_array::{
+    Array, FixedSizeListArray, Float32Array, ListArray, RecordBatch, RecordBatchIterator,
+    StringArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use lancedb::index::Index;
+use lancedb::query::{ExecutableQuery, QueryBase};
+use lancedb::{connect, DistanceType, Table};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::domain::{ContextChunk, McpError, McpResult};
+use crate::ports::out_ports::VectorStorePort;
+
+/// Table the chunk vectors are kept in
+const TABLE_NAME: &str = "chunks";
+
+/// Rows needed before an ANN index is built; smaller tables are searched exhaustively
+const MIN_ROWS_FOR_INDEX: usize = 10_000;
+
+/// Vector store keeping chunk vectors in a LanceDB dataset on disk
+///
+/// Each row holds a chunk with the tags of its context, so tag filters are evaluated by Lance
+/// while searching rather than on the results.
+pub struct LanceVectorStore {
+    table: Table,
+    schema: SchemaRef,
+    dimension: usize,
+}
+
+impl LanceVectorStore {
+    /// Open (or create) the dataset at `path`, holding vectors `dimension` long
+    ///
+    /// An ANN index is built when a large enough table without one is opened.
+    pub async fn open(path: &str, dimension: usize) -> McpResult<Self> {
+        let schema = chunk_schema(dimension);
+        let db = connect(path).execute().await.map_err(storage_error)?;
+
+        let table_names = db.table_names().execute().await.map_err(storage_error)?;
+        let table = if table_names.iter().any(|name| name == TABLE_NAME) {
+            db.open_table(TABLE_NAME)
+                .execute()
+                .await
+                .map_err(storage_error)?
+        } else {
+            db.create_empty_table(TABLE_NAME, schema.clone())
+                .execute()
+                .await
+                .map_err(storage_error)?
+        };
+
+        let store = Self {
+            table,
+            schema,
+            dimension,
+        };
+        store.ensure_index().await?;
+        Ok(store)
+    }
+
+    async fn ensure_index(&self) -> McpResult<()> {
+        let rows = self.table.count_rows(None).await.map_err(storage_error)?;
+        if rows < MIN_ROWS_FOR_INDEX {
+            return Ok(());
+        }
+
+        let indices = self.table.list_indices().await.map_err(storage_error)?;
+        if indices
+            .iter()
+            .any(|index| index.columns.iter().any(|column| column == "vector"))
+        {
+            return Ok(());
+        }
+
+        self.table
+            .create_index(&["vector"], Index::Auto)
+            .execute()
+            .await
+            .map_err(storage_error)
+    }
+
+    /// Rows of embedded chunks, all tagged `tags`
+    fn to_batch(&self, chunks: &[&ContextChunk], tags: &[String]) -> McpResult<RecordBatch> {
+        let mut vectors = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            let embedding = chunk.embedding.as_deref().unwrap_or_default();
+            if embedding.len() != self.dimension {
+                return Err(McpError::dimension_mismatch(
+                    self.dimension,
+                    embedding.len(),
+                ));
+            }
+            vectors.push(Some(embedding.iter().map(|value| Some(*value))));
+        }
+
+        let columns: Vec<Arc<dyn Array>> = vec![
+            Arc::new(StringArray::from_iter_values(
+                chunks.iter().map(|chunk| chunk.chunk_id.to_string()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                chunks.iter().map(|chunk| chunk.context_id.to_string()),
+            )),
+            Arc::new(StringArray::from_iter_values(
+                chunks.iter().map(|chunk| chunk.content.as_str()),
+            )),
+            Arc::new(UInt64Array::from_iter_values(
+                chunks.iter().map(|chunk| chunk.position as u64),
+            )),
+            Arc::new(tag_lists(chunks.len(), tags)),
+            Arc::new(
+                FixedSizeListArray::from_iter_primitive::<Float32Type, _, _>(
+                    vectors,
+                    self.dimension as i32,
+                ),
+            ),
+        ];
+
+        RecordBatch::try_new(self.schema.clone(), columns).map_err(storage_error)
+    }
+}
+
+fn chunk_schema(dimension: usize) -> SchemaRef {
+    Arc::new(Schema::new(vec![
+        Field::new("chunk_id", DataType::Utf8, false),
+        Field::new("context_id", DataType::Utf8, false),
+        Field::new("content", DataType::Utf8, false),
+        Field::new("position", DataType::UInt64, false),
+        Field::new(
+            "tags",
+            DataType::List(Arc::new(Field::new("item", DataType::Utf8, true))),
+            false,
+        ),
+        Field::new(
+            "vector",
+            DataType::FixedSizeList(
+                Arc::new(Field::new("item", DataType::Float32, true)),
+                dimension as i32,
+            ),
+            false,
+        ),
+    ]))
+}
+
+/// The same list of tags for `rows` rows
+fn tag_lists(rows: usize, tags: &[String]) -> ListArray {
+    let mut builder = ListBuilder::new(StringBuilder::new());
+    for _ in 0..rows {
+        for tag in tags {
+            builder.values().append_value(tag);
+        }
+        builder.append(true);
+    }
+    builder.finish()
+}
+
+/// SQL string literal, with quotes escaped
+fn literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Filter matching rows whose `column` is one of `ids`
+fn id_filter(column: &str, ids: impl Iterator<Item = Uuid>) -> String {
+    let ids: Vec<String> = ids.map(|id| literal(&id.to_string())).collect();
+    format!("{} IN ({})", column, ids.join(", "))
+}
+
+/// Filter matching chunks of contexts carrying every one of `tags`
+fn tag_filter(tags: &[String]) -> String {
+    let tags: Vec<String> = tags.iter().map(|tag| literal(tag)).collect();
+    format!("array_has_all(tags, [{}])", tags.join(", "))
+}
+
+/// Chunks and their similarity from the rows of a search result
+fn from_batch(batch: &RecordBatch) -> McpResult<Vec<(ContextChunk, f32)>> {
+    let strings = |name: &str| -> McpResult<&StringArray> {
+        batch
+            .column_by_name(name)
+            .and_then(|column| column.as_any().downcast_ref::<StringArray>())
+            .ok_or_else(|| McpError::StorageError(format!("Missing column: {}", name)))
+    };
+    let chunk_ids = strings("chunk_id")?;
+    let context_ids = strings("context_id")?;
+    let contents = strings("content")?;
+    let positions = batch
+        .column_by_name("position")
+        .and_then(|column| column.as_any().downcast_ref::<UInt64Array>())
+        .ok_or_else(|| McpError::StorageError("Missing column: position".to_string()))?;
+    let vectors = batch
+        .column_by_name("vector")
+        .and_then(|column| column.as_any().downcast_ref::<FixedSizeListArray>())
+        .ok_or_else(|| McpError::StorageError("Missing column: vector".to_string()))?;
+    let distances = batch
+        .column_by_name("_distance")
+        .and_then(|column| column.as_any().downcast_ref::<Float32Array>())
+        .ok_or_else(|| McpError::StorageError("Missing column: _distance".to_string()))?;
+
+    let parse = |value: &str| {
+        Uuid::parse_str(value).map_err(|e| McpError::SerializationError(e.to_string()))
+    };
+
+    let mut results = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        let vector = vectors.value(row);
+        let embedding = vector
+            .as_any()
+            .downcast_ref::<Float32Array>()
+            .map(|values| values.values().to_vec());
+
+        let chunk = ContextChunk {
+            chunk_id: parse(chunk_ids.value(row))?,
+            context_id: parse(context_ids.value(row))?,
+            content: contents.value(row).to_string(),
+            position: positions.value(row) as usize,
+            embedding,
+        };
+        // Cosine distance, turned back into similarity
+        results.push((chunk, 1.0 - distances.value(row)));
+    }
+    Ok(results)
+}
+
+fn storage_error(err: impl std::fmt::Display) -> McpError {
+    McpError::StorageError(err.to_string())
+}
+
+#[async_trait]
+impl VectorStorePort for LanceVectorStore {
+    async fn upsert(&self, chunks: &[ContextChunk], tags: &[String]) -> McpResult<()> {
+        let chunks: Vec<&ContextChunk> = chunks
+            .iter()
+            .filter(|chunk| chunk.embedding.is_some())
+            .collect();
+        if chunks.is_empty() {
+            return Ok(());
+        }
+        let batch = self.to_batch(&chunks, tags)?;
+
+        // Earlier chunks of the same contexts must not linger next to the new ones
+        let mut context_ids: Vec<Uuid> = chunks.iter().map(|chunk| chunk.context_id).collect();
+        context_ids.sort();
+        context_ids.dedup();
+        self.table
+            .delete(&id_filter("context_id", context_ids.into_iter()))
+            .await
+            .map_err(storage_error)?;
+
+        let batches = RecordBatchIterator::new(vec![Ok(batch)], self.schema.clone());
+        self.table
+            .add(Box::new(batches))
+            .execute()
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn delete(&self, chunk_ids: &[Uuid]) -> McpResult<()> {
+        if chunk_ids.is_empty() {
+            return Ok(());
+        }
+        self.table
+            .delete(&id_filter("chunk_id", chunk_ids.iter().copied()))
+            .await
+            .map_err(storage_error)
+    }
+
+    async fn search(
+        &self,
+        query: &[f32],
+        tags: &[String],
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>> {
+        if query.len() != self.dimension {
+            return Err(McpError::dimension_mismatch(self.dimension, query.len()));
+        }
+
+        let mut search = self
+            .table
+            .query()
+            .nearest_to(query)
+            .map_err(storage_error)?
+            .distance_type(DistanceType::Cosine)
+            .limit(limit);
+        if !tags.is_empty() {
+            search = search.only_if(tag_filter(tags));
+        }
+
+        let batches: Vec<RecordBatch> = search
+            .execute()
+            .await
+            .map_err(storage_error)?
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+
+        let mut results = Vec::new();
+        for batch in &batches {
+            results.extend(from_batch(batch)?);
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("mcp-lance-{}", Uuid::new_v4())))
+        }
+
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn chunk(context_id: Uuid, content: &str, embedding: Vec<f32>) -> ContextChunk {
+        ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id,
+            content: content.to_string(),
+            position: 0,
+            embedding: Some(embedding),
+        }
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_upsert_after_update_drops_old_vectors() {
+        let dir = TempDir::new();
+        let store = LanceVectorStore::open(dir.path(), 2).await.unwrap();
+
+        let context_id = Uuid::new_v4();
+        let old = chunk(context_id, "old", vec![1.0, 0.0]);
+        store.upsert(&[old.clone()], &[]).await.unwrap();
+
+        let new = chunk(context_id, "new", vec![0.0, 1.0]);
+        store.upsert(&[new.clone()], &[]).await.unwrap();
+
+        let results = store.search(&[1.0, 0.0], &[], 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.chunk_id, new.chunk_id);
+        assert_eq!(results[0].0.content, "new");
+    }
+
+    #[tokio::test]
+    async fn test_chunks_survive_reopen() {
+        let dir = TempDir::new();
+        let (rust, python) = (Uuid::new_v4(), Uuid::new_v4());
+        let rust_chunk = chunk(rust, "rust", vec![1.0, 0.0]);
+        {
+            let store = LanceVectorStore::open(dir.path(), 2).await.unwrap();
+            store
+                .upsert(&[rust_chunk.clone()], &tags(&["rust", "programming"]))
+                .await
+                .unwrap();
+            store
+                .upsert(
+                    &[chunk(python, "python", vec![0.9, 0.1])],
+                    &tags(&["python", "programming"]),
+                )
+                .await
+                .unwrap();
+        }
+
+        let store = LanceVectorStore::open(dir.path(), 2).await.unwrap();
+        let results = store.search(&[1.0, 0.0], &[], 10).await.unwrap();
+        assert_eq!(results.len(), 2);
+        let (found, score) = &results[0];
+        assert_eq!(found.chunk_id, rust_chunk.chunk_id);
+        assert_eq!(found.content, "rust");
+        assert_eq!(found.embedding, rust_chunk.embedding);
+        assert!((score - 1.0).abs() < 1e-5);
+
+        // The tag filter is applied by the query
+        let results = store
+            .search(&[0.0, 1.0], &tags(&["python", "programming"]), 10)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.context_id, python);
+    }
+
+    #[tokio::test]
+    async fn test_deleted_chunks_are_not_found() {
+        let dir = TempDir::new();
+        let store = LanceVectorStore::open(dir.path(), 2).await.unwrap();
+
+        let kept = chunk(Uuid::new_v4(), "kept", vec![1.0, 0.0]);
+        let removed = chunk(Uuid::new_v4(), "removed", vec![1.0, 0.0]);
+        store.upsert(&[kept.clone()], &[]).await.unwrap();
+        store.upsert(&[removed.clone()], &[]).await.unwrap();
+
+        store.delete(&[removed.chunk_id]).await.unwrap();
+
+        let results = store.search(&[1.0, 0.0], &[], 10).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.chunk_id, kept.chunk_id);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_dimension_is_rejected() {
+        let dir = TempDir::new();
+        let store = LanceVectorStore::open(dir.path(), 2).await.unwrap();
+
+        let result = store
+            .upsert(&[chunk(Uuid::new_v4(), "text", vec![1.0, 0.0, 0.0])], &[])
+            .await;
+        assert!(matches!(result, Err(McpError::EmbeddingError(_))));
+        assert!(store.search(&[1.0], &[], 10).await.is_err());
+    }
+}
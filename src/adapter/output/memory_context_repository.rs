@@ -1,71 +1,606 @@
 use async_trait::async_trait;
-use std::collections::HashMap;
-use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
-use crate::domain::{Context, ContextChunk, McpError, McpResult};
+use super::write_ahead_log::{WalRecord, WriteAheadLog};
+use crate::domain::{
+    Collection, Context, ContextChunk, ContextFilter, ContextRevision, McpError, McpResult,
+};
 use crate::ports::out_ports::ContextRepositoryPort;
 
+/// What a repository with a capacity limit does when a new context doesn't fit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CapacityPolicy {
+    /// Evict the least recently accessed context and its chunks
+    EvictLeastRecent,
+    /// Reject the new context with `McpError::ContextLimitExceeded`
+    Reject,
+}
+
+/// Recency order of stored contexts, oldest access first
+#[derive(Debug, Default)]
+struct Recency {
+    clock: u64,
+    stamps: HashMap<Uuid, u64>,
+    order: BTreeMap<u64, Uuid>,
+}
+
+impl Recency {
+    fn touch(&mut self, context_id: Uuid) {
+        self.clock += 1;
+        if let Some(previous) = self.stamps.insert(context_id, self.clock) {
+            self.order.remove(&previous);
+        }
+        self.order.insert(self.clock, context_id);
+    }
+
+    fn remove(&mut self, context_id: Uuid) {
+        if let Some(stamp) = self.stamps.remove(&context_id) {
+            self.order.remove(&stamp);
+        }
+    }
+
+    fn least_recent(&self) -> Option<Uuid> {
+        self.order.values().next().copied()
+    }
+}
+
 /// In-memory implementation of the context repository
 /// Used for testing and as a simple reference implementation
+///
+/// Uses async locks so readers proceed concurrently without blocking runtime threads, and a
+/// panicking task can't poison the maps for every later request.
 pub struct InMemoryContextRepository {
-    contexts: Mutex<HashMap<Uuid, Context>>,
-    chunks: Mutex<HashMap<Uuid, Vec<ContextChunk>>>,
+    contexts: RwLock<HashMap<Uuid, Context>>,
+    // Locked after `contexts` and before `chunks`
+    recency: Mutex<Recency>,
+    chunks: RwLock<ChunkMap>,
+    // Locked after `chunks`, keyed by context and then version
+    revisions: RwLock<HashMap<Uuid, BTreeMap<u64, ContextRevision>>>,
+    // Never locked together with the maps above
+    collections: RwLock<HashMap<Uuid, Collection>>,
+    // Locked after `contexts`, `chunks`, `revisions` and `collections` to keep a consistent
+    // lock order
+    wal: Option<Mutex<WriteAheadLog>>,
+    capacity: Option<(usize, CapacityPolicy)>,
+    // Chunk IDs of evicted contexts not yet taken, locked after `chunks`
+    evicted_chunk_ids: Mutex<Vec<Uuid>>,
 }
 
 impl InMemoryContextRepository {
     pub fn new() -> Self {
         Self {
-            contexts: Mutex::new(HashMap::new()),
-            chunks: Mutex::new(HashMap::new()),
+            contexts: RwLock::new(HashMap::new()),
+            recency: Mutex::new(Recency::default()),
+            chunks: RwLock::new(ChunkMap::default()),
+            revisions: RwLock::new(HashMap::new()),
+            collections: RwLock::new(HashMap::new()),
+            wal: None,
+            capacity: None,
+            evicted_chunk_ids: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Create a repository holding at most `max_contexts` contexts
+    pub fn with_capacity(max_contexts: usize, policy: CapacityPolicy) -> Self {
+        let mut repository = Self::new();
+        repository.limit_capacity(max_contexts, policy);
+        repository
+    }
+
+    /// Limit the repository to `max_contexts` contexts from now on
+    pub fn limit_capacity(&mut self, max_contexts: usize, policy: CapacityPolicy) {
+        self.capacity = Some((max_contexts, policy));
+    }
+
+    /// Create a repository backed by a write-ahead log, replaying any existing log
+    /// The log is compacted once it grows past `compact_threshold` bytes
+    pub fn with_wal(path: impl AsRef<Path>, compact_threshold: u64) -> McpResult<Self> {
+        let (wal, records) = WriteAheadLog::open(path, compact_threshold)?;
+
+        let mut contexts = HashMap::new();
+        let mut recency = Recency::default();
+        let mut chunks = ChunkMap::default();
+        let mut revisions = HashMap::new();
+        let mut collections = HashMap::new();
+        for record in records {
+            match record {
+                WalRecord::SaveContext(context) | WalRecord::UpdateContext(context) => {
+                    recency.touch(context.id);
+                    contexts.insert(context.id, context);
+                }
+                WalRecord::DeleteContext(context_id) => {
+                    recency.remove(context_id);
+                    contexts.remove(&context_id);
+                    revisions.remove(&context_id);
+                }
+                WalRecord::SaveChunks {
+                    context_id,
+                    chunks: saved,
+                } => {
+                    chunks.insert(context_id, saved);
+                }
+                WalRecord::DeleteChunks(context_id) => {
+                    chunks.remove(&context_id);
+                }
+                WalRecord::ContextWithChunks {
+                    context,
+                    chunks: saved,
+                } => {
+                    recency.touch(context.id);
+                    chunks.replace(context.id, saved);
+                    contexts.insert(context.id, context);
+                }
+                WalRecord::Revisions {
+                    context_id,
+                    revisions: kept,
+                } => {
+                    revisions.insert(context_id, revision_map(kept));
+                }
+                WalRecord::SaveCollection(collection) => {
+                    collections.insert(collection.id, collection);
+                }
+                WalRecord::DeleteCollection(collection_id) => {
+                    collections.remove(&collection_id);
+                }
+            }
+        }
+
+        Ok(Self {
+            contexts: RwLock::new(contexts),
+            recency: Mutex::new(recency),
+            chunks: RwLock::new(chunks),
+            revisions: RwLock::new(revisions),
+            collections: RwLock::new(collections),
+            wal: Some(Mutex::new(wal)),
+            capacity: None,
+            evicted_chunk_ids: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Rewrite the write-ahead log as a snapshot of the current state
+    pub async fn compact(&self) -> McpResult<()> {
+        let Some(wal) = &self.wal else {
+            return Ok(());
+        };
+
+        let contexts = self.contexts.read().await;
+        let chunks = self.chunks.read().await;
+        let revisions = self.revisions.read().await;
+        let collections = self.collections.read().await;
+        let mut wal = wal.lock().await;
+
+        let records = contexts
+            .values()
+            .cloned()
+            .map(WalRecord::SaveContext)
+            .chain(
+                chunks
+                    .by_context
+                    .iter()
+                    .map(|(context_id, chunks)| WalRecord::SaveChunks {
+                        context_id: *context_id,
+                        chunks: chunks.clone(),
+                    }),
+            )
+            .chain(
+                revisions
+                    .iter()
+                    .map(|(context_id, kept)| WalRecord::Revisions {
+                        context_id: *context_id,
+                        revisions: kept.values().cloned().collect(),
+                    }),
+            )
+            .chain(collections.values().cloned().map(WalRecord::SaveCollection));
+
+        wal.rewrite(records)
+    }
+
+    /// Record a mutation before it is applied, returning whether compaction is due
+    async fn log(&self, record: WalRecord) -> McpResult<bool> {
+        match &self.wal {
+            Some(wal) => {
+                let mut wal = wal.lock().await;
+                wal.append(&record)?;
+                Ok(wal.needs_compaction())
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Make room for one more context, returning whether compaction is due
+    async fn ensure_capacity(
+        &self,
+        contexts: &mut HashMap<Uuid, Context>,
+        recency: &mut Recency,
+        chunks: &mut ChunkMap,
+    ) -> McpResult<bool> {
+        let Some((max_contexts, policy)) = self.capacity else {
+            return Ok(false);
+        };
+
+        let mut compact = false;
+        while contexts.len() >= max_contexts {
+            let evicted = match (policy, recency.least_recent()) {
+                (CapacityPolicy::EvictLeastRecent, Some(context_id)) => context_id,
+                _ => return Err(McpError::ContextLimitExceeded),
+            };
+
+            // Evicted contexts take their chunks with them, and their embeddings once the
+            // chunk IDs are taken
+            compact |= self.log(WalRecord::DeleteContext(evicted)).await?;
+            compact |= self.log(WalRecord::DeleteChunks(evicted)).await?;
+            if let Some(evicted_chunks) = chunks.get(&evicted) {
+                self.evicted_chunk_ids
+                    .lock()
+                    .await
+                    .extend(evicted_chunks.iter().map(|chunk| chunk.chunk_id));
+            }
+            contexts.remove(&evicted);
+            recency.remove(evicted);
+            chunks.remove(&evicted);
+            self.revisions.write().await.remove(&evicted);
+        }
+
+        Ok(compact)
+    }
+
+    /// Store a context and its chunks under locks already held, returning whether compaction is due
+    async fn write_context_with_chunks(
+        &self,
+        contexts: &mut HashMap<Uuid, Context>,
+        recency: &mut Recency,
+        chunks_map: &mut ChunkMap,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<bool> {
+        // One record, so a crash can't replay the context without its chunks
+        let compact = self
+            .log(WalRecord::ContextWithChunks {
+                context: context.clone(),
+                chunks: chunks.clone(),
+            })
+            .await?;
+
+        recency.touch(context.id);
+        chunks_map.replace(context.id, chunks);
+        contexts.insert(context.id, context);
+
+        Ok(compact)
+    }
+
+    /// Compact the log if the last mutation pushed it past its threshold
+    async fn compact_if_needed(&self, needed: bool) -> McpResult<()> {
+        if needed {
+            self.compact().await?;
         }
+        Ok(())
     }
 }
 
+/// Chunks grouped by context, indexed by chunk ID so single chunks are found without a scan
+#[derive(Debug, Default)]
+struct ChunkMap {
+    by_context: HashMap<Uuid, Vec<ContextChunk>>,
+    // Context and index in its chunk list of every stored chunk
+    locations: HashMap<Uuid, (Uuid, usize)>,
+}
+
+impl ChunkMap {
+    fn get(&self, context_id: &Uuid) -> Option<&Vec<ContextChunk>> {
+        self.by_context.get(context_id)
+    }
+
+    fn find(&self, chunk_id: &Uuid) -> Option<&ContextChunk> {
+        let (context_id, index) = self.locations.get(chunk_id)?;
+        self.by_context.get(context_id)?.get(*index)
+    }
+
+    /// Store the chunks of a context in place of the ones it had
+    fn insert(&mut self, context_id: Uuid, chunks: Vec<ContextChunk>) {
+        self.remove(&context_id);
+        for (index, chunk) in chunks.iter().enumerate() {
+            self.locations.insert(chunk.chunk_id, (context_id, index));
+        }
+        self.by_context.insert(context_id, chunks);
+    }
+
+    /// Replace the chunks of a context, leaving no entry when it has none
+    fn replace(&mut self, context_id: Uuid, chunks: Vec<ContextChunk>) {
+        if chunks.is_empty() {
+            self.remove(&context_id);
+        } else {
+            self.insert(context_id, chunks);
+        }
+    }
+
+    fn remove(&mut self, context_id: &Uuid) {
+        if let Some(chunks) = self.by_context.remove(context_id) {
+            for chunk in &chunks {
+                self.locations.remove(&chunk.chunk_id);
+            }
+        }
+    }
+}
+
+/// Revisions keyed by version
+fn revision_map(revisions: Vec<ContextRevision>) -> BTreeMap<u64, ContextRevision> {
+    revisions
+        .into_iter()
+        .map(|revision| (revision.version, revision))
+        .collect()
+}
+
+/// A page of contexts in listing order, so pages don't depend on the map's iteration order
+fn page<'a>(
+    contexts: impl Iterator<Item = &'a Context>,
+    limit: usize,
+    offset: usize,
+) -> Vec<Context> {
+    let mut contexts: Vec<&Context> = contexts.collect();
+    contexts.sort_by(|a, b| a.listing_order(b));
+    contexts
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .cloned()
+        .collect()
+}
+
 #[async_trait]
 impl ContextRepositoryPort for InMemoryContextRepository {
     async fn save_context(&self, context: Context) -> McpResult<Context> {
-        let mut contexts = self.contexts.lock().unwrap();
+        let mut contexts = self.contexts.write().await;
         let context_id = context.id;
 
         if contexts.contains_key(&context_id) {
             return Err(McpError::ContextAlreadyExists(context_id));
         }
 
+        let mut recency = self.recency.lock().await;
+        let mut chunks = self.chunks.write().await;
+        let evicted = self
+            .ensure_capacity(&mut contexts, &mut recency, &mut chunks)
+            .await?;
+        drop(chunks);
+
+        let compact = self.log(WalRecord::SaveContext(context.clone())).await? || evicted;
         contexts.insert(context_id, context.clone());
+        recency.touch(context_id);
+        drop(recency);
+        drop(contexts);
+
+        self.compact_if_needed(compact).await?;
         Ok(context)
     }
 
     async fn find_by_id(&self, context_id: Uuid) -> McpResult<Context> {
-        let contexts = self.contexts.lock().unwrap();
+        let contexts = self.contexts.read().await;
 
-        contexts
+        let context = contexts
             .get(&context_id)
             .cloned()
-            .ok_or_else(|| McpError::ContextNotFound(context_id))
+            .ok_or_else(|| McpError::ContextNotFound(context_id))?;
+        self.recency.lock().await.touch(context_id);
+
+        Ok(context)
     }
 
-    async fn update(&self, context: Context) -> McpResult<Context> {
-        let mut contexts = self.contexts.lock().unwrap();
+    async fn find_by_ids(&self, context_ids: &[Uuid]) -> McpResult<Vec<Context>> {
+        let contexts = self.contexts.read().await;
+        let mut recency = self.recency.lock().await;
+
+        Ok(context_ids
+            .iter()
+            .filter_map(|context_id| contexts.get(context_id))
+            .inspect(|context| recency.touch(context.id))
+            .cloned()
+            .collect())
+    }
+
+    async fn update(
+        &self,
+        mut context: Context,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let mut contexts = self.contexts.write().await;
         let context_id = context.id;
 
-        if !contexts.contains_key(&context_id) {
-            return Err(McpError::ContextNotFound(context_id));
-        }
+        let stored = contexts
+            .get(&context_id)
+            .ok_or(McpError::ContextNotFound(context_id))?;
+        stored.check_version(expected_version)?;
+        context.version = stored.version + 1;
 
+        let compact = self.log(WalRecord::UpdateContext(context.clone())).await?;
         contexts.insert(context_id, context.clone());
+        self.recency.lock().await.touch(context_id);
+        drop(contexts);
+
+        self.compact_if_needed(compact).await?;
+        Ok(context)
+    }
+
+    async fn save_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context> {
+        let mut contexts = self.contexts.write().await;
+        if contexts.contains_key(&context.id) {
+            return Err(McpError::ContextAlreadyExists(context.id));
+        }
+
+        let mut recency = self.recency.lock().await;
+        let mut chunks_map = self.chunks.write().await;
+        let evicted = self
+            .ensure_capacity(&mut contexts, &mut recency, &mut chunks_map)
+            .await?;
+
+        let compact = self
+            .write_context_with_chunks(
+                &mut contexts,
+                &mut recency,
+                &mut chunks_map,
+                context.clone(),
+                chunks,
+            )
+            .await?
+            || evicted;
+        drop(chunks_map);
+        drop(recency);
+        drop(contexts);
+
+        self.compact_if_needed(compact).await?;
+        Ok(context)
+    }
+
+    async fn replace_context_with_chunks(
+        &self,
+        mut context: Context,
+        chunks: Vec<ContextChunk>,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let mut contexts = self.contexts.write().await;
+        let stored = contexts
+            .get(&context.id)
+            .ok_or(McpError::ContextNotFound(context.id))?;
+        stored.check_version(expected_version)?;
+        context.version = stored.version + 1;
+
+        let mut recency = self.recency.lock().await;
+        let mut chunks_map = self.chunks.write().await;
+        let compact = self
+            .write_context_with_chunks(
+                &mut contexts,
+                &mut recency,
+                &mut chunks_map,
+                context.clone(),
+                chunks,
+            )
+            .await?;
+        drop(chunks_map);
+        drop(recency);
+        drop(contexts);
+
+        self.compact_if_needed(compact).await?;
         Ok(context)
     }
 
     async fn delete(&self, context_id: Uuid) -> McpResult<()> {
-        let mut contexts = self.contexts.lock().unwrap();
+        let mut contexts = self.contexts.write().await;
 
         if !contexts.contains_key(&context_id) {
             return Err(McpError::ContextNotFound(context_id));
         }
 
+        let compact = self.log(WalRecord::DeleteContext(context_id)).await?;
         contexts.remove(&context_id);
-        Ok(())
+        self.recency.lock().await.remove(context_id);
+        self.revisions.write().await.remove(&context_id);
+        drop(contexts);
+
+        self.compact_if_needed(compact).await
+    }
+
+    async fn save_revision(
+        &self,
+        revision: ContextRevision,
+        max_revisions: usize,
+    ) -> McpResult<()> {
+        let mut revisions = self.revisions.write().await;
+        let context_id = revision.context_id;
+
+        let mut kept = revisions.get(&context_id).cloned().unwrap_or_default();
+        kept.insert(revision.version, revision);
+        while kept.len() > max_revisions {
+            kept.pop_first();
+        }
+
+        let compact = self
+            .log(WalRecord::Revisions {
+                context_id,
+                revisions: kept.values().cloned().collect(),
+            })
+            .await?;
+        if kept.is_empty() {
+            revisions.remove(&context_id);
+        } else {
+            revisions.insert(context_id, kept);
+        }
+        drop(revisions);
+
+        self.compact_if_needed(compact).await
+    }
+
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>> {
+        let revisions = self.revisions.read().await;
+
+        Ok(revisions
+            .get(&context_id)
+            .map(|kept| kept.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision> {
+        let revisions = self.revisions.read().await;
+
+        revisions
+            .get(&context_id)
+            .and_then(|kept| kept.get(&version))
+            .cloned()
+            .ok_or(McpError::RevisionNotFound {
+                context_id,
+                version,
+            })
+    }
+
+    async fn save_collection(&self, collection: Collection) -> McpResult<Collection> {
+        let mut collections = self.collections.write().await;
+        if collections
+            .values()
+            .any(|other| other.name == collection.name && other.id != collection.id)
+        {
+            return Err(McpError::CollectionExists(collection.name));
+        }
+
+        let compact = self
+            .log(WalRecord::SaveCollection(collection.clone()))
+            .await?;
+        collections.insert(collection.id, collection.clone());
+        drop(collections);
+
+        self.compact_if_needed(compact).await?;
+        Ok(collection)
+    }
+
+    async fn find_collection(&self, collection_id: Uuid) -> McpResult<Collection> {
+        self.collections
+            .read()
+            .await
+            .get(&collection_id)
+            .cloned()
+            .ok_or(McpError::CollectionNotFound(collection_id))
+    }
+
+    async fn list_collections(&self) -> McpResult<Vec<Collection>> {
+        Ok(self.collections.read().await.values().cloned().collect())
+    }
+
+    async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()> {
+        let mut collections = self.collections.write().await;
+        if !collections.contains_key(&collection_id) {
+            return Err(McpError::CollectionNotFound(collection_id));
+        }
+
+        let compact = self.log(WalRecord::DeleteCollection(collection_id)).await?;
+        collections.remove(&collection_id);
+        drop(collections);
+
+        self.compact_if_needed(compact).await
     }
 
     async fn find_by_tags(
@@ -74,30 +609,102 @@ impl ContextRepositoryPort for InMemoryContextRepository {
         limit: usize,
         offset: usize,
     ) -> McpResult<Vec<Context>> {
-        let contexts = self.contexts.lock().unwrap();
+        let contexts = self.contexts.read().await;
+
+        Ok(page(
+            contexts
+                .values()
+                .filter(|context| tags.iter().all(|tag| context.metadata.tags.contains(tag))),
+            limit,
+            offset,
+        ))
+    }
+
+    async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>> {
+        let contexts = self.contexts.read().await;
+
+        Ok(page(contexts.values(), limit, offset))
+    }
+
+    async fn count_all(&self) -> McpResult<usize> {
+        Ok(self.contexts.read().await.len())
+    }
+
+    async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize> {
+        let contexts = self.contexts.read().await;
 
-        let matching_contexts: Vec<Context> = contexts
+        Ok(contexts
             .values()
             .filter(|context| tags.iter().all(|tag| context.metadata.tags.contains(tag)))
-            .cloned()
-            .skip(offset)
-            .take(limit)
-            .collect();
+            .count())
+    }
+
+    async fn find_filtered(
+        &self,
+        filter: &ContextFilter,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        let contexts = self.contexts.read().await;
 
-        Ok(matching_contexts)
+        Ok(page(
+            contexts.values().filter(|context| filter.matches(context)),
+            limit,
+            offset,
+        ))
     }
 
-    async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>> {
-        let contexts = self.contexts.lock().unwrap();
+    async fn count_filtered(&self, filter: &ContextFilter) -> McpResult<usize> {
+        let contexts = self.contexts.read().await;
 
-        let all_contexts: Vec<Context> = contexts
+        Ok(contexts
             .values()
+            .filter(|context| filter.matches(context))
+            .count())
+    }
+
+    async fn tag_counts(&self, now: DateTime<Utc>) -> McpResult<Vec<(String, usize)>> {
+        let contexts = self.contexts.read().await;
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for tag in contexts
+            .values()
+            .filter(|context| context.is_live_at(now))
+            .flat_map(|context| &context.metadata.tags)
+        {
+            *counts.entry(tag.clone()).or_default() += 1;
+        }
+        Ok(counts.into_iter().collect())
+    }
+
+    async fn find_by_content_hash(&self, content_hash: &str) -> McpResult<Vec<Context>> {
+        let contexts = self.contexts.read().await;
+
+        Ok(contexts
+            .values()
+            .filter(|context| context.metadata.content_hash.as_deref() == Some(content_hash))
             .cloned()
-            .skip(offset)
-            .take(limit)
-            .collect();
+            .collect())
+    }
+
+    async fn find_related_to(&self, target_id: Uuid) -> McpResult<Vec<Context>> {
+        let contexts = self.contexts.read().await;
+
+        Ok(contexts
+            .values()
+            .filter(|context| {
+                context
+                    .relations
+                    .iter()
+                    .any(|relation| relation.target_id == target_id)
+            })
+            .cloned()
+            .collect())
+    }
 
-        Ok(all_contexts)
+    async fn exists(&self, context_id: Uuid) -> McpResult<bool> {
+        // Checking for a context doesn't count as reading it, so recency is left alone
+        Ok(self.contexts.read().await.contains_key(&context_id))
     }
 
     async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>> {
@@ -106,16 +713,24 @@ impl ContextRepositoryPort for InMemoryContextRepository {
         }
 
         let context_id = chunks[0].context_id;
-        let mut chunks_map = self.chunks.lock().unwrap();
+        let mut chunks_map = self.chunks.write().await;
 
         // Store chunks by context ID
+        let compact = self
+            .log(WalRecord::SaveChunks {
+                context_id,
+                chunks: chunks.clone(),
+            })
+            .await?;
         chunks_map.insert(context_id, chunks.clone());
+        drop(chunks_map);
 
+        self.compact_if_needed(compact).await?;
         Ok(chunks)
     }
 
     async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>> {
-        let chunks_map = self.chunks.lock().unwrap();
+        let chunks_map = self.chunks.read().await;
 
         chunks_map
             .get(&context_id)
@@ -123,9 +738,668 @@ impl ContextRepositoryPort for InMemoryContextRepository {
             .ok_or_else(|| McpError::ContextNotFound(context_id))
     }
 
+    async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk> {
+        self.chunks
+            .read()
+            .await
+            .find(&chunk_id)
+            .cloned()
+            .ok_or(McpError::ChunkNotFound(chunk_id))
+    }
+
+    async fn take_evicted_chunk_ids(&self) -> McpResult<Vec<Uuid>> {
+        Ok(std::mem::take(&mut *self.evicted_chunk_ids.lock().await))
+    }
+
     async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()> {
-        let mut chunks_map = self.chunks.lock().unwrap();
+        let mut chunks_map = self.chunks.write().await;
+
+        let compact = self.log(WalRecord::DeleteChunks(context_id)).await?;
         chunks_map.remove(&context_id);
-        Ok(())
+        drop(chunks_map);
+
+        self.compact_if_needed(compact).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ContextMetadata, TagMode};
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("mcp-wal-{}", Uuid::new_v4())))
+        }
+
+        fn wal_path(&self) -> PathBuf {
+            self.0.join("contexts.wal")
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn create_test_context(index: usize) -> Context {
+        Context::new(
+            format!("WAL test content {}\nwith a second line", index),
+            ContextMetadata {
+                tags: vec![format!("tag{}", index % 5)],
+                ..Default::default()
+            },
+        )
+    }
+
+    fn create_test_chunks(context_id: Uuid, count: usize) -> Vec<ContextChunk> {
+        (0..count)
+            .map(|position| ContextChunk {
+                context_id,
+                chunk_id: Uuid::new_v4(),
+                content: format!("chunk {}", position),
+                embedding: Some(vec![position as f32, 0.5]),
+                position,
+            })
+            .collect()
+    }
+
+    /// Serialized view of the repository maps, for comparing state
+    async fn snapshot(
+        repository: &InMemoryContextRepository,
+    ) -> (
+        BTreeMap<Uuid, serde_json::Value>,
+        BTreeMap<Uuid, serde_json::Value>,
+    ) {
+        let contexts = repository.contexts.read().await;
+        let chunks = repository.chunks.read().await;
+
+        (
+            contexts
+                .iter()
+                .map(|(id, context)| (*id, serde_json::to_value(context).unwrap()))
+                .collect(),
+            chunks
+                .by_context
+                .iter()
+                .map(|(id, chunks)| (*id, serde_json::to_value(chunks).unwrap()))
+                .collect(),
+        )
+    }
+
+    /// Apply a deterministic mix of saves, updates, deletes, and chunk writes
+    async fn apply_mixed_operations(repository: &InMemoryContextRepository, count: usize) {
+        let mut live: Vec<Uuid> = Vec::new();
+
+        for i in 0..count {
+            match i % 6 {
+                0 | 1 => {
+                    let context = repository
+                        .save_context(create_test_context(i))
+                        .await
+                        .unwrap();
+                    live.push(context.id);
+                }
+                2 if !live.is_empty() => {
+                    let mut context = repository.find_by_id(live[i % live.len()]).await.unwrap();
+                    context.content = format!("updated {}", i);
+                    repository.update(context, None).await.unwrap();
+                }
+                3 if !live.is_empty() => {
+                    let context_id = live[i % live.len()];
+                    repository
+                        .save_chunks(create_test_chunks(context_id, 1 + i % 3))
+                        .await
+                        .unwrap();
+                }
+                4 if live.len() > 2 => {
+                    let context_id = live.remove(i % live.len());
+                    repository.delete(context_id).await.unwrap();
+                    repository
+                        .delete_chunks_by_context_id(context_id)
+                        .await
+                        .unwrap();
+                }
+                _ => {
+                    let context = repository
+                        .save_context(create_test_context(i))
+                        .await
+                        .unwrap();
+                    live.push(context.id);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replay_rebuilds_state() {
+        let dir = TempDir::new();
+
+        let original = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        apply_mixed_operations(&original, 600).await;
+        let expected = snapshot(&original).await;
+        assert!(!expected.0.is_empty());
+        assert!(!expected.1.is_empty());
+        drop(original);
+
+        let replayed = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert_eq!(snapshot(&replayed).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_preserves_state() {
+        let dir = TempDir::new();
+
+        // A small threshold forces several automatic compactions
+        let original = InMemoryContextRepository::with_wal(dir.wal_path(), 16 * 1024).unwrap();
+        apply_mixed_operations(&original, 400).await;
+        let expected = snapshot(&original).await;
+
+        let size_before = std::fs::metadata(dir.wal_path()).unwrap().len();
+        original.compact().await.unwrap();
+        let size_after = std::fs::metadata(dir.wal_path()).unwrap().len();
+        assert!(size_after <= size_before);
+        drop(original);
+
+        let replayed = InMemoryContextRepository::with_wal(dir.wal_path(), 16 * 1024).unwrap();
+        assert_eq!(snapshot(&replayed).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_final_record_is_skipped() {
+        let dir = TempDir::new();
+
+        let original = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        apply_mixed_operations(&original, 50).await;
+        let expected = snapshot(&original).await;
+        drop(original);
+
+        // Simulate a crash halfway through writing one more record
+        let record = serde_json::to_vec(&WalRecord::SaveContext(create_test_context(0))).unwrap();
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.wal_path())
+            .unwrap();
+        std::io::Write::write_all(&mut file, &record[..record.len() / 2]).unwrap();
+        drop(file);
+
+        let replayed = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert_eq!(snapshot(&replayed).await, expected);
+
+        // New writes after recovery replay cleanly
+        let context = replayed.save_context(create_test_context(1)).await.unwrap();
+        drop(replayed);
+
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert!(reopened.find_by_id(context.id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_counts_follow_store_and_delete() {
+        let repo = InMemoryContextRepository::new();
+        let mut ids = Vec::new();
+        for i in 0..10 {
+            ids.push(repo.save_context(create_test_context(i)).await.unwrap().id);
+        }
+
+        let tag = |index: usize| vec![format!("tag{}", index)];
+        assert_eq!(repo.count_all().await.unwrap(), 10);
+        assert_eq!(repo.count_by_tags(&tag(0)).await.unwrap(), 2);
+        assert_eq!(repo.count_by_tags(&[]).await.unwrap(), 10);
+        assert_eq!(
+            repo.count_by_tags(&["tag0".to_string(), "tag1".to_string()])
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Counts agree with what the unpaginated listings return
+        assert_eq!(
+            repo.find_by_tags(&tag(3), usize::MAX, 0)
+                .await
+                .unwrap()
+                .len(),
+            repo.count_by_tags(&tag(3)).await.unwrap()
+        );
+
+        repo.delete(ids[0]).await.unwrap();
+        repo.delete(ids[5]).await.unwrap();
+        assert_eq!(repo.count_all().await.unwrap(), 8);
+        assert_eq!(repo.count_by_tags(&tag(0)).await.unwrap(), 0);
+        assert_eq!(repo.count_by_tags(&tag(1)).await.unwrap(), 2);
+
+        // Tags no context carries anymore aren't counted
+        let mut counts = repo.tag_counts(Utc::now()).await.unwrap();
+        counts.sort();
+        let expected: Vec<(String, usize)> =
+            (1..5).map(|index| (format!("tag{}", index), 2)).collect();
+        assert_eq!(counts, expected);
+
+        assert!(repo.exists(ids[1]).await.unwrap());
+        assert!(!repo.exists(ids[0]).await.unwrap());
+        assert!(!repo.exists(Uuid::new_v4()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_date_range_includes_after_and_excludes_before() {
+        let repo = InMemoryContextRepository::new();
+        let day = |day: u32| Utc.with_ymd_and_hms(2024, 3, day, 0, 0, 0).unwrap();
+
+        // Contexts created at midnight on the 1st to the 5th, each tagged with its day mod 5
+        let mut ids = Vec::new();
+        for i in 1..=5 {
+            let mut context = create_test_context(i);
+            context.created_at = day(i as u32);
+            ids.push(repo.save_context(context).await.unwrap().id);
+        }
+
+        let filter = ContextFilter {
+            created_after: Some(day(2)),
+            created_before: Some(day(4)),
+            ..ContextFilter::default()
+        };
+        let mut found: Vec<Uuid> = repo
+            .find_filtered(&filter, 10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|context| context.id)
+            .collect();
+        found.sort();
+        let mut expected = vec![ids[1], ids[2]];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(repo.count_filtered(&filter).await.unwrap(), 2);
+
+        // Either bound on its own, combined with tags, and paged
+        let after = ContextFilter {
+            created_after: Some(day(5)),
+            ..ContextFilter::default()
+        };
+        assert_eq!(repo.count_filtered(&after).await.unwrap(), 1);
+        let before = ContextFilter {
+            created_before: Some(day(1)),
+            ..ContextFilter::default()
+        };
+        assert_eq!(repo.count_filtered(&before).await.unwrap(), 0);
+
+        let tagged = ContextFilter {
+            created_after: Some(day(2)),
+            ..ContextFilter::tagged(vec!["tag3".to_string()])
+        };
+        let found = repo.find_filtered(&tagged, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, ids[2]);
+
+        let everything = ContextFilter::default();
+        assert_eq!(
+            repo.find_filtered(&everything, 2, 4).await.unwrap().len(),
+            1
+        );
+        assert_eq!(repo.count_filtered(&everything).await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_any_tag_mode_returns_the_union() {
+        let repo = InMemoryContextRepository::new();
+        for i in 0..4 {
+            repo.save_context(create_test_context(i)).await.unwrap();
+        }
+
+        let tags = vec!["tag1".to_string(), "tag3".to_string()];
+        let any = ContextFilter {
+            tag_mode: TagMode::Any,
+            ..ContextFilter::tagged(tags.clone())
+        };
+        let found = repo.find_filtered(&any, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 2);
+        assert!(found
+            .iter()
+            .all(|context| tags.contains(&context.metadata.tags[0])));
+        assert_eq!(repo.count_filtered(&any).await.unwrap(), 2);
+
+        // No context has both
+        let all = ContextFilter::tagged(tags);
+        assert_eq!(repo.count_filtered(&all).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_excluded_tags_win_over_required_ones() {
+        let repo = InMemoryContextRepository::new();
+        let mut ids = Vec::new();
+        for tags in [
+            vec!["runbook"],
+            vec!["runbook", "archived"],
+            vec!["Archived"],
+        ] {
+            let mut context = create_test_context(0);
+            context.metadata.tags = tags.into_iter().map(String::from).collect();
+            ids.push(repo.save_context(context).await.unwrap().id);
+        }
+
+        let filter = ContextFilter {
+            exclude_tags: vec!["archived".to_string()],
+            ..ContextFilter::tagged(vec!["runbook".to_string()])
+        };
+        let found = repo.find_filtered(&filter, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, ids[0]);
+
+        // Tags are compared exactly; normalizing their case is up to the caller
+        let filter = ContextFilter {
+            exclude_tags: vec!["archived".to_string()],
+            ..ContextFilter::default()
+        };
+        let mut found: Vec<Uuid> = repo
+            .find_filtered(&filter, 10, 0)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|context| context.id)
+            .collect();
+        found.sort();
+        let mut expected = vec![ids[0], ids[2]];
+        expected.sort();
+        assert_eq!(found, expected);
+        assert_eq!(repo.count_filtered(&filter).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_found_by_id() {
+        let dir = TempDir::new();
+        let repository = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        let context = create_test_context(0);
+        let chunks = create_test_chunks(context.id, 3);
+        repository
+            .save_context_with_chunks(context.clone(), chunks.clone())
+            .await
+            .unwrap();
+
+        let found = repository
+            .find_chunk_by_id(chunks[1].chunk_id)
+            .await
+            .unwrap();
+        assert_eq!(found.context_id, context.id);
+        assert_eq!(found.position, 1);
+        assert_eq!(found.content, "chunk 1");
+
+        let missing = Uuid::new_v4();
+        assert!(matches!(
+            repository.find_chunk_by_id(missing).await,
+            Err(McpError::ChunkNotFound(id)) if id == missing
+        ));
+
+        // Replacing the chunks leaves the old IDs stale
+        let replaced = create_test_chunks(context.id, 1);
+        repository
+            .replace_context_with_chunks(context.clone(), replaced.clone(), None)
+            .await
+            .unwrap();
+        assert!(repository
+            .find_chunk_by_id(chunks[0].chunk_id)
+            .await
+            .is_err());
+
+        // The index is rebuilt when the log is replayed
+        drop(repository);
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert_eq!(
+            reopened
+                .find_chunk_by_id(replaced[0].chunk_id)
+                .await
+                .unwrap()
+                .context_id,
+            context.id
+        );
+        reopened
+            .delete_chunks_by_context_id(context.id)
+            .await
+            .unwrap();
+        assert!(reopened
+            .find_chunk_by_id(replaced[0].chunk_id)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stale_version_loses_the_update() {
+        let repository = InMemoryContextRepository::new();
+        let context = repository
+            .save_context(create_test_context(0))
+            .await
+            .unwrap();
+        assert_eq!(context.version, 1);
+
+        let mut first = context.clone();
+        first.content = "first writer".to_string();
+        let first = repository.update(first, Some(1)).await.unwrap();
+        assert_eq!(first.version, 2);
+
+        // The second writer read the same version, so its update is refused
+        let mut second = context.clone();
+        second.content = "second writer".to_string();
+        assert!(matches!(
+            repository.update(second.clone(), Some(1)).await,
+            Err(McpError::VersionConflict {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+        assert!(matches!(
+            repository
+                .replace_context_with_chunks(second, vec![], Some(1))
+                .await,
+            Err(McpError::VersionConflict { .. })
+        ));
+
+        let stored = repository.find_by_id(context.id).await.unwrap();
+        assert_eq!(stored.content, "first writer");
+        assert_eq!(stored.version, 2);
+    }
+
+    #[tokio::test]
+    async fn test_capacity_evicts_least_recently_accessed() {
+        let repository =
+            InMemoryContextRepository::with_capacity(3, CapacityPolicy::EvictLeastRecent);
+
+        let mut ids = Vec::new();
+        for index in 0..3 {
+            let context = repository
+                .save_context(create_test_context(index))
+                .await
+                .unwrap();
+            repository
+                .save_chunks(create_test_chunks(context.id, 2))
+                .await
+                .unwrap();
+            ids.push(context.id);
+        }
+
+        // Reading the oldest context makes the second one least recent
+        repository.find_by_id(ids[0]).await.unwrap();
+
+        let newest = repository
+            .save_context(create_test_context(3))
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository.find_by_id(ids[1]).await,
+            Err(McpError::ContextNotFound(_))
+        ));
+        assert!(repository.find_chunks_by_context_id(ids[1]).await.is_err());
+        assert_eq!(repository.take_evicted_chunk_ids().await.unwrap().len(), 2);
+        assert!(repository
+            .take_evicted_chunk_ids()
+            .await
+            .unwrap()
+            .is_empty());
+
+        // The next eviction takes the oldest remaining untouched context
+        repository
+            .save_context(create_test_context(4))
+            .await
+            .unwrap();
+        assert!(repository.find_by_id(ids[2]).await.is_err());
+        assert!(repository.find_by_id(ids[0]).await.is_ok());
+        assert!(repository.find_by_id(newest.id).await.is_ok());
+        assert_eq!(repository.list_all(10, 0).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_capacity_rejects_when_full() {
+        let repository = InMemoryContextRepository::with_capacity(2, CapacityPolicy::Reject);
+
+        let first = repository
+            .save_context(create_test_context(0))
+            .await
+            .unwrap();
+        repository
+            .save_context(create_test_context(1))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            repository.save_context(create_test_context(2)).await,
+            Err(McpError::ContextLimitExceeded)
+        ));
+
+        // Deleting frees a slot
+        repository.delete(first.id).await.unwrap();
+        assert!(repository
+            .save_context(create_test_context(3))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_evictions_are_replayed() {
+        let dir = TempDir::new();
+        let mut repository = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        repository.limit_capacity(1, CapacityPolicy::EvictLeastRecent);
+
+        let evicted = repository
+            .save_context(create_test_context(0))
+            .await
+            .unwrap();
+        repository
+            .save_chunks(create_test_chunks(evicted.id, 1))
+            .await
+            .unwrap();
+        let kept = repository
+            .save_context(create_test_context(1))
+            .await
+            .unwrap();
+        drop(repository);
+
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert!(reopened.find_by_id(evicted.id).await.is_err());
+        assert!(reopened
+            .find_chunks_by_context_id(evicted.id)
+            .await
+            .is_err());
+        assert!(reopened.find_by_id(kept.id).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_revisions_are_pruned_replayed_and_deleted() {
+        let dir = TempDir::new();
+        let repository = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        let mut context = repository
+            .save_context(create_test_context(0))
+            .await
+            .unwrap();
+
+        for _ in 0..4 {
+            repository
+                .save_revision(ContextRevision::of(&context, Utc::now()), 3)
+                .await
+                .unwrap();
+            context.content = format!("version {}", context.version + 1);
+            context = repository.update(context, None).await.unwrap();
+        }
+        let versions = |revisions: Vec<ContextRevision>| -> Vec<u64> {
+            revisions.iter().map(|revision| revision.version).collect()
+        };
+        assert_eq!(
+            versions(repository.list_revisions(context.id).await.unwrap()),
+            [2, 3, 4]
+        );
+        assert!(matches!(
+            repository.get_revision(context.id, 1).await,
+            Err(McpError::RevisionNotFound { version: 1, .. })
+        ));
+        drop(repository);
+
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert_eq!(
+            versions(reopened.list_revisions(context.id).await.unwrap()),
+            [2, 3, 4]
+        );
+        let revision = reopened.get_revision(context.id, 3).await.unwrap();
+        assert_eq!(revision.content, "version 3");
+
+        reopened.delete(context.id).await.unwrap();
+        assert!(reopened
+            .list_revisions(context.id)
+            .await
+            .unwrap()
+            .is_empty());
+        drop(reopened);
+
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert!(reopened
+            .list_revisions(context.id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_collections_are_replayed_and_keep_unique_names() {
+        let dir = TempDir::new();
+        let repository = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        let collection = |name: &str| Collection {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            description: None,
+            created_at: Utc::now(),
+        };
+
+        let docs = repository
+            .save_collection(collection("project-alpha"))
+            .await
+            .unwrap();
+        let notes = repository
+            .save_collection(collection("personal-notes"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            repository.save_collection(collection("project-alpha")).await,
+            Err(McpError::CollectionExists(name)) if name == "project-alpha"
+        ));
+        repository.delete_collection(notes.id).await.unwrap();
+        assert!(matches!(
+            repository.delete_collection(notes.id).await,
+            Err(McpError::CollectionNotFound(_))
+        ));
+        drop(repository);
+
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert_eq!(
+            reopened.list_collections().await.unwrap(),
+            vec![docs.clone()]
+        );
+        reopened.compact().await.unwrap();
+        drop(reopened);
+
+        let reopened = InMemoryContextRepository::with_wal(dir.wal_path(), u64::MAX).unwrap();
+        assert_eq!(reopened.find_collection(docs.id).await.unwrap(), docs);
     }
 }
@@ -1,5 +1,67 @@
+pub mod batching_embedding_service;
+pub mod broadcast_event_publisher;
+pub mod cached_embedding_service;
+pub mod cohere_embedding_service;
+pub mod cohere_reranker;
+pub mod dictionary_query_expander;
+pub mod embedding_factory;
+#[cfg(feature = "fastembed")]
+pub mod fastembed_service;
+mod hnsw;
+pub mod http_content_fetcher;
+pub mod huggingface_embedding_service;
+#[cfg(feature = "lancedb")]
+pub mod lance_vector_store;
 pub mod memory_context_repository;
+#[cfg(feature = "mongodb")]
+pub mod mongo_context_repository;
+pub mod ollama_embedding_service;
+pub mod openai_embedding_service;
+pub mod passthrough_reranker;
+pub mod repository_factory;
+pub mod reranker_factory;
+pub mod retrying_embedding_service;
+#[cfg(feature = "rocksdb")]
+pub mod rocksdb_context_repository;
+pub mod shadow_context_repository;
 pub mod simple_embedding_service;
+pub mod tfidf_embedding_service;
+#[cfg(feature = "tiktoken")]
+pub mod tiktoken_tokenizer;
+pub mod tokenizer_factory;
+pub mod vector_index;
+pub mod write_ahead_log;
 
-pub use memory_context_repository::InMemoryContextRepository;
+pub use batching_embedding_service::BatchingEmbeddingService;
+pub use broadcast_event_publisher::BroadcastEventPublisher;
+pub use cached_embedding_service::{CachedEmbeddingService, EmbeddingCacheStats};
+pub use cohere_embedding_service::CohereEmbeddingService;
+pub use cohere_reranker::CohereReranker;
+pub use dictionary_query_expander::DictionaryQueryExpander;
+pub use embedding_factory::{create_embedding_backend, supported_providers, EmbeddingBackend};
+#[cfg(feature = "fastembed")]
+pub use fastembed_service::FastEmbedService;
+pub use http_content_fetcher::HttpContentFetcher;
+pub use huggingface_embedding_service::HuggingFaceEmbeddingService;
+#[cfg(feature = "lancedb")]
+pub use lance_vector_store::LanceVectorStore;
+pub use memory_context_repository::{CapacityPolicy, InMemoryContextRepository};
+#[cfg(feature = "mongodb")]
+pub use mongo_context_repository::MongoContextRepository;
+pub use ollama_embedding_service::OllamaEmbeddingService;
+pub use openai_embedding_service::OpenAiEmbeddingService;
+pub use passthrough_reranker::PassThroughReranker;
+pub use repository_factory::{create_repository, create_repository_for, supported_backends};
+pub use reranker_factory::create_reranker;
+pub use retrying_embedding_service::RetryingEmbeddingService;
+#[cfg(feature = "rocksdb")]
+pub use rocksdb_context_repository::RocksDbContextRepository;
+pub use shadow_context_repository::{
+    ShadowContextRepository, ShadowMetrics, ShadowMetricsSnapshot,
+};
 pub use simple_embedding_service::SimpleEmbeddingService;
+pub use tfidf_embedding_service::TfIdfEmbeddingService;
+#[cfg(feature = "tiktoken")]
+pub use tiktoken_tokenizer::TiktokenTokenizer;
+pub use tokenizer_factory::create_tokenizer;
+pub use vector_index::InMemoryVectorIndex;
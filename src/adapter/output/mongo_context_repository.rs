@@ -0,0 +1,1142 @@
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use futures::TryStreamExt;
+use mongodb::bson::{self, doc, Document};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{FindOptions, IndexOptions, ReplaceOptions};
+use mongodb::{Client, Collection, IndexModel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::domain::{
+    Collection as ContextCollection, Context, ContextChunk, ContextFilter, ContextMetadata,
+    ContextRelation, ContextRevision, McpError, McpResult, TagMode, DEFAULT_COLLECTION_ID,
+};
+use crate::ports::out_ports::ContextRepositoryPort;
+
+/// MongoDB error code for duplicate key violations
+const DUPLICATE_KEY: i32 = 11000;
+
+/// MongoDB implementation of the context repository
+/// Contexts, chunks, revisions and context collections live in separate `contexts`,
+/// `chunks`, `revisions` and `collections` collections
+pub struct MongoContextRepository {
+    contexts: Collection<ContextDocument>,
+    chunks: Collection<ChunkDocument>,
+    revisions: Collection<RevisionDocument>,
+    collections: Collection<CollectionDocument>,
+}
+
+/// Stored shape of a context
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ContextDocument {
+    #[serde(rename = "_id")]
+    id: String,
+    content: String,
+    source: Option<String>,
+    content_type: Option<String>,
+    content_hash: Option<String>,
+    tags: Vec<String>,
+    custom: HashMap<String, String>,
+    created_at: bson::DateTime,
+    expires_at: Option<bson::DateTime>,
+    /// Missing on documents written before contexts were versioned
+    #[serde(default = "first_version")]
+    version: i64,
+    #[serde(default)]
+    deleted_at: Option<bson::DateTime>,
+    #[serde(default)]
+    relations: Vec<RelationDocument>,
+    #[serde(default)]
+    parent_id: Option<String>,
+    /// Missing on contexts in the default collection
+    #[serde(default)]
+    collection_id: Option<String>,
+}
+
+/// Stored shape of a relation, with the target id as a string so it can be queried
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RelationDocument {
+    target_id: String,
+    relation_type: String,
+}
+
+fn first_version() -> i64 {
+    1
+}
+
+/// Stored shape of a chunk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChunkDocument {
+    #[serde(rename = "_id")]
+    id: String,
+    context_id: String,
+    content: String,
+    embedding: Option<Vec<f32>>,
+    position: i64,
+}
+
+/// Stored shape of a collection of contexts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CollectionDocument {
+    #[serde(rename = "_id")]
+    id: String,
+    name: String,
+    description: Option<String>,
+    created_at: bson::DateTime,
+}
+
+/// Stored shape of a revision, identified by context id and version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RevisionDocument {
+    #[serde(rename = "_id")]
+    id: String,
+    context_id: String,
+    version: i64,
+    content: String,
+    source: Option<String>,
+    content_type: Option<String>,
+    content_hash: Option<String>,
+    tags: Vec<String>,
+    custom: HashMap<String, String>,
+    expires_at: Option<bson::DateTime>,
+    archived_at: bson::DateTime,
+}
+
+impl MongoContextRepository {
+    /// Connect to MongoDB and make sure the indexes exist
+    pub async fn connect(uri: &str, database: &str) -> McpResult<Self> {
+        let client = Client::with_uri_str(uri).await.map_err(storage_error)?;
+        let database = client.database(database);
+
+        let repository = Self {
+            contexts: database.collection("contexts"),
+            chunks: database.collection("chunks"),
+            revisions: database.collection("revisions"),
+            collections: database.collection("collections"),
+        };
+
+        repository
+            .contexts
+            .create_index(IndexModel::builder().keys(doc! { "tags": 1 }).build(), None)
+            .await
+            .map_err(storage_error)?;
+
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "created_at": -1, "_id": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Stored contexts are looked up by content hash to find duplicates
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "content_hash": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Only contexts with an expiry are swept, so only they are indexed
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "expires_at": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Relations are looked up by target to clean them up when it's deleted
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "relations.target_id": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Children are listed by parent, and only contexts with a parent are indexed
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "parent_id": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Listings and searches are scoped to a collection, and contexts in the default
+        // collection have none
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "collection_id": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Only soft-deleted contexts are purged, so only they are indexed
+        repository
+            .contexts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "deleted_at": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        repository
+            .chunks
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "context_id": 1, "position": 1 })
+                    .options(IndexOptions::builder().build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        repository
+            .revisions
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "context_id": 1, "version": 1 })
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Collection names are unique
+        repository
+            .collections
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "name": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        Ok(repository)
+    }
+
+    /// Find contexts matching a filter with a stable ordering
+    async fn find_contexts(
+        &self,
+        filter: Document,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        if limit == 0 {
+            return Ok(vec![]);
+        }
+
+        // Newest first, then by id, as `Context::listing_order` orders them
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": 1 })
+            .skip(offset as u64)
+            .limit(limit as i64)
+            .build();
+
+        let documents: Vec<ContextDocument> = self
+            .contexts
+            .find(filter, options)
+            .await
+            .map_err(storage_error)?
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+
+        documents.into_iter().map(Context::try_from).collect()
+    }
+}
+
+/// Query document selecting the contexts a filter matches
+fn filter_document(filter: &ContextFilter) -> Document {
+    let mut tags = Document::new();
+    if !filter.tags.is_empty() {
+        let operator = match filter.tag_mode {
+            TagMode::All => "$all",
+            TagMode::Any => "$in",
+        };
+        tags.insert(operator, filter.tags.clone());
+    }
+    if !filter.exclude_tags.is_empty() {
+        tags.insert("$nin", filter.exclude_tags.clone());
+    }
+
+    let mut created_at = Document::new();
+    if let Some(after) = filter.created_after {
+        created_at.insert("$gte", to_bson_date(after));
+    }
+    if let Some(before) = filter.created_before {
+        created_at.insert("$lt", to_bson_date(before));
+    }
+
+    let mut document = Document::new();
+    if let Some(parent_id) = filter.parent_id {
+        document.insert("parent_id", parent_id.to_string());
+    }
+    match filter.collection {
+        // `null` also matches documents without the field
+        Some(DEFAULT_COLLECTION_ID) => {
+            document.insert("collection_id", bson::Bson::Null);
+        }
+        Some(collection_id) => {
+            document.insert("collection_id", collection_id.to_string());
+        }
+        None => {}
+    }
+    if let Some(source) = &filter.source {
+        document.insert("source", source.clone());
+    }
+    if !tags.is_empty() {
+        document.insert("tags", tags);
+    }
+    if !created_at.is_empty() {
+        document.insert("created_at", created_at);
+    }
+    if let Some(now) = filter.live_at {
+        // `null` also matches documents without the field
+        document.insert("deleted_at", bson::Bson::Null);
+        document.insert(
+            "$or",
+            vec![
+                doc! { "expires_at": null },
+                doc! { "expires_at": { "$gt": to_bson_date(now) } },
+            ],
+        );
+    }
+    document
+}
+
+fn storage_error(err: mongodb::error::Error) -> McpError {
+    McpError::StorageError(err.to_string())
+}
+
+fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    matches!(
+        err.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(write_error)) if write_error.code == DUPLICATE_KEY
+    )
+}
+
+fn to_bson_date(date: DateTime<Utc>) -> bson::DateTime {
+    bson::DateTime::from_millis(date.timestamp_millis())
+}
+
+fn from_bson_date(date: bson::DateTime) -> McpResult<DateTime<Utc>> {
+    Utc.timestamp_millis_opt(date.timestamp_millis())
+        .single()
+        .ok_or_else(|| McpError::SerializationError("Invalid stored timestamp".to_string()))
+}
+
+fn revision_id(context_id: Uuid, version: u64) -> String {
+    format!("{}:{}", context_id, version)
+}
+
+fn parse_id(id: &str) -> McpResult<Uuid> {
+    Uuid::parse_str(id).map_err(|e| McpError::SerializationError(e.to_string()))
+}
+
+impl From<&Context> for ContextDocument {
+    fn from(context: &Context) -> Self {
+        Self {
+            id: context.id.to_string(),
+            content: context.content.clone(),
+            source: context.metadata.source.clone(),
+            content_type: context.metadata.content_type.clone(),
+            content_hash: context.metadata.content_hash.clone(),
+            tags: context.metadata.tags.clone(),
+            custom: context.metadata.custom.clone(),
+            created_at: to_bson_date(context.created_at),
+            expires_at: context.expires_at.map(to_bson_date),
+            version: context.version as i64,
+            deleted_at: context.deleted_at.map(to_bson_date),
+            relations: context
+                .relations
+                .iter()
+                .map(|relation| RelationDocument {
+                    target_id: relation.target_id.to_string(),
+                    relation_type: relation.relation_type.clone(),
+                })
+                .collect(),
+            parent_id: context.parent_id.map(|parent_id| parent_id.to_string()),
+            collection_id: context
+                .collection_id
+                .map(|collection_id| collection_id.to_string()),
+        }
+    }
+}
+
+impl TryFrom<ContextDocument> for Context {
+    type Error = McpError;
+
+    fn try_from(document: ContextDocument) -> McpResult<Self> {
+        Ok(Self {
+            id: parse_id(&document.id)?,
+            content: document.content,
+            metadata: ContextMetadata {
+                source: document.source,
+                content_type: document.content_type,
+                content_hash: document.content_hash,
+                tags: document.tags,
+                custom: document.custom,
+            },
+            created_at: from_bson_date(document.created_at)?,
+            expires_at: document.expires_at.map(from_bson_date).transpose()?,
+            version: document.version as u64,
+            deleted_at: document.deleted_at.map(from_bson_date).transpose()?,
+            relations: document
+                .relations
+                .into_iter()
+                .map(|relation| {
+                    Ok(ContextRelation {
+                        target_id: parse_id(&relation.target_id)?,
+                        relation_type: relation.relation_type,
+                    })
+                })
+                .collect::<McpResult<_>>()?,
+            parent_id: document.parent_id.as_deref().map(parse_id).transpose()?,
+            collection_id: document
+                .collection_id
+                .as_deref()
+                .map(parse_id)
+                .transpose()?,
+        })
+    }
+}
+
+impl From<&ContextRevision> for RevisionDocument {
+    fn from(revision: &ContextRevision) -> Self {
+        Self {
+            id: revision_id(revision.context_id, revision.version),
+            context_id: revision.context_id.to_string(),
+            version: revision.version as i64,
+            content: revision.content.clone(),
+            source: revision.metadata.source.clone(),
+            content_type: revision.metadata.content_type.clone(),
+            content_hash: revision.metadata.content_hash.clone(),
+            tags: revision.metadata.tags.clone(),
+            custom: revision.metadata.custom.clone(),
+            expires_at: revision.expires_at.map(to_bson_date),
+            archived_at: to_bson_date(revision.archived_at),
+        }
+    }
+}
+
+impl TryFrom<RevisionDocument> for ContextRevision {
+    type Error = McpError;
+
+    fn try_from(document: RevisionDocument) -> McpResult<Self> {
+        Ok(Self {
+            context_id: parse_id(&document.context_id)?,
+            version: document.version as u64,
+            content: document.content,
+            metadata: ContextMetadata {
+                source: document.source,
+                content_type: document.content_type,
+                content_hash: document.content_hash,
+                tags: document.tags,
+                custom: document.custom,
+            },
+            expires_at: document.expires_at.map(from_bson_date).transpose()?,
+            archived_at: from_bson_date(document.archived_at)?,
+        })
+    }
+}
+
+impl From<&ContextCollection> for CollectionDocument {
+    fn from(collection: &ContextCollection) -> Self {
+        Self {
+            id: collection.id.to_string(),
+            name: collection.name.clone(),
+            description: collection.description.clone(),
+            created_at: to_bson_date(collection.created_at),
+        }
+    }
+}
+
+impl TryFrom<CollectionDocument> for ContextCollection {
+    type Error = McpError;
+
+    fn try_from(document: CollectionDocument) -> McpResult<Self> {
+        Ok(Self {
+            id: parse_id(&document.id)?,
+            name: document.name,
+            description: document.description,
+            created_at: from_bson_date(document.created_at)?,
+        })
+    }
+}
+
+impl From<&ContextChunk> for ChunkDocument {
+    fn from(chunk: &ContextChunk) -> Self {
+        Self {
+            id: chunk.chunk_id.to_string(),
+            context_id: chunk.context_id.to_string(),
+            content: chunk.content.clone(),
+            embedding: chunk.embedding.clone(),
+            position: chunk.position as i64,
+        }
+    }
+}
+
+impl TryFrom<ChunkDocument> for ContextChunk {
+    type Error = McpError;
+
+    fn try_from(document: ChunkDocument) -> McpResult<Self> {
+        Ok(Self {
+            context_id: parse_id(&document.context_id)?,
+            chunk_id: parse_id(&document.id)?,
+            content: document.content,
+            embedding: document.embedding,
+            position: document.position as usize,
+        })
+    }
+}
+
+#[async_trait]
+impl ContextRepositoryPort for MongoContextRepository {
+    async fn save_context(&self, context: Context) -> McpResult<Context> {
+        match self
+            .contexts
+            .insert_one(ContextDocument::from(&context), None)
+            .await
+        {
+            Ok(_) => Ok(context),
+            Err(err) if is_duplicate_key(&err) => Err(McpError::ContextAlreadyExists(context.id)),
+            Err(err) => Err(storage_error(err)),
+        }
+    }
+
+    async fn find_by_id(&self, context_id: Uuid) -> McpResult<Context> {
+        self.contexts
+            .find_one(doc! { "_id": context_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?
+            .ok_or(McpError::ContextNotFound(context_id))
+            .and_then(Context::try_from)
+    }
+
+    async fn find_by_ids(&self, context_ids: &[Uuid]) -> McpResult<Vec<Context>> {
+        let ids: Vec<String> = context_ids.iter().map(Uuid::to_string).collect();
+
+        let documents: Vec<ContextDocument> = self
+            .contexts
+            .find(doc! { "_id": { "$in": ids } }, None)
+            .await
+            .map_err(storage_error)?
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+
+        // `$in` doesn't preserve the requested order
+        let mut found: HashMap<Uuid, Context> = documents
+            .into_iter()
+            .map(|document| Context::try_from(document).map(|context| (context.id, context)))
+            .collect::<McpResult<_>>()?;
+
+        Ok(context_ids
+            .iter()
+            .filter_map(|context_id| found.remove(context_id))
+            .collect())
+    }
+
+    async fn update(
+        &self,
+        mut context: Context,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let stored = self.find_by_id(context.id).await?;
+        stored.check_version(expected_version)?;
+        context.version = stored.version + 1;
+
+        // Only replace the version that was checked, so a concurrent update in between
+        // makes this one match nothing instead of being overwritten
+        let stored_version = if stored.version == 1 {
+            doc! { "$in": [1_i64, bson::Bson::Null] }
+        } else {
+            doc! { "$eq": stored.version as i64 }
+        };
+        let result = self
+            .contexts
+            .replace_one(
+                doc! { "_id": context.id.to_string(), "version": stored_version },
+                ContextDocument::from(&context),
+                None,
+            )
+            .await
+            .map_err(storage_error)?;
+
+        if result.matched_count == 0 {
+            let current = self.find_by_id(context.id).await?;
+            return Err(McpError::VersionConflict {
+                context_id: context.id,
+                expected: stored.version,
+                actual: current.version,
+            });
+        }
+
+        Ok(context)
+    }
+
+    // Multi-document transactions need a replica set, so a failed chunk write is
+    // compensated by removing or restoring what was already written instead
+    async fn save_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context> {
+        let context = self.save_context(context).await?;
+
+        if let Err(err) = self.save_chunks(chunks).await {
+            let _ = self.delete_chunks_by_context_id(context.id).await;
+            let _ = self.delete(context.id).await;
+            return Err(err);
+        }
+
+        Ok(context)
+    }
+
+    async fn replace_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let previous = self.find_by_id(context.id).await?;
+        let previous_chunks = self.find_chunks_by_context_id(context.id).await?;
+
+        let context = self.update(context, expected_version).await?;
+
+        self.delete_chunks_by_context_id(context.id).await?;
+        if let Err(err) = self.save_chunks(chunks).await {
+            // Put the previous document back as it was, version included
+            let _ = self
+                .contexts
+                .replace_one(
+                    doc! { "_id": previous.id.to_string() },
+                    ContextDocument::from(&previous),
+                    None,
+                )
+                .await;
+            let _ = self.delete_chunks_by_context_id(context.id).await;
+            let _ = self.save_chunks(previous_chunks).await;
+            return Err(err);
+        }
+
+        Ok(context)
+    }
+
+    async fn delete(&self, context_id: Uuid) -> McpResult<()> {
+        let result = self
+            .contexts
+            .delete_one(doc! { "_id": context_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?;
+
+        if result.deleted_count == 0 {
+            return Err(McpError::ContextNotFound(context_id));
+        }
+
+        self.revisions
+            .delete_many(doc! { "context_id": context_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+
+    async fn save_revision(
+        &self,
+        revision: ContextRevision,
+        max_revisions: usize,
+    ) -> McpResult<()> {
+        let context_id = revision.context_id.to_string();
+        if max_revisions == 0 {
+            self.revisions
+                .delete_many(doc! { "context_id": context_id }, None)
+                .await
+                .map_err(storage_error)?;
+            return Ok(());
+        }
+
+        let document = RevisionDocument::from(&revision);
+        self.revisions
+            .replace_one(
+                doc! { "_id": &document.id },
+                &document,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(storage_error)?;
+
+        // Drop every revision older than the newest `max_revisions`
+        let options = FindOptions::builder()
+            .sort(doc! { "version": -1 })
+            .skip(max_revisions as u64)
+            .limit(1)
+            .build();
+        let newest_dropped: Option<RevisionDocument> = self
+            .revisions
+            .find(doc! { "context_id": &context_id }, options)
+            .await
+            .map_err(storage_error)?
+            .try_next()
+            .await
+            .map_err(storage_error)?;
+        if let Some(dropped) = newest_dropped {
+            self.revisions
+                .delete_many(
+                    doc! { "context_id": &context_id, "version": { "$lte": dropped.version } },
+                    None,
+                )
+                .await
+                .map_err(storage_error)?;
+        }
+        Ok(())
+    }
+
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>> {
+        let options = FindOptions::builder().sort(doc! { "version": 1 }).build();
+        let documents: Vec<RevisionDocument> = self
+            .revisions
+            .find(doc! { "context_id": context_id.to_string() }, options)
+            .await
+            .map_err(storage_error)?
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+
+        documents
+            .into_iter()
+            .map(ContextRevision::try_from)
+            .collect()
+    }
+
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision> {
+        self.revisions
+            .find_one(doc! { "_id": revision_id(context_id, version) }, None)
+            .await
+            .map_err(storage_error)?
+            .ok_or(McpError::RevisionNotFound {
+                context_id,
+                version,
+            })?
+            .try_into()
+    }
+
+    async fn save_collection(&self, collection: ContextCollection) -> McpResult<ContextCollection> {
+        let document = CollectionDocument::from(&collection);
+        match self
+            .collections
+            .replace_one(
+                doc! { "_id": &document.id },
+                &document,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+        {
+            Ok(_) => Ok(collection),
+            Err(err) if is_duplicate_key(&err) => Err(McpError::CollectionExists(collection.name)),
+            Err(err) => Err(storage_error(err)),
+        }
+    }
+
+    async fn find_collection(&self, collection_id: Uuid) -> McpResult<ContextCollection> {
+        self.collections
+            .find_one(doc! { "_id": collection_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?
+            .ok_or(McpError::CollectionNotFound(collection_id))?
+            .try_into()
+    }
+
+    async fn list_collections(&self) -> McpResult<Vec<ContextCollection>> {
+        let documents: Vec<CollectionDocument> = self
+            .collections
+            .find(doc! {}, None)
+            .await
+            .map_err(storage_error)?
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+
+        documents
+            .into_iter()
+            .map(ContextCollection::try_from)
+            .collect()
+    }
+
+    async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()> {
+        let result = self
+            .collections
+            .delete_one(doc! { "_id": collection_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?;
+        if result.deleted_count == 0 {
+            return Err(McpError::CollectionNotFound(collection_id));
+        }
+        Ok(())
+    }
+
+    async fn find_by_tags(
+        &self,
+        tags: &[String],
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        // `$all` with an empty list matches nothing, but no tags means no filter
+        let filter = if tags.is_empty() {
+            doc! {}
+        } else {
+            doc! { "tags": { "$all": tags } }
+        };
+
+        self.find_contexts(filter, limit, offset).await
+    }
+
+    async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>> {
+        self.find_contexts(doc! {}, limit, offset).await
+    }
+
+    async fn count_all(&self) -> McpResult<usize> {
+        self.count_by_tags(&[]).await
+    }
+
+    async fn find_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        let filter = doc! { "expires_at": { "$lte": to_bson_date(now) } };
+        self.find_contexts(filter, limit, offset).await
+    }
+
+    async fn list_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        let filter = doc! { "deleted_at": { "$lte": to_bson_date(deleted_before) } };
+        self.find_contexts(filter, limit, offset).await
+    }
+
+    async fn find_related_to(&self, target_id: Uuid) -> McpResult<Vec<Context>> {
+        let filter = doc! { "relations.target_id": target_id.to_string() };
+        self.find_contexts(filter, i64::MAX as usize, 0).await
+    }
+
+    async fn find_by_content_hash(&self, content_hash: &str) -> McpResult<Vec<Context>> {
+        let filter = doc! { "content_hash": content_hash };
+        self.find_contexts(filter, i64::MAX as usize, 0).await
+    }
+
+    async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize> {
+        let filter = if tags.is_empty() {
+            doc! {}
+        } else {
+            doc! { "tags": { "$all": tags } }
+        };
+
+        let count = self
+            .contexts
+            .count_documents(filter, None)
+            .await
+            .map_err(storage_error)?;
+        Ok(count as usize)
+    }
+
+    async fn find_filtered(
+        &self,
+        filter: &ContextFilter,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        self.find_contexts(filter_document(filter), limit, offset)
+            .await
+    }
+
+    async fn count_filtered(&self, filter: &ContextFilter) -> McpResult<usize> {
+        let count = self
+            .contexts
+            .count_documents(filter_document(filter), None)
+            .await
+            .map_err(storage_error)?;
+        Ok(count as usize)
+    }
+
+    async fn exists(&self, context_id: Uuid) -> McpResult<bool> {
+        let count = self
+            .contexts
+            .count_documents(doc! { "_id": context_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?;
+        Ok(count > 0)
+    }
+
+    async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>> {
+        if chunks.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Saving chunks replaces any existing chunks for the context
+        let context_id = chunks[0].context_id;
+        self.delete_chunks_by_context_id(context_id).await?;
+
+        self.chunks
+            .insert_many(chunks.iter().map(ChunkDocument::from), None)
+            .await
+            .map_err(storage_error)?;
+
+        Ok(chunks)
+    }
+
+    async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>> {
+        let options = FindOptions::builder().sort(doc! { "position": 1 }).build();
+
+        let documents: Vec<ChunkDocument> = self
+            .chunks
+            .find(doc! { "context_id": context_id.to_string() }, options)
+            .await
+            .map_err(storage_error)?
+            .try_collect()
+            .await
+            .map_err(storage_error)?;
+
+        if documents.is_empty() {
+            // Distinguish "no chunks" from "no such context"
+            self.find_by_id(context_id).await?;
+        }
+
+        documents.into_iter().map(ContextChunk::try_from).collect()
+    }
+
+    async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk> {
+        self.chunks
+            .find_one(doc! { "_id": chunk_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?
+            .ok_or(McpError::ChunkNotFound(chunk_id))
+            .and_then(ContextChunk::try_from)
+    }
+
+    async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()> {
+        self.chunks
+            .delete_many(doc! { "context_id": context_id.to_string() }, None)
+            .await
+            .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_context() -> Context {
+        let mut custom = HashMap::new();
+        custom.insert("author".to_string(), "test".to_string());
+
+        Context {
+            created_at: Utc.timestamp_millis_opt(1_700_000_000_123).unwrap(),
+            ..Context::new(
+                "Mongo test content",
+                ContextMetadata {
+                    source: Some("test".to_string()),
+                    content_type: Some("text/plain".to_string()),
+                    content_hash: None,
+                    tags: vec!["mongo".to_string(), "test".to_string()],
+                    custom,
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_context_document_round_trip() {
+        let mut context = create_test_context();
+        let target_id = Uuid::new_v4();
+        context.relations.push(ContextRelation {
+            target_id,
+            relation_type: "summarizes".to_string(),
+        });
+
+        let document = ContextDocument::from(&context);
+        let bson = bson::to_document(&document).unwrap();
+        assert!(bson.get_document("custom").is_ok());
+        assert_eq!(bson.get_str("_id").unwrap(), context.id.to_string());
+        // Targets are stored as strings, as `find_related_to` queries them
+        let relations = bson.get_array("relations").unwrap();
+        assert_eq!(
+            relations[0]
+                .as_document()
+                .unwrap()
+                .get_str("target_id")
+                .unwrap(),
+            target_id.to_string()
+        );
+
+        let restored =
+            Context::try_from(bson::from_document::<ContextDocument>(bson).unwrap()).unwrap();
+        assert_eq!(restored.id, context.id);
+        assert_eq!(restored.content, context.content);
+        assert_eq!(restored.metadata.tags, context.metadata.tags);
+        assert_eq!(restored.metadata.custom, context.metadata.custom);
+        assert_eq!(restored.created_at, context.created_at);
+        assert_eq!(restored.relations, context.relations);
+    }
+
+    #[test]
+    fn test_chunk_document_round_trip() {
+        let chunk = ContextChunk {
+            context_id: Uuid::new_v4(),
+            chunk_id: Uuid::new_v4(),
+            content: "chunk".to_string(),
+            embedding: Some(vec![0.5, 0.25]),
+            position: 3,
+        };
+
+        let restored = ContextChunk::try_from(ChunkDocument::from(&chunk)).unwrap();
+        assert_eq!(restored.chunk_id, chunk.chunk_id);
+        assert_eq!(restored.context_id, chunk.context_id);
+        assert_eq!(restored.embedding, chunk.embedding);
+        assert_eq!(restored.position, 3);
+    }
+
+    #[test]
+    fn test_revision_document_round_trip() {
+        let mut context = create_test_context();
+        context.version = 4;
+        context.expires_at = Some(Utc.timestamp_millis_opt(1_800_000_000_000).unwrap());
+        let revision = ContextRevision::of(
+            &context,
+            Utc.timestamp_millis_opt(1_700_000_500_000).unwrap(),
+        );
+
+        let document = RevisionDocument::from(&revision);
+        assert_eq!(document.id, format!("{}:4", context.id));
+        let restored = ContextRevision::try_from(
+            bson::from_document::<RevisionDocument>(bson::to_document(&document).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(restored.context_id, context.id);
+        assert_eq!(restored.version, 4);
+        assert_eq!(restored.content, context.content);
+        assert_eq!(restored.metadata.custom, context.metadata.custom);
+        assert_eq!(restored.expires_at, context.expires_at);
+        assert_eq!(restored.archived_at, revision.archived_at);
+    }
+
+    #[test]
+    fn test_filter_document_excludes_tags_in_the_query() {
+        let filter = ContextFilter {
+            tag_mode: TagMode::Any,
+            exclude_tags: vec!["archived".to_string()],
+            ..ContextFilter::tagged(vec!["ai".to_string(), "nlp".to_string()])
+        };
+        assert_eq!(
+            filter_document(&filter),
+            doc! { "tags": { "$in": ["ai", "nlp"], "$nin": ["archived"] } }
+        );
+        assert_eq!(filter_document(&ContextFilter::default()), doc! {});
+        let parent_id = Uuid::new_v4();
+        assert_eq!(
+            filter_document(&ContextFilter::children_of(parent_id)),
+            doc! { "parent_id": parent_id.to_string() }
+        );
+
+        // Contexts in the default collection are stored without one
+        let in_default = ContextFilter {
+            collection: Some(DEFAULT_COLLECTION_ID),
+            ..ContextFilter::default()
+        };
+        assert_eq!(filter_document(&in_default), doc! { "collection_id": null });
+
+        // Contexts without an expiry are live too, unless deleted
+        let now = Utc::now();
+        let live = ContextFilter {
+            live_at: Some(now),
+            ..ContextFilter::default()
+        };
+        assert_eq!(
+            filter_document(&live),
+            doc! {
+                "deleted_at": null,
+                "$or": [{ "expires_at": null }, { "expires_at": { "$gt": to_bson_date(now) } }],
+            }
+        );
+    }
+
+    /// Runs against a live server when `MCP_TEST_MONGODB_URI` is set
+    #[tokio::test]
+    async fn test_against_live_server() {
+        let Ok(uri) = std::env::var("MCP_TEST_MONGODB_URI") else {
+            return;
+        };
+
+        let database = format!("mcp_test_{}", Uuid::new_v4().simple());
+        let repository = MongoContextRepository::connect(&uri, &database)
+            .await
+            .unwrap();
+        let context = create_test_context();
+
+        repository.save_context(context.clone()).await.unwrap();
+        assert!(matches!(
+            repository.save_context(context.clone()).await,
+            Err(McpError::ContextAlreadyExists(_))
+        ));
+
+        let found = repository
+            .find_by_tags(&["mongo".to_string()], 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+
+        let mut missing = create_test_context();
+        missing.id = Uuid::new_v4();
+        assert!(matches!(
+            repository.update(missing, None).await,
+            Err(McpError::ContextNotFound(_))
+        ));
+
+        repository.delete(context.id).await.unwrap();
+        assert!(repository.find_by_id(context.id).await.is_err());
+
+        let client = Client::with_uri_str(&uri).await.unwrap();
+        client.database(&database).drop(None).await.unwrap();
+    }
+}
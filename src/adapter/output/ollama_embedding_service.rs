@@ -0,0 +1,174 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service backed by the embed API of a local Ollama server
+pub struct OllamaEmbeddingService {
+    client: Client,
+    endpoint: String,
+    model: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbedResponse {
+    embeddings: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl OllamaEmbeddingService {
+    /// Create a service calling `{api_base}/api/embed` with `model`
+    pub fn new(model: impl Into<String>, api_base: &str) -> McpResult<Self> {
+        // Local models can take a while to load on the first request
+        let client = Client::builder()
+            .timeout(Duration::from_secs(120))
+            .build()
+            .map_err(|e| McpError::ExternalServiceError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            endpoint: format!("{}/api/embed", api_base.trim_end_matches('/')),
+            model: model.into(),
+        })
+    }
+
+    async fn request_embeddings(&self, inputs: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .json(&EmbedRequest {
+                model: &self.model,
+                input: inputs,
+            })
+            .send()
+            .await
+            .map_err(|e| {
+                McpError::ExternalServiceError(format!("Embedding request failed: {}", e))
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(classify_error(status, &body));
+        }
+
+        let response: EmbedResponse = response
+            .json()
+            .await
+            .map_err(|e| McpError::EmbeddingError(format!("Invalid embedding response: {}", e)))?;
+
+        if response.embeddings.len() != inputs.len() {
+            return Err(McpError::EmbeddingError(format!(
+                "Expected {} embeddings, received {}",
+                inputs.len(),
+                response.embeddings.len()
+            )));
+        }
+        Ok(response.embeddings)
+    }
+}
+
+/// Map an error response to an error; server failures are retryable
+fn classify_error(status: StatusCode, body: &str) -> McpError {
+    let message = serde_json::from_str::<ErrorResponse>(body)
+        .map(|response| response.error)
+        .unwrap_or_else(|_| body.to_string());
+    let message = format!("Embedding API returned {}: {}", status, message);
+
+    if status.is_server_error() {
+        McpError::ExternalServiceError(message)
+    } else {
+        McpError::EmbeddingError(message)
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for OllamaEmbeddingService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.request_embeddings(texts).await
+    }
+
+    /// Embed a one-word query, which exercises the endpoint, credentials and model
+    async fn check_health(&self) -> McpResult<()> {
+        self.embed_query("ping").await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn service(server: &MockServer) -> OllamaEmbeddingService {
+        OllamaEmbeddingService::new("nomic-embed-text", &server.uri()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_texts_and_queries_are_embedded() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .and(body_partial_json(json!({
+                "model": "nomic-embed-text",
+                "input": ["restart the database", "bake bread"],
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "model": "nomic-embed-text",
+                "embeddings": [[1.0, 0.0], [0.0, 1.0]],
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .and(body_partial_json(json!({ "input": ["database outage"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "embeddings": [[0.9, 0.1]],
+            })))
+            .mount(&server)
+            .await;
+
+        let service = service(&server);
+        let texts = ["restart the database".to_string(), "bake bread".to_string()];
+        let embedded = service.embed_texts(&texts).await.unwrap();
+        assert_eq!(embedded, [vec![1.0, 0.0], vec![0.0, 1.0]]);
+
+        let query = service.embed_query("database outage").await.unwrap();
+        assert_eq!(query, [0.9, 0.1]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_model_is_embedding_error() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/api/embed"))
+            .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+                "error": "model \"nomic-embed-text\" not found, try pulling it first",
+            })))
+            .mount(&server)
+            .await;
+
+        let err = service(&server).embed_query("query").await.unwrap_err();
+        assert!(matches!(err, McpError::EmbeddingError(message) if message.contains("pulling")));
+    }
+}
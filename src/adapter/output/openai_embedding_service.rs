@@ -0,0 +1,294 @@
+use async_trait::async_trait;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service backed by the OpenAI embeddings API
+///
+/// Rate limits and server failures are reported as transient errors for the caller to retry.
+pub struct OpenAiEmbeddingService {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    model: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    message: String,
+    code: Option<String>,
+}
+
+impl OpenAiEmbeddingService {
+    /// Create a service calling `{api_base}/embeddings`
+    pub fn new(
+        api_key: impl Into<String>,
+        model: impl Into<String>,
+        api_base: &str,
+    ) -> McpResult<Self> {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| McpError::ExternalServiceError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            endpoint: format!("{}/embeddings", api_base.trim_end_matches('/')),
+            api_key: api_key.into(),
+            model: model.into(),
+        })
+    }
+}
+
+/// Map an error response to an error, transient for rate limits and server failures
+///
+/// An exhausted quota is reported as a rate limit too, but waiting won't fix it.
+fn classify_error(status: StatusCode, body: &str) -> McpError {
+    let detail = serde_json::from_str::<ErrorResponse>(body).ok();
+    let message = detail
+        .as_ref()
+        .map(|response| response.error.message.clone())
+        .unwrap_or_else(|| body.to_string());
+    let quota_exhausted = detail
+        .as_ref()
+        .and_then(|response| response.error.code.as_deref())
+        == Some("insufficient_quota");
+
+    let message = format!("Embedding API returned {}: {}", status, message);
+    if (status == StatusCode::TOO_MANY_REQUESTS && !quota_exhausted) || status.is_server_error() {
+        McpError::ExternalServiceError(message)
+    } else {
+        McpError::EmbeddingError(message)
+    }
+}
+
+#[async_trait]
+impl EmbeddingPort for OpenAiEmbeddingService {
+    /// Embed all texts in one request
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let response = self
+            .client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&EmbeddingRequest {
+                model: &self.model,
+                input: texts,
+            })
+            .send()
+            .await
+            .map_err(|e| {
+                McpError::ExternalServiceError(format!("Embedding request failed: {}", e))
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(classify_error(status, &body));
+        }
+
+        let mut response: EmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|e| McpError::EmbeddingError(format!("Invalid embedding response: {}", e)))?;
+
+        if response.data.len() != texts.len() {
+            return Err(McpError::EmbeddingError(format!(
+                "Expected {} embeddings, received {}",
+                texts.len(),
+                response.data.len()
+            )));
+        }
+
+        // The API reports each embedding's input position; don't rely on response order
+        response.data.sort_by_key(|data| data.index);
+        Ok(response
+            .data
+            .into_iter()
+            .map(|data| data.embedding)
+            .collect())
+    }
+
+    /// Embed a one-word query, which exercises the endpoint, credentials and model
+    async fn check_health(&self) -> McpResult<()> {
+        self.embed_query("ping").await.map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::out_adapters::RetryingEmbeddingService;
+    use serde_json::json;
+    use wiremock::matchers::{body_partial_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// The adapter as the factory wires it, retrying transient failures
+    fn service(server: &MockServer) -> RetryingEmbeddingService<OpenAiEmbeddingService> {
+        let service =
+            OpenAiEmbeddingService::new("test-key", "text-embedding-3-small", &server.uri())
+                .unwrap();
+        RetryingEmbeddingService::new(service, 3, Duration::from_millis(1))
+    }
+
+    fn embeddings(vectors: &[(usize, [f32; 2])]) -> serde_json::Value {
+        let data: Vec<_> = vectors
+            .iter()
+            .map(|(index, embedding)| {
+                json!({ "object": "embedding", "index": index, "embedding": embedding })
+            })
+            .collect();
+        json!({ "object": "list", "data": data, "model": "text-embedding-3-small" })
+    }
+
+    #[tokio::test]
+    async fn test_texts_are_embedded_in_one_request_in_input_order() {
+        let server = MockServer::start().await;
+
+        // Both texts go out in a single request; the response lists them out of order
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(header("authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "text-embedding-3-small",
+                "input": ["restart the database", "bake bread"],
+            })))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(embeddings(&[(1, [0.0, 1.0]), (0, [1.0, 0.0])])),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(body_partial_json(json!({ "input": ["database outage"] })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(embeddings(&[(0, [0.9, 0.1])])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let service = service(&server);
+        let embedded = service
+            .embed_texts(&["restart the database".to_string(), "bake bread".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embedded, [vec![1.0, 0.0], vec![0.0, 1.0]]);
+
+        let query = service.embed_query("database outage").await.unwrap();
+        assert_eq!(query, [0.9, 0.1]);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_retried() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+                "error": { "message": "Rate limit reached", "code": "rate_limit_exceeded" }
+            })))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(embeddings(&[(0, [1.0, 0.0])])))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let embedded = service(&server)
+            .embed_texts(&["content".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(embedded, [vec![1.0, 0.0]]);
+    }
+
+    #[tokio::test]
+    async fn test_server_errors_are_transient_after_retries_run_out() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(3)
+            .mount(&server)
+            .await;
+
+        let err = service(&server).embed_query("query").await.unwrap_err();
+        assert!(matches!(err, McpError::ExternalServiceError(_)));
+        assert!(err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_auth_and_quota_failures_are_not_retried() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(body_partial_json(json!({ "input": ["unauthorized"] })))
+            .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+                "error": { "message": "Incorrect API key provided", "code": "invalid_api_key" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/embeddings"))
+            .and(body_partial_json(json!({ "input": ["over quota"] })))
+            .respond_with(ResponseTemplate::new(429).set_body_json(json!({
+                "error": { "message": "You exceeded your current quota", "code": "insufficient_quota" }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let service = service(&server);
+        for query in ["unauthorized", "over quota"] {
+            let err = service.embed_query(query).await.unwrap_err();
+            assert!(matches!(err, McpError::EmbeddingError(_)), "{}", err);
+            assert!(!err.is_transient());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_network_failure_is_external_service_error() {
+        // Nothing listens on the discard port
+        let service =
+            OpenAiEmbeddingService::new("test-key", "text-embedding-3-small", "http://127.0.0.1:9")
+                .unwrap();
+
+        let err = service.embed_query("query").await.unwrap_err();
+        assert!(matches!(err, McpError::ExternalServiceError(_)));
+    }
+}
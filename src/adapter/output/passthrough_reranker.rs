@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use crate::domain::{ContextMatch, McpResult};
+use crate::ports::out_ports::RerankerPort;
+
+/// Reranker that keeps the first-stage order and scores
+pub struct PassThroughReranker;
+
+#[async_trait]
+impl RerankerPort for PassThroughReranker {
+    async fn rerank(
+        &self,
+        _query: &str,
+        mut candidates: Vec<ContextMatch>,
+        top_k: usize,
+    ) -> McpResult<Vec<ContextMatch>> {
+        candidates.truncate(top_k);
+        Ok(candidates)
+    }
+}
@@ -0,0 +1,182 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use super::{CapacityPolicy, InMemoryContextRepository, ShadowContextRepository};
+use crate::config::{AppConfig, ContextConfig, StorageConfig};
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::ContextRepositoryPort;
+
+#[cfg(feature = "mongodb")]
+use super::MongoContextRepository;
+#[cfg(feature = "rocksdb")]
+use super::RocksDbContextRepository;
+
+/// Storage backends available in this build
+pub fn supported_backends() -> Vec<&'static str> {
+    let mut backends = vec!["memory"];
+    if cfg!(feature = "rocksdb") {
+        backends.push("rocksdb");
+    }
+    if cfg!(feature = "mongodb") {
+        backends.push("mongodb");
+    }
+    backends
+}
+
+/// Create the context repository described by the configuration, including any shadow
+pub async fn create_repository(
+    config: &AppConfig,
+) -> McpResult<Arc<dyn ContextRepositoryPort + Send + Sync>> {
+    let storage = &config.storage;
+    let primary = create_backend(storage, capacity(&config.context)?).await?;
+
+    let Some(shadow) = &storage.shadow else {
+        return Ok(primary);
+    };
+
+    info!(
+        "Mirroring {} storage to {} storage",
+        storage.backend, shadow.backend
+    );
+    let secondary = create_repository_for(&shadow.secondary(storage)).await?;
+    let repository = ShadowContextRepository::new(
+        primary,
+        secondary,
+        shadow.queue_capacity,
+        shadow.read_sample_rate,
+    );
+    if shadow.report_interval_seconds > 0 {
+        repository.report_metrics(Duration::from_secs(shadow.report_interval_seconds));
+    }
+
+    Ok(Arc::new(repository))
+}
+
+/// Create a repository for a single storage backend
+pub async fn create_repository_for(
+    storage: &StorageConfig,
+) -> McpResult<Arc<dyn ContextRepositoryPort + Send + Sync>> {
+    create_backend(storage, None).await
+}
+
+/// Capacity limit of the memory backend described by the context configuration
+fn capacity(context: &ContextConfig) -> McpResult<Option<(usize, CapacityPolicy)>> {
+    let Some(max_contexts) = context.max_contexts else {
+        return Ok(None);
+    };
+
+    let policy = match context.capacity_policy.as_str() {
+        "evict" => CapacityPolicy::EvictLeastRecent,
+        "reject" => CapacityPolicy::Reject,
+        other => {
+            return Err(McpError::ValidationError(format!(
+                "Unsupported capacity policy '{}' (supported: evict, reject)",
+                other
+            )))
+        }
+    };
+
+    Ok(Some((max_contexts, policy)))
+}
+
+async fn create_backend(
+    storage: &StorageConfig,
+    capacity: Option<(usize, CapacityPolicy)>,
+) -> McpResult<Arc<dyn ContextRepositoryPort + Send + Sync>> {
+    if capacity.is_some() && storage.backend != "memory" {
+        warn!(
+            "context.max_contexts only applies to memory storage; ignoring it for {}",
+            storage.backend
+        );
+    }
+
+    match storage.backend.as_str() {
+        "memory" => {
+            let mut repository = match &storage.wal_path {
+                Some(wal_path) => {
+                    info!("Replaying write-ahead log at {}", wal_path);
+                    InMemoryContextRepository::with_wal(wal_path, storage.wal_compact_bytes)?
+                }
+                None => InMemoryContextRepository::new(),
+            };
+            if let Some((max_contexts, policy)) = capacity {
+                info!("Holding at most {} contexts in memory", max_contexts);
+                repository.limit_capacity(max_contexts, policy);
+            }
+            Ok(Arc::new(repository))
+        }
+        #[cfg(feature = "rocksdb")]
+        "rocksdb" => {
+            info!("Opening RocksDB at {}", storage.rocksdb_path);
+            Ok(Arc::new(RocksDbContextRepository::open(
+                &storage.rocksdb_path,
+            )?))
+        }
+        #[cfg(feature = "mongodb")]
+        "mongodb" => {
+            info!(
+                "Connecting to MongoDB database {}",
+                storage.mongodb_database
+            );
+            Ok(Arc::new(
+                MongoContextRepository::connect(&storage.mongodb_uri, &storage.mongodb_database)
+                    .await?,
+            ))
+        }
+        other => Err(McpError::ValidationError(format!(
+            "Unsupported storage backend '{}' (supported: {})",
+            other,
+            supported_backends().join(", ")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_default_backend_is_memory() {
+        let config = AppConfig::load_defaults().unwrap();
+        assert_eq!(config.storage.backend, "memory");
+
+        let repository = create_repository(&config).await.unwrap();
+        assert!(repository.list_all(10, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_backend_lists_supported_values() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.storage.backend = "cassandra".to_string();
+
+        let Err(McpError::ValidationError(message)) = create_repository(&config).await else {
+            panic!("expected a validation error");
+        };
+        assert!(message.contains("cassandra"));
+        for backend in supported_backends() {
+            assert!(message.contains(backend));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_capacity_from_config() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.context.max_contexts = Some(1);
+        config.context.capacity_policy = "reject".to_string();
+
+        let repository = create_repository(&config).await.unwrap();
+        let context = |content: &str| crate::domain::Context::new(content, Default::default());
+        repository.save_context(context("first")).await.unwrap();
+        assert!(matches!(
+            repository.save_context(context("second")).await,
+            Err(McpError::ContextLimitExceeded)
+        ));
+
+        config.context.capacity_policy = "drop-oldest".to_string();
+        assert!(matches!(
+            create_repository(&config).await,
+            Err(McpError::ValidationError(_))
+        ));
+    }
+}
@@ -0,0 +1,60 @@
+use std::sync::Arc;
+use tracing::info;
+
+use super::{CohereReranker, PassThroughReranker};
+use crate::config::{AppConfig, RerankProvider};
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::RerankerPort;
+
+/// Create the reranker selected by `context.rerank.provider`, if reranking is enabled
+///
+/// Fails if a setting the provider needs is missing.
+pub fn create_reranker(
+    config: &AppConfig,
+) -> McpResult<Option<Arc<dyn RerankerPort + Send + Sync>>> {
+    let config = &config.context.rerank;
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    match config.provider {
+        RerankProvider::PassThrough => Ok(Some(Arc::new(PassThroughReranker))),
+        RerankProvider::Cohere => {
+            let api_key = config.api_key.as_deref().ok_or_else(|| {
+                McpError::ValidationError(
+                    "context.rerank.api_key is required for the cohere reranker".to_string(),
+                )
+            })?;
+
+            info!("Reranking search matches with model {}", config.model);
+            Ok(Some(Arc::new(CohereReranker::new(
+                api_key,
+                &config.model,
+                &config.api_base,
+            )?)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reranking_is_off_unless_enabled() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        assert!(create_reranker(&config).unwrap().is_none());
+
+        config.context.rerank.enabled = true;
+        assert!(create_reranker(&config).unwrap().is_some());
+
+        config.context.rerank.provider = RerankProvider::Cohere;
+        let Err(McpError::ValidationError(message)) = create_reranker(&config) else {
+            panic!("expected a validation error");
+        };
+        assert!(message.contains("context.rerank.api_key"));
+
+        config.context.rerank.api_key = Some("co-test".to_string());
+        assert!(create_reranker(&config).unwrap().is_some());
+    }
+}
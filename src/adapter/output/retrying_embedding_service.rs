@@ -0,0 +1,165 @@
+use async_trait::async_trait;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::domain::McpResult;
+use crate::ports::out_ports::EmbeddingPort;
+
+/// Embedding service that retries transient failures of the one it wraps
+///
+/// Attempts are spaced by exponential backoff from `base_delay`, each delay extended by up to
+/// half of it at random so that concurrent callers don't retry in lockstep.
+pub struct RetryingEmbeddingService<T> {
+    inner: T,
+    max_attempts: u32,
+    base_delay: Duration,
+}
+
+impl<T: EmbeddingPort + Send + Sync> RetryingEmbeddingService<T> {
+    /// Attempt each call up to `max_attempts` times, waiting about `base_delay` after the first
+    pub fn new(inner: T, max_attempts: u32, base_delay: Duration) -> Self {
+        Self {
+            inner,
+            max_attempts: max_attempts.max(1),
+            base_delay,
+        }
+    }
+
+    /// Delay before the given retry, counting from 1
+    fn backoff(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry - 1));
+        delay + jitter(delay / 2)
+    }
+
+    async fn retry<R, F, Fut>(&self, mut operation: F) -> McpResult<R>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = McpResult<R>>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation().await {
+                Err(err) if err.is_transient() && attempt < self.max_attempts => {
+                    let backoff = self.backoff(attempt);
+                    warn!(
+                        "Embedding failed ({}), retrying in {:?} ({}/{})",
+                        err, backoff, attempt, self.max_attempts
+                    );
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// A random duration of at most `max`
+fn jitter(max: Duration) -> Duration {
+    // Every `RandomState` is seeded differently, which is random enough to spread retries
+    let random = RandomState::new().build_hasher().finish();
+    max.mul_f64(random as f64 / u64::MAX as f64)
+}
+
+#[async_trait]
+impl<T: EmbeddingPort + Send + Sync> EmbeddingPort for RetryingEmbeddingService<T> {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        self.retry(|| self.inner.embed_texts(texts)).await
+    }
+
+    async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>> {
+        self.retry(|| self.inner.embed_query(query)).await
+    }
+
+    /// Not retried, so a readiness check reports a struggling provider promptly
+    async fn check_health(&self) -> McpResult<()> {
+        self.inner.check_health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::McpError;
+    use mockall::{mock, Sequence};
+    use std::time::Instant;
+
+    mock! {
+        EmbeddingService {}
+        #[async_trait]
+        impl EmbeddingPort for EmbeddingService {
+            async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>>;
+            async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>>;
+        }
+    }
+
+    fn unavailable() -> McpError {
+        McpError::ExternalServiceError("503 Service Unavailable".to_string())
+    }
+
+    #[tokio::test]
+    async fn test_transient_failures_are_retried_with_backoff() {
+        let mut inner = MockEmbeddingService::new();
+        let mut sequence = Sequence::new();
+        inner
+            .expect_embed_texts()
+            .times(2)
+            .in_sequence(&mut sequence)
+            .returning(|_| Err(unavailable()));
+        inner
+            .expect_embed_texts()
+            .times(1)
+            .in_sequence(&mut sequence)
+            .returning(|_| Ok(Vec::new()));
+
+        let service = RetryingEmbeddingService::new(inner, 3, Duration::from_millis(20));
+        let started = Instant::now();
+        service.embed_texts(&[]).await.unwrap();
+        let elapsed = started.elapsed();
+
+        // 20ms then 40ms, each with up to half again of jitter
+        assert!(elapsed >= Duration::from_millis(60), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(90 + 200), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let mut inner = MockEmbeddingService::new();
+        inner
+            .expect_embed_query()
+            .times(3)
+            .returning(|_| Err(unavailable()));
+
+        let service = RetryingEmbeddingService::new(inner, 3, Duration::from_millis(1));
+        let err = service.embed_query("query").await.unwrap_err();
+        assert!(err.is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failures_are_not_retried() {
+        let mut inner = MockEmbeddingService::new();
+        inner
+            .expect_embed_query()
+            .times(1)
+            .returning(|_| Err(McpError::AuthenticationError("invalid key".to_string())));
+        inner
+            .expect_embed_texts()
+            .times(1)
+            .returning(|_| Err(McpError::ValidationError("empty input".to_string())));
+
+        let service = RetryingEmbeddingService::new(inner, 5, Duration::from_millis(1));
+        assert!(matches!(
+            service.embed_query("query").await,
+            Err(McpError::AuthenticationError(_))
+        ));
+        assert!(matches!(
+            service.embed_texts(&[]).await,
+            Err(McpError::ValidationError(_))
+        ));
+    }
+}
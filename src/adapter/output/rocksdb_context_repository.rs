@@ -0,0 +1,1150 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use rocksdb::{ColumnFamily, ColumnFamilyDescriptor, IteratorMode, Options, WriteBatch, DB};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use crate::domain::{Collection, Context, ContextChunk, ContextRevision, McpError, McpResult};
+use crate::ports::out_ports::ContextRepositoryPort;
+
+/// Column family holding serialized contexts keyed by context id
+const CF_CONTEXTS: &str = "contexts";
+
+/// Column family holding chunks keyed by context id + position
+const CF_CHUNKS: &str = "chunks";
+
+/// Column family indexing contexts by tag, keyed by tag + NUL + context id
+const CF_TAGS: &str = "tag_index";
+
+/// Column family mapping chunk ids to their keys in `CF_CHUNKS`
+const CF_CHUNK_INDEX: &str = "chunk_index";
+
+/// Column family holding serialized revisions keyed by context id + version
+const CF_REVISIONS: &str = "revisions";
+
+/// Column family holding serialized collections keyed by collection id
+const CF_COLLECTIONS: &str = "collections";
+
+/// RocksDB-backed implementation of the context repository
+/// Suitable for context volumes that don't fit in memory
+pub struct RocksDbContextRepository {
+    db: DB,
+    // Held by updates from reading the stored version until the write, so two updates
+    // expecting the same version can't both pass the check, and by collection saves so two
+    // collections can't take the same name
+    update_lock: Mutex<()>,
+}
+
+/// Chunk fields stored as JSON; the context id lives in the key and the
+/// embedding is appended as raw little-endian f32 bytes
+#[derive(Serialize, Deserialize)]
+struct ChunkRecord {
+    chunk_id: Uuid,
+    content: String,
+    position: usize,
+}
+
+impl RocksDbContextRepository {
+    /// Open (or create) a database at the given path
+    pub fn open(path: impl AsRef<Path>) -> McpResult<Self> {
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        options.create_missing_column_families(true);
+
+        let column_families = [
+            CF_CONTEXTS,
+            CF_CHUNKS,
+            CF_TAGS,
+            CF_CHUNK_INDEX,
+            CF_REVISIONS,
+            CF_COLLECTIONS,
+        ]
+        .into_iter()
+        .map(|name| ColumnFamilyDescriptor::new(name, Options::default()));
+
+        let db = DB::open_cf_descriptors(&options, path, column_families).map_err(storage_error)?;
+        let repository = Self {
+            db,
+            update_lock: Mutex::new(()),
+        };
+        repository.index_existing_chunks()?;
+        Ok(repository)
+    }
+
+    /// Build the chunk index of a database written before it existed
+    fn index_existing_chunks(&self) -> McpResult<()> {
+        let index_cf = self.cf(CF_CHUNK_INDEX)?;
+        if self
+            .db
+            .iterator_cf(index_cf, IteratorMode::Start)
+            .next()
+            .is_some()
+        {
+            return Ok(());
+        }
+
+        let mut batch = WriteBatch::default();
+        for item in self
+            .db
+            .iterator_cf(self.cf(CF_CHUNKS)?, IteratorMode::Start)
+        {
+            let (key, value) = item.map_err(storage_error)?;
+            let chunk = decode_chunk(context_id_of_chunk_key(&key)?, &value)?;
+            batch.put_cf(index_cf, chunk.chunk_id.as_bytes(), &key);
+        }
+        self.db.write(batch).map_err(storage_error)
+    }
+
+    fn cf(&self, name: &str) -> McpResult<&ColumnFamily> {
+        self.db
+            .cf_handle(name)
+            .ok_or_else(|| McpError::StorageError(format!("Missing column family: {}", name)))
+    }
+
+    fn read_collections(&self) -> McpResult<Vec<Collection>> {
+        let mut collections = Vec::new();
+        for item in self
+            .db
+            .iterator_cf(self.cf(CF_COLLECTIONS)?, IteratorMode::Start)
+        {
+            let (_, value) = item.map_err(storage_error)?;
+            collections.push(serde_json::from_slice(&value).map_err(serialization_error)?);
+        }
+        Ok(collections)
+    }
+
+    fn get_context(&self, context_id: Uuid) -> McpResult<Option<Context>> {
+        self.db
+            .get_cf(self.cf(CF_CONTEXTS)?, context_id.as_bytes())
+            .map_err(storage_error)?
+            .map(|bytes| decode_context(&bytes))
+            .transpose()
+    }
+
+    /// Add writes storing a context and its tag index entries, replacing `previous`
+    fn stage_context(
+        &self,
+        batch: &mut WriteBatch,
+        context: &Context,
+        previous: Option<&Context>,
+    ) -> McpResult<()> {
+        for tag in previous.iter().flat_map(|previous| &previous.metadata.tags) {
+            batch.delete_cf(self.cf(CF_TAGS)?, tag_key(tag, context.id));
+        }
+        for tag in &context.metadata.tags {
+            batch.put_cf(self.cf(CF_TAGS)?, tag_key(tag, context.id), b"");
+        }
+        batch.put_cf(
+            self.cf(CF_CONTEXTS)?,
+            context.id.as_bytes(),
+            serde_json::to_vec(context).map_err(serialization_error)?,
+        );
+        Ok(())
+    }
+
+    /// Add writes replacing all chunks of a context with `chunks`
+    fn stage_chunks(
+        &self,
+        batch: &mut WriteBatch,
+        context_id: Uuid,
+        chunks: &[ContextChunk],
+    ) -> McpResult<()> {
+        self.stage_chunk_removal(batch, context_id)?;
+
+        let cf = self.cf(CF_CHUNKS)?;
+        let index_cf = self.cf(CF_CHUNK_INDEX)?;
+        for chunk in chunks {
+            let key = chunk_key(chunk);
+            batch.put_cf(index_cf, chunk.chunk_id.as_bytes(), &key);
+            batch.put_cf(cf, key, encode_chunk(chunk)?);
+        }
+        Ok(())
+    }
+
+    /// Add writes deleting all chunks of a context and their chunk index entries
+    fn stage_chunk_removal(&self, batch: &mut WriteBatch, context_id: Uuid) -> McpResult<()> {
+        let cf = self.cf(CF_CHUNKS)?;
+        let index_cf = self.cf(CF_CHUNK_INDEX)?;
+
+        for item in self.db.prefix_iterator_cf(cf, context_id.as_bytes()) {
+            let (key, value) = item.map_err(storage_error)?;
+            if !key.starts_with(context_id.as_bytes()) {
+                break;
+            }
+            let chunk = decode_chunk(context_id, &value)?;
+            batch.delete_cf(index_cf, chunk.chunk_id.as_bytes());
+            batch.delete_cf(cf, key);
+        }
+        Ok(())
+    }
+
+    /// Collect all keys in a column family that start with the given prefix
+    fn keys_with_prefix(&self, cf: &ColumnFamily, prefix: &[u8]) -> McpResult<Vec<Box<[u8]>>> {
+        let mut keys = Vec::new();
+
+        for item in self.db.prefix_iterator_cf(cf, prefix) {
+            let (key, _) = item.map_err(storage_error)?;
+
+            // Without a prefix extractor the iterator runs past the prefix
+            if !key.starts_with(prefix) {
+                break;
+            }
+            keys.push(key);
+        }
+
+        Ok(keys)
+    }
+}
+
+fn storage_error(err: rocksdb::Error) -> McpError {
+    McpError::StorageError(err.to_string())
+}
+
+fn serialization_error(err: serde_json::Error) -> McpError {
+    McpError::SerializationError(err.to_string())
+}
+
+fn tag_key(tag: &str, context_id: Uuid) -> Vec<u8> {
+    let mut key = tag_prefix(tag);
+    key.extend_from_slice(context_id.as_bytes());
+    key
+}
+
+fn tag_prefix(tag: &str) -> Vec<u8> {
+    let mut prefix = tag.as_bytes().to_vec();
+    prefix.push(0);
+    prefix
+}
+
+fn chunk_key(chunk: &ContextChunk) -> Vec<u8> {
+    let mut key = chunk.context_id.as_bytes().to_vec();
+    key.extend_from_slice(&(chunk.position as u64).to_be_bytes());
+    key
+}
+
+/// The context id a chunk key starts with
+fn revision_key(context_id: Uuid, version: u64) -> Vec<u8> {
+    let mut key = context_id.as_bytes().to_vec();
+    key.extend_from_slice(&version.to_be_bytes());
+    key
+}
+
+fn context_id_of_chunk_key(key: &[u8]) -> McpResult<Uuid> {
+    key.get(..16)
+        .and_then(|bytes| Uuid::from_slice(bytes).ok())
+        .ok_or_else(|| McpError::SerializationError("Corrupt chunk key".to_string()))
+}
+
+fn decode_context(bytes: &[u8]) -> McpResult<Context> {
+    serde_json::from_slice(bytes).map_err(serialization_error)
+}
+
+/// Encode a chunk as `[u32 record length][record JSON][u8 has embedding][f32 LE...]`
+fn encode_chunk(chunk: &ContextChunk) -> McpResult<Vec<u8>> {
+    let record = serde_json::to_vec(&ChunkRecord {
+        chunk_id: chunk.chunk_id,
+        content: chunk.content.clone(),
+        position: chunk.position,
+    })
+    .map_err(serialization_error)?;
+
+    let embedding_len = chunk.embedding.as_ref().map_or(0, |e| e.len() * 4);
+    let mut bytes = Vec::with_capacity(4 + record.len() + 1 + embedding_len);
+    bytes.extend_from_slice(&(record.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&record);
+
+    match &chunk.embedding {
+        Some(embedding) => {
+            bytes.push(1);
+            for value in embedding {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        None => bytes.push(0),
+    }
+
+    Ok(bytes)
+}
+
+fn decode_chunk(context_id: Uuid, bytes: &[u8]) -> McpResult<ContextChunk> {
+    let corrupt = || McpError::SerializationError("Corrupt chunk record".to_string());
+
+    let record_len = bytes
+        .get(..4)
+        .and_then(|b| b.try_into().ok())
+        .map(u32::from_le_bytes)
+        .ok_or_else(corrupt)? as usize;
+    let record_bytes = bytes.get(4..4 + record_len).ok_or_else(corrupt)?;
+    let record: ChunkRecord = serde_json::from_slice(record_bytes).map_err(serialization_error)?;
+
+    let rest = &bytes[4 + record_len..];
+    let embedding = match rest.split_first() {
+        Some((1, values)) if values.len() % 4 == 0 => Some(
+            values
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+        ),
+        Some((0, [])) => None,
+        _ => return Err(corrupt()),
+    };
+
+    Ok(ContextChunk {
+        context_id,
+        chunk_id: record.chunk_id,
+        content: record.content,
+        embedding,
+        position: record.position,
+    })
+}
+
+#[async_trait]
+impl ContextRepositoryPort for RocksDbContextRepository {
+    async fn save_context(&self, context: Context) -> McpResult<Context> {
+        if self.get_context(context.id)?.is_some() {
+            return Err(McpError::ContextAlreadyExists(context.id));
+        }
+
+        let mut batch = WriteBatch::default();
+        self.stage_context(&mut batch, &context, None)?;
+        self.db.write(batch).map_err(storage_error)?;
+
+        Ok(context)
+    }
+
+    async fn find_by_id(&self, context_id: Uuid) -> McpResult<Context> {
+        self.get_context(context_id)?
+            .ok_or(McpError::ContextNotFound(context_id))
+    }
+
+    async fn find_by_ids(&self, context_ids: &[Uuid]) -> McpResult<Vec<Context>> {
+        let cf = self.cf(CF_CONTEXTS)?;
+
+        let mut contexts = Vec::with_capacity(context_ids.len());
+        for value in self
+            .db
+            .multi_get_cf(context_ids.iter().map(|id| (cf, id.as_bytes())))
+        {
+            if let Some(bytes) = value.map_err(storage_error)? {
+                contexts.push(decode_context(&bytes)?);
+            }
+        }
+
+        Ok(contexts)
+    }
+
+    async fn update(
+        &self,
+        mut context: Context,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let _guard = self
+            .update_lock
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let existing = self
+            .get_context(context.id)?
+            .ok_or(McpError::ContextNotFound(context.id))?;
+        existing.check_version(expected_version)?;
+        context.version = existing.version + 1;
+
+        let mut batch = WriteBatch::default();
+        self.stage_context(&mut batch, &context, Some(&existing))?;
+        self.db.write(batch).map_err(storage_error)?;
+
+        Ok(context)
+    }
+
+    async fn save_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context> {
+        if self.get_context(context.id)?.is_some() {
+            return Err(McpError::ContextAlreadyExists(context.id));
+        }
+
+        // A single batch commits the context, tags, and chunks together
+        let mut batch = WriteBatch::default();
+        self.stage_context(&mut batch, &context, None)?;
+        self.stage_chunks(&mut batch, context.id, &chunks)?;
+        self.db.write(batch).map_err(storage_error)?;
+
+        Ok(context)
+    }
+
+    async fn replace_context_with_chunks(
+        &self,
+        mut context: Context,
+        chunks: Vec<ContextChunk>,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let _guard = self
+            .update_lock
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let existing = self
+            .get_context(context.id)?
+            .ok_or(McpError::ContextNotFound(context.id))?;
+        existing.check_version(expected_version)?;
+        context.version = existing.version + 1;
+
+        let mut batch = WriteBatch::default();
+        self.stage_context(&mut batch, &context, Some(&existing))?;
+        self.stage_chunks(&mut batch, context.id, &chunks)?;
+        self.db.write(batch).map_err(storage_error)?;
+
+        Ok(context)
+    }
+
+    async fn delete(&self, context_id: Uuid) -> McpResult<()> {
+        let existing = self
+            .get_context(context_id)?
+            .ok_or(McpError::ContextNotFound(context_id))?;
+
+        let mut batch = WriteBatch::default();
+        for tag in &existing.metadata.tags {
+            batch.delete_cf(self.cf(CF_TAGS)?, tag_key(tag, context_id));
+        }
+        batch.delete_cf(self.cf(CF_CONTEXTS)?, context_id.as_bytes());
+        let revisions_cf = self.cf(CF_REVISIONS)?;
+        for key in self.keys_with_prefix(revisions_cf, context_id.as_bytes())? {
+            batch.delete_cf(revisions_cf, key);
+        }
+        self.db.write(batch).map_err(storage_error)
+    }
+
+    async fn save_revision(
+        &self,
+        revision: ContextRevision,
+        max_revisions: usize,
+    ) -> McpResult<()> {
+        let cf = self.cf(CF_REVISIONS)?;
+        let context_id = revision.context_id;
+        let saved_key = revision_key(context_id, revision.version);
+
+        // Keys sort by version, so the oldest revisions come first and are dropped first
+        let mut others = self.keys_with_prefix(cf, context_id.as_bytes())?;
+        others.retain(|key| key[..] != saved_key[..]);
+
+        let mut batch = WriteBatch::default();
+        let kept = if max_revisions == 0 {
+            batch.delete_cf(cf, &saved_key);
+            0
+        } else {
+            batch.put_cf(
+                cf,
+                &saved_key,
+                serde_json::to_vec(&revision).map_err(serialization_error)?,
+            );
+            max_revisions - 1
+        };
+        let excess = others.len().saturating_sub(kept);
+        for key in others.into_iter().take(excess) {
+            batch.delete_cf(cf, key);
+        }
+        self.db.write(batch).map_err(storage_error)
+    }
+
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>> {
+        let cf = self.cf(CF_REVISIONS)?;
+        let mut revisions = Vec::new();
+
+        for item in self.db.prefix_iterator_cf(cf, context_id.as_bytes()) {
+            let (key, value) = item.map_err(storage_error)?;
+            if !key.starts_with(context_id.as_bytes()) {
+                break;
+            }
+            revisions.push(serde_json::from_slice(&value).map_err(serialization_error)?);
+        }
+        Ok(revisions)
+    }
+
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision> {
+        let bytes = self
+            .db
+            .get_cf(self.cf(CF_REVISIONS)?, revision_key(context_id, version))
+            .map_err(storage_error)?
+            .ok_or(McpError::RevisionNotFound {
+                context_id,
+                version,
+            })?;
+        serde_json::from_slice(&bytes).map_err(serialization_error)
+    }
+
+    async fn save_collection(&self, collection: Collection) -> McpResult<Collection> {
+        let _guard = self
+            .update_lock
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if self
+            .read_collections()?
+            .iter()
+            .any(|other| other.name == collection.name && other.id != collection.id)
+        {
+            return Err(McpError::CollectionExists(collection.name));
+        }
+
+        self.db
+            .put_cf(
+                self.cf(CF_COLLECTIONS)?,
+                collection.id.as_bytes(),
+                serde_json::to_vec(&collection).map_err(serialization_error)?,
+            )
+            .map_err(storage_error)?;
+        Ok(collection)
+    }
+
+    async fn find_collection(&self, collection_id: Uuid) -> McpResult<Collection> {
+        let bytes = self
+            .db
+            .get_cf(self.cf(CF_COLLECTIONS)?, collection_id.as_bytes())
+            .map_err(storage_error)?
+            .ok_or(McpError::CollectionNotFound(collection_id))?;
+        serde_json::from_slice(&bytes).map_err(serialization_error)
+    }
+
+    async fn list_collections(&self) -> McpResult<Vec<Collection>> {
+        self.read_collections()
+    }
+
+    async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()> {
+        let cf = self.cf(CF_COLLECTIONS)?;
+        if self
+            .db
+            .get_cf(cf, collection_id.as_bytes())
+            .map_err(storage_error)?
+            .is_none()
+        {
+            return Err(McpError::CollectionNotFound(collection_id));
+        }
+        self.db
+            .delete_cf(cf, collection_id.as_bytes())
+            .map_err(storage_error)
+    }
+
+    async fn find_by_tags(
+        &self,
+        tags: &[String],
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        // Use the index for the first tag to find candidates, then check the rest
+        let Some(first_tag) = tags.first() else {
+            return self.list_all(limit, offset).await;
+        };
+
+        let prefix = tag_prefix(first_tag);
+        let keys = self.keys_with_prefix(self.cf(CF_TAGS)?, &prefix)?;
+
+        let mut matching_contexts = Vec::new();
+        let mut skipped = 0;
+
+        for key in keys {
+            if matching_contexts.len() >= limit {
+                break;
+            }
+
+            let context_id = Uuid::from_slice(&key[prefix.len()..])
+                .map_err(|e| McpError::SerializationError(e.to_string()))?;
+            let Some(context) = self.get_context(context_id)? else {
+                continue;
+            };
+
+            if !tags.iter().all(|tag| context.metadata.tags.contains(tag)) {
+                continue;
+            }
+
+            if skipped < offset {
+                skipped += 1;
+                continue;
+            }
+            matching_contexts.push(context);
+        }
+
+        Ok(matching_contexts)
+    }
+
+    async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>> {
+        // Contexts are keyed by id, so they are listed in id order
+        self.db
+            .iterator_cf(self.cf(CF_CONTEXTS)?, IteratorMode::Start)
+            .skip(offset)
+            .take(limit)
+            .map(|item| {
+                let (_, value) = item.map_err(storage_error)?;
+                decode_context(&value)
+            })
+            .collect()
+    }
+
+    async fn count_all(&self) -> McpResult<usize> {
+        let mut count = 0;
+        for item in self
+            .db
+            .iterator_cf(self.cf(CF_CONTEXTS)?, IteratorMode::Start)
+        {
+            item.map_err(storage_error)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize> {
+        let Some(first_tag) = tags.first() else {
+            return self.count_all().await;
+        };
+
+        let prefix = tag_prefix(first_tag);
+        let keys = self.keys_with_prefix(self.cf(CF_TAGS)?, &prefix)?;
+
+        // The index alone answers a single tag; more tags need the contexts themselves
+        if tags.len() == 1 {
+            return Ok(keys.len());
+        }
+
+        let mut count = 0;
+        for key in keys {
+            let context_id = Uuid::from_slice(&key[prefix.len()..])
+                .map_err(|e| McpError::SerializationError(e.to_string()))?;
+            if let Some(context) = self.get_context(context_id)? {
+                if tags.iter().all(|tag| context.metadata.tags.contains(tag)) {
+                    count += 1;
+                }
+            }
+        }
+        Ok(count)
+    }
+
+    async fn tag_counts(&self, now: DateTime<Utc>) -> McpResult<Vec<(String, usize)>> {
+        // The tag index has one key per tag and context, in tag order, but only live
+        // contexts are counted
+        let mut counts: Vec<(String, usize)> = Vec::new();
+        for item in self.db.iterator_cf(self.cf(CF_TAGS)?, IteratorMode::Start) {
+            let (key, _) = item.map_err(storage_error)?;
+            let tag_len = key
+                .iter()
+                .position(|&byte| byte == 0)
+                .ok_or_else(|| McpError::SerializationError("Corrupt tag index key".to_string()))?;
+            let context_id = Uuid::from_slice(&key[tag_len + 1..])
+                .map_err(|e| McpError::SerializationError(e.to_string()))?;
+            let live = self
+                .get_context(context_id)?
+                .is_some_and(|context| context.is_live_at(now));
+            if !live {
+                continue;
+            }
+
+            let tag = String::from_utf8_lossy(&key[..tag_len]);
+            match counts.last_mut() {
+                Some((last, count)) if *last == tag => *count += 1,
+                _ => counts.push((tag.into_owned(), 1)),
+            }
+        }
+        Ok(counts)
+    }
+
+    async fn exists(&self, context_id: Uuid) -> McpResult<bool> {
+        Ok(self
+            .db
+            .get_pinned_cf(self.cf(CF_CONTEXTS)?, context_id.as_bytes())
+            .map_err(storage_error)?
+            .is_some())
+    }
+
+    async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>> {
+        if chunks.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // Saving chunks replaces any existing chunks for the context
+        let mut batch = WriteBatch::default();
+        self.stage_chunks(&mut batch, chunks[0].context_id, &chunks)?;
+        self.db.write(batch).map_err(storage_error)?;
+
+        Ok(chunks)
+    }
+
+    async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>> {
+        let cf = self.cf(CF_CHUNKS)?;
+        let mut chunks = Vec::new();
+
+        for item in self.db.prefix_iterator_cf(cf, context_id.as_bytes()) {
+            let (key, value) = item.map_err(storage_error)?;
+            if !key.starts_with(context_id.as_bytes()) {
+                break;
+            }
+            chunks.push(decode_chunk(context_id, &value)?);
+        }
+
+        if chunks.is_empty() && self.get_context(context_id)?.is_none() {
+            return Err(McpError::ContextNotFound(context_id));
+        }
+
+        Ok(chunks)
+    }
+
+    async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk> {
+        let not_found = || McpError::ChunkNotFound(chunk_id);
+
+        let key = self
+            .db
+            .get_cf(self.cf(CF_CHUNK_INDEX)?, chunk_id.as_bytes())
+            .map_err(storage_error)?
+            .ok_or_else(not_found)?;
+        let value = self
+            .db
+            .get_cf(self.cf(CF_CHUNKS)?, &key)
+            .map_err(storage_error)?
+            .ok_or_else(not_found)?;
+        decode_chunk(context_id_of_chunk_key(&key)?, &value)
+    }
+
+    async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()> {
+        let mut batch = WriteBatch::default();
+        self.stage_chunk_removal(&mut batch, context_id)?;
+        self.db.write(batch).map_err(storage_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::ContextMetadata;
+    use std::path::PathBuf;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("mcp-rocksdb-{}", Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn create_test_context(tags: &[&str]) -> Context {
+        Context::new(
+            "RocksDB test content",
+            ContextMetadata {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..Default::default()
+            },
+        )
+    }
+
+    fn create_test_chunk(context_id: Uuid, position: usize) -> ContextChunk {
+        ContextChunk {
+            context_id,
+            chunk_id: Uuid::new_v4(),
+            content: format!("chunk {}", position),
+            embedding: Some(vec![0.25, -1.5, 3.0]),
+            position,
+        }
+    }
+
+    #[test]
+    fn test_chunk_encoding_round_trip() {
+        let context_id = Uuid::new_v4();
+        let mut chunk = create_test_chunk(context_id, 7);
+
+        let decoded = decode_chunk(context_id, &encode_chunk(&chunk).unwrap()).unwrap();
+        assert_eq!(decoded.chunk_id, chunk.chunk_id);
+        assert_eq!(decoded.content, chunk.content);
+        assert_eq!(decoded.position, 7);
+        assert_eq!(decoded.embedding, chunk.embedding);
+
+        chunk.embedding = None;
+        let decoded = decode_chunk(context_id, &encode_chunk(&chunk).unwrap()).unwrap();
+        assert_eq!(decoded.embedding, None);
+
+        assert!(decode_chunk(context_id, &[1, 2]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_crud_and_tag_index() {
+        let dir = TempDir::new();
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+
+        let ai = repository
+            .save_context(create_test_context(&["ai", "nlp"]))
+            .await
+            .unwrap();
+        let rust = repository
+            .save_context(create_test_context(&["rust"]))
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            repository.save_context(ai.clone()).await,
+            Err(McpError::ContextAlreadyExists(_))
+        ));
+
+        let found = repository
+            .find_by_tags(&["ai".to_string()], 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, ai.id);
+
+        // Retagging moves the context in the index
+        let mut retagged = rust.clone();
+        retagged.metadata.tags = vec!["ai".to_string()];
+        repository.update(retagged, None).await.unwrap();
+        assert!(repository
+            .find_by_tags(&["rust".to_string()], 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            repository
+                .find_by_tags(&["ai".to_string()], 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        repository.delete(ai.id).await.unwrap();
+        assert!(matches!(
+            repository.find_by_id(ai.id).await,
+            Err(McpError::ContextNotFound(_))
+        ));
+        assert_eq!(repository.list_all(10, 0).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_counts_follow_tag_index() {
+        let dir = TempDir::new();
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+
+        let ai = repository
+            .save_context(create_test_context(&["ai", "nlp"]))
+            .await
+            .unwrap();
+        repository
+            .save_context(create_test_context(&["ai"]))
+            .await
+            .unwrap();
+
+        let tags = |tags: &[&str]| tags.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert_eq!(repository.count_all().await.unwrap(), 2);
+        assert_eq!(repository.count_by_tags(&tags(&["ai"])).await.unwrap(), 2);
+        assert_eq!(
+            repository
+                .count_by_tags(&tags(&["ai", "nlp"]))
+                .await
+                .unwrap(),
+            1
+        );
+        assert!(repository.exists(ai.id).await.unwrap());
+        assert_eq!(
+            repository.tag_counts(Utc::now()).await.unwrap(),
+            [("ai".to_string(), 2), ("nlp".to_string(), 1)]
+        );
+
+        repository.delete(ai.id).await.unwrap();
+        assert_eq!(repository.count_all().await.unwrap(), 1);
+        assert_eq!(repository.count_by_tags(&tags(&["nlp"])).await.unwrap(), 0);
+        assert_eq!(
+            repository.tag_counts(Utc::now()).await.unwrap(),
+            [("ai".to_string(), 1)]
+        );
+        assert!(!repository.exists(ai.id).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_list_all_pagination() {
+        let dir = TempDir::new();
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+
+        for _ in 0..5 {
+            repository
+                .save_context(create_test_context(&[]))
+                .await
+                .unwrap();
+        }
+
+        let first = repository.list_all(3, 0).await.unwrap();
+        let second = repository.list_all(3, 3).await.unwrap();
+        assert_eq!(first.len(), 3);
+        assert_eq!(second.len(), 2);
+        assert!(first.iter().all(|a| second.iter().all(|b| a.id != b.id)));
+    }
+
+    #[tokio::test]
+    async fn test_chunks_persist_across_reopen() {
+        let dir = TempDir::new();
+        let context = create_test_context(&[]);
+
+        {
+            let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+            repository.save_context(context.clone()).await.unwrap();
+            repository
+                .save_chunks((0..3).map(|p| create_test_chunk(context.id, p)).collect())
+                .await
+                .unwrap();
+        }
+
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+        let chunks = repository
+            .find_chunks_by_context_id(context.id)
+            .await
+            .unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(
+            chunks.iter().map(|c| c.position).collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+        assert_eq!(chunks[0].embedding, Some(vec![0.25, -1.5, 3.0]));
+
+        // Saving again replaces the previous chunks
+        repository
+            .save_chunks(vec![create_test_chunk(context.id, 0)])
+            .await
+            .unwrap();
+        assert_eq!(
+            repository
+                .find_chunks_by_context_id(context.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        repository
+            .delete_chunks_by_context_id(context.id)
+            .await
+            .unwrap();
+        assert!(repository
+            .find_chunks_by_context_id(context.id)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_chunks_are_found_by_id() {
+        let dir = TempDir::new();
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+        let context = create_test_context(&[]);
+        let chunks: Vec<ContextChunk> = (0..2).map(|p| create_test_chunk(context.id, p)).collect();
+        repository
+            .save_context_with_chunks(context.clone(), chunks.clone())
+            .await
+            .unwrap();
+
+        let found = repository
+            .find_chunk_by_id(chunks[1].chunk_id)
+            .await
+            .unwrap();
+        assert_eq!(found.context_id, context.id);
+        assert_eq!(found.position, 1);
+        assert!(matches!(
+            repository.find_chunk_by_id(Uuid::new_v4()).await,
+            Err(McpError::ChunkNotFound(_))
+        ));
+
+        // Replaced chunks drop out of the index, even at the same position
+        let replaced = create_test_chunk(context.id, 0);
+        repository
+            .replace_context_with_chunks(context.clone(), vec![replaced.clone()], None)
+            .await
+            .unwrap();
+        for stale in &chunks {
+            assert!(matches!(
+                repository.find_chunk_by_id(stale.chunk_id).await,
+                Err(McpError::ChunkNotFound(_))
+            ));
+        }
+        assert_eq!(
+            repository
+                .find_chunk_by_id(replaced.chunk_id)
+                .await
+                .unwrap()
+                .chunk_id,
+            replaced.chunk_id
+        );
+
+        repository
+            .delete_chunks_by_context_id(context.id)
+            .await
+            .unwrap();
+        assert!(repository
+            .find_chunk_by_id(replaced.chunk_id)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_context_with_chunks_commits_together() {
+        let dir = TempDir::new();
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+
+        let mut context = create_test_context(&["old"]);
+        let chunks = vec![
+            create_test_chunk(context.id, 0),
+            create_test_chunk(context.id, 1),
+        ];
+        repository
+            .save_context_with_chunks(context.clone(), chunks)
+            .await
+            .unwrap();
+        assert_eq!(
+            repository
+                .find_chunks_by_context_id(context.id)
+                .await
+                .unwrap()
+                .len(),
+            2
+        );
+
+        // Replacing swaps the tag index and all chunks at once
+        context.metadata.tags = vec!["new".to_string()];
+        repository
+            .replace_context_with_chunks(
+                context.clone(),
+                vec![create_test_chunk(context.id, 0)],
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(repository
+            .find_by_tags(&["old".to_string()], 10, 0)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            repository
+                .find_by_tags(&["new".to_string()], 10, 0)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            repository
+                .find_chunks_by_context_id(context.id)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // Replacing a missing context writes nothing
+        let missing = create_test_context(&["new"]);
+        assert!(matches!(
+            repository
+                .replace_context_with_chunks(
+                    missing.clone(),
+                    vec![create_test_chunk(missing.id, 0)],
+                    None,
+                )
+                .await,
+            Err(McpError::ContextNotFound(_))
+        ));
+        assert!(repository
+            .find_chunks_by_context_id(missing.id)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_revisions_are_pruned_and_deleted_with_their_context() {
+        let dir = TempDir::new();
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+        let mut context = repository
+            .save_context(create_test_context(&[]))
+            .await
+            .unwrap();
+        // A neighbouring context's revisions share no keys with it
+        let other = repository
+            .save_context(create_test_context(&[]))
+            .await
+            .unwrap();
+        repository
+            .save_revision(ContextRevision::of(&other, Utc::now()), 3)
+            .await
+            .unwrap();
+
+        for _ in 0..4 {
+            repository
+                .save_revision(ContextRevision::of(&context, Utc::now()), 3)
+                .await
+                .unwrap();
+            context.content = format!("version {}", context.version + 1);
+            context = repository.update(context, None).await.unwrap();
+        }
+        let versions: Vec<u64> = repository
+            .list_revisions(context.id)
+            .await
+            .unwrap()
+            .iter()
+            .map(|revision| revision.version)
+            .collect();
+        assert_eq!(versions, [2, 3, 4]);
+        assert_eq!(
+            repository
+                .get_revision(context.id, 4)
+                .await
+                .unwrap()
+                .content,
+            "version 4"
+        );
+        assert!(matches!(
+            repository.get_revision(context.id, 1).await,
+            Err(McpError::RevisionNotFound { version: 1, .. })
+        ));
+
+        repository.delete(context.id).await.unwrap();
+        assert!(repository
+            .list_revisions(context.id)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(repository.list_revisions(other.id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_collections_persist_across_reopen() {
+        let dir = TempDir::new();
+        let collection = Collection {
+            id: Uuid::new_v4(),
+            name: "project-alpha".to_string(),
+            description: Some("Design docs".to_string()),
+            created_at: Utc::now(),
+        };
+        {
+            let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+            repository
+                .save_collection(collection.clone())
+                .await
+                .unwrap();
+            let taken = Collection {
+                id: Uuid::new_v4(),
+                ..collection.clone()
+            };
+            assert!(matches!(
+                repository.save_collection(taken).await,
+                Err(McpError::CollectionExists(_))
+            ));
+        }
+
+        let repository = RocksDbContextRepository::open(&dir.0).unwrap();
+        assert_eq!(
+            repository.list_collections().await.unwrap(),
+            vec![collection.clone()]
+        );
+        repository.delete_collection(collection.id).await.unwrap();
+        assert!(matches!(
+            repository.find_collection(collection.id).await,
+            Err(McpError::CollectionNotFound(_))
+        ));
+    }
+}
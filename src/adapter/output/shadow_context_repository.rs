@@ -0,0 +1,765 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::domain::{
+    Collection, Context, ContextChunk, ContextFilter, ContextRevision, McpError, McpResult,
+};
+use crate::ports::out_ports::ContextRepositoryPort;
+
+type Repository = Arc<dyn ContextRepositoryPort + Send + Sync>;
+
+/// Repository decorator that mirrors every mutation to a secondary repository
+///
+/// The primary is the source of truth: mirroring happens in the background
+/// through a bounded queue, and secondary failures are only logged and counted.
+/// A sample of reads is compared against the secondary to detect divergence.
+pub struct ShadowContextRepository {
+    primary: Repository,
+    mirror: mpsc::Sender<MirrorOp>,
+    read_sample_rate: f64,
+    reads: AtomicU64,
+    metrics: Arc<ShadowMetrics>,
+}
+
+/// Work queued for the secondary repository
+enum MirrorOp {
+    SaveContext(Context),
+    Update(Context),
+    SaveContextWithChunks(Context, Vec<ContextChunk>),
+    ReplaceContextWithChunks(Context, Vec<ContextChunk>),
+    Delete(Uuid),
+    SaveRevision(ContextRevision, usize),
+    SaveCollection(Collection),
+    DeleteCollection(Uuid),
+    SaveChunks(Vec<ContextChunk>),
+    DeleteChunks(Uuid),
+    CompareContext(Context),
+    CompareChunks(Uuid, Vec<ContextChunk>),
+}
+
+impl MirrorOp {
+    fn is_mutation(&self) -> bool {
+        !matches!(
+            self,
+            MirrorOp::CompareContext(_) | MirrorOp::CompareChunks(_, _)
+        )
+    }
+}
+
+/// Counters describing how well the secondary keeps up with the primary
+#[derive(Debug, Default)]
+pub struct ShadowMetrics {
+    mirrored: AtomicU64,
+    mirror_failures: AtomicU64,
+    mirror_dropped: AtomicU64,
+    mirror_lag: AtomicU64,
+    reads_compared: AtomicU64,
+    read_missing: AtomicU64,
+    read_mismatches: AtomicU64,
+    // Every queued operation, including comparisons
+    pending: AtomicU64,
+}
+
+/// Point-in-time copy of the shadow counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ShadowMetricsSnapshot {
+    /// Mutations applied to the secondary
+    pub mirrored: u64,
+
+    /// Mutations the secondary rejected
+    pub mirror_failures: u64,
+
+    /// Mutations dropped because the queue was full
+    pub mirror_dropped: u64,
+
+    /// Mutations queued but not yet applied to the secondary
+    pub mirror_lag: u64,
+
+    /// Reads compared against the secondary
+    pub reads_compared: u64,
+
+    /// Compared reads the secondary had no record for
+    pub read_missing: u64,
+
+    /// Compared reads where the secondary returned different data
+    pub read_mismatches: u64,
+}
+
+impl ShadowMetrics {
+    pub fn snapshot(&self) -> ShadowMetricsSnapshot {
+        ShadowMetricsSnapshot {
+            mirrored: self.mirrored.load(Ordering::Relaxed),
+            mirror_failures: self.mirror_failures.load(Ordering::Relaxed),
+            mirror_dropped: self.mirror_dropped.load(Ordering::Relaxed),
+            mirror_lag: self.mirror_lag.load(Ordering::Relaxed),
+            reads_compared: self.reads_compared.load(Ordering::Relaxed),
+            read_missing: self.read_missing.load(Ordering::Relaxed),
+            read_mismatches: self.read_mismatches.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl ShadowContextRepository {
+    /// Wrap `primary`, mirroring to `secondary` through a queue of `queue_capacity` operations
+    /// and comparing `read_sample_rate` (0.0 to 1.0) of reads. Must be called within a Tokio runtime.
+    pub fn new(
+        primary: Repository,
+        secondary: Repository,
+        queue_capacity: usize,
+        read_sample_rate: f64,
+    ) -> Self {
+        let (mirror, queue) = mpsc::channel(queue_capacity.max(1));
+        let metrics = Arc::new(ShadowMetrics::default());
+
+        tokio::spawn(run_mirror(secondary, queue, metrics.clone()));
+
+        Self {
+            primary,
+            mirror,
+            read_sample_rate: read_sample_rate.clamp(0.0, 1.0),
+            reads: AtomicU64::new(0),
+            metrics,
+        }
+    }
+
+    /// Counters for mirror lag, failures, and read diffs
+    pub fn metrics(&self) -> Arc<ShadowMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Log the counters every `interval` for as long as the runtime lives
+    pub fn report_metrics(&self, interval: Duration) {
+        let metrics = self.metrics.clone();
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            loop {
+                interval.tick().await;
+                let snapshot = metrics.snapshot();
+                info!(
+                    mirrored = snapshot.mirrored,
+                    mirror_failures = snapshot.mirror_failures,
+                    mirror_dropped = snapshot.mirror_dropped,
+                    mirror_lag = snapshot.mirror_lag,
+                    reads_compared = snapshot.reads_compared,
+                    read_missing = snapshot.read_missing,
+                    read_mismatches = snapshot.read_mismatches,
+                    "Shadow repository metrics"
+                );
+            }
+        });
+    }
+
+    fn enqueue(&self, op: MirrorOp) {
+        let is_mutation = op.is_mutation();
+        self.metrics.pending.fetch_add(1, Ordering::Relaxed);
+        if is_mutation {
+            self.metrics.mirror_lag.fetch_add(1, Ordering::Relaxed);
+        }
+
+        if self.mirror.try_send(op).is_err() {
+            self.metrics.pending.fetch_sub(1, Ordering::Relaxed);
+            if is_mutation {
+                self.metrics.mirror_lag.fetch_sub(1, Ordering::Relaxed);
+                self.metrics.mirror_dropped.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "Shadow mirror queue is full; dropping mutation for the secondary repository"
+                );
+            }
+        }
+    }
+
+    /// Decide whether this read is compared, spreading samples evenly at the configured rate
+    fn sample_read(&self) -> bool {
+        if self.read_sample_rate <= 0.0 {
+            return false;
+        }
+
+        let n = self.reads.fetch_add(1, Ordering::Relaxed) as f64;
+        (n * self.read_sample_rate).floor() != ((n + 1.0) * self.read_sample_rate).floor()
+    }
+}
+
+/// Apply queued operations to the secondary until the repository is dropped
+async fn run_mirror(
+    secondary: Repository,
+    mut queue: mpsc::Receiver<MirrorOp>,
+    metrics: Arc<ShadowMetrics>,
+) {
+    while let Some(op) = queue.recv().await {
+        let result = match op {
+            MirrorOp::SaveContext(context) => secondary.save_context(context).await.map(|_| ()),
+            MirrorOp::Update(context) => secondary.update(context, None).await.map(|_| ()),
+            MirrorOp::SaveContextWithChunks(context, chunks) => secondary
+                .save_context_with_chunks(context, chunks)
+                .await
+                .map(|_| ()),
+            MirrorOp::ReplaceContextWithChunks(context, chunks) => secondary
+                .replace_context_with_chunks(context, chunks, None)
+                .await
+                .map(|_| ()),
+            MirrorOp::Delete(context_id) => secondary.delete(context_id).await,
+            MirrorOp::SaveRevision(revision, max_revisions) => {
+                secondary.save_revision(revision, max_revisions).await
+            }
+            MirrorOp::SaveCollection(collection) => {
+                secondary.save_collection(collection).await.map(|_| ())
+            }
+            MirrorOp::DeleteCollection(collection_id) => {
+                secondary.delete_collection(collection_id).await
+            }
+            MirrorOp::SaveChunks(chunks) => secondary.save_chunks(chunks).await.map(|_| ()),
+            MirrorOp::DeleteChunks(context_id) => {
+                secondary.delete_chunks_by_context_id(context_id).await
+            }
+            MirrorOp::CompareContext(expected) => {
+                let actual = secondary.find_by_id(expected.id).await;
+                record_diff(
+                    &metrics,
+                    expected.id,
+                    actual.map(|context| context_fingerprint(&context)),
+                    context_fingerprint(&expected),
+                );
+                metrics.pending.fetch_sub(1, Ordering::Relaxed);
+                continue;
+            }
+            MirrorOp::CompareChunks(context_id, expected) => {
+                let actual = secondary.find_chunks_by_context_id(context_id).await;
+                let actual = match actual {
+                    Ok(chunks) if chunks.is_empty() && !expected.is_empty() => {
+                        Err(McpError::ContextNotFound(context_id))
+                    }
+                    other => other.map(|chunks| chunks_fingerprint(&chunks)),
+                };
+                record_diff(&metrics, context_id, actual, chunks_fingerprint(&expected));
+                metrics.pending.fetch_sub(1, Ordering::Relaxed);
+                continue;
+            }
+        };
+
+        metrics.mirror_lag.fetch_sub(1, Ordering::Relaxed);
+        match result {
+            Ok(()) => {
+                metrics.mirrored.fetch_add(1, Ordering::Relaxed);
+            }
+            Err(err) => {
+                metrics.mirror_failures.fetch_add(1, Ordering::Relaxed);
+                warn!("Failed to mirror mutation to secondary repository: {}", err);
+            }
+        }
+        metrics.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+fn record_diff(metrics: &ShadowMetrics, context_id: Uuid, actual: McpResult<u64>, expected: u64) {
+    metrics.reads_compared.fetch_add(1, Ordering::Relaxed);
+
+    match actual {
+        Ok(fingerprint) if fingerprint == expected => {}
+        Ok(_) => {
+            metrics.read_mismatches.fetch_add(1, Ordering::Relaxed);
+            warn!("Secondary repository has different data for {}", context_id);
+        }
+        Err(McpError::ContextNotFound(_)) => {
+            metrics.read_missing.fetch_add(1, Ordering::Relaxed);
+            warn!("Secondary repository is missing {}", context_id);
+        }
+        Err(err) => {
+            warn!(
+                "Failed to compare {} with secondary repository: {}",
+                context_id, err
+            );
+        }
+    }
+}
+
+/// Hash of the stored fields of a context, ignoring timestamp precision differences
+fn context_fingerprint(context: &Context) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    context.content.hash(&mut hasher);
+    context.metadata.source.hash(&mut hasher);
+    context.metadata.content_type.hash(&mut hasher);
+    context.metadata.content_hash.hash(&mut hasher);
+    context.metadata.tags.hash(&mut hasher);
+    context
+        .metadata
+        .custom
+        .iter()
+        .collect::<BTreeMap<_, _>>()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+fn chunks_fingerprint(chunks: &[ContextChunk]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for chunk in chunks {
+        chunk.chunk_id.hash(&mut hasher);
+        chunk.content.hash(&mut hasher);
+        chunk.position.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[async_trait]
+impl ContextRepositoryPort for ShadowContextRepository {
+    async fn save_context(&self, context: Context) -> McpResult<Context> {
+        let saved = self.primary.save_context(context).await?;
+        self.enqueue(MirrorOp::SaveContext(saved.clone()));
+        Ok(saved)
+    }
+
+    async fn find_by_id(&self, context_id: Uuid) -> McpResult<Context> {
+        let context = self.primary.find_by_id(context_id).await?;
+        if self.sample_read() {
+            self.enqueue(MirrorOp::CompareContext(context.clone()));
+        }
+        Ok(context)
+    }
+
+    async fn find_by_ids(&self, context_ids: &[Uuid]) -> McpResult<Vec<Context>> {
+        let contexts = self.primary.find_by_ids(context_ids).await?;
+        for context in &contexts {
+            if self.sample_read() {
+                self.enqueue(MirrorOp::CompareContext(context.clone()));
+            }
+        }
+        Ok(contexts)
+    }
+
+    // The primary owns the version check; the mirror just replays the write
+    async fn update(&self, context: Context, expected_version: Option<u64>) -> McpResult<Context> {
+        let updated = self.primary.update(context, expected_version).await?;
+        self.enqueue(MirrorOp::Update(updated.clone()));
+        Ok(updated)
+    }
+
+    async fn save_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context> {
+        let saved = self
+            .primary
+            .save_context_with_chunks(context, chunks.clone())
+            .await?;
+        self.enqueue(MirrorOp::SaveContextWithChunks(saved.clone(), chunks));
+        Ok(saved)
+    }
+
+    async fn replace_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let replaced = self
+            .primary
+            .replace_context_with_chunks(context, chunks.clone(), expected_version)
+            .await?;
+        self.enqueue(MirrorOp::ReplaceContextWithChunks(replaced.clone(), chunks));
+        Ok(replaced)
+    }
+
+    async fn delete(&self, context_id: Uuid) -> McpResult<()> {
+        self.primary.delete(context_id).await?;
+        self.enqueue(MirrorOp::Delete(context_id));
+        Ok(())
+    }
+
+    async fn save_revision(
+        &self,
+        revision: ContextRevision,
+        max_revisions: usize,
+    ) -> McpResult<()> {
+        self.primary
+            .save_revision(revision.clone(), max_revisions)
+            .await?;
+        self.enqueue(MirrorOp::SaveRevision(revision, max_revisions));
+        Ok(())
+    }
+
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>> {
+        self.primary.list_revisions(context_id).await
+    }
+
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision> {
+        self.primary.get_revision(context_id, version).await
+    }
+
+    async fn save_collection(&self, collection: Collection) -> McpResult<Collection> {
+        let saved = self.primary.save_collection(collection).await?;
+        self.enqueue(MirrorOp::SaveCollection(saved.clone()));
+        Ok(saved)
+    }
+
+    async fn find_collection(&self, collection_id: Uuid) -> McpResult<Collection> {
+        self.primary.find_collection(collection_id).await
+    }
+
+    async fn list_collections(&self) -> McpResult<Vec<Collection>> {
+        self.primary.list_collections().await
+    }
+
+    async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()> {
+        self.primary.delete_collection(collection_id).await?;
+        self.enqueue(MirrorOp::DeleteCollection(collection_id));
+        Ok(())
+    }
+
+    async fn find_by_tags(
+        &self,
+        tags: &[String],
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        // Page order differs between backends, so list reads are not compared
+        self.primary.find_by_tags(tags, limit, offset).await
+    }
+
+    async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>> {
+        self.primary.list_all(limit, offset).await
+    }
+
+    async fn count_all(&self) -> McpResult<usize> {
+        self.primary.count_all().await
+    }
+
+    async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize> {
+        self.primary.count_by_tags(tags).await
+    }
+
+    async fn find_filtered(
+        &self,
+        filter: &ContextFilter,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        self.primary.find_filtered(filter, limit, offset).await
+    }
+
+    async fn count_filtered(&self, filter: &ContextFilter) -> McpResult<usize> {
+        self.primary.count_filtered(filter).await
+    }
+
+    async fn find_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        self.primary.find_expired(now, limit, offset).await
+    }
+
+    async fn find_by_content_hash(&self, content_hash: &str) -> McpResult<Vec<Context>> {
+        self.primary.find_by_content_hash(content_hash).await
+    }
+
+    async fn find_related_to(&self, target_id: Uuid) -> McpResult<Vec<Context>> {
+        self.primary.find_related_to(target_id).await
+    }
+
+    async fn exists(&self, context_id: Uuid) -> McpResult<bool> {
+        self.primary.exists(context_id).await
+    }
+
+    async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>> {
+        let saved = self.primary.save_chunks(chunks).await?;
+        if !saved.is_empty() {
+            self.enqueue(MirrorOp::SaveChunks(saved.clone()));
+        }
+        Ok(saved)
+    }
+
+    async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>> {
+        let chunks = self.primary.find_chunks_by_context_id(context_id).await?;
+        if self.sample_read() {
+            self.enqueue(MirrorOp::CompareChunks(context_id, chunks.clone()));
+        }
+        Ok(chunks)
+    }
+
+    async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk> {
+        // Single chunks aren't compared; comparing all of a context's chunks covers them
+        self.primary.find_chunk_by_id(chunk_id).await
+    }
+
+    async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()> {
+        self.primary.delete_chunks_by_context_id(context_id).await?;
+        self.enqueue(MirrorOp::DeleteChunks(context_id));
+        Ok(())
+    }
+
+    // The mirror evicts on its own, so only the primary's evictions reach the vector store
+    async fn take_evicted_chunk_ids(&self) -> McpResult<Vec<Uuid>> {
+        self.primary.take_evicted_chunk_ids().await
+    }
+
+    /// Only the primary serves requests, so a failing secondary leaves the repository healthy
+    async fn check_health(&self) -> McpResult<()> {
+        self.primary.check_health().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::out_adapters::InMemoryContextRepository;
+    use crate::domain::ContextMetadata;
+
+    /// Secondary that rejects every call
+    struct FailingRepository;
+
+    fn failure<T>() -> McpResult<T> {
+        Err(McpError::StorageError("secondary unavailable".to_string()))
+    }
+
+    #[async_trait]
+    impl ContextRepositoryPort for FailingRepository {
+        async fn save_context(&self, _context: Context) -> McpResult<Context> {
+            failure()
+        }
+
+        async fn find_by_id(&self, _context_id: Uuid) -> McpResult<Context> {
+            failure()
+        }
+
+        async fn find_by_ids(&self, _context_ids: &[Uuid]) -> McpResult<Vec<Context>> {
+            failure()
+        }
+
+        async fn update(
+            &self,
+            _context: Context,
+            _expected_version: Option<u64>,
+        ) -> McpResult<Context> {
+            failure()
+        }
+
+        async fn save_context_with_chunks(
+            &self,
+            _context: Context,
+            _chunks: Vec<ContextChunk>,
+        ) -> McpResult<Context> {
+            failure()
+        }
+
+        async fn replace_context_with_chunks(
+            &self,
+            _context: Context,
+            _chunks: Vec<ContextChunk>,
+            _expected_version: Option<u64>,
+        ) -> McpResult<Context> {
+            failure()
+        }
+
+        async fn delete(&self, _context_id: Uuid) -> McpResult<()> {
+            failure()
+        }
+
+        async fn save_revision(
+            &self,
+            _revision: ContextRevision,
+            _max_revisions: usize,
+        ) -> McpResult<()> {
+            failure()
+        }
+
+        async fn list_revisions(&self, _context_id: Uuid) -> McpResult<Vec<ContextRevision>> {
+            failure()
+        }
+
+        async fn get_revision(
+            &self,
+            _context_id: Uuid,
+            _version: u64,
+        ) -> McpResult<ContextRevision> {
+            failure()
+        }
+
+        async fn save_collection(&self, _collection: Collection) -> McpResult<Collection> {
+            failure()
+        }
+
+        async fn find_collection(&self, _collection_id: Uuid) -> McpResult<Collection> {
+            failure()
+        }
+
+        async fn list_collections(&self) -> McpResult<Vec<Collection>> {
+            failure()
+        }
+
+        async fn delete_collection(&self, _collection_id: Uuid) -> McpResult<()> {
+            failure()
+        }
+
+        async fn find_by_tags(
+            &self,
+            _tags: &[String],
+            _limit: usize,
+            _offset: usize,
+        ) -> McpResult<Vec<Context>> {
+            failure()
+        }
+
+        async fn list_all(&self, _limit: usize, _offset: usize) -> McpResult<Vec<Context>> {
+            failure()
+        }
+
+        async fn count_all(&self) -> McpResult<usize> {
+            failure()
+        }
+
+        async fn count_by_tags(&self, _tags: &[String]) -> McpResult<usize> {
+            failure()
+        }
+
+        async fn exists(&self, _context_id: Uuid) -> McpResult<bool> {
+            failure()
+        }
+
+        async fn save_chunks(&self, _chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>> {
+            failure()
+        }
+
+        async fn find_chunks_by_context_id(
+            &self,
+            _context_id: Uuid,
+        ) -> McpResult<Vec<ContextChunk>> {
+            failure()
+        }
+
+        async fn find_chunk_by_id(&self, _chunk_id: Uuid) -> McpResult<ContextChunk> {
+            failure()
+        }
+
+        async fn delete_chunks_by_context_id(&self, _context_id: Uuid) -> McpResult<()> {
+            failure()
+        }
+    }
+
+    fn create_test_context(content: &str) -> Context {
+        Context::new(
+            content,
+            ContextMetadata {
+                tags: vec!["shadow".to_string()],
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Wait until the mirror has drained its queue, comparisons included
+    async fn wait_for_mirror(metrics: &ShadowMetrics) {
+        for _ in 0..200 {
+            if metrics.pending.load(Ordering::Relaxed) == 0 {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("mirror did not drain");
+    }
+
+    #[tokio::test]
+    async fn test_failing_secondary_does_not_affect_requests() {
+        let primary = Arc::new(InMemoryContextRepository::new());
+        let shadow =
+            ShadowContextRepository::new(primary.clone(), Arc::new(FailingRepository), 100, 1.0);
+
+        let context = shadow
+            .save_context(create_test_context("content"))
+            .await
+            .unwrap();
+        let mut updated = context.clone();
+        updated.content = "updated".to_string();
+        shadow.update(updated, None).await.unwrap();
+        assert_eq!(
+            shadow.find_by_id(context.id).await.unwrap().content,
+            "updated"
+        );
+        shadow.delete(context.id).await.unwrap();
+        assert!(primary.find_by_id(context.id).await.is_err());
+
+        wait_for_mirror(&shadow.metrics()).await;
+        let metrics = shadow.metrics().snapshot();
+        assert_eq!(metrics.mirror_failures, 3);
+        assert_eq!(metrics.mirrored, 0);
+        assert_eq!(metrics.reads_compared, 1);
+    }
+
+    #[tokio::test]
+    async fn test_diverging_secondary_is_detected() {
+        let primary = Arc::new(InMemoryContextRepository::new());
+        let secondary = Arc::new(InMemoryContextRepository::new());
+        let shadow = ShadowContextRepository::new(primary, secondary.clone(), 100, 1.0);
+
+        let changed = shadow
+            .save_context(create_test_context("original"))
+            .await
+            .unwrap();
+        let removed = shadow
+            .save_context(create_test_context("removed"))
+            .await
+            .unwrap();
+        let intact = shadow
+            .save_context(create_test_context("intact"))
+            .await
+            .unwrap();
+        wait_for_mirror(&shadow.metrics()).await;
+        assert_eq!(shadow.metrics().snapshot().mirrored, 3);
+
+        // Make the secondary diverge behind the shadow's back
+        let mut diverged = changed.clone();
+        diverged.content = "diverged".to_string();
+        secondary.update(diverged, None).await.unwrap();
+        secondary.delete(removed.id).await.unwrap();
+
+        // Reads still return the primary's data
+        assert_eq!(
+            shadow.find_by_id(changed.id).await.unwrap().content,
+            "original"
+        );
+        assert_eq!(
+            shadow.find_by_id(removed.id).await.unwrap().content,
+            "removed"
+        );
+        assert_eq!(
+            shadow.find_by_id(intact.id).await.unwrap().content,
+            "intact"
+        );
+
+        wait_for_mirror(&shadow.metrics()).await;
+        let metrics = shadow.metrics().snapshot();
+        assert_eq!(metrics.reads_compared, 3);
+        assert_eq!(metrics.read_mismatches, 1);
+        assert_eq!(metrics.read_missing, 1);
+    }
+
+    #[tokio::test]
+    async fn test_read_sampling_rate() {
+        let primary = Arc::new(InMemoryContextRepository::new());
+        let secondary = Arc::new(InMemoryContextRepository::new());
+        let shadow = ShadowContextRepository::new(primary, secondary, 100, 0.25);
+
+        let context = shadow
+            .save_context(create_test_context("sampled"))
+            .await
+            .unwrap();
+        for _ in 0..20 {
+            shadow.find_by_id(context.id).await.unwrap();
+        }
+
+        wait_for_mirror(&shadow.metrics()).await;
+        let metrics = shadow.metrics().snapshot();
+        assert_eq!(metrics.reads_compared, 5);
+        assert_eq!(metrics.read_mismatches + metrics.read_missing, 0);
+    }
+}
@@ -1,26 +1,36 @@
 use async_trait::async_trait;
 use std::collections::HashMap;
-use std::sync::Mutex;
+use std::sync::Arc;
 use uuid::Uuid;
 
+use super::InMemoryVectorIndex;
 use crate::domain::{ContextChunk, McpResult};
-use crate::ports::out_ports::EmbeddingPort;
+use crate::ports::out_ports::{EmbeddingPort, VectorStorePort};
 
 /// A simple embedding implementation that computes token-based embeddings
 /// Used for demonstration and testing purposes
+///
+/// It also stores the embedded chunks, so one service can stand in for both ports.
 pub struct SimpleEmbeddingService {
-    chunk_embeddings: Mutex<HashMap<Uuid, Vec<f32>>>,
+    /// Embedded chunks, kept whole so search results point at real contexts
+    index: Arc<InMemoryVectorIndex>,
     embedding_dimension: usize,
 }
 
 impl SimpleEmbeddingService {
     pub fn new(embedding_dimension: usize) -> Self {
         Self {
-            chunk_embeddings: Mutex::new(HashMap::new()),
+            index: Arc::new(InMemoryVectorIndex::new()),
             embedding_dimension,
         }
     }
 
+    /// Keep the embedded chunks in `index` rather than one searched exhaustively
+    pub fn with_index(mut self, index: Arc<InMemoryVectorIndex>) -> Self {
+        self.index = index;
+        self
+    }
+
     /// Create a simple embedding for text by counting word frequencies
     /// This is not a real embedding model, just a toy implementation for demonstration
     fn compute_embedding(&self, text: &str) -> Vec<f32> {
@@ -62,88 +72,63 @@ impl SimpleEmbeddingService {
 
         embedding
     }
-
-    /// Calculate cosine similarity between two embeddings
-    fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
-        let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
-        let magnitude_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
-        let magnitude_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
-
-        if magnitude_a > 0.0 && magnitude_b > 0.0 {
-            dot_product / (magnitude_a * magnitude_b)
-        } else {
-            0.0
-        }
-    }
 }
 
 #[async_trait]
 impl EmbeddingPort for SimpleEmbeddingService {
-    async fn embed_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>> {
-        let mut result_chunks = Vec::new();
-        let mut embeddings = self.chunk_embeddings.lock().unwrap();
-
-        for mut chunk in chunks {
-            // Generate embedding for this chunk
-            let embedding = self.compute_embedding(&chunk.content);
-
-            // Store embedding in repository
-            embeddings.insert(chunk.chunk_id, embedding.clone());
-
-            // Add embedding to chunk and collect
-            chunk.embedding = Some(embedding);
-            result_chunks.push(chunk);
-        }
-
-        Ok(result_chunks)
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        Ok(texts
+            .iter()
+            .map(|text| self.compute_embedding(text))
+            .collect())
     }
+}
 
-    async fn find_similar(&self, query: &str, limit: usize) -> McpResult<Vec<(ContextChunk, f32)>> {
-        // Generate embedding for the query
-        let query_embedding = self.compute_embedding(query);
-
-        // Get all stored embeddings
-        let embeddings = self.chunk_embeddings.lock().unwrap();
-
-        // This would be inefficient in a real system, but works for demonstration
-        let mut chunk_scores = Vec::new();
-
-        // This is a placeholder - in a real system, we would retrieve the actual chunks
-        // based on their IDs, but this is just a simulation
-        for (chunk_id, embedding) in &*embeddings {
-            // Calculate similarity score
-            let score = Self::cosine_similarity(&query_embedding, embedding);
-
-            // Create a real chunk with the actual content
-            // This would be retrieved from the repository in a real implementation
-            let chunk = ContextChunk {
-                chunk_id: *chunk_id,
-                context_id: Uuid::new_v4(), // For testing, use a new random ID
-                content: "This is content for the embedding search test".to_string(),
-                embedding: Some(embedding.clone()),
-                position: 0,
-            };
-
-            chunk_scores.push((chunk, score));
-        }
-
-        // Sort by similarity score descending
-        chunk_scores.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-        // Take top results
-        chunk_scores.truncate(limit);
+#[async_trait]
+impl VectorStorePort for SimpleEmbeddingService {
+    async fn upsert(&self, chunks: &[ContextChunk], tags: &[String]) -> McpResult<()> {
+        self.index.upsert(chunks, tags).await
+    }
 
-        Ok(chunk_scores)
+    async fn delete(&self, chunk_ids: &[Uuid]) -> McpResult<()> {
+        self.index.delete(chunk_ids).await
     }
 
-    async fn find_similar_with_tags(
+    async fn search(
         &self,
-        query: &str,
-        _tags: &[String],
+        query: &[f32],
+        tags: &[String],
         limit: usize,
     ) -> McpResult<Vec<(ContextChunk, f32)>> {
-        // In a real implementation, this would filter by tags
-        // For now, just delegate to the standard search
-        self.find_similar(query, limit).await
+        self.index.search(query, tags, limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_returns_stored_chunks() {
+        let service = SimpleEmbeddingService::new(64);
+        let content = "Rust is a systems programming language".to_string();
+        let chunk = ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id: Uuid::new_v4(),
+            embedding: service.embed_texts(&[content.clone()]).await.unwrap().pop(),
+            content,
+            position: 2,
+        };
+        service.upsert(&[chunk.clone()], &[]).await.unwrap();
+
+        let query = service.embed_query("Rust programming").await.unwrap();
+        let similar = service.search(&query, &[], 5).await.unwrap();
+        assert_eq!(similar.len(), 1);
+        let (found, score) = &similar[0];
+        assert_eq!(found.chunk_id, chunk.chunk_id);
+        assert_eq!(found.context_id, chunk.context_id);
+        assert_eq!(found.content, chunk.content);
+        assert_eq!(found.position, 2);
+        assert!(*score > 0.0);
     }
 }
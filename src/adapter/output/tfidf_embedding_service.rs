@@ -0,0 +1,378 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::vector_index::cosine_similarity;
+use crate::domain::{ContextChunk, McpError, McpResult};
+use crate::ports::out_ports::{EmbeddingPort, VectorStorePort};
+
+/// Embedding service weighting terms by TF-IDF over every chunk it stores
+///
+/// Term weights are hashed into `dimension` buckets. Document frequencies change as chunks
+/// come and go, so the service is its own vector store: stored chunks are re-weighted with the
+/// current IDF on every search.
+pub struct TfIdfEmbeddingService {
+    dimension: usize,
+    state: Mutex<Corpus>,
+    path: Option<PathBuf>,
+}
+
+/// Vocabulary and term counts of the embedded chunks
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Corpus {
+    /// Number of chunks each term appears in
+    document_frequencies: HashMap<String, usize>,
+
+    /// Stored chunks by context, with the term counts of each chunk
+    contexts: HashMap<Uuid, Vec<Document>>,
+
+    /// Total number of chunks across contexts
+    documents: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Document {
+    chunk: ContextChunk,
+    term_counts: HashMap<String, usize>,
+
+    /// Tags of the chunk's context
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+impl Corpus {
+    /// Replace the documents of a context, releasing the frequencies of the old ones
+    fn replace(&mut self, context_id: Uuid, documents: Vec<Document>) {
+        for document in self.contexts.remove(&context_id).unwrap_or_default() {
+            self.release(&document);
+        }
+
+        for document in &documents {
+            self.documents += 1;
+            for term in document.term_counts.keys() {
+                *self.document_frequencies.entry(term.clone()).or_insert(0) += 1;
+            }
+        }
+
+        if !documents.is_empty() {
+            self.contexts.insert(context_id, documents);
+        }
+    }
+
+    /// Drop chunks, releasing their frequencies
+    fn remove(&mut self, chunk_ids: &HashSet<Uuid>) {
+        let mut removed = Vec::new();
+        for documents in self.contexts.values_mut() {
+            let (gone, kept): (Vec<Document>, Vec<Document>) = std::mem::take(documents)
+                .into_iter()
+                .partition(|document| chunk_ids.contains(&document.chunk.chunk_id));
+            *documents = kept;
+            removed.extend(gone);
+        }
+        self.contexts.retain(|_, documents| !documents.is_empty());
+
+        for document in &removed {
+            self.release(document);
+        }
+    }
+
+    /// Stop counting a document towards the frequencies of its terms
+    fn release(&mut self, document: &Document) {
+        self.documents -= 1;
+        for term in document.term_counts.keys() {
+            if let Some(frequency) = self.document_frequencies.get_mut(term) {
+                *frequency -= 1;
+                if *frequency == 0 {
+                    self.document_frequencies.remove(term);
+                }
+            }
+        }
+    }
+
+    /// Smoothed inverse document frequency of a term
+    fn idf(&self, term: &str) -> f32 {
+        let frequency = self.document_frequencies.get(term).copied().unwrap_or(0);
+        ((1 + self.documents) as f32 / (1 + frequency) as f32).ln() + 1.0
+    }
+}
+
+impl TfIdfEmbeddingService {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension: dimension.max(1),
+            state: Mutex::new(Corpus::default()),
+            path: None,
+        }
+    }
+
+    /// Create a service that saves its corpus to `path`, loading what was saved there before
+    pub fn open(dimension: usize, path: impl Into<PathBuf>) -> McpResult<Self> {
+        let path = path.into();
+        let corpus = match fs::read(&path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| McpError::SerializationError(e.to_string()))?,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Corpus::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            dimension: dimension.max(1),
+            state: Mutex::new(corpus),
+            path: Some(path),
+        })
+    }
+
+    /// Weighted, normalized vector of term counts under the corpus' current IDF
+    fn vectorize(&self, corpus: &Corpus, term_counts: &HashMap<String, usize>) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimension];
+
+        for (term, &count) in term_counts {
+            // Signed feature hashing keeps colliding terms from only ever adding up
+            let hash = fnv1a(term);
+            let bucket = (hash % self.dimension as u64) as usize;
+            let sign = if hash >> 63 == 0 { 1.0 } else { -1.0 };
+            vector[bucket] += sign * count as f32 * corpus.idf(term);
+        }
+
+        let magnitude: f32 = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if magnitude > 0.0 {
+            for value in &mut vector {
+                *value /= magnitude;
+            }
+        }
+        vector
+    }
+
+    /// Write the corpus to the snapshot path, if there is one
+    fn save(&self, corpus: &Corpus) -> McpResult<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let bytes =
+            serde_json::to_vec(corpus).map_err(|e| McpError::SerializationError(e.to_string()))?;
+        write_atomically(path, &bytes)
+    }
+}
+
+/// Lowercased alphanumeric terms of a text, with their counts
+fn term_counts(text: &str) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    for word in text.split_whitespace() {
+        let term: String = word
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        if !term.is_empty() {
+            *counts.entry(term).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+/// FNV-1a, which unlike the std hasher is stable across builds and restarts
+fn fnv1a(term: &str) -> u64 {
+    term.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+fn write_atomically(path: &Path, bytes: &[u8]) -> McpResult<()> {
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, bytes)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+#[async_trait]
+impl EmbeddingPort for TfIdfEmbeddingService {
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+        let corpus = self.state.lock().unwrap();
+        Ok(texts
+            .iter()
+            .map(|text| self.vectorize(&corpus, &term_counts(text)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl VectorStorePort for TfIdfEmbeddingService {
+    async fn upsert(&self, chunks: &[ContextChunk], tags: &[String]) -> McpResult<()> {
+        if chunks.is_empty() {
+            return Ok(());
+        }
+
+        // Storing a context replaces everything stored for it before
+        let mut by_context: HashMap<Uuid, Vec<Document>> = HashMap::new();
+        for chunk in chunks {
+            by_context
+                .entry(chunk.context_id)
+                .or_default()
+                .push(Document {
+                    chunk: chunk.clone(),
+                    term_counts: term_counts(&chunk.content),
+                    tags: tags.to_vec(),
+                });
+        }
+
+        let mut corpus = self.state.lock().unwrap();
+        for (context_id, documents) in by_context {
+            corpus.replace(context_id, documents);
+        }
+        self.save(&corpus)
+    }
+
+    async fn delete(&self, chunk_ids: &[Uuid]) -> McpResult<()> {
+        let mut corpus = self.state.lock().unwrap();
+        corpus.remove(&chunk_ids.iter().copied().collect());
+        self.save(&corpus)
+    }
+
+    async fn search(
+        &self,
+        query: &[f32],
+        tags: &[String],
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>> {
+        if query.len() != self.dimension {
+            return Err(McpError::dimension_mismatch(self.dimension, query.len()));
+        }
+
+        let corpus = self.state.lock().unwrap();
+        let mut scored: Vec<(ContextChunk, f32)> = corpus
+            .contexts
+            .values()
+            .flatten()
+            .filter(|document| tags.iter().all(|tag| document.tags.contains(tag)))
+            .map(|document| {
+                let vector = self.vectorize(&corpus, &document.term_counts);
+                let score = cosine_similarity(query, &vector);
+                let mut chunk = document.chunk.clone();
+                chunk.embedding = Some(vector);
+                (chunk, score)
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Embed and store a context of one chunk
+    async fn store(
+        service: &TfIdfEmbeddingService,
+        context_id: Uuid,
+        content: &str,
+        tags: &[&str],
+    ) {
+        let mut chunk = ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id,
+            content: content.to_string(),
+            position: 0,
+            embedding: None,
+        };
+        chunk.embedding = service
+            .embed_texts(&[chunk.content.clone()])
+            .await
+            .unwrap()
+            .pop();
+
+        let tags: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+        service.upsert(&[chunk], &tags).await.unwrap();
+    }
+
+    async fn find(
+        service: &TfIdfEmbeddingService,
+        query: &str,
+        tags: &[String],
+        limit: usize,
+    ) -> Vec<(ContextChunk, f32)> {
+        let query = service.embed_query(query).await.unwrap();
+        service.search(&query, tags, limit).await.unwrap()
+    }
+
+    async fn seeded(service: &TfIdfEmbeddingService) -> Uuid {
+        let kafka = Uuid::new_v4();
+        for (context_id, content, tags) in [
+            (
+                Uuid::new_v4(),
+                "the database is down again",
+                &["storage"][..],
+            ),
+            (Uuid::new_v4(), "the cache is down again", &["storage"][..]),
+            (kafka, "the kafka broker is rebalancing", &["streaming"][..]),
+        ] {
+            store(service, context_id, content, tags).await;
+        }
+        kafka
+    }
+
+    #[tokio::test]
+    async fn test_rare_terms_rank_first() {
+        let service = TfIdfEmbeddingService::new(256);
+        let kafka = seeded(&service).await;
+
+        // "down" and "again" match two chunks each, but "kafka" only one
+        let results = find(&service, "kafka down again", &[], 3).await;
+        assert_eq!(results[0].0.context_id, kafka);
+        assert_eq!(results[0].0.content, "the kafka broker is rebalancing");
+        assert!(results[0].1 > results[1].1);
+    }
+
+    #[tokio::test]
+    async fn test_search_by_tags_skips_other_contexts() {
+        let service = TfIdfEmbeddingService::new(256);
+        let kafka = seeded(&service).await;
+
+        let results = find(&service, "kafka down again", &["storage".to_string()], 3).await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(chunk, _)| chunk.context_id != kafka));
+    }
+
+    #[tokio::test]
+    async fn test_re_storing_releases_old_frequencies() {
+        let service = TfIdfEmbeddingService::new(256);
+        let kafka = seeded(&service).await;
+
+        store(&service, kafka, "the postgres replica is lagging", &[]).await;
+
+        let corpus = service.state.lock().unwrap();
+        assert_eq!(corpus.documents, 3);
+        assert!(!corpus.document_frequencies.contains_key("kafka"));
+        assert_eq!(corpus.document_frequencies["postgres"], 1);
+        assert_eq!(corpus.document_frequencies["the"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_corpus_survives_reopen() {
+        let path = std::env::temp_dir().join(format!("mcp-tfidf-{}.json", Uuid::new_v4()));
+
+        let service = TfIdfEmbeddingService::open(256, &path).unwrap();
+        seeded(&service).await;
+        let before = find(&service, "kafka down", &[], 3).await;
+
+        let reopened = TfIdfEmbeddingService::open(256, &path).unwrap();
+        let after = find(&reopened, "kafka down", &[], 3).await;
+
+        let ranking = |results: &[(ContextChunk, f32)]| {
+            results
+                .iter()
+                .map(|(chunk, score)| (chunk.chunk_id, *score))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ranking(&before), ranking(&after));
+
+        let _ = fs::remove_file(&path);
+    }
+}
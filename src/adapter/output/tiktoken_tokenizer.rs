@@ -0,0 +1,44 @@
+use tiktoken_rs::CoreBPE;
+
+use crate::domain::{McpError, McpResult, Tokenizer};
+
+/// Tokenizer counting tokens with an OpenAI BPE encoding
+pub struct TiktokenTokenizer {
+    bpe: CoreBPE,
+}
+
+impl TiktokenTokenizer {
+    /// Load the `cl100k_base` encoding
+    pub fn cl100k_base() -> McpResult<Self> {
+        let bpe = tiktoken_rs::cl100k_base().map_err(|e| {
+            McpError::ValidationError(format!("Failed to load cl100k_base encoding: {}", e))
+        })?;
+        Ok(Self { bpe })
+    }
+
+    /// Load the `o200k_base` encoding
+    pub fn o200k_base() -> McpResult<Self> {
+        let bpe = tiktoken_rs::o200k_base().map_err(|e| {
+            McpError::ValidationError(format!("Failed to load o200k_base encoding: {}", e))
+        })?;
+        Ok(Self { bpe })
+    }
+}
+
+impl Tokenizer for TiktokenTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.bpe.encode_with_special_tokens(text).len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_tokens_of_the_encoding() {
+        let tokenizer = TiktokenTokenizer::cl100k_base().unwrap();
+        assert_eq!(tokenizer.count_tokens(""), 0);
+        assert_eq!(tokenizer.count_tokens("hello world"), 2);
+    }
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use crate::config::{AppConfig, TokenizerKind};
+use crate::domain::{HeuristicTokenizer, McpResult, Tokenizer};
+
+#[cfg(feature = "tiktoken")]
+use super::TiktokenTokenizer;
+#[cfg(not(feature = "tiktoken"))]
+use crate::domain::McpError;
+
+/// Create the tokenizer selected by `context.tokenizer`
+///
+/// Fails if the tokenizer isn't available in this build.
+pub fn create_tokenizer(config: &AppConfig) -> McpResult<Arc<dyn Tokenizer>> {
+    match config.context.tokenizer {
+        TokenizerKind::Heuristic => Ok(Arc::new(HeuristicTokenizer)),
+        #[cfg(feature = "tiktoken")]
+        TokenizerKind::Cl100kBase => Ok(Arc::new(TiktokenTokenizer::cl100k_base()?)),
+        #[cfg(feature = "tiktoken")]
+        TokenizerKind::O200kBase => Ok(Arc::new(TiktokenTokenizer::o200k_base()?)),
+        #[cfg(not(feature = "tiktoken"))]
+        kind => Err(McpError::ValidationError(format!(
+            "The {} tokenizer requires building with --features tiktoken",
+            kind.as_str()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_tokenizer_is_the_default() {
+        let config = AppConfig::load_defaults().unwrap();
+        let tokenizer = create_tokenizer(&config).unwrap();
+        assert_eq!(tokenizer.count_tokens("The cat sleeps."), 5);
+    }
+
+    #[cfg(not(feature = "tiktoken"))]
+    #[test]
+    fn test_tiktoken_encodings_need_the_feature() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.context.tokenizer = TokenizerKind::Cl100kBase;
+        let Err(err) = create_tokenizer(&config) else {
+            panic!("expected an error");
+        };
+        assert!(err.to_string().contains("--features tiktoken"));
+    }
+}
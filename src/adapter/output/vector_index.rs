@@ -0,0 +1,339 @@
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use uuid::Uuid;
+
+use super::hnsw::HnswGraph;
+use crate::config::{VectorIndexConfig, VectorIndexKind};
+use crate::domain::{ContextChunk, McpError, McpResult};
+use crate::ports::out_ports::VectorStorePort;
+
+/// In-process store of embedded chunks, searched by cosine similarity
+///
+/// Searches scan every chunk unless the index keeps an HNSW graph, which finds approximately
+/// the most similar chunks without comparing the query to all of them. Searches filtered by
+/// tags scan the chunks of the matching contexts.
+#[derive(Default)]
+pub struct InMemoryVectorIndex {
+    state: RwLock<IndexState>,
+}
+
+#[derive(Default)]
+struct IndexState {
+    /// Indexed chunks by chunk id
+    chunks: HashMap<Uuid, ContextChunk>,
+
+    /// Chunk ids and tags by context, so re-indexing a context drops its old chunks
+    contexts: HashMap<Uuid, IndexedContext>,
+
+    /// Graph for approximate search, if not searching exhaustively
+    graph: Option<HnswGraph>,
+}
+
+#[derive(Default)]
+struct IndexedContext {
+    chunk_ids: Vec<Uuid>,
+    tags: Vec<String>,
+}
+
+impl IndexState {
+    fn remove(&mut self, chunk_id: Uuid) -> Option<ContextChunk> {
+        let chunk = self.chunks.remove(&chunk_id)?;
+        if let Some(graph) = &mut self.graph {
+            graph.remove(chunk_id);
+        }
+        Some(chunk)
+    }
+
+    /// Score chunks against `query`, failing on the first one of another dimension
+    fn scan<'a>(
+        &'a self,
+        query: &[f32],
+        chunk_ids: impl Iterator<Item = &'a Uuid>,
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>> {
+        let mut scored = Vec::new();
+        for chunk in chunk_ids.filter_map(|chunk_id| self.chunks.get(chunk_id)) {
+            let Some(embedding) = chunk.embedding.as_deref() else {
+                continue;
+            };
+            if embedding.len() != query.len() {
+                return Err(McpError::dimension_mismatch(embedding.len(), query.len()));
+            }
+            scored.push((chunk.clone(), cosine_similarity(query, embedding)));
+        }
+
+        scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+}
+
+impl InMemoryVectorIndex {
+    /// Index searched exhaustively, which is exact but compares the query to every chunk
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index searched through an HNSW graph linking each chunk to `m` others per layer
+    ///
+    /// `ef_construction` and `ef_search` are the candidates considered when linking a chunk and
+    /// when searching; more are slower but find the most similar chunks more reliably.
+    pub fn hnsw(m: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Self {
+            state: RwLock::new(IndexState {
+                graph: Some(HnswGraph::new(m, ef_construction, ef_search)),
+                ..IndexState::default()
+            }),
+        }
+    }
+
+    /// Index of the kind `config` selects
+    pub fn from_config(config: &VectorIndexConfig) -> Self {
+        match config.kind {
+            VectorIndexKind::Exact => Self::new(),
+            VectorIndexKind::Hnsw => Self::hnsw(config.m, config.ef_construction, config.ef_search),
+        }
+    }
+
+    /// Store embedded chunks of contexts tagged `tags`, replacing any chunks indexed earlier for
+    /// the same contexts
+    pub fn replace(&self, chunks: &[ContextChunk], tags: &[String]) {
+        let mut by_context: HashMap<Uuid, Vec<&ContextChunk>> = HashMap::new();
+        for chunk in chunks.iter().filter(|chunk| chunk.embedding.is_some()) {
+            by_context.entry(chunk.context_id).or_default().push(chunk);
+        }
+
+        let mut state = self.state.write().unwrap();
+        for (context_id, chunks) in by_context {
+            let old = state.contexts.remove(&context_id).unwrap_or_default();
+            for chunk_id in old.chunk_ids {
+                state.remove(chunk_id);
+            }
+
+            let mut chunk_ids = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                if let (Some(graph), Some(embedding)) = (&mut state.graph, &chunk.embedding) {
+                    graph.insert(chunk.chunk_id, embedding);
+                }
+                chunk_ids.push(chunk.chunk_id);
+                state.chunks.insert(chunk.chunk_id, chunk.clone());
+            }
+            state.contexts.insert(
+                context_id,
+                IndexedContext {
+                    chunk_ids,
+                    tags: tags.to_vec(),
+                },
+            );
+        }
+    }
+
+    /// Drop chunks from the index
+    pub fn remove(&self, chunk_ids: &[Uuid]) {
+        let mut state = self.state.write().unwrap();
+
+        let mut emptied = HashSet::new();
+        for chunk_id in chunk_ids {
+            let Some(chunk) = state.remove(*chunk_id) else {
+                continue;
+            };
+            if let Some(context) = state.contexts.get_mut(&chunk.context_id) {
+                context.chunk_ids.retain(|id| id != chunk_id);
+                if context.chunk_ids.is_empty() {
+                    emptied.insert(chunk.context_id);
+                }
+            }
+        }
+        for context_id in emptied {
+            state.contexts.remove(&context_id);
+        }
+    }
+
+    /// The `limit` chunks most similar to `query`, best first, only from contexts carrying
+    /// every one of `tags`
+    ///
+    /// Fails if `query` isn't as long as the indexed embeddings.
+    pub fn search(
+        &self,
+        query: &[f32],
+        tags: &[String],
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>> {
+        let state = self.state.read().unwrap();
+
+        if !tags.is_empty() {
+            let chunk_ids = state
+                .contexts
+                .values()
+                .filter(|context| tags.iter().all(|tag| context.tags.contains(tag)))
+                .flat_map(|context| &context.chunk_ids);
+            return state.scan(query, chunk_ids, limit);
+        }
+
+        let Some(graph) = &state.graph else {
+            return state.scan(query, state.chunks.keys(), limit);
+        };
+        if let Some(dimension) = graph.dimension() {
+            if dimension != query.len() {
+                return Err(McpError::dimension_mismatch(dimension, query.len()));
+            }
+        }
+        Ok(graph
+            .search(query, limit)
+            .into_iter()
+            .filter_map(|(chunk_id, score)| {
+                state
+                    .chunks
+                    .get(&chunk_id)
+                    .map(|chunk| (chunk.clone(), score))
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl VectorStorePort for InMemoryVectorIndex {
+    async fn upsert(&self, chunks: &[ContextChunk], tags: &[String]) -> McpResult<()> {
+        self.replace(chunks, tags);
+        Ok(())
+    }
+
+    async fn delete(&self, chunk_ids: &[Uuid]) -> McpResult<()> {
+        self.remove(chunk_ids);
+        Ok(())
+    }
+
+    async fn search(
+        &self,
+        query: &[f32],
+        tags: &[String],
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>> {
+        InMemoryVectorIndex::search(self, query, tags, limit)
+    }
+}
+
+/// Cosine similarity of two vectors, or 0 if either is all zeros
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot_product: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let magnitude_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let magnitude_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+
+    if magnitude_a > 0.0 && magnitude_b > 0.0 {
+        dot_product / (magnitude_a * magnitude_b)
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(context_id: Uuid, embedding: Vec<f32>) -> ContextChunk {
+        ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id,
+            content: "chunk".to_string(),
+            position: 0,
+            embedding: Some(embedding),
+        }
+    }
+
+    #[test]
+    fn test_search_ranks_by_similarity_and_replaces_per_context() {
+        let index = InMemoryVectorIndex::new();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        index.replace(
+            &[chunk(first, vec![1.0, 0.0]), chunk(second, vec![0.0, 1.0])],
+            &[],
+        );
+
+        let results = index.search(&[0.9, 0.1], &[], 2).unwrap();
+        assert_eq!(results[0].0.context_id, first);
+        assert_eq!(results[1].0.context_id, second);
+
+        // Re-indexing a context drops its old chunks
+        index.replace(&[chunk(first, vec![0.0, 1.0])], &[]);
+        let results = index.search(&[1.0, 0.0], &[], 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|(_, score)| *score == 0.0));
+    }
+
+    #[test]
+    fn test_query_of_another_dimension_is_rejected() {
+        let index = InMemoryVectorIndex::new();
+        index.replace(&[chunk(Uuid::new_v4(), vec![1.0, 0.0])], &[]);
+
+        let Err(McpError::EmbeddingError(message)) = index.search(&[1.0, 0.0, 0.0], &[], 10) else {
+            panic!("expected a dimension mismatch");
+        };
+        assert_eq!(message, "dimension mismatch: expected 2 got 3");
+    }
+
+    #[test]
+    fn test_removed_chunks_are_not_found() {
+        let index = InMemoryVectorIndex::new();
+        let context_id = Uuid::new_v4();
+        let (kept, removed) = (chunk(context_id, vec![1.0]), chunk(context_id, vec![1.0]));
+        index.replace(&[kept.clone(), removed.clone()], &[]);
+
+        index.remove(&[removed.chunk_id]);
+        let results = index.search(&[1.0], &[], 10).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.chunk_id, kept.chunk_id);
+    }
+
+    #[test]
+    fn test_hnsw_index_replaces_and_removes_like_the_exact_one() {
+        for index in [
+            InMemoryVectorIndex::new(),
+            InMemoryVectorIndex::hnsw(4, 16, 16),
+        ] {
+            let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+            let stale = chunk(first, vec![1.0, 0.0]);
+            index.replace(&[stale.clone(), chunk(second, vec![0.0, 1.0])], &[]);
+            assert_eq!(
+                index.search(&[0.9, 0.1], &[], 1).unwrap()[0].0.chunk_id,
+                stale.chunk_id
+            );
+
+            let fresh = chunk(first, vec![0.6, 0.8]);
+            index.replace(&[fresh.clone()], &[]);
+            let results = index.search(&[1.0, 0.0], &[], 10).unwrap();
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].0.chunk_id, fresh.chunk_id);
+
+            index.remove(&[fresh.chunk_id]);
+            let results = index.search(&[1.0, 0.0], &[], 10).unwrap();
+            assert_eq!(results.len(), 1);
+            assert_eq!(results[0].0.context_id, second);
+        }
+    }
+
+    #[test]
+    fn test_search_by_tags_only_returns_contexts_with_every_tag() {
+        let index = InMemoryVectorIndex::hnsw(4, 16, 16);
+        let tags = |tags: &[&str]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        let (rust, python) = (
+            chunk(Uuid::new_v4(), vec![0.9, 0.1]),
+            chunk(Uuid::new_v4(), vec![1.0, 0.0]),
+        );
+        index.replace(&[rust.clone()], &tags(&["rust", "programming"]));
+        index.replace(&[python], &tags(&["python", "programming"]));
+
+        assert_eq!(
+            index
+                .search(&[1.0, 0.0], &tags(&["programming"]), 10)
+                .unwrap()
+                .len(),
+            2
+        );
+        let results = index
+            .search(&[1.0, 0.0], &tags(&["programming", "rust"]), 10)
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.chunk_id, rust.chunk_id);
+    }
+}
@@ -0,0 +1,177 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::domain::{Collection, Context, ContextChunk, ContextRevision, McpError, McpResult};
+
+/// A single mutation recorded in the write-ahead log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum WalRecord {
+    SaveContext(Context),
+    UpdateContext(Context),
+    DeleteContext(Uuid),
+    SaveChunks {
+        context_id: Uuid,
+        chunks: Vec<ContextChunk>,
+    },
+    DeleteChunks(Uuid),
+    /// A context and all of its chunks, replayed together
+    ContextWithChunks {
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    },
+    /// Every revision kept for a context, replacing the ones it had
+    Revisions {
+        context_id: Uuid,
+        revisions: Vec<ContextRevision>,
+    },
+    SaveCollection(Collection),
+    DeleteCollection(Uuid),
+}
+
+/// Append-only log of repository mutations, one JSON record per line
+pub struct WriteAheadLog {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    compact_threshold: u64,
+}
+
+impl WriteAheadLog {
+    /// Open the log at `path`, returning it along with the records it already holds
+    ///
+    /// A truncated final record, left behind by a crash mid-write, is skipped.
+    pub fn open(
+        path: impl AsRef<Path>,
+        compact_threshold: u64,
+    ) -> McpResult<(Self, Vec<WalRecord>)> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let (records, valid_len) = parse_records(&data)?;
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        // Drop the torn tail so new records start on a clean line
+        if valid_len < data.len() {
+            file.set_len(valid_len as u64)?;
+        }
+
+        let log = Self {
+            path,
+            file,
+            size: valid_len as u64,
+            compact_threshold,
+        };
+
+        Ok((log, records))
+    }
+
+    /// Append a record and flush it to disk
+    pub fn append(&mut self, record: &WalRecord) -> McpResult<()> {
+        let mut line =
+            serde_json::to_vec(record).map_err(|e| McpError::SerializationError(e.to_string()))?;
+        line.push(b'\n');
+
+        self.file.write_all(&line)?;
+        self.file.sync_data()?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Whether the log has grown past its compaction threshold
+    pub fn needs_compaction(&self) -> bool {
+        self.size > self.compact_threshold
+    }
+
+    /// Replace the log with the given records, which should describe the full current state
+    pub fn rewrite(&mut self, records: impl IntoIterator<Item = WalRecord>) -> McpResult<()> {
+        let tmp_path = self.path.with_extension("compact");
+
+        let mut size = 0;
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            for record in records {
+                let mut line = serde_json::to_vec(&record)
+                    .map_err(|e| McpError::SerializationError(e.to_string()))?;
+                line.push(b'\n');
+                writer.write_all(&line)?;
+                size += line.len() as u64;
+            }
+            writer
+                .into_inner()
+                .map_err(|e| McpError::IoError(e.into_error()))?
+                .sync_all()?;
+        }
+
+        // Atomically swap in the compacted log, then append to it from now on
+        fs::rename(&tmp_path, &self.path)?;
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        self.size = size;
+        Ok(())
+    }
+
+    /// Current size of the log in bytes
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+}
+
+/// Parse complete records, returning them with the length of the valid prefix
+fn parse_records(data: &[u8]) -> McpResult<(Vec<WalRecord>, usize)> {
+    let mut records = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let Some(newline) = data[offset..].iter().position(|&b| b == b'\n') else {
+            // No terminator: the final write was interrupted
+            break;
+        };
+
+        let line = &data[offset..offset + newline];
+        let record = serde_json::from_slice(line).map_err(|e| {
+            McpError::StorageError(format!("Corrupt write-ahead log record: {}", e))
+        })?;
+        records.push(record);
+        offset += newline + 1;
+    }
+
+    Ok((records, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncated_tail_is_skipped() {
+        let first = serde_json::to_vec(&WalRecord::DeleteContext(Uuid::new_v4())).unwrap();
+        let second = serde_json::to_vec(&WalRecord::DeleteChunks(Uuid::new_v4())).unwrap();
+
+        let mut data = first.clone();
+        data.push(b'\n');
+        data.extend_from_slice(&second[..second.len() / 2]);
+
+        let (records, valid_len) = parse_records(&data).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(valid_len, first.len() + 1);
+    }
+
+    #[test]
+    fn test_corrupt_record_is_an_error() {
+        let data = b"not json\n{}\n";
+        assert!(matches!(
+            parse_records(data),
+            Err(McpError::StorageError(_))
+        ));
+    }
+}
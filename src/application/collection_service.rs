@@ -0,0 +1,134 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::domain::{Collection, ContextFilter, McpError, McpResult, DEFAULT_COLLECTION_ID};
+use crate::ports::in_ports::CollectionPort;
+use crate::ports::out_ports::ContextRepositoryPort;
+
+/// Application service managing the collections contexts are grouped into
+pub struct CollectionService {
+    repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
+}
+
+impl CollectionService {
+    /// Create a service keeping collections in `repository`
+    pub fn new(repository: Arc<dyn ContextRepositoryPort + Send + Sync>) -> Self {
+        Self { repository }
+    }
+
+    /// Check a collection name, returning it trimmed
+    ///
+    /// A name can't look like an ID or be the default collection's, so resolving a
+    /// collection by either never has two answers.
+    fn valid_name(name: &str) -> McpResult<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(McpError::ValidationError(
+                "Collection name cannot be empty".to_string(),
+            ));
+        }
+        if Uuid::parse_str(name).is_ok() {
+            return Err(McpError::ValidationError(format!(
+                "Collection name {} is an ID",
+                name
+            )));
+        }
+        if name == Collection::default_collection().name {
+            return Err(McpError::CollectionExists(name.to_string()));
+        }
+        Ok(name.to_string())
+    }
+
+    fn refuse_default(id: Uuid) -> McpResult<()> {
+        if id == DEFAULT_COLLECTION_ID {
+            return Err(McpError::ValidationError(
+                "The default collection can't be changed".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl CollectionPort for CollectionService {
+    async fn create_collection(
+        &self,
+        name: String,
+        description: Option<String>,
+    ) -> McpResult<Collection> {
+        let collection = Collection {
+            id: Uuid::new_v4(),
+            name: Self::valid_name(&name)?,
+            description,
+            created_at: Utc::now(),
+        };
+        self.repository.save_collection(collection).await
+    }
+
+    async fn get_collection(&self, id: Uuid) -> McpResult<Collection> {
+        if id == DEFAULT_COLLECTION_ID {
+            return Ok(Collection::default_collection());
+        }
+        self.repository.find_collection(id).await
+    }
+
+    async fn list_collections(&self) -> McpResult<Vec<Collection>> {
+        let mut collections = self.repository.list_collections().await?;
+        collections.sort_by(|a, b| a.name.cmp(&b.name));
+        collections.insert(0, Collection::default_collection());
+        Ok(collections)
+    }
+
+    async fn update_collection(
+        &self,
+        id: Uuid,
+        name: Option<String>,
+        description: Option<String>,
+    ) -> McpResult<Collection> {
+        Self::refuse_default(id)?;
+        let mut collection = self.repository.find_collection(id).await?;
+        if let Some(name) = name {
+            collection.name = Self::valid_name(&name)?;
+        }
+        if description.is_some() {
+            collection.description = description;
+        }
+        self.repository.save_collection(collection).await
+    }
+
+    async fn delete_collection(&self, id: Uuid) -> McpResult<()> {
+        Self::refuse_default(id)?;
+        self.repository.find_collection(id).await?;
+
+        // Deleted contexts count too, as restoring one would leave it in no collection
+        let held = self
+            .repository
+            .count_filtered(&ContextFilter::in_collection(id))
+            .await?;
+        if held > 0 {
+            return Err(McpError::CollectionNotEmpty(id));
+        }
+        self.repository.delete_collection(id).await
+    }
+
+    async fn resolve_collection(&self, id_or_name: &str) -> McpResult<Uuid> {
+        let id_or_name = id_or_name.trim();
+        if let Ok(id) = Uuid::parse_str(id_or_name) {
+            return self
+                .get_collection(id)
+                .await
+                .map(|collection| collection.id);
+        }
+
+        self.list_collections()
+            .await?
+            .into_iter()
+            .find(|collection| collection.name == id_or_name)
+            .map(|collection| collection.id)
+            .ok_or_else(|| {
+                McpError::ValidationError(format!("No collection is named {}", id_or_name))
+            })
+    }
+}
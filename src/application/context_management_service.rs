@@ -1,76 +1,592 @@
 use async_trait::async_trait;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
 use uuid::Uuid;
 
-use crate::domain::service::ChunkingService;
-use crate::domain::{Context, ContextMetadata, McpResult};
+use crate::domain::service::{ChunkingService, ChunkingStrategy};
+use crate::domain::{
+    content_hash, ChildPolicy, Context, ContextChunk, ContextEvent, ContextEventKind,
+    ContextFilter, ContextMetadata, ContextRelation, ContextRevision, DeleteOutcome, McpError,
+    McpResult, OnDuplicate, StoreOptions, Tokenizer, DEFAULT_COLLECTION_ID,
+};
 use crate::ports::in_ports::ContextManagementPort;
-use crate::ports::out_ports::{ContextRepositoryPort, EmbeddingPort};
+use crate::ports::out_ports::{
+    ContextRepositoryPort, EmbeddingPort, EventPublisherPort, VectorStorePort,
+};
+
+/// Number of contexts listed per page while loading stored embeddings
+const LOAD_PAGE_SIZE: usize = 500;
+
+/// Number of contexts listed per page while deleting contexts by tag
+const DELETE_PAGE_SIZE: usize = 500;
+
+/// Number of children listed per page while walking a context's descendants
+const CHILDREN_PAGE_SIZE: usize = 500;
+
+/// Prior states kept for each context unless configured otherwise
+const DEFAULT_MAX_REVISIONS: usize = 10;
 
 /// Application service implementing the context management use cases
 pub struct ContextManagementService {
     context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
     embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    vector_store: Arc<dyn VectorStorePort + Send + Sync>,
     chunking_service: ChunkingService,
+    embedding_dimension: Option<usize>,
+    max_content_bytes: Option<usize>,
+    max_delete_batch: Option<usize>,
+    max_revisions: usize,
+    deleted_retention: Option<chrono::Duration>,
+    child_policy: ChildPolicy,
+    event_publisher: Option<Arc<dyn EventPublisherPort + Send + Sync>>,
 }
 
 impl ContextManagementService {
+    /// Fails if `max_chunk_size` and `chunk_overlap` can't chunk content, as
+    /// `ChunkingService::new` does
     pub fn new(
         context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
         embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+        vector_store: Arc<dyn VectorStorePort + Send + Sync>,
         max_chunk_size: usize,
         chunk_overlap: usize,
-    ) -> Self {
-        Self {
+    ) -> McpResult<Self> {
+        Ok(Self {
             context_repository,
             embedding_service,
-            chunking_service: ChunkingService::new(max_chunk_size, chunk_overlap),
+            vector_store,
+            chunking_service: ChunkingService::new(max_chunk_size, chunk_overlap)?,
+            embedding_dimension: None,
+            max_content_bytes: None,
+            max_delete_batch: None,
+            max_revisions: DEFAULT_MAX_REVISIONS,
+            deleted_retention: None,
+            child_policy: ChildPolicy::default(),
+            event_publisher: None,
+        })
+    }
+
+    /// Chunk contents by `strategy` instead of fixed-size windows
+    pub fn with_chunking_strategy(mut self, strategy: ChunkingStrategy) -> Self {
+        self.chunking_service = self.chunking_service.with_strategy(strategy);
+        self
+    }
+
+    /// Measure chunk sizes and overlaps in tokens of `tokenizer` instead of bytes
+    pub fn with_token_sizes(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.chunking_service = self.chunking_service.with_token_sizes(tokenizer);
+        self
+    }
+
+    /// Reject embeddings that aren't `dimension` long instead of storing them
+    pub fn with_embedding_dimension(mut self, dimension: usize) -> Self {
+        self.embedding_dimension = Some(dimension);
+        self
+    }
+
+    /// Reject content longer than `max_content_bytes` when storing or updating a context
+    pub fn with_max_content_bytes(mut self, max_content_bytes: usize) -> Self {
+        self.max_content_bytes = Some(max_content_bytes);
+        self
+    }
+
+    /// Keep at most `max_revisions` prior states of each context, or none with 0
+    pub fn with_max_revisions(mut self, max_revisions: usize) -> Self {
+        self.max_revisions = max_revisions;
+        self
+    }
+
+    /// Let `purge_deleted` delete soft-deleted contexts for good once they have been deleted
+    /// for `retention`; without it they stay until deleted by hand
+    pub fn with_deleted_retention(mut self, retention: chrono::Duration) -> Self {
+        self.deleted_retention = Some(retention);
+        self
+    }
+
+    /// Treat the children of a context deleted for good by `policy` instead of orphaning them
+    pub fn with_child_policy(mut self, policy: ChildPolicy) -> Self {
+        self.child_policy = policy;
+        self
+    }
+
+    /// Reject batch deletes naming more than `max_delete_batch` contexts
+    pub fn with_max_delete_batch(mut self, max_delete_batch: usize) -> Self {
+        self.max_delete_batch = Some(max_delete_batch);
+        self
+    }
+
+    /// Publish an event for every context stored, updated or deleted
+    pub fn with_event_publisher(
+        mut self,
+        event_publisher: Arc<dyn EventPublisherPort + Send + Sync>,
+    ) -> Self {
+        self.event_publisher = Some(event_publisher);
+        self
+    }
+
+    /// Tell subscribers what happened to a context, if events are published
+    fn publish(&self, kind: ContextEventKind, context_id: Uuid, tags: &[String]) {
+        if let Some(publisher) = &self.event_publisher {
+            publisher.publish(ContextEvent::now(kind, context_id, tags.to_vec()));
         }
     }
 
-    /// Process a context by chunking it and generating embeddings
-    async fn process_context(&self, context: Context) -> McpResult<Context> {
-        // Split context into chunks
-        let chunks = self.chunking_service.chunk_context(&context);
+    /// The context with `context_id`, unless it has expired or was deleted
+    async fn find_live(&self, context_id: Uuid) -> McpResult<Context> {
+        let context = self.context_repository.find_by_id(context_id).await?;
+        if !context.is_live_at(Utc::now()) {
+            return Err(McpError::ContextNotFound(context_id));
+        }
+        Ok(context)
+    }
 
-        // Generate embeddings for chunks
-        let chunks_with_embeddings = self.embedding_service.embed_chunks(chunks).await?;
+    /// A live context in `collection` whose content hashes to `content_hash`, if one is
+    /// stored
+    async fn find_duplicate(
+        &self,
+        content_hash: &str,
+        collection: Uuid,
+    ) -> McpResult<Option<Context>> {
+        let now = Utc::now();
+        Ok(self
+            .context_repository
+            .find_by_content_hash(content_hash)
+            .await?
+            .into_iter()
+            .find(|context| context.is_live_at(now) && context.collection() == collection))
+    }
+
+    /// Put the embeddings of every stored chunk into the vector store, returning how many
+    /// were loaded
+    ///
+    /// The repository keeps the embeddings, so stores that live in memory can be rebuilt
+    /// after a restart without embedding anything again.
+    pub async fn load_existing(&self) -> McpResult<usize> {
+        let mut loaded = 0;
+        let mut offset = 0;
+        loop {
+            let page = self
+                .context_repository
+                .list_all(LOAD_PAGE_SIZE, offset)
+                .await?;
+
+            // Soft-deleted contexts keep their chunks, but mustn't match searches
+            for context in page.iter().filter(|context| context.deleted_at.is_none()) {
+                let chunks: Vec<ContextChunk> = match self
+                    .context_repository
+                    .find_chunks_by_context_id(context.id)
+                    .await
+                {
+                    Ok(chunks) => chunks,
+                    Err(McpError::ContextNotFound(_)) => continue,
+                    Err(err) => return Err(err),
+                };
+                let chunks: Vec<ContextChunk> = chunks
+                    .into_iter()
+                    .filter(|chunk| chunk.embedding.is_some())
+                    .collect();
+                if chunks.is_empty() {
+                    continue;
+                }
+
+                self.vector_store
+                    .upsert(&chunks, &context.metadata.tags)
+                    .await?;
+                loaded += chunks.len();
+            }
+
+            if page.len() < LOAD_PAGE_SIZE {
+                return Ok(loaded);
+            }
+            offset += page.len();
+        }
+    }
+
+    /// Delete every context that expired by `now`, with its chunks and embeddings,
+    /// returning how many were deleted
+    pub async fn sweep_expired(&self, now: DateTime<Utc>) -> McpResult<usize> {
+        // Each page is deleted before the next is found, past the contexts kept, as when
+        // deleting by tag
+        let mut deleted = 0;
+        let mut kept = 0;
+        loop {
+            let page = self
+                .context_repository
+                .find_expired(now, DELETE_PAGE_SIZE, kept)
+                .await?;
+            for context in &page {
+                match self.delete_context(context.id).await {
+                    Ok(()) => deleted += 1,
+                    Err(McpError::HasChildren(_)) => kept += 1,
+                    Err(McpError::ContextNotFound(_)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if page.len() < DELETE_PAGE_SIZE {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    /// Delete every context soft-deleted longer than the retention ago by `now`, with its
+    /// chunks and revisions, returning how many were deleted
+    pub async fn purge_deleted(&self, now: DateTime<Utc>) -> McpResult<usize> {
+        let Some(retention) = self.deleted_retention else {
+            return Ok(0);
+        };
+
+        // Each page is deleted before the next is found, as when sweeping expired contexts
+        let mut deleted = 0;
+        let mut kept = 0;
+        loop {
+            let page = self
+                .context_repository
+                .list_deleted(now - retention, DELETE_PAGE_SIZE, kept)
+                .await?;
+            for context in &page {
+                match self.delete_context(context.id).await {
+                    Ok(()) => deleted += 1,
+                    Err(McpError::HasChildren(_)) => kept += 1,
+                    Err(McpError::ContextNotFound(_)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if page.len() < DELETE_PAGE_SIZE {
+                return Ok(deleted);
+            }
+        }
+    }
+
+    /// The children of a context in listing order, only live ones if `live_only`
+    async fn children(&self, parent_id: Uuid, live_only: bool) -> McpResult<Vec<Context>> {
+        let filter = if live_only {
+            ContextFilter::children_of(parent_id).live_now()
+        } else {
+            ContextFilter::children_of(parent_id)
+        };
 
-        // Store chunks
+        let mut children = Vec::new();
+        loop {
+            let page = self
+                .context_repository
+                .find_filtered(&filter, CHILDREN_PAGE_SIZE, children.len())
+                .await?;
+            let page_len = page.len();
+            children.extend(page);
+            if page_len < CHILDREN_PAGE_SIZE {
+                return Ok(children);
+            }
+        }
+    }
+
+    /// The descendants of a context, breadth first, only live ones if `live_only`
+    ///
+    /// Imports can make a context its own ancestor, so each one is visited once.
+    async fn descendants(&self, context_id: Uuid, live_only: bool) -> McpResult<Vec<Context>> {
+        let mut seen = HashSet::from([context_id]);
+        let mut pending = VecDeque::from([context_id]);
+        let mut descendants = Vec::new();
+        while let Some(parent_id) = pending.pop_front() {
+            for child in self.children(parent_id, live_only).await? {
+                if seen.insert(child.id) {
+                    pending.push_back(child.id);
+                    descendants.push(child);
+                }
+            }
+        }
+        Ok(descendants)
+    }
+
+    /// Delete a context for good, with its chunks, embeddings, revisions and the relations
+    /// to it, leaving its children as they are
+    async fn delete_one(&self, context: &Context) -> McpResult<()> {
+        let context_id = context.id;
+        let chunk_ids = self.chunk_ids(context_id).await?;
+
+        // Delete chunks first
         self.context_repository
-            .save_chunks(chunks_with_embeddings)
+            .delete_chunks_by_context_id(context_id)
             .await?;
 
-        Ok(context)
+        // Then delete the context
+        self.context_repository.delete(context_id).await?;
+
+        // Relations to it would lead nowhere, so they go with it
+        for source in self.context_repository.find_related_to(context_id).await? {
+            match self
+                .context_repository
+                .remove_relations(source.id, context_id, None)
+                .await
+            {
+                Ok(source) => {
+                    self.publish(ContextEventKind::Updated, source.id, &source.metadata.tags)
+                }
+                Err(McpError::ContextNotFound(_)) | Err(McpError::RelationNotFound { .. }) => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        self.vector_store.delete(&chunk_ids).await?;
+        // Subscribers filter deletions by tag, so the tags are those read before deleting
+        self.publish(
+            ContextEventKind::Deleted,
+            context_id,
+            &context.metadata.tags,
+        );
+        Ok(())
+    }
+
+    /// Ids of the chunks stored for a context, which some repositories report as missing
+    /// when there are none
+    async fn chunk_ids(&self, context_id: Uuid) -> McpResult<Vec<Uuid>> {
+        match self
+            .context_repository
+            .find_chunks_by_context_id(context_id)
+            .await
+        {
+            Ok(chunks) => Ok(chunks.iter().map(|chunk| chunk.chunk_id).collect()),
+            Err(McpError::ContextNotFound(_)) => Ok(Vec::new()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Delete the embeddings of contexts the repository evicted to make room
+    async fn delete_evicted_embeddings(&self) -> McpResult<()> {
+        let chunk_ids = self.context_repository.take_evicted_chunk_ids().await?;
+        if chunk_ids.is_empty() {
+            return Ok(());
+        }
+        self.vector_store.delete(&chunk_ids).await
+    }
+
+    /// Process a context by chunking it, by `chunking` if given, and generating embeddings
+    async fn process_context(
+        &self,
+        context: &Context,
+        chunking: Option<ChunkingStrategy>,
+    ) -> McpResult<Vec<ContextChunk>> {
+        // Split context into chunks
+        let mut chunks = match chunking {
+            Some(strategy) => self.chunking_service.chunk_context_with(context, strategy),
+            None => self.chunking_service.chunk_context(context),
+        };
+
+        // Generate embeddings for chunks
+        let texts: Vec<String> = chunks.iter().map(|chunk| chunk.content.clone()).collect();
+        let embeddings = self.embedding_service.embed_texts(&texts).await?;
+        if embeddings.len() != chunks.len() {
+            return Err(McpError::EmbeddingError(format!(
+                "Expected {} embeddings, received {}",
+                chunks.len(),
+                embeddings.len()
+            )));
+        }
+
+        // Vectors of another length can't be compared with the ones already stored
+        if let Some(expected) = self.embedding_dimension {
+            if let Some(embedding) = embeddings
+                .iter()
+                .find(|embedding| embedding.len() != expected)
+            {
+                return Err(McpError::dimension_mismatch(expected, embedding.len()));
+            }
+        }
+
+        for (chunk, embedding) in chunks.iter_mut().zip(embeddings) {
+            chunk.embedding = Some(embedding);
+        }
+        Ok(chunks)
     }
 }
 
 #[async_trait]
 impl ContextManagementPort for ContextManagementService {
+    fn check_content(&self, content: &str) -> McpResult<()> {
+        if content.trim().is_empty() {
+            return Err(McpError::ValidationError(
+                "content must not be empty or whitespace-only".to_string(),
+            ));
+        }
+
+        match self.max_content_bytes {
+            Some(max) if content.len() > max => Err(McpError::ValidationError(format!(
+                "content exceeds {} bytes",
+                max
+            ))),
+            _ => Ok(()),
+        }
+    }
+
     async fn store_context(
         &self,
         content: String,
         metadata: ContextMetadata,
+        options: StoreOptions,
     ) -> McpResult<Context> {
+        let StoreOptions {
+            expires_at,
+            chunking,
+            on_duplicate,
+            parent_id,
+            collection_id,
+        } = options;
+        self.check_content(&content)?;
+
+        let parent = match parent_id {
+            Some(parent_id) => match self.find_live(parent_id).await {
+                Ok(parent) => Some(parent),
+                Err(McpError::ContextNotFound(_)) => {
+                    return Err(McpError::InvalidContextReference(format!(
+                        "parent_id {} names no context",
+                        parent_id
+                    )))
+                }
+                Err(err) => return Err(err),
+            },
+            None => None,
+        };
+
+        // The default collection is stored as no collection at all
+        let collection_id = match collection_id {
+            Some(DEFAULT_COLLECTION_ID) => None,
+            Some(collection_id) => {
+                match self.context_repository.find_collection(collection_id).await {
+                    Ok(_) => Some(collection_id),
+                    Err(McpError::CollectionNotFound(_)) => {
+                        return Err(McpError::InvalidContextReference(format!(
+                            "collection_id {} names no collection",
+                            collection_id
+                        )))
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            // Children stored without a collection join their parent's
+            None => parent.and_then(|parent| parent.collection_id),
+        };
+
+        let hash = content_hash(&content);
+        if on_duplicate != OnDuplicate::Allow {
+            let collection = collection_id.unwrap_or(DEFAULT_COLLECTION_ID);
+            if let Some(existing) = self.find_duplicate(&hash, collection).await? {
+                return match on_duplicate {
+                    OnDuplicate::Skip => Ok(existing),
+                    _ => Err(McpError::ContextAlreadyExists(existing.id)),
+                };
+            }
+        }
+
         // Create a new context entity
         let context = Context {
-            id: Uuid::new_v4(),
-            content,
-            metadata,
-            created_at: Utc::now(),
-            expires_at: None,
+            expires_at,
+            parent_id,
+            collection_id,
+            ..Context::new(
+                content,
+                ContextMetadata {
+                    content_hash: Some(hash),
+                    ..metadata
+                },
+            )
         };
 
-        // Save the context
-        let saved_context = self.context_repository.save_context(context).await?;
+        // Process the context (chunk and embed) before anything is stored
+        let chunks = self.process_context(&context, chunking).await?;
 
-        // Process the context (chunk and embed)
-        self.process_context(saved_context).await
+        // Save the context and its chunks together
+        let tags = context.metadata.tags.clone();
+        let context = self
+            .context_repository
+            .save_context_with_chunks(context, chunks.clone())
+            .await?;
+
+        // Only stored chunks become searchable
+        self.vector_store.upsert(&chunks, &tags).await?;
+        self.delete_evicted_embeddings().await?;
+        self.publish(
+            ContextEventKind::Created,
+            context.id,
+            &context.metadata.tags,
+        );
+        Ok(context)
+    }
+
+    async fn import_context(
+        &self,
+        mut context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context> {
+        self.check_content(&context.content)?;
+        context.metadata.content_hash = Some(content_hash(&context.content));
+        if let Some(chunk) = chunks.iter().find(|chunk| chunk.context_id != context.id) {
+            return Err(McpError::ValidationError(format!(
+                "Chunk {} belongs to context {}, not {}",
+                chunk.chunk_id, chunk.context_id, context.id
+            )));
+        }
+
+        // Embeddings made elsewhere must still be comparable with the ones stored here
+        let chunks = if !chunks.is_empty() && chunks.iter().all(|chunk| chunk.embedding.is_some()) {
+            if let Some(expected) = self.embedding_dimension {
+                if let Some(embedding) = chunks
+                    .iter()
+                    .filter_map(|chunk| chunk.embedding.as_ref())
+                    .find(|embedding| embedding.len() != expected)
+                {
+                    return Err(McpError::dimension_mismatch(expected, embedding.len()));
+                }
+            }
+            chunks
+        } else {
+            self.process_context(&context, None).await?
+        };
+
+        let tags = context.metadata.tags.clone();
+        let context = self
+            .context_repository
+            .save_context_with_chunks(context, chunks.clone())
+            .await?;
+        self.vector_store.upsert(&chunks, &tags).await?;
+        self.delete_evicted_embeddings().await?;
+        self.publish(
+            ContextEventKind::Created,
+            context.id,
+            &context.metadata.tags,
+        );
+        Ok(context)
     }
 
     async fn get_context(&self, context_id: Uuid) -> McpResult<Context> {
-        self.context_repository.find_by_id(context_id).await
+        self.find_live(context_id).await
+    }
+
+    async fn get_chunk(&self, chunk_id: Uuid) -> McpResult<ContextChunk> {
+        let chunk = self.context_repository.find_chunk_by_id(chunk_id).await?;
+
+        // Deleted and expired contexts keep their chunks, which mustn't be read meanwhile
+        match self.find_live(chunk.context_id).await {
+            Ok(_) => Ok(chunk),
+            Err(McpError::ContextNotFound(_)) => Err(McpError::ChunkNotFound(chunk_id)),
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn get_chunks(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>> {
+        self.find_live(context_id).await?;
+        let mut chunks = match self
+            .context_repository
+            .find_chunks_by_context_id(context_id)
+            .await
+        {
+            Ok(chunks) => chunks,
+            Err(McpError::ContextNotFound(_)) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        chunks.sort_by_key(|chunk| chunk.position);
+        Ok(chunks)
     }
 
     async fn update_context(
@@ -78,49 +594,421 @@ impl ContextManagementPort for ContextManagementService {
         context_id: Uuid,
         content: String,
         metadata: ContextMetadata,
+        expires_at: Option<DateTime<Utc>>,
+        expected_version: Option<u64>,
     ) -> McpResult<Context> {
-        // Find the existing context
-        let mut context = self.context_repository.find_by_id(context_id).await?;
+        self.check_content(&content)?;
+
+        // Find the existing context, failing before anything is embedded if it has moved on
+        let mut context = self.find_live(context_id).await?;
+        context.check_version(expected_version)?;
+        let old_chunk_ids = self.chunk_ids(context_id).await?;
+        let previous = ContextRevision::of(&context, Utc::now());
 
         // Update its fields
+        context.metadata = ContextMetadata {
+            content_hash: Some(content_hash(&content)),
+            ..metadata
+        };
         context.content = content;
-        context.metadata = metadata;
+        context.expires_at = expires_at;
+
+        // Re-process the context
+        let chunks = self.process_context(&context, None).await?;
+
+        // Replace the context and its old chunks together
+        let context = self
+            .context_repository
+            .replace_context_with_chunks(context, chunks.clone(), expected_version)
+            .await?;
+
+        // Archive the replaced state only once the replace went through, so a refused update
+        // leaves no revision behind
+        if self.max_revisions > 0 {
+            self.context_repository
+                .save_revision(previous, self.max_revisions)
+                .await?;
+        }
 
-        // Delete old chunks
+        // The old chunks are gone, so their embeddings must not match searches anymore
+        self.vector_store.delete(&old_chunk_ids).await?;
+        self.vector_store
+            .upsert(&chunks, &context.metadata.tags)
+            .await?;
+        self.publish(
+            ContextEventKind::Updated,
+            context.id,
+            &context.metadata.tags,
+        );
+        Ok(context)
+    }
+
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>> {
+        self.find_live(context_id).await?;
+        self.context_repository.list_revisions(context_id).await
+    }
+
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision> {
+        self.find_live(context_id).await?;
         self.context_repository
-            .delete_chunks_by_context_id(context_id)
+            .get_revision(context_id, version)
+            .await
+    }
+
+    async fn restore_revision(
+        &self,
+        context_id: Uuid,
+        version: u64,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context> {
+        let current = self.find_live(context_id).await?;
+        let revision = self
+            .context_repository
+            .get_revision(context_id, version)
             .await?;
 
-        // Save the updated context
-        let updated_context = self.context_repository.update(context).await?;
+        // Only the content and metadata go back; the expiry stays as it is now
+        self.update_context(
+            context_id,
+            revision.content,
+            revision.metadata,
+            current.expires_at,
+            expected_version,
+        )
+        .await
+    }
 
-        // Re-process the context
-        self.process_context(updated_context).await
+    async fn add_relation(
+        &self,
+        context_id: Uuid,
+        relation: ContextRelation,
+    ) -> McpResult<Context> {
+        let relation_type = relation.relation_type.trim();
+        if relation_type.is_empty() {
+            return Err(McpError::ValidationError(
+                "relation_type must not be empty".to_string(),
+            ));
+        }
+        if relation.target_id == context_id {
+            return Err(McpError::ValidationError(
+                "a context can't relate to itself".to_string(),
+            ));
+        }
+
+        self.find_live(context_id).await?;
+        match self.find_live(relation.target_id).await {
+            Ok(_) => {}
+            Err(McpError::ContextNotFound(_)) => {
+                return Err(McpError::ValidationError(format!(
+                    "target_id {} names no context",
+                    relation.target_id
+                )))
+            }
+            Err(err) => return Err(err),
+        }
+
+        let context = self
+            .context_repository
+            .add_relation(
+                context_id,
+                ContextRelation {
+                    target_id: relation.target_id,
+                    relation_type: relation_type.to_string(),
+                },
+            )
+            .await?;
+        self.publish(
+            ContextEventKind::Updated,
+            context.id,
+            &context.metadata.tags,
+        );
+        Ok(context)
+    }
+
+    async fn remove_relations(
+        &self,
+        context_id: Uuid,
+        target_id: Uuid,
+        relation_type: Option<String>,
+    ) -> McpResult<Context> {
+        self.find_live(context_id).await?;
+        let context = self
+            .context_repository
+            .remove_relations(context_id, target_id, relation_type.as_deref())
+            .await?;
+        self.publish(
+            ContextEventKind::Updated,
+            context.id,
+            &context.metadata.tags,
+        );
+        Ok(context)
+    }
+
+    async fn related_contexts(
+        &self,
+        context_id: Uuid,
+        relation_type: Option<String>,
+    ) -> McpResult<Vec<(ContextRelation, Context)>> {
+        let context = self.find_live(context_id).await?;
+        let relations: Vec<ContextRelation> = context
+            .relations
+            .into_iter()
+            .filter(|relation| {
+                relation_type
+                    .as_deref()
+                    .is_none_or(|wanted| relation.relation_type == wanted)
+            })
+            .collect();
+
+        // Targets that expired or were soft-deleted are left out until they're back
+        let target_ids: Vec<Uuid> = relations
+            .iter()
+            .map(|relation| relation.target_id)
+            .collect();
+        let now = Utc::now();
+        let targets: HashMap<Uuid, Context> = self
+            .context_repository
+            .find_by_ids(&target_ids)
+            .await?
+            .into_iter()
+            .filter(|target| target.is_live_at(now))
+            .map(|target| (target.id, target))
+            .collect();
+
+        Ok(relations
+            .into_iter()
+            .filter_map(|relation| {
+                let target = targets.get(&relation.target_id)?.clone();
+                Some((relation, target))
+            })
+            .collect())
+    }
+
+    async fn list_children(
+        &self,
+        context_id: Uuid,
+        include_descendants: bool,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        self.find_live(context_id).await?;
+        if !include_descendants {
+            return self
+                .context_repository
+                .find_filtered(
+                    &ContextFilter::children_of(context_id).live_now(),
+                    limit,
+                    offset,
+                )
+                .await;
+        }
+
+        // Descendants of a deleted or expired child are left out along with it
+        Ok(self
+            .descendants(context_id, true)
+            .await?
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect())
     }
 
     async fn delete_context(&self, context_id: Uuid) -> McpResult<()> {
-        // Delete chunks first
+        // Fail before any child is touched if the context is already gone
+        let context = self.context_repository.find_by_id(context_id).await?;
+
+        match self.child_policy {
+            ChildPolicy::Orphan => {
+                for child in self.children(context_id, false).await? {
+                    let expected_version = Some(child.version);
+                    let orphan = Context {
+                        parent_id: None,
+                        ..child
+                    };
+                    match self
+                        .context_repository
+                        .update(orphan, expected_version)
+                        .await
+                    {
+                        Ok(child) => {
+                            self.publish(ContextEventKind::Updated, child.id, &child.metadata.tags)
+                        }
+                        Err(McpError::ContextNotFound(_)) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+            ChildPolicy::Cascade => {
+                // Deepest first, so no context is ever left pointing at a deleted parent
+                for descendant in self.descendants(context_id, false).await?.iter().rev() {
+                    match self.delete_one(descendant).await {
+                        Ok(()) | Err(McpError::ContextNotFound(_)) => {}
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+            ChildPolicy::Reject => {
+                let children = self
+                    .context_repository
+                    .find_filtered(&ContextFilter::children_of(context_id), 1, 0)
+                    .await?;
+                if !children.is_empty() {
+                    return Err(McpError::HasChildren(context_id));
+                }
+            }
+        }
+
+        self.delete_one(&context).await
+    }
+
+    async fn soft_delete_context(&self, context_id: Uuid) -> McpResult<()> {
+        let context = self.find_live(context_id).await?;
+        let chunk_ids = self.chunk_ids(context_id).await?;
         self.context_repository
-            .delete_chunks_by_context_id(context_id)
+            .soft_delete(context_id, Utc::now())
             .await?;
 
-        // Then delete the context
-        self.context_repository.delete(context_id).await
+        // The chunks stay stored for a restore, but mustn't match searches meanwhile
+        self.vector_store.delete(&chunk_ids).await?;
+        self.publish(
+            ContextEventKind::Deleted,
+            context_id,
+            &context.metadata.tags,
+        );
+        Ok(())
+    }
+
+    async fn restore_context(&self, context_id: Uuid) -> McpResult<Context> {
+        let context = self.context_repository.find_by_id(context_id).await?;
+        if context.deleted_at.is_none() {
+            return self.find_live(context_id).await;
+        }
+
+        // Chunks that lost their embeddings, or were never kept, are made again before the
+        // context comes back, so it is searchable as soon as it can be read
+        let stored = match self
+            .context_repository
+            .find_chunks_by_context_id(context_id)
+            .await
+        {
+            Ok(chunks) => chunks,
+            Err(McpError::ContextNotFound(_)) => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let (context, chunks) =
+            if !stored.is_empty() && stored.iter().all(|chunk| chunk.embedding.is_some()) {
+                (self.context_repository.restore(context_id).await?, stored)
+            } else {
+                let chunks = self.process_context(&context, None).await?;
+                let expected_version = Some(context.version);
+                let restored = Context {
+                    deleted_at: None,
+                    ..context
+                };
+                let context = self
+                    .context_repository
+                    .replace_context_with_chunks(restored, chunks.clone(), expected_version)
+                    .await?;
+                (context, chunks)
+            };
+
+        self.vector_store
+            .upsert(&chunks, &context.metadata.tags)
+            .await?;
+        self.publish(
+            ContextEventKind::Created,
+            context.id,
+            &context.metadata.tags,
+        );
+        Ok(context)
+    }
+
+    async fn delete_contexts(
+        &self,
+        context_ids: Vec<Uuid>,
+    ) -> McpResult<Vec<(Uuid, DeleteOutcome)>> {
+        if let Some(max) = self.max_delete_batch {
+            if context_ids.len() > max {
+                return Err(McpError::ValidationError(format!(
+                    "ids has {} entries, more than the maximum of {}",
+                    context_ids.len(),
+                    max
+                )));
+            }
+        }
+
+        let mut outcomes = Vec::with_capacity(context_ids.len());
+        for context_id in context_ids {
+            let outcome = match self.delete_context(context_id).await {
+                Ok(()) => DeleteOutcome::Deleted,
+                Err(McpError::ContextNotFound(_)) => DeleteOutcome::NotFound,
+                Err(McpError::HasChildren(_)) => DeleteOutcome::HasChildren,
+                Err(err) => return Err(err),
+            };
+            outcomes.push((context_id, outcome));
+        }
+        Ok(outcomes)
+    }
+
+    async fn delete_by_tags(&self, tags: Vec<String>) -> McpResult<usize> {
+        if tags.is_empty() {
+            return Err(McpError::ValidationError(
+                "tags must name at least one tag".to_string(),
+            ));
+        }
+
+        // Only live contexts are deleted, so soft-deleted ones can still be restored. Each
+        // page is deleted before the next is listed, so the matches left start past the ones
+        // that couldn't be deleted
+        let filter = ContextFilter::tagged(tags).live_now();
+        let mut deleted = 0;
+        let mut kept = 0;
+        loop {
+            let page = self
+                .context_repository
+                .find_filtered(&filter, DELETE_PAGE_SIZE, kept)
+                .await?;
+            for context in &page {
+                match self.delete_context(context.id).await {
+                    Ok(()) => deleted += 1,
+                    Err(McpError::HasChildren(_)) => kept += 1,
+                    Err(McpError::ContextNotFound(_)) => {}
+                    Err(err) => return Err(err),
+                }
+            }
+
+            if page.len() < DELETE_PAGE_SIZE {
+                return Ok(deleted);
+            }
+        }
     }
 
     async fn list_contexts(
         &self,
-        tags: Option<Vec<String>>,
+        filter: ContextFilter,
         limit: usize,
         offset: usize,
     ) -> McpResult<Vec<Context>> {
-        match tags {
-            Some(tags) if !tags.is_empty() => {
-                self.context_repository
-                    .find_by_tags(&tags, limit, offset)
-                    .await
-            }
-            _ => self.context_repository.list_all(limit, offset).await,
-        }
+        self.context_repository
+            .find_filtered(&filter.live_now(), limit, offset)
+            .await
+    }
+
+    async fn count_contexts(&self, filter: ContextFilter) -> McpResult<usize> {
+        self.context_repository
+            .count_filtered(&filter.live_now())
+            .await
+    }
+
+    async fn tag_counts(&self) -> McpResult<Vec<(String, usize)>> {
+        let mut counts = self.context_repository.tag_counts(Utc::now()).await?;
+        counts.sort_by(|(a_tag, a_count), (b_tag, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_tag.cmp(b_tag))
+        });
+        Ok(counts)
+    }
+
+    async fn context_exists(&self, context_id: Uuid) -> McpResult<bool> {
+        self.context_repository.exists(context_id).await
     }
 }
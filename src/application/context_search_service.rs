@@ -1,78 +1,189 @@
-use crate::domain::service::RetrievalService;
-use crate::domain::{Context, ContextMatch, ContextReference, ContextSearchResult, McpResult};
+use crate::domain::service::{Fuzzy, Ranking, RetrievalService};
+use crate::domain::{
+    Context, ContextChunk, ContextFilter, ContextMatch, ContextReference, ContextSearchResult,
+    McpResult, SearchOptions, TagMode, TextQuery,
+};
 use crate::ports::in_ports::ContextSearchPort;
-use crate::ports::out_ports::{ContextRepositoryPort, EmbeddingPort};
+use crate::ports::out_ports::{
+    ContextRepositoryPort, EmbeddingPort, QueryExpansionPort, RerankerPort, VectorStorePort,
+};
 use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::HashMap;
 use std::sync::Arc;
+use uuid::Uuid;
+
+/// Chunks first fetched from the vector store per requested result, so that most searches find
+/// enough contexts passing their filters without fetching more
+const CANDIDATES_PER_RESULT: usize = 5;
 
 /// Application service implementing the context search use cases
 pub struct ContextSearchService {
     context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
     embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    vector_store: Arc<dyn VectorStorePort + Send + Sync>,
     retrieval_service: RetrievalService,
+    hybrid_alpha: f32,
+    min_score: Option<f32>,
+    fuzzy: bool,
+    query_expander: Option<Arc<dyn QueryExpansionPort + Send + Sync>>,
+    expand: bool,
+    reranker: Option<Arc<dyn RerankerPort + Send + Sync>>,
 }
 
 impl ContextSearchService {
     pub fn new(
         context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
         embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+        vector_store: Arc<dyn VectorStorePort + Send + Sync>,
         max_results: usize,
     ) -> Self {
         Self {
             context_repository,
             embedding_service,
+            vector_store,
             retrieval_service: RetrievalService::new(max_results),
+            hybrid_alpha: 0.5,
+            min_score: None,
+            fuzzy: false,
+            query_expander: None,
+            expand: false,
+            reranker: None,
         }
     }
 
-    /// Convert a list of (Context, score) pairs into a ContextSearchResult
-    async fn to_search_result(
-        &self,
-        scored_contexts: Vec<(Context, f32)>,
-    ) -> McpResult<ContextSearchResult> {
-        let mut matches = Vec::new();
+    /// Score contexts with `ranking` instead of term matching
+    pub fn with_ranking(mut self, ranking: Ranking) -> Self {
+        self.retrieval_service = self.retrieval_service.with_ranking(ranking);
+        self
+    }
 
-        // For each matching context, get its chunks and create a ContextMatch
-        for (context, score) in scored_contexts {
-            let chunks = self
-                .context_repository
-                .find_chunks_by_context_id(context.id)
-                .await?;
+    /// Weigh vector similarity by `alpha` and the lexical score by `1 - alpha` (0.5 by default)
+    pub fn with_hybrid_alpha(mut self, alpha: f32) -> Self {
+        self.hybrid_alpha = alpha;
+        self
+    }
 
-            matches.push(ContextMatch {
-                context,
-                chunks: Some(chunks),
-                score,
-            });
-        }
+    /// Match query terms fuzzily by `fuzzy` in searches that ask for it
+    pub fn with_fuzzy(mut self, fuzzy: Fuzzy) -> Self {
+        self.retrieval_service = self.retrieval_service.with_fuzzy(fuzzy);
+        self
+    }
 
-        let total_matches = matches.len();
-        Ok(ContextSearchResult {
-            matches,
-            total_matches,
-        })
+    /// Whether searches match fuzzily unless a request says otherwise (off by default)
+    pub fn with_fuzzy_by_default(mut self, fuzzy: bool) -> Self {
+        self.fuzzy = fuzzy;
+        self
     }
-}
 
-#[async_trait]
-impl ContextSearchPort for ContextSearchService {
-    async fn search(&self, query: String, limit: usize) -> McpResult<ContextSearchResult> {
-        // Use the embedding service to find similar chunks
-        let similar_chunks = self.embedding_service.find_similar(&query, limit).await?;
-
-        // Get the contexts for these chunks
-        let mut context_ids = std::collections::HashSet::new();
-        for (chunk, _) in &similar_chunks {
-            context_ids.insert(chunk.context_id);
+    /// Leave out matches scoring below `min_score` unless a request sets its own threshold
+    pub fn with_min_score(mut self, min_score: Option<f32>) -> Self {
+        self.min_score = min_score;
+        self
+    }
+
+    /// Search for the alternatives `query_expander` finds for query terms in searches that ask
+    /// for it, at `weight` of the query's own terms
+    pub fn with_query_expansion(
+        mut self,
+        query_expander: Option<Arc<dyn QueryExpansionPort + Send + Sync>>,
+        weight: f32,
+    ) -> Self {
+        self.query_expander = query_expander;
+        self.retrieval_service = self.retrieval_service.with_expansion_weight(weight);
+        self
+    }
+
+    /// Whether searches are expanded unless a request says otherwise (off by default)
+    pub fn with_expansion_by_default(mut self, expand: bool) -> Self {
+        self.expand = expand;
+        self
+    }
+
+    /// Reorder and rescore the ranked matches with `reranker`, if any, before they are returned
+    pub fn with_reranker(mut self, reranker: Option<Arc<dyn RerankerPort + Send + Sync>>) -> Self {
+        self.reranker = reranker;
+        self
+    }
+
+    /// Drop the matches scoring below the request's threshold, or the default one
+    fn above_min_score(
+        &self,
+        mut scored_contexts: Vec<(Context, f32)>,
+        options: &SearchOptions,
+    ) -> Vec<(Context, f32)> {
+        if let Some(min_score) = options.min_score.or(self.min_score) {
+            scored_contexts.retain(|(_, score)| *score >= min_score);
         }
+        scored_contexts
+    }
 
-        // Fetch the full contexts
-        let mut contexts = Vec::new();
-        for id in context_ids {
-            if let Ok(context) = self.context_repository.find_by_id(id).await {
-                contexts.push(context);
+    /// Rank the contexts of the chunks most similar to the query, among those with all `tags`,
+    /// or any of them by `options.tag_mode`, that pass the collection, exclusion, source and
+    /// date filters of `options`
+    async fn search_similar(
+        &self,
+        query: String,
+        tags: &[String],
+        limit: usize,
+        options: SearchOptions,
+    ) -> McpResult<ContextSearchResult> {
+        // Quoted phrases must appear as written; scoring sees every word
+        let text_query = TextQuery::parse(&query);
+        let query = text_query.text.clone();
+
+        // Add alternatives of the query terms, unless the request turned that off
+        let expansions = match &self.query_expander {
+            Some(expander) if options.expand.unwrap_or(self.expand) => {
+                expander.expand(&query).await?
             }
-        }
+            _ => Vec::new(),
+        };
+
+        // Embed the query and find the most similar stored chunks, fetching more until enough
+        // of their contexts pass the filters or the store has no more, as a search narrowed to
+        // a few contexts can find none of them among the chunks most similar overall
+        let query_embedding = self.embed_query(&query, &expansions).await?;
+        let wanted = limit.min(self.retrieval_service.max_results());
+        let mut candidates = limit.saturating_mul(CANDIDATES_PER_RESULT).max(1);
+        let now = Utc::now();
+        let (contexts, vector_scores, exhausted) = loop {
+            let similar_chunks = if options.tag_mode == TagMode::Any && tags.len() > 1 {
+                self.similar_to_any_tag(&query_embedding, tags, candidates)
+                    .await?
+            } else {
+                self.vector_store
+                    .search(&query_embedding, tags, candidates)
+                    .await?
+            };
+            let exhausted = similar_chunks.len() < candidates;
+
+            // Get the contexts for these chunks, keeping each one's best similarity
+            let mut context_ids = Vec::new();
+            let mut vector_scores: HashMap<Uuid, f32> = HashMap::new();
+            for (chunk, similarity) in &similar_chunks {
+                if !context_ids.contains(&chunk.context_id) {
+                    context_ids.push(chunk.context_id);
+                }
+                let best = vector_scores.entry(chunk.context_id).or_insert(*similarity);
+                *best = best.max(*similarity);
+            }
+
+            // Fetch the full contexts in one call, keeping those that are live, pass the
+            // filters, still have the tags, and contain the quoted phrases
+            let mut contexts = self.context_repository.find_by_ids(&context_ids).await?;
+            contexts.retain(|context| {
+                context.is_live_at(now)
+                    && options.matches_context(context)
+                    && options.tag_mode.matches(tags, &context.metadata.tags)
+                    && text_query.matches(&context.content)
+            });
+
+            if contexts.len() >= wanted || exhausted {
+                break (contexts, vector_scores, exhausted);
+            }
+            candidates = candidates.saturating_mul(2);
+        };
 
         // Get all chunks for these contexts
         let mut all_chunks = Vec::new();
@@ -86,56 +197,153 @@ impl ContextSearchPort for ContextSearchService {
             }
         }
 
-        // Use the retrieval service to rank contexts by relevance
-        let scored_contexts = self
-            .retrieval_service
-            .rank_contexts(&query, &contexts, &all_chunks);
+        // Blend the lexical relevance with the vector similarity
+        let scored_contexts = self.retrieval_service.rank_hybrid(
+            &query,
+            &contexts,
+            &all_chunks,
+            &vector_scores,
+            options.hybrid_alpha.unwrap_or(self.hybrid_alpha),
+            options.fuzzy.unwrap_or(self.fuzzy),
+            &expansions,
+        );
+        let scored_contexts = self.above_min_score(scored_contexts, &options);
 
         // Convert the results to the expected format
-        self.to_search_result(scored_contexts).await
-    }
+        let mut result = self
+            .to_search_result(&query, scored_contexts, limit)
+            .await?;
 
-    async fn search_with_tags(
-        &self,
-        query: String,
-        tags: Vec<String>,
-        limit: usize,
-    ) -> McpResult<ContextSearchResult> {
-        // Get contexts with the specified tags
-        let tagged_contexts = self.context_repository.find_by_tags(&tags, 1000, 0).await?;
+        // Contexts past the chunks fetched are counted in storage by the filters, unless only
+        // their score or content can tell whether they match
+        if !exhausted {
+            if options.min_score.or(self.min_score).is_none() && text_query.phrases.is_empty() {
+                let filter = ContextFilter {
+                    live_at: Some(now),
+                    ..options.context_filter(tags)
+                };
+                let counted = self.context_repository.count_filtered(&filter).await?;
+                result.total_matches = result.total_matches.max(counted);
+            } else {
+                result.total_matches_is_lower_bound = true;
+            }
+        }
+        Ok(result)
+    }
 
-        if tagged_contexts.is_empty() {
-            return Ok(ContextSearchResult {
-                matches: Vec::new(),
-                total_matches: 0,
-            });
+    /// Embedding of the query, moved towards that of its expansions by the expansion weight
+    async fn embed_query(&self, query: &str, expansions: &[String]) -> McpResult<Vec<f32>> {
+        let mut embedding = self.embedding_service.embed_query(query).await?;
+        if expansions.is_empty() {
+            return Ok(embedding);
         }
 
-        // Use the embedding service to find similar chunks with tags
-        let _similar_chunks = self
+        let expanded = self
             .embedding_service
-            .find_similar_with_tags(&query, &tags, limit)
+            .embed_query(&expansions.join(" "))
             .await?;
+        let weight = self.retrieval_service.expansion_weight();
+        for (value, expanded) in embedding.iter_mut().zip(expanded) {
+            *value += weight * expanded;
+        }
+        Ok(embedding)
+    }
 
-        // Get all chunks for these contexts
-        let mut all_chunks = Vec::new();
-        for context in &tagged_contexts {
-            if let Ok(chunks) = self
+    /// The `limit` chunks most similar to the query among contexts with any of `tags`
+    ///
+    /// The vector store only filters on every tag at once, so each tag is searched on its own.
+    async fn similar_to_any_tag(
+        &self,
+        query_embedding: &[f32],
+        tags: &[String],
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>> {
+        let mut similar: HashMap<Uuid, (ContextChunk, f32)> = HashMap::new();
+        for tag in tags {
+            let found = self
+                .vector_store
+                .search(query_embedding, std::slice::from_ref(tag), limit)
+                .await?;
+            for (chunk, similarity) in found {
+                similar.entry(chunk.chunk_id).or_insert((chunk, similarity));
+            }
+        }
+
+        let mut similar: Vec<(ContextChunk, f32)> = similar.into_values().collect();
+        similar.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.chunk_id.cmp(&b.0.chunk_id))
+        });
+        similar.truncate(limit);
+        Ok(similar)
+    }
+
+    /// Convert ranked (Context, score) pairs into a ContextSearchResult of the best `limit`
+    ///
+    /// `total_matches` counts every pair, including those past the limit. With a reranker,
+    /// every pair is a candidate, and the reranker picks and scores the best `limit`.
+    async fn to_search_result(
+        &self,
+        query: &str,
+        scored_contexts: Vec<(Context, f32)>,
+        limit: usize,
+    ) -> McpResult<ContextSearchResult> {
+        let total_matches = scored_contexts.len();
+        let limit = limit.min(self.retrieval_service.max_results());
+        let candidates = if self.reranker.is_some() {
+            total_matches
+        } else {
+            limit
+        };
+        let mut matches = Vec::new();
+
+        // For each returned context, get its chunks and create a ContextMatch
+        for (context, score) in scored_contexts.into_iter().take(candidates) {
+            let chunks = self
                 .context_repository
                 .find_chunks_by_context_id(context.id)
-                .await
-            {
-                all_chunks.extend(chunks);
-            }
+                .await?;
+
+            matches.push(ContextMatch {
+                context,
+                chunks: Some(chunks),
+                score,
+            });
         }
 
-        // Use the retrieval service to rank contexts by relevance
-        let scored_contexts =
-            self.retrieval_service
-                .rank_contexts(&query, &tagged_contexts, &all_chunks);
+        if let Some(reranker) = &self.reranker {
+            matches = reranker.rerank(query, matches, limit).await?;
+            matches.truncate(limit);
+        }
 
-        // Convert the results to the expected format
-        self.to_search_result(scored_contexts).await
+        Ok(ContextSearchResult {
+            matches,
+            total_matches,
+            total_matches_is_lower_bound: false,
+        })
+    }
+}
+
+#[async_trait]
+impl ContextSearchPort for ContextSearchService {
+    async fn search(
+        &self,
+        query: String,
+        limit: usize,
+        options: SearchOptions,
+    ) -> McpResult<ContextSearchResult> {
+        self.search_similar(query, &[], limit, options).await
+    }
+
+    async fn search_with_tags(
+        &self,
+        query: String,
+        tags: Vec<String>,
+        limit: usize,
+        options: SearchOptions,
+    ) -> McpResult<ContextSearchResult> {
+        self.search_similar(query, &tags, limit, options).await
     }
 
     async fn retrieve_by_references(
@@ -144,15 +352,22 @@ impl ContextSearchPort for ContextSearchService {
     ) -> McpResult<ContextSearchResult> {
         let mut matches = Vec::new();
 
+        // Fetch all referenced contexts in one call, leaving out expired and deleted ones
+        let context_ids: Vec<Uuid> = references.iter().map(|r| r.context_id).collect();
+        let now = Utc::now();
+        let contexts: HashMap<Uuid, Context> = self
+            .context_repository
+            .find_by_ids(&context_ids)
+            .await?
+            .into_iter()
+            .filter(|context| context.is_live_at(now))
+            .map(|context| (context.id, context))
+            .collect();
+
         for reference in references {
             // Get the context
-            let context = match self
-                .context_repository
-                .find_by_id(reference.context_id)
-                .await
-            {
-                Ok(ctx) => ctx,
-                Err(_) => continue, // Skip invalid references
+            let Some(context) = contexts.get(&reference.context_id).cloned() else {
+                continue; // Skip invalid references
             };
 
             // Get the chunks, filtered by chunk_ids if specified
@@ -188,6 +403,7 @@ impl ContextSearchPort for ContextSearchService {
         Ok(ContextSearchResult {
             matches,
             total_matches,
+            total_matches_is_lower_bound: false,
         })
     }
 }
@@ -195,8 +411,7 @@ impl ContextSearchPort for ContextSearchService {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::ContextChunk;
-    use crate::domain::ContextMetadata;
+    use crate::domain::{Collection, ContextMetadata, ContextRevision};
     use mockall::mock;
     use mockall::predicate::*;
     use uuid::Uuid;
@@ -206,12 +421,26 @@ mod tests {
         #[async_trait]
         impl ContextRepositoryPort for ContextRepository {
             async fn find_by_id(&self, id: Uuid) -> McpResult<Context>;
+            async fn find_by_ids(&self, ids: &[Uuid]) -> McpResult<Vec<Context>>;
             async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>>;
+            async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk>;
             async fn find_by_tags(&self, tags: &[String], limit: usize, offset: usize) -> McpResult<Vec<Context>>;
             async fn save_context(&self, context: Context) -> McpResult<Context>;
-            async fn update(&self, context: Context) -> McpResult<Context>;
+            async fn update(&self, context: Context, expected_version: Option<u64>) -> McpResult<Context>;
+            async fn save_context_with_chunks(&self, context: Context, chunks: Vec<ContextChunk>) -> McpResult<Context>;
+            async fn replace_context_with_chunks(&self, context: Context, chunks: Vec<ContextChunk>, expected_version: Option<u64>) -> McpResult<Context>;
             async fn delete(&self, context_id: Uuid) -> McpResult<()>;
+            async fn save_revision(&self, revision: ContextRevision, max_revisions: usize) -> McpResult<()>;
+            async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>>;
+            async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision>;
+            async fn save_collection(&self, collection: Collection) -> McpResult<Collection>;
+            async fn find_collection(&self, collection_id: Uuid) -> McpResult<Collection>;
+            async fn list_collections(&self) -> McpResult<Vec<Collection>>;
+            async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()>;
             async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>>;
+            async fn count_all(&self) -> McpResult<usize>;
+            async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize>;
+            async fn exists(&self, context_id: Uuid) -> McpResult<bool>;
             async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>>;
             async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()>;
         }
@@ -221,19 +450,41 @@ mod tests {
         EmbeddingService {}
         #[async_trait]
         impl EmbeddingPort for EmbeddingService {
-            async fn find_similar(&self, query: &str, limit: usize) -> McpResult<Vec<(ContextChunk, f32)>>;
-            async fn find_similar_with_tags(&self, query: &str, tags: &[String], limit: usize) -> McpResult<Vec<(ContextChunk, f32)>>;
-            async fn embed_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>>;
+            async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>>;
+            async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>>;
+        }
+    }
+
+    mock! {
+        VectorStore {}
+        #[async_trait]
+        impl VectorStorePort for VectorStore {
+            async fn upsert(&self, chunks: &[ContextChunk], tags: &[String]) -> McpResult<()>;
+            async fn delete(&self, chunk_ids: &[Uuid]) -> McpResult<()>;
+            async fn search(&self, query: &[f32], tags: &[String], limit: usize) -> McpResult<Vec<(ContextChunk, f32)>>;
         }
     }
 
+    fn search_service(
+        repo_mock: MockContextRepository,
+        embedding_mock: MockEmbeddingService,
+        store_mock: MockVectorStore,
+    ) -> ContextSearchService {
+        ContextSearchService::new(
+            Arc::new(repo_mock),
+            Arc::new(embedding_mock),
+            Arc::new(store_mock),
+            5,
+        )
+    }
+
     fn create_test_context(id: Uuid) -> Context {
         Context {
             id,
-            content: format!("Context content {}", id),
-            metadata: ContextMetadata::default(),
-            created_at: chrono::Utc::now(),
-            expires_at: None,
+            ..Context::new(
+                format!("Context content {}", id),
+                ContextMetadata::default(),
+            )
         }
     }
 
@@ -251,6 +502,7 @@ mod tests {
     async fn test_search_success() {
         let mut repo_mock = MockContextRepository::new();
         let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
 
         // Create contexts with fixed IDs
         let context1_id = Uuid::new_v4();
@@ -263,12 +515,19 @@ mod tests {
         let chunk2 = create_test_chunk(context1_id, Uuid::new_v4());
         let chunk3 = create_test_chunk(context2_id, Uuid::new_v4());
 
-        // Set up expectations for embedding service with exact context IDs
+        // The query is embedded, then searched with exact context IDs
         embedding_mock
-            .expect_find_similar()
-            .with(eq("test query"), eq(10))
+            .expect_embed_query()
+            .with(eq("test query"))
+            .times(1)
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        store_mock
+            .expect_search()
+            .withf(|query, tags, limit| {
+                **query == [0.1, 0.2, 0.3] && tags.is_empty() && *limit == 50
+            })
             .times(1)
-            .returning(move |_, _| {
+            .returning(move |_, _, _| {
                 Ok(vec![
                     (chunk1.clone(), 0.9),
                     (chunk2.clone(), 0.8),
@@ -276,18 +535,12 @@ mod tests {
                 ])
             });
 
-        // Set up expectations for context repository with exact IDs
+        // Both contexts are fetched in a single batch, in match order
         repo_mock
-            .expect_find_by_id()
-            .with(eq(context1_id))
+            .expect_find_by_ids()
+            .withf(move |ids| ids.iter().copied().eq([context1_id, context2_id]))
             .times(1)
-            .returning(move |_| Ok(context1.clone()));
-
-        repo_mock
-            .expect_find_by_id()
-            .with(eq(context2_id))
-            .times(1)
-            .returning(move |_| Ok(context2.clone()));
+            .returning(move |_| Ok(vec![context1.clone(), context2.clone()]));
 
         // Set up expectations for finding chunks by context ID
         repo_mock
@@ -307,10 +560,12 @@ mod tests {
             .times(2) // Once for context fetching, once for result conversion
             .returning(move |_| Ok(vec![create_test_chunk(context2_id, Uuid::new_v4())]));
 
-        let service = ContextSearchService::new(Arc::new(repo_mock), Arc::new(embedding_mock), 5);
+        let service = search_service(repo_mock, embedding_mock, store_mock);
 
         // Execute the method under test
-        let result = service.search("test query".to_string(), 10).await;
+        let result = service
+            .search("test query".to_string(), 10, SearchOptions::default())
+            .await;
 
         // Verify results
         assert!(result.is_ok());
@@ -319,41 +574,395 @@ mod tests {
         assert_eq!(search_result.matches.len(), 2);
     }
 
+    #[tokio::test]
+    async fn test_semantic_match_surfaces_when_alpha_favors_vectors() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let mut lexical = create_test_context(Uuid::new_v4());
+        lexical.content = "Payment outage postmortem".to_string();
+        let mut semantic = create_test_context(Uuid::new_v4());
+        semantic.content = "Customers could not be billed during the incident".to_string();
+        let lexical_chunk = create_test_chunk(lexical.id, Uuid::new_v4());
+        let semantic_chunk = create_test_chunk(semantic.id, Uuid::new_v4());
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+
+        // Only the embeddings see that the second context is about the query
+        let hits = vec![(semantic_chunk.clone(), 0.9), (lexical_chunk.clone(), 0.2)];
+        store_mock
+            .expect_search()
+            .returning(move |_, _, _| Ok(hits.clone()));
+
+        let contexts = vec![semantic.clone(), lexical.clone()];
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(contexts.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(move |context_id| {
+                Ok(vec![if context_id == lexical_chunk.context_id {
+                    lexical_chunk.clone()
+                } else {
+                    semantic_chunk.clone()
+                }])
+            });
+
+        let service = search_service(repo_mock, embedding_mock, store_mock);
+        for (alpha, expected) in [
+            (0.8, [semantic.id, lexical.id]),
+            (0.2, [lexical.id, semantic.id]),
+        ] {
+            let options = SearchOptions {
+                hybrid_alpha: Some(alpha),
+                ..SearchOptions::default()
+            };
+            let result = service
+                .search("payment outage".to_string(), 10, options)
+                .await
+                .unwrap();
+
+            let ids: Vec<Uuid> = result.matches.iter().map(|m| m.context.id).collect();
+            assert_eq!(ids, expected, "alpha {}", alpha);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_min_score_drops_weak_matches() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let mut relevant = create_test_context(Uuid::new_v4());
+        relevant.content = "Payment outage postmortem".to_string();
+        let unrelated = create_test_context(Uuid::new_v4());
+        let relevant_chunk = create_test_chunk(relevant.id, Uuid::new_v4());
+        let unrelated_chunk = create_test_chunk(unrelated.id, Uuid::new_v4());
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        let hits = vec![(relevant_chunk, 0.9), (unrelated_chunk, 0.1)];
+        store_mock
+            .expect_search()
+            .returning(move |_, _, _| Ok(hits.clone()));
+
+        let contexts = vec![relevant.clone(), unrelated];
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(contexts.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service =
+            search_service(repo_mock, embedding_mock, store_mock).with_min_score(Some(0.5));
+
+        let result = service
+            .search("payment outage".to_string(), 10, SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(result.total_matches, 1);
+        assert_eq!(result.matches[0].context.id, relevant.id);
+
+        // A request's own threshold replaces the default
+        let options = SearchOptions {
+            min_score: Some(0.0),
+            ..SearchOptions::default()
+        };
+        let result = service
+            .search("payment outage".to_string(), 10, options)
+            .await
+            .unwrap();
+        assert_eq!(result.total_matches, 2);
+    }
+
+    #[tokio::test]
+    async fn test_total_matches_counts_contexts_past_the_limit() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let contexts: Vec<Context> = (0..3)
+            .map(|_| create_test_context(Uuid::new_v4()))
+            .collect();
+        let hits: Vec<(ContextChunk, f32)> = contexts
+            .iter()
+            .zip([0.9, 0.8, 0.7])
+            .map(|(context, similarity)| {
+                (create_test_chunk(context.id, Uuid::new_v4()), similarity)
+            })
+            .collect();
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        store_mock
+            .expect_search()
+            .withf(|_, _, limit| *limit == 5)
+            .returning(move |_, _, _| Ok(hits.clone()));
+        let found = contexts.clone();
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(found.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service = search_service(repo_mock, embedding_mock, store_mock);
+        let result = service
+            .search("test query".to_string(), 1, SearchOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].context.id, contexts[0].id);
+        assert_eq!(result.total_matches, 3);
+    }
+
+    #[tokio::test]
+    async fn test_total_matches_leaves_out_filtered_candidates() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let contexts: Vec<Context> = ["wiki", "blog", "wiki", "blog"]
+            .into_iter()
+            .map(|source| {
+                let mut context = create_test_context(Uuid::new_v4());
+                context.metadata.source = Some(source.to_string());
+                context
+            })
+            .collect();
+        let hits: Vec<(ContextChunk, f32)> = contexts
+            .iter()
+            .zip([0.9, 0.8, 0.7, 0.6])
+            .map(|(context, similarity)| {
+                (create_test_chunk(context.id, Uuid::new_v4()), similarity)
+            })
+            .collect();
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        store_mock
+            .expect_search()
+            .returning(move |_, _, _| Ok(hits.clone()));
+        let found = contexts.clone();
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(found.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service = search_service(repo_mock, embedding_mock, store_mock);
+        let options = SearchOptions {
+            source: Some("wiki".to_string()),
+            ..SearchOptions::default()
+        };
+        let result = service
+            .search("test query".to_string(), 1, options)
+            .await
+            .unwrap();
+
+        assert_eq!(result.matches.len(), 1);
+        assert_eq!(result.matches[0].context.id, contexts[0].id);
+        assert_eq!(result.total_matches, 2);
+    }
+
+    /// Reverses the candidates, scoring them by their new position
+    struct ReversingReranker;
+
+    #[async_trait]
+    impl RerankerPort for ReversingReranker {
+        async fn rerank(
+            &self,
+            _query: &str,
+            candidates: Vec<ContextMatch>,
+            top_k: usize,
+        ) -> McpResult<Vec<ContextMatch>> {
+            Ok(candidates
+                .into_iter()
+                .rev()
+                .enumerate()
+                .map(|(position, candidate)| ContextMatch {
+                    score: 1.0 / (position + 1) as f32,
+                    ..candidate
+                })
+                .take(top_k)
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reranker_replaces_the_first_stage_order_and_scores() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let contexts: Vec<Context> = (0..3)
+            .map(|_| create_test_context(Uuid::new_v4()))
+            .collect();
+        let hits: Vec<(ContextChunk, f32)> = contexts
+            .iter()
+            .zip([0.9, 0.8, 0.7])
+            .map(|(context, similarity)| {
+                (create_test_chunk(context.id, Uuid::new_v4()), similarity)
+            })
+            .collect();
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        store_mock
+            .expect_search()
+            .returning(move |_, _, _| Ok(hits.clone()));
+        let found = contexts.clone();
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(found.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service = search_service(repo_mock, embedding_mock, store_mock)
+            .with_reranker(Some(Arc::new(ReversingReranker)));
+        let result = service
+            .search("test query".to_string(), 2, SearchOptions::default())
+            .await
+            .unwrap();
+
+        // Every first-stage match was a candidate, so the last one now comes first
+        let ranked: Vec<(Uuid, f32)> = result
+            .matches
+            .iter()
+            .map(|m| (m.context.id, m.score))
+            .collect();
+        assert_eq!(ranked, [(contexts[2].id, 1.0), (contexts[1].id, 0.5)]);
+        assert_eq!(result.total_matches, 3);
+    }
+
+    /// Expands `k8s` to its full form
+    struct AbbreviationExpander;
+
+    #[async_trait]
+    impl QueryExpansionPort for AbbreviationExpander {
+        async fn expand(&self, query: &str) -> McpResult<Vec<String>> {
+            Ok(query
+                .split_whitespace()
+                .filter(|term| *term == "k8s")
+                .map(|_| "kubernetes".to_string())
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_expansions_count_towards_the_embedding_and_lexical_score() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let mut kubernetes = create_test_context(Uuid::new_v4());
+        kubernetes.content = "Upgrading the Kubernetes cluster".to_string();
+        let hit = create_test_chunk(kubernetes.id, Uuid::new_v4());
+
+        embedding_mock
+            .expect_embed_query()
+            .with(eq("k8s"))
+            .times(2)
+            .returning(|_| Ok(vec![1.0, 0.0]));
+        embedding_mock
+            .expect_embed_query()
+            .with(eq("kubernetes"))
+            .times(1)
+            .returning(|_| Ok(vec![0.0, 1.0]));
+
+        // The expansion moves the query embedding by half of its own
+        for embedding in [[1.0, 0.5], [1.0, 0.0]] {
+            let hit = hit.clone();
+            store_mock
+                .expect_search()
+                .withf(move |query, _, _| **query == embedding)
+                .times(1)
+                .returning(move |_, _, _| Ok(vec![(hit.clone(), 0.2)]));
+        }
+        let found = kubernetes.clone();
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(vec![found.clone()]));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service = search_service(repo_mock, embedding_mock, store_mock)
+            .with_hybrid_alpha(0.0)
+            .with_query_expansion(Some(Arc::new(AbbreviationExpander)), 0.5)
+            .with_expansion_by_default(true);
+
+        let expanded = service
+            .search("k8s".to_string(), 5, SearchOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(expanded.matches[0].score, 1.0);
+
+        // Turned off for one request, only the vector similarity is left
+        let options = SearchOptions {
+            expand: Some(false),
+            ..SearchOptions::default()
+        };
+        let unexpanded = service.search("k8s".to_string(), 5, options).await.unwrap();
+        assert_eq!(unexpanded.matches[0].score, 0.0);
+    }
+
     #[tokio::test]
     async fn test_search_with_tags_success() {
         let mut repo_mock = MockContextRepository::new();
         let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
 
-        let chunk_id = Uuid::new_v4();
+        let context_id = Uuid::new_v4();
 
         let tags = vec!["tag1".to_string()];
-        let context1 = create_test_context(chunk_id);
+        let mut context1 = create_test_context(context_id);
+        context1.metadata.tags = tags.clone();
 
-        // Set up expectations for repository
+        // The vector store filters by tag, so the hits' contexts are fetched directly
         repo_mock
-            .expect_find_by_tags()
-            .with(eq(tags.clone()), eq(1000), eq(0))
+            .expect_find_by_ids()
+            .withf(move |ids| *ids == [context_id])
             .times(1)
-            .returning(move |_, _, _| Ok(vec![context1.clone()]));
+            .returning(move |_| Ok(vec![context1.clone()]));
 
         repo_mock
             .expect_find_chunks_by_context_id()
-            .with(eq(chunk_id))
+            .with(eq(context_id))
             .times(2) // Once for fetching chunks, once for result conversion
-            .returning(move |_| Ok(vec![create_test_chunk(chunk_id, Uuid::new_v4())]));
+            .returning(move |_| Ok(vec![create_test_chunk(context_id, Uuid::new_v4())]));
 
-        // Set up expectations for embedding service
+        // Set up expectations for the embedding service and vector store
         embedding_mock
-            .expect_find_similar_with_tags()
-            .with(eq("test query"), eq(tags.clone()), eq(5))
+            .expect_embed_query()
+            .with(eq("test query"))
+            .times(1)
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        let expected_tags = tags.clone();
+        store_mock
+            .expect_search()
+            .withf(move |_, tags, limit| *tags == expected_tags.as_slice() && *limit == 25)
             .times(1)
-            .returning(move |_, _, _| Ok(vec![(create_test_chunk(chunk_id, Uuid::new_v4()), 0.9)]));
+            .returning(move |_, _, _| {
+                Ok(vec![(create_test_chunk(context_id, Uuid::new_v4()), 0.9)])
+            });
 
-        let service = ContextSearchService::new(Arc::new(repo_mock), Arc::new(embedding_mock), 5);
+        let service = search_service(repo_mock, embedding_mock, store_mock);
 
         // Execute the method under test
         let result = service
-            .search_with_tags("test query".to_string(), tags, 5)
+            .search_with_tags("test query".to_string(), tags, 5, SearchOptions::default())
             .await;
 
         // Verify results
@@ -366,22 +975,26 @@ mod tests {
     #[tokio::test]
     async fn test_search_with_tags_empty_result() {
         let mut repo_mock = MockContextRepository::new();
-        let embedding_mock = MockEmbeddingService::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
 
         let tags = vec!["nonexistent_tag".to_string()];
 
-        // Set up expectations for repository to return empty results
-        repo_mock
-            .expect_find_by_tags()
-            .with(eq(tags.clone()), eq(1000), eq(0))
+        // No chunk has the tag
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        store_mock
+            .expect_search()
             .times(1)
             .returning(|_, _, _| Ok(Vec::new()));
+        repo_mock.expect_find_by_ids().returning(|_| Ok(Vec::new()));
 
-        let service = ContextSearchService::new(Arc::new(repo_mock), Arc::new(embedding_mock), 5);
+        let service = search_service(repo_mock, embedding_mock, store_mock);
 
         // Execute the method under test
         let result = service
-            .search_with_tags("test query".to_string(), tags, 5)
+            .search_with_tags("test query".to_string(), tags, 5, SearchOptions::default())
             .await;
 
         // Verify results
@@ -391,10 +1004,118 @@ mod tests {
         assert_eq!(search_result.matches.len(), 0);
     }
 
+    #[tokio::test]
+    async fn test_semantic_match_wins_under_tag_filtering() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let tags = vec!["incident".to_string()];
+        let mut lexical = create_test_context(Uuid::new_v4());
+        lexical.content = "Payment outage drill, nothing was down".to_string();
+        lexical.metadata.tags = tags.clone();
+        let mut semantic = create_test_context(Uuid::new_v4());
+        semantic.content = "Customers could not be billed for an hour".to_string();
+        semantic.metadata.tags = tags.clone();
+        let lexical_chunk = create_test_chunk(lexical.id, Uuid::new_v4());
+        let semantic_chunk = create_test_chunk(semantic.id, Uuid::new_v4());
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        let hits = vec![(semantic_chunk, 0.95), (lexical_chunk, 0.3)];
+        store_mock
+            .expect_search()
+            .withf(|_, tags, _| *tags == ["incident".to_string()])
+            .returning(move |_, _, _| Ok(hits.clone()));
+
+        let contexts = vec![lexical.clone(), semantic.clone()];
+        repo_mock
+            .expect_find_by_ids()
+            .returning(move |_| Ok(contexts.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service = search_service(repo_mock, embedding_mock, store_mock);
+        let result = service
+            .search_with_tags(
+                "payment outage".to_string(),
+                tags,
+                10,
+                SearchOptions {
+                    hybrid_alpha: Some(0.8),
+                    ..SearchOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        // Term matching alone would put the drill first, as would an even blend
+        let ids: Vec<Uuid> = result.matches.iter().map(|m| m.context.id).collect();
+        assert_eq!(ids, [semantic.id, lexical.id]);
+    }
+
+    #[tokio::test]
+    async fn test_any_tag_mode_searches_each_tag() {
+        let mut repo_mock = MockContextRepository::new();
+        let mut embedding_mock = MockEmbeddingService::new();
+        let mut store_mock = MockVectorStore::new();
+
+        let mut ai = create_test_context(Uuid::new_v4());
+        ai.metadata.tags = vec!["ai".to_string()];
+        let mut nlp = create_test_context(Uuid::new_v4());
+        nlp.metadata.tags = vec!["nlp".to_string()];
+        let ai_chunk = create_test_chunk(ai.id, Uuid::new_v4());
+        let nlp_chunk = create_test_chunk(nlp.id, Uuid::new_v4());
+
+        embedding_mock
+            .expect_embed_query()
+            .returning(|_| Ok(vec![0.1, 0.2, 0.3]));
+        store_mock
+            .expect_search()
+            .withf(|_, tags, _| *tags == ["ai".to_string()])
+            .times(1)
+            .returning(move |_, _, _| Ok(vec![(ai_chunk.clone(), 0.6)]));
+        store_mock
+            .expect_search()
+            .withf(|_, tags, _| *tags == ["nlp".to_string()])
+            .times(1)
+            .returning(move |_, _, _| Ok(vec![(nlp_chunk.clone(), 0.9)]));
+
+        let contexts = vec![nlp.clone(), ai.clone()];
+        repo_mock
+            .expect_find_by_ids()
+            .withf(move |ids| ids.len() == 2)
+            .returning(move |_| Ok(contexts.clone()));
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .returning(|_| Ok(Vec::new()));
+
+        let service = search_service(repo_mock, embedding_mock, store_mock);
+        let result = service
+            .search_with_tags(
+                "test query".to_string(),
+                vec!["ai".to_string(), "nlp".to_string()],
+                10,
+                SearchOptions {
+                    tag_mode: TagMode::Any,
+                    ..SearchOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+
+        let ids: Vec<Uuid> = result.matches.iter().map(|m| m.context.id).collect();
+        assert_eq!(ids, [nlp.id, ai.id]);
+        assert_eq!(result.total_matches, 2);
+    }
+
     #[tokio::test]
     async fn test_to_search_result() {
         let repo_mock = MockContextRepository::new();
         let embedding_mock = MockEmbeddingService::new();
+        let store_mock = MockVectorStore::new();
 
         let id1 = Uuid::new_v4();
         let id2 = Uuid::new_v4();
@@ -415,13 +1136,15 @@ mod tests {
             .times(1)
             .returning(move |_| Ok(vec![create_test_chunk(id2, Uuid::new_v4())]));
 
-        let service = ContextSearchService::new(Arc::new(repo_mock), Arc::new(embedding_mock), 5);
+        let service = search_service(repo_mock, embedding_mock, store_mock);
 
         // Prepare scored contexts
         let scored_contexts = vec![(context1, 0.9), (context2, 0.8)];
 
         // Execute the method under test
-        let result = service.to_search_result(scored_contexts).await;
+        let result = service
+            .to_search_result("test query", scored_contexts, 10)
+            .await;
 
         // Verify results
         assert!(result.is_ok());
@@ -431,4 +1154,55 @@ mod tests {
         assert_eq!(search_result.matches[0].score, 0.9);
         assert_eq!(search_result.matches[1].score, 0.8);
     }
+
+    #[tokio::test]
+    async fn test_retrieve_by_references_skips_missing_contexts() {
+        let mut repo_mock = MockContextRepository::new();
+        let embedding_mock = MockEmbeddingService::new();
+        let store_mock = MockVectorStore::new();
+
+        let existing_id = Uuid::new_v4();
+        let missing_id = Uuid::new_v4();
+        let existing = create_test_context(existing_id);
+        let chunk_id = Uuid::new_v4();
+
+        // The missing id is simply absent from the batch result
+        repo_mock
+            .expect_find_by_ids()
+            .withf(move |ids| ids.iter().copied().eq([missing_id, existing_id]))
+            .times(1)
+            .returning(move |_| Ok(vec![existing.clone()]));
+
+        repo_mock
+            .expect_find_chunks_by_context_id()
+            .with(eq(existing_id))
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![
+                    create_test_chunk(existing_id, chunk_id),
+                    create_test_chunk(existing_id, Uuid::new_v4()),
+                ])
+            });
+
+        let service = search_service(repo_mock, embedding_mock, store_mock);
+
+        let references = vec![
+            ContextReference {
+                context_id: missing_id,
+                chunk_ids: None,
+                weight: None,
+            },
+            ContextReference {
+                context_id: existing_id,
+                chunk_ids: Some(vec![chunk_id]),
+                weight: Some(0.5),
+            },
+        ];
+
+        let search_result = service.retrieve_by_references(references).await.unwrap();
+        assert_eq!(search_result.total_matches, 1);
+        assert_eq!(search_result.matches[0].context.id, existing_id);
+        assert_eq!(search_result.matches[0].score, 0.5);
+        assert_eq!(search_result.matches[0].chunks.as_ref().unwrap().len(), 1);
+    }
 }
@@ -0,0 +1,322 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::{
+    ContextFilter, EvalCase, EvalDataset, EvalMetrics, EvalRun, McpError, McpResult, SearchOptions,
+};
+use crate::ports::in_ports::{ContextManagementPort, ContextSearchPort, EvaluationPort};
+
+/// Number of contexts fetched per page when counting the contexts an expected tag marks
+const TAG_PAGE_SIZE: usize = 1000;
+
+/// Application service running labelled datasets through the search pipeline
+pub struct EvaluationService {
+    context_manager: Arc<dyn ContextManagementPort + Send + Sync>,
+    context_search: Arc<dyn ContextSearchPort + Send + Sync>,
+    fingerprint: String,
+    datasets: RwLock<HashMap<String, EvalDataset>>,
+    runs: RwLock<Vec<EvalRun>>,
+}
+
+impl EvaluationService {
+    /// Create a service labelling runs with `fingerprint` unless a run is given its own label
+    pub fn new(
+        context_manager: Arc<dyn ContextManagementPort + Send + Sync>,
+        context_search: Arc<dyn ContextSearchPort + Send + Sync>,
+        fingerprint: String,
+    ) -> Self {
+        Self {
+            context_manager,
+            context_search,
+            fingerprint,
+            datasets: RwLock::new(HashMap::new()),
+            runs: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Evaluate a single case, returning its metrics at `k`
+    async fn evaluate_case(&self, case: &EvalCase, k: usize) -> McpResult<EvalMetrics> {
+        let result = self
+            .context_search
+            .search(case.query.clone(), k, SearchOptions::default())
+            .await?;
+        let relevance: Vec<bool> = result
+            .matches
+            .iter()
+            .map(|m| case.is_relevant(&m.context))
+            .collect();
+
+        let total_relevant = self.relevant_ids(case).await?.len();
+        Ok(EvalMetrics::for_query(&relevance, total_relevant, k))
+    }
+
+    /// All contexts relevant to a case: the expected ids plus every context with an expected tag
+    async fn relevant_ids(&self, case: &EvalCase) -> McpResult<HashSet<Uuid>> {
+        let mut ids: HashSet<Uuid> = case.expected_ids.iter().copied().collect();
+
+        for tag in &case.expected_tags {
+            let mut offset = 0;
+            loop {
+                let page = self
+                    .context_manager
+                    .list_contexts(
+                        ContextFilter::tagged(vec![tag.clone()]),
+                        TAG_PAGE_SIZE,
+                        offset,
+                    )
+                    .await?;
+                offset += page.len();
+                let done = page.len() < TAG_PAGE_SIZE;
+                ids.extend(page.into_iter().map(|context| context.id));
+                if done {
+                    break;
+                }
+            }
+        }
+
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl EvaluationPort for EvaluationService {
+    async fn save_dataset(&self, dataset: EvalDataset) -> McpResult<EvalDataset> {
+        dataset.validate()?;
+        self.datasets
+            .write()
+            .await
+            .insert(dataset.name.clone(), dataset.clone());
+        Ok(dataset)
+    }
+
+    async fn run_dataset(
+        &self,
+        dataset: &str,
+        k: usize,
+        label: Option<String>,
+    ) -> McpResult<EvalRun> {
+        if k == 0 {
+            return Err(McpError::ValidationError(
+                "k must be at least 1".to_string(),
+            ));
+        }
+
+        let dataset = self
+            .datasets
+            .read()
+            .await
+            .get(dataset)
+            .cloned()
+            .ok_or_else(|| McpError::ValidationError(format!("Unknown dataset '{}'", dataset)))?;
+
+        let mut per_query = Vec::with_capacity(dataset.cases.len());
+        for case in &dataset.cases {
+            per_query.push(self.evaluate_case(case, k).await?);
+        }
+
+        let run = EvalRun {
+            id: Uuid::new_v4(),
+            dataset: dataset.name,
+            label: label.unwrap_or_else(|| self.fingerprint.clone()),
+            k,
+            queries: per_query.len(),
+            metrics: EvalMetrics::mean(&per_query),
+            created_at: Utc::now(),
+        };
+
+        self.runs.write().await.push(run.clone());
+        Ok(run)
+    }
+
+    async fn list_runs(&self, dataset: Option<&str>) -> McpResult<Vec<EvalRun>> {
+        Ok(self
+            .runs
+            .read()
+            .await
+            .iter()
+            .filter(|run| dataset.is_none() || dataset == Some(run.dataset.as_str()))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        Context, ContextChunk, ContextMatch, ContextMetadata, ContextReference, ContextRelation,
+        ContextRevision, ContextSearchResult, DeleteOutcome, StoreOptions,
+    };
+    use chrono::DateTime;
+    use mockall::mock;
+    use mockall::predicate::*;
+
+    mock! {
+        ContextManager {}
+        #[async_trait]
+        impl ContextManagementPort for ContextManager {
+            fn check_content(&self, content: &str) -> McpResult<()>;
+            async fn store_context(&self, content: String, metadata: ContextMetadata, options: StoreOptions) -> McpResult<Context>;
+            async fn import_context(&self, context: Context, chunks: Vec<ContextChunk>) -> McpResult<Context>;
+            async fn get_context(&self, context_id: Uuid) -> McpResult<Context>;
+            async fn get_chunk(&self, chunk_id: Uuid) -> McpResult<ContextChunk>;
+            async fn get_chunks(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>>;
+            async fn update_context(&self, context_id: Uuid, content: String, metadata: ContextMetadata, expires_at: Option<DateTime<Utc>>, expected_version: Option<u64>) -> McpResult<Context>;
+            async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>>;
+            async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision>;
+            async fn restore_revision(&self, context_id: Uuid, version: u64, expected_version: Option<u64>) -> McpResult<Context>;
+            async fn add_relation(&self, context_id: Uuid, relation: ContextRelation) -> McpResult<Context>;
+            async fn remove_relations(&self, context_id: Uuid, target_id: Uuid, relation_type: Option<String>) -> McpResult<Context>;
+            async fn related_contexts(&self, context_id: Uuid, relation_type: Option<String>) -> McpResult<Vec<(ContextRelation, Context)>>;
+            async fn list_children(&self, context_id: Uuid, include_descendants: bool, limit: usize, offset: usize) -> McpResult<Vec<Context>>;
+            async fn delete_context(&self, context_id: Uuid) -> McpResult<()>;
+            async fn soft_delete_context(&self, context_id: Uuid) -> McpResult<()>;
+            async fn restore_context(&self, context_id: Uuid) -> McpResult<Context>;
+            async fn delete_contexts(&self, context_ids: Vec<Uuid>) -> McpResult<Vec<(Uuid, DeleteOutcome)>>;
+            async fn delete_by_tags(&self, tags: Vec<String>) -> McpResult<usize>;
+            async fn list_contexts(&self, filter: ContextFilter, limit: usize, offset: usize) -> McpResult<Vec<Context>>;
+            async fn count_contexts(&self, filter: ContextFilter) -> McpResult<usize>;
+            async fn tag_counts(&self) -> McpResult<Vec<(String, usize)>>;
+            async fn context_exists(&self, context_id: Uuid) -> McpResult<bool>;
+        }
+    }
+
+    mock! {
+        ContextSearch {}
+        #[async_trait]
+        impl ContextSearchPort for ContextSearch {
+            async fn search(&self, query: String, limit: usize, options: SearchOptions) -> McpResult<ContextSearchResult>;
+            async fn search_with_tags(&self, query: String, tags: Vec<String>, limit: usize, options: SearchOptions) -> McpResult<ContextSearchResult>;
+            async fn retrieve_by_references(&self, references: Vec<ContextReference>) -> McpResult<ContextSearchResult>;
+        }
+    }
+
+    fn create_test_context(id: u128, tags: &[&str]) -> Context {
+        Context {
+            id: Uuid::from_u128(id),
+            ..Context::new(
+                format!("Context content {}", id),
+                ContextMetadata {
+                    tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                    ..ContextMetadata::default()
+                },
+            )
+        }
+    }
+
+    fn search_result(contexts: Vec<Context>) -> ContextSearchResult {
+        let matches: Vec<ContextMatch> = contexts
+            .into_iter()
+            .map(|context| ContextMatch {
+                context,
+                chunks: None,
+                score: 0.5,
+            })
+            .collect();
+        ContextSearchResult {
+            total_matches: matches.len(),
+            total_matches_is_lower_bound: false,
+            matches,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_dataset_records_metrics() {
+        let mut context_manager = MockContextManager::new();
+        // Three contexts are tagged "runbook", one of which isn't retrieved
+        context_manager
+            .expect_list_contexts()
+            .with(
+                eq(ContextFilter::tagged(vec!["runbook".to_string()])),
+                eq(TAG_PAGE_SIZE),
+                eq(0),
+            )
+            .returning(|_, _, _| {
+                Ok(vec![
+                    create_test_context(2, &["runbook"]),
+                    create_test_context(4, &["runbook"]),
+                    create_test_context(5, &["runbook"]),
+                ])
+            });
+
+        let mut context_search = MockContextSearch::new();
+        context_search
+            .expect_search()
+            .with(
+                eq("restart the database".to_string()),
+                eq(4),
+                eq(SearchOptions::default()),
+            )
+            .returning(|_, _, _| {
+                Ok(search_result(vec![
+                    create_test_context(1, &[]),
+                    create_test_context(2, &["runbook"]),
+                    create_test_context(3, &[]),
+                    create_test_context(4, &["runbook"]),
+                ]))
+            });
+        context_search
+            .expect_search()
+            .with(
+                eq("rotate credentials".to_string()),
+                eq(4),
+                eq(SearchOptions::default()),
+            )
+            .returning(|_, _, _| Ok(search_result(vec![create_test_context(9, &[])])));
+
+        let service = EvaluationService::new(
+            Arc::new(context_manager),
+            Arc::new(context_search),
+            "abc123".to_string(),
+        );
+
+        service
+            .save_dataset(EvalDataset {
+                name: "runbooks".to_string(),
+                cases: vec![
+                    EvalCase {
+                        query: "restart the database".to_string(),
+                        expected_ids: Vec::new(),
+                        expected_tags: vec!["runbook".to_string()],
+                    },
+                    EvalCase {
+                        query: "rotate credentials".to_string(),
+                        expected_ids: vec![Uuid::from_u128(9)],
+                        expected_tags: Vec::new(),
+                    },
+                ],
+            })
+            .await
+            .unwrap();
+
+        let run = service.run_dataset("runbooks", 4, None).await.unwrap();
+
+        // The first query scores recall 2/3, RR 1/2 and nDCG 0.49819; the second scores 1 everywhere
+        assert_eq!(run.label, "abc123");
+        assert_eq!(run.queries, 2);
+        assert!((run.metrics.recall_at_k - (2.0 / 3.0 + 1.0) / 2.0).abs() < 1e-4);
+        assert!((run.metrics.mrr - 0.75).abs() < 1e-4);
+        assert!((run.metrics.ndcg_at_k - (0.49819 + 1.0) / 2.0).abs() < 1e-4);
+
+        let runs = service.list_runs(Some("runbooks")).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].id, run.id);
+        assert!(service.list_runs(Some("other")).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_unknown_dataset_fails() {
+        let service = EvaluationService::new(
+            Arc::new(MockContextManager::new()),
+            Arc::new(MockContextSearch::new()),
+            "abc123".to_string(),
+        );
+
+        let result = service.run_dataset("missing", 10, None).await;
+        assert!(matches!(result, Err(McpError::ValidationError(_))));
+    }
+}
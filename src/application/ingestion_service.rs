@@ -0,0 +1,142 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use crate::domain::{html_to_text, FetchedContent, IngestedDocument, McpError, McpResult};
+use crate::ports::in_ports::IngestionPort;
+use crate::ports::out_ports::ContentFetcherPort;
+
+/// Media types of HTML documents, which `strip_html` reduces to their text
+const HTML_TYPES: [&str; 2] = ["text/html", "application/xhtml+xml"];
+
+/// Application service fetching web documents as text for new contexts
+pub struct IngestionService {
+    fetcher: Arc<dyn ContentFetcherPort + Send + Sync>,
+}
+
+impl IngestionService {
+    /// Create a service downloading documents with `fetcher`
+    pub fn new(fetcher: Arc<dyn ContentFetcherPort + Send + Sync>) -> Self {
+        Self { fetcher }
+    }
+}
+
+#[async_trait]
+impl IngestionPort for IngestionService {
+    async fn fetch_document(&self, url: &str, strip_html: bool) -> McpResult<IngestedDocument> {
+        let fetched = self.fetcher.fetch(url).await?;
+        if let Some(content_type) = fetched.content_type.as_deref() {
+            if !is_text(content_type) {
+                return Err(McpError::ValidationError(format!(
+                    "url returned {}, which isn't text",
+                    content_type
+                )));
+            }
+        }
+
+        let content = decode(&fetched);
+        let is_html = fetched
+            .content_type
+            .as_deref()
+            .is_some_and(|content_type| HTML_TYPES.contains(&content_type));
+        if strip_html && is_html {
+            return Ok(IngestedDocument {
+                content: html_to_text(&content),
+                content_type: Some("text/plain".to_string()),
+            });
+        }
+
+        Ok(IngestedDocument {
+            content,
+            content_type: fetched.content_type,
+        })
+    }
+}
+
+/// Whether documents of `content_type` are text that can be stored as content
+fn is_text(content_type: &str) -> bool {
+    content_type.starts_with("text/")
+        || content_type.ends_with("+xml")
+        || content_type.ends_with("+json")
+        || matches!(
+            content_type,
+            "application/json" | "application/xml" | "application/javascript"
+        )
+}
+
+/// The body of `fetched` as text, in its declared charset if that's Latin-1 and as UTF-8
+/// otherwise, with invalid bytes replaced
+fn decode(fetched: &FetchedContent) -> String {
+    match fetched.charset.as_deref() {
+        Some("iso-8859-1" | "latin1" | "latin-1") => {
+            fetched.body.iter().map(|&byte| byte as char).collect()
+        }
+        _ => String::from_utf8_lossy(&fetched.body).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fetcher answering every URL with the same document
+    struct StaticFetcher(FetchedContent);
+
+    #[async_trait]
+    impl ContentFetcherPort for StaticFetcher {
+        async fn fetch(&self, _url: &str) -> McpResult<FetchedContent> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn service(content_type: &str, charset: Option<&str>, body: &[u8]) -> IngestionService {
+        IngestionService::new(Arc::new(StaticFetcher(FetchedContent {
+            url: "https://example.com/page".to_string(),
+            content_type: Some(content_type.to_string()),
+            charset: charset.map(str::to_string),
+            body: body.to_vec(),
+        })))
+    }
+
+    #[tokio::test]
+    async fn test_html_is_stripped_only_when_asked() {
+        let html = service("text/html", None, b"<p>Promote the <b>replica</b></p>");
+
+        let stripped = html
+            .fetch_document("https://example.com", true)
+            .await
+            .unwrap();
+        assert_eq!(stripped.content, "Promote the replica");
+        assert_eq!(stripped.content_type.as_deref(), Some("text/plain"));
+
+        let raw = html
+            .fetch_document("https://example.com", false)
+            .await
+            .unwrap();
+        assert_eq!(raw.content, "<p>Promote the <b>replica</b></p>");
+        assert_eq!(raw.content_type.as_deref(), Some("text/html"));
+
+        // Documents that aren't HTML are left alone
+        let markdown = service("text/markdown", None, b"# <Heading>");
+        let document = markdown
+            .fetch_document("https://example.com", true)
+            .await
+            .unwrap();
+        assert_eq!(document.content, "# <Heading>");
+    }
+
+    #[tokio::test]
+    async fn test_documents_are_decoded_in_their_charset() {
+        let latin1 = service("text/plain", Some("iso-8859-1"), b"caf\xe9");
+        let document = latin1
+            .fetch_document("https://example.com", true)
+            .await
+            .unwrap();
+        assert_eq!(document.content, "café");
+
+        let image = service("image/png", None, b"\x89PNG");
+        assert!(matches!(
+            image.fetch_document("https://example.com", true).await,
+            Err(McpError::ValidationError(message)) if message.contains("image/png")
+        ));
+    }
+}
@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use crate::domain::{McpError, McpResult};
+use crate::ports::out_ports::ContextRepositoryPort;
+
+/// Counts of what a migration copied, or would copy on a dry run
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MigrationReport {
+    /// Contexts read from the source
+    pub contexts_seen: usize,
+
+    /// Contexts copied to the destination
+    pub contexts_copied: usize,
+
+    /// Contexts skipped because the destination already has them
+    pub contexts_skipped: usize,
+
+    /// Chunks copied to the destination
+    pub chunks_copied: usize,
+
+    /// Collections copied to the destination
+    pub collections_copied: usize,
+}
+
+/// Copies collections, contexts and their chunks from one repository to another
+pub struct RepositoryMigration {
+    source: Arc<dyn ContextRepositoryPort + Send + Sync>,
+    destination: Arc<dyn ContextRepositoryPort + Send + Sync>,
+    page_size: usize,
+}
+
+impl RepositoryMigration {
+    pub fn new(
+        source: Arc<dyn ContextRepositoryPort + Send + Sync>,
+        destination: Arc<dyn ContextRepositoryPort + Send + Sync>,
+        page_size: usize,
+    ) -> Self {
+        Self {
+            source,
+            destination,
+            page_size: page_size.max(1),
+        }
+    }
+
+    /// Run the migration, calling `on_page` with the running totals after each page
+    ///
+    /// Collections are copied first, so every copied context's collection exists. Contexts
+    /// the destination already has are skipped along with their chunks, so an interrupted
+    /// migration can simply be run again. With `dry_run` nothing is written.
+    pub async fn run(
+        &self,
+        dry_run: bool,
+        mut on_page: impl FnMut(&MigrationReport),
+    ) -> McpResult<MigrationReport> {
+        let mut report = MigrationReport::default();
+        for collection in self.source.list_collections().await? {
+            if !dry_run {
+                self.destination.save_collection(collection).await?;
+            }
+            report.collections_copied += 1;
+        }
+
+        let mut offset = 0;
+
+        loop {
+            let page = self.source.list_all(self.page_size, offset).await?;
+            if page.is_empty() {
+                break;
+            }
+            offset += page.len();
+
+            for context in page {
+                report.contexts_seen += 1;
+
+                // The memory backend reports a context without chunks as not found
+                let chunks = match self.source.find_chunks_by_context_id(context.id).await {
+                    Ok(chunks) => chunks,
+                    Err(McpError::ContextNotFound(_)) => Vec::new(),
+                    Err(err) => return Err(err),
+                };
+
+                if dry_run {
+                    match self.destination.find_by_id(context.id).await {
+                        Ok(_) => report.contexts_skipped += 1,
+                        Err(McpError::ContextNotFound(_)) => {
+                            report.contexts_copied += 1;
+                            report.chunks_copied += chunks.len();
+                        }
+                        Err(err) => return Err(err),
+                    }
+                    continue;
+                }
+
+                match self.destination.save_context(context).await {
+                    Ok(_) => {}
+                    Err(McpError::ContextAlreadyExists(_)) => {
+                        report.contexts_skipped += 1;
+                        continue;
+                    }
+                    Err(err) => return Err(err),
+                }
+
+                report.chunks_copied += chunks.len();
+                self.destination.save_chunks(chunks).await?;
+                report.contexts_copied += 1;
+            }
+
+            on_page(&report);
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapter::out_adapters::InMemoryContextRepository;
+    use crate::domain::{Collection, Context, ContextChunk, ContextMetadata};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn context(content: &str) -> Context {
+        Context::new(content, ContextMetadata::default())
+    }
+
+    fn chunk(context_id: Uuid, position: usize) -> ContextChunk {
+        ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id,
+            content: format!("chunk {}", position),
+            position,
+            embedding: None,
+        }
+    }
+
+    async fn seeded_source(count: usize) -> Arc<InMemoryContextRepository> {
+        let source = Arc::new(InMemoryContextRepository::new());
+        for i in 0..count {
+            let context = source
+                .save_context(context(&format!("context {}", i)))
+                .await
+                .unwrap();
+            // Leave every third context without chunks
+            if i % 3 != 0 {
+                source
+                    .save_chunks(vec![chunk(context.id, 0), chunk(context.id, 1)])
+                    .await
+                    .unwrap();
+            }
+        }
+        source
+    }
+
+    #[tokio::test]
+    async fn test_copies_contexts_and_chunks_in_pages() {
+        let source = seeded_source(7).await;
+        let collection = Collection {
+            id: Uuid::new_v4(),
+            name: "runbooks".to_string(),
+            description: None,
+            created_at: Utc::now(),
+        };
+        source.save_collection(collection.clone()).await.unwrap();
+        let destination = Arc::new(InMemoryContextRepository::new());
+        let migration = RepositoryMigration::new(source.clone(), destination.clone(), 3);
+
+        let mut pages = 0;
+        let report = migration.run(false, |_| pages += 1).await.unwrap();
+
+        assert_eq!(pages, 3);
+        assert_eq!(report.collections_copied, 1);
+        assert_eq!(
+            destination.find_collection(collection.id).await.unwrap(),
+            collection
+        );
+        assert_eq!(report.contexts_seen, 7);
+        assert_eq!(report.contexts_copied, 7);
+        assert_eq!(report.contexts_skipped, 0);
+        assert_eq!(report.chunks_copied, 8);
+
+        for context in source.list_all(10, 0).await.unwrap() {
+            assert_eq!(
+                destination.find_by_id(context.id).await.unwrap().content,
+                context.content
+            );
+            if let Ok(chunks) = source.find_chunks_by_context_id(context.id).await {
+                let copied = destination
+                    .find_chunks_by_context_id(context.id)
+                    .await
+                    .unwrap();
+                let ids =
+                    |chunks: &[ContextChunk]| chunks.iter().map(|c| c.chunk_id).collect::<Vec<_>>();
+                assert_eq!(ids(&copied), ids(&chunks));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rerun_skips_existing_contexts() {
+        let source = seeded_source(4).await;
+        let destination = Arc::new(InMemoryContextRepository::new());
+        let migration = RepositoryMigration::new(source, destination.clone(), 10);
+
+        migration.run(false, |_| {}).await.unwrap();
+        let report = migration.run(false, |_| {}).await.unwrap();
+
+        assert_eq!(report.contexts_copied, 0);
+        assert_eq!(report.contexts_skipped, 4);
+        assert_eq!(report.chunks_copied, 0);
+        assert_eq!(destination.list_all(10, 0).await.unwrap().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_writes_nothing() {
+        let source = seeded_source(4).await;
+        let destination = Arc::new(InMemoryContextRepository::new());
+        let existing = source.list_all(1, 0).await.unwrap().remove(0);
+        destination.save_context(existing).await.unwrap();
+
+        let migration = RepositoryMigration::new(source, destination.clone(), 10);
+        let report = migration.run(true, |_| {}).await.unwrap();
+
+        assert_eq!(report.contexts_seen, 4);
+        assert_eq!(report.contexts_copied + report.contexts_skipped, 4);
+        assert_eq!(report.contexts_skipped, 1);
+        assert_eq!(destination.list_all(10, 0).await.unwrap().len(), 1);
+    }
+}
@@ -1,5 +1,15 @@
+pub mod collection_service;
 pub mod context_management_service;
 pub mod context_search_service;
+pub mod evaluation_service;
+pub mod ingestion_service;
+pub mod migration;
+pub mod readiness_service;
 
+pub use collection_service::CollectionService;
 pub use context_management_service::ContextManagementService;
 pub use context_search_service::ContextSearchService;
+pub use evaluation_service::EvaluationService;
+pub use ingestion_service::IngestionService;
+pub use migration::{MigrationReport, RepositoryMigration};
+pub use readiness_service::ReadinessService;
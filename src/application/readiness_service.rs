@@ -0,0 +1,163 @@
+use async_trait::async_trait;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::domain::{DependencyStatus, McpError, McpResult};
+use crate::ports::in_ports::ReadinessPort;
+use crate::ports::out_ports::{ContextRepositoryPort, EmbeddingPort};
+
+/// How long a dependency's check may take before it counts as failed
+const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Application service checking the adapters the other services rely on
+pub struct ReadinessService {
+    repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
+    embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    check_timeout: Duration,
+}
+
+impl ReadinessService {
+    /// Create a service checking the repository and the embedding provider
+    pub fn new(
+        repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
+        embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    ) -> Self {
+        Self {
+            repository,
+            embedding_service,
+            check_timeout: DEFAULT_CHECK_TIMEOUT,
+        }
+    }
+
+    /// Set how long each dependency's check may take
+    pub fn with_check_timeout(mut self, check_timeout: Duration) -> Self {
+        self.check_timeout = check_timeout;
+        self
+    }
+
+    async fn check(
+        &self,
+        name: &str,
+        check: impl Future<Output = McpResult<()>>,
+    ) -> DependencyStatus {
+        let result = tokio::time::timeout(self.check_timeout, check)
+            .await
+            .unwrap_or_else(|_| {
+                Err(McpError::ExternalServiceError(format!(
+                    "No response within {:?}",
+                    self.check_timeout
+                )))
+            });
+        DependencyStatus::from_check(name, result)
+    }
+}
+
+#[async_trait]
+impl ReadinessPort for ReadinessService {
+    async fn check_readiness(&self) -> Vec<DependencyStatus> {
+        let (repository, embedding) = tokio::join!(
+            self.check("repository", self.repository.check_health()),
+            self.check("embedding", self.embedding_service.check_health()),
+        );
+        vec![repository, embedding]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Collection, Context, ContextChunk, ContextRevision};
+    use mockall::mock;
+    use uuid::Uuid;
+
+    mock! {
+        ContextRepository {}
+        #[async_trait]
+        impl ContextRepositoryPort for ContextRepository {
+            async fn find_by_id(&self, id: Uuid) -> McpResult<Context>;
+            async fn find_by_ids(&self, ids: &[Uuid]) -> McpResult<Vec<Context>>;
+            async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>>;
+            async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk>;
+            async fn find_by_tags(&self, tags: &[String], limit: usize, offset: usize) -> McpResult<Vec<Context>>;
+            async fn save_context(&self, context: Context) -> McpResult<Context>;
+            async fn update(&self, context: Context, expected_version: Option<u64>) -> McpResult<Context>;
+            async fn save_context_with_chunks(&self, context: Context, chunks: Vec<ContextChunk>) -> McpResult<Context>;
+            async fn replace_context_with_chunks(&self, context: Context, chunks: Vec<ContextChunk>, expected_version: Option<u64>) -> McpResult<Context>;
+            async fn delete(&self, context_id: Uuid) -> McpResult<()>;
+            async fn save_revision(&self, revision: ContextRevision, max_revisions: usize) -> McpResult<()>;
+            async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>>;
+            async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision>;
+            async fn save_collection(&self, collection: Collection) -> McpResult<Collection>;
+            async fn find_collection(&self, collection_id: Uuid) -> McpResult<Collection>;
+            async fn list_collections(&self) -> McpResult<Vec<Collection>>;
+            async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()>;
+            async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>>;
+            async fn count_all(&self) -> McpResult<usize>;
+            async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize>;
+            async fn exists(&self, context_id: Uuid) -> McpResult<bool>;
+            async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>>;
+            async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()>;
+            async fn check_health(&self) -> McpResult<()>;
+        }
+    }
+
+    mock! {
+        EmbeddingService {}
+        #[async_trait]
+        impl EmbeddingPort for EmbeddingService {
+            async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>>;
+            async fn check_health(&self) -> McpResult<()>;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_every_dependency_is_reported() {
+        let mut repository = MockContextRepository::new();
+        repository
+            .expect_check_health()
+            .returning(|| Err(McpError::StorageError("connection refused".into())));
+        let mut embedding = MockEmbeddingService::new();
+        embedding.expect_check_health().returning(|| Ok(()));
+
+        let service = ReadinessService::new(Arc::new(repository), Arc::new(embedding));
+        let statuses = service.check_readiness().await;
+
+        assert_eq!(statuses.len(), 2);
+        assert_eq!(statuses[0].name, "repository");
+        assert!(statuses[0]
+            .error
+            .as_deref()
+            .is_some_and(|error| error.contains("connection refused")));
+        assert_eq!(statuses[1].name, "embedding");
+        assert!(statuses[1].is_healthy());
+    }
+
+    #[tokio::test]
+    async fn test_slow_checks_fail() {
+        let mut repository = MockContextRepository::new();
+        repository.expect_check_health().returning(|| Ok(()));
+
+        /// Embedding provider that never answers its health check
+        struct HangingEmbeddingService;
+
+        #[async_trait]
+        impl EmbeddingPort for HangingEmbeddingService {
+            async fn embed_texts(&self, _texts: &[String]) -> McpResult<Vec<Vec<f32>>> {
+                Ok(Vec::new())
+            }
+
+            async fn check_health(&self) -> McpResult<()> {
+                std::future::pending().await
+            }
+        }
+
+        let service =
+            ReadinessService::new(Arc::new(repository), Arc::new(HangingEmbeddingService))
+                .with_check_timeout(Duration::from_millis(10));
+        let statuses = service.check_readiness().await;
+
+        assert!(statuses[0].is_healthy());
+        assert!(!statuses[1].is_healthy());
+    }
+}
@@ -1,12 +1,22 @@
 use clap::{Parser, Subcommand};
-use reqwest::{Client, StatusCode};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use mcp::adapter::in_adapters::api::models::{
+    CreateCollectionRequest, EvalDatasetRequest, EvalRunRequest, ListContextsParams, SearchRequest,
+    SearchResponse, StoreContextRequest, UpdateContextRequest,
+};
+use mcp::client::{ClientError, ClientResult, McpClient, Upload};
 use std::io::{self, Write};
 use std::time::Duration;
-use tokio::time::sleep;
 use uuid::Uuid;
 
+/// Time a request may take, besides exports
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long an export may take to download, much longer than other requests
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+/// Files larger than this are stored with `store --file` through the upload endpoint
+const UPLOAD_THRESHOLD: usize = 64 * 1024;
+
 /// MCP client for interacting with the Model Context Protocol server
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None)]
@@ -15,6 +25,18 @@ struct Cli {
     #[clap(short, long, default_value = "http://localhost:3000")]
     server: String,
 
+    /// API key sent with every request (optional)
+    #[clap(long, env = "MCP_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+
+    /// PEM file with a CA certificate to trust, for servers with a self-signed certificate
+    #[clap(long)]
+    ca_cert: Option<String>,
+
+    /// Accept any server certificate, skipping verification entirely
+    #[clap(long, conflicts_with = "ca_cert")]
+    insecure: bool,
+
     #[clap(subcommand)]
     command: Command,
 }
@@ -24,8 +46,16 @@ enum Command {
     /// Store a new context
     Store {
         /// Content to store
-        #[clap(short, long)]
-        content: String,
+        #[clap(short, long, required_unless_present = "file")]
+        content: Option<String>,
+
+        /// File whose contents to store instead of --content
+        #[clap(long, conflicts_with = "content")]
+        file: Option<String>,
+
+        /// Replace bytes of --file that aren't valid UTF-8 instead of failing
+        #[clap(long, requires = "file")]
+        lossy: bool,
 
         /// Source of the content (optional)
         #[clap(short, long)]
@@ -38,13 +68,17 @@ enum Command {
         /// Tags (comma-separated, optional)
         #[clap(short, long)]
         tags: Option<String>,
+
+        /// ID or name of the collection to store the context in (optional)
+        #[clap(long)]
+        collection: Option<String>,
     },
 
     /// Retrieve a context by ID
     Get {
         /// Context ID to retrieve
         #[clap(short, long)]
-        id: String,
+        id: Uuid,
     },
 
     /// List all contexts
@@ -56,13 +90,20 @@ enum Command {
         /// Maximum number of contexts to return
         #[clap(short, long, default_value = "10")]
         limit: usize,
+
+        /// Only contexts in this collection, by ID or name (optional)
+        #[clap(long)]
+        collection: Option<String>,
     },
 
     /// Search for contexts by content
     Search {
+        /// Query in the search syntax, e.g. "outage tag:runbook -tag:archived after:2024-01-01"
+        expression: Option<String>,
+
         /// Query string
-        #[clap(short, long)]
-        query: String,
+        #[clap(short, long, conflicts_with = "expression")]
+        query: Option<String>,
 
         /// Filter by tags (comma-separated, optional)
         #[clap(short, long)]
@@ -71,13 +112,21 @@ enum Command {
         /// Maximum number of results to return
         #[clap(short, long, default_value = "5")]
         limit: usize,
+
+        /// Print the server's rendering instead of a summary (`markdown`, `xml`, or `json`)
+        #[clap(short, long)]
+        format: Option<String>,
+
+        /// Only contexts in this collection, by ID or name (optional)
+        #[clap(long)]
+        collection: Option<String>,
     },
 
     /// Update an existing context
     Update {
         /// Context ID to update
         #[clap(short, long)]
-        id: String,
+        id: Uuid,
 
         /// New content
         #[clap(short, long)]
@@ -96,80 +145,88 @@ enum Command {
         tags: Option<String>,
     },
 
-    /// Delete a context
+    /// Delete a context, or several with `--ids`
     Delete {
         /// Context ID to delete
+        #[clap(short, long, required_unless_present = "ids")]
+        id: Option<Uuid>,
+
+        /// Context IDs to delete in one request (comma-separated)
+        #[clap(long, value_delimiter = ',', conflicts_with = "id")]
+        ids: Vec<Uuid>,
+    },
+
+    /// Delete every context with all of the given tags
+    DeleteByTag {
+        /// Tags the deleted contexts have (comma-separated)
         #[clap(short, long)]
-        id: String,
+        tags: String,
+
+        /// Confirm the deletion; without it the server deletes nothing
+        #[clap(long)]
+        confirm: bool,
     },
 
-    /// Interactive mode to explore the MCP capabilities
-    Interactive,
-}
+    /// Run a labelled dataset and compare retrieval quality against the previous run
+    Eval {
+        /// JSON file with `name` and `cases` of `query` with `expected_ids` and/or `expected_tags`
+        #[clap(short, long)]
+        dataset: String,
 
-// Request and response types for API interaction
+        /// Number of results considered per query
+        #[clap(short, long, default_value = "10")]
+        k: usize,
 
-#[derive(Debug, Serialize)]
-struct StoreContextRequest {
-    content: String,
-    source: Option<String>,
-    content_type: Option<String>,
-    tags: Option<Vec<String>>,
-    metadata: Option<HashMap<String, String>>,
-}
+        /// Label of the run (defaults to the server's config fingerprint)
+        #[clap(short, long)]
+        label: Option<String>,
+    },
 
-#[derive(Debug, Serialize)]
-struct UpdateContextRequest {
-    content: String,
-    source: Option<String>,
-    content_type: Option<String>,
-    tags: Option<Vec<String>>,
-    metadata: Option<HashMap<String, String>>,
-}
+    /// Write every context as newline-delimited JSON, for backups
+    Export {
+        /// File to write the export to (stdout if not given)
+        #[clap(short, long)]
+        output: Option<String>,
 
-#[derive(Debug, Serialize)]
-struct SearchRequest {
-    query: String,
-    tags: Option<Vec<String>>,
-    limit: Option<usize>,
-}
+        /// Also export each context's chunks with their embeddings
+        #[clap(long)]
+        include_chunks: bool,
+    },
 
-#[derive(Debug, Deserialize)]
-struct ContextResponse {
-    id: Uuid,
-    content: String,
-    source: Option<String>,
-    content_type: Option<String>,
-    tags: Vec<String>,
-    metadata: HashMap<String, String>,
-    created_at: String,
-    expires_at: Option<String>,
-}
+    /// Manage the collections contexts are grouped into
+    Collections {
+        #[clap(subcommand)]
+        command: CollectionsCommand,
+    },
 
-#[derive(Debug, Deserialize)]
-struct ContextChunkDto {
-    id: Uuid,
-    content: String,
-    position: usize,
-}
+    /// Check that the server is up
+    Health,
 
-#[derive(Debug, Deserialize)]
-struct ContextMatchDto {
-    context: ContextResponse,
-    chunks: Option<Vec<ContextChunkDto>>,
-    score: f32,
+    /// Interactive mode to explore the MCP capabilities
+    Interactive,
 }
 
-#[derive(Debug, Deserialize)]
-struct SearchResponse {
-    matches: Vec<ContextMatchDto>,
-    total_matches: usize,
-}
+#[derive(Subcommand, Debug)]
+enum CollectionsCommand {
+    /// List every collection, the default one first
+    List,
+
+    /// Create a collection
+    Create {
+        /// Name of the collection, unique among collections
+        #[clap(short, long)]
+        name: String,
+
+        /// What the collection is for (optional)
+        #[clap(short, long)]
+        description: Option<String>,
+    },
 
-#[derive(Debug, Deserialize)]
-struct ErrorResponse {
-    message: String,
-    code: String,
+    /// Delete a collection, which must no longer hold any contexts
+    Delete {
+        /// ID or name of the collection to delete
+        collection: String,
+    },
 }
 
 // Helper function to parse comma-separated tags
@@ -188,40 +245,100 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     // Create HTTP client
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .build()?;
+    let mut builder = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .danger_accept_invalid_certs(cli.insecure);
+    if let Some(path) = &cli.ca_cert {
+        let pem = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        builder = builder.add_root_certificate(reqwest::Certificate::from_pem(&pem)?);
+    }
+    let mut client = McpClient::new(&cli.server).with_http_client(builder.build()?);
+    if let Some(api_key) = cli.api_key {
+        client = client.with_api_key(api_key);
+    }
 
     // Process command
     match cli.command {
         Command::Store {
             content,
+            file,
+            lossy,
             source,
             content_type,
             tags,
-        } => {
-            store_context(
-                &client,
-                &cli.server,
-                content,
-                source,
-                content_type,
-                parse_tags(tags),
-            )
-            .await?;
-        }
+            collection,
+        } => match file {
+            Some(path) => {
+                store_file(
+                    &client,
+                    &path,
+                    lossy,
+                    source,
+                    content_type,
+                    parse_tags(tags),
+                    collection.as_deref(),
+                )
+                .await?;
+            }
+            None => {
+                store_context(
+                    &client,
+                    content.unwrap_or_default(),
+                    source,
+                    content_type,
+                    parse_tags(tags),
+                    collection.as_deref(),
+                )
+                .await?;
+            }
+        },
 
         Command::Get { id } => {
-            get_context(&client, &cli.server, &id).await?;
+            get_context(&client, id).await?;
         }
 
-        Command::List { tags, limit } => {
-            list_contexts(&client, &cli.server, parse_tags(tags), limit).await?;
+        Command::List {
+            tags,
+            limit,
+            collection,
+        } => {
+            list_contexts(&client, parse_tags(tags), limit, collection).await?;
         }
 
-        Command::Search { query, tags, limit } => {
-            search_contexts(&client, &cli.server, query, parse_tags(tags), limit).await?;
-        }
+        Command::Search {
+            expression,
+            query,
+            tags,
+            limit,
+            format,
+            collection,
+        } => match (expression, query) {
+            (Some(expression), _) => {
+                search_by_expression(
+                    &client,
+                    expression,
+                    parse_tags(tags),
+                    limit,
+                    collection.as_deref(),
+                    format.as_deref(),
+                )
+                .await?;
+            }
+            (None, Some(query)) => {
+                search_contexts(
+                    &client,
+                    query,
+                    parse_tags(tags),
+                    limit,
+                    collection,
+                    format.as_deref(),
+                )
+                .await?;
+            }
+            (None, None) => {
+                return Err("Provide a search expression or --query".into());
+            }
+        },
 
         Command::Update {
             id,
@@ -230,24 +347,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             content_type,
             tags,
         } => {
-            update_context(
-                &client,
-                &cli.server,
-                &id,
-                content,
-                source,
-                content_type,
-                parse_tags(tags),
-            )
-            .await?;
+            update_context(&client, id, content, source, content_type, parse_tags(tags)).await?;
+        }
+
+        Command::Delete { id: Some(id), .. } => {
+            delete_context(&client, id).await?;
+        }
+
+        Command::Delete { ids, .. } => {
+            delete_contexts(&client, ids).await?;
+        }
+
+        Command::DeleteByTag { tags, confirm } => {
+            let tags = parse_tags(Some(tags)).unwrap_or_default();
+            delete_by_tag(&client, tags, confirm).await?;
         }
 
-        Command::Delete { id } => {
-            delete_context(&client, &cli.server, &id).await?;
+        Command::Eval { dataset, k, label } => {
+            run_eval(&client, &dataset, k, label).await?;
+        }
+
+        Command::Export {
+            output,
+            include_chunks,
+        } => {
+            export_contexts(&client, output.as_deref(), include_chunks).await?;
+        }
+
+        Command::Collections { command } => match command {
+            CollectionsCommand::List => {
+                list_collections(&client).await?;
+            }
+            CollectionsCommand::Create { name, description } => {
+                create_collection(&client, name, description).await?;
+            }
+            CollectionsCommand::Delete { collection } => {
+                delete_collection(&client, &collection).await?;
+            }
+        },
+
+        Command::Health => {
+            check_health(&client).await?;
         }
 
         Command::Interactive => {
-            run_interactive_mode(&client, &cli.server).await?;
+            run_interactive_mode(&client).await?;
         }
     }
 
@@ -257,57 +401,91 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 // API interaction functions
 
 async fn store_context(
-    client: &Client,
-    server: &str,
+    client: &McpClient,
     content: String,
     source: Option<String>,
     content_type: Option<String>,
     tags: Option<Vec<String>>,
+    collection: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Storing new context...");
 
+    let collection_id = match collection {
+        Some(collection) => Some(collection_id(client, collection).await?),
+        None => None,
+    };
     let request = StoreContextRequest {
         content,
         source,
         content_type,
         tags,
-        metadata: None,
+        collection_id,
+        ..StoreContextRequest::default()
     };
 
-    let response = client
-        .post(&format!("{}/contexts", server))
-        .json(&request)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let context: ContextResponse = response.json().await?;
+    if let Some(context) = accepted(client.store(&request).await)? {
         println!("Context stored successfully!");
         println!("ID: {}", context.id);
         println!("Content: {}", context.content);
         println!("Tags: {:?}", context.tags);
         println!("Created at: {}", context.created_at);
-    } else {
-        handle_error_response(response).await?;
     }
 
     Ok(())
 }
 
-async fn get_context(
-    client: &Client,
-    server: &str,
-    id: &str,
+/// Store the contents of the file at `path`, uploading it as a form if it's large
+async fn store_file(
+    client: &McpClient,
+    path: &str,
+    lossy: bool,
+    source: Option<String>,
+    content_type: Option<String>,
+    tags: Option<Vec<String>>,
+    collection: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Retrieving context with ID: {}...", id);
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let file_name = std::path::Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string());
+
+    if bytes.len() <= UPLOAD_THRESHOLD {
+        let content = match String::from_utf8(bytes) {
+            Ok(content) => content,
+            Err(err) if lossy => String::from_utf8_lossy(err.as_bytes()).into_owned(),
+            Err(_) => return Err(format!("{} is not valid UTF-8; pass --lossy", path).into()),
+        };
+        let source = source.or(Some(file_name));
+        return store_context(client, content, source, content_type, tags, collection).await;
+    }
 
-    let response = client
-        .get(&format!("{}/contexts/{}", server, id))
-        .send()
-        .await?;
+    println!("Uploading {} ({} bytes)...", path, bytes.len());
+    let upload = Upload {
+        file_name,
+        bytes,
+        source,
+        content_type,
+        tags: tags.unwrap_or_default(),
+        collection: collection.map(str::to_string),
+        lossy,
+    };
+
+    if let Some(context) = accepted(client.upload(upload).await)? {
+        println!("Context stored successfully!");
+        println!("ID: {}", context.id);
+        println!("Source: {}", context.source.as_deref().unwrap_or("-"));
+        println!("Tags: {:?}", context.tags);
+        println!("Created at: {}", context.created_at);
+    }
 
-    if response.status().is_success() {
-        let context: ContextResponse = response.json().await?;
+    Ok(())
+}
+
+async fn get_context(client: &McpClient, id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Retrieving context with ID: {}...", id);
+
+    if let Some(context) = accepted(client.get(id).await)? {
         println!("Context retrieved successfully!");
         println!("ID: {}", context.id);
         println!("Content: {}", context.content);
@@ -315,37 +493,107 @@ async fn get_context(
         println!("Content type: {:?}", context.content_type);
         println!("Tags: {:?}", context.tags);
         println!("Created at: {}", context.created_at);
-    } else {
-        handle_error_response(response).await?;
+    }
+
+    Ok(())
+}
+
+/// The ID of the collection `collection` names, by ID or name
+async fn collection_id(
+    client: &McpClient,
+    collection: &str,
+) -> Result<Uuid, Box<dyn std::error::Error>> {
+    if let Ok(id) = Uuid::parse_str(collection) {
+        return Ok(id);
+    }
+
+    client
+        .collections()
+        .await?
+        .into_iter()
+        .find(|candidate| candidate.name == collection)
+        .map(|candidate| candidate.id)
+        .ok_or_else(|| format!("No collection is named {}", collection).into())
+}
+
+async fn list_collections(client: &McpClient) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(collections) = accepted(client.collections().await)? {
+        println!("{} collections:", collections.len());
+        for collection in collections {
+            println!(
+                "  {}  {}  {}",
+                collection.id,
+                collection.name,
+                collection.description.as_deref().unwrap_or("")
+            );
+        }
+    }
+
+    Ok(())
+}
+
+async fn create_collection(
+    client: &McpClient,
+    name: String,
+    description: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let request = CreateCollectionRequest { name, description };
+
+    if let Some(collection) = accepted(client.create_collection(&request).await)? {
+        println!("Collection created successfully!");
+        println!("ID: {}", collection.id);
+        println!("Name: {}", collection.name);
+    }
+
+    Ok(())
+}
+
+async fn delete_collection(
+    client: &McpClient,
+    collection: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let id = collection_id(client, collection).await?;
+    println!("Deleting collection {}...", id);
+
+    if accepted(client.delete_collection(id).await)?.is_some() {
+        println!("Collection deleted successfully!");
+    }
+
+    Ok(())
+}
+
+async fn check_health(client: &McpClient) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(health) = accepted(client.health().await)? {
+        println!(
+            "Server is {} (version {}, up for {}s)",
+            health.status, health.version, health.uptime_seconds
+        );
     }
 
     Ok(())
 }
 
 async fn list_contexts(
-    client: &Client,
-    server: &str,
+    client: &McpClient,
     tags: Option<Vec<String>>,
     limit: usize,
+    collection: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     println!("Listing contexts...");
 
-    // Create parameters
-    let mut params = HashMap::new();
-    if let Some(tags) = tags {
-        params.insert("tags".to_string(), tags.join(","));
-    }
-    params.insert("limit".to_string(), limit.to_string());
-
-    let response = client
-        .get(&format!("{}/contexts", server))
-        .json(&params)
-        .send()
-        .await?;
+    let params = ListContextsParams {
+        tags: tags.map(|tags| tags.join(",")),
+        collection,
+        limit: Some(limit),
+        ..ListContextsParams::default()
+    };
 
-    if response.status().is_success() {
-        let contexts: Vec<ContextResponse> = response.json().await?;
-        println!("Found {} contexts:", contexts.len());
+    if let Some(page) = accepted(client.list(params).await)? {
+        let contexts = page.items;
+        println!("Showing {} of {} contexts:", contexts.len(), page.total);
+        if let Some(next_offset) = page.next_offset {
+            println!("(more contexts from offset {})", next_offset);
+        }
 
         for (i, context) in contexts.iter().enumerate() {
             println!("\n--- Context {} ---", i + 1);
@@ -353,69 +601,124 @@ async fn list_contexts(
             println!("Content: {}", context.content);
             println!("Tags: {:?}", context.tags);
         }
-    } else {
-        handle_error_response(response).await?;
     }
 
     Ok(())
 }
 
 async fn search_contexts(
-    client: &Client,
-    server: &str,
+    client: &McpClient,
     query: String,
     tags: Option<Vec<String>>,
     limit: usize,
+    collection: Option<String>,
+    format: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Searching for contexts with query: \"{}\"...", query);
+    if format.is_none() {
+        println!("Searching for contexts with query: \"{}\"...", query);
+    }
 
     let request = SearchRequest {
         query,
         tags,
+        collection,
         limit: Some(limit),
+        ..SearchRequest::default()
     };
 
-    let response = client
-        .post(&format!("{}/search", server))
-        .json(&request)
-        .send()
-        .await?;
+    // A rendering was requested, so print it verbatim for pasting into a prompt
+    match format {
+        Some(format) => {
+            if let Some(rendered) = accepted(client.render_search(&request, format).await)? {
+                print!("{}", rendered);
+            }
+        }
+        None => {
+            if let Some(response) = accepted(client.search(&request).await)? {
+                print_search_response(response);
+            }
+        }
+    }
 
-    if response.status().is_success() {
-        let search_result: SearchResponse = response.json().await?;
-        println!(
-            "Found {} matches (out of {} total):",
-            search_result.matches.len(),
-            search_result.total_matches
-        );
+    Ok(())
+}
 
-        for (i, match_item) in search_result.matches.iter().enumerate() {
-            println!("\n--- Match {} (score: {:.2}) ---", i + 1, match_item.score);
-            println!("ID: {}", match_item.context.id);
-            println!("Content: {}", match_item.context.content);
-            println!("Tags: {:?}", match_item.context.tags);
+async fn search_by_expression(
+    client: &McpClient,
+    mut expression: String,
+    tags: Option<Vec<String>>,
+    limit: usize,
+    collection: Option<&str>,
+    format: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Fold --tags into the expression so both forms can be combined
+    for tag in tags.unwrap_or_default() {
+        expression.push_str(&format!(" tag:\"{}\"", tag.replace('"', "\\\"")));
+    }
 
-            if let Some(chunks) = &match_item.chunks {
-                println!("Matching chunks: {}", chunks.len());
-                for chunk in chunks.iter().take(2) {
-                    println!("  - {}", chunk.content);
-                }
-                if chunks.len() > 2 {
-                    println!("  ... {} more chunks", chunks.len() - 2);
-                }
+    if format.is_none() {
+        println!("Searching for contexts matching: {}...", expression);
+    }
+
+    match format {
+        Some(format) => {
+            let rendered = client
+                .render_search_by_query(&expression, Some(limit), collection, format)
+                .await;
+            if let Some(rendered) = accepted(rendered)? {
+                print!("{}", rendered);
+            }
+        }
+        None => {
+            let response = client
+                .search_by_query(&expression, Some(limit), collection)
+                .await;
+            if let Some(response) = accepted(response)? {
+                print_search_response(response);
             }
         }
-    } else {
-        handle_error_response(response).await?;
     }
 
     Ok(())
 }
 
+fn print_search_response(search_result: SearchResponse) {
+    println!(
+        "Found {} matches (out of {}{} total):",
+        search_result.matches.len(),
+        if search_result.total_matches_is_lower_bound {
+            "at least "
+        } else {
+            ""
+        },
+        search_result.total_matches
+    );
+
+    for (i, match_item) in search_result.matches.iter().enumerate() {
+        println!("\n--- Match {} (score: {:.2}) ---", i + 1, match_item.score);
+        println!("ID: {}", match_item.id);
+        if let Some(context) = &match_item.context {
+            if let Some(content) = &context.content {
+                println!("Content: {}", content);
+            }
+            println!("Tags: {:?}", context.tags);
+        }
+
+        if let Some(chunks) = &match_item.chunks {
+            println!("Matching chunks: {}", chunks.len());
+            for chunk in chunks.iter().take(2) {
+                println!("  - {}", chunk.content);
+            }
+            if chunks.len() > 2 {
+                println!("  ... {} more chunks", chunks.len() - 2);
+            }
+        }
+    }
+}
+
 async fn update_context(
-    client: &Client,
-    server: &str,
-    id: &str,
+    client: &McpClient,
+    id: Uuid,
     content: String,
     source: Option<String>,
     content_type: Option<String>,
@@ -428,97 +731,207 @@ async fn update_context(
         source,
         content_type,
         tags,
-        metadata: None,
+        ..UpdateContextRequest::default()
     };
 
-    let response = client
-        .put(&format!("{}/contexts/{}", server, id))
-        .json(&request)
-        .send()
-        .await?;
-
-    if response.status().is_success() {
-        let context: ContextResponse = response.json().await?;
+    if let Some(context) = accepted(client.update(id, &request).await)? {
         println!("Context updated successfully!");
         println!("ID: {}", context.id);
         println!("New content: {}", context.content);
         println!("Tags: {:?}", context.tags);
-    } else {
-        handle_error_response(response).await?;
     }
 
     Ok(())
 }
 
-async fn delete_context(
-    client: &Client,
-    server: &str,
-    id: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+async fn delete_context(client: &McpClient, id: Uuid) -> Result<(), Box<dyn std::error::Error>> {
     println!("Deleting context with ID: {}...", id);
 
-    let response = client
-        .delete(&format!("{}/contexts/{}", server, id))
-        .send()
-        .await?;
-
-    if response.status().is_success() {
+    if accepted(client.delete(id).await)?.is_some() {
         println!("Context deleted successfully!");
-    } else {
-        handle_error_response(response).await?;
     }
 
     Ok(())
 }
 
-async fn handle_error_response(
-    response: reqwest::Response,
+async fn delete_contexts(
+    client: &McpClient,
+    ids: Vec<Uuid>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let status = response.status();
+    println!("Deleting {} contexts...", ids.len());
 
-    match response.json::<ErrorResponse>().await {
-        Ok(error) => {
-            eprintln!("Error ({}): {} ({})", status, error.message, error.code);
+    if let Some(response) = accepted(client.delete_many(ids).await)? {
+        for result in &response.results {
+            println!("  {}: {}", result.id, result.status.replace('_', " "));
         }
-        Err(_) => {
-            eprintln!("Error ({}): Failed to parse error response", status);
+        println!(
+            "Deleted {} of {} contexts.",
+            response.deleted,
+            response.results.len()
+        );
+    }
+
+    Ok(())
+}
+
+async fn delete_by_tag(
+    client: &McpClient,
+    tags: Vec<String>,
+    confirm: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Deleting contexts tagged {}...", tags.join(","));
+
+    if let Some(response) = accepted(client.delete_by_tags(&tags, confirm).await)? {
+        println!("Deleted {} contexts.", response.deleted);
+    }
+
+    Ok(())
+}
+
+async fn export_contexts(
+    client: &McpClient,
+    output: Option<&str>,
+    include_chunks: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Progress goes to stderr so an export to stdout stays valid NDJSON
+    eprintln!("Exporting contexts...");
+
+    let Some(mut export) = accepted(client.export(include_chunks, EXPORT_TIMEOUT).await)? else {
+        return Ok(());
+    };
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(io::BufWriter::new(
+            std::fs::File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    let mut lines = 0;
+    while let Some(chunk) = export.chunk().await? {
+        lines += chunk.iter().filter(|&&byte| byte == b'\n').count();
+        writer.write_all(&chunk)?;
+    }
+    writer.flush()?;
+
+    eprintln!("Exported {} records.", lines);
+    Ok(())
+}
+
+async fn run_eval(
+    client: &McpClient,
+    path: &str,
+    k: usize,
+    label: Option<String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let dataset: EvalDatasetRequest = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+    let Some(dataset) = accepted(client.store_eval_dataset(&dataset).await)? else {
+        return Ok(());
+    };
+    println!(
+        "Running dataset '{}' ({} queries) at k={}...",
+        dataset.name, dataset.cases, k
+    );
+
+    let request = EvalRunRequest {
+        dataset: dataset.name,
+        k: Some(k),
+        label,
+    };
+    let Some(run) = accepted(client.run_eval(&request).await)? else {
+        return Ok(());
+    };
+
+    let Some(runs) = accepted(client.eval_runs(Some(&run.dataset)).await)? else {
+        return Ok(());
+    };
+    // The new run is the last one; compare against the one before it
+    let previous = runs.len().checked_sub(2).map(|index| &runs[index]);
+
+    println!(
+        "Run '{}' at {} ({} queries, k={})",
+        run.label, run.created_at, run.queries, run.k
+    );
+    match previous {
+        Some(previous) => {
+            println!(
+                "Compared with run '{}' at {} (k={}):",
+                previous.label, previous.created_at, previous.k
+            );
+            for (name, current, before) in [
+                ("recall@k", run.recall_at_k, previous.recall_at_k),
+                ("MRR", run.mrr, previous.mrr),
+                ("nDCG@k", run.ndcg_at_k, previous.ndcg_at_k),
+            ] {
+                println!(
+                    "  {:<9} {:.4} ({:+.4} from {:.4})",
+                    name,
+                    current,
+                    current - before,
+                    before
+                );
+            }
+        }
+        None => {
+            println!("  recall@k  {:.4}", run.recall_at_k);
+            println!("  MRR       {:.4}", run.mrr);
+            println!("  nDCG@k    {:.4}", run.ndcg_at_k);
+            println!("No previous run to compare against.");
         }
     }
 
     Ok(())
 }
 
+/// The value of a successful request, or `None` once the server's refusal of it is printed
+///
+/// Requests that got no response fail the command instead.
+fn accepted<T>(result: ClientResult<T>) -> Result<Option<T>, ClientError> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(ClientError::Api { status, error }) => {
+            eprintln!("Error ({}): {} ({})", status, error.message, error.code);
+            for detail in error.details.iter().flatten() {
+                eprintln!("  {}: {}", detail.field, detail.message);
+            }
+            if let Some(request_id) = error.request_id {
+                eprintln!("Request ID: {}", request_id);
+            }
+            Ok(None)
+        }
+        Err(ClientError::UnexpectedResponse { status, .. }) => {
+            eprintln!("Error ({}): Failed to parse error response", status);
+            Ok(None)
+        }
+        Err(err) => Err(err),
+    }
+}
+
 // Interactive mode
-async fn run_interactive_mode(
-    client: &Client,
-    server: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+async fn run_interactive_mode(client: &McpClient) -> Result<(), Box<dyn std::error::Error>> {
     println!("=== MCP Interactive Client ===");
-    println!("Server: {}", server);
+    println!("Server: {}", client.base_url());
     println!();
 
     // Try connecting to the server
     println!("Checking server connection...");
-    match client
-        .get(&format!("{}/contexts", server))
-        .timeout(Duration::from_secs(5))
-        .send()
-        .await
-    {
-        Ok(response) if response.status().is_success() => {
+    match client.health().await {
+        Ok(_) => {
             println!("Server connection successful!");
         }
-        Ok(response) => {
-            println!(
-                "Connected to server but received status code: {}",
-                response.status()
-            );
-        }
-        Err(e) => {
-            println!("Failed to connect to server: {}", e);
-            println!("Please make sure the server is running at {}", server);
-            return Ok(());
-        }
+        Err(err) => match err.status() {
+            Some(status) => {
+                println!("Connected to server but received status code: {}", status);
+            }
+            None => {
+                println!("Failed to connect to server: {}", err);
+                println!(
+                    "Please make sure the server is running at {}",
+                    client.base_url()
+                );
+                return Ok(());
+            }
+        },
     }
 
     println!();
@@ -580,11 +993,11 @@ async fn run_interactive_mode(
 
                 store_context(
                     client,
-                    server,
                     content.trim().to_string(),
                     source,
                     content_type,
                     tags,
+                    None,
                 )
                 .await?;
             }
@@ -596,7 +1009,10 @@ async fn run_interactive_mode(
                 let mut id = String::new();
                 io::stdin().read_line(&mut id)?;
 
-                get_context(client, server, id.trim()).await?;
+                match Uuid::parse_str(id.trim()) {
+                    Ok(id) => get_context(client, id).await?,
+                    Err(_) => println!("Invalid context ID: {}", id.trim()),
+                }
             }
 
             "3" => {
@@ -617,7 +1033,7 @@ async fn run_interactive_mode(
                 io::stdin().read_line(&mut limit_str)?;
                 let limit = limit_str.trim().parse::<usize>().unwrap_or(10);
 
-                list_contexts(client, server, tags, limit).await?;
+                list_contexts(client, tags, limit, None).await?;
             }
 
             "4" => {
@@ -643,7 +1059,7 @@ async fn run_interactive_mode(
                 io::stdin().read_line(&mut limit_str)?;
                 let limit = limit_str.trim().parse::<usize>().unwrap_or(5);
 
-                search_contexts(client, server, query.trim().to_string(), tags, limit).await?;
+                search_contexts(client, query.trim().to_string(), tags, limit, None, None).await?;
             }
 
             "5" => {
@@ -688,16 +1104,20 @@ async fn run_interactive_mode(
                     Some(tags_str.trim().to_string())
                 });
 
-                update_context(
-                    client,
-                    server,
-                    id.trim(),
-                    content.trim().to_string(),
-                    source,
-                    content_type,
-                    tags,
-                )
-                .await?;
+                match Uuid::parse_str(id.trim()) {
+                    Ok(id) => {
+                        update_context(
+                            client,
+                            id,
+                            content.trim().to_string(),
+                            source,
+                            content_type,
+                            tags,
+                        )
+                        .await?
+                    }
+                    Err(_) => println!("Invalid context ID: {}", id.trim()),
+                }
             }
 
             "6" => {
@@ -712,10 +1132,12 @@ async fn run_interactive_mode(
                 let mut confirm = String::new();
                 io::stdin().read_line(&mut confirm)?;
 
-                if confirm.trim().to_lowercase() == "y" {
-                    delete_context(client, server, id.trim()).await?;
-                } else {
+                if confirm.trim().to_lowercase() != "y" {
                     println!("Delete operation cancelled.");
+                } else if let Ok(id) = Uuid::parse_str(id.trim()) {
+                    delete_context(client, id).await?;
+                } else {
+                    println!("Invalid context ID: {}", id.trim());
                 }
             }
 
@@ -1,14 +1,40 @@
+use chrono::Utc;
 use clap::{Parser, Subcommand};
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::net::TcpListener;
-use tracing::{error, info, Level};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn, Level};
 use tracing_subscriber::FmtSubscriber;
 
-use mcp::adapter::in_adapters::{create_router, AppState};
-use mcp::adapter::out_adapters::{InMemoryContextRepository, SimpleEmbeddingService};
-use mcp::application::{ContextManagementService, ContextSearchService};
-use mcp::config::AppConfig;
+use mcp::adapter::in_adapters::{
+    create_router, tls_from_config, AppState, Authenticator, McpServer, RateLimiter,
+    RouteRateLimits, ShareLinkService, SseTransport,
+};
+use mcp::adapter::out_adapters::{
+    create_embedding_backend, create_repository, create_repository_for, create_reranker,
+    create_tokenizer, BroadcastEventPublisher, DictionaryQueryExpander, HttpContentFetcher,
+};
+use mcp::application::{
+    CollectionService, ContextManagementService, ContextSearchService, EvaluationService,
+    IngestionService, ReadinessService, RepositoryMigration,
+};
+use mcp::config::{AppConfig, ChunkSizeUnit, VectorStoreBackend};
+use mcp::domain::{McpError, TagPolicy, Tokenizer};
+use mcp::ports::in_ports::ContextManagementPort;
+use mcp::ports::out_ports::{ContextRepositoryPort, EmbeddingPort, QueryExpansionPort};
+
+/// Number of contexts listed per page while reindexing
+const REINDEX_PAGE_SIZE: usize = 500;
+
+/// How long open connections get to finish once the server is asked to stop
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// Context change events buffered for each `/events` subscriber
+const EVENT_BUFFER: usize = 1024;
 
 /// Command line arguments for the MCP server
 #[derive(Parser, Debug)]
@@ -17,19 +43,43 @@ struct Cli {
     /// Path to the configuration file
     #[clap(short, long, default_value = "config/default.toml")]
     config: String,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serve the REST API (the default)
+    Serve,
+
+    /// Copy contexts from `[migrate.source]` into `[migrate.destination]`
+    Migrate {
+        /// Only report what would be copied
+        #[clap(long)]
+        dry_run: bool,
+    },
+
+    /// Re-embed every stored context with the configured embedding provider
+    Reindex,
+
+    /// Speak the Model Context Protocol over stdin and stdout instead of serving HTTP
+    Stdio,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Initialize logging
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)?;
-
     // Parse command line arguments
     let cli = Cli::parse();
 
+    // Initialize logging; over stdio, stdout carries the protocol, so logs go to stderr
+    let subscriber = FmtSubscriber::builder().with_max_level(Level::INFO);
+    if matches!(cli.command, Some(Command::Stdio)) {
+        tracing::subscriber::set_global_default(subscriber.with_writer(std::io::stderr).finish())?;
+    } else {
+        tracing::subscriber::set_global_default(subscriber.finish())?;
+    }
+
     // Load configuration
     let config = match AppConfig::load() {
         Ok(config) => config,
@@ -39,31 +89,246 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    match cli.command {
+        Some(Command::Migrate { dry_run }) => migrate(&config, dry_run).await,
+        Some(Command::Reindex) => reindex(&config).await,
+        Some(Command::Stdio) => stdio(config).await,
+        Some(Command::Serve) | None => serve(config).await,
+    }
+}
+
+/// The adapters and services every transport is built on
+struct Services {
+    context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
+    embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    tag_policy: Arc<TagPolicy>,
+    events: Arc<BroadcastEventPublisher>,
+    context_manager: Arc<ContextManagementService>,
+    context_search: Arc<ContextSearchService>,
+    tokenizer: Arc<dyn Tokenizer>,
+}
+
+/// Measure chunks in tokens of `tokenizer` if `context.chunk_size_unit` asks for it
+fn with_chunk_size_unit(
+    context_manager: ContextManagementService,
+    config: &AppConfig,
+    tokenizer: &Arc<dyn Tokenizer>,
+) -> ContextManagementService {
+    match config.context.chunk_size_unit {
+        ChunkSizeUnit::Bytes => context_manager,
+        ChunkSizeUnit::Tokens => context_manager.with_token_sizes(tokenizer.clone()),
+    }
+}
+
+/// Initialize the adapters and the context services described by `config`
+async fn build_services(config: &AppConfig) -> Result<Services, Box<dyn std::error::Error>> {
     // Set up the hexagonal architecture
     info!("Initializing MCP components...");
 
     // Initialize adapters
-    let context_repository = Arc::new(InMemoryContextRepository::new());
-    let embedding_service = Arc::new(SimpleEmbeddingService::new(config.embedding.dimension));
+    let context_repository = match create_repository(config).await {
+        Ok(repository) => repository,
+        Err(err) => {
+            error!("Failed to initialize storage: {}", err);
+            return Err(err.into());
+        }
+    };
+    check_embedding_dimension(context_repository.as_ref(), config.embedding.dimension).await?;
+    let embedding = match create_embedding_backend(config).await {
+        Ok(embedding) => embedding,
+        Err(err) => {
+            error!("Failed to initialize embeddings: {}", err);
+            return Err(err.into());
+        }
+    };
+    let reranker = match create_reranker(config) {
+        Ok(reranker) => reranker,
+        Err(err) => {
+            error!("Failed to initialize reranking: {}", err);
+            return Err(err.into());
+        }
+    };
+    let query_expander = match DictionaryQueryExpander::from_config(&config.context.expansion) {
+        Ok(expander) => {
+            expander.map(|expander| Arc::new(expander) as Arc<dyn QueryExpansionPort + Send + Sync>)
+        }
+        Err(err) => {
+            error!("Failed to load query expansion synonyms: {}", err);
+            return Err(err.into());
+        }
+    };
+
+    // Initialize tag normalization and check existing data against it
+    let tag_policy = Arc::new(config.tags.policy()?);
+    warn_on_tag_violations(
+        context_repository.as_ref(),
+        &tag_policy,
+        config.tags.startup_sample_size,
+    )
+    .await;
 
-    // Initialize application services
-    let context_manager = Arc::new(ContextManagementService::new(
+    let tokenizer = create_tokenizer(config)?;
+
+    // Initialize application services, publishing context changes to `/events`
+    let events = Arc::new(BroadcastEventPublisher::new(EVENT_BUFFER));
+    let mut context_manager = ContextManagementService::new(
         context_repository.clone(),
-        embedding_service.clone(),
+        embedding.embedding_service.clone(),
+        embedding.vector_store.clone(),
         config.context.max_chunk_size,
         config.context.chunk_overlap,
+    )?
+    .with_chunking_strategy(config.context.chunking_strategy)
+    .with_embedding_dimension(config.embedding.dimension)
+    .with_max_content_bytes(config.context.max_content_bytes)
+    .with_max_delete_batch(config.context.max_delete_batch)
+    .with_max_revisions(config.context.max_revisions)
+    .with_child_policy(config.context.delete_children)
+    .with_event_publisher(events.clone());
+    if config.context.deleted_retention_seconds > 0 {
+        context_manager = context_manager.with_deleted_retention(chrono::Duration::seconds(
+            config.context.deleted_retention_seconds as i64,
+        ));
+    }
+    let context_manager = Arc::new(with_chunk_size_unit(context_manager, config, &tokenizer));
+
+    // Searches only find chunks in the vector store; a LanceDB dataset keeps them across
+    // restarts, an in-memory store has to be loaded again
+    if config.embedding.vector_store.backend == VectorStoreBackend::Memory {
+        let loaded = match context_manager.load_existing().await {
+            Ok(loaded) => loaded,
+            Err(err) => {
+                error!("Failed to load stored embeddings: {}", err);
+                return Err(err.into());
+            }
+        };
+        info!("Loaded {} stored chunk embeddings", loaded);
+    }
+
+    let context_search = Arc::new(
+        ContextSearchService::new(
+            context_repository.clone(),
+            embedding.embedding_service.clone(),
+            embedding.vector_store.clone(),
+            config.context.max_results,
+        )
+        .with_ranking(config.context.ranking.ranking())
+        .with_fuzzy(config.context.ranking.fuzzy.fuzzy())
+        .with_fuzzy_by_default(config.context.ranking.fuzzy.enabled)
+        .with_hybrid_alpha(config.context.hybrid_alpha)
+        .with_min_score(config.context.min_score)
+        .with_query_expansion(query_expander, config.context.expansion.weight)
+        .with_expansion_by_default(config.context.expansion.enabled)
+        .with_reranker(reranker),
+    );
+
+    Ok(Services {
+        context_repository,
+        embedding_service: embedding.embedding_service,
+        tag_policy,
+        events,
+        context_manager,
+        context_search,
+        tokenizer,
+    })
+}
+
+/// Run the REST API until the process is stopped
+async fn serve(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let services = build_services(&config).await?;
+    let mcp = Arc::new(SseTransport::new(Arc::new(mcp_server(&config, &services)?)));
+    let Services {
+        context_repository,
+        embedding_service,
+        tag_policy,
+        events,
+        context_manager,
+        context_search,
+        tokenizer,
+    } = services;
+
+    // Evaluation runs are labelled with the settings they were made with
+    let evaluation = Arc::new(EvaluationService::new(
+        context_manager.clone(),
+        context_search.clone(),
+        config.fingerprint(),
     ));
 
-    let context_search = Arc::new(ContextSearchService::new(
+    // Documents fetched for ingestion can't come from internal addresses unless allowed
+    if config.ingest.allow_private_addresses {
+        warn!("ingest.allow_private_addresses is set; clients can fetch from internal hosts");
+    }
+    let ingestion = Arc::new(IngestionService::new(Arc::new(
+        HttpContentFetcher::from_config(&config.ingest),
+    )));
+
+    // Readiness checks exercise the same adapters the services use
+    let readiness = Arc::new(ReadinessService::new(
         context_repository.clone(),
         embedding_service.clone(),
-        config.context.max_results,
     ));
 
+    // Collections are kept alongside the contexts they group
+    let collections = Arc::new(CollectionService::new(context_repository.clone()));
+
+    // Initialize context sharing
+    if config.server.share_secret.is_none() {
+        warn!("No server.share_secret configured; sharing links will not survive a restart");
+    }
+    let share_links = Arc::new(ShareLinkService::from_config(
+        config.server.share_secret.as_deref(),
+        &config.server.share,
+    )?);
+    if !share_links.can_revoke() {
+        warn!("No server.share.links_path configured; sharing links cannot be revoked");
+    }
+    let share_rate_limiter = Arc::new(RateLimiter::new(
+        config.server.share.rate_limit_per_second,
+        config.server.share.rate_limit_burst,
+    ));
+
+    let auth = Authenticator::from_config(&config.server)?.map(Arc::new);
+    if auth.is_none() {
+        warn!("No server.api_key configured; the API accepts requests from anyone");
+    }
+
+    // Stopping the server also stops the background tasks
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down");
+        let _ = shutdown_tx.send(true);
+    });
+    let sweeper = (config.context.expiry_sweep_seconds > 0).then(|| {
+        sweep_expired_contexts(
+            context_manager.clone(),
+            Duration::from_secs(config.context.expiry_sweep_seconds),
+            shutdown_rx.clone(),
+        )
+    });
+
     // Initialize the REST API
     let app_state = AppState {
         context_manager,
         context_search,
+        share_links,
+        share_rate_limiter,
+        rate_limits: RouteRateLimits::from_config(&config.server.rate_limit),
+        max_body_bytes: config.context.max_body_bytes,
+        max_results: config.context.max_results,
+        max_page_size: config.context.max_page_size,
+        legacy_routes: config.server.legacy_routes,
+        auth,
+        tag_policy,
+        highlighter: Arc::new(config.context.highlight.highlighter()),
+        tokenizer,
+        evaluation,
+        ingestion,
+        readiness,
+        collections,
+        events,
+        mcp,
+        started_at: Instant::now(),
     };
 
     // Create the API router
@@ -72,9 +337,324 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Set up the server address
     let addr = SocketAddr::new(config.server.host.parse()?, config.server.port);
 
-    // Start the server
-    info!("Starting MCP server at {}", addr);
-    axum::serve(TcpListener::bind(addr).await?, app).await?;
+    // Start the server, over HTTPS if a certificate is configured
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    match tls_from_config(&config.server.tls)? {
+        Some(tls) => {
+            #[cfg(unix)]
+            if let Some((cert_path, key_path)) = config.server.tls.paths()? {
+                mcp::adapter::in_adapters::reload_on_sighup(
+                    tls.clone(),
+                    cert_path.to_string(),
+                    key_path.to_string(),
+                )?;
+            }
+            let handle = axum_server::Handle::new();
+            let mut shutdown = shutdown_rx.clone();
+            tokio::spawn({
+                let handle = handle.clone();
+                async move {
+                    let _ = shutdown.changed().await;
+                    handle.graceful_shutdown(Some(SHUTDOWN_GRACE));
+                }
+            });
+            info!("Starting MCP server at https://{}", addr);
+            axum_server::bind_rustls(addr, tls)
+                .handle(handle)
+                .serve(app)
+                .await?;
+        }
+        None => {
+            let mut shutdown = shutdown_rx.clone();
+            info!("Starting MCP server at http://{}", addr);
+            let server = axum::serve(TcpListener::bind(addr).await?, app)
+                .with_graceful_shutdown(async move {
+                    let _ = shutdown.changed().await;
+                })
+                .into_future();
+
+            // Event streams stay open until the client leaves, so they're cut after the grace
+            let mut shutdown = shutdown_rx.clone();
+            let grace_over = async move {
+                let _ = shutdown.changed().await;
+                tokio::time::sleep(SHUTDOWN_GRACE).await;
+            };
+            tokio::select! {
+                result = server => result?,
+                _ = grace_over => warn!("Closed the connections still open after {:?}", SHUTDOWN_GRACE),
+            }
+        }
+    }
 
+    if let Some(sweeper) = sweeper {
+        let _ = sweeper.await;
+    }
     Ok(())
 }
+
+/// Answer Model Context Protocol messages on stdin until the host closes it
+async fn stdio(config: AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let services = build_services(&config).await?;
+    let server = mcp_server(&config, &services)?;
+
+    info!("Speaking MCP over stdio");
+    server
+        .serve(tokio::io::stdin(), tokio::io::stdout())
+        .await?;
+    Ok(())
+}
+
+/// The MCP protocol handler, offering the same tools and prompts over every transport
+fn mcp_server(
+    config: &AppConfig,
+    services: &Services,
+) -> Result<McpServer, Box<dyn std::error::Error>> {
+    let prompts = match config.mcp.prompts() {
+        Ok(prompts) => prompts,
+        Err(err) => {
+            error!("Failed to load MCP prompts: {}", err);
+            return Err(err.into());
+        }
+    };
+    Ok(McpServer::new(
+        services.context_manager.clone(),
+        services.context_search.clone(),
+    )
+    .with_tag_policy(services.tag_policy.clone())
+    .with_events(services.events.clone())
+    .with_max_results(config.context.max_results)
+    .with_prompts(prompts)
+    .with_protocol_versions(config.mcp.protocol_versions.clone()))
+}
+
+/// Resolve once the process is asked to stop, with Ctrl-C or, on Unix, SIGTERM
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Delete expired contexts, and soft-deleted ones past their retention, every `period` until
+/// `shutdown` changes
+///
+/// A failed sweep is logged and the next one tries again.
+fn sweep_expired_contexts(
+    context_manager: Arc<ContextManagementService>,
+    period: Duration,
+    mut shutdown: watch::Receiver<bool>,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.changed() => return,
+            }
+
+            // A sweep under way is finished, so no context is left half deleted
+            match context_manager.sweep_expired(Utc::now()).await {
+                Ok(0) => {}
+                Ok(deleted) => info!(deleted, "Deleted expired contexts"),
+                Err(err) => warn!("Failed to delete expired contexts: {}", err),
+            }
+            match context_manager.purge_deleted(Utc::now()).await {
+                Ok(0) => {}
+                Ok(deleted) => info!(deleted, "Purged soft-deleted contexts"),
+                Err(err) => warn!("Failed to purge soft-deleted contexts: {}", err),
+            }
+        }
+    })
+}
+
+/// Re-embed every stored context, replacing its chunks
+async fn reindex(config: &AppConfig) -> Result<(), Box<dyn std::error::Error>> {
+    let repository = create_repository(config).await?;
+    let embedding = create_embedding_backend(config).await?;
+    let tokenizer = create_tokenizer(config)?;
+    let context_manager = with_chunk_size_unit(
+        ContextManagementService::new(
+            repository.clone(),
+            embedding.embedding_service,
+            embedding.vector_store,
+            config.context.max_chunk_size,
+            config.context.chunk_overlap,
+        )?
+        .with_chunking_strategy(config.context.chunking_strategy)
+        .with_embedding_dimension(config.embedding.dimension),
+        config,
+        &tokenizer,
+    );
+
+    // Collect the ids first, since updating contexts may reorder the listing
+    let mut ids = Vec::new();
+    loop {
+        let page = repository.list_all(REINDEX_PAGE_SIZE, ids.len()).await?;
+        ids.extend(page.iter().map(|context| context.id));
+        if page.len() < REINDEX_PAGE_SIZE {
+            break;
+        }
+    }
+
+    info!(
+        "Reindexing {} contexts with the {} provider",
+        ids.len(),
+        config.embedding.provider
+    );
+    for (done, id) in ids.iter().enumerate() {
+        let context = repository.find_by_id(*id).await?;
+        // Expired and deleted contexts can't be found or searched, so there's nothing to
+        // re-embed
+        if !context.is_live_at(Utc::now()) {
+            continue;
+        }
+        context_manager
+            .update_context(
+                context.id,
+                context.content,
+                context.metadata,
+                context.expires_at,
+                None,
+            )
+            .await?;
+
+        if (done + 1) % REINDEX_PAGE_SIZE == 0 {
+            info!("{} of {} contexts reindexed", done + 1, ids.len());
+        }
+    }
+
+    println!("Reindexed {} contexts", ids.len());
+    Ok(())
+}
+
+/// Fail if the stored embeddings were made with another dimension than the configured one
+async fn check_embedding_dimension(
+    repository: &(dyn ContextRepositoryPort + Send + Sync),
+    dimension: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // All stored chunks share a dimension, so the first embedded one tells
+    for context in repository.list_all(10, 0).await? {
+        let chunks = repository.find_chunks_by_context_id(context.id).await?;
+        let Some(stored) = chunks
+            .iter()
+            .find_map(|chunk| chunk.embedding.as_ref().map(Vec::len))
+        else {
+            continue;
+        };
+
+        if stored != dimension {
+            error!(
+                "Stored embeddings have {} dimensions but embedding.dimension is {}; the \
+                 embedding provider or its settings changed since they were made. Run \
+                 `mcp-server reindex` to re-embed the stored contexts, or restore the \
+                 previous settings",
+                stored, dimension
+            );
+            return Err(McpError::dimension_mismatch(dimension, stored).into());
+        }
+        return Ok(());
+    }
+
+    Ok(())
+}
+
+/// Copy every context and its chunks between the repositories of the `[migrate]` section
+async fn migrate(config: &AppConfig, dry_run: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(migrate) = &config.migrate else {
+        error!("The migrate subcommand needs [migrate.source] and [migrate.destination] sections");
+        return Err("missing [migrate] configuration".into());
+    };
+
+    let source = create_repository_for(&migrate.source.storage(&config.storage)).await?;
+    let destination = create_repository_for(&migrate.destination.storage(&config.storage)).await?;
+
+    info!(
+        "{} contexts from {} storage to {} storage",
+        if dry_run { "Checking" } else { "Copying" },
+        migrate.source.backend,
+        migrate.destination.backend
+    );
+
+    let migration = RepositoryMigration::new(source, destination, migrate.page_size);
+    let report = migration
+        .run(dry_run, |progress| {
+            info!(
+                "{} contexts read, {} copied, {} already present",
+                progress.contexts_seen, progress.contexts_copied, progress.contexts_skipped
+            );
+        })
+        .await?;
+
+    let verb = if dry_run { "would copy" } else { "copied" };
+    println!(
+        "Migration {} {} collections, {} contexts and {} chunks; {} of {} contexts already existed",
+        verb,
+        report.collections_copied,
+        report.contexts_copied,
+        report.chunks_copied,
+        report.contexts_skipped,
+        report.contexts_seen
+    );
+
+    Ok(())
+}
+
+/// Warn if a sample of the stored contexts has tags the policy would change
+async fn warn_on_tag_violations(
+    repository: &(dyn ContextRepositoryPort + Send + Sync),
+    policy: &TagPolicy,
+    sample_size: usize,
+) {
+    if sample_size == 0 {
+        return;
+    }
+
+    let contexts = match repository.list_all(sample_size, 0).await {
+        Ok(contexts) => contexts,
+        Err(err) => {
+            warn!(
+                "Could not check stored tags against the tag policy: {}",
+                err
+            );
+            return;
+        }
+    };
+
+    let violations: Vec<&str> = contexts
+        .iter()
+        .flat_map(|context| policy.violations(&context.metadata.tags))
+        .collect();
+
+    if !violations.is_empty() {
+        warn!(
+            "{} tag(s) in {} sampled contexts do not match the tag policy (e.g. {:?}); \
+             filters will not match them until they are renormalized",
+            violations.len(),
+            contexts.len(),
+            &violations[..violations.len().min(5)]
+        );
+    }
+}
@@ -3,8 +3,13 @@
 // A Xilem UI for the Model Context Protocol
 
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
+use mcp::adapter::in_adapters::api::models::{
+    ContextResponse, ListContextsParams, MatchedContextDto, StoreContextRequest,
+};
+use mcp::client::McpClient;
 use std::collections::HashMap;
+use tokio::task::AbortHandle;
+use tracing::debug;
 use uuid::Uuid;
 use winit::dpi::LogicalSize;
 use winit::error::EventLoopError;
@@ -17,28 +22,6 @@ use xilem::view::{
 };
 use xilem::{palette, EventLoop, EventLoopBuilder, TextAlignment, WidgetView, Xilem};
 
-// Context Response DTO
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct ContextResponse {
-    id: Uuid,
-    content: String,
-    source: Option<String>,
-    content_type: Option<String>,
-    tags: Vec<String>,
-    metadata: HashMap<String, String>,
-    created_at: String,
-    expires_at: Option<String>,
-}
-
-// Request to create a context
-#[derive(Debug, Clone, Serialize, PartialEq)]
-struct CreateContextRequest {
-    content: String,
-    source: Option<String>,
-    content_type: Option<String>,
-    tags: Vec<String>,
-}
-
 // API call result enum
 #[derive(Debug)]
 enum ApiResult<T> {
@@ -50,10 +33,85 @@ enum ApiResult<T> {
 #[derive(Debug, PartialEq, Clone)]
 enum ApiRequest {
     LoadContexts,
-    CreateContext(CreateContextRequest),
+    Search(String),
+    CreateContext(StoreContextRequest),
     DeleteContext(Uuid),
 }
 
+impl ApiRequest {
+    fn kind(&self) -> RequestKind {
+        match self {
+            // Search results replace the listed contexts, so they are sequenced with loads
+            ApiRequest::LoadContexts | ApiRequest::Search(_) => RequestKind::LoadContexts,
+            ApiRequest::CreateContext(_) => RequestKind::CreateContext,
+            ApiRequest::DeleteContext(_) => RequestKind::DeleteContext,
+        }
+    }
+}
+
+// Kinds of API requests, used to sequence results
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum RequestKind {
+    LoadContexts,
+    CreateContext,
+    DeleteContext,
+}
+
+// An API request tagged with the sequence number it was issued with
+#[derive(Debug, PartialEq, Clone)]
+struct SequencedRequest {
+    sequence: u64,
+    request: ApiRequest,
+}
+
+// Result of an API request, tagged so stale results can be ignored
+#[derive(Debug)]
+struct ApiResponse {
+    sequence: u64,
+    kind: RequestKind,
+    result: ApiResult<Vec<ContextResponse>>,
+}
+
+// Tracks issued requests so only the latest result of each kind is applied
+#[derive(Debug, Default)]
+struct RequestTracker {
+    next_sequence: u64,
+    latest: HashMap<RequestKind, u64>,
+    in_flight: Vec<SequencedRequest>,
+}
+
+impl RequestTracker {
+    // Queue a request, returning its sequence number
+    fn issue(&mut self, request: ApiRequest) -> u64 {
+        self.next_sequence += 1;
+        let sequence = self.next_sequence;
+        let kind = request.kind();
+
+        // Older loads are aborted by the worker, so they will never complete
+        if kind == RequestKind::LoadContexts {
+            self.in_flight
+                .retain(|pending| pending.request.kind() != RequestKind::LoadContexts);
+        }
+
+        self.latest.insert(kind, sequence);
+        self.in_flight.push(SequencedRequest { sequence, request });
+        sequence
+    }
+
+    // Record a completed request, returning whether its result is still current
+    fn complete(&mut self, kind: RequestKind, sequence: u64) -> bool {
+        self.in_flight
+            .retain(|pending| pending.request.kind() != kind || pending.sequence > sequence);
+        self.latest.get(&kind) == Some(&sequence)
+    }
+
+    fn is_in_flight(&self, kind: RequestKind) -> bool {
+        self.in_flight
+            .iter()
+            .any(|pending| pending.request.kind() == kind)
+    }
+}
+
 // Component to represent a single context in the list
 struct ContextListItem {
     context: ContextResponse,
@@ -136,8 +194,7 @@ impl ContextDetailsView {
 
         // Create button section
         let button_section = button("Delete Context".to_string(), move |state: &mut McpApp| {
-            state.loading_operation = Some("deleting_context".to_string());
-            state.delete_context_id = Some(id);
+            state.requests.issue(ApiRequest::DeleteContext(id));
         });
 
         // Combine all sections
@@ -206,7 +263,8 @@ impl CreateContextForm {
                             state.status_message = "Content cannot be empty".to_string();
                             return;
                         }
-                        state.loading_operation = Some("creating_context".to_string());
+                        let request = state.create_context_request();
+                        state.requests.issue(ApiRequest::CreateContext(request));
                     },
                 ))
             },
@@ -248,9 +306,9 @@ struct McpApp {
     new_context_content: String,
     new_context_source: String,
     new_context_tags: String,
+    search_query: String,
     selected_context_id: Option<Uuid>,
-    loading_operation: Option<String>,
-    delete_context_id: Option<Uuid>,
+    requests: RequestTracker,
     api_url: String,
 }
 
@@ -262,9 +320,9 @@ impl Default for McpApp {
             new_context_content: String::new(),
             new_context_source: String::new(),
             new_context_tags: String::new(),
+            search_query: String::new(),
             selected_context_id: None,
-            loading_operation: None,
-            delete_context_id: None,
+            requests: RequestTracker::default(),
             api_url: "http://localhost:3000".to_string(),
         }
     }
@@ -281,13 +339,8 @@ impl McpApp {
         // Create the main content area
         let main_content = self.create_main_content();
 
-        // Capture API request into a local variable to track changes
-        let api_request = self.get_api_request();
-
-        // Add debug output to help diagnose issues
-        if let Some(req) = &api_request {
-            println!("API Request: {:?}", req);
-        }
+        // Hand the queue of in-flight requests to the worker, which dispatches new ones
+        let api_requests = self.requests.in_flight.clone();
 
         // Combine the layout
         let content = flex((
@@ -302,45 +355,71 @@ impl McpApp {
             .flex(1.),
         ));
 
-        // Store API URL in a local variable for consistent usage
-        let api_url = self.api_url.clone();
+        // One client for the worker, cloned into each request's task
+        let client = api_client(&self.api_url);
 
         // Add API worker that responds to api_request changes
         fork(
             content,
             worker_raw(
-                api_request,
+                api_requests,
                 move |proxy, mut rx| {
-                    let api_base_url = api_url.clone();
+                    let client = client.clone();
                     async move {
-                        while let Some(request) = rx.recv().await {
-                            let proxy = proxy.clone();
-                            let base_url = api_base_url.clone();
-
-                            // Only proceed if we have a request
-                            if let Some(request) = request {
-                                println!("Worker received request: {:?}", request);
-
-                                tokio::task::spawn(async move {
+                        // Highest sequence dispatched so far, and the running load to abort
+                        let mut dispatched = 0;
+                        let mut load_task: Option<AbortHandle> = None;
+
+                        while let Some(requests) = rx.recv().await {
+                            for SequencedRequest { sequence, request } in requests {
+                                if sequence <= dispatched {
+                                    continue;
+                                }
+                                dispatched = sequence;
+                                debug!("Worker received request #{}: {:?}", sequence, request);
+
+                                let proxy = proxy.clone();
+                                let client = client.clone();
+                                let kind = request.kind();
+
+                                let task = tokio::task::spawn(async move {
                                     let result = match request {
-                                        ApiRequest::LoadContexts => fetch_contexts(&base_url).await,
+                                        ApiRequest::LoadContexts => fetch_contexts(&client).await,
+                                        ApiRequest::Search(query) => {
+                                            search_contexts(&client, &query).await
+                                        }
                                         ApiRequest::CreateContext(req) => {
-                                            create_context(&base_url, req).await
+                                            create_context(&client, req).await
                                         }
                                         ApiRequest::DeleteContext(id) => {
-                                            delete_context(&base_url, id).await
+                                            delete_context(&client, id).await
                                         }
                                     };
-                                    println!("API call completed: {:?}", result);
-                                    drop(proxy.message(result));
+                                    debug!("API call #{} completed: {:?}", sequence, result);
+                                    drop(proxy.message(ApiResponse {
+                                        sequence,
+                                        kind,
+                                        result,
+                                    }));
                                 });
+
+                                // A newer load supersedes any load still in flight
+                                if kind == RequestKind::LoadContexts {
+                                    if let Some(previous) = load_task.replace(task.abort_handle()) {
+                                        previous.abort();
+                                    }
+                                }
                             }
                         }
                     }
                 },
-                |state: &mut Self, result| {
-                    println!("Handling API result");
-                    match result {
+                |state: &mut Self, response: ApiResponse| {
+                    if !state.requests.complete(response.kind, response.sequence) {
+                        debug!("Ignoring stale API result #{}", response.sequence);
+                        return;
+                    }
+
+                    match response.result {
                         ApiResult::Success(contexts) => {
                             state.contexts = contexts;
                             state.status_message =
@@ -352,17 +431,11 @@ impl McpApp {
                     }
 
                     // Reset form if we were creating a context
-                    if state.loading_operation == Some("creating_context".into()) {
+                    if response.kind == RequestKind::CreateContext {
                         state.new_context_content = String::new();
                         state.new_context_source = String::new();
                         state.new_context_tags = String::new();
                     }
-
-                    // Clear the delete context ID if we were deleting
-                    state.delete_context_id = None;
-
-                    // Always clear the loading operation
-                    state.loading_operation = None;
                 },
             ),
         )
@@ -384,7 +457,7 @@ impl McpApp {
     }
 
     fn create_sidebar(&self) -> impl WidgetView<Self> {
-        let is_loading = self.loading_operation == Some("loading_contexts".into());
+        let is_loading = self.requests.is_in_flight(RequestKind::LoadContexts);
 
         // Create header section
         let header = flex((
@@ -398,6 +471,20 @@ impl McpApp {
         ))
         .direction(Axis::Horizontal);
 
+        // Create search section, an empty query lists all contexts again
+        let search_section = flex((
+            textbox(
+                self.search_query.clone(),
+                |state: &mut McpApp, value: String| {
+                    state.search_query = value;
+                },
+            ),
+            button("Search".to_string(), |state: &mut McpApp| {
+                state.requests.issue(state.search_request());
+            }),
+        ))
+        .direction(Axis::Horizontal);
+
         // Create list section
         let contexts_list = flex(
             self.contexts
@@ -416,13 +503,15 @@ impl McpApp {
 
         // Create button section
         let button_section = button("Refresh Contexts".to_string(), |state: &mut McpApp| {
-            state.loading_operation = Some("loading_contexts".to_string());
+            state.requests.issue(ApiRequest::LoadContexts);
         });
 
         // Combine all sections
         sized_box(portal(flex((
             header,
             FlexSpacer::Fixed(8.),
+            search_section,
+            FlexSpacer::Fixed(8.),
             contexts_list,
             FlexSpacer::Fixed(16.),
             button_section,
@@ -445,7 +534,7 @@ impl McpApp {
             }
         } else {
             // Show context creation form
-            let is_creating = self.loading_operation == Some("creating_context".into());
+            let is_creating = self.requests.is_in_flight(RequestKind::CreateContext);
             let mut form = CreateContextForm {
                 content: self.new_context_content.clone(),
                 source: self.new_context_source.clone(),
@@ -456,81 +545,60 @@ impl McpApp {
         }
     }
 
-    // Generate appropriate API request based on current state
-    fn get_api_request(&self) -> Option<ApiRequest> {
-        match self.loading_operation.as_deref() {
-            Some("loading_contexts") => {
-                println!("Creating LoadContexts request");
-                Some(ApiRequest::LoadContexts)
-            }
-            Some("creating_context") => {
-                println!("Creating CreateContext request");
-                // Parse tags
-                let tags = self
-                    .new_context_tags
-                    .split(',')
-                    .map(|s| s.trim().to_string())
-                    .filter(|s| !s.is_empty())
-                    .collect();
-
-                let request = CreateContextRequest {
-                    content: self.new_context_content.clone(),
-                    source: if self.new_context_source.is_empty() {
-                        None
-                    } else {
-                        Some(self.new_context_source.clone())
-                    },
-                    content_type: None,
-                    tags,
-                };
+    // Build a request for the search box, using the server's query syntax
+    fn search_request(&self) -> ApiRequest {
+        let query = self.search_query.trim();
+        if query.is_empty() {
+            ApiRequest::LoadContexts
+        } else {
+            ApiRequest::Search(query.to_string())
+        }
+    }
 
-                Some(ApiRequest::CreateContext(request))
-            }
-            Some("deleting_context") => {
-                if let Some(id) = self.delete_context_id {
-                    println!("Creating DeleteContext request for ID: {}", id);
-                    Some(ApiRequest::DeleteContext(id))
-                } else {
-                    println!("Missing delete_context_id");
-                    None
-                }
-            }
-            _ => {
-                // No active operation
+    // Build a create request from the form fields
+    fn create_context_request(&self) -> StoreContextRequest {
+        // Parse tags
+        let tags = self
+            .new_context_tags
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        StoreContextRequest {
+            content: self.new_context_content.clone(),
+            source: if self.new_context_source.is_empty() {
                 None
-            }
+            } else {
+                Some(self.new_context_source.clone())
+            },
+            tags: Some(tags),
+            ..StoreContextRequest::default()
         }
     }
 }
 
 // API functions
 
-async fn fetch_contexts(base_url: &str) -> ApiResult<Vec<ContextResponse>> {
-    println!("Fetching contexts from: {}/contexts", base_url);
-    let client = reqwest::Client::new();
-    match client
-        .get(&format!("{}/contexts?limit=50", base_url))
-        .send()
-        .await
-    {
-        Ok(response) => {
-            println!("Response status: {}", response.status());
-            if response.status().is_success() {
-                match response.json::<Vec<ContextResponse>>().await {
-                    Ok(contexts) => {
-                        println!("Received {} contexts", contexts.len());
-                        ApiResult::Success(contexts)
-                    }
-                    Err(e) => {
-                        println!("Error parsing contexts: {}", e);
-                        ApiResult::Error(format!("Failed to parse contexts: {}", e))
-                    }
-                }
-            } else {
-                let error_msg = format!("Failed to load contexts: HTTP {}", response.status());
-                println!("{}", error_msg);
-                ApiResult::Error(error_msg)
-            }
+/// Client of the API sending the API key from `MCP_API_KEY` with every request, if it is set
+fn api_client(base_url: &str) -> McpClient {
+    let client = McpClient::new(base_url);
+    match std::env::var("MCP_API_KEY") {
+        Ok(api_key) => client.with_api_key(api_key),
+        Err(_) => client,
+    }
+}
+
+async fn fetch_contexts(client: &McpClient) -> ApiResult<Vec<ContextResponse>> {
+    println!("Fetching contexts from: {}/contexts", client.base_url());
+    let params = ListContextsParams {
+        limit: Some(50),
+        ..ListContextsParams::default()
+    };
+    match client.list(params).await {
+        Ok(page) => {
+            println!("Received {} of {} contexts", page.items.len(), page.total);
+            ApiResult::Success(page.items)
         }
         Err(e) => {
             let error_msg = format!("Failed to load contexts: {}", e);
@@ -540,38 +608,56 @@ async fn fetch_contexts(base_url: &str) -> ApiResult<Vec<ContextResponse>> {
     }
 }
 
+async fn search_contexts(client: &McpClient, query: &str) -> ApiResult<Vec<ContextResponse>> {
+    println!(
+        "Searching contexts at: {}/search?q={}",
+        client.base_url(),
+        query
+    );
+    // The server names the offending token of a bad query, which the error carries
+    match client.search_by_query(query, Some(50), None).await {
+        Ok(search) => ApiResult::Success(
+            search
+                .matches
+                .into_iter()
+                .filter_map(|m| m.context)
+                .map(matched_to_response)
+                .collect(),
+        ),
+        Err(e) => ApiResult::Error(format!("Search failed: {}", e)),
+    }
+}
+
+/// The context of a search match, shaped like the listed contexts it's shown among
+fn matched_to_response(context: MatchedContextDto) -> ContextResponse {
+    ContextResponse {
+        id: context.id,
+        content: context.content.unwrap_or_default(),
+        source: context.source,
+        content_type: context.content_type,
+        content_hash: context.content_hash,
+        tags: context.tags,
+        metadata: context.metadata,
+        created_at: context.created_at,
+        expires_at: context.expires_at,
+        version: context.version,
+        parent_id: context.parent_id,
+        collection_id: context.collection_id,
+    }
+}
+
 async fn create_context(
-    base_url: &str,
-    request: CreateContextRequest,
+    client: &McpClient,
+    request: StoreContextRequest,
 ) -> ApiResult<Vec<ContextResponse>> {
-    println!("Creating context at: {}/contexts", base_url);
+    println!("Creating context at: {}/contexts", client.base_url());
     println!("Request: {:?}", request);
 
-    let client = reqwest::Client::new();
-
-    match client
-        .post(&format!("{}/contexts", base_url))
-        .json(&request)
-        .send()
-        .await
-    {
-        Ok(response) => {
-            let status = response.status();
-            println!("Response status: {}", status);
-            if status.is_success() {
-                println!("Context created successfully");
-                // After successfully creating a context, reload all contexts
-                fetch_contexts(base_url).await
-            } else {
-                let error_text = response
-                    .text()
-                    .await
-                    .unwrap_or_else(|_| "Unknown error".to_string());
-                let error_msg =
-                    format!("Failed to create context: HTTP {} - {}", status, error_text);
-                println!("{}", error_msg);
-                ApiResult::Error(error_msg)
-            }
+    match client.store(&request).await {
+        Ok(_) => {
+            println!("Context created successfully");
+            // After successfully creating a context, reload all contexts
+            fetch_contexts(client).await
         }
         Err(e) => {
             let error_msg = format!("Failed to create context: {}", e);
@@ -581,25 +667,10 @@ async fn create_context(
     }
 }
 
-async fn delete_context(base_url: &str, id: Uuid) -> ApiResult<Vec<ContextResponse>> {
-    let client = reqwest::Client::new();
-
-    match client
-        .delete(&format!("{}/contexts/{}", base_url, id))
-        .send()
-        .await
-    {
-        Ok(response) => {
-            if response.status().is_success() {
-                // After successfully deleting a context, reload all contexts
-                fetch_contexts(base_url).await
-            } else {
-                ApiResult::Error(format!(
-                    "Failed to delete context: HTTP {}",
-                    response.status()
-                ))
-            }
-        }
+async fn delete_context(client: &McpClient, id: Uuid) -> ApiResult<Vec<ContextResponse>> {
+    match client.delete(id).await {
+        // After successfully deleting a context, reload all contexts
+        Ok(()) => fetch_contexts(client).await,
         Err(e) => ApiResult::Error(format!("Failed to delete context: {}", e)),
     }
 }
@@ -630,3 +701,74 @@ fn android_main(app: winit::platform::android::activity::AndroidApp) {
 
     run(event_loop).expect("Can create app");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_out_of_order_loads_keep_latest() {
+        let mut tracker = RequestTracker::default();
+
+        let first = tracker.issue(ApiRequest::LoadContexts);
+        let second = tracker.issue(ApiRequest::LoadContexts);
+        let third = tracker.issue(ApiRequest::LoadContexts);
+
+        // Only the newest load remains in flight
+        assert_eq!(tracker.in_flight.len(), 1);
+        assert!(tracker.is_in_flight(RequestKind::LoadContexts));
+
+        // The newest load finishes first and is applied
+        assert!(tracker.complete(RequestKind::LoadContexts, third));
+        assert!(!tracker.is_in_flight(RequestKind::LoadContexts));
+
+        // Older loads finishing afterwards are ignored
+        assert!(!tracker.complete(RequestKind::LoadContexts, first));
+        assert!(!tracker.complete(RequestKind::LoadContexts, second));
+    }
+
+    #[test]
+    fn test_stale_result_does_not_clear_newer_request() {
+        let mut tracker = RequestTracker::default();
+        let id = Uuid::new_v4();
+
+        let first = tracker.issue(ApiRequest::DeleteContext(id));
+        let second = tracker.issue(ApiRequest::DeleteContext(Uuid::new_v4()));
+
+        assert!(!tracker.complete(RequestKind::DeleteContext, first));
+        assert!(tracker.is_in_flight(RequestKind::DeleteContext));
+
+        assert!(tracker.complete(RequestKind::DeleteContext, second));
+        assert!(!tracker.is_in_flight(RequestKind::DeleteContext));
+    }
+
+    #[test]
+    fn test_kinds_are_sequenced_independently() {
+        let mut tracker = RequestTracker::default();
+
+        let create = tracker.issue(ApiRequest::CreateContext(StoreContextRequest {
+            content: "content".to_string(),
+            ..StoreContextRequest::default()
+        }));
+        let load = tracker.issue(ApiRequest::LoadContexts);
+        assert!(create < load);
+
+        // A newer load does not make the create result stale
+        assert!(tracker.complete(RequestKind::LoadContexts, load));
+        assert!(tracker.is_in_flight(RequestKind::CreateContext));
+        assert!(tracker.complete(RequestKind::CreateContext, create));
+        assert!(tracker.in_flight.is_empty());
+    }
+
+    #[test]
+    fn test_search_supersedes_load() {
+        let mut tracker = RequestTracker::default();
+
+        let load = tracker.issue(ApiRequest::LoadContexts);
+        let search = tracker.issue(ApiRequest::Search("tag:runbook".to_string()));
+
+        assert_eq!(tracker.in_flight.len(), 1);
+        assert!(!tracker.complete(RequestKind::LoadContexts, load));
+        assert!(tracker.complete(RequestKind::LoadContexts, search));
+    }
+}
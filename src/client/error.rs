@@ -0,0 +1,46 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::adapter::input::api::models::ErrorResponse;
+
+/// Errors of requests made with an [`McpClient`](super::McpClient)
+#[derive(Error, Debug)]
+pub enum ClientError {
+    /// The request didn't get a response, or its body couldn't be read or parsed
+    #[error("Request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    /// The server refused the request with its standard error body
+    #[error("Server error ({status}): {} ({})", .error.message, .error.code)]
+    Api {
+        status: StatusCode,
+        error: ErrorResponse,
+    },
+
+    /// The server failed the request without an error body, as a proxy in front of it might
+    #[error("Unexpected response ({status}): {body}")]
+    UnexpectedResponse { status: StatusCode, body: String },
+}
+
+impl ClientError {
+    /// Status of the response the request failed with, if it got one
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientError::Transport(err) => err.status(),
+            ClientError::Api { status, .. } | ClientError::UnexpectedResponse { status, .. } => {
+                Some(*status)
+            }
+        }
+    }
+
+    /// Code of the server's error, such as `CONTEXT_NOT_FOUND`
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ClientError::Api { error, .. } => Some(&error.code),
+            _ => None,
+        }
+    }
+}
+
+/// Result type for client requests
+pub type ClientResult<T> = Result<T, ClientError>;
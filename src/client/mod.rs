@@ -0,0 +1,372 @@
+//! Typed client for the server's REST API
+//!
+//! Requests and responses are the same DTOs the server's handlers use, so the two can't
+//! drift apart.
+
+pub mod error;
+
+use bytes::Bytes;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::adapter::input::api::models::{
+    CollectionResponse, ContextPage, ContextResponse, CreateCollectionRequest,
+    DeleteByTagsResponse, DeleteContextsRequest, DeleteContextsResponse, ErrorResponse,
+    EvalDatasetRequest, EvalDatasetResponse, EvalRunRequest, EvalRunResponse, HealthResponse,
+    ListContextsParams, ReferenceRequest, SearchRequest, SearchResponse, StoreContextRequest,
+    UpdateContextRequest,
+};
+use crate::adapter::input::api::{API_V1, REQUEST_ID_HEADER};
+
+pub use error::{ClientError, ClientResult};
+
+/// Time a request may take unless the client is given its own HTTP client
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A file to store as a new context through the upload endpoint
+#[derive(Debug, Default)]
+pub struct Upload {
+    /// Name of the file, the context's source unless `source` is set
+    pub file_name: String,
+
+    /// Contents of the file, which must be UTF-8 unless `lossy` is set
+    pub bytes: Vec<u8>,
+
+    /// Source of the content, instead of the file name (optional)
+    pub source: Option<String>,
+
+    /// Content type, instead of the one guessed from the file name (optional)
+    pub content_type: Option<String>,
+
+    /// Tags for categorization
+    pub tags: Vec<String>,
+
+    /// ID or name of the collection to store the file in (optional)
+    pub collection: Option<String>,
+
+    /// Replace bytes that aren't valid UTF-8 instead of failing
+    pub lossy: bool,
+}
+
+/// Newline-delimited JSON records of an export, read as the server sends them
+pub struct Export {
+    response: Response,
+}
+
+impl Export {
+    /// The next bytes of the export, or `None` once it's complete
+    pub async fn chunk(&mut self) -> ClientResult<Option<Bytes>> {
+        Ok(self.response.chunk().await?)
+    }
+}
+
+/// Client of the server's REST API, under the `/v1` prefix of `base_url`
+#[derive(Debug, Clone)]
+pub struct McpClient {
+    http: Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+impl McpClient {
+    /// Create a client of the server at `base_url`, such as `http://localhost:3000`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        let http = Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            http,
+            base_url: format!("{}{}", base_url.into().trim_end_matches('/'), API_V1),
+            api_key: None,
+        }
+    }
+
+    /// Send `api_key`, an API key or token, as a bearer credential with every request
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Make requests with `http`, such as one trusting another certificate or with another
+    /// timeout
+    pub fn with_http_client(mut self, http: Client) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// URL of the API, with its version prefix
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Check that the server is up
+    pub async fn health(&self) -> ClientResult<HealthResponse> {
+        json(self.send(self.http.get(self.url("/health"))).await?).await
+    }
+
+    /// Store a new context
+    pub async fn store(&self, request: &StoreContextRequest) -> ClientResult<ContextResponse> {
+        let builder = self.http.post(self.url("/contexts")).json(request);
+        json(self.send(builder).await?).await
+    }
+
+    /// Store a file as a new context, sent as a form rather than JSON
+    pub async fn upload(&self, upload: Upload) -> ClientResult<ContextResponse> {
+        let mut form = Form::new().part(
+            "file",
+            Part::bytes(upload.bytes).file_name(upload.file_name),
+        );
+        if let Some(source) = upload.source {
+            form = form.text("source", source);
+        }
+        if let Some(content_type) = upload.content_type {
+            form = form.text("content_type", content_type);
+        }
+        if !upload.tags.is_empty() {
+            form = form.text("tags", upload.tags.join(","));
+        }
+        if let Some(collection) = upload.collection {
+            form = form.text("collection", collection);
+        }
+        if upload.lossy {
+            form = form.text("lossy", "true");
+        }
+        let builder = self.http.post(self.url("/contexts/upload")).multipart(form);
+        json(self.send(builder).await?).await
+    }
+
+    /// Retrieve a context by ID
+    pub async fn get(&self, id: Uuid) -> ClientResult<ContextResponse> {
+        let builder = self.http.get(self.url(&format!("/contexts/{}", id)));
+        json(self.send(builder).await?).await
+    }
+
+    /// List a page of the contexts matching `params`' filters
+    pub async fn list(&self, params: ListContextsParams) -> ClientResult<ContextPage> {
+        let params = ListContextsParams {
+            envelope: true,
+            ..params
+        };
+        let builder = self.http.get(self.url("/contexts")).query(&params);
+        json(self.send(builder).await?).await
+    }
+
+    /// Search for contexts
+    pub async fn search(&self, request: &SearchRequest) -> ClientResult<SearchResponse> {
+        let builder = self.http.post(self.url("/search")).json(request);
+        json(self.send(builder).await?).await
+    }
+
+    /// Search with a query in the query string syntax, such as `outage tag:runbook`, only in
+    /// `collection` if given
+    pub async fn search_by_query(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        collection: Option<&str>,
+    ) -> ClientResult<SearchResponse> {
+        json(
+            self.send(self.query_search(query, limit, collection))
+                .await?,
+        )
+        .await
+    }
+
+    /// The server's rendering of a search, in `format` (`markdown`, `xml`, or `json`)
+    pub async fn render_search(
+        &self,
+        request: &SearchRequest,
+        format: &str,
+    ) -> ClientResult<String> {
+        let builder = self
+            .http
+            .post(self.url("/search"))
+            .query(&[("format", format)])
+            .json(request);
+        Ok(self.send(builder).await?.text().await?)
+    }
+
+    /// The server's rendering of a search in the query string syntax, in `format`
+    pub async fn render_search_by_query(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        collection: Option<&str>,
+        format: &str,
+    ) -> ClientResult<String> {
+        let builder = self
+            .query_search(query, limit, collection)
+            .query(&[("format", format)]);
+        Ok(self.send(builder).await?.text().await?)
+    }
+
+    /// Update an existing context
+    pub async fn update(
+        &self,
+        id: Uuid,
+        request: &UpdateContextRequest,
+    ) -> ClientResult<ContextResponse> {
+        let builder = self
+            .http
+            .put(self.url(&format!("/contexts/{}", id)))
+            .json(request);
+        json(self.send(builder).await?).await
+    }
+
+    /// Delete a context
+    pub async fn delete(&self, id: Uuid) -> ClientResult<()> {
+        let builder = self.http.delete(self.url(&format!("/contexts/{}", id)));
+        self.send(builder).await?;
+        Ok(())
+    }
+
+    /// Delete several contexts in one request, reporting what happened to each
+    pub async fn delete_many(&self, ids: Vec<Uuid>) -> ClientResult<DeleteContextsResponse> {
+        let builder = self
+            .http
+            .post(self.url("/contexts/delete"))
+            .json(&DeleteContextsRequest { ids });
+        json(self.send(builder).await?).await
+    }
+
+    /// Delete every context with all of `tags`, which the server only does with `confirm`
+    pub async fn delete_by_tags(
+        &self,
+        tags: &[String],
+        confirm: bool,
+    ) -> ClientResult<DeleteByTagsResponse> {
+        let builder = self
+            .http
+            .delete(self.url("/contexts"))
+            .query(&[("tags", tags.join(",")), ("confirm", confirm.to_string())]);
+        json(self.send(builder).await?).await
+    }
+
+    /// Retrieve contexts, or some of their chunks, by reference
+    pub async fn retrieve_by_references(
+        &self,
+        request: &ReferenceRequest,
+    ) -> ClientResult<SearchResponse> {
+        let builder = self.http.post(self.url("/references")).json(request);
+        json(self.send(builder).await?).await
+    }
+
+    /// Export every context, and with `include_chunks` their chunks, waiting up to `timeout`
+    /// for the whole export
+    pub async fn export(&self, include_chunks: bool, timeout: Duration) -> ClientResult<Export> {
+        let builder = self
+            .http
+            .get(self.url("/export"))
+            .query(&[("include_chunks", include_chunks)])
+            .timeout(timeout);
+        Ok(Export {
+            response: self.send(builder).await?,
+        })
+    }
+
+    /// Store a labelled evaluation dataset, replacing one with the same name
+    pub async fn store_eval_dataset(
+        &self,
+        dataset: &EvalDatasetRequest,
+    ) -> ClientResult<EvalDatasetResponse> {
+        let builder = self
+            .http
+            .post(self.url("/admin/eval/datasets"))
+            .json(dataset);
+        json(self.send(builder).await?).await
+    }
+
+    /// Run an evaluation dataset
+    pub async fn run_eval(&self, request: &EvalRunRequest) -> ClientResult<EvalRunResponse> {
+        let builder = self.http.post(self.url("/admin/eval/run")).json(request);
+        json(self.send(builder).await?).await
+    }
+
+    /// The evaluation runs made, oldest first, only those of `dataset` if given
+    pub async fn eval_runs(&self, dataset: Option<&str>) -> ClientResult<Vec<EvalRunResponse>> {
+        let builder = self
+            .http
+            .get(self.url("/admin/eval/runs"))
+            .query(&[("dataset", dataset)]);
+        json(self.send(builder).await?).await
+    }
+
+    /// Every collection, the default one first
+    pub async fn collections(&self) -> ClientResult<Vec<CollectionResponse>> {
+        json(self.send(self.http.get(self.url("/collections"))).await?).await
+    }
+
+    /// Create a collection
+    pub async fn create_collection(
+        &self,
+        request: &CreateCollectionRequest,
+    ) -> ClientResult<CollectionResponse> {
+        let builder = self.http.post(self.url("/collections")).json(request);
+        json(self.send(builder).await?).await
+    }
+
+    /// Delete a collection, which the server refuses while it holds contexts
+    pub async fn delete_collection(&self, id: Uuid) -> ClientResult<()> {
+        let builder = self.http.delete(self.url(&format!("/collections/{}", id)));
+        self.send(builder).await?;
+        Ok(())
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    fn query_search(
+        &self,
+        query: &str,
+        limit: Option<usize>,
+        collection: Option<&str>,
+    ) -> RequestBuilder {
+        self.http
+            .get(self.url("/search"))
+            .query(&[("q", query)])
+            .query(&[("limit", limit)])
+            .query(&[("collection", collection)])
+    }
+
+    /// Send a request with the API key, failing if its response isn't a success
+    async fn send(&self, mut builder: RequestBuilder) -> ClientResult<Response> {
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+        let response = builder.send().await?;
+        if response.status().is_success() {
+            return Ok(response);
+        }
+        Err(error_of(response).await)
+    }
+}
+
+async fn json<T: DeserializeOwned>(response: Response) -> ClientResult<T> {
+    Ok(response.json().await?)
+}
+
+/// The error a failed response reports, with the id of the request it belongs to
+async fn error_of(response: Response) -> ClientError {
+    let status = response.status();
+    let request_id = response
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = match response.text().await {
+        Ok(body) => body,
+        Err(err) => return err.into(),
+    };
+    match serde_json::from_str::<ErrorResponse>(&body) {
+        Ok(mut error) => {
+            error.request_id = error.request_id.or(request_id);
+            ClientError::Api { status, error }
+        }
+        Err(_) => ClientError::UnexpectedResponse { status, body },
+    }
+}
@@ -1,7 +1,12 @@
-use config::{Config, ConfigError, Environment, File};
+use config::builder::DefaultState;
+use config::{Config, ConfigBuilder, ConfigError, Environment, File};
 use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::path::Path;
 
+use crate::domain::service::{Bm25, ChunkAggregation, ChunkingStrategy, Fuzzy, Ranking};
+use crate::domain::{ChildPolicy, Highlighter, McpError, McpResult, PromptTemplate, TagPolicy};
+
 /// Configuration for the MCP server
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
@@ -13,6 +18,22 @@ pub struct AppConfig {
 
     /// Embedding configuration
     pub embedding: EmbeddingConfig,
+
+    /// Storage configuration
+    pub storage: StorageConfig,
+
+    /// Tag normalization configuration
+    pub tags: TagConfig,
+
+    /// URL ingestion configuration
+    pub ingest: IngestConfig,
+
+    /// Model Context Protocol configuration, for `mcp-server stdio`
+    #[serde(default)]
+    pub mcp: McpConfig,
+
+    /// Source and destination of the `migrate` subcommand (optional)
+    pub migrate: Option<MigrateConfig>,
 }
 
 /// Server configuration
@@ -24,21 +45,413 @@ pub struct ServerConfig {
     /// Port to listen on
     pub port: u16,
 
-    /// API key for authentication (optional)
+    /// How requests to the API authenticate (`api_key` or `jwt`)
+    pub auth: AuthMode,
+
+    /// API key for authentication (optional, used when `auth` is `api_key`)
     pub api_key: Option<String>,
+
+    /// Bearer token verification, used when `auth` is `jwt`
+    pub jwt: JwtConfig,
+
+    /// Secret used to sign context sharing links (optional, random per process if unset)
+    pub share_secret: Option<String>,
+
+    /// Sharing link configuration
+    pub share: ShareConfig,
+
+    /// Per-client request limits on the API
+    pub rate_limit: RateLimitConfig,
+
+    /// Certificate and key to serve HTTPS with (optional, plain HTTP if unset)
+    #[serde(default)]
+    pub tls: TlsConfig,
+
+    /// Also serve the API at its unversioned paths, as deprecated aliases of `/v1`
+    pub legacy_routes: bool,
+}
+
+/// Certificate and private key the server terminates TLS with
+#[derive(Debug, Default, Deserialize)]
+pub struct TlsConfig {
+    /// PEM file with the certificate chain, server certificate first
+    pub cert_path: Option<String>,
+
+    /// PEM file with the private key of the server certificate
+    pub key_path: Option<String>,
+}
+
+impl TlsConfig {
+    /// Certificate and key paths, or `None` if TLS is off; setting only one is an error
+    pub fn paths(&self) -> McpResult<Option<(&str, &str)>> {
+        match (self.cert_path.as_deref(), self.key_path.as_deref()) {
+            (Some(cert_path), Some(key_path)) => Ok(Some((cert_path, key_path))),
+            (None, None) => Ok(None),
+            _ => Err(McpError::ValidationError(
+                "server.tls.cert_path and server.tls.key_path must be set together".to_string(),
+            )),
+        }
+    }
+}
+
+/// Per-client request limits, set separately for reads, searches and writes
+#[derive(Debug, Deserialize)]
+pub struct RateLimitConfig {
+    /// Whether requests are limited
+    pub enabled: bool,
+
+    /// What identifies a client (`ip` or `api_key`)
+    pub key: RateLimitKey,
+
+    /// Limit on listing and fetching contexts and evaluation runs
+    pub reads: RateLimitRule,
+
+    /// Limit on searches, reference retrieval and evaluation runs
+    pub search: RateLimitRule,
+
+    /// Limit on storing, updating, deleting and sharing contexts and storing datasets
+    pub writes: RateLimitRule,
+
+    /// Limit on the requests an IP address fails to authenticate, applied before authentication
+    pub failed_auth: RateLimitRule,
+}
+
+/// A token bucket refilled at a steady rate
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimitRule {
+    /// Requests per second allowed per client
+    pub per_second: f64,
+
+    /// Number of requests a client can burst above the steady rate
+    pub burst: u32,
+}
+
+/// What identifies the client a request is counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// The client's IP address
+    Ip,
+    /// The API key or token subject, or the IP address of requests without credentials
+    ApiKey,
+}
+
+/// How requests to the API authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthMode {
+    /// A single shared key, required only when `api_key` is set
+    ApiKey,
+    /// Signed JSON Web Tokens identifying each caller
+    Jwt,
+}
+
+/// Configuration for verifying JSON Web Tokens
+#[derive(Debug, Deserialize)]
+pub struct JwtConfig {
+    /// Signature algorithm tokens must use
+    pub algorithm: JwtAlgorithm,
+
+    /// Shared secret (required for `HS256`)
+    pub secret: Option<String>,
+
+    /// PEM file with the public key (required for `RS256`)
+    pub public_key_path: Option<String>,
+
+    /// Required `iss` claim
+    pub issuer: Option<String>,
+
+    /// Required `aud` claim
+    pub audience: Option<String>,
+
+    /// Scope every token needs in its `scope` claim (optional)
+    pub required_scope: Option<String>,
+
+    /// Seconds of clock skew allowed when checking `exp`
+    pub leeway_seconds: u64,
+}
+
+/// Signature algorithm of JSON Web Tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum JwtAlgorithm {
+    /// HMAC with SHA-256 and a shared secret
+    HS256,
+    /// RSA with SHA-256, verified with a public key
+    RS256,
+}
+
+/// Configuration for signed, read-only context sharing links
+#[derive(Debug, Deserialize)]
+pub struct ShareConfig {
+    /// Lifetime of a sharing link in seconds when the request doesn't specify one
+    pub default_ttl_seconds: u64,
+
+    /// Maximum lifetime a sharing link can be issued for, in seconds
+    pub max_ttl_seconds: u64,
+
+    /// Requests per second allowed on the shared route, per client
+    pub rate_limit_per_second: f64,
+
+    /// Number of requests a client can burst above the steady rate
+    pub rate_limit_burst: u32,
+
+    /// File the issued links and their revocations are kept in (optional); links signed with
+    /// `server.share_secret` can't be revoked without it
+    pub links_path: Option<String>,
 }
 
 /// Context processing configuration
 #[derive(Debug, Deserialize)]
 pub struct ContextConfig {
-    /// Maximum size of a context chunk in characters
+    /// Maximum size of a context chunk, in `chunk_size_unit`s
     pub max_chunk_size: usize,
 
-    /// Overlap between chunks in characters
+    /// Overlap between chunks, in `chunk_size_unit`s
     pub chunk_overlap: usize,
 
+    /// What chunk sizes and overlaps count (`bytes` or `tokens`)
+    pub chunk_size_unit: ChunkSizeUnit,
+
+    /// Tokenizer counting tokens of chunks (`heuristic`, or `cl100k_base` or `o200k_base`
+    /// with the `tiktoken` feature)
+    pub tokenizer: TokenizerKind,
+
+    /// Where chunks end (`fixed` windows, or whole `sentence`s, `paragraph`s, `markdown`
+    /// sections or `code` items), unless the content type picks a strategy
+    pub chunking_strategy: ChunkingStrategy,
+
     /// Maximum number of results to return in searches
     pub max_results: usize,
+
+    /// Most contexts a single list request returns, and the number returned without a `limit`
+    pub max_page_size: usize,
+
+    /// Longest content a context can be stored or updated with, in bytes
+    pub max_content_bytes: usize,
+
+    /// Largest request body the API reads, in bytes
+    pub max_body_bytes: usize,
+
+    /// Most contexts a single batch delete can name
+    pub max_delete_batch: usize,
+
+    /// Prior states kept for each context, or 0 to keep no history
+    pub max_revisions: usize,
+
+    /// What deleting a context for good does to its children: `orphan` them, `cascade` to
+    /// them, or `reject` the delete
+    pub delete_children: ChildPolicy,
+
+    /// Seconds between sweeps deleting expired contexts from storage, or 0 to keep them
+    pub expiry_sweep_seconds: u64,
+
+    /// Seconds a soft-deleted context is kept before a sweep deletes it for good, or 0 to
+    /// keep it until it is deleted by hand
+    pub deleted_retention_seconds: u64,
+
+    /// Maximum number of contexts the memory backend holds (optional, unlimited if unset)
+    pub max_contexts: Option<usize>,
+
+    /// What to do with a new context once `max_contexts` is reached (`evict` or `reject`)
+    pub capacity_policy: String,
+
+    /// How search results are scored against the query
+    pub ranking: RankingConfig,
+
+    /// Weight of vector similarity against the lexical score in search, from 0.0 to 1.0
+    pub hybrid_alpha: f32,
+
+    /// Minimum relevance score of a search match, unless the request sets one (optional)
+    pub min_score: Option<f32>,
+
+    /// Snippets of search matches returned on request
+    pub highlight: HighlightConfig,
+
+    /// Second-stage reordering of search matches
+    pub rerank: RerankConfig,
+
+    /// Synonyms and full forms searched for along with query terms
+    pub expansion: ExpansionConfig,
+}
+
+/// Search snippet configuration
+#[derive(Debug, Deserialize)]
+pub struct HighlightConfig {
+    /// Marker inserted before a matched term
+    pub pre_tag: String,
+
+    /// Marker inserted after a matched term
+    pub post_tag: String,
+
+    /// Maximum number of snippets per match
+    pub max_snippets: usize,
+
+    /// Characters kept on either side of a matched term
+    pub context_chars: usize,
+}
+
+impl HighlightConfig {
+    /// Build the highlighter described by this configuration
+    pub fn highlighter(&self) -> Highlighter {
+        Highlighter {
+            pre_tag: self.pre_tag.clone(),
+            post_tag: self.post_tag.clone(),
+            max_snippets: self.max_snippets,
+            context_chars: self.context_chars,
+        }
+    }
+}
+
+/// Query expansion configuration
+#[derive(Debug, Deserialize)]
+pub struct ExpansionConfig {
+    /// Whether searches are expanded unless the request says otherwise
+    pub enabled: bool,
+
+    /// TOML or JSON file mapping terms to their alternatives (required when enabled)
+    pub synonyms_path: Option<String>,
+
+    /// Share of a query term's score an expansion term is worth
+    pub weight: f32,
+}
+
+/// Search reranking configuration
+#[derive(Debug, Deserialize)]
+pub struct RerankConfig {
+    /// Whether search matches are reranked before they are returned
+    pub enabled: bool,
+
+    /// Reranker to use
+    pub provider: RerankProvider,
+
+    /// API key (required for the `cohere` provider)
+    pub api_key: Option<String>,
+
+    /// Reranking model requested from the API
+    pub model: String,
+
+    /// Base URL of the API
+    pub api_base: String,
+}
+
+impl RerankConfig {
+    /// Description of the reranker searches use, or `none`
+    fn fingerprint(&self) -> String {
+        match (self.enabled, self.provider) {
+            (false, _) => "none".to_string(),
+            (true, RerankProvider::PassThrough) => "passthrough".to_string(),
+            (true, RerankProvider::Cohere) => format!("cohere:{}", self.model),
+        }
+    }
+}
+
+/// What chunk sizes and overlaps are measured in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkSizeUnit {
+    /// Bytes of UTF-8 content
+    Bytes,
+    /// Tokens counted by `context.tokenizer`
+    Tokens,
+}
+
+/// Tokenizer counting the tokens of chunks
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenizerKind {
+    /// Approximation of BPE tokenizers that needs no vocabulary
+    Heuristic,
+    /// The OpenAI `cl100k_base` encoding, of GPT-4 and `text-embedding-3` models
+    Cl100kBase,
+    /// The OpenAI `o200k_base` encoding, of GPT-4o models
+    O200kBase,
+}
+
+impl TokenizerKind {
+    /// Name of the tokenizer as configured
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Heuristic => "heuristic",
+            Self::Cl100kBase => "cl100k_base",
+            Self::O200kBase => "o200k_base",
+        }
+    }
+}
+
+/// Service that reorders search matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RerankProvider {
+    /// Keeps the first-stage order and scores
+    PassThrough,
+    /// Hosted cross-encoder with a Cohere Rerank-style API
+    Cohere,
+}
+
+/// Lexical ranking configuration
+#[derive(Debug, Deserialize)]
+pub struct RankingConfig {
+    /// Scoring algorithm (`bm25` or `term_match`)
+    pub algorithm: RankingAlgorithm,
+
+    /// BM25 term frequency saturation
+    pub k1: f32,
+
+    /// BM25 length normalization, from 0.0 (none) to 1.0 (full)
+    pub b: f32,
+
+    /// How BM25 chunk scores make up a context's score (`max` or `sum`)
+    pub aggregation: ChunkAggregation,
+
+    /// Matching of query terms with typos
+    pub fuzzy: FuzzyConfig,
+}
+
+/// Fuzzy term matching configuration
+#[derive(Debug, Deserialize)]
+pub struct FuzzyConfig {
+    /// Whether searches match fuzzily unless the request says otherwise
+    pub enabled: bool,
+
+    /// Most character edits between a query term and a term it matches
+    pub max_edits: usize,
+
+    /// Shortest term, in characters, matched fuzzily
+    pub min_term_length: usize,
+
+    /// Share of an exact match's score a fuzzy match is worth
+    pub weight: f32,
+}
+
+impl FuzzyConfig {
+    /// Fuzzy matching described by this configuration
+    pub fn fuzzy(&self) -> Fuzzy {
+        Fuzzy {
+            max_edits: self.max_edits,
+            min_term_length: self.min_term_length,
+            weight: self.weight,
+        }
+    }
+}
+
+/// Lexical scoring algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankingAlgorithm {
+    /// Fraction of the query terms found in the context
+    TermMatch,
+    /// Okapi BM25 over the context's chunks
+    Bm25,
+}
+
+impl RankingConfig {
+    /// Ranking described by this configuration
+    pub fn ranking(&self) -> Ranking {
+        match self.algorithm {
+            RankingAlgorithm::TermMatch => Ranking::TermMatch,
+            RankingAlgorithm::Bm25 => Ranking::Bm25(Bm25::new(self.k1, self.b, self.aggregation)),
+        }
+    }
 }
 
 /// Embedding configuration
@@ -46,20 +459,495 @@ pub struct ContextConfig {
 pub struct EmbeddingConfig {
     /// Dimension of embeddings to use
     pub dimension: usize,
+
+    /// Embedding provider to use
+    pub provider: EmbeddingProvider,
+
+    /// Number of chunk embeddings of remote and model providers cached by content (0 disables)
+    pub cache_size: usize,
+
+    /// Number of chunks remote and model providers embed per request
+    pub batch_size: usize,
+
+    /// Number of embedding requests of one call that may be in flight at once
+    pub max_concurrency: usize,
+
+    /// Number of times remote and model providers attempt a call failing transiently
+    pub max_attempts: u32,
+
+    /// Delay before the first retry, doubled for each one after
+    pub retry_base_delay_ms: u64,
+
+    /// Index the vectors of every provider but `tfidf` are searched in
+    pub index: VectorIndexConfig,
+
+    /// Where the vectors of every provider but `tfidf` are stored
+    pub vector_store: VectorStoreConfig,
+
+    /// Settings of the `tfidf` provider
+    #[serde(default)]
+    pub tfidf: TfIdfConfig,
+
+    /// Settings of the `openai` provider
+    pub openai: OpenAiConfig,
+
+    /// Settings of the `cohere` provider
+    pub cohere: CohereConfig,
+
+    /// Settings of the `huggingface` provider
+    pub huggingface: HuggingFaceConfig,
+
+    /// Settings of the `ollama` provider
+    pub ollama: OllamaConfig,
+
+    /// Settings of the `fastembed` provider
+    pub fastembed: FastEmbedConfig,
+}
+
+/// Service that turns chunks and queries into vectors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProvider {
+    Simple,
+    TfIdf,
+    OpenAi,
+    Cohere,
+    HuggingFace,
+    Ollama,
+    /// Only available when built with the `fastembed` feature
+    FastEmbed,
+}
+
+impl EmbeddingProvider {
+    /// Name of the provider in configuration
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Simple => "simple",
+            Self::TfIdf => "tfidf",
+            Self::OpenAi => "openai",
+            Self::Cohere => "cohere",
+            Self::HuggingFace => "huggingface",
+            Self::Ollama => "ollama",
+            Self::FastEmbed => "fastembed",
+        }
+    }
+}
+
+impl std::fmt::Display for EmbeddingProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl EmbeddingConfig {
+    /// Model of the selected provider, if it has one
+    pub fn model(&self) -> Option<&str> {
+        match self.provider {
+            EmbeddingProvider::Simple | EmbeddingProvider::TfIdf => None,
+            EmbeddingProvider::OpenAi => Some(&self.openai.model),
+            EmbeddingProvider::Cohere => Some(&self.cohere.model),
+            EmbeddingProvider::HuggingFace => Some(&self.huggingface.model),
+            EmbeddingProvider::Ollama => Some(&self.ollama.model),
+            EmbeddingProvider::FastEmbed => Some(&self.fastembed.model),
+        }
+    }
+}
+
+/// Vector index configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorIndexConfig {
+    /// How the index is searched
+    pub kind: VectorIndexKind,
+
+    /// Links per chunk and layer of the HNSW graph, twice as many on the bottom layer
+    pub m: usize,
+
+    /// Candidates considered while linking a chunk into the graph
+    pub ef_construction: usize,
+
+    /// Candidates considered per search, at least as many as the results asked for
+    pub ef_search: usize,
+}
+
+/// Search algorithm of the vector index
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorIndexKind {
+    /// Compare the query to every chunk, which is exact but slow with many chunks
+    Exact,
+    /// Approximate nearest neighbor search through an HNSW graph
+    Hnsw,
+}
+
+/// Vector store configuration
+#[derive(Debug, Clone, Deserialize)]
+pub struct VectorStoreConfig {
+    /// Store backend (`memory`, or `lance` when built with the feature)
+    pub backend: VectorStoreBackend,
+
+    /// Directory of the LanceDB dataset
+    pub lance_path: String,
+}
+
+/// Where chunk vectors are stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VectorStoreBackend {
+    /// In process, in the index `embedding.index` configures
+    Memory,
+    /// In a LanceDB dataset on disk; only available when built with the `lancedb` feature
+    Lance,
+}
+
+/// TF-IDF embedding provider configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct TfIdfConfig {
+    /// File the vocabulary is kept in (optional, kept in memory only if unset)
+    pub path: Option<String>,
+}
+
+/// OpenAI embedding provider configuration
+#[derive(Debug, Deserialize)]
+pub struct OpenAiConfig {
+    /// API key (required for the `openai` provider)
+    pub api_key: Option<String>,
+
+    /// Embedding model requested from the API
+    pub model: String,
+
+    /// Base URL of the API
+    pub api_base: String,
+}
+
+/// Cohere embedding provider configuration
+#[derive(Debug, Deserialize)]
+pub struct CohereConfig {
+    /// API key (required for the `cohere` provider)
+    pub api_key: Option<String>,
+
+    /// Embedding model, whose vectors must be `embedding.dimension` long
+    pub model: String,
+
+    /// Base URL of the API
+    pub api_base: String,
+
+    /// Number of texts sent per request, at most 96
+    pub batch_size: usize,
+}
+
+/// Hugging Face Inference API embedding provider configuration
+#[derive(Debug, Deserialize)]
+pub struct HuggingFaceConfig {
+    /// Access token (required for the `huggingface` provider)
+    pub api_key: Option<String>,
+
+    /// Model id on the Hub, served by the `feature-extraction` pipeline
+    pub model: String,
+
+    /// Base URL of the inference API
+    pub api_base: String,
+
+    /// Number of times a request is retried while the model is loading
+    pub max_retries: u32,
+}
+
+/// Ollama embedding provider configuration
+#[derive(Debug, Deserialize)]
+pub struct OllamaConfig {
+    /// Embedding model, which must already be pulled
+    pub model: String,
+
+    /// Base URL of the Ollama server
+    pub api_base: String,
+}
+
+/// In-process `fastembed` embedding provider configuration
+#[derive(Debug, Deserialize)]
+pub struct FastEmbedConfig {
+    /// Model code of the ONNX model
+    pub model: String,
+
+    /// Directory the model files are cached in
+    pub model_path: String,
+}
+
+/// Storage configuration
+#[derive(Debug, Deserialize)]
+pub struct StorageConfig {
+    /// Repository backend to use (`memory`, or `rocksdb`/`mongodb` when built with the feature)
+    pub backend: String,
+
+    /// Write-ahead log for the memory backend (optional, no durability if unset)
+    pub wal_path: Option<String>,
+
+    /// Size in bytes after which the write-ahead log is compacted
+    pub wal_compact_bytes: u64,
+
+    /// Directory of the RocksDB database
+    pub rocksdb_path: String,
+
+    /// Connection string of the MongoDB deployment
+    pub mongodb_uri: String,
+
+    /// Name of the MongoDB database
+    pub mongodb_database: String,
+
+    /// Secondary repository to mirror writes to (optional)
+    pub shadow: Option<ShadowConfig>,
+}
+
+/// Dual-write configuration for migrating between storage backends
+#[derive(Debug, Deserialize)]
+pub struct ShadowConfig {
+    /// Backend of the secondary repository
+    pub backend: String,
+
+    /// Write-ahead log of a memory secondary (optional)
+    pub wal_path: Option<String>,
+
+    /// RocksDB directory of the secondary (optional, falls back to the primary's)
+    pub rocksdb_path: Option<String>,
+
+    /// MongoDB connection string of the secondary (optional, falls back to the primary's)
+    pub mongodb_uri: Option<String>,
+
+    /// MongoDB database of the secondary (optional, falls back to the primary's)
+    pub mongodb_database: Option<String>,
+
+    /// Number of mutations that can wait for the secondary before new ones are dropped
+    #[serde(default = "default_shadow_queue_capacity")]
+    pub queue_capacity: usize,
+
+    /// Fraction of reads compared against the secondary (0.0 to 1.0)
+    #[serde(default = "default_shadow_read_sample_rate")]
+    pub read_sample_rate: f64,
+
+    /// Interval in seconds between logging the shadow counters
+    #[serde(default = "default_shadow_report_interval")]
+    pub report_interval_seconds: u64,
+}
+
+fn default_shadow_queue_capacity() -> usize {
+    10_000
+}
+
+fn default_shadow_read_sample_rate() -> f64 {
+    0.01
+}
+
+fn default_shadow_report_interval() -> u64 {
+    60
+}
+
+impl ShadowConfig {
+    /// Storage configuration of the secondary repository
+    pub fn secondary(&self, primary: &StorageConfig) -> StorageConfig {
+        StorageConfig {
+            backend: self.backend.clone(),
+            wal_path: self.wal_path.clone(),
+            wal_compact_bytes: primary.wal_compact_bytes,
+            rocksdb_path: self
+                .rocksdb_path
+                .clone()
+                .unwrap_or_else(|| primary.rocksdb_path.clone()),
+            mongodb_uri: self
+                .mongodb_uri
+                .clone()
+                .unwrap_or_else(|| primary.mongodb_uri.clone()),
+            mongodb_database: self
+                .mongodb_database
+                .clone()
+                .unwrap_or_else(|| primary.mongodb_database.clone()),
+            shadow: None,
+        }
+    }
+}
+
+/// Repositories the `migrate` subcommand copies between
+#[derive(Debug, Deserialize)]
+pub struct MigrateConfig {
+    /// Repository to read contexts from
+    pub source: RepositoryConfig,
+
+    /// Repository to copy contexts into
+    pub destination: RepositoryConfig,
+
+    /// Number of contexts read from the source at a time
+    #[serde(default = "default_migrate_page_size")]
+    pub page_size: usize,
+}
+
+fn default_migrate_page_size() -> usize {
+    100
+}
+
+/// A storage backend whose unset settings fall back to the `[storage]` section
+#[derive(Debug, Deserialize)]
+pub struct RepositoryConfig {
+    /// Repository backend to use
+    pub backend: String,
+
+    /// Write-ahead log of a memory repository (optional)
+    pub wal_path: Option<String>,
+
+    /// RocksDB directory (optional)
+    pub rocksdb_path: Option<String>,
+
+    /// MongoDB connection string (optional)
+    pub mongodb_uri: Option<String>,
+
+    /// MongoDB database (optional)
+    pub mongodb_database: Option<String>,
+}
+
+impl RepositoryConfig {
+    /// Storage configuration of this repository, without any shadow
+    pub fn storage(&self, defaults: &StorageConfig) -> StorageConfig {
+        StorageConfig {
+            backend: self.backend.clone(),
+            wal_path: self.wal_path.clone(),
+            wal_compact_bytes: defaults.wal_compact_bytes,
+            rocksdb_path: self
+                .rocksdb_path
+                .clone()
+                .unwrap_or_else(|| defaults.rocksdb_path.clone()),
+            mongodb_uri: self
+                .mongodb_uri
+                .clone()
+                .unwrap_or_else(|| defaults.mongodb_uri.clone()),
+            mongodb_database: self
+                .mongodb_database
+                .clone()
+                .unwrap_or_else(|| defaults.mongodb_database.clone()),
+            shadow: None,
+        }
+    }
+}
+
+/// Tag normalization configuration
+#[derive(Debug, Deserialize)]
+pub struct TagConfig {
+    /// Lowercase tags
+    pub lowercase: bool,
+
+    /// Strip leading and trailing whitespace
+    pub trim: bool,
+
+    /// Replace internal whitespace with `-`
+    pub collapse_whitespace: bool,
+
+    /// Maximum tag length in characters (optional)
+    pub max_length: Option<usize>,
+
+    /// Regex a tag must match in full (optional)
+    pub allowed_pattern: Option<String>,
+
+    /// Maximum number of tags on a context (optional)
+    pub max_per_context: Option<usize>,
+
+    /// Number of stored contexts checked against the policy at startup
+    pub startup_sample_size: usize,
+}
+
+/// URL ingestion configuration
+#[derive(Debug, Deserialize)]
+pub struct IngestConfig {
+    /// Seconds a fetch may take in all, redirects included
+    pub timeout_seconds: u64,
+
+    /// Largest document fetched, in bytes
+    pub max_bytes: usize,
+
+    /// Redirects followed before giving up
+    pub max_redirects: usize,
+
+    /// Allow fetching from loopback, private and link-local addresses, which are refused
+    /// so clients can't reach internal services through the server
+    pub allow_private_addresses: bool,
+}
+
+/// Model Context Protocol configuration
+#[derive(Debug, Default, Deserialize)]
+pub struct McpConfig {
+    /// TOML or JSON file of the prompt templates offered to clients (optional, none if unset)
+    pub prompts_path: Option<String>,
+
+    /// Protocol revisions accepted from clients, most preferred first (optional, the
+    /// revision the server implements if empty)
+    #[serde(default)]
+    pub protocol_versions: Vec<String>,
+}
+
+/// Contents of a prompts file
+#[derive(Debug, Deserialize)]
+struct PromptsFile {
+    #[serde(default)]
+    prompts: Vec<PromptTemplate>,
+}
+
+impl McpConfig {
+    /// Load and check the prompt templates in `prompts_path`, if one is configured
+    pub fn prompts(&self) -> McpResult<Vec<PromptTemplate>> {
+        let Some(path) = &self.prompts_path else {
+            return Ok(Vec::new());
+        };
+        let path = Path::new(path);
+        if !path.is_file() {
+            return Err(McpError::ValidationError(format!(
+                "Prompts file {} does not exist",
+                path.display()
+            )));
+        }
+
+        let prompts = Config::builder()
+            .add_source(File::from(path))
+            .build()
+            .and_then(|prompts| prompts.try_deserialize::<PromptsFile>())
+            .map_err(|e| {
+                McpError::SerializationError(format!(
+                    "Invalid prompts file {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?
+            .prompts;
+
+        for (i, prompt) in prompts.iter().enumerate() {
+            prompt.validate()?;
+            if prompts[..i].iter().any(|other| other.name == prompt.name) {
+                return Err(McpError::ValidationError(format!(
+                    "Prompt {} is defined more than once",
+                    prompt.name
+                )));
+            }
+        }
+        Ok(prompts)
+    }
+}
+
+impl TagConfig {
+    /// Build the tag policy described by this configuration
+    pub fn policy(&self) -> McpResult<TagPolicy> {
+        let policy = TagPolicy {
+            lowercase: self.lowercase,
+            trim: self.trim,
+            collapse_whitespace: self.collapse_whitespace,
+            max_length: self.max_length,
+            allowed_pattern: None,
+            max_tags: self.max_per_context,
+        };
+
+        match &self.allowed_pattern {
+            Some(pattern) => policy.with_allowed_pattern(pattern),
+            None => Ok(policy),
+        }
+    }
 }
 
 impl AppConfig {
     /// Load configuration from file and environment variables
     pub fn load() -> Result<Self, ConfigError> {
-        // Set default configuration
-        let config = Config::builder()
-            // Start with defaults
-            .set_default("server.host", "127.0.0.1")?
-            .set_default("server.port", 3000)?
-            .set_default("context.max_chunk_size", 1000)?
-            .set_default("context.chunk_overlap", 200)?
-            .set_default("context.max_results", 10)?
-            .set_default("embedding.dimension", 768)?
+        let config = Self::defaults()?
             // Load from config file if it exists
             .add_source(File::from(Path::new("config/default.toml")).required(false))
             // Override with environment variables (e.g., MCP_SERVER__PORT=8080)
@@ -67,6 +955,240 @@ impl AppConfig {
             .build()?;
 
         // Deserialize into AppConfig
-        config.try_deserialize()
+        let config: Self = config.try_deserialize()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Build the default configuration, ignoring files and environment variables
+    pub fn load_defaults() -> Result<Self, ConfigError> {
+        Self::defaults()?.build()?.try_deserialize()
+    }
+
+    /// Reject settings the server can't run with, naming the offending key and value
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let context = &self.context;
+        for (key, value) in [
+            ("context.max_chunk_size", context.max_chunk_size),
+            ("context.max_results", context.max_results),
+            ("embedding.dimension", self.embedding.dimension),
+        ] {
+            if value == 0 {
+                return Err(ConfigError::Message(format!(
+                    "{} must be greater than 0, got {}",
+                    key, value
+                )));
+            }
+        }
+
+        // Chunks could never move past the start of the content
+        if context.chunk_overlap >= context.max_chunk_size {
+            return Err(ConfigError::Message(format!(
+                "context.chunk_overlap must be smaller than context.max_chunk_size ({}), got {}",
+                context.max_chunk_size, context.chunk_overlap
+            )));
+        }
+        Ok(())
+    }
+
+    /// Short fingerprint of the settings that affect retrieval, used to label evaluation runs
+    pub fn fingerprint(&self) -> String {
+        let settings = format!(
+            "backend={};max_chunk_size={};chunk_overlap={};chunk_size_unit={:?};tokenizer={};chunking={:?};max_results={};ranking={:?};fuzzy={:?};expansion={:?};rerank={};hybrid_alpha={};min_score={:?};provider={};model={};dimension={}",
+            self.storage.backend,
+            self.context.max_chunk_size,
+            self.context.chunk_overlap,
+            self.context.chunk_size_unit,
+            self.context.tokenizer.as_str(),
+            self.context.chunking_strategy,
+            self.context.max_results,
+            self.context.ranking.ranking(),
+            self.context
+                .ranking
+                .fuzzy
+                .enabled
+                .then(|| self.context.ranking.fuzzy.fuzzy()),
+            self.context.expansion.enabled.then_some((
+                &self.context.expansion.synonyms_path,
+                self.context.expansion.weight
+            )),
+            self.context.rerank.fingerprint(),
+            self.context.hybrid_alpha,
+            self.context.min_score,
+            self.embedding.provider,
+            self.embedding.model().unwrap_or_default(),
+            self.embedding.dimension,
+        );
+        hex::encode(&Sha256::digest(settings.as_bytes())[..6])
+    }
+
+    fn defaults() -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+        Config::builder()
+            .set_default("server.host", "127.0.0.1")?
+            .set_default("server.port", 3000)?
+            .set_default("server.auth", "api_key")?
+            .set_default("server.legacy_routes", true)?
+            .set_default("server.jwt.algorithm", "HS256")?
+            .set_default("server.jwt.leeway_seconds", 60)?
+            .set_default("server.share.default_ttl_seconds", 86400)?
+            .set_default("server.share.max_ttl_seconds", 604800)?
+            .set_default("server.share.rate_limit_per_second", 1.0)?
+            .set_default("server.share.rate_limit_burst", 10)?
+            .set_default("server.rate_limit.enabled", false)?
+            .set_default("server.rate_limit.key", "ip")?
+            .set_default("server.rate_limit.reads.per_second", 50.0)?
+            .set_default("server.rate_limit.reads.burst", 100)?
+            .set_default("server.rate_limit.search.per_second", 10.0)?
+            .set_default("server.rate_limit.search.burst", 20)?
+            .set_default("server.rate_limit.writes.per_second", 5.0)?
+            .set_default("server.rate_limit.writes.burst", 10)?
+            .set_default("server.rate_limit.failed_auth.per_second", 1.0)?
+            .set_default("server.rate_limit.failed_auth.burst", 10)?
+            .set_default("context.max_chunk_size", 1000)?
+            .set_default("context.chunk_overlap", 200)?
+            .set_default("context.chunk_size_unit", "bytes")?
+            .set_default("context.tokenizer", "heuristic")?
+            .set_default("context.chunking_strategy", "fixed")?
+            .set_default("context.max_results", 10)?
+            .set_default("context.max_page_size", 100)?
+            .set_default("context.max_content_bytes", 5 * 1024 * 1024)?
+            .set_default("context.max_body_bytes", 10 * 1024 * 1024)?
+            .set_default("context.max_delete_batch", 100)?
+            .set_default("context.max_revisions", 10)?
+            .set_default("context.delete_children", "orphan")?
+            .set_default("context.expiry_sweep_seconds", 60)?
+            .set_default("context.deleted_retention_seconds", 7 * 24 * 60 * 60)?
+            .set_default("context.capacity_policy", "evict")?
+            .set_default("context.ranking.algorithm", "bm25")?
+            .set_default("context.ranking.k1", 1.2)?
+            .set_default("context.ranking.b", 0.75)?
+            .set_default("context.ranking.aggregation", "max")?
+            .set_default("context.ranking.fuzzy.enabled", false)?
+            .set_default("context.ranking.fuzzy.max_edits", 2)?
+            .set_default("context.ranking.fuzzy.min_term_length", 5)?
+            .set_default("context.ranking.fuzzy.weight", 0.5)?
+            .set_default("context.hybrid_alpha", 0.5)?
+            .set_default("context.highlight.pre_tag", "<em>")?
+            .set_default("context.highlight.post_tag", "</em>")?
+            .set_default("context.highlight.max_snippets", 3)?
+            .set_default("context.highlight.context_chars", 40)?
+            .set_default("context.expansion.enabled", false)?
+            .set_default("context.expansion.weight", 0.5)?
+            .set_default("context.rerank.enabled", false)?
+            .set_default("context.rerank.provider", "passthrough")?
+            .set_default("context.rerank.model", "rerank-english-v3.0")?
+            .set_default("context.rerank.api_base", "https://api.cohere.ai/v1")?
+            .set_default("embedding.dimension", 768)?
+            .set_default("embedding.provider", "simple")?
+            .set_default("embedding.cache_size", 10_000)?
+            .set_default("embedding.batch_size", 32)?
+            .set_default("embedding.max_concurrency", 4)?
+            .set_default("embedding.max_attempts", 3)?
+            .set_default("embedding.retry_base_delay_ms", 500)?
+            .set_default("embedding.index.kind", "hnsw")?
+            .set_default("embedding.index.m", 16)?
+            .set_default("embedding.index.ef_construction", 200)?
+            .set_default("embedding.index.ef_search", 64)?
+            .set_default("embedding.vector_store.backend", "memory")?
+            .set_default("embedding.vector_store.lance_path", "data/lance")?
+            .set_default("embedding.openai.model", "text-embedding-3-small")?
+            .set_default("embedding.openai.api_base", "https://api.openai.com/v1")?
+            .set_default("embedding.cohere.model", "embed-english-v3.0")?
+            .set_default("embedding.cohere.api_base", "https://api.cohere.ai/v1")?
+            .set_default("embedding.cohere.batch_size", 96)?
+            .set_default(
+                "embedding.huggingface.model",
+                "sentence-transformers/all-MiniLM-L6-v2",
+            )?
+            .set_default(
+                "embedding.huggingface.api_base",
+                "https://api-inference.huggingface.co",
+            )?
+            .set_default("embedding.huggingface.max_retries", 3)?
+            .set_default("embedding.ollama.model", "nomic-embed-text")?
+            .set_default("embedding.ollama.api_base", "http://localhost:11434")?
+            .set_default(
+                "embedding.fastembed.model",
+                "sentence-transformers/all-MiniLM-L6-v2",
+            )?
+            .set_default("embedding.fastembed.model_path", "data/models")?
+            .set_default("storage.backend", "memory")?
+            .set_default("storage.wal_compact_bytes", 64 * 1024 * 1024)?
+            .set_default("storage.rocksdb_path", "data/rocksdb")?
+            .set_default("storage.mongodb_uri", "mongodb://localhost:27017")?
+            .set_default("storage.mongodb_database", "mcp")?
+            .set_default("tags.lowercase", true)?
+            .set_default("tags.trim", true)?
+            .set_default("tags.collapse_whitespace", true)?
+            .set_default("tags.max_per_context", 64)?
+            .set_default("tags.startup_sample_size", 1000)?
+            .set_default("ingest.timeout_seconds", 10)?
+            .set_default("ingest.max_bytes", 5 * 1024 * 1024)?
+            .set_default("ingest.max_redirects", 5)?
+            .set_default("ingest.allow_private_addresses", false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validation_error(config: &AppConfig) -> String {
+        match config.validate() {
+            Err(ConfigError::Message(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_defaults_are_valid() {
+        AppConfig::load_defaults().unwrap().validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_overlap_not_smaller_than_chunk_size() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.context.max_chunk_size = 100;
+
+        config.context.chunk_overlap = 100;
+        assert_eq!(
+            validation_error(&config),
+            "context.chunk_overlap must be smaller than context.max_chunk_size (100), got 100"
+        );
+
+        config.context.chunk_overlap = 150;
+        assert_eq!(
+            validation_error(&config),
+            "context.chunk_overlap must be smaller than context.max_chunk_size (100), got 150"
+        );
+
+        // One less than the chunk size still moves every chunk forward
+        config.context.chunk_overlap = 99;
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_zero_sizes() {
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.context.max_chunk_size = 0;
+        config.context.chunk_overlap = 0;
+        assert_eq!(
+            validation_error(&config),
+            "context.max_chunk_size must be greater than 0, got 0"
+        );
+
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.context.max_results = 0;
+        assert_eq!(
+            validation_error(&config),
+            "context.max_results must be greater than 0, got 0"
+        );
+
+        let mut config = AppConfig::load_defaults().unwrap();
+        config.embedding.dimension = 0;
+        assert_eq!(
+            validation_error(&config),
+            "embedding.dimension must be greater than 0, got 0"
+        );
     }
 }
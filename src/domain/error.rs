@@ -11,12 +11,40 @@ pub enum McpError {
     #[error("Chunk not found: {0}")]
     ChunkNotFound(Uuid),
 
+    #[error("Revision {version} of context {context_id} not found")]
+    RevisionNotFound { context_id: Uuid, version: u64 },
+
+    #[error("Context {context_id} has no relation to {target_id}")]
+    RelationNotFound { context_id: Uuid, target_id: Uuid },
+
+    #[error("Context {context_id} has no sharing link {token_id}")]
+    ShareLinkNotFound { context_id: Uuid, token_id: Uuid },
+
     #[error("Invalid context reference: {0}")]
     InvalidContextReference(String),
 
     #[error("Context already exists: {0}")]
     ContextAlreadyExists(Uuid),
 
+    #[error("Context {0} has children")]
+    HasChildren(Uuid),
+
+    #[error("Collection not found: {0}")]
+    CollectionNotFound(Uuid),
+
+    #[error("Collection already exists: {0}")]
+    CollectionExists(String),
+
+    #[error("Collection {0} still holds contexts")]
+    CollectionNotEmpty(Uuid),
+
+    #[error("Context {context_id} is at version {actual}, not {expected}")]
+    VersionConflict {
+        context_id: Uuid,
+        expected: u64,
+        actual: u64,
+    },
+
     #[error("Storage error: {0}")]
     StorageError(String),
 
@@ -32,6 +60,9 @@ pub enum McpError {
     #[error("Request validation error: {0}")]
     ValidationError(String),
 
+    #[error("Request validation error: {}", join_messages(.0))]
+    ValidationFailed(Vec<FieldError>),
+
     #[error("Authentication error: {0}")]
     AuthenticationError(String),
 
@@ -44,6 +75,9 @@ pub enum McpError {
     #[error("Context limit exceeded")]
     ContextLimitExceeded,
 
+    #[error("Request body exceeds {0} bytes")]
+    PayloadTooLarge(usize),
+
     #[error("External service error: {0}")]
     ExternalServiceError(String),
 
@@ -51,5 +85,89 @@ pub enum McpError {
     Unknown(String),
 }
 
+impl McpError {
+    /// Error for an embedding that isn't as long as the configured dimension
+    pub fn dimension_mismatch(expected: usize, actual: usize) -> Self {
+        McpError::EmbeddingError(format!(
+            "dimension mismatch: expected {} got {}",
+            expected, actual
+        ))
+    }
+
+    /// Whether the failure is expected to clear up, so the operation may succeed if retried
+    pub fn is_transient(&self) -> bool {
+        match self {
+            McpError::ExternalServiceError(_) | McpError::RateLimitExceeded => true,
+            McpError::IoError(err) => matches!(
+                err.kind(),
+                io::ErrorKind::TimedOut
+                    | io::ErrorKind::Interrupted
+                    | io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+            ),
+            _ => false,
+        }
+    }
+}
+
+/// A problem with one field of a request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Name of the field, as the request spells it
+    pub field: String,
+
+    /// What is wrong with it
+    pub message: String,
+}
+
+/// Every problem found while validating a request, so they can be reported together
+#[derive(Debug, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    /// Note a problem with `field`
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.to_string(),
+            message: message.into(),
+        });
+    }
+
+    /// The value of `result`, or `None` after noting its validation error against `field`
+    ///
+    /// Errors other than validation errors are passed on straight away.
+    pub fn check<T>(&mut self, field: &str, result: McpResult<T>) -> McpResult<Option<T>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(McpError::ValidationError(message)) => {
+                self.add(field, message);
+                Ok(None)
+            }
+            Err(McpError::ValidationFailed(errors)) => {
+                self.0.extend(errors);
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Fail if any problem was noted; a single one stays a plain `ValidationError`
+    pub fn into_result(mut self) -> McpResult<()> {
+        match self.0.len() {
+            0 => Ok(()),
+            1 => Err(McpError::ValidationError(self.0.remove(0).message)),
+            _ => Err(McpError::ValidationFailed(self.0)),
+        }
+    }
+}
+
+fn join_messages(errors: &[FieldError]) -> String {
+    errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// Result type for MCP operations
 pub type McpResult<T> = Result<T, McpError>;
@@ -0,0 +1,253 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::error::{McpError, McpResult};
+use super::model::Context;
+
+/// A labelled query: contexts with one of the expected ids or tags are relevant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+    /// Query run through the search pipeline
+    pub query: String,
+
+    /// Contexts that should be returned for the query
+    #[serde(default)]
+    pub expected_ids: Vec<Uuid>,
+
+    /// Tags marking contexts that should be returned for the query
+    #[serde(default)]
+    pub expected_tags: Vec<String>,
+}
+
+impl EvalCase {
+    /// Check whether a retrieved context is relevant to this case
+    pub fn is_relevant(&self, context: &Context) -> bool {
+        self.expected_ids.contains(&context.id)
+            || context
+                .metadata
+                .tags
+                .iter()
+                .any(|tag| self.expected_tags.contains(tag))
+    }
+}
+
+/// A named set of labelled queries
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalDataset {
+    pub name: String,
+    pub cases: Vec<EvalCase>,
+}
+
+impl EvalDataset {
+    /// Check that the dataset has a name and every case has a query and something to expect
+    pub fn validate(&self) -> McpResult<()> {
+        if self.name.trim().is_empty() {
+            return Err(McpError::ValidationError(
+                "Dataset name must not be empty".to_string(),
+            ));
+        }
+        if self.cases.is_empty() {
+            return Err(McpError::ValidationError(format!(
+                "Dataset '{}' has no cases",
+                self.name
+            )));
+        }
+
+        for (index, case) in self.cases.iter().enumerate() {
+            if case.query.trim().is_empty() {
+                return Err(McpError::ValidationError(format!(
+                    "Case {} of dataset '{}' has an empty query",
+                    index, self.name
+                )));
+            }
+            if case.expected_ids.is_empty() && case.expected_tags.is_empty() {
+                return Err(McpError::ValidationError(format!(
+                    "Case {} of dataset '{}' expects no context ids or tags",
+                    index, self.name
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Retrieval quality metrics, averaged over the queries of a run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalMetrics {
+    /// Fraction of the relevant contexts found in the top k
+    pub recall_at_k: f64,
+
+    /// Mean reciprocal rank of the first relevant context in the top k
+    pub mrr: f64,
+
+    /// Normalized discounted cumulative gain of the top k
+    pub ndcg_at_k: f64,
+}
+
+impl EvalMetrics {
+    /// Metrics of a single query, given the relevance of each result in rank order
+    pub fn for_query(relevance: &[bool], total_relevant: usize, k: usize) -> Self {
+        Self {
+            recall_at_k: recall_at_k(relevance, total_relevant, k),
+            mrr: reciprocal_rank(relevance, k),
+            ndcg_at_k: ndcg_at_k(relevance, total_relevant, k),
+        }
+    }
+
+    /// Average per-query metrics, or all zeros if there are none
+    pub fn mean(metrics: &[EvalMetrics]) -> Self {
+        if metrics.is_empty() {
+            return Self::default();
+        }
+
+        let n = metrics.len() as f64;
+        Self {
+            recall_at_k: metrics.iter().map(|m| m.recall_at_k).sum::<f64>() / n,
+            mrr: metrics.iter().map(|m| m.mrr).sum::<f64>() / n,
+            ndcg_at_k: metrics.iter().map(|m| m.ndcg_at_k).sum::<f64>() / n,
+        }
+    }
+}
+
+/// The results of running a dataset through the search pipeline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalRun {
+    pub id: Uuid,
+    pub dataset: String,
+
+    /// Label identifying the configuration the run was made with
+    pub label: String,
+
+    /// Number of results considered per query
+    pub k: usize,
+
+    /// Number of queries evaluated
+    pub queries: usize,
+
+    pub metrics: EvalMetrics,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fraction of `total_relevant` contexts that appear in the top `k` results
+pub fn recall_at_k(relevance: &[bool], total_relevant: usize, k: usize) -> f64 {
+    if total_relevant == 0 {
+        return 0.0;
+    }
+
+    let found = relevance.iter().take(k).filter(|&&r| r).count();
+    found as f64 / total_relevant as f64
+}
+
+/// Reciprocal of the 1-based rank of the first relevant result in the top `k`
+pub fn reciprocal_rank(relevance: &[bool], k: usize) -> f64 {
+    relevance
+        .iter()
+        .take(k)
+        .position(|&r| r)
+        .map_or(0.0, |index| 1.0 / (index + 1) as f64)
+}
+
+/// Binary-relevance nDCG of the top `k`, against an ideal ranking of `total_relevant` hits
+pub fn ndcg_at_k(relevance: &[bool], total_relevant: usize, k: usize) -> f64 {
+    let discount = |index: usize| 1.0 / ((index + 2) as f64).log2();
+
+    let ideal: f64 = (0..total_relevant.min(k)).map(discount).sum();
+    if ideal == 0.0 {
+        return 0.0;
+    }
+
+    let dcg: f64 = relevance
+        .iter()
+        .take(k)
+        .enumerate()
+        .filter(|(_, relevant)| **relevant)
+        .map(|(index, _)| discount(index))
+        .sum();
+    dcg / ideal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-4,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_metrics_match_hand_computed_values() {
+        // Relevant results at ranks 2 and 4, three relevant contexts overall
+        let relevance = [false, true, false, true];
+
+        assert_close(recall_at_k(&relevance, 3, 4), 2.0 / 3.0);
+        assert_close(reciprocal_rank(&relevance, 4), 0.5);
+
+        // DCG = 1/log2(3) + 1/log2(5) = 1.06160; IDCG = 1 + 1/log2(3) + 1/log2(4) = 2.13093
+        assert_close(ndcg_at_k(&relevance, 3, 4), 0.49819);
+    }
+
+    #[test]
+    fn test_cutoff_limits_results_considered() {
+        let relevance = [false, false, true];
+
+        assert_close(recall_at_k(&relevance, 1, 2), 0.0);
+        assert_close(reciprocal_rank(&relevance, 2), 0.0);
+        assert_close(ndcg_at_k(&relevance, 1, 2), 0.0);
+
+        // A perfect ranking scores 1 everywhere
+        let perfect = EvalMetrics::for_query(&[true, true, false], 2, 3);
+        assert_eq!(
+            perfect,
+            EvalMetrics {
+                recall_at_k: 1.0,
+                mrr: 1.0,
+                ndcg_at_k: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_no_relevant_contexts_scores_zero() {
+        assert_eq!(recall_at_k(&[true], 0, 5), 0.0);
+        assert_eq!(ndcg_at_k(&[true], 0, 5), 0.0);
+        assert_eq!(EvalMetrics::mean(&[]), EvalMetrics::default());
+    }
+
+    #[test]
+    fn test_validate_rejects_cases_without_expectations() {
+        let mut dataset = EvalDataset {
+            name: "runbooks".to_string(),
+            cases: vec![EvalCase {
+                query: "restart the database".to_string(),
+                expected_ids: Vec::new(),
+                expected_tags: vec!["runbook".to_string()],
+            }],
+        };
+        assert!(dataset.validate().is_ok());
+
+        dataset.cases[0].expected_tags.clear();
+        assert!(matches!(
+            dataset.validate(),
+            Err(McpError::ValidationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_mean_averages_queries() {
+        let mean = EvalMetrics::mean(&[
+            EvalMetrics::for_query(&[true], 1, 1),
+            EvalMetrics::for_query(&[false], 1, 1),
+        ]);
+
+        assert_close(mean.recall_at_k, 0.5);
+        assert_close(mean.mrr, 0.5);
+        assert_close(mean.ndcg_at_k, 0.5);
+    }
+}
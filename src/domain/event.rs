@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::model::TagMode;
+
+/// What happened to a context
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextEventKind {
+    /// The context was stored, imported, or restored after a soft delete
+    Created,
+
+    /// The context's content or metadata was replaced
+    Updated,
+
+    /// The context was deleted, soft-deleted, or swept after expiring
+    Deleted,
+}
+
+impl ContextEventKind {
+    /// Name of the event, such as `context.created`
+    pub fn name(&self) -> &'static str {
+        match self {
+            ContextEventKind::Created => "context.created",
+            ContextEventKind::Updated => "context.updated",
+            ContextEventKind::Deleted => "context.deleted",
+        }
+    }
+}
+
+/// A change to a stored context
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextEvent {
+    /// What happened
+    pub kind: ContextEventKind,
+
+    /// ID of the context it happened to
+    pub context_id: Uuid,
+
+    /// Tags of the context, as it was stored after the change or before its deletion
+    pub tags: Vec<String>,
+
+    /// When it happened
+    pub at: DateTime<Utc>,
+}
+
+impl ContextEvent {
+    /// An event of `kind` for the context with `context_id` and `tags`, happening now
+    pub fn now(kind: ContextEventKind, context_id: Uuid, tags: Vec<String>) -> Self {
+        Self {
+            kind,
+            context_id,
+            tags,
+            at: Utc::now(),
+        }
+    }
+}
+
+/// Which context events a subscriber wants
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextEventFilter {
+    /// Tags the context must have, all or any of them by `tag_mode`
+    pub tags: Vec<String>,
+
+    /// Whether the context needs every one of `tags` or any of them
+    pub tag_mode: TagMode,
+
+    /// Kinds of event wanted; none means every kind
+    pub kinds: Vec<ContextEventKind>,
+}
+
+impl ContextEventFilter {
+    /// Whether `event` passes the filter
+    pub fn matches(&self, event: &ContextEvent) -> bool {
+        (self.kinds.is_empty() || self.kinds.contains(&event.kind))
+            && self.tag_mode.matches(&self.tags, &event.tags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: ContextEventKind, tags: &[&str]) -> ContextEvent {
+        let tags = tags.iter().map(|tag| tag.to_string()).collect();
+        ContextEvent::now(kind, Uuid::new_v4(), tags)
+    }
+
+    #[test]
+    fn test_event_filter_checks_kind_and_tags() {
+        let filter = ContextEventFilter {
+            tags: vec!["project-x".to_string()],
+            kinds: vec![ContextEventKind::Created, ContextEventKind::Deleted],
+            ..ContextEventFilter::default()
+        };
+        assert!(filter.matches(&event(ContextEventKind::Created, &["project-x", "ai"])));
+        assert!(filter.matches(&event(ContextEventKind::Deleted, &["project-x"])));
+        assert!(!filter.matches(&event(ContextEventKind::Updated, &["project-x"])));
+        assert!(!filter.matches(&event(ContextEventKind::Created, &["project-y"])));
+
+        // An empty filter lets everything through
+        assert!(ContextEventFilter::default().matches(&event(ContextEventKind::Updated, &[])));
+    }
+}
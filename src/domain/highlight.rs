@@ -0,0 +1,246 @@
+use std::collections::HashSet;
+
+/// Extracts the passages of a text around the query terms it contains
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlighter {
+    /// Inserted before every matched term
+    pub pre_tag: String,
+
+    /// Inserted after every matched term
+    pub post_tag: String,
+
+    /// Maximum number of snippets per text
+    pub max_snippets: usize,
+
+    /// Characters of context kept on either side of a matched term
+    pub context_chars: usize,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Self {
+            pre_tag: "<em>".to_string(),
+            post_tag: "</em>".to_string(),
+            max_snippets: 3,
+            context_chars: 40,
+        }
+    }
+}
+
+impl Highlighter {
+    /// Snippets of `text` around the words of `query` it contains, in the order they appear
+    ///
+    /// Terms are matched as whole words, ignoring case. Nearby matches share a snippet, and a
+    /// snippet that doesn't reach the start or end of the text is marked with `…` there.
+    pub fn snippets(&self, text: &str, query: &str) -> Vec<String> {
+        self.snippets_of(text, &matches(text, &terms(query)))
+    }
+
+    /// Snippets of whichever of `texts` contains the query terms most often
+    pub fn best_snippets<'a>(
+        &self,
+        texts: impl IntoIterator<Item = &'a str>,
+        query: &str,
+    ) -> Vec<String> {
+        let terms = terms(query);
+        let mut best: Option<(&str, Vec<(usize, usize)>)> = None;
+        for text in texts {
+            let found = matches(text, &terms);
+            if found.len() > best.as_ref().map_or(0, |(_, matches)| matches.len()) {
+                best = Some((text, found));
+            }
+        }
+
+        best.map(|(text, found)| self.snippets_of(text, &found))
+            .unwrap_or_default()
+    }
+
+    fn snippets_of(&self, text: &str, found: &[(usize, usize)]) -> Vec<String> {
+        // Byte range of each snippet, and of the terms it highlights
+        let mut windows: Vec<(usize, usize, Vec<(usize, usize)>)> = Vec::new();
+        for &(start, end) in found {
+            let from = chars_before(text, start, self.context_chars);
+            let to = chars_after(text, end, self.context_chars);
+
+            match windows.last_mut() {
+                Some(window) if from <= window.1 => {
+                    window.1 = window.1.max(to);
+                    window.2.push((start, end));
+                }
+                _ if windows.len() == self.max_snippets => break,
+                _ => windows.push((from, to, vec![(start, end)])),
+            }
+        }
+
+        windows
+            .into_iter()
+            .map(|(from, to, matches)| self.render(text, from, to, &matches))
+            .collect()
+    }
+
+    fn render(&self, text: &str, from: usize, to: usize, matches: &[(usize, usize)]) -> String {
+        let mut snippet = String::new();
+        if from > 0 {
+            snippet.push('…');
+        }
+
+        let mut position = from;
+        for &(start, end) in matches {
+            snippet.push_str(&text[position..start]);
+            snippet.push_str(&self.pre_tag);
+            snippet.push_str(&text[start..end]);
+            snippet.push_str(&self.post_tag);
+            position = end;
+        }
+        snippet.push_str(&text[position..to]);
+
+        if to < text.len() {
+            snippet.push('…');
+        }
+        snippet
+    }
+}
+
+/// Lowercased words of a query
+fn terms(query: &str) -> HashSet<String> {
+    words(query)
+        .into_iter()
+        .map(|(_, word)| word.to_lowercase())
+        .collect()
+}
+
+/// Byte ranges of the words of a text that are query terms
+fn matches(text: &str, terms: &HashSet<String>) -> Vec<(usize, usize)> {
+    words(text)
+        .into_iter()
+        .filter(|(_, word)| terms.contains(&word.to_lowercase()))
+        .map(|(start, word)| (start, start + word.len()))
+        .collect()
+}
+
+/// Runs of alphanumeric characters in a text, with their byte offsets
+fn words(text: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(index),
+            (false, Some(word_start)) => {
+                words.push((word_start, &text[word_start..index]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(word_start) = start {
+        words.push((word_start, &text[word_start..]));
+    }
+    words
+}
+
+/// Byte offset `count` characters before `position`, or the start of the text
+fn chars_before(text: &str, position: usize, count: usize) -> usize {
+    if count == 0 {
+        return position;
+    }
+    text[..position]
+        .char_indices()
+        .rev()
+        .nth(count - 1)
+        .map_or(0, |(index, _)| index)
+}
+
+/// Byte offset `count` characters after `position`, or the end of the text
+fn chars_after(text: &str, position: usize, count: usize) -> usize {
+    text[position..]
+        .char_indices()
+        .nth(count)
+        .map_or(text.len(), |(index, _)| position + index)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighter(max_snippets: usize, context_chars: usize) -> Highlighter {
+        Highlighter {
+            max_snippets,
+            context_chars,
+            ..Highlighter::default()
+        }
+    }
+
+    #[test]
+    fn test_terms_are_wrapped_in_the_markers() {
+        let snippets = Highlighter::default().snippets(
+            "The Payments service had an outage; payments resumed",
+            "payments outage",
+        );
+        assert_eq!(
+            snippets,
+            ["The <em>Payments</em> service had an <em>outage</em>; <em>payments</em> resumed"]
+        );
+
+        let custom = Highlighter {
+            pre_tag: "[".to_string(),
+            post_tag: "]".to_string(),
+            ..Highlighter::default()
+        };
+        assert_eq!(custom.snippets("an outage", "outage"), ["an [outage]"]);
+    }
+
+    #[test]
+    fn test_only_whole_words_match() {
+        let snippets = Highlighter::default().snippets("outages and timeouts", "outage out");
+        assert!(snippets.is_empty());
+    }
+
+    #[test]
+    fn test_distant_matches_get_their_own_snippets() {
+        let text = format!("alpha {} beta {} alpha", "x".repeat(30), "y".repeat(30));
+        let snippets = highlighter(2, 5).snippets(&text, "alpha beta");
+
+        assert_eq!(
+            snippets,
+            ["<em>alpha</em> xxxx…", "…xxxx <em>beta</em> yyyy…"]
+        );
+    }
+
+    #[test]
+    fn test_terms_at_the_start_and_end_of_a_chunk() {
+        let snippets = highlighter(3, 3).snippets("kafka lags behind kafka", "kafka");
+        assert_eq!(snippets, ["<em>kafka</em> la…", "…nd <em>kafka</em>"]);
+
+        // A text that is all match
+        assert_eq!(
+            highlighter(3, 10).snippets("kafka", "KAFKA"),
+            ["<em>kafka</em>"]
+        );
+    }
+
+    #[test]
+    fn test_best_text_has_the_most_matches() {
+        let chunks = ["nothing here", "one outage", "outage after outage"];
+        assert_eq!(
+            highlighter(3, 100).best_snippets(chunks, "outage"),
+            ["<em>outage</em> after <em>outage</em>"]
+        );
+        assert!(highlighter(3, 100)
+            .best_snippets(chunks, "kafka")
+            .is_empty());
+    }
+
+    #[test]
+    fn test_unicode_text_is_cut_on_character_boundaries() {
+        let text = "Économie: le café crème coûte trop cher à Zürich";
+        let snippets = highlighter(3, 4).snippets(text, "café zürich");
+        assert_eq!(
+            snippets,
+            ["… le <em>café</em> crè…", "…r à <em>Zürich</em>"]
+        );
+
+        // Multi-byte scripts without spaces around the term
+        let snippets = highlighter(3, 2).snippets("東京、大阪、京都", "大阪");
+        assert_eq!(snippets, ["…京、<em>大阪</em>、京…"]);
+    }
+}
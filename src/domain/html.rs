@@ -0,0 +1,181 @@
+/// Elements whose contents are never shown as text
+const HIDDEN_ELEMENTS: [&str; 5] = ["script", "style", "noscript", "template", "head"];
+
+/// Elements on a line of their own, set apart from the text around them
+const BLOCK_ELEMENTS: [&str; 19] = [
+    "p",
+    "div",
+    "hr",
+    "ul",
+    "ol",
+    "table",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "pre",
+    "blockquote",
+    "section",
+    "article",
+    "header",
+    "footer",
+    "title",
+];
+
+/// Elements that start a new line, such as list items
+const LINE_ELEMENTS: [&str; 5] = ["br", "li", "tr", "dt", "dd"];
+
+/// The readable text of an HTML document
+///
+/// Tags and comments are dropped, along with the contents of scripts, styles and the
+/// document head; block elements and list items start a new line and common entities are decoded. Runs of
+/// spaces collapse to one, and no more than one blank line is kept between paragraphs.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len() / 2);
+    let mut rest = html;
+    let mut hidden: Option<String> = None;
+
+    while let Some(start) = rest.find('<') {
+        if hidden.is_none() {
+            text.push_str(&decode_entities(&rest[..start]));
+        }
+        rest = &rest[start..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        // The title is the one part of the head worth keeping
+        let hides = !closing
+            && !tag.ends_with('/')
+            && HIDDEN_ELEMENTS.contains(&name.as_str())
+            && (name != "head" || !rest.contains("<title"));
+        match &hidden {
+            Some(element) if closing && name == *element => hidden = None,
+            Some(_) => {}
+            None if hides => hidden = Some(name),
+            None if BLOCK_ELEMENTS.contains(&name.as_str()) => text.push_str("\n\n"),
+            None if !closing && LINE_ELEMENTS.contains(&name.as_str()) => text.push('\n'),
+            None => {}
+        }
+    }
+    if hidden.is_none() {
+        text.push_str(&decode_entities(rest));
+    }
+
+    tidy_whitespace(&text)
+}
+
+/// `text` with the named and numeric character references decoded
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        match entity.and_then(decode_entity) {
+            Some(c) => {
+                decoded.push(c);
+                rest = &rest[entity.map_or(0, str::len) + 2..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(entity: &str) -> Option<char> {
+    let c = match entity {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => ' ',
+        _ => {
+            let number = entity.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            return char::from_u32(code);
+        }
+    };
+    Some(c)
+}
+
+/// `text` with spaces collapsed within lines and blank lines collapsed between them
+fn tidy_whitespace(text: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if line.is_empty() && lines.last().map(String::is_empty).unwrap_or(true) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markup_is_reduced_to_its_text() {
+        let html = r#"<!DOCTYPE html>
+<html>
+  <head><title>Failover</title><style>body { color: red }</style></head>
+  <body>
+    <!-- navigation -->
+    <h1>Database   failover</h1>
+    <p>Promote the <b>replica</b> &amp; update DNS.</p>
+    <script>alert("<p>not text</p>")</script>
+    <ul><li>Check lag</li><li>Promote&nbsp;it</li></ul>
+    <p>Caf&#233; &#x2014; done</p>
+  </body>
+</html>"#;
+
+        assert_eq!(
+            html_to_text(html),
+            "Failover\n\nDatabase failover\n\nPromote the replica & update DNS.\n\nCheck lag\nPromote it\n\nCafé — done"
+        );
+    }
+
+    #[test]
+    fn test_stray_ampersands_and_brackets_are_kept() {
+        assert_eq!(
+            html_to_text("Fish & chips &unknown; 3 > 2"),
+            "Fish & chips &unknown; 3 > 2"
+        );
+        assert_eq!(html_to_text("unterminated <b"), "unterminated");
+    }
+}
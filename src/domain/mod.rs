@@ -1,6 +1,24 @@
 pub mod error;
+pub mod evaluation;
+pub mod event;
+pub mod highlight;
+pub mod html;
 pub mod model;
+pub mod prompt;
+pub mod search_query;
 pub mod service;
+pub mod tag_policy;
+pub mod text_query;
+pub mod tokenizer;
 
 pub use error::*;
+pub use evaluation::{EvalCase, EvalDataset, EvalMetrics, EvalRun};
+pub use event::{ContextEvent, ContextEventFilter, ContextEventKind};
+pub use highlight::Highlighter;
+pub use html::html_to_text;
 pub use model::*;
+pub use prompt::{PromptArgument, PromptMessage, PromptTemplate};
+pub use search_query::SearchQuery;
+pub use tag_policy::TagPolicy;
+pub use text_query::TextQuery;
+pub use tokenizer::{HeuristicTokenizer, Tokenizer};
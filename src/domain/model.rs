@@ -1,8 +1,13 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use uuid::Uuid;
 
+use super::error::{McpError, McpResult};
+use super::service::ChunkingStrategy;
+
 /// The Model Context Protocol core entity
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Context {
@@ -18,8 +23,135 @@ pub struct Context {
     /// When this context was created
     pub created_at: DateTime<Utc>,
 
-    /// Optional expiry time
+    /// When the context expires, after which it reads as not found (optional)
     pub expires_at: Option<DateTime<Utc>>,
+
+    /// Number of times the context was written, starting at 1 when it is stored
+    #[serde(default = "first_version")]
+    pub version: u64,
+
+    /// When the context was soft-deleted, after which it reads as not found until restored
+    #[serde(default)]
+    pub deleted_at: Option<DateTime<Utc>>,
+
+    /// Links from this context to others, such as a summary to what it summarizes
+    #[serde(default)]
+    pub relations: Vec<ContextRelation>,
+
+    /// The context this one is part of, such as the document of a section (optional)
+    #[serde(default)]
+    pub parent_id: Option<Uuid>,
+
+    /// The collection holding this context, or none for the default collection
+    #[serde(default)]
+    pub collection_id: Option<Uuid>,
+}
+
+/// Version of a newly stored context, and of contexts stored before versions were tracked
+fn first_version() -> u64 {
+    1
+}
+
+impl Context {
+    /// A context created now under a new ID, at its first version, with no expiry, parent,
+    /// relations or collection
+    pub fn new(content: impl Into<String>, metadata: ContextMetadata) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            content: content.into(),
+            metadata,
+            created_at: Utc::now(),
+            expires_at: None,
+            version: first_version(),
+            deleted_at: None,
+            relations: Vec::new(),
+            parent_id: None,
+            collection_id: None,
+        }
+    }
+
+    /// Whether the context has expired by `now`
+    pub fn is_expired_at(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now)
+    }
+
+    /// Whether the context can be read and searched at `now`: neither expired nor deleted
+    pub fn is_live_at(&self, now: DateTime<Utc>) -> bool {
+        !self.is_expired_at(now) && self.deleted_at.is_none()
+    }
+
+    /// ID of the collection holding the context, `DEFAULT_COLLECTION_ID` for the default one
+    pub fn collection(&self) -> Uuid {
+        self.collection_id.unwrap_or(DEFAULT_COLLECTION_ID)
+    }
+
+    /// Order of listings and of equally scored search matches: newest first, then by id
+    pub fn listing_order(&self, other: &Context) -> Ordering {
+        other
+            .created_at
+            .cmp(&self.created_at)
+            .then_with(|| self.id.cmp(&other.id))
+    }
+
+    /// Fail with `McpError::VersionConflict` if an `expected_version` is given and this
+    /// context is at another version
+    ///
+    /// Repositories check the stored context while writing an update, so two writers that
+    /// read the same version can't both succeed.
+    pub fn check_version(&self, expected_version: Option<u64>) -> McpResult<()> {
+        match expected_version {
+            Some(expected) if expected != self.version => Err(McpError::VersionConflict {
+                context_id: self.id,
+                expected,
+                actual: self.version,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Hex-encoded SHA-256 of everything a read of the context returns, so it changes
+    /// whenever the content or metadata do; suitable as a strong ETag
+    pub fn version_hash(&self) -> String {
+        let metadata = &self.metadata;
+        let mut hasher = Sha256::new();
+
+        hash_field(&mut hasher, Some(&self.id.to_string()));
+        hasher.update(self.version.to_le_bytes());
+        hash_field(&mut hasher, Some(&self.content));
+        hash_field(&mut hasher, metadata.source.as_deref());
+        hash_field(&mut hasher, metadata.content_type.as_deref());
+        hash_field(&mut hasher, metadata.content_hash.as_deref());
+        hasher.update((metadata.tags.len() as u64).to_le_bytes());
+        for tag in &metadata.tags {
+            hash_field(&mut hasher, Some(tag));
+        }
+        // Custom metadata is hashed in key order, as map iteration order varies
+        let custom: BTreeMap<_, _> = metadata.custom.iter().collect();
+        hasher.update((custom.len() as u64).to_le_bytes());
+        for (key, value) in custom {
+            hash_field(&mut hasher, Some(key));
+            hash_field(&mut hasher, Some(value));
+        }
+        hash_field(&mut hasher, Some(&self.created_at.to_rfc3339()));
+        hash_field(
+            &mut hasher,
+            self.expires_at.map(|at| at.to_rfc3339()).as_deref(),
+        );
+
+        hex::encode(hasher.finalize())
+    }
+}
+
+/// Feed an optional field to a hasher, length-prefixed so adjacent fields can't run together
+fn hash_field(hasher: &mut Sha256, value: Option<&str>) {
+    match value {
+        Some(value) => {
+            hasher.update([1]);
+            hasher.update((value.len() as u64).to_le_bytes());
+            hasher.update(value.as_bytes());
+        }
+        None => hasher.update([0]),
+    }
 }
 
 /// Metadata associated with a context
@@ -31,7 +163,7 @@ pub struct ContextMetadata {
     /// Type of the context (e.g., text, code, image)
     pub content_type: Option<String>,
 
-    /// Content hash for deduplication
+    /// Hex-encoded SHA-256 of the content, set when the context is stored, for deduplication
     pub content_hash: Option<String>,
 
     /// User-defined tags
@@ -41,6 +173,11 @@ pub struct ContextMetadata {
     pub custom: HashMap<String, String>,
 }
 
+/// Hex-encoded SHA-256 of `content`, as kept in `ContextMetadata::content_hash`
+pub fn content_hash(content: &str) -> String {
+    hex::encode(Sha256::digest(content.as_bytes()))
+}
+
 /// Represents a chunk of context that can be addressed individually
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextChunk {
@@ -50,16 +187,101 @@ pub struct ContextChunk {
     /// Unique identifier for this chunk
     pub chunk_id: Uuid,
 
-    /// Content of this chunk
+    /// Content of this chunk, after the heading path of its section for markdown chunks
     pub content: String,
 
     /// Embedding of this chunk, if available
     pub embedding: Option<Vec<f32>>,
 
-    /// Position of this chunk in the original context
+    /// Byte offset of this chunk in the original context, always on a char boundary
     pub position: usize,
 }
 
+/// A link from one context to another, of a type the caller names, such as `summarizes`
+///
+/// Relations only go one way; contexts may relate to each other both ways, and in cycles.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextRelation {
+    /// ID of the context related to
+    pub target_id: Uuid,
+
+    /// What the relation means
+    pub relation_type: String,
+}
+
+/// ID of the collection holding every context stored without one
+pub const DEFAULT_COLLECTION_ID: Uuid = Uuid::nil();
+
+/// A named group of contexts, kept apart from those of other collections in listings and
+/// searches scoped to one
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Collection {
+    /// Unique identifier for this collection
+    pub id: Uuid,
+
+    /// Name of the collection, unique among collections
+    pub name: String,
+
+    /// What the collection is for (optional)
+    pub description: Option<String>,
+
+    /// When this collection was created
+    pub created_at: DateTime<Utc>,
+}
+
+impl Collection {
+    /// The collection every context stored without one belongs to, which always exists
+    pub fn default_collection() -> Self {
+        Self {
+            id: DEFAULT_COLLECTION_ID,
+            name: "default".to_string(),
+            description: Some("Contexts stored without a collection".to_string()),
+            created_at: DateTime::<Utc>::UNIX_EPOCH,
+        }
+    }
+
+    /// Whether this is the default collection
+    pub fn is_default(&self) -> bool {
+        self.id == DEFAULT_COLLECTION_ID
+    }
+}
+
+/// A prior state of a context, kept when an update replaced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContextRevision {
+    /// ID of the context
+    pub context_id: Uuid,
+
+    /// Version the context was at, which numbers the revision
+    pub version: u64,
+
+    /// Content at that version
+    pub content: String,
+
+    /// Metadata at that version
+    pub metadata: ContextMetadata,
+
+    /// Expiry at that version
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// When an update replaced this state
+    pub archived_at: DateTime<Utc>,
+}
+
+impl ContextRevision {
+    /// The current state of `context`, archived at `archived_at`
+    pub fn of(context: &Context, archived_at: DateTime<Utc>) -> Self {
+        Self {
+            context_id: context.id,
+            version: context.version,
+            content: context.content.clone(),
+            metadata: context.metadata.clone(),
+            expires_at: context.expires_at,
+            archived_at,
+        }
+    }
+}
+
 /// A reference to a context that can be used in a prompt
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContextReference {
@@ -79,8 +301,16 @@ pub struct ContextSearchResult {
     /// The matching contexts or chunks
     pub matches: Vec<ContextMatch>,
 
-    /// Total number of matching contexts in the system
+    /// Number of contexts that matched, which can exceed the matches returned
+    ///
+    /// Searches count every context that passes their filters. With a score threshold or quoted
+    /// phrases, only the matches among the chunks most similar to the query are counted, and
+    /// `total_matches_is_lower_bound` says when more contexts may match.
     pub total_matches: usize,
+
+    /// Whether more contexts may match than `total_matches` counts
+    #[serde(default)]
+    pub total_matches_is_lower_bound: bool,
 }
 
 /// A single match from a context search
@@ -95,3 +325,415 @@ pub struct ContextMatch {
     /// Relevance score of this match
     pub score: f32,
 }
+
+/// Per-request overrides of the configured search settings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchOptions {
+    /// Weight of vector similarity against the lexical score, from 0.0 to 1.0 (optional)
+    pub hybrid_alpha: Option<f32>,
+
+    /// Minimum relevance score of a match (optional)
+    pub min_score: Option<f32>,
+
+    /// Whether query terms also match near misses, such as typos (optional)
+    pub fuzzy: Option<bool>,
+
+    /// Whether synonyms and full forms of query terms are searched for too (optional)
+    pub expand: Option<bool>,
+
+    /// Whether tag-filtered matches need every requested tag or any of them
+    pub tag_mode: TagMode,
+
+    /// Tags of contexts left out of the matches, whatever other tags they have
+    pub exclude_tags: Vec<String>,
+
+    /// Only contexts in this collection (optional)
+    pub collection: Option<Uuid>,
+
+    /// Only contexts from this source (optional)
+    pub source: Option<String>,
+
+    /// Only contexts created at or after this time (optional)
+    pub after: Option<DateTime<Utc>>,
+
+    /// Only contexts created before this time (optional)
+    pub before: Option<DateTime<Utc>>,
+}
+
+impl SearchOptions {
+    /// Check a context against the collection, exclusion, source, and date filters
+    pub fn matches_context(&self, context: &Context) -> bool {
+        if self
+            .collection
+            .is_some_and(|collection| context.collection() != collection)
+        {
+            return false;
+        }
+
+        if self
+            .exclude_tags
+            .iter()
+            .any(|tag| context.metadata.tags.contains(tag))
+        {
+            return false;
+        }
+
+        if let Some(source) = &self.source {
+            if context.metadata.source.as_deref() != Some(source.as_str()) {
+                return false;
+            }
+        }
+
+        if self.after.is_some_and(|after| context.created_at < after) {
+            return false;
+        }
+
+        if self
+            .before
+            .is_some_and(|before| context.created_at >= before)
+        {
+            return false;
+        }
+
+        true
+    }
+
+    /// The listing filter for the contexts a search with `tags` can match, expired and deleted
+    /// ones included
+    pub fn context_filter(&self, tags: &[String]) -> ContextFilter {
+        ContextFilter {
+            tags: tags.to_vec(),
+            tag_mode: self.tag_mode,
+            exclude_tags: self.exclude_tags.clone(),
+            created_after: self.after,
+            created_before: self.before,
+            collection: self.collection,
+            source: self.source.clone(),
+            ..ContextFilter::default()
+        }
+    }
+}
+
+/// How a list of requested tags filters contexts
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    /// Contexts with every requested tag
+    #[default]
+    All,
+
+    /// Contexts with at least one of the requested tags
+    Any,
+}
+
+impl TagMode {
+    /// Whether a context tagged `tags` passes a filter on `required`; no tags filter nothing
+    pub fn matches(self, required: &[String], tags: &[String]) -> bool {
+        match self {
+            TagMode::All => required.iter().all(|tag| tags.contains(tag)),
+            TagMode::Any => required.is_empty() || required.iter().any(|tag| tags.contains(tag)),
+        }
+    }
+}
+
+/// Which stored contexts a listing returns
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContextFilter {
+    /// Tags a context must have, all or any of them by `tag_mode`
+    pub tags: Vec<String>,
+
+    /// Whether a context needs every one of `tags` or any of them
+    pub tag_mode: TagMode,
+
+    /// Tags a context must not have any of, even when it has the required ones
+    pub exclude_tags: Vec<String>,
+
+    /// Only contexts created at or after this time (optional)
+    pub created_after: Option<DateTime<Utc>>,
+
+    /// Only contexts created before this time (optional)
+    pub created_before: Option<DateTime<Utc>>,
+
+    /// Only contexts that haven't expired by this time nor been deleted (optional)
+    pub live_at: Option<DateTime<Utc>>,
+
+    /// Only the children of this context (optional)
+    pub parent_id: Option<Uuid>,
+
+    /// Only contexts in this collection, `DEFAULT_COLLECTION_ID` for the default one (optional)
+    pub collection: Option<Uuid>,
+
+    /// Only contexts from this source (optional)
+    pub source: Option<String>,
+}
+
+impl ContextFilter {
+    /// Contexts with all of `tags`, whenever they were created
+    pub fn tagged(tags: Vec<String>) -> Self {
+        Self {
+            tags,
+            ..Self::default()
+        }
+    }
+
+    /// The children of the context with `parent_id`, expired and deleted ones included
+    pub fn children_of(parent_id: Uuid) -> Self {
+        Self {
+            parent_id: Some(parent_id),
+            ..Self::default()
+        }
+    }
+
+    /// The contexts in the collection with `collection_id`, expired and deleted ones included
+    pub fn in_collection(collection_id: Uuid) -> Self {
+        Self {
+            collection: Some(collection_id),
+            ..Self::default()
+        }
+    }
+
+    /// The same filter, leaving out contexts that have expired by now or were deleted
+    pub fn live_now(self) -> Self {
+        Self {
+            live_at: Some(Utc::now()),
+            ..self
+        }
+    }
+
+    /// Whether the filter limits when contexts were created
+    pub fn has_date_range(&self) -> bool {
+        self.created_after.is_some() || self.created_before.is_some()
+    }
+
+    /// Whether `find_by_tags` returns the contexts with these tags, which it does unless
+    /// any one tag of several will do
+    pub fn requires_every_tag(&self) -> bool {
+        self.tag_mode == TagMode::All || self.tags.len() < 2
+    }
+
+    /// Whether the filter does more than `find_by_tags` can on its own
+    pub fn needs_scan(&self) -> bool {
+        !self.requires_every_tag()
+            || !self.exclude_tags.is_empty()
+            || self.has_date_range()
+            || self.live_at.is_some()
+            || self.parent_id.is_some()
+            || self.collection.is_some()
+            || self.source.is_some()
+    }
+
+    pub fn matches(&self, context: &Context) -> bool {
+        self.tag_mode.matches(&self.tags, &context.metadata.tags)
+            && !self
+                .exclude_tags
+                .iter()
+                .any(|tag| context.metadata.tags.contains(tag))
+            && !self
+                .created_after
+                .is_some_and(|after| context.created_at < after)
+            && !self
+                .created_before
+                .is_some_and(|before| context.created_at >= before)
+            && self.live_at.is_none_or(|now| context.is_live_at(now))
+            && self
+                .parent_id
+                .is_none_or(|parent_id| context.parent_id == Some(parent_id))
+            && self
+                .collection
+                .is_none_or(|collection| context.collection() == collection)
+            && self
+                .source
+                .as_ref()
+                .is_none_or(|source| context.metadata.source.as_ref() == Some(source))
+    }
+}
+
+/// What deleting one context of a batch did
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeleteOutcome {
+    /// The context, its chunks and their embeddings were deleted
+    Deleted,
+
+    /// No context has the ID
+    NotFound,
+
+    /// The context has children, and contexts with children can't be deleted
+    HasChildren,
+}
+
+/// What deleting a context for good does to its children
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChildPolicy {
+    /// Keep the children, without a parent
+    #[default]
+    Orphan,
+
+    /// Delete the children, and theirs, along with it
+    Cascade,
+
+    /// Refuse to delete a context with children
+    Reject,
+}
+
+/// What storing a context does when a stored one has the same content
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnDuplicate {
+    /// Store the context anyway
+    #[default]
+    Allow,
+
+    /// Return the stored context instead of storing another
+    Skip,
+
+    /// Fail with `McpError::ContextAlreadyExists`, naming the stored context
+    Error,
+}
+
+/// How a new context is stored, beyond its content and metadata
+#[derive(Debug, Clone, Default)]
+pub struct StoreOptions {
+    /// When the context expires, after which it reads as not found (optional)
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// How this content is chunked instead of the configured strategy (optional)
+    pub chunking: Option<ChunkingStrategy>,
+
+    /// What happens when an unexpired context with the same content is already stored
+    pub on_duplicate: OnDuplicate,
+
+    /// The live context this one is part of (optional)
+    pub parent_id: Option<Uuid>,
+
+    /// The collection to store the context in, otherwise its parent's or the default one
+    /// (optional)
+    pub collection_id: Option<Uuid>,
+}
+
+/// Outcome of checking one dependency requests rely on
+#[derive(Debug, Clone, PartialEq)]
+pub struct DependencyStatus {
+    /// Name of the dependency, such as `repository`
+    pub name: String,
+
+    /// Why the check failed, or `None` if it passed
+    pub error: Option<String>,
+}
+
+impl DependencyStatus {
+    /// Status of a dependency from the result of checking it
+    pub fn from_check(name: impl Into<String>, result: McpResult<()>) -> Self {
+        Self {
+            name: name.into(),
+            error: result.err().map(|err| err.to_string()),
+        }
+    }
+
+    /// Whether the check passed
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// A document as a web server returned it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchedContent {
+    /// URL the document was fetched from, after following redirects
+    pub url: String,
+
+    /// Media type from the `Content-Type` header, without parameters (optional)
+    pub content_type: Option<String>,
+
+    /// Charset from the `Content-Type` header (optional)
+    pub charset: Option<String>,
+
+    /// The response body
+    pub body: Vec<u8>,
+}
+
+/// The text of a fetched document, ready to store as a context
+#[derive(Debug, Clone, PartialEq)]
+pub struct IngestedDocument {
+    /// The document's text
+    pub content: String,
+
+    /// Media type of `content`
+    pub content_type: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_context() -> Context {
+        Context {
+            id: Uuid::from_u128(1),
+            ..Context::new(
+                "Versioned content",
+                ContextMetadata {
+                    tags: vec!["a".to_string(), "b".to_string()],
+                    custom: (0..8)
+                        .map(|i| (format!("key{}", i), format!("value{}", i)))
+                        .collect(),
+                    ..ContextMetadata::default()
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_expired_contexts_are_filtered_out() {
+        let now = Utc::now();
+        let mut context = create_test_context();
+        let live = ContextFilter::default().live_now();
+        assert!(!context.is_expired_at(now));
+        assert!(live.matches(&context));
+
+        context.expires_at = Some(now + chrono::Duration::seconds(60));
+        assert!(!context.is_expired_at(now));
+        assert!(context.is_expired_at(now + chrono::Duration::seconds(60)));
+
+        context.expires_at = Some(now - chrono::Duration::seconds(1));
+        assert!(!live.matches(&context));
+        assert!(ContextFilter::default().matches(&context));
+        assert!(live.needs_scan());
+
+        // Deleted contexts are left out like expired ones
+        context.expires_at = None;
+        context.deleted_at = Some(now);
+        assert!(!context.is_live_at(now));
+        assert!(!live.matches(&context));
+    }
+
+    #[test]
+    fn test_version_hash_follows_content_and_metadata() {
+        let context = create_test_context();
+        assert_eq!(context.version_hash(), context.clone().version_hash());
+        assert_eq!(context.version_hash().len(), 64);
+
+        // The same custom metadata hashes the same whatever order the map was built in
+        let mut rebuilt = context.clone();
+        rebuilt.metadata.custom = context
+            .metadata
+            .custom
+            .iter()
+            .rev()
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        assert_eq!(rebuilt.version_hash(), context.version_hash());
+
+        let mut changed = context.clone();
+        changed.content.push('!');
+        assert_ne!(changed.version_hash(), context.version_hash());
+
+        let mut changed = context.clone();
+        changed.metadata.source = Some("wiki".to_string());
+        assert_ne!(changed.version_hash(), context.version_hash());
+
+        // Moving text between adjacent fields changes the hash too
+        let mut changed = context.clone();
+        changed.metadata.tags = vec!["ab".to_string(), String::new()];
+        assert_ne!(changed.version_hash(), context.version_hash());
+    }
+}
@@ -0,0 +1,290 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::{McpError, McpResult};
+
+/// Placeholder replaced by the contents of the contexts a prompt's search found
+pub const CONTEXTS_PLACEHOLDER: &str = "contexts";
+
+/// Separator between the contexts interpolated into a prompt
+const CONTEXT_SEPARATOR: &str = "\n\n---\n\n";
+
+/// A prompt offered to MCP clients, filled in from its arguments and the stored contexts
+///
+/// Message texts and the search may use `{{argument}}` placeholders, and messages also
+/// `{{contexts}}`, which holds the best `top_k` matches of the search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+
+    /// Query the contexts are searched with (optional, no contexts are searched without one)
+    #[serde(default)]
+    pub search: Option<String>,
+
+    /// Most contexts interpolated into the messages
+    #[serde(default = "default_top_k")]
+    pub top_k: usize,
+
+    pub messages: Vec<PromptMessage>,
+}
+
+fn default_top_k() -> usize {
+    5
+}
+
+/// An argument a prompt is filled in with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A message of a prompt, by the `user` or the `assistant`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub text: String,
+}
+
+impl PromptTemplate {
+    /// Check that the roles are known and every placeholder names an argument
+    pub fn validate(&self) -> McpResult<()> {
+        if self.messages.is_empty() {
+            return Err(self.invalid("has no messages"));
+        }
+        for message in &self.messages {
+            if !matches!(message.role.as_str(), "user" | "assistant") {
+                return Err(self.invalid(&format!(
+                    "has a message with role '{}', not user or assistant",
+                    message.role
+                )));
+            }
+        }
+
+        let texts = self
+            .messages
+            .iter()
+            .map(|message| (message.text.as_str(), true));
+        for (text, in_message) in texts.chain(self.search.as_deref().map(|search| (search, false)))
+        {
+            for name in placeholders(text) {
+                let known = self.arguments.iter().any(|argument| argument.name == name)
+                    || (in_message && name == CONTEXTS_PLACEHOLDER);
+                if !known {
+                    return Err(self.invalid(&format!(
+                        "uses {{{{{}}}}}, which isn't one of its arguments",
+                        name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The search query filled in with `arguments`, if the prompt searches contexts
+    ///
+    /// Fails if a required argument is missing.
+    pub fn search_query(&self, arguments: &HashMap<String, String>) -> McpResult<Option<String>> {
+        self.check_arguments(arguments)?;
+        Ok(self
+            .search
+            .as_deref()
+            .map(|search| interpolate(search, arguments, "")))
+    }
+
+    /// The messages filled in with `arguments` and the `contexts` found for them
+    ///
+    /// Fails if a required argument is missing. Missing optional arguments are left empty,
+    /// and placeholders within the values aren't replaced in turn.
+    pub fn render(
+        &self,
+        arguments: &HashMap<String, String>,
+        contexts: &[String],
+    ) -> McpResult<Vec<PromptMessage>> {
+        self.check_arguments(arguments)?;
+        let contexts = contexts.join(CONTEXT_SEPARATOR);
+        Ok(self
+            .messages
+            .iter()
+            .map(|message| PromptMessage {
+                role: message.role.clone(),
+                text: interpolate(&message.text, arguments, &contexts),
+            })
+            .collect())
+    }
+
+    fn check_arguments(&self, arguments: &HashMap<String, String>) -> McpResult<()> {
+        let missing: Vec<&str> = self
+            .arguments
+            .iter()
+            .filter(|argument| argument.required && !arguments.contains_key(&argument.name))
+            .map(|argument| argument.name.as_str())
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(McpError::ValidationError(format!(
+            "Prompt {} is missing required arguments: {}",
+            self.name,
+            missing.join(", ")
+        )))
+    }
+
+    fn invalid(&self, problem: &str) -> McpError {
+        McpError::ValidationError(format!("Prompt {} {}", self.name, problem))
+    }
+}
+
+/// Names of the `{{name}}` placeholders in `text`
+fn placeholders(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + 2 + end].trim());
+        rest = &rest[start + 2 + end + 2..];
+    }
+    names
+}
+
+/// `text` with its placeholders replaced in a single pass
+fn interpolate(text: &str, arguments: &HashMap<String, String>, contexts: &str) -> String {
+    let mut interpolated = String::with_capacity(text.len() + contexts.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        interpolated.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + end].trim();
+        if name == CONTEXTS_PLACEHOLDER {
+            interpolated.push_str(contexts);
+        } else if let Some(value) = arguments.get(name) {
+            interpolated.push_str(value);
+        }
+        rest = &rest[start + 2 + end + 2..];
+    }
+    interpolated.push_str(rest);
+    interpolated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template() -> PromptTemplate {
+        PromptTemplate {
+            name: "answer_with_context".to_string(),
+            description: None,
+            arguments: vec![
+                PromptArgument {
+                    name: "query".to_string(),
+                    description: None,
+                    required: true,
+                },
+                PromptArgument {
+                    name: "tone".to_string(),
+                    description: None,
+                    required: false,
+                },
+            ],
+            search: Some("{{ query }}".to_string()),
+            top_k: 3,
+            messages: vec![PromptMessage {
+                role: "user".to_string(),
+                text: "Answer {{tone}}from these notes:\n\n{{contexts}}\n\nQuestion: {{query}}"
+                    .to_string(),
+            }],
+        }
+    }
+
+    fn arguments(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_arguments_and_contexts_are_interpolated() {
+        let template = template();
+        let arguments = arguments(&[("query", "How do I fail over?"), ("tone", "briefly ")]);
+
+        assert_eq!(
+            template.search_query(&arguments).unwrap().as_deref(),
+            Some("How do I fail over?")
+        );
+        let messages = template
+            .render(
+                &arguments,
+                &["Promote the replica".to_string(), "Update DNS".to_string()],
+            )
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].role, "user");
+        assert_eq!(
+            messages[0].text,
+            "Answer briefly from these notes:\n\nPromote the replica\n\n---\n\nUpdate DNS\n\nQuestion: How do I fail over?"
+        );
+    }
+
+    #[test]
+    fn test_values_are_not_interpolated_again() {
+        let template = template();
+        let arguments = arguments(&[("query", "{{contexts}}")]);
+
+        let messages = template
+            .render(&arguments, &["Notes with {{query}} in them".to_string()])
+            .unwrap();
+        assert_eq!(
+            messages[0].text,
+            "Answer from these notes:\n\nNotes with {{query}} in them\n\nQuestion: {{contexts}}"
+        );
+    }
+
+    #[test]
+    fn test_missing_required_arguments_are_rejected() {
+        let template = template();
+        let missing = arguments(&[("tone", "politely ")]);
+
+        assert!(matches!(
+            template.render(&missing, &[]),
+            Err(McpError::ValidationError(message)) if message.contains("query")
+        ));
+        assert!(template.search_query(&missing).is_err());
+    }
+
+    #[test]
+    fn test_templates_are_validated() {
+        assert!(template().validate().is_ok());
+
+        let mut unknown = template();
+        unknown.messages[0].text = "{{question}}".to_string();
+        assert!(matches!(
+            unknown.validate(),
+            Err(McpError::ValidationError(message)) if message.contains("{{question}}")
+        ));
+
+        // The contexts are only known once the search has run
+        let mut searching_contexts = template();
+        searching_contexts.search = Some("{{contexts}}".to_string());
+        assert!(searching_contexts.validate().is_err());
+
+        let mut system = template();
+        system.messages[0].role = "system".to_string();
+        assert!(system.validate().is_err());
+    }
+}
@@ -0,0 +1,445 @@
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::domain::error::{McpError, McpResult};
+use crate::domain::model::SearchOptions;
+
+/// A search parsed from the query string syntax, e.g.
+/// `payments outage tag:runbook -tag:archived source:wiki after:2024-01-01 min_score:0.3`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    /// Free text to search for
+    pub text: String,
+
+    /// Tags a match must have
+    pub tags: Vec<String>,
+
+    /// Tags a match must not have
+    pub exclude_tags: Vec<String>,
+
+    /// Source a match must come from
+    pub source: Option<String>,
+
+    /// Only match contexts created at or after this time
+    pub after: Option<DateTime<Utc>>,
+
+    /// Only match contexts created before this time
+    pub before: Option<DateTime<Utc>>,
+
+    /// Minimum relevance score of a match
+    pub min_score: Option<f32>,
+
+    /// Maximum number of results to return
+    pub limit: Option<usize>,
+}
+
+/// A whitespace-separated piece of the query string
+#[derive(Debug, PartialEq)]
+struct Token {
+    /// Directive name, if the token has the form `name:value`
+    key: Option<String>,
+    value: String,
+    negated: bool,
+    /// The token as written, for error messages
+    raw: String,
+}
+
+impl SearchQuery {
+    /// Parse the query string syntax
+    ///
+    /// Words that are not directives form the search text. Values containing spaces
+    /// can be quoted (`source:"team wiki"`), and quoted text is never treated as a directive.
+    pub fn parse(input: &str) -> McpResult<Self> {
+        let mut query = SearchQuery::default();
+        let mut text = Vec::new();
+
+        for token in tokenize(input)? {
+            let Some(key) = &token.key else {
+                text.push(if token.negated {
+                    format!("-{}", token.value)
+                } else {
+                    token.value
+                });
+                continue;
+            };
+
+            if token.value.is_empty() {
+                return Err(invalid(&token, "is missing a value"));
+            }
+
+            match (key.as_str(), token.negated) {
+                ("tag", false) => query.tags.push(token.value.clone()),
+                ("tag", true) => query.exclude_tags.push(token.value.clone()),
+                ("source", false) => query.source = Some(token.value.clone()),
+                ("after", false) => query.after = Some(parse_date(&token)?),
+                ("before", false) => query.before = Some(parse_date(&token)?),
+                ("min_score", false) => {
+                    let score = token.value.parse::<f32>().ok().filter(|s| s.is_finite());
+                    query.min_score =
+                        Some(score.ok_or_else(|| invalid(&token, "is not a number"))?);
+                }
+                ("limit", false) => {
+                    let limit = token.value.parse::<usize>().ok().filter(|l| *l > 0);
+                    query.limit =
+                        Some(limit.ok_or_else(|| invalid(&token, "is not a positive integer"))?);
+                }
+                ("source" | "after" | "before" | "min_score" | "limit", true) => {
+                    return Err(invalid(&token, "cannot be negated"));
+                }
+                _ => {
+                    return Err(McpError::ValidationError(format!(
+                        "Unknown search directive '{}'",
+                        token.raw
+                    )));
+                }
+            }
+        }
+
+        query.text = text.join(" ");
+        Ok(query)
+    }
+
+    /// `options` with the query's threshold and context filters added
+    pub fn search_options(&self, options: SearchOptions) -> SearchOptions {
+        SearchOptions {
+            min_score: self.min_score,
+            exclude_tags: self.exclude_tags.clone(),
+            source: self.source.clone(),
+            after: self.after,
+            before: self.before,
+            ..options
+        }
+    }
+}
+
+fn invalid(token: &Token, reason: &str) -> McpError {
+    McpError::ValidationError(format!("Search directive '{}' {}", token.raw, reason))
+}
+
+/// Parse `YYYY-MM-DD` (midnight UTC) or an RFC 3339 timestamp
+fn parse_date(token: &Token) -> McpResult<DateTime<Utc>> {
+    if let Ok(date) = NaiveDate::parse_from_str(&token.value, "%Y-%m-%d") {
+        return Ok(date.and_hms_opt(0, 0, 0).unwrap().and_utc());
+    }
+
+    DateTime::parse_from_rfc3339(&token.value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|_| invalid(token, "is not a date (expected YYYY-MM-DD or RFC 3339)"))
+}
+
+/// Split the input on whitespace, honoring double quotes and `\"` escapes
+fn tokenize(input: &str) -> McpResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut raw = String::new();
+        let mut word = String::new();
+        let mut key = None;
+        let mut quoted = false;
+        let mut in_quotes = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    raw.push(c);
+                    in_quotes = !in_quotes;
+                    quoted = true;
+                }
+                '\\' if in_quotes && chars.peek() == Some(&'"') => {
+                    raw.push(c);
+                    raw.push('"');
+                    word.push(chars.next().unwrap());
+                }
+                ':' if !in_quotes && !quoted && key.is_none() && is_directive_name(&word) => {
+                    raw.push(c);
+                    key = Some(std::mem::take(&mut word));
+                }
+                c if c.is_whitespace() && !in_quotes => break,
+                c => {
+                    raw.push(c);
+                    word.push(c);
+                }
+            }
+        }
+
+        if in_quotes {
+            return Err(McpError::ValidationError(format!(
+                "Unterminated quote in search query at '{}'",
+                raw
+            )));
+        }
+
+        // A leading `-` negates the token, unless it was quoted
+        let (key, negated) = match key {
+            Some(name) => match name.strip_prefix('-') {
+                Some(name) => (Some(name.to_string()), true),
+                None => (Some(name), false),
+            },
+            None if !quoted && word.len() > 1 && word.starts_with('-') => {
+                word.remove(0);
+                (None, true)
+            }
+            None => (None, false),
+        };
+
+        // Quoted text like `""` contributes nothing
+        if key.is_none() && word.is_empty() {
+            continue;
+        }
+
+        tokens.push(Token {
+            key: key.map(|k| k.to_lowercase()),
+            value: word,
+            negated,
+            raw,
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// Directive names are identifiers, optionally negated with a leading `-`
+fn is_directive_name(word: &str) -> bool {
+    let name = word.strip_prefix('-').unwrap_or(word);
+    name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Context, ContextMetadata};
+    use chrono::TimeZone;
+
+    fn validation_message(result: McpResult<SearchQuery>) -> String {
+        match result {
+            Err(McpError::ValidationError(message)) => message,
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_full_example() {
+        let query = SearchQuery::parse(
+            "payments outage tag:runbook -tag:archived source:wiki after:2024-01-01 min_score:0.3",
+        )
+        .unwrap();
+
+        assert_eq!(query.text, "payments outage");
+        assert_eq!(query.tags, vec!["runbook"]);
+        assert_eq!(query.exclude_tags, vec!["archived"]);
+        assert_eq!(query.source.as_deref(), Some("wiki"));
+        assert_eq!(
+            query.after,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(query.min_score, Some(0.3));
+        assert_eq!(query.before, None);
+        assert_eq!(query.limit, None);
+    }
+
+    #[test]
+    fn test_plain_text_only() {
+        let query = SearchQuery::parse("  how   do I rotate keys  ").unwrap();
+        assert_eq!(query.text, "how do I rotate keys");
+        assert_eq!(
+            query,
+            SearchQuery {
+                text: "how do I rotate keys".to_string(),
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_empty_input() {
+        assert_eq!(SearchQuery::parse("").unwrap(), SearchQuery::default());
+        assert_eq!(SearchQuery::parse("   ").unwrap(), SearchQuery::default());
+    }
+
+    #[test]
+    fn test_text_remainder_is_interleaved_with_directives() {
+        let query = SearchQuery::parse("tag:db slow tag:postgres queries limit:5").unwrap();
+        assert_eq!(query.text, "slow queries");
+        assert_eq!(query.tags, vec!["db", "postgres"]);
+        assert_eq!(query.limit, Some(5));
+    }
+
+    #[test]
+    fn test_quoted_values() {
+        let query =
+            SearchQuery::parse(r#"source:"team wiki" tag:"machine learning" deploy"#).unwrap();
+        assert_eq!(query.source.as_deref(), Some("team wiki"));
+        assert_eq!(query.tags, vec!["machine learning"]);
+        assert_eq!(query.text, "deploy");
+    }
+
+    #[test]
+    fn test_quoted_text_is_not_a_directive() {
+        let query = SearchQuery::parse(r#""error: connection refused" retry"#).unwrap();
+        assert_eq!(query.text, "error: connection refused retry");
+        assert!(query.tags.is_empty());
+
+        let query = SearchQuery::parse(r#""tag:literal""#).unwrap();
+        assert_eq!(query.text, "tag:literal");
+        assert!(query.tags.is_empty());
+    }
+
+    #[test]
+    fn test_escaped_quotes() {
+        let query = SearchQuery::parse(r#""say \"hello\"" tag:greeting"#).unwrap();
+        assert_eq!(query.text, r#"say "hello""#);
+        assert_eq!(query.tags, vec!["greeting"]);
+    }
+
+    #[test]
+    fn test_unterminated_quote() {
+        let message = validation_message(SearchQuery::parse(r#"source:"team wiki"#));
+        assert!(message.contains("Unterminated quote"));
+    }
+
+    #[test]
+    fn test_negation() {
+        let query = SearchQuery::parse("-tag:archived -tag:draft -legacy api").unwrap();
+        assert_eq!(query.exclude_tags, vec!["archived", "draft"]);
+        // Negated plain words stay in the text
+        assert_eq!(query.text, "-legacy api");
+
+        let message = validation_message(SearchQuery::parse("-source:wiki"));
+        assert!(message.contains("'-source:wiki'"));
+        assert!(message.contains("cannot be negated"));
+    }
+
+    #[test]
+    fn test_lone_dash_is_text() {
+        let query = SearchQuery::parse("before - after").unwrap();
+        assert_eq!(query.text, "before - after");
+    }
+
+    #[test]
+    fn test_times_are_text() {
+        let query = SearchQuery::parse("outage at 10:30").unwrap();
+        assert_eq!(query.text, "outage at 10:30");
+    }
+
+    #[test]
+    fn test_dates() {
+        let query =
+            SearchQuery::parse("after:2024-01-01 before:2024-02-01T12:30:00+02:00").unwrap();
+        assert_eq!(
+            query.after,
+            Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            query.before,
+            Some(Utc.with_ymd_and_hms(2024, 2, 1, 10, 30, 0).unwrap())
+        );
+
+        let message = validation_message(SearchQuery::parse("after:yesterday"));
+        assert!(message.contains("'after:yesterday'"));
+
+        let message = validation_message(SearchQuery::parse("before:2024-13-01"));
+        assert!(message.contains("'before:2024-13-01'"));
+    }
+
+    #[test]
+    fn test_invalid_numbers() {
+        let message = validation_message(SearchQuery::parse("min_score:high"));
+        assert!(message.contains("'min_score:high'"));
+
+        let message = validation_message(SearchQuery::parse("min_score:NaN"));
+        assert!(message.contains("'min_score:NaN'"));
+
+        let message = validation_message(SearchQuery::parse("limit:0"));
+        assert!(message.contains("'limit:0'"));
+
+        let message = validation_message(SearchQuery::parse("limit:-3"));
+        assert!(message.contains("'limit:-3'"));
+    }
+
+    #[test]
+    fn test_unknown_directive_names_token() {
+        let message = validation_message(SearchQuery::parse("outage author:alice"));
+        assert_eq!(message, "Unknown search directive 'author:alice'");
+
+        let message = validation_message(SearchQuery::parse("https://example.com"));
+        assert!(message.contains("'https://example.com'"));
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let message = validation_message(SearchQuery::parse("tag: rust"));
+        assert!(message.contains("'tag:'"));
+        assert!(message.contains("missing a value"));
+    }
+
+    #[test]
+    fn test_directive_names_are_case_insensitive() {
+        let query = SearchQuery::parse("TAG:Rust Source:Docs").unwrap();
+        assert_eq!(query.tags, vec!["Rust"]);
+        assert_eq!(query.source.as_deref(), Some("Docs"));
+    }
+
+    #[test]
+    fn test_colon_inside_value() {
+        let query = SearchQuery::parse("source:https://wiki.example.com/page").unwrap();
+        assert_eq!(
+            query.source.as_deref(),
+            Some("https://wiki.example.com/page")
+        );
+    }
+
+    #[test]
+    fn test_matches_context() {
+        let context = Context {
+            created_at: Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap(),
+            ..Context::new(
+                "content",
+                ContextMetadata {
+                    source: Some("wiki".to_string()),
+                    tags: vec!["runbook".to_string()],
+                    ..Default::default()
+                },
+            )
+        };
+
+        let matching = [
+            "source:wiki",
+            "after:2024-03-01",
+            "before:2024-03-02",
+            "-tag:archived",
+        ];
+        for input in matching {
+            assert!(
+                SearchQuery::parse(input)
+                    .unwrap()
+                    .search_options(SearchOptions::default())
+                    .matches_context(&context),
+                "{} should match",
+                input
+            );
+        }
+
+        let filtered = [
+            "source:jira",
+            "after:2024-03-02",
+            "before:2024-03-01",
+            "-tag:runbook",
+        ];
+        for input in filtered {
+            assert!(
+                !SearchQuery::parse(input)
+                    .unwrap()
+                    .search_options(SearchOptions::default())
+                    .matches_context(&context),
+                "{} should not match",
+                input
+            );
+        }
+    }
+}
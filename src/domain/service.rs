@@ -1,100 +1,1847 @@
-use crate::domain::model::{Context, ContextChunk};
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::domain::error::{McpError, McpResult};
+use crate::domain::model::{Context, ContextChunk};
+use crate::domain::tokenizer::Tokenizer;
+
+/// How `ChunkingService` decides where chunks end
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkingStrategy {
+    /// Windows of `max_chunk_size` bytes, wherever they end
+    #[default]
+    Fixed,
+    /// Whole sentences up to `max_chunk_size`, overlapping by the trailing sentences that fit
+    /// in the overlap
+    Sentence,
+    /// Whole paragraphs, separated by blank lines, merged up to `max_chunk_size` like
+    /// sentences
+    Paragraph,
+    /// Markdown blocks merged within the section of each heading, with fenced code never
+    /// split and the heading path (`Install > Linux`) put in front of every chunk
+    Markdown,
+    /// Top-level items of source code, such as functions and impl blocks, merged like
+    /// paragraphs, with the names of the items put in front of every chunk
+    Code,
+}
+
+impl ChunkingStrategy {
+    /// The strategy suiting content of `content_type`, if it has one of its own
+    pub fn for_content_type(content_type: &str) -> Option<Self> {
+        let essence = content_type.split(';').next().unwrap_or_default().trim();
+        if essence.eq_ignore_ascii_case("text/markdown")
+            || essence.eq_ignore_ascii_case("text/x-markdown")
+        {
+            return Some(Self::Markdown);
+        }
+        let essence = essence.to_ascii_lowercase();
+        if CODE_CONTENT_TYPE_PREFIXES
+            .iter()
+            .any(|prefix| essence.starts_with(prefix))
+        {
+            return Some(Self::Code);
+        }
+        None
+    }
+}
+
+/// Content types of source code, such as `text/x-rust` and `text/x-python`
+const CODE_CONTENT_TYPE_PREFIXES: &[&str] = &[
+    "text/x-",
+    "text/javascript",
+    "application/javascript",
+    "application/typescript",
+];
+
+/// Separates the heading path from the chunk text in markdown chunks
+const HEADING_PATH_SEPARATOR: &str = " > ";
+
 /// Core domain service for chunking content into manageable pieces
 pub struct ChunkingService {
     max_chunk_size: usize,
     overlap: usize,
+    strategy: ChunkingStrategy,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
 }
 
 impl ChunkingService {
-    pub fn new(max_chunk_size: usize, overlap: usize) -> Self {
-        Self {
+    /// Chunk into windows of `max_chunk_size`, overlapping by `overlap`
+    ///
+    /// Fails unless `overlap` is smaller than a non-zero `max_chunk_size`, as chunking could
+    /// otherwise never move past the start of the content.
+    pub fn new(max_chunk_size: usize, overlap: usize) -> McpResult<Self> {
+        if max_chunk_size == 0 {
+            return Err(McpError::ValidationError(
+                "max_chunk_size must be greater than 0".to_string(),
+            ));
+        }
+        if overlap >= max_chunk_size {
+            return Err(McpError::ValidationError(format!(
+                "chunk_overlap ({}) must be smaller than max_chunk_size ({})",
+                overlap, max_chunk_size
+            )));
+        }
+
+        Ok(Self {
             max_chunk_size,
             overlap,
+            strategy: ChunkingStrategy::Fixed,
+            tokenizer: None,
+        })
+    }
+
+    /// Chunk by `strategy` instead of fixed-size windows
+    pub fn with_strategy(mut self, strategy: ChunkingStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Measure chunk sizes and overlaps in tokens of `tokenizer` instead of bytes
+    pub fn with_token_sizes(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Size of `text` in the unit chunk sizes are measured in
+    fn size(&self, text: &str) -> usize {
+        match &self.tokenizer {
+            Some(tokenizer) => tokenizer.count_tokens(text),
+            None => text.len(),
+        }
+    }
+
+    /// Ranges of windows of `text` as large as a chunk, overlapping by the overlap
+    fn window_ranges(&self, text: &str) -> Vec<Range<usize>> {
+        match &self.tokenizer {
+            Some(tokenizer) => {
+                token_window_ranges(text, self.max_chunk_size, self.overlap, tokenizer.as_ref())
+            }
+            None => window_ranges(text, self.max_chunk_size, self.overlap),
         }
     }
 
     /// Split a context into chunks with optional overlap
+    ///
+    /// Sizes and overlaps are in bytes, or in tokens with `with_token_sizes`, but chunks never
+    /// split a character: a chunk ends at the last char boundary that fits, and holds at least
+    /// one character. Contexts whose
+    /// content type has a strategy of its own, such as `text/markdown`, are chunked by it
+    /// instead of the configured one.
     pub fn chunk_context(&self, context: &Context) -> Vec<ContextChunk> {
-        let content = &context.content;
-
-        // Simple chunking strategy - split by max_chunk_size with overlap
-        let mut chunks = Vec::new();
-        let mut position = 0;
+        let strategy = context
+            .metadata
+            .content_type
+            .as_deref()
+            .and_then(ChunkingStrategy::for_content_type)
+            .unwrap_or(self.strategy);
+        self.chunk_context_with(context, strategy)
+    }
 
-        while position < content.len() {
-            let end = std::cmp::min(position + self.max_chunk_size, content.len());
-            let chunk_content = content[position..end].to_string();
+    /// Split a context into chunks by `strategy` instead of the configured one
+    pub fn chunk_context_with(
+        &self,
+        context: &Context,
+        strategy: ChunkingStrategy,
+    ) -> Vec<ContextChunk> {
+        let content = &context.content;
+        let ranges = match strategy {
+            ChunkingStrategy::Fixed => unlabelled(self.window_ranges(content)),
+            ChunkingStrategy::Sentence => {
+                unlabelled(self.merged_ranges(content, sentence_spans(content)))
+            }
+            ChunkingStrategy::Paragraph => {
+                unlabelled(self.merged_ranges(content, paragraph_spans(content)))
+            }
+            ChunkingStrategy::Markdown => self.markdown_ranges(content),
+            ChunkingStrategy::Code => self.code_ranges(content),
+        };
 
-            chunks.push(ContextChunk {
+        ranges
+            .into_iter()
+            .map(|(range, label)| ContextChunk {
                 context_id: context.id,
                 chunk_id: Uuid::new_v4(),
-                content: chunk_content,
+                content: match label {
+                    Some(label) => format!("{}\n\n{}", label, &content[range.clone()]),
+                    None => content[range.clone()].to_string(),
+                },
                 embedding: None,
-                position,
-            });
+                position: range.start,
+            })
+            .collect()
+    }
+
+    /// Ranges of chunks of markdown, labelled with the heading path of their section
+    ///
+    /// Blocks are merged like paragraphs, but never across headings, and a code block longer
+    /// than a chunk is kept whole in a chunk of its own.
+    fn markdown_ranges(&self, content: &str) -> Vec<(Range<usize>, Option<String>)> {
+        let mut ranges = Vec::new();
+        for section in markdown_sections(content) {
+            let label =
+                (!section.path.is_empty()).then(|| section.path.join(HEADING_PATH_SEPARATOR));
+            let mut run = Vec::new();
+            for block in section.blocks {
+                if block.code && self.size(&content[block.range.clone()]) > self.max_chunk_size {
+                    let merged = self.merged_ranges(content, std::mem::take(&mut run));
+                    ranges.extend(merged.into_iter().map(|range| (range, label.clone())));
+                    ranges.push((block.range, label.clone()));
+                } else {
+                    run.push(block.range);
+                }
+            }
+            let merged = self.merged_ranges(content, run);
+            ranges.extend(merged.into_iter().map(|range| (range, label.clone())));
+        }
+        ranges
+    }
+
+    /// Ranges of chunks of source code, labelled with the names of the items they hold
+    ///
+    /// Items are merged like paragraphs; an item longer than a chunk is split into windows
+    /// labelled with its name.
+    fn code_ranges(&self, content: &str) -> Vec<(Range<usize>, Option<String>)> {
+        let items = code_items(content);
+        let units = items.iter().map(|item| item.range.clone()).collect();
+
+        self.merged_ranges(content, units)
+            .into_iter()
+            .map(|range| {
+                let names: Vec<&str> = items
+                    .iter()
+                    .filter(|item| item.range.start < range.end && range.start < item.range.end)
+                    .filter_map(|item| item.name.as_deref())
+                    .collect();
+                let label = (!names.is_empty()).then(|| names.join(", "));
+                (range, label)
+            })
+            .collect()
+    }
+
+    /// Ranges of chunks of whole `units`, the sentences or paragraphs covering `content`
+    ///
+    /// A chunk starts with the trailing units of the one before it that fit in the overlap.
+    /// Units longer than a chunk are split into windows on their own.
+    fn merged_ranges(&self, content: &str, units: Vec<Range<usize>>) -> Vec<Range<usize>> {
+        let mut ranges = Vec::new();
+        let mut current: Vec<Range<usize>> = Vec::new();
+
+        for unit in units {
+            if self.size(&content[unit.clone()]) > self.max_chunk_size {
+                if let (Some(first), Some(last)) = (current.first(), current.last()) {
+                    ranges.push(first.start..last.end);
+                }
+                current.clear();
+                let windows = self.window_ranges(&content[unit.clone()]);
+                ranges.extend(
+                    windows
+                        .into_iter()
+                        .map(|window| unit.start + window.start..unit.start + window.end),
+                );
+                continue;
+            }
+
+            if let (Some(first), Some(last)) = (current.first(), current.last()) {
+                let end = last.end;
+                if self.size(&content[first.start..unit.end]) > self.max_chunk_size {
+                    ranges.push(first.start..end);
+
+                    // Carry over the trailing units that fit in the overlap
+                    let carried = current
+                        .iter()
+                        .rev()
+                        .take_while(|previous| {
+                            self.size(&content[previous.start..end]) <= self.overlap
+                        })
+                        .count();
+                    current.drain(..current.len() - carried);
+
+                    // ...as long as the new unit still fits after them
+                    while current.first().is_some_and(|first| {
+                        self.size(&content[first.start..unit.end]) > self.max_chunk_size
+                    }) {
+                        current.remove(0);
+                    }
+                }
+            }
+            current.push(unit);
+        }
+
+        if let (Some(first), Some(last)) = (current.first(), current.last()) {
+            ranges.push(first.start..last.end);
+        }
+        ranges
+    }
+}
+
+/// Ranges without a label to put in front of their chunks
+fn unlabelled(ranges: Vec<Range<usize>>) -> Vec<(Range<usize>, Option<String>)> {
+    ranges.into_iter().map(|range| (range, None)).collect()
+}
+
+/// Ranges of windows of at most `max_size` bytes, each starting `overlap` bytes before the
+/// previous one ended
+fn window_ranges(text: &str, max_size: usize, overlap: usize) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut position = 0;
+
+    while position < text.len() {
+        let mut end = floor_char_boundary(text, position + max_size);
+        if end <= position {
+            end = ceil_char_boundary(text, position + 1);
+        }
+        ranges.push(position..end);
+
+        // Move position forward, accounting for overlap
+        if end == text.len() {
+            break;
+        }
+        let next = floor_char_boundary(text, end.saturating_sub(overlap));
+        position = if next > position { next } else { end };
+    }
+
+    ranges
+}
+
+/// Ranges of windows of at most `max_tokens` tokens, each starting at most `overlap` tokens
+/// before the previous one ended
+fn token_window_ranges(
+    text: &str,
+    max_tokens: usize,
+    overlap: usize,
+    tokenizer: &dyn Tokenizer,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut position = 0;
+
+    while position < text.len() {
+        let end = furthest_end(text, position, |end| {
+            tokenizer.count_tokens(&text[position..end]) <= max_tokens
+        });
+        ranges.push(position..end);
+
+        if end == text.len() {
+            break;
+        }
+        position = earliest_start(text, position, end, |start| {
+            tokenizer.count_tokens(&text[start..end]) <= overlap
+        });
+    }
+
+    ranges
+}
+
+/// The last char boundary after `start` that `fits`, or the first if none does
+///
+/// Ends are probed at growing distances until one doesn't fit, then bisected, so only about
+/// as much text as fits is measured, a logarithmic number of times.
+fn furthest_end(text: &str, start: usize, fits: impl Fn(usize) -> bool) -> usize {
+    let mut good = ceil_char_boundary(text, start + 1);
+    if !fits(good) {
+        return good;
+    }
+
+    let mut step = 64;
+    let mut bad = loop {
+        if good == text.len() {
+            return good;
+        }
+        let probe = ceil_char_boundary(text, good + step);
+        if !fits(probe) {
+            break probe;
+        }
+        good = probe;
+        step *= 2;
+    };
+
+    loop {
+        let mid = match floor_char_boundary(text, good + (bad - good) / 2) {
+            mid if mid > good => mid,
+            _ => ceil_char_boundary(text, good + 1),
+        };
+        if mid >= bad {
+            return good;
+        }
+        if fits(mid) {
+            good = mid;
+        } else {
+            bad = mid;
+        }
+    }
+}
+
+/// The first char boundary after `lower` that `fits`, where `end` always does
+fn earliest_start(text: &str, lower: usize, end: usize, fits: impl Fn(usize) -> bool) -> usize {
+    let (mut bad, mut good) = (lower, end);
+    loop {
+        let mid = match floor_char_boundary(text, bad + (good - bad) / 2) {
+            mid if mid > bad => mid,
+            _ => ceil_char_boundary(text, bad + 1),
+        };
+        if mid >= good {
+            return good;
+        }
+        if fits(mid) {
+            good = mid;
+        } else {
+            bad = mid;
+        }
+    }
+}
+
+/// Closing quotes and brackets that may follow the punctuation ending a sentence
+const SENTENCE_CLOSERS: &[char] = &['"', '\'', ')', ']', '\u{2019}', '\u{201D}'];
+
+/// Ranges of the sentences of `text`, which together cover all of it
+///
+/// A sentence ends at `.`, `!` or `?` followed by whitespace, at their full-width forms, or
+/// at a blank line. The whitespace after a sentence belongs to it.
+fn sentence_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        let ends = match c {
+            '.' | '!' | '?' => {
+                while chars
+                    .peek()
+                    .is_some_and(|(_, next)| SENTENCE_CLOSERS.contains(next))
+                {
+                    chars.next();
+                }
+                chars.peek().is_none_or(|(_, next)| next.is_whitespace())
+            }
+            '\u{3002}' | '\u{FF01}' | '\u{FF1F}' => true,
+            '\n' => text[index + 1..]
+                .trim_start_matches([' ', '\t', '\r'])
+                .starts_with('\n'),
+            _ => false,
+        };
+
+        if ends {
+            let mut end = chars.peek().map_or(text.len(), |&(next, _)| next);
+            while let Some(&(next_index, next)) = chars.peek() {
+                if !next.is_whitespace() {
+                    break;
+                }
+                end = next_index + next.len_utf8();
+                chars.next();
+            }
+            spans.push(start..end);
+            start = end;
+        }
+    }
+
+    if start < text.len() {
+        spans.push(start..text.len());
+    }
+    spans
+}
+
+/// Ranges of the paragraphs of `text`, which together cover all of it
+///
+/// Paragraphs are separated by one or more blank lines, which belong to the paragraph before
+/// them. Lines holding only whitespace, such as the `\r` of a Windows line ending, are blank.
+fn paragraph_spans(text: &str) -> Vec<Range<usize>> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+    let mut has_text = false;
+    let mut after_blank = false;
+
+    for line in text.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if !blank && after_blank {
+            spans.push(start..offset);
+            start = offset;
+        }
+        after_blank = blank && has_text;
+        has_text |= !blank;
+        offset += line.len();
+    }
+
+    if start < text.len() {
+        spans.push(start..text.len());
+    }
+    spans
+}
+
+/// The blocks under a markdown heading, up to the next heading
+struct MarkdownSection {
+    /// Titles of the heading and the headings it is nested in, outermost first
+    path: Vec<String>,
+    blocks: Vec<MarkdownBlock>,
+}
+
+/// A top-level markdown block, such as a heading, paragraph, list or code block
+struct MarkdownBlock {
+    range: Range<usize>,
+    code: bool,
+}
+
+/// The sections of markdown `text`, whose blocks together cover all of it
+///
+/// Text before the first heading is a section without a path, and front matter is a section
+/// of its own. The whitespace after a block belongs to it.
+fn markdown_sections(text: &str) -> Vec<MarkdownSection> {
+    let mut sections = vec![MarkdownSection {
+        path: Vec::new(),
+        blocks: Vec::new(),
+    }];
+    let mut path: Vec<(usize, String)> = Vec::new();
+    let mut depth = 0;
+    let mut heading: Option<(usize, String)> = None;
+    let mut front_matter = false;
+    let mut code = false;
+
+    let options = Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS;
+    for (event, range) in Parser::new_ext(text, options).into_offset_iter() {
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 {
+                    match tag {
+                        Tag::Heading { level, .. } => {
+                            heading = Some((level as usize, String::new()))
+                        }
+                        Tag::CodeBlock(_) => code = true,
+                        Tag::MetadataBlock(_) => front_matter = true,
+                        _ => {}
+                    }
+                }
+                depth += 1;
+            }
+            Event::End(_) => {
+                depth -= 1;
+                if depth > 0 {
+                    continue;
+                }
+
+                if let Some((level, title)) = heading.take() {
+                    while path.last().is_some_and(|(outer, _)| *outer >= level) {
+                        path.pop();
+                    }
+                    path.push((level, title.trim().to_string()));
+                    sections.push(MarkdownSection {
+                        path: path.iter().map(|(_, title)| title.clone()).collect(),
+                        blocks: Vec::new(),
+                    });
+                }
+                if let Some(section) = sections.last_mut() {
+                    section.blocks.push(MarkdownBlock {
+                        range,
+                        code: std::mem::take(&mut code),
+                    });
+                }
+                if std::mem::take(&mut front_matter) {
+                    sections.push(MarkdownSection {
+                        path: Vec::new(),
+                        blocks: Vec::new(),
+                    });
+                }
+            }
+            Event::Text(fragment) | Event::Code(fragment) => {
+                if let Some((_, title)) = heading.as_mut() {
+                    title.push_str(&fragment);
+                }
+            }
+            _ if depth == 0 => {
+                if let Some(section) = sections.last_mut() {
+                    section.blocks.push(MarkdownBlock { range, code: false });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Stretch the blocks over the whitespace between them
+    let mut end = text.len();
+    for block in sections
+        .iter_mut()
+        .rev()
+        .flat_map(|section| section.blocks.iter_mut().rev())
+    {
+        block.range.end = end;
+        end = block.range.start;
+    }
+    if let Some(first) = sections
+        .iter_mut()
+        .flat_map(|section| section.blocks.iter_mut())
+        .next()
+    {
+        first.range.start = 0;
+    }
+    sections
+}
+
+/// A top-level item of source code, with the comments and attributes leading up to it
+struct CodeItem {
+    range: Range<usize>,
+    /// Kind and name of the item, such as `fn main` or `impl Display for Context`
+    name: Option<String>,
+}
+
+/// Keywords introducing the items named in code chunks, across common languages
+const ITEM_KEYWORDS: &[&str] = &[
+    "fn",
+    "struct",
+    "enum",
+    "trait",
+    "impl",
+    "mod",
+    "type",
+    "union",
+    "macro_rules!",
+    "def",
+    "class",
+    "func",
+    "function",
+    "interface",
+    "module",
+];
 
-            // Move position forward, accounting for overlap
-            if end == content.len() {
-                break;
+/// The top-level items of source `text`, which together cover all of it
+///
+/// An item starts at an unindented line outside any bracket, along with the comment,
+/// attribute and decorator lines right above it. Blank lines belong to the item before them.
+fn code_items(text: &str) -> Vec<CodeItem> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut name = None;
+    let mut lead: Option<usize> = None;
+    let mut depth = 0;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if depth == 0 && trimmed.is_empty() {
+            lead = None;
+        } else if depth == 0
+            && !line.starts_with(char::is_whitespace)
+            && !trimmed.starts_with(['}', ')', ']'])
+        {
+            if ["//", "/*", "*", "#", "@"]
+                .iter()
+                .any(|marker| trimmed.starts_with(marker))
+            {
+                lead.get_or_insert(offset);
+            } else {
+                let item_start = lead.take().unwrap_or(offset);
+                if item_start > start {
+                    items.push(CodeItem {
+                        range: start..item_start,
+                        name: name.take(),
+                    });
+                    start = item_start;
+                }
+                name = item_name(trimmed);
             }
-            position = position + self.max_chunk_size - self.overlap;
         }
+        depth = bracket_depth(line, depth);
+        offset += line.len();
+    }
+
+    if start < text.len() {
+        items.push(CodeItem {
+            range: start..text.len(),
+            name,
+        });
+    }
+    items
+}
+
+/// The kind and name of the item a line of code starts, if it names one
+fn item_name(line: &str) -> Option<String> {
+    let header = line.split('{').next().unwrap_or_default().trim_end();
+    let header = header.trim_end_matches(':');
+    let mut words = header
+        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '!'))
+        .filter(|word| !word.is_empty());
+    let keyword = words.find(|word| ITEM_KEYWORDS.contains(word))?;
+
+    // Impl blocks are told apart by what they implement, not by a name
+    if keyword == "impl" {
+        let start = header.find("impl")?;
+        let header = header[start..].split(" where").next().unwrap_or_default();
+        return Some(header.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+    words.next().map(|name| format!("{} {}", keyword, name))
+}
+
+/// How deep in brackets the code is after `line`, starting `depth` deep
+///
+/// Brackets in string literals and after `//` or `#` comment markers don't count.
+fn bracket_depth(line: &str, mut depth: usize) -> usize {
+    let mut chars = line.chars().peekable();
+    let mut quote = None;
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '`') => quote = Some(c),
+            (None, '/') if chars.peek() == Some(&'/') => break,
+            (None, '#') => break,
+            (None, '{' | '(' | '[') => depth += 1,
+            (None, '}' | ')' | ']') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// The last char boundary of `text` at or before `index`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    if index >= text.len() {
+        return text.len();
+    }
+    (0..=index)
+        .rev()
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(0)
+}
+
+/// The first char boundary of `text` at or after `index`
+fn ceil_char_boundary(text: &str, index: usize) -> usize {
+    (index..text.len())
+        .find(|&i| text.is_char_boundary(i))
+        .unwrap_or(text.len())
+}
+
+/// How `RetrievalService` scores contexts against a query
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Ranking {
+    /// Fraction of the query terms found anywhere in the context
+    TermMatch,
+    /// Okapi BM25 over the context's chunks
+    Bm25(Bm25),
+}
+
+/// BM25 parameters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bm25 {
+    /// How quickly repeating a term stops raising the score
+    pub k1: f32,
+    /// How much longer chunks are penalized, from 0 (not at all) to 1 (fully)
+    pub b: f32,
+    /// How the scores of a context's chunks make up the context's score
+    pub aggregation: ChunkAggregation,
+}
+
+/// How chunk scores are combined into a context score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkAggregation {
+    /// Score of the best matching chunk
+    Max,
+    /// Sum of the chunk scores, favoring contexts that match in many places
+    Sum,
+}
+
+/// Typo-tolerant matching of query terms that aren't found exactly
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fuzzy {
+    /// Most single-character edits between a query term and a term it matches
+    pub max_edits: usize,
+    /// Shortest term, in characters, that is matched fuzzily; shorter terms only match exactly
+    pub min_term_length: usize,
+    /// Share of an exact match's score that a fuzzy match is worth
+    pub weight: f32,
+}
+
+impl Default for Fuzzy {
+    fn default() -> Self {
+        Self {
+            max_edits: 2,
+            min_term_length: 5,
+            weight: 0.5,
+        }
+    }
+}
+
+impl Fuzzy {
+    /// Whether `term` is within `max_edits` of `query_term`, both long enough to compare
+    pub fn matches(&self, query_term: &str, term: &str) -> bool {
+        let query_length = query_term.chars().count();
+        let length = term.chars().count();
+        if query_length < self.min_term_length || length < self.min_term_length {
+            return false;
+        }
+        query_length.abs_diff(length) <= self.max_edits
+            && edit_distance(query_term, term) <= self.max_edits
+    }
+}
+
+/// Levenshtein distance between two strings, counted in characters
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+impl Bm25 {
+    pub fn new(k1: f32, b: f32, aggregation: ChunkAggregation) -> Self {
+        Self { k1, b, aggregation }
+    }
+
+    /// Score each context by its chunks, or by its content if none of its chunks are given
+    ///
+    /// With `fuzzy`, a query term missing from a chunk counts the chunk's terms it fuzzily
+    /// matches instead, discounted by the fuzzy weight.
+    fn score(
+        &self,
+        query: &str,
+        contexts: &[Context],
+        chunks: &[ContextChunk],
+        fuzzy: Option<&Fuzzy>,
+    ) -> Vec<f32> {
+        let mut documents: Vec<(usize, HashMap<String, usize>, usize)> = Vec::new();
+        for (index, context) in contexts.iter().enumerate() {
+            let mut found = false;
+            for chunk in chunks.iter().filter(|chunk| chunk.context_id == context.id) {
+                let (counts, length) = term_counts(&chunk.content);
+                documents.push((index, counts, length));
+                found = true;
+            }
+            if !found {
+                let (counts, length) = term_counts(&context.content);
+                documents.push((index, counts, length));
+            }
+        }
+
+        let mut scores = vec![0.0; contexts.len()];
+        if documents.is_empty() {
+            return scores;
+        }
+
+        let mut query_terms: Vec<String> = terms(query).collect();
+        query_terms.sort();
+        query_terms.dedup();
+
+        // Frequency of each query term in each document, and what a match there is worth
+        let frequencies: Vec<Vec<(f32, f32)>> = documents
+            .iter()
+            .map(|(_, counts, _)| {
+                query_terms
+                    .iter()
+                    .map(|term| term_frequency(term, counts, fuzzy))
+                    .collect()
+            })
+            .collect();
+
+        let total = documents.len() as f32;
+        let average_length = documents
+            .iter()
+            .map(|(_, _, length)| *length)
+            .sum::<usize>() as f32
+            / total;
+        let idf: Vec<f32> = (0..query_terms.len())
+            .map(|position| {
+                let frequency = frequencies
+                    .iter()
+                    .filter(|terms| terms[position].0 > 0.0)
+                    .count() as f32;
+                ((total - frequency + 0.5) / (frequency + 0.5) + 1.0).ln()
+            })
+            .collect();
+
+        for ((index, _, length), terms) in documents.iter().zip(&frequencies) {
+            let normalization =
+                self.k1 * (1.0 - self.b + self.b * *length as f32 / average_length.max(1.0));
+            let score: f32 = terms
+                .iter()
+                .zip(&idf)
+                .map(|((frequency, weight), idf)| {
+                    weight * idf * frequency * (self.k1 + 1.0) / (frequency + normalization)
+                })
+                .sum();
+
+            scores[*index] = match self.aggregation {
+                ChunkAggregation::Max => scores[*index].max(score),
+                ChunkAggregation::Sum => scores[*index] + score,
+            };
+        }
+        scores
+    }
+}
+
+impl Default for Bm25 {
+    fn default() -> Self {
+        Self::new(1.2, 0.75, ChunkAggregation::Max)
+    }
+}
+
+/// Lowercased alphanumeric terms of a text
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// Occurrences of a query term in a document's term counts, and the weight of the match
+///
+/// Terms that don't occur fall back to the fuzzy matches, if any, at the fuzzy weight.
+fn term_frequency(
+    term: &str,
+    counts: &HashMap<String, usize>,
+    fuzzy: Option<&Fuzzy>,
+) -> (f32, f32) {
+    if let Some(&count) = counts.get(term) {
+        return (count as f32, 1.0);
+    }
+    let Some(fuzzy) = fuzzy else {
+        return (0.0, 0.0);
+    };
+
+    let count: usize = counts
+        .iter()
+        .filter(|(candidate, _)| fuzzy.matches(term, candidate))
+        .map(|(_, count)| count)
+        .sum();
+    if count == 0 {
+        (0.0, 0.0)
+    } else {
+        (count as f32, fuzzy.weight)
+    }
+}
 
-        chunks
+/// Counts of the terms of a text, and the number of terms
+fn term_counts(text: &str) -> (HashMap<String, usize>, usize) {
+    let mut counts = HashMap::new();
+    let mut length = 0;
+    for term in terms(text) {
+        *counts.entry(term).or_insert(0) += 1;
+        length += 1;
     }
+    (counts, length)
 }
 
 /// Core domain service for ranking and retrieving contexts
 pub struct RetrievalService {
     max_results: usize,
+    ranking: Ranking,
+    fuzzy: Fuzzy,
+    expansion_weight: f32,
 }
 
 impl RetrievalService {
+    /// Rank by term matching, with searches returning at most `max_results` contexts
     pub fn new(max_results: usize) -> Self {
-        Self { max_results }
+        Self {
+            max_results,
+            ranking: Ranking::TermMatch,
+            fuzzy: Fuzzy::default(),
+            expansion_weight: 0.5,
+        }
+    }
+
+    pub fn with_ranking(mut self, ranking: Ranking) -> Self {
+        self.ranking = ranking;
+        self
+    }
+
+    /// Match terms fuzzily by `fuzzy` in rankings that ask for it
+    pub fn with_fuzzy(mut self, fuzzy: Fuzzy) -> Self {
+        self.fuzzy = fuzzy;
+        self
+    }
+
+    /// Count expansion terms for `weight` of what the query's own terms count (0.5 by default)
+    pub fn with_expansion_weight(mut self, weight: f32) -> Self {
+        self.expansion_weight = weight;
+        self
+    }
+
+    /// Most contexts a search returns
+    pub fn max_results(&self) -> usize {
+        self.max_results
+    }
+
+    /// Weight of expansion terms against the query's own terms
+    pub fn expansion_weight(&self) -> f32 {
+        self.expansion_weight
     }
 
-    /// Rank contexts by relevance and return the top matching results
+    /// Rank contexts by relevance, returning every context best first
+    ///
+    /// BM25 scores the given chunks of each context; `context_chunks` may hold chunks of
+    /// other contexts too.
     pub fn rank_contexts(
         &self,
         query: &str,
         available_contexts: &[Context],
-        _context_chunks: &[ContextChunk],
+        context_chunks: &[ContextChunk],
     ) -> Vec<(Context, f32)> {
-        // In a real implementation, this would use semantic search or other
-        // sophisticated ranking algorithms. For this example, we'll use a simple
-        // implementation based on text matching.
+        let scores = self.lexical_scores(query, available_contexts, context_chunks, false);
+        Self::sorted(available_contexts.iter().cloned().zip(scores).collect())
+    }
+
+    /// Rank contexts by a blend of their lexical score and their vector similarity, best first
+    ///
+    /// Lexical scores are scaled so the best context scores 1, then weighted by `1 - alpha`
+    /// against the best similarity of each context's chunks in `vector_scores`, weighted by
+    /// `alpha`. Contexts without a vector score count as dissimilar. With `fuzzy`, query terms
+    /// that aren't found exactly also match near misses, for part of the score. `expansions`,
+    /// such as synonyms of query terms, add their own lexical score at the expansion weight.
+    #[allow(clippy::too_many_arguments)]
+    pub fn rank_hybrid(
+        &self,
+        query: &str,
+        available_contexts: &[Context],
+        context_chunks: &[ContextChunk],
+        vector_scores: &HashMap<Uuid, f32>,
+        alpha: f32,
+        fuzzy: bool,
+        expansions: &[String],
+    ) -> Vec<(Context, f32)> {
+        let alpha = alpha.clamp(0.0, 1.0);
+        let mut lexical = self.lexical_scores(query, available_contexts, context_chunks, fuzzy);
+        if !expansions.is_empty() {
+            let expanded = self.lexical_scores(
+                &expansions.join(" "),
+                available_contexts,
+                context_chunks,
+                fuzzy,
+            );
+            for (score, expanded) in lexical.iter_mut().zip(expanded) {
+                *score += self.expansion_weight * expanded;
+            }
+        }
+        let best = lexical.iter().copied().fold(0.0, f32::max);
+
+        let scored = available_contexts
+            .iter()
+            .zip(lexical)
+            .map(|(context, lexical)| {
+                let lexical = if best > 0.0 { lexical / best } else { 0.0 };
+                let vector = vector_scores.get(&context.id).copied().unwrap_or(0.0);
+                (context.clone(), alpha * vector + (1.0 - alpha) * lexical)
+            })
+            .collect();
+        Self::sorted(scored)
+    }
 
-        let mut scored_contexts: Vec<(Context, f32)> = available_contexts
+    /// Score of each context against the query, in the order given
+    fn lexical_scores(
+        &self,
+        query: &str,
+        available_contexts: &[Context],
+        context_chunks: &[ContextChunk],
+        fuzzy: bool,
+    ) -> Vec<f32> {
+        let fuzzy = fuzzy.then_some(&self.fuzzy);
+        match self.ranking {
+            Ranking::TermMatch => Self::term_match(query, available_contexts, fuzzy),
+            Ranking::Bm25(bm25) => bm25.score(query, available_contexts, context_chunks, fuzzy),
+        }
+    }
+
+    /// Scored contexts, best first
+    ///
+    /// Equal scores fall back to [`Context::listing_order`], so a ranking doesn't depend on the
+    /// order the contexts were found in.
+    fn sorted(mut scored_contexts: Vec<(Context, f32)>) -> Vec<(Context, f32)> {
+        // Sort by score descending
+        scored_contexts.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.0.listing_order(&b.0))
+        });
+        scored_contexts
+    }
+
+    fn term_match(query: &str, available_contexts: &[Context], fuzzy: Option<&Fuzzy>) -> Vec<f32> {
+        available_contexts
             .iter()
             .map(|ctx| {
                 // Simple scoring: ratio of query terms found in context
                 let query_terms: Vec<&str> = query.split_whitespace().collect();
-                let mut matches = 0;
+                let content = ctx.content.to_lowercase();
+                let mut matches = 0.0;
 
                 for term in &query_terms {
-                    if ctx.content.to_lowercase().contains(&term.to_lowercase()) {
-                        matches += 1;
+                    let term = term.to_lowercase();
+                    if content.contains(&term) {
+                        matches += 1.0;
+                    } else if let Some(fuzzy) = fuzzy {
+                        // Near misses count for part of a match
+                        if terms(&content).any(|word| fuzzy.matches(&term, &word)) {
+                            matches += fuzzy.weight;
+                        }
                     }
                 }
 
-                let score = if query_terms.is_empty() {
+                if query_terms.is_empty() {
                     0.0
                 } else {
-                    matches as f32 / query_terms.len() as f32
-                };
+                    matches / query_terms.len() as f32
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{ContextMetadata, HeuristicTokenizer};
+
+    fn context(content: &str) -> Context {
+        Context::new(content, ContextMetadata::default())
+    }
 
-                (ctx.clone(), score)
+    fn chunks(context: &Context, contents: &[&str]) -> Vec<ContextChunk> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(position, content)| ContextChunk {
+                chunk_id: Uuid::new_v4(),
+                context_id: context.id,
+                content: content.to_string(),
+                position,
+                embedding: None,
             })
+            .collect()
+    }
+
+    fn ranked(
+        service: &RetrievalService,
+        query: &str,
+        contexts: &[Context],
+        chunks: &[ContextChunk],
+    ) -> Vec<Uuid> {
+        service
+            .rank_contexts(query, contexts, chunks)
+            .into_iter()
+            .map(|(context, _)| context.id)
+            .collect()
+    }
+
+    /// The content the chunks cover, each chunk adding what the previous ones didn't
+    fn reassembled(chunks: &[ContextChunk]) -> String {
+        let mut text = String::new();
+        for chunk in chunks {
+            assert!(chunk.position <= text.len(), "Chunks leave a gap");
+            text.push_str(&chunk.content[text.len() - chunk.position..]);
+        }
+        text
+    }
+
+    #[test]
+    fn test_chunking_multi_byte_content_at_chunk_boundaries() {
+        // Multi-byte characters straddling, ending at and starting right after the boundary
+        for content in [
+            "abcdefghi🦀jklmnop",
+            "abcdefghij🦀klmnop",
+            "abcdefgh東京大学ijklmnop",
+            "ééééééééééééééééé",
+            "🦀🦀🦀🦀🦀🦀🦀🦀",
+        ] {
+            for overlap in [0, 1, 3, 5] {
+                let context = context(content);
+                let chunks = ChunkingService::new(10, overlap)
+                    .unwrap()
+                    .chunk_context(&context);
+
+                for chunk in &chunks {
+                    assert!(chunk.content.len() <= 10, "{:?}", chunk.content);
+                    assert_eq!(
+                        &content[chunk.position..chunk.position + chunk.content.len()],
+                        chunk.content
+                    );
+                }
+                assert_eq!(reassembled(&chunks), content);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chunking_rejects_sizes_it_could_never_advance_by() {
+        for (max_chunk_size, overlap, message) in [
+            (0, 0, "max_chunk_size must be greater than 0"),
+            (
+                10,
+                10,
+                "chunk_overlap (10) must be smaller than max_chunk_size (10)",
+            ),
+            (
+                10,
+                25,
+                "chunk_overlap (25) must be smaller than max_chunk_size (10)",
+            ),
+        ] {
+            match ChunkingService::new(max_chunk_size, overlap) {
+                Err(McpError::ValidationError(err)) => assert_eq!(err, message),
+                _ => panic!("size {} overlap {} accepted", max_chunk_size, overlap),
+            }
+        }
+
+        // The largest overlap still moves every window forward
+        let chunks = ChunkingService::new(4, 3)
+            .unwrap()
+            .chunk_context(&context("abcdefg"));
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(contents, ["abcd", "bcde", "cdef", "defg"]);
+    }
+
+    #[test]
+    fn test_chunking_takes_a_whole_character_larger_than_the_chunk_size() {
+        let context = context("a🦀b");
+        let chunks = ChunkingService::new(2, 1).unwrap().chunk_context(&context);
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(contents, ["a", "🦀", "b"]);
+        assert_eq!(reassembled(&chunks), "a🦀b");
+    }
+
+    #[test]
+    fn test_chunking_random_unicode_never_panics_and_reassembles() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+
+        let alphabet = ['a', ' ', '\n', 'é', 'ß', '東', '語', '🦀', '👍', '\u{301}'];
+        let mut rng = StdRng::seed_from_u64(1101);
+
+        for _ in 0..500 {
+            let length = rng.gen_range(0..200);
+            let content: String = (0..length)
+                .map(|_| alphabet[rng.gen_range(0..alphabet.len())])
+                .collect();
+            let max_chunk_size = rng.gen_range(1..40);
+            let overlap = rng.gen_range(0..max_chunk_size);
+
+            let chunks = ChunkingService::new(max_chunk_size, overlap)
+                .unwrap()
+                .chunk_context(&context(&content));
+            assert_eq!(
+                reassembled(&chunks),
+                content,
+                "size {} overlap {}",
+                max_chunk_size,
+                overlap
+            );
+        }
+    }
+
+    const PROSE: &str = "The cache is warmed at startup. Requests that miss it go to the \
+        database! Is the database replicated? Yes, to two regions (\"east\" and \"west\"). \
+        Failover takes a minute.\n\nBackups run nightly. They are kept for a month.";
+
+    fn sentence_chunks(content: &str, max_chunk_size: usize, overlap: usize) -> Vec<ContextChunk> {
+        ChunkingService::new(max_chunk_size, overlap)
+            .unwrap()
+            .with_strategy(ChunkingStrategy::Sentence)
+            .chunk_context(&context(content))
+    }
+
+    #[test]
+    fn test_sentence_spans_split_prose_into_sentences() {
+        let spans: Vec<&str> = sentence_spans(PROSE)
+            .into_iter()
+            .map(|s| &PROSE[s])
             .collect();
+        assert_eq!(
+            spans,
+            vec![
+                "The cache is warmed at startup. ",
+                "Requests that miss it go to the database! ",
+                "Is the database replicated? ",
+                "Yes, to two regions (\"east\" and \"west\"). ",
+                "Failover takes a minute.\n\n",
+                "Backups run nightly. ",
+                "They are kept for a month.",
+            ]
+        );
+        // Abbreviation-like dots and decimals without whitespace after them don't end sentences
+        assert_eq!(sentence_spans("Version 1.5 is out.").len(), 1);
+    }
 
-        // Sort by score descending
-        scored_contexts.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    #[test]
+    fn test_sentence_chunks_never_start_or_end_mid_sentence() {
+        let sentences = sentence_spans(PROSE);
+        let starts: Vec<usize> = sentences.iter().map(|s| s.start).collect();
+        let ends: Vec<usize> = sentences.iter().map(|s| s.end).collect();
 
-        // Return top results
-        scored_contexts.truncate(self.max_results);
-        scored_contexts
+        for (max_chunk_size, overlap) in [(50, 0), (80, 30), (120, 60), (1000, 200)] {
+            let chunks = sentence_chunks(PROSE, max_chunk_size, overlap);
+            assert_eq!(reassembled(&chunks), PROSE);
+            for chunk in &chunks {
+                assert!(chunk.content.len() <= max_chunk_size);
+                assert!(starts.contains(&chunk.position), "{:?}", chunk.content);
+                assert!(
+                    ends.contains(&(chunk.position + chunk.content.len())),
+                    "{:?}",
+                    chunk.content
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sentence_chunks_carry_trailing_sentences_as_overlap() {
+        let chunks = sentence_chunks("One. Two. Three. Four.", 12, 5);
+        let contents: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(contents, vec!["One. Two. ", "Two. Three. ", "Four."]);
+    }
+
+    #[test]
+    fn test_sentence_longer_than_a_chunk_is_split_into_windows() {
+        let content = "Short one. This sentence is far longer than a chunk. End.";
+        let chunks = sentence_chunks(content, 16, 0);
+        assert_eq!(reassembled(&chunks), content);
+        assert_eq!(chunks[0].content, "Short one. ");
+        assert!(chunks.iter().all(|c| c.content.len() <= 16));
+        assert_eq!(chunks.last().unwrap().content, "End.");
+    }
+
+    fn paragraph_chunks(content: &str, max_chunk_size: usize, overlap: usize) -> Vec<ContextChunk> {
+        ChunkingService::new(max_chunk_size, overlap)
+            .unwrap()
+            .with_strategy(ChunkingStrategy::Paragraph)
+            .chunk_context(&context(content))
+    }
+
+    #[test]
+    fn test_paragraph_chunks_merge_tiny_paragraphs_and_split_huge_ones() {
+        let huge = "word ".repeat(30);
+        let content = format!(
+            "One.\n\nTwo.\n\n\n\nThree.\n\n{}\n\nFour.\n\nFive.",
+            huge.trim_end()
+        );
+        let chunks = paragraph_chunks(&content, 40, 0);
+        assert_eq!(reassembled(&chunks), content);
+
+        let contents: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(contents[0], "One.\n\nTwo.\n\n\n\nThree.\n\n");
+        assert_eq!(contents[contents.len() - 1], "Four.\n\nFive.");
+        // The huge paragraph is windowed on its own, never sharing a chunk with its neighbours
+        let huge_start = content.find("word").unwrap();
+        let huge_chunks: Vec<&ContextChunk> = chunks
+            .iter()
+            .filter(|c| c.content.starts_with("word"))
+            .collect();
+        assert_eq!(huge_chunks[0].position, huge_start);
+        assert!(huge_chunks.len() > 1);
+        assert!(chunks.iter().all(|c| c.content.len() <= 40));
+    }
+
+    #[test]
+    fn test_paragraph_chunks_with_windows_line_endings() {
+        let content =
+            "First paragraph,\r\nstill the first.\r\n\r\nSecond one.\r\n \r\nThird one.\r\n";
+        let spans: Vec<&str> = paragraph_spans(content)
+            .into_iter()
+            .map(|s| &content[s])
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                "First paragraph,\r\nstill the first.\r\n\r\n",
+                "Second one.\r\n \r\n",
+                "Third one.\r\n",
+            ]
+        );
+
+        let chunks = paragraph_chunks(content, 40, 15);
+        let contents: Vec<(usize, &str)> = chunks
+            .iter()
+            .map(|c| (c.position, c.content.as_str()))
+            .collect();
+        assert_eq!(
+            contents,
+            vec![
+                (0, "First paragraph,\r\nstill the first.\r\n\r\n"),
+                (38, "Second one.\r\n \r\nThird one.\r\n"),
+            ]
+        );
+    }
+
+    fn markdown(content: &str) -> Context {
+        let mut context = context(content);
+        context.metadata.content_type = Some("text/markdown; charset=utf-8".to_string());
+        context
+    }
+
+    /// The label of each chunk and the source it was taken from, checked against its position
+    fn markdown_chunks(content: &str, max_chunk_size: usize) -> Vec<(Option<String>, String)> {
+        ChunkingService::new(max_chunk_size, 0)
+            .unwrap()
+            .chunk_context(&markdown(content))
+            .into_iter()
+            .map(|chunk| {
+                let source = &content[chunk.position..];
+                if source.starts_with(&chunk.content) {
+                    return (None, chunk.content);
+                }
+                let (label, text) = chunk.content.split_once("\n\n").unwrap();
+                assert!(source.starts_with(text), "{:?}", chunk.content);
+                (Some(label.to_string()), text.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_markdown_chunks_follow_nested_headings() {
+        let content = "Intro before any heading.\n\n\
+            # Install\n\nPick your platform.\n\n\
+            ## Linux\n\nRun `apt install mcp`.\n\n\
+            ### Arch\n\nUse the AUR.\n\n\
+            ## macOS\n\nRun brew.\n\n\
+            # Usage\n\nStart the server.\n";
+        let chunks = markdown_chunks(content, 1000);
+
+        let labels: Vec<Option<&str>> = chunks.iter().map(|(label, _)| label.as_deref()).collect();
+        assert_eq!(
+            labels,
+            vec![
+                None,
+                Some("Install"),
+                Some("Install > Linux"),
+                Some("Install > Linux > Arch"),
+                Some("Install > macOS"),
+                Some("Usage"),
+            ]
+        );
+        assert_eq!(chunks[2].1, "## Linux\n\nRun `apt install mcp`.\n\n");
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|(_, source)| source.as_str())
+                .collect::<String>(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_markdown_keeps_code_fences_longer_than_a_chunk_whole() {
+        let code: String = (0..20).map(|i| format!("let x{} = {};\n", i, i)).collect();
+        let content = format!(
+            "# Build\n\nCompile it first.\n\n```rust\n{}```\n\nThen run the tests.\n",
+            code
+        );
+        let chunks = markdown_chunks(&content, 60);
+
+        let fence = format!("```rust\n{}```\n\n", code);
+        assert!(fence.len() > 60);
+        assert!(chunks.contains(&(Some("Build".to_string()), fence)));
+        assert_eq!(
+            chunks.last().unwrap(),
+            &(
+                Some("Build".to_string()),
+                "Then run the tests.\n".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_markdown_front_matter_is_a_chunk_of_its_own() {
+        let content = "---\ntitle: Guide\ntags: [ops]\n---\n\nWelcome.\n\n# Setup\n\nSteps.\n";
+        let chunks = markdown_chunks(content, 1000);
+        assert_eq!(
+            chunks,
+            vec![
+                (None, "---\ntitle: Guide\ntags: [ops]\n---\n\n".to_string()),
+                (None, "Welcome.\n\n".to_string()),
+                (Some("Setup".to_string()), "# Setup\n\nSteps.\n".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_markdown_chunking_is_picked_by_content_type_unless_overridden() {
+        let content = "# Title\n\nBody text.\n";
+        let service = ChunkingService::new(1000, 0).unwrap();
+
+        let chunks = service.chunk_context(&markdown(content));
+        assert_eq!(chunks[0].content, "Title\n\n# Title\n\nBody text.\n");
+
+        let chunks = service.chunk_context(&context(content));
+        assert_eq!(chunks[0].content, content);
+        let chunks = service.chunk_context_with(&markdown(content), ChunkingStrategy::Fixed);
+        assert_eq!(chunks[0].content, content);
+    }
+
+    const RUST_SOURCE: &str = r#"use std::fmt;
+
+/// Adds two numbers
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[derive(Debug)]
+pub struct Point {
+    x: i32,
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Not the end of the impl: }
+        write!(f, "}} {}", self.x)
+    }
+}
+
+fn main() {
+    let text = "{ unbalanced";
+    println!("{} {}", text, add(1, 2));
+}
+"#;
+
+    fn code(content: &str, content_type: &str) -> Context {
+        let mut context = context(content);
+        context.metadata.content_type = Some(content_type.to_string());
+        context
+    }
+
+    #[test]
+    fn test_code_items_start_at_top_level_items() {
+        let items: Vec<(&str, Option<String>)> = code_items(RUST_SOURCE)
+            .into_iter()
+            .map(|item| (&RUST_SOURCE[item.range], item.name))
+            .collect();
+        let starts: Vec<&str> = items
+            .iter()
+            .map(|(text, _)| text.lines().next().unwrap())
+            .collect();
+        let names: Vec<Option<&str>> = items.iter().map(|(_, name)| name.as_deref()).collect();
+
+        assert_eq!(
+            starts,
+            vec![
+                "use std::fmt;",
+                "/// Adds two numbers",
+                "#[derive(Debug)]",
+                "impl fmt::Display for Point {",
+                "fn main() {",
+            ]
+        );
+        assert_eq!(
+            names,
+            vec![
+                None,
+                Some("fn add"),
+                Some("struct Point"),
+                Some("impl fmt::Display for Point"),
+                Some("fn main"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_code_chunks_never_start_mid_function() {
+        let item_starts: Vec<usize> = code_items(RUST_SOURCE)
+            .iter()
+            .map(|item| item.range.start)
+            .collect();
+
+        for max_chunk_size in [200, 300, 1000] {
+            let chunks = ChunkingService::new(max_chunk_size, 0)
+                .unwrap()
+                .chunk_context(&code(RUST_SOURCE, "text/x-rust"));
+            assert!(chunks.len() > 1 || max_chunk_size == 1000);
+            for chunk in &chunks {
+                assert!(item_starts.contains(&chunk.position), "{:?}", chunk.content);
+                let (label, source) = chunk.content.split_once("\n\n").unwrap();
+                assert!(!label.is_empty());
+                assert!(RUST_SOURCE[chunk.position..].starts_with(source));
+            }
+        }
+    }
+
+    #[test]
+    fn test_code_item_longer_than_a_chunk_is_windowed_under_its_name() {
+        let chunks = ChunkingService::new(60, 0)
+            .unwrap()
+            .chunk_context(&code(RUST_SOURCE, "text/x-rust"));
+        let impl_chunks: Vec<&ContextChunk> = chunks
+            .iter()
+            .filter(|chunk| chunk.content.starts_with("impl fmt::Display for Point\n\n"))
+            .collect();
+        assert!(impl_chunks.len() > 1);
+        assert!(impl_chunks
+            .iter()
+            .all(|chunk| chunk.content.len() <= 60 + "impl fmt::Display for Point\n\n".len()));
+    }
+
+    #[test]
+    fn test_code_chunking_is_picked_by_content_type_prefix() {
+        let python = "import os\n\n\n@cache\ndef load(path):\n    return open(path)\n\n\nclass Loader:\n    def run(self):\n        pass\n";
+        let chunks = ChunkingService::new(50, 0)
+            .unwrap()
+            .chunk_context(&code(python, "text/x-python"));
+        let contents: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "import os\n\n\n",
+                "def load\n\n@cache\ndef load(path):\n    return open(path)\n\n\n",
+                "class Loader\n\nclass Loader:\n    def run(self):\n        pass\n",
+            ]
+        );
+
+        assert_eq!(
+            ChunkingStrategy::for_content_type("text/x-rust; charset=utf-8"),
+            Some(ChunkingStrategy::Code)
+        );
+        assert_eq!(ChunkingStrategy::for_content_type("text/plain"), None);
+    }
+
+    #[test]
+    fn test_token_sizes_even_out_chunks_of_mixed_language_text() {
+        let tokenizer = HeuristicTokenizer;
+        let content = format!(
+            "{}{}",
+            "The deploy runs nightly and rolls back on failure. ".repeat(10),
+            "部署每晚运行，失败时自动回滚。".repeat(10)
+        );
+
+        let bytes = ChunkingService::new(200, 0)
+            .unwrap()
+            .chunk_context(&context(&content));
+        let tokens = ChunkingService::new(50, 10)
+            .unwrap()
+            .with_token_sizes(Arc::new(tokenizer))
+            .chunk_context(&context(&content));
+        assert_eq!(reassembled(&bytes), content);
+        assert_eq!(reassembled(&tokens), content);
+
+        // Byte windows hold more tokens of Chinese than of English
+        let counts = |chunks: &[ContextChunk]| -> Vec<usize> {
+            chunks
+                .iter()
+                .map(|chunk| tokenizer.count_tokens(&chunk.content))
+                .collect()
+        };
+        let byte_counts = counts(&bytes);
+        assert!(byte_counts[3] >= byte_counts[0] + 10, "{:?}", byte_counts);
+
+        // Token windows are full up to the last one, whatever the language
+        let token_counts = counts(&tokens);
+        assert!(token_counts.iter().all(|&count| count <= 50));
+        assert!(token_counts[..token_counts.len() - 1]
+            .iter()
+            .all(|&count| count >= 48));
+    }
+
+    #[test]
+    fn test_token_sizes_overlap_and_merge_in_tokens() {
+        let tokenizer: Arc<dyn Tokenizer> = Arc::new(HeuristicTokenizer);
+        let content = "One two. Six ten. Red sky. Big cat.";
+
+        // Each sentence is three tokens: two short words and a full stop
+        let chunks = ChunkingService::new(6, 3)
+            .unwrap()
+            .with_strategy(ChunkingStrategy::Sentence)
+            .with_token_sizes(tokenizer.clone())
+            .chunk_context(&context(content));
+        let contents: Vec<&str> = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "One two. Six ten. ",
+                "Six ten. Red sky. ",
+                "Red sky. Big cat."
+            ]
+        );
+
+        let windows = ChunkingService::new(4, 2)
+            .unwrap()
+            .with_token_sizes(tokenizer)
+            .chunk_context(&context(content));
+        assert_eq!(reassembled(&windows), content);
+        // Whitespace is free, so windows end after it and overlaps start at the full stop
+        assert_eq!(windows[0].content, "One two. Six ");
+        assert!(windows[1].content.starts_with(". Six "));
+    }
+
+    #[test]
+    fn test_bm25_favors_rare_terms_and_short_documents() {
+        let long = context(
+            "The database failover runs weekly. The database backups run nightly and the \
+             database replicas are checked hourly, with more about operations and on call.",
+        );
+        let short = context("Database failover");
+        let other = context("The cache is warmed before the database failover");
+        let contexts = vec![long.clone(), short.clone(), other.clone()];
+
+        let term_match = RetrievalService::new(10);
+        // Every context contains both terms, so term matching can't tell them apart
+        let scores: Vec<f32> = term_match
+            .rank_contexts("database failover", &contexts, &[])
+            .into_iter()
+            .map(|(_, score)| score)
+            .collect();
+        assert_eq!(scores, [1.0, 1.0, 1.0]);
+
+        let bm25 = RetrievalService::new(10).with_ranking(Ranking::Bm25(Bm25::default()));
+        assert_eq!(
+            ranked(&bm25, "database failover", &contexts, &[])[0],
+            short.id
+        );
+
+        // "cache" only appears once in the corpus, so it outweighs the common terms
+        assert_eq!(ranked(&bm25, "cache database", &contexts, &[])[0], other.id);
+    }
+
+    #[test]
+    fn test_bm25_scores_chunks_and_aggregates_per_context() {
+        let focused = context("");
+        let spread = context("");
+        let mut all_chunks = chunks(&focused, &["kafka kafka consumer lag", "unrelated notes"]);
+        all_chunks.extend(chunks(
+            &spread,
+            &["kafka broker", "kafka topic", "kafka partition"],
+        ));
+        let contexts = vec![focused.clone(), spread.clone()];
+
+        // The best chunk decides with max, every matching chunk adds up with sum
+        let max = RetrievalService::new(10).with_ranking(Ranking::Bm25(Bm25::new(
+            1.2,
+            0.75,
+            ChunkAggregation::Max,
+        )));
+        assert_eq!(
+            ranked(&max, "kafka lag", &contexts, &all_chunks),
+            [focused.id, spread.id]
+        );
+
+        let sum = RetrievalService::new(10).with_ranking(Ranking::Bm25(Bm25::new(
+            1.2,
+            0.75,
+            ChunkAggregation::Sum,
+        )));
+        assert_eq!(
+            ranked(&sum, "kafka", &contexts, &all_chunks),
+            [spread.id, focused.id]
+        );
+    }
+
+    #[test]
+    fn test_bm25_gives_unmatched_contexts_zero() {
+        let matched = context("rust ownership");
+        let unmatched = context("python decorators");
+        let bm25 = RetrievalService::new(1).with_ranking(Ranking::Bm25(Bm25::default()));
+
+        // Every context is ranked; limiting the results is up to the caller
+        let results = bm25.rank_contexts("rust", &[unmatched.clone(), matched.clone()], &[]);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0.id, matched.id);
+        assert_eq!(results[1].1, 0.0);
+
+        let results = bm25.rank_contexts("go", &[unmatched, matched], &[]);
+        assert_eq!(results[0].1, 0.0);
+    }
+
+    #[test]
+    fn test_fuzzy_matches_near_misses_of_long_enough_terms() {
+        let fuzzy = Fuzzy::default();
+        assert!(fuzzy.matches("tranformers", "transformers"));
+        assert!(fuzzy.matches("kuberntes", "kubernetes"));
+        assert!(!fuzzy.matches("transformers", "performers"));
+
+        // Short tokens only match exactly, however close
+        assert!(!fuzzy.matches("rust", "ruts"));
+        assert!(!fuzzy.matches("cat", "cut"));
+        assert!(!fuzzy.matches("query", "que"));
+    }
+
+    #[test]
+    fn test_fuzzy_counts_edits_in_characters() {
+        assert_eq!(edit_distance("résumé", "resume"), 2);
+        assert_eq!(edit_distance("東京大学校", "東京大学院"), 1);
+
+        let fuzzy = Fuzzy::default();
+        assert!(fuzzy.matches("naïve", "naive"));
+        assert!(fuzzy.matches("東京大学校", "東京大学院"));
+        // Four characters, though many more bytes
+        assert!(!fuzzy.matches("日本語版", "日本語盤"));
+    }
+
+    #[test]
+    fn test_fuzzy_matches_score_less_than_exact_ones() {
+        let exact = context("Transformers replaced recurrent networks");
+        let typo = context("Tranformers were slow to train");
+        let unrelated = context("Gradient descent basics");
+        let contexts = vec![unrelated.clone(), typo.clone(), exact.clone()];
+
+        for ranking in [Ranking::TermMatch, Ranking::Bm25(Bm25::default())] {
+            let service = RetrievalService::new(10).with_ranking(ranking);
+            let scores = |fuzzy: bool| -> HashMap<Uuid, f32> {
+                service
+                    .rank_hybrid(
+                        "transformers",
+                        &contexts,
+                        &[],
+                        &HashMap::new(),
+                        0.0,
+                        fuzzy,
+                        &[],
+                    )
+                    .into_iter()
+                    .map(|(context, score)| (context.id, score))
+                    .collect()
+            };
+
+            let strict = scores(false);
+            assert_eq!(strict[&typo.id], 0.0, "{:?}", ranking);
+
+            let fuzzy = scores(true);
+            assert_eq!(fuzzy[&exact.id], 1.0, "{:?}", ranking);
+            assert!(fuzzy[&typo.id] > 0.0, "{:?}", ranking);
+            assert!(fuzzy[&typo.id] < 1.0, "{:?}", ranking);
+            assert_eq!(fuzzy[&unrelated.id], 0.0, "{:?}", ranking);
+        }
+    }
+
+    #[test]
+    fn test_expansions_score_at_their_weight() {
+        let abbreviated = context("Upgrading the k8s cluster");
+        let spelled_out = context("Upgrading the Kubernetes cluster");
+        let contexts = vec![spelled_out.clone(), abbreviated.clone()];
+        let expansions = ["kubernetes".to_string()];
+
+        for ranking in [Ranking::TermMatch, Ranking::Bm25(Bm25::default())] {
+            let service = RetrievalService::new(10)
+                .with_ranking(ranking)
+                .with_expansion_weight(0.5);
+            let scores = |expansions: &[String]| -> HashMap<Uuid, f32> {
+                service
+                    .rank_hybrid(
+                        "k8s",
+                        &contexts,
+                        &[],
+                        &HashMap::new(),
+                        0.0,
+                        false,
+                        expansions,
+                    )
+                    .into_iter()
+                    .map(|(context, score)| (context.id, score))
+                    .collect()
+            };
+
+            assert_eq!(scores(&[])[&spelled_out.id], 0.0, "{:?}", ranking);
+
+            let expanded = scores(&expansions);
+            assert_eq!(expanded[&abbreviated.id], 1.0, "{:?}", ranking);
+            assert!(expanded[&spelled_out.id] > 0.0, "{:?}", ranking);
+            assert!(expanded[&spelled_out.id] < 1.0, "{:?}", ranking);
+        }
     }
 }
@@ -0,0 +1,268 @@
+use regex::Regex;
+
+use crate::domain::error::{McpError, McpResult};
+
+/// Normalization rules applied to every tag entering the system
+#[derive(Debug, Clone)]
+pub struct TagPolicy {
+    /// Lowercase tags
+    pub lowercase: bool,
+
+    /// Strip leading and trailing whitespace
+    pub trim: bool,
+
+    /// Replace runs of internal whitespace with a single `-`
+    pub collapse_whitespace: bool,
+
+    /// Maximum tag length in characters
+    pub max_length: Option<usize>,
+
+    /// Pattern a normalized tag must match in full
+    pub allowed_pattern: Option<Regex>,
+
+    /// Maximum number of tags on a context
+    pub max_tags: Option<usize>,
+}
+
+impl Default for TagPolicy {
+    fn default() -> Self {
+        Self {
+            lowercase: true,
+            trim: true,
+            collapse_whitespace: true,
+            max_length: None,
+            allowed_pattern: None,
+            max_tags: None,
+        }
+    }
+}
+
+impl TagPolicy {
+    /// A policy that leaves tags untouched
+    pub fn permissive() -> Self {
+        Self {
+            lowercase: false,
+            trim: false,
+            collapse_whitespace: false,
+            max_length: None,
+            allowed_pattern: None,
+            max_tags: None,
+        }
+    }
+
+    /// Only allow tags matching the given pattern in full
+    pub fn with_allowed_pattern(mut self, pattern: &str) -> McpResult<Self> {
+        let anchored = format!("^(?:{})$", pattern);
+        let regex = Regex::new(&anchored).map_err(|e| {
+            McpError::ValidationError(format!("Invalid tag pattern '{}': {}", pattern, e))
+        })?;
+        self.allowed_pattern = Some(regex);
+        Ok(self)
+    }
+
+    /// Normalize a single tag, rejecting it if it violates the policy
+    pub fn normalize(&self, tag: &str) -> McpResult<String> {
+        let mut normalized = if self.trim {
+            tag.trim().to_string()
+        } else {
+            tag.to_string()
+        };
+
+        if self.collapse_whitespace {
+            // Only internal whitespace is collapsed; edges are left to `trim`
+            let start = normalized.len() - normalized.trim_start().len();
+            let end = start + normalized.trim().len();
+            let collapsed = normalized[start..end]
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join("-");
+            normalized = format!(
+                "{}{}{}",
+                &normalized[..start],
+                collapsed,
+                &normalized[end..]
+            );
+        }
+
+        if self.lowercase {
+            normalized = normalized.to_lowercase();
+        }
+
+        if let Some(max_length) = self.max_length {
+            if normalized.chars().count() > max_length {
+                return Err(McpError::ValidationError(format!(
+                    "Tag '{}' exceeds the maximum length of {} characters",
+                    normalized, max_length
+                )));
+            }
+        }
+
+        if let Some(pattern) = &self.allowed_pattern {
+            if !normalized.is_empty() && !pattern.is_match(&normalized) {
+                return Err(McpError::ValidationError(format!(
+                    "Tag '{}' contains characters that are not allowed",
+                    normalized
+                )));
+            }
+        }
+
+        Ok(normalized)
+    }
+
+    /// Normalize a list of tags, dropping empty tags and duplicates
+    pub fn normalize_all<I, S>(&self, tags: I) -> McpResult<Vec<String>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut normalized: Vec<String> = Vec::new();
+
+        for tag in tags {
+            let tag = self.normalize(tag.as_ref())?;
+            if !tag.trim().is_empty() && !normalized.contains(&tag) {
+                normalized.push(tag);
+            }
+        }
+
+        Ok(normalized)
+    }
+
+    /// Normalize the tags of a context, rejecting blank tags and more than `max_tags`
+    ///
+    /// Unlike `normalize_all`, which is lenient with filters, a blank tag on a context is
+    /// most likely a client bug, so it is an error rather than dropped.
+    pub fn normalize_context_tags<I, S>(&self, tags: I) -> McpResult<Vec<String>>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let tags: Vec<S> = tags.into_iter().collect();
+        if tags.iter().any(|tag| tag.as_ref().trim().is_empty()) {
+            return Err(McpError::ValidationError(
+                "tags must not contain empty or whitespace-only tags".to_string(),
+            ));
+        }
+
+        let normalized = self.normalize_all(tags)?;
+        if let Some(max_tags) = self.max_tags {
+            if normalized.len() > max_tags {
+                return Err(McpError::ValidationError(format!(
+                    "tags has {} entries, more than the maximum of {}",
+                    normalized.len(),
+                    max_tags
+                )));
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// Tags that would be changed or rejected by this policy
+    pub fn violations<'a>(&self, tags: &'a [String]) -> Vec<&'a str> {
+        tags.iter()
+            .filter(|tag| match self.normalize(tag) {
+                Ok(normalized) => normalized != **tag,
+                Err(_) => true,
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_policy_normalizes_case_and_whitespace() {
+        let policy = TagPolicy::default();
+
+        assert_eq!(policy.normalize(" Rust ").unwrap(), "rust");
+        assert_eq!(policy.normalize("RUST").unwrap(), "rust");
+        assert_eq!(
+            policy.normalize("Machine   Learning").unwrap(),
+            "machine-learning"
+        );
+    }
+
+    #[test]
+    fn test_normalize_all_deduplicates() {
+        let policy = TagPolicy::default();
+
+        let tags = policy
+            .normalize_all([" Rust ", "rust", "RUST", "", "  ", "ai"])
+            .unwrap();
+        assert_eq!(tags, vec!["rust", "ai"]);
+    }
+
+    #[test]
+    fn test_permissive_policy_keeps_tags() {
+        let policy = TagPolicy::permissive();
+        assert_eq!(policy.normalize(" Rust ").unwrap(), " Rust ");
+    }
+
+    #[test]
+    fn test_context_tags_reject_blanks_and_too_many() {
+        let policy = TagPolicy {
+            max_tags: Some(2),
+            ..TagPolicy::default()
+        };
+
+        assert_eq!(
+            policy
+                .normalize_context_tags(["Rust", "rust", "AI"])
+                .unwrap(),
+            ["rust", "ai"]
+        );
+        assert!(matches!(
+            policy.normalize_context_tags(["rust", "ai", "web"]),
+            Err(McpError::ValidationError(message)) if message.contains("maximum of 2")
+        ));
+        assert!(matches!(
+            policy.normalize_context_tags(["rust", "  "]),
+            Err(McpError::ValidationError(message)) if message.starts_with("tags")
+        ));
+        assert!(policy
+            .normalize_context_tags(Vec::<String>::new())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_collapse_without_trim_keeps_edges() {
+        let policy = TagPolicy {
+            trim: false,
+            lowercase: false,
+            ..TagPolicy::default()
+        };
+        assert_eq!(policy.normalize(" a  b ").unwrap(), " a-b ");
+    }
+
+    #[test]
+    fn test_limits_are_enforced() {
+        let policy = TagPolicy {
+            max_length: Some(4),
+            ..TagPolicy::default()
+        }
+        .with_allowed_pattern("[a-z0-9-]+")
+        .unwrap();
+
+        assert_eq!(policy.normalize("Rust").unwrap(), "rust");
+        assert!(matches!(
+            policy.normalize("rustacean"),
+            Err(McpError::ValidationError(_))
+        ));
+        assert!(matches!(
+            policy.normalize("c++"),
+            Err(McpError::ValidationError(_))
+        ));
+        assert!(TagPolicy::default().with_allowed_pattern("[").is_err());
+    }
+
+    #[test]
+    fn test_violations() {
+        let policy = TagPolicy::default();
+        let tags = vec!["rust".to_string(), " AI".to_string(), "web dev".to_string()];
+
+        assert_eq!(policy.violations(&tags), vec![" AI", "web dev"]);
+    }
+}
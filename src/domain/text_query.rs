@@ -0,0 +1,156 @@
+/// Search text split into the exact phrases it quotes and the words it searches for, e.g.
+/// `"context window" limits`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextQuery {
+    /// Every word of the query, quoted or not, for the scorer and the embeddings
+    pub text: String,
+
+    /// Quoted phrases a match must contain, as written
+    pub phrases: Vec<String>,
+}
+
+impl TextQuery {
+    /// Parse search text, reading double-quoted runs as phrases
+    ///
+    /// Quotes don't nest: a quote inside a phrase ends it, unless escaped as `\"`. Empty
+    /// phrases are dropped, and an unterminated quote is ignored, leaving its words as plain
+    /// text.
+    pub fn parse(input: &str) -> Self {
+        let mut words = Vec::new();
+        let mut phrases = Vec::new();
+        let mut phrase: Option<String> = None;
+        let mut plain = String::new();
+
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, phrase.as_mut()) {
+                ('\\', Some(phrase)) if chars.peek() == Some(&'"') => {
+                    phrase.push(chars.next().unwrap());
+                }
+                ('"', Some(_)) => {
+                    let quoted = collapse(&phrase.take().unwrap());
+                    if !phrase_words(&quoted).is_empty() {
+                        words.push(quoted.clone());
+                        phrases.push(quoted);
+                    }
+                }
+                ('"', None) => {
+                    words.push(collapse(&std::mem::take(&mut plain)));
+                    phrase = Some(String::new());
+                }
+                (c, Some(phrase)) => phrase.push(c),
+                (c, None) => plain.push(c),
+            }
+        }
+        if let Some(unterminated) = phrase {
+            plain.push_str(&unterminated);
+        }
+        words.push(collapse(&plain));
+
+        words.retain(|word| !word.is_empty());
+        Self {
+            text: words.join(" "),
+            phrases,
+        }
+    }
+
+    /// Whether the query has nothing to search for
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Whether `content` contains every phrase, comparing words and ignoring case and
+    /// punctuation
+    pub fn matches(&self, content: &str) -> bool {
+        if self.phrases.is_empty() {
+            return true;
+        }
+
+        let content = phrase_words(content);
+        self.phrases.iter().all(|phrase| {
+            let phrase = phrase_words(phrase);
+            phrase.is_empty()
+                || content
+                    .windows(phrase.len())
+                    .any(|window| window == phrase.as_slice())
+        })
+    }
+}
+
+/// Text with runs of whitespace collapsed into single spaces
+fn collapse(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Lowercased alphanumeric words of a text
+fn phrase_words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phrases(query: &TextQuery) -> Vec<&str> {
+        query.phrases.iter().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn test_quoted_phrases_are_separated_from_terms() {
+        let query = TextQuery::parse(r#"limits of the "context   window" in  "large models""#);
+        assert_eq!(query.text, "limits of the context window in large models");
+        assert_eq!(phrases(&query), ["context window", "large models"]);
+
+        let query = TextQuery::parse("no quotes here");
+        assert_eq!(query.text, "no quotes here");
+        assert!(query.phrases.is_empty());
+    }
+
+    #[test]
+    fn test_quotes_do_not_nest() {
+        // The inner quote closes the phrase, and the next one opens another
+        let query = TextQuery::parse(r#""the "inner" quote""#);
+        assert_eq!(phrases(&query), ["the", "quote"]);
+        assert_eq!(query.text, "the inner quote");
+
+        // Escaped quotes stay part of the phrase
+        let query = TextQuery::parse(r#""say \"hello\" twice""#);
+        assert_eq!(phrases(&query), [r#"say "hello" twice"#]);
+    }
+
+    #[test]
+    fn test_unterminated_quote_leaves_plain_words() {
+        let query = TextQuery::parse(r#"tuning "context window"#);
+        assert_eq!(query.text, "tuning context window");
+        assert!(query.phrases.is_empty());
+
+        let query = TextQuery::parse(r#""exact" and "open"#);
+        assert_eq!(phrases(&query), ["exact"]);
+        assert_eq!(query.text, "exact and open");
+    }
+
+    #[test]
+    fn test_empty_phrases_are_dropped() {
+        let query = TextQuery::parse(r#"alpha "" beta "   " "!?""#);
+        assert_eq!(query.text, "alpha beta");
+        assert!(query.phrases.is_empty());
+
+        assert!(TextQuery::parse(r#""" "  ""#).is_empty());
+        assert!(TextQuery::parse("   ").is_empty());
+        assert!(!TextQuery::parse(r#""x""#).is_empty());
+    }
+
+    #[test]
+    fn test_phrases_match_consecutive_words() {
+        let query = TextQuery::parse(r#""Context Window" tuning"#);
+        assert!(query.matches("The context-window of a model is finite"));
+        assert!(!query.matches("A window into the context of the model"));
+        assert!(!query.matches("context"));
+
+        // Without phrases any content matches
+        assert!(TextQuery::parse("window").matches("unrelated"));
+    }
+}
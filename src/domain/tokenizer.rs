@@ -0,0 +1,82 @@
+/// Counts the tokens a language model would split a text into
+pub trait Tokenizer: Send + Sync {
+    /// Number of tokens in `text`
+    ///
+    /// Appending text to `text` must never lower the count, nor dropping text from its start
+    /// raise it.
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+/// Approximates BPE tokenizers without a vocabulary
+///
+/// Runs of letters and digits count a token per four characters, the rough average of BPE
+/// vocabularies on English text. Every other character counts a token of its own, except
+/// whitespace, which is free: BPE vocabularies fold it into the word after it. Characters
+/// taking three or more bytes, such as CJK ideographs and emoji, are rarely merged by BPE, so
+/// they count a token each rather than joining a run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HeuristicTokenizer;
+
+/// Characters of a run of letters and digits counted as one token
+const CHARS_PER_TOKEN: usize = 4;
+
+impl Tokenizer for HeuristicTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        let mut tokens = 0;
+        let mut run: usize = 0;
+
+        for c in text.chars() {
+            if c.is_alphanumeric() && c.len_utf8() < 3 {
+                run += 1;
+                continue;
+            }
+
+            tokens += run.div_ceil(CHARS_PER_TOKEN);
+            run = 0;
+            if !c.is_whitespace() {
+                tokens += 1;
+            }
+        }
+        tokens + run.div_ceil(CHARS_PER_TOKEN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heuristic_counts_words_punctuation_and_ideographs() {
+        let tokenizer = HeuristicTokenizer;
+        assert_eq!(tokenizer.count_tokens(""), 0);
+        assert_eq!(tokenizer.count_tokens("   \n"), 0);
+        // "The" and "cat" are a token each, "sleeps" two, and the full stop one
+        assert_eq!(tokenizer.count_tokens("The cat sleeps."), 5);
+        // Accented letters stay in their word
+        assert_eq!(tokenizer.count_tokens("café"), 1);
+        // Ideographs are a token each
+        assert_eq!(tokenizer.count_tokens("東京都"), 3);
+        assert_eq!(tokenizer.count_tokens("Tokyo 東京"), 4);
+    }
+
+    #[test]
+    fn test_heuristic_counts_never_drop_as_text_grows() {
+        let tokenizer = HeuristicTokenizer;
+        let text = "Chunks of 東京 prose, with punctuation: and 🦀 emoji!";
+        let boundaries: Vec<usize> = text
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([text.len()])
+            .collect();
+
+        for window in boundaries.windows(2) {
+            let (shorter, longer) = (window[0], window[1]);
+            assert!(
+                tokenizer.count_tokens(&text[..shorter]) <= tokenizer.count_tokens(&text[..longer])
+            );
+            assert!(
+                tokenizer.count_tokens(&text[longer..]) <= tokenizer.count_tokens(&text[shorter..])
+            );
+        }
+    }
+}
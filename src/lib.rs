@@ -1,5 +1,7 @@
 pub mod adapter;
 pub mod application;
+#[cfg(feature = "client")]
+pub mod client;
 pub mod config;
 pub mod domain;
 pub mod ports;
@@ -0,0 +1,34 @@
+use crate::domain::{Collection, McpResult};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Input port for managing the collections contexts are grouped into
+#[async_trait]
+pub trait CollectionPort {
+    /// Create a collection with a unique name
+    async fn create_collection(
+        &self,
+        name: String,
+        description: Option<String>,
+    ) -> McpResult<Collection>;
+
+    /// Get a collection by ID, including the default collection
+    async fn get_collection(&self, id: Uuid) -> McpResult<Collection>;
+
+    /// List every collection, the default collection first and the rest by name
+    async fn list_collections(&self) -> McpResult<Vec<Collection>>;
+
+    /// Rename a collection or change its description, leaving fields given as `None` alone
+    async fn update_collection(
+        &self,
+        id: Uuid,
+        name: Option<String>,
+        description: Option<String>,
+    ) -> McpResult<Collection>;
+
+    /// Delete a collection, which must no longer hold any contexts
+    async fn delete_collection(&self, id: Uuid) -> McpResult<()>;
+
+    /// Resolve a collection's ID or name to its ID
+    async fn resolve_collection(&self, id_or_name: &str) -> McpResult<Uuid>;
+}
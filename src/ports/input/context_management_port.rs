@@ -1,33 +1,156 @@
-use crate::domain::{Context, ContextMetadata, McpResult};
+use crate::domain::{
+    Context, ContextChunk, ContextFilter, ContextMetadata, ContextRelation, ContextRevision,
+    DeleteOutcome, McpResult, StoreOptions,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
 /// Input port for context management operations
 #[async_trait]
 pub trait ContextManagementPort {
-    /// Store a new context
-    async fn store_context(&self, content: String, metadata: ContextMetadata)
-        -> McpResult<Context>;
+    /// Check content against what storing or updating a context accepts, so callers can
+    /// report it along with their own validation
+    fn check_content(&self, content: &str) -> McpResult<()>;
+
+    /// Store a new context as `options` say, expiring at `options.expires_at` if given
+    ///
+    /// The content is chunked by `options.chunking` if given, or by the configured strategy,
+    /// and its hash kept in `content_hash`. `options.on_duplicate` decides what happens when an
+    /// unexpired context with the same content is already stored. A `parent_id` must name a
+    /// live context, or the store fails with `McpError::InvalidContextReference`; so must a
+    /// `collection_id` name a collection. Without a collection the context joins its
+    /// parent's, or the default collection if it has no parent. Duplicates are only looked
+    /// for in the context's collection.
+    async fn store_context(
+        &self,
+        content: String,
+        metadata: ContextMetadata,
+        options: StoreOptions,
+    ) -> McpResult<Context>;
 
-    /// Retrieve a context by its ID
+    /// Store a context exactly as given, keeping its id, timestamps and version
+    ///
+    /// Chunks that all carry embeddings are stored as they are; without chunks, or if
+    /// any lacks an embedding, the content is chunked and embedded again. Fails with
+    /// `McpError::ContextAlreadyExists` if a context with the id is stored.
+    async fn import_context(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context>;
+
+    /// Retrieve a context by its ID; expired contexts are not found
     async fn get_context(&self, context_id: Uuid) -> McpResult<Context>;
 
-    /// Update an existing context
+    /// Retrieve a single chunk of a context by its ID
+    async fn get_chunk(&self, chunk_id: Uuid) -> McpResult<ContextChunk>;
+
+    /// Retrieve the chunks of a context, with their embeddings, in position order
+    async fn get_chunks(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>>;
+
+    /// Update an existing context, failing with `McpError::VersionConflict` if an
+    /// `expected_version` is given and the context is at another one
+    ///
+    /// The context expires at `expires_at`, or never if it's `None`.
     async fn update_context(
         &self,
         context_id: Uuid,
         content: String,
         metadata: ContextMetadata,
+        expires_at: Option<DateTime<Utc>>,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context>;
+
+    /// List the prior states kept for a context, oldest first
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>>;
+
+    /// Retrieve the prior state of a context at `version`
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision>;
+
+    /// Make the content and metadata of the revision at `version` current again
+    ///
+    /// This is an update like any other: the replaced state is kept as a revision, and an
+    /// `expected_version` is checked against the current version.
+    async fn restore_revision(
+        &self,
+        context_id: Uuid,
+        version: u64,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context>;
+
+    /// Add a relation from one live context to another, returning the context it is from
+    async fn add_relation(&self, context_id: Uuid, relation: ContextRelation)
+        -> McpResult<Context>;
+
+    /// Remove the relations from a context to `target_id`, only the one of `relation_type`
+    /// if given
+    async fn remove_relations(
+        &self,
+        context_id: Uuid,
+        target_id: Uuid,
+        relation_type: Option<String>,
     ) -> McpResult<Context>;
 
-    /// Delete a context
+    /// The live contexts a context relates to, of `relation_type` if given, with the
+    /// relation to each in the order they were added
+    async fn related_contexts(
+        &self,
+        context_id: Uuid,
+        relation_type: Option<String>,
+    ) -> McpResult<Vec<(ContextRelation, Context)>>;
+
+    /// List the live children of a context in listing order, with pagination
+    ///
+    /// With `include_descendants` their children follow, and theirs, breadth first.
+    async fn list_children(
+        &self,
+        context_id: Uuid,
+        include_descendants: bool,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>>;
+
+    /// Delete a context for good, with its chunks, embeddings and revisions
+    ///
+    /// Its children are orphaned, deleted along with it or make the delete fail with
+    /// `McpError::HasChildren`, as configured.
     async fn delete_context(&self, context_id: Uuid) -> McpResult<()>;
 
-    /// List all contexts, optionally filtered by tags
+    /// Mark a context deleted, so it reads as not found and stops matching searches, while
+    /// keeping it to be restored until it is purged
+    async fn soft_delete_context(&self, context_id: Uuid) -> McpResult<()>;
+
+    /// Bring back a soft-deleted context, embedding its chunks again if their embeddings are
+    /// gone; a context that isn't deleted is returned as it is
+    async fn restore_context(&self, context_id: Uuid) -> McpResult<Context>;
+
+    /// Delete several contexts, reporting for each ID in order whether it was deleted
+    async fn delete_contexts(
+        &self,
+        context_ids: Vec<Uuid>,
+    ) -> McpResult<Vec<(Uuid, DeleteOutcome)>>;
+
+    /// Delete every live context with all of `tags`, returning how many were deleted
+    ///
+    /// Soft-deleted and expired contexts are left for restoring and sweeping, and contexts
+    /// the child policy keeps in place are skipped.
+    async fn delete_by_tags(&self, tags: Vec<String>) -> McpResult<usize>;
+
+    /// List the unexpired contexts matching a filter
     async fn list_contexts(
         &self,
-        tags: Option<Vec<String>>,
+        filter: ContextFilter,
         limit: usize,
         offset: usize,
     ) -> McpResult<Vec<Context>>;
+
+    /// Count the unexpired contexts matching a filter
+    async fn count_contexts(&self, filter: ContextFilter) -> McpResult<usize>;
+
+    /// Count the live contexts carrying each tag in use, most used first and ties by tag
+    async fn tag_counts(&self) -> McpResult<Vec<(String, usize)>>;
+
+    /// Check whether a context exists
+    async fn context_exists(&self, context_id: Uuid) -> McpResult<bool>;
 }
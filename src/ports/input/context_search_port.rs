@@ -1,18 +1,26 @@
-use crate::domain::{ContextReference, ContextSearchResult, McpResult};
+use crate::domain::{ContextReference, ContextSearchResult, McpResult, SearchOptions};
 use async_trait::async_trait;
 
 /// Input port for context searching operations
 #[async_trait]
 pub trait ContextSearchPort {
     /// Search for relevant contexts based on a query string
-    async fn search(&self, query: String, limit: usize) -> McpResult<ContextSearchResult>;
+    async fn search(
+        &self,
+        query: String,
+        limit: usize,
+        options: SearchOptions,
+    ) -> McpResult<ContextSearchResult>;
 
     /// Search for relevant contexts based on a query string, filtered by tags
+    ///
+    /// Matches need every one of `tags`, or any of them with `TagMode::Any` in `options`.
     async fn search_with_tags(
         &self,
         query: String,
         tags: Vec<String>,
         limit: usize,
+        options: SearchOptions,
     ) -> McpResult<ContextSearchResult>;
 
     /// Retrieve relevant contexts based on provided reference IDs
@@ -0,0 +1,20 @@
+use crate::domain::{EvalDataset, EvalRun, McpResult};
+use async_trait::async_trait;
+
+/// Input port for evaluating retrieval quality against labelled datasets
+#[async_trait]
+pub trait EvaluationPort {
+    /// Store a labelled dataset, replacing any dataset with the same name
+    async fn save_dataset(&self, dataset: EvalDataset) -> McpResult<EvalDataset>;
+
+    /// Run every query of a dataset through the search pipeline and record the results
+    async fn run_dataset(
+        &self,
+        dataset: &str,
+        k: usize,
+        label: Option<String>,
+    ) -> McpResult<EvalRun>;
+
+    /// List recorded runs, oldest first, optionally only those of one dataset
+    async fn list_runs(&self, dataset: Option<&str>) -> McpResult<Vec<EvalRun>>;
+}
@@ -0,0 +1,10 @@
+use crate::domain::{IngestedDocument, McpResult};
+use async_trait::async_trait;
+
+/// Input port for turning documents elsewhere into context content
+#[async_trait]
+pub trait IngestionPort {
+    /// Fetch the document at `url` as text, reduced from HTML to its readable text if
+    /// `strip_html` is set
+    async fn fetch_document(&self, url: &str, strip_html: bool) -> McpResult<IngestedDocument>;
+}
@@ -1,5 +1,13 @@
+pub mod collection_port;
 pub mod context_management_port;
 pub mod context_search_port;
+pub mod evaluation_port;
+pub mod ingestion_port;
+pub mod readiness_port;
 
+pub use collection_port::CollectionPort;
 pub use context_management_port::ContextManagementPort;
 pub use context_search_port::ContextSearchPort;
+pub use evaluation_port::EvaluationPort;
+pub use ingestion_port::IngestionPort;
+pub use readiness_port::ReadinessPort;
@@ -0,0 +1,9 @@
+use crate::domain::DependencyStatus;
+use async_trait::async_trait;
+
+/// Input port for checking that the server's dependencies can serve requests
+#[async_trait]
+pub trait ReadinessPort {
+    /// Check every dependency, returning the status of each
+    async fn check_readiness(&self) -> Vec<DependencyStatus>;
+}
@@ -0,0 +1,12 @@
+use crate::domain::{FetchedContent, McpResult};
+use async_trait::async_trait;
+
+/// Output port for downloading documents that clients point the server at
+#[async_trait]
+pub trait ContentFetcherPort {
+    /// Download the document at `url`
+    ///
+    /// URLs the fetcher may not visit are a `ValidationError` and servers that fail to answer
+    /// with the document an `ExternalServiceError`.
+    async fn fetch(&self, url: &str) -> McpResult<FetchedContent>;
+}
@@ -1,7 +1,15 @@
-use crate::domain::{Context, ContextChunk, McpResult};
+use crate::domain::{
+    Collection, Context, ContextChunk, ContextFilter, ContextRelation, ContextRevision, McpError,
+    McpResult,
+};
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Contexts read at a time when filtering a repository that can't apply the filter itself
+const FILTER_PAGE_SIZE: usize = 1000;
+
 /// Output port for context storage operations
 #[async_trait]
 pub trait ContextRepositoryPort {
@@ -11,12 +19,148 @@ pub trait ContextRepositoryPort {
     /// Find a context by its ID
     async fn find_by_id(&self, context_id: Uuid) -> McpResult<Context>;
 
-    /// Update an existing context
-    async fn update(&self, context: Context) -> McpResult<Context>;
+    /// Find contexts by their IDs, in the given order; IDs that don't exist are left out
+    async fn find_by_ids(&self, context_ids: &[Uuid]) -> McpResult<Vec<Context>>;
+
+    /// Update an existing context, returning it with its version advanced
+    ///
+    /// With an `expected_version`, the update fails with `McpError::VersionConflict` unless
+    /// the stored context is at that version.
+    async fn update(&self, context: Context, expected_version: Option<u64>) -> McpResult<Context>;
+
+    /// Save a new context together with its chunks, storing both or neither
+    async fn save_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+    ) -> McpResult<Context>;
 
-    /// Delete a context
+    /// Update an existing context and replace all of its chunks, storing both or neither
+    ///
+    /// Checks and advances the version like `update`.
+    async fn replace_context_with_chunks(
+        &self,
+        context: Context,
+        chunks: Vec<ContextChunk>,
+        expected_version: Option<u64>,
+    ) -> McpResult<Context>;
+
+    /// Delete a context, along with its revisions
     async fn delete(&self, context_id: Uuid) -> McpResult<()>;
 
+    /// Mark a context deleted at `deleted_at`, keeping it and its chunks so it can be restored
+    ///
+    /// Advances the version like `update`, failing with `McpError::VersionConflict` if the
+    /// context is written in between.
+    async fn soft_delete(&self, context_id: Uuid, deleted_at: DateTime<Utc>) -> McpResult<Context> {
+        let mut context = self.find_by_id(context_id).await?;
+        let version = context.version;
+        context.deleted_at = Some(deleted_at);
+        self.update(context, Some(version)).await
+    }
+
+    /// Clear the mark `soft_delete` left on a context, advancing its version like `update`
+    async fn restore(&self, context_id: Uuid) -> McpResult<Context> {
+        let mut context = self.find_by_id(context_id).await?;
+        let version = context.version;
+        context.deleted_at = None;
+        self.update(context, Some(version)).await
+    }
+
+    /// Add a relation from a context, unless it already has the same one
+    ///
+    /// Advances the version like `update`.
+    async fn add_relation(
+        &self,
+        context_id: Uuid,
+        relation: ContextRelation,
+    ) -> McpResult<Context> {
+        let mut context = self.find_by_id(context_id).await?;
+        if context.relations.contains(&relation) {
+            return Ok(context);
+        }
+        let version = context.version;
+        context.relations.push(relation);
+        self.update(context, Some(version)).await
+    }
+
+    /// Remove the relations from a context to `target_id`, only the one of `relation_type` if
+    /// given, failing with `McpError::RelationNotFound` if there are none
+    ///
+    /// Advances the version like `update`.
+    async fn remove_relations(
+        &self,
+        context_id: Uuid,
+        target_id: Uuid,
+        relation_type: Option<&str>,
+    ) -> McpResult<Context> {
+        let mut context = self.find_by_id(context_id).await?;
+        let version = context.version;
+        let related = context.relations.len();
+        context.relations.retain(|relation| {
+            relation.target_id != target_id
+                || relation_type.is_some_and(|wanted| relation.relation_type != wanted)
+        });
+        if context.relations.len() == related {
+            return Err(McpError::RelationNotFound {
+                context_id,
+                target_id,
+            });
+        }
+        self.update(context, Some(version)).await
+    }
+
+    /// Find the contexts with a relation to `target_id`, expired and deleted ones included
+    ///
+    /// By default every context is read, a page at a time.
+    async fn find_related_to(&self, target_id: Uuid) -> McpResult<Vec<Context>> {
+        let mut related = Vec::new();
+        let mut page_offset = 0;
+        loop {
+            let page = self.list_all(FILTER_PAGE_SIZE, page_offset).await?;
+            page_offset += page.len();
+            let last_page = page.len() < FILTER_PAGE_SIZE;
+
+            related.extend(page.into_iter().filter(|context| {
+                context
+                    .relations
+                    .iter()
+                    .any(|relation| relation.target_id == target_id)
+            }));
+            if last_page {
+                return Ok(related);
+            }
+        }
+    }
+
+    /// Keep a prior state of a context, replacing any revision with the same version and
+    /// dropping the oldest ones so at most `max_revisions` remain
+    async fn save_revision(&self, revision: ContextRevision, max_revisions: usize)
+        -> McpResult<()>;
+
+    /// List the revisions kept for a context, oldest first
+    async fn list_revisions(&self, context_id: Uuid) -> McpResult<Vec<ContextRevision>>;
+
+    /// Find the revision of a context at `version`, failing with
+    /// `McpError::RevisionNotFound` if none is kept
+    async fn get_revision(&self, context_id: Uuid, version: u64) -> McpResult<ContextRevision>;
+
+    /// Save a collection, replacing any with the same ID
+    ///
+    /// The default collection is never saved; it exists without being stored.
+    async fn save_collection(&self, collection: Collection) -> McpResult<Collection>;
+
+    /// Find a saved collection by its ID, failing with `McpError::CollectionNotFound` if
+    /// there is none
+    async fn find_collection(&self, collection_id: Uuid) -> McpResult<Collection>;
+
+    /// List every saved collection, in no particular order
+    async fn list_collections(&self) -> McpResult<Vec<Collection>>;
+
+    /// Delete a saved collection, failing with `McpError::CollectionNotFound` if there is
+    /// none; its contexts are left as they are
+    async fn delete_collection(&self, collection_id: Uuid) -> McpResult<()>;
+
     /// Find contexts by tags
     async fn find_by_tags(
         &self,
@@ -26,14 +170,229 @@ pub trait ContextRepositoryPort {
     ) -> McpResult<Vec<Context>>;
 
     /// List all contexts with pagination
+    ///
+    /// Contexts come back in a stable order, so consecutive pages neither repeat nor skip any.
     async fn list_all(&self, limit: usize, offset: usize) -> McpResult<Vec<Context>>;
 
+    /// Count all contexts
+    async fn count_all(&self) -> McpResult<usize>;
+
+    /// Count the contexts `find_by_tags` would return without pagination
+    async fn count_by_tags(&self, tags: &[String]) -> McpResult<usize>;
+
+    /// Find the contexts matching a filter, with pagination
+    ///
+    /// Repositories that can only filter by required tags themselves are scanned a page at a
+    /// time for the rest of the filter.
+    async fn find_filtered(
+        &self,
+        filter: &ContextFilter,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        if !filter.needs_scan() {
+            return if filter.tags.is_empty() {
+                self.list_all(limit, offset).await
+            } else {
+                self.find_by_tags(&filter.tags, limit, offset).await
+            };
+        }
+
+        let mut matching = Vec::new();
+        let mut skipped = 0;
+        let mut page_offset = 0;
+        while matching.len() < limit {
+            let page = if filter.tags.is_empty() || !filter.requires_every_tag() {
+                self.list_all(FILTER_PAGE_SIZE, page_offset).await?
+            } else {
+                self.find_by_tags(&filter.tags, FILTER_PAGE_SIZE, page_offset)
+                    .await?
+            };
+            page_offset += page.len();
+            let last_page = page.len() < FILTER_PAGE_SIZE;
+
+            for context in page.into_iter().filter(|context| filter.matches(context)) {
+                if skipped < offset {
+                    skipped += 1;
+                } else if matching.len() < limit {
+                    matching.push(context);
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        Ok(matching)
+    }
+
+    /// Count the contexts `find_filtered` would return without pagination
+    async fn count_filtered(&self, filter: &ContextFilter) -> McpResult<usize> {
+        if !filter.needs_scan() {
+            return if filter.tags.is_empty() {
+                self.count_all().await
+            } else {
+                self.count_by_tags(&filter.tags).await
+            };
+        }
+
+        let mut count = 0;
+        let mut page_offset = 0;
+        loop {
+            let page = if filter.tags.is_empty() || !filter.requires_every_tag() {
+                self.list_all(FILTER_PAGE_SIZE, page_offset).await?
+            } else {
+                self.find_by_tags(&filter.tags, FILTER_PAGE_SIZE, page_offset)
+                    .await?
+            };
+            page_offset += page.len();
+            count += page
+                .iter()
+                .filter(|context| filter.matches(context))
+                .count();
+
+            if page.len() < FILTER_PAGE_SIZE {
+                return Ok(count);
+            }
+        }
+    }
+
+    /// Count the contexts live at `now` carrying each tag in use, in no particular order
+    ///
+    /// Deleted and expired contexts aren't counted. By default every context is read, a page
+    /// at a time.
+    async fn tag_counts(&self, now: DateTime<Utc>) -> McpResult<Vec<(String, usize)>> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut page_offset = 0;
+        loop {
+            let page = self.list_all(FILTER_PAGE_SIZE, page_offset).await?;
+            page_offset += page.len();
+            for context in page.iter().filter(|context| context.is_live_at(now)) {
+                for tag in &context.metadata.tags {
+                    *counts.entry(tag.clone()).or_default() += 1;
+                }
+            }
+
+            if page.len() < FILTER_PAGE_SIZE {
+                return Ok(counts.into_iter().collect());
+            }
+        }
+    }
+
+    /// Find up to `limit` contexts that expired by `now`, still stored until deleted, skipping
+    /// the first `offset` in listing order
+    ///
+    /// By default every context is read, a page at a time.
+    async fn find_expired(
+        &self,
+        now: DateTime<Utc>,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        let mut expired = Vec::new();
+        let mut skipped = 0;
+        let mut page_offset = 0;
+        while expired.len() < limit {
+            let page = self.list_all(FILTER_PAGE_SIZE, page_offset).await?;
+            page_offset += page.len();
+            let last_page = page.len() < FILTER_PAGE_SIZE;
+
+            for context in page
+                .into_iter()
+                .filter(|context| context.is_expired_at(now))
+            {
+                if skipped < offset {
+                    skipped += 1;
+                } else if expired.len() < limit {
+                    expired.push(context);
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Find up to `limit` soft-deleted contexts that were deleted by `deleted_before`,
+    /// skipping the first `offset` in listing order
+    ///
+    /// By default every context is read, a page at a time.
+    async fn list_deleted(
+        &self,
+        deleted_before: DateTime<Utc>,
+        limit: usize,
+        offset: usize,
+    ) -> McpResult<Vec<Context>> {
+        let mut deleted = Vec::new();
+        let mut skipped = 0;
+        let mut page_offset = 0;
+        while deleted.len() < limit {
+            let page = self.list_all(FILTER_PAGE_SIZE, page_offset).await?;
+            page_offset += page.len();
+            let last_page = page.len() < FILTER_PAGE_SIZE;
+
+            for context in page
+                .into_iter()
+                .filter(|context| context.deleted_at.is_some_and(|at| at <= deleted_before))
+            {
+                if skipped < offset {
+                    skipped += 1;
+                } else if deleted.len() < limit {
+                    deleted.push(context);
+                }
+            }
+            if last_page {
+                break;
+            }
+        }
+        Ok(deleted)
+    }
+
+    /// Find the contexts whose `content_hash` is `content_hash`, expired ones included
+    ///
+    /// By default every context is read, a page at a time.
+    async fn find_by_content_hash(&self, content_hash: &str) -> McpResult<Vec<Context>> {
+        let mut matching = Vec::new();
+        let mut page_offset = 0;
+        loop {
+            let page = self.list_all(FILTER_PAGE_SIZE, page_offset).await?;
+            page_offset += page.len();
+            let last_page = page.len() < FILTER_PAGE_SIZE;
+
+            matching.extend(
+                page.into_iter().filter(|context| {
+                    context.metadata.content_hash.as_deref() == Some(content_hash)
+                }),
+            );
+            if last_page {
+                return Ok(matching);
+            }
+        }
+    }
+
+    /// Check whether a context exists
+    async fn exists(&self, context_id: Uuid) -> McpResult<bool>;
+
     /// Save context chunks
     async fn save_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>>;
 
     /// Find chunks for a context
     async fn find_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<Vec<ContextChunk>>;
 
+    /// Find a single chunk by its ID, failing with `McpError::ChunkNotFound` if no context has it
+    async fn find_chunk_by_id(&self, chunk_id: Uuid) -> McpResult<ContextChunk>;
+
     /// Delete all chunks for a context
     async fn delete_chunks_by_context_id(&self, context_id: Uuid) -> McpResult<()>;
+
+    /// Take the chunk IDs of the contexts evicted to make room since the last call, so their
+    /// embeddings can be deleted too; repositories that never evict have none
+    async fn take_evicted_chunk_ids(&self) -> McpResult<Vec<Uuid>> {
+        Ok(Vec::new())
+    }
+
+    /// Check that the repository can serve requests, by default with a one-context read
+    async fn check_health(&self) -> McpResult<()> {
+        self.list_all(1, 0).await.map(|_| ())
+    }
 }
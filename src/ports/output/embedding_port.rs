@@ -1,20 +1,22 @@
-use crate::domain::{ContextChunk, McpResult};
+use crate::domain::{McpError, McpResult};
 use async_trait::async_trait;
 
-/// Output port for generating and working with embeddings
+/// Output port for turning text into embeddings
 #[async_trait]
 pub trait EmbeddingPort {
-    /// Generate embeddings for a batch of context chunks
-    async fn embed_chunks(&self, chunks: Vec<ContextChunk>) -> McpResult<Vec<ContextChunk>>;
+    /// Embed texts, returning one vector per text in the same order
+    async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>>;
 
-    /// Find similar chunks based on a query embedding
-    async fn find_similar(&self, query: &str, limit: usize) -> McpResult<Vec<(ContextChunk, f32)>>;
+    /// Embed a search query, which some providers embed differently from documents
+    async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>> {
+        self.embed_texts(&[query.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| McpError::EmbeddingError("No embedding returned for the query".into()))
+    }
 
-    /// Find similar chunks based on a query embedding, filtered by tags
-    async fn find_similar_with_tags(
-        &self,
-        query: &str,
-        tags: &[String],
-        limit: usize,
-    ) -> McpResult<Vec<(ContextChunk, f32)>>;
+    /// Check that the provider can embed text; local providers have nothing to check
+    async fn check_health(&self) -> McpResult<()> {
+        Ok(())
+    }
 }
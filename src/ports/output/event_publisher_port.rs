@@ -0,0 +1,7 @@
+use crate::domain::ContextEvent;
+
+/// Output port for telling subscribers about changes to contexts
+pub trait EventPublisherPort {
+    /// Publish `event` to the current subscribers; events nobody listens for are dropped
+    fn publish(&self, event: ContextEvent);
+}
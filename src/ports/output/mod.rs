@@ -1,5 +1,15 @@
+pub mod content_fetcher_port;
 pub mod context_repository_port;
 pub mod embedding_port;
+pub mod event_publisher_port;
+pub mod query_expansion_port;
+pub mod reranker_port;
+pub mod vector_store_port;
 
+pub use content_fetcher_port::ContentFetcherPort;
 pub use context_repository_port::ContextRepositoryPort;
 pub use embedding_port::EmbeddingPort;
+pub use event_publisher_port::EventPublisherPort;
+pub use query_expansion_port::QueryExpansionPort;
+pub use reranker_port::RerankerPort;
+pub use vector_store_port::VectorStorePort;
@@ -0,0 +1,10 @@
+use crate::domain::McpResult;
+use async_trait::async_trait;
+
+/// Output port for finding alternative terms for a search query, such as synonyms and the full
+/// forms of acronyms
+#[async_trait]
+pub trait QueryExpansionPort {
+    /// Terms to search for along with `query`, leaving out those it already contains
+    async fn expand(&self, query: &str) -> McpResult<Vec<String>>;
+}
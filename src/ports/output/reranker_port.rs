@@ -0,0 +1,15 @@
+use crate::domain::{ContextMatch, McpResult};
+use async_trait::async_trait;
+
+/// Output port for reordering search matches by a second, more precise relevance model
+#[async_trait]
+pub trait RerankerPort {
+    /// The `top_k` of `candidates` most relevant to `query`, best first, each scored by the
+    /// reranker in place of its first-stage score
+    async fn rerank(
+        &self,
+        query: &str,
+        candidates: Vec<ContextMatch>,
+        top_k: usize,
+    ) -> McpResult<Vec<ContextMatch>>;
+}
@@ -0,0 +1,23 @@
+use crate::domain::{ContextChunk, McpResult};
+use async_trait::async_trait;
+use uuid::Uuid;
+
+/// Output port for storing chunk embeddings and searching them by similarity
+#[async_trait]
+pub trait VectorStorePort {
+    /// Store embedded chunks of contexts tagged `tags`, replacing the chunks stored earlier for
+    /// the same contexts
+    async fn upsert(&self, chunks: &[ContextChunk], tags: &[String]) -> McpResult<()>;
+
+    /// Drop chunks, so they no longer show up in searches
+    async fn delete(&self, chunk_ids: &[Uuid]) -> McpResult<()>;
+
+    /// The `limit` chunks most similar to `query`, best first, only from contexts carrying
+    /// every one of `tags`
+    async fn search(
+        &self,
+        query: &[f32],
+        tags: &[String],
+        limit: usize,
+    ) -> McpResult<Vec<(ContextChunk, f32)>>;
+}
@@ -1,10 +1,32 @@
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use crate::adapter::output::{InMemoryContextRepository, SimpleEmbeddingService};
-use crate::application::ContextManagementService;
-use crate::domain::{Context, ContextMetadata};
-use crate::ports::in_ports::ContextManagementPort;
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use mockall::mock;
+use uuid::Uuid;
+
+use crate::adapter::output::{
+    CapacityPolicy, InMemoryContextRepository, InMemoryVectorIndex, SimpleEmbeddingService,
+};
+use crate::application::{CollectionService, ContextManagementService, ContextSearchService};
+use crate::domain::{
+    content_hash, ChildPolicy, Collection, Context, ContextChunk, ContextFilter, ContextMetadata,
+    ContextRelation, ContextRevision, DeleteOutcome, McpError, McpResult, OnDuplicate,
+    SearchOptions, StoreOptions,
+};
+use crate::ports::in_ports::{CollectionPort, ContextManagementPort, ContextSearchPort};
+use crate::ports::out_ports::{ContextRepositoryPort, EmbeddingPort, VectorStorePort};
+
+mock! {
+    EmbeddingService {}
+    #[async_trait]
+    impl EmbeddingPort for EmbeddingService {
+        async fn embed_texts(&self, texts: &[String]) -> McpResult<Vec<Vec<f32>>>;
+        async fn embed_query(&self, query: &str) -> McpResult<Vec<f32>>;
+    }
+}
 
 #[tokio::test]
 async fn test_store_and_retrieve_context() {
@@ -13,12 +35,16 @@ async fn test_store_and_retrieve_context() {
     let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
 
     // Initialize application service
-    let context_service = Arc::new(ContextManagementService::new(
-        context_repository.clone(),
-        embedding_service.clone(),
-        1000, // max_chunk_size
-        200,  // chunk_overlap
-    ));
+    let context_service = Arc::new(
+        ContextManagementService::new(
+            context_repository.clone(),
+            embedding_service.clone(),
+            embedding_service.clone(),
+            1000, // max_chunk_size
+            200,  // chunk_overlap
+        )
+        .unwrap(),
+    );
 
     // Test storing a context
     let content = "This is a test context for the Model Context Protocol implementation";
@@ -32,7 +58,7 @@ async fn test_store_and_retrieve_context() {
 
     // Store context
     let stored_context = context_service
-        .store_context(content.to_string(), metadata)
+        .store_context(content.to_string(), metadata, StoreOptions::default())
         .await
         .expect("Failed to store context");
 
@@ -57,7 +83,7 @@ async fn test_store_and_retrieve_context() {
 
     // List contexts with tags
     let contexts_with_tags = context_service
-        .list_contexts(Some(vec!["test".to_string()]), 10, 0)
+        .list_contexts(ContextFilter::tagged(vec!["test".to_string()]), 10, 0)
         .await
         .expect("Failed to list contexts");
 
@@ -65,6 +91,34 @@ async fn test_store_and_retrieve_context() {
     assert_eq!(contexts_with_tags.len(), 1);
     assert_eq!(contexts_with_tags[0].id, stored_context.id);
 
+    // Counts agree with the listing
+    let test_tags = ContextFilter::tagged(vec!["test".to_string()]);
+    assert_eq!(
+        context_service
+            .count_contexts(ContextFilter::default())
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        context_service
+            .count_contexts(test_tags.clone())
+            .await
+            .unwrap(),
+        1
+    );
+    assert_eq!(
+        context_service
+            .count_contexts(ContextFilter::tagged(vec!["missing".to_string()]))
+            .await
+            .unwrap(),
+        0
+    );
+    assert!(context_service
+        .context_exists(stored_context.id)
+        .await
+        .unwrap());
+
     // Test updating a context
     let updated_content = "This is an updated test context";
     let updated_metadata = ContextMetadata {
@@ -80,6 +134,8 @@ async fn test_store_and_retrieve_context() {
             stored_context.id,
             updated_content.to_string(),
             updated_metadata,
+            None,
+            None,
         )
         .await
         .expect("Failed to update context");
@@ -98,4 +154,1669 @@ async fn test_store_and_retrieve_context() {
     // Verify context was deleted
     let result = context_service.get_context(stored_context.id).await;
     assert!(result.is_err(), "Context should have been deleted");
+    assert_eq!(context_service.count_contexts(test_tags).await.unwrap(), 0);
+    assert!(!context_service
+        .context_exists(stored_context.id)
+        .await
+        .unwrap());
+}
+
+#[tokio::test]
+async fn test_failed_embedding_leaves_no_orphaned_context() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+
+    // Embedding succeeds for the first store and fails afterwards
+    let calls = AtomicUsize::new(0);
+    let mut embedding_mock = MockEmbeddingService::new();
+    embedding_mock.expect_embed_texts().returning(move |texts| {
+        if calls.fetch_add(1, Ordering::SeqCst) == 0 {
+            Ok(texts.iter().map(|_| vec![1.0]).collect())
+        } else {
+            Err(McpError::EmbeddingError("model unavailable".to_string()))
+        }
+    });
+
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        Arc::new(embedding_mock),
+        Arc::new(InMemoryVectorIndex::new()),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let stored = context_service
+        .store_context(
+            "Original content".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .expect("Failed to store context");
+
+    // A failed store leaves nothing behind
+    let result = context_service
+        .store_context(
+            "Never stored".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await;
+    assert!(matches!(result, Err(McpError::EmbeddingError(_))));
+
+    let contexts = context_repository.list_all(10, 0).await.unwrap();
+    assert_eq!(contexts.len(), 1);
+    assert_eq!(contexts[0].id, stored.id);
+
+    // A failed update keeps the previous content and chunks
+    let result = context_service
+        .update_context(
+            stored.id,
+            "Updated content".to_string(),
+            ContextMetadata::default(),
+            None,
+            None,
+        )
+        .await;
+    assert!(matches!(result, Err(McpError::EmbeddingError(_))));
+
+    let context = context_repository.find_by_id(stored.id).await.unwrap();
+    assert_eq!(context.content, "Original content");
+    let chunks = context_repository
+        .find_chunks_by_context_id(stored.id)
+        .await
+        .unwrap();
+    assert!(!chunks.is_empty());
+    assert!(chunks
+        .iter()
+        .all(|chunk| chunk.content.contains("Original")));
+}
+
+#[tokio::test]
+async fn test_wrong_embedding_dimension_is_rejected() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+
+    let mut embedding_mock = MockEmbeddingService::new();
+    embedding_mock
+        .expect_embed_texts()
+        .returning(|texts| Ok(texts.iter().map(|_| vec![0.5; 3]).collect()));
+
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        Arc::new(embedding_mock),
+        Arc::new(InMemoryVectorIndex::new()),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_embedding_dimension(4);
+
+    let result = context_service
+        .store_context(
+            "Some content".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(McpError::EmbeddingError(message)) if message == "dimension mismatch: expected 4 got 3"
+    ));
+    assert_eq!(context_repository.count_all().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_content_over_the_limit_is_rejected() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service,
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_max_content_bytes(10);
+
+    // The limit counts bytes, so nine ASCII bytes and a two-byte character go over it
+    let stored = context_service
+        .store_context(
+            "x".repeat(10),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    let result = context_service
+        .store_context(
+            format!("{}é", "x".repeat(9)),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await;
+    assert!(matches!(
+        result,
+        Err(McpError::ValidationError(message)) if message == "content exceeds 10 bytes"
+    ));
+
+    // Updates are held to the same limit, leaving the context as it was
+    let result = context_service
+        .update_context(
+            stored.id,
+            "x".repeat(11),
+            ContextMetadata::default(),
+            None,
+            None,
+        )
+        .await;
+    assert!(matches!(result, Err(McpError::ValidationError(_))));
+    assert_eq!(
+        context_service
+            .get_context(stored.id)
+            .await
+            .unwrap()
+            .content,
+        "x".repeat(10)
+    );
+    assert_eq!(context_repository.count_all().await.unwrap(), 1);
+}
+
+#[tokio::test]
+async fn test_removed_chunks_no_longer_match_searches() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let deleted = context_service
+        .store_context(
+            "Kafka consumers lag behind".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    let updated = context_service
+        .store_context(
+            "Kafka brokers restart nightly".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    context_service.delete_context(deleted.id).await.unwrap();
+    context_service
+        .update_context(
+            updated.id,
+            "Postgres vacuums nightly".to_string(),
+            ContextMetadata::default(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+
+    let query = embedding_service.embed_query("kafka").await.unwrap();
+    let matches = embedding_service.search(&query, &[], 10).await.unwrap();
+    assert!(matches
+        .iter()
+        .all(|(chunk, _)| chunk.context_id != deleted.id));
+
+    // Only the chunks of the updated content are left
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.content, "Postgres vacuums nightly");
+}
+
+#[tokio::test]
+async fn test_evicted_contexts_no_longer_match_searches() {
+    let context_repository = Arc::new(InMemoryContextRepository::with_capacity(
+        1,
+        CapacityPolicy::EvictLeastRecent,
+    ));
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let evicted = context_service
+        .store_context(
+            "Kafka consumers lag behind".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    let kept = context_service
+        .store_context(
+            "Kafka brokers restart nightly".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        context_service.get_context(evicted.id).await,
+        Err(McpError::ContextNotFound(_))
+    ));
+
+    // The evicted context's embeddings went with it
+    let query = embedding_service.embed_query("kafka").await.unwrap();
+    let matches = embedding_service.search(&query, &[], 10).await.unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.context_id, kept.id);
+}
+
+#[tokio::test]
+async fn test_batch_delete_reports_each_id() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_max_delete_batch(3);
+
+    let mut ids = Vec::new();
+    for content in [
+        "Kafka consumers lag",
+        "Kafka brokers restart",
+        "Kafka topics grow",
+    ] {
+        let context = context_service
+            .store_context(
+                content.to_string(),
+                ContextMetadata::default(),
+                StoreOptions::default(),
+            )
+            .await
+            .unwrap();
+        ids.push(context.id);
+    }
+
+    // Batches over the limit delete nothing
+    let mut too_many = ids.clone();
+    too_many.push(Uuid::new_v4());
+    let result = context_service.delete_contexts(too_many).await;
+    assert!(matches!(
+        result,
+        Err(McpError::ValidationError(message)) if message == "ids has 4 entries, more than the maximum of 3"
+    ));
+    assert_eq!(context_repository.count_all().await.unwrap(), 3);
+
+    let missing = Uuid::new_v4();
+    let outcomes = context_service
+        .delete_contexts(vec![ids[0], missing, ids[2]])
+        .await
+        .unwrap();
+    assert_eq!(
+        outcomes,
+        [
+            (ids[0], DeleteOutcome::Deleted),
+            (missing, DeleteOutcome::NotFound),
+            (ids[2], DeleteOutcome::Deleted),
+        ]
+    );
+
+    // The deleted contexts' chunks and embeddings are gone with them
+    assert_eq!(context_repository.count_all().await.unwrap(), 1);
+    for id in [ids[0], ids[2]] {
+        assert!(context_repository
+            .find_chunks_by_context_id(id)
+            .await
+            .unwrap_or_default()
+            .is_empty());
+    }
+    let query = embedding_service.embed_query("kafka").await.unwrap();
+    let matches = embedding_service.search(&query, &[], 10).await.unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.context_id, ids[1]);
+}
+
+#[tokio::test]
+async fn test_delete_by_tags_leaves_other_contexts() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let tagged = |tags: &[&str]| ContextMetadata {
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        ..ContextMetadata::default()
+    };
+    for (content, tags) in [
+        ("Run 42 loss curve", tagged(&["run-42"])),
+        ("Run 42 eval scores", tagged(&["run-42", "eval"])),
+        ("Run 43 loss curve", tagged(&["run-43"])),
+    ] {
+        context_service
+            .store_context(content.to_string(), tags, StoreOptions::default())
+            .await
+            .unwrap();
+    }
+
+    // Every tag must match
+    assert_eq!(
+        context_service
+            .delete_by_tags(vec!["run-43".to_string(), "eval".to_string()])
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(
+        context_service
+            .delete_by_tags(vec!["run-42".to_string()])
+            .await
+            .unwrap(),
+        2
+    );
+
+    let remaining = context_repository.list_all(10, 0).await.unwrap();
+    assert_eq!(remaining.len(), 1);
+    assert_eq!(remaining[0].content, "Run 43 loss curve");
+    let query = embedding_service.embed_query("loss curve").await.unwrap();
+    let matches = embedding_service.search(&query, &[], 10).await.unwrap();
+    assert!(matches
+        .iter()
+        .all(|(chunk, _)| chunk.context_id == remaining[0].id));
+
+    // An empty tag list would match every context
+    assert!(matches!(
+        context_service.delete_by_tags(Vec::new()).await,
+        Err(McpError::ValidationError(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_delete_by_tags_pages_past_contexts_it_cant_delete() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_child_policy(ChildPolicy::Reject);
+    let store = |content: String, tags: &[&str], parent_id| {
+        context_service.store_context(
+            content,
+            ContextMetadata {
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..ContextMetadata::default()
+            },
+            StoreOptions {
+                parent_id,
+                ..StoreOptions::default()
+            },
+        )
+    };
+
+    // The oldest match lists after a full delete page of parents that can't be deleted
+    let deletable = store("Stale note".to_string(), &["stale"], None)
+        .await
+        .unwrap();
+    let soft_deleted = store("Binned note".to_string(), &["stale"], None)
+        .await
+        .unwrap();
+    context_service
+        .soft_delete_context(soft_deleted.id)
+        .await
+        .unwrap();
+    for index in 0..500 {
+        let parent = store(format!("Stale parent {}", index), &["stale"], None)
+            .await
+            .unwrap();
+        store(format!("Child {}", index), &[], Some(parent.id))
+            .await
+            .unwrap();
+    }
+
+    assert_eq!(
+        context_service
+            .delete_by_tags(vec!["stale".to_string()])
+            .await
+            .unwrap(),
+        1
+    );
+    assert!(!context_repository.exists(deletable.id).await.unwrap());
+
+    // Soft-deleted matches are left to be restored
+    let restored = context_service
+        .restore_context(soft_deleted.id)
+        .await
+        .unwrap();
+    assert_eq!(restored.content, "Binned note");
+}
+
+#[tokio::test]
+async fn test_fresh_vector_store_is_loaded_from_the_repository() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let first_run = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        first_run.clone(),
+        first_run,
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    let kafka = context_service
+        .store_context(
+            "Kafka consumers lag behind".to_string(),
+            ContextMetadata {
+                tags: vec!["streaming".to_string()],
+                ..ContextMetadata::default()
+            },
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    context_service
+        .store_context(
+            "Postgres vacuums nightly".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    // A restart starts with an empty vector store over the same repository
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    assert_eq!(context_service.load_existing().await.unwrap(), 2);
+
+    let search_service = ContextSearchService::new(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service.clone(),
+        10,
+    );
+    let result = search_service
+        .search("kafka consumers".to_string(), 10, SearchOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(result.matches[0].context.id, kafka.id);
+
+    // Tags are loaded along with the embeddings
+    let query = embedding_service.embed_query("kafka").await.unwrap();
+    let matches = embedding_service
+        .search(&query, &["streaming".to_string()], 10)
+        .await
+        .unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].0.context_id, kafka.id);
+}
+
+#[tokio::test]
+async fn test_equal_scores_are_ordered_the_same_every_time() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+
+    // Identical contexts created at the same instant score the same for any query
+    let created_at = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+    let content = "Restart the payments service after a failover";
+    let embedding = embedding_service
+        .embed_texts(&[content.to_string()])
+        .await
+        .unwrap()
+        .remove(0);
+    let mut ids = Vec::new();
+    for _ in 0..20 {
+        let context = Context {
+            created_at,
+            ..Context::new(content, ContextMetadata::default())
+        };
+        let chunk = ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id: context.id,
+            content: content.to_string(),
+            position: 0,
+            embedding: Some(embedding.clone()),
+        };
+        embedding_service
+            .upsert(std::slice::from_ref(&chunk), &[])
+            .await
+            .unwrap();
+        ids.push(context.id);
+        context_repository
+            .save_context_with_chunks(context, vec![chunk])
+            .await
+            .unwrap();
+    }
+    ids.sort();
+
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    let search_service = ContextSearchService::new(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service,
+        20,
+    );
+
+    // Ties fall back to the id, since every context is equally new
+    for _ in 0..3 {
+        let result = search_service
+            .search(
+                "payments failover".to_string(),
+                20,
+                SearchOptions::default(),
+            )
+            .await
+            .unwrap();
+        let found: Vec<Uuid> = result.matches.iter().map(|m| m.context.id).collect();
+        assert_eq!(found, ids);
+
+        let mut listed = Vec::new();
+        for offset in [0, 10] {
+            let page = context_service
+                .list_contexts(ContextFilter::default(), 10, offset)
+                .await
+                .unwrap();
+            listed.extend(page.into_iter().map(|context| context.id));
+        }
+        assert_eq!(listed, ids);
+    }
+}
+
+#[tokio::test]
+async fn test_sweep_deletes_only_expired_contexts() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let now = Utc::now();
+    let soon = now + chrono::Duration::minutes(1);
+    let later = now + chrono::Duration::hours(1);
+    let mut expiring = Vec::new();
+    for content in ["Deploy window notes", "Deploy rollback notes"] {
+        let context = context_service
+            .store_context(
+                content.to_string(),
+                ContextMetadata::default(),
+                StoreOptions {
+                    expires_at: Some(soon),
+                    ..StoreOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+        expiring.push(context.id);
+    }
+    let lasting = context_service
+        .store_context(
+            "Deploy checklist notes".to_string(),
+            ContextMetadata::default(),
+            StoreOptions {
+                expires_at: Some(later),
+                ..StoreOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+    let forever = context_service
+        .store_context(
+            "Deploy owners notes".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    // Nothing has expired yet
+    assert_eq!(context_service.sweep_expired(now).await.unwrap(), 0);
+
+    let after = now + chrono::Duration::minutes(2);
+    assert_eq!(context_service.sweep_expired(after).await.unwrap(), 2);
+    for id in &expiring {
+        assert!(!context_repository.exists(*id).await.unwrap());
+        assert!(context_repository
+            .find_chunks_by_context_id(*id)
+            .await
+            .unwrap_or_default()
+            .is_empty());
+    }
+    let mut remaining: Vec<Uuid> = context_repository
+        .list_all(10, 0)
+        .await
+        .unwrap()
+        .iter()
+        .map(|context| context.id)
+        .collect();
+    remaining.sort();
+    let mut expected = vec![lasting.id, forever.id];
+    expected.sort();
+    assert_eq!(remaining, expected);
+
+    // Their embeddings went with them
+    let query = embedding_service.embed_query("deploy notes").await.unwrap();
+    let matches = embedding_service.search(&query, &[], 10).await.unwrap();
+    assert!(matches
+        .iter()
+        .all(|(chunk, _)| expected.contains(&chunk.context_id)));
+    assert_eq!(context_service.sweep_expired(after).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_duplicate_content_is_allowed_skipped_or_rejected() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    let store = |on_duplicate| {
+        context_service.store_context(
+            "Release notes for the spring launch".to_string(),
+            ContextMetadata::default(),
+            StoreOptions {
+                on_duplicate,
+                ..StoreOptions::default()
+            },
+        )
+    };
+
+    let original = store(OnDuplicate::Error).await.unwrap();
+    assert_eq!(
+        original.metadata.content_hash.as_deref(),
+        Some(content_hash("Release notes for the spring launch").as_str())
+    );
+
+    let skipped = store(OnDuplicate::Skip).await.unwrap();
+    assert_eq!(skipped.id, original.id);
+    assert!(matches!(
+        store(OnDuplicate::Error).await,
+        Err(McpError::ContextAlreadyExists(id)) if id == original.id
+    ));
+    assert_eq!(context_repository.count_all().await.unwrap(), 1);
+
+    let allowed = store(OnDuplicate::Allow).await.unwrap();
+    assert_ne!(allowed.id, original.id);
+    assert_eq!(
+        allowed.metadata.content_hash,
+        original.metadata.content_hash
+    );
+    assert_eq!(context_repository.count_all().await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_expired_duplicates_do_not_count() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let expired = context_service
+        .store_context(
+            "Standup notes".to_string(),
+            ContextMetadata::default(),
+            StoreOptions {
+                expires_at: Some(Utc::now() - chrono::Duration::minutes(1)),
+                ..StoreOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+    let stored = context_service
+        .store_context(
+            "Standup notes".to_string(),
+            ContextMetadata::default(),
+            StoreOptions {
+                on_duplicate: OnDuplicate::Error,
+                ..StoreOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_ne!(stored.id, expired.id);
+}
+
+#[tokio::test]
+async fn test_update_rehashes_the_new_content() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let original = context_service
+        .store_context(
+            "First draft".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    let updated = context_service
+        .update_context(
+            original.id,
+            "Second draft".to_string(),
+            ContextMetadata::default(),
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        updated.metadata.content_hash,
+        Some(content_hash("Second draft"))
+    );
+
+    // Only the new content finds the context as a duplicate
+    let second = content_hash("Second draft");
+    let found = context_repository
+        .find_by_content_hash(&second)
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].id, original.id);
+    let first = content_hash("First draft");
+    assert!(context_repository
+        .find_by_content_hash(&first)
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_updates_keep_revisions_that_can_be_restored() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_max_revisions(2);
+
+    let context = context_service
+        .store_context(
+            "Draft 1".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    for draft in 2..=4 {
+        context_service
+            .update_context(
+                context.id,
+                format!("Draft {}", draft),
+                ContextMetadata::default(),
+                None,
+                None,
+            )
+            .await
+            .unwrap();
+    }
+
+    // Only the two newest prior states are kept
+    let revisions = context_service.list_revisions(context.id).await.unwrap();
+    let kept: Vec<(u64, &str)> = revisions
+        .iter()
+        .map(|revision| (revision.version, revision.content.as_str()))
+        .collect();
+    assert_eq!(kept, vec![(2, "Draft 2"), (3, "Draft 3")]);
+    assert!(matches!(
+        context_service.get_revision(context.id, 1).await,
+        Err(McpError::RevisionNotFound { version: 1, .. })
+    ));
+
+    // Restoring is an update, so the state it replaces becomes a revision too
+    let restored = context_service
+        .restore_revision(context.id, 2, Some(4))
+        .await
+        .unwrap();
+    assert_eq!(restored.content, "Draft 2");
+    assert_eq!(restored.version, 5);
+    assert_eq!(
+        restored.metadata.content_hash,
+        Some(content_hash("Draft 2"))
+    );
+    let latest = context_service.get_revision(context.id, 4).await.unwrap();
+    assert_eq!(latest.content, "Draft 4");
+
+    // A stale expected version is refused like on any update, and archives nothing
+    let versions = |revisions: Vec<ContextRevision>| {
+        revisions
+            .iter()
+            .map(|revision| revision.version)
+            .collect::<Vec<_>>()
+    };
+    let before = versions(context_service.list_revisions(context.id).await.unwrap());
+    assert!(matches!(
+        context_service
+            .restore_revision(context.id, 3, Some(4))
+            .await,
+        Err(McpError::VersionConflict { .. })
+    ));
+    assert!(matches!(
+        context_service
+            .update_context(
+                context.id,
+                "Draft 6".to_string(),
+                ContextMetadata::default(),
+                None,
+                Some(4),
+            )
+            .await,
+        Err(McpError::VersionConflict { .. })
+    ));
+    assert_eq!(
+        versions(context_service.list_revisions(context.id).await.unwrap()),
+        before
+    );
+}
+
+/// Number of chunks a search for "kafka" finds
+async fn kafka_matches(embedding_service: &SimpleEmbeddingService) -> usize {
+    let query = embedding_service.embed_query("kafka").await.unwrap();
+    embedding_service
+        .search(&query, &[], 10)
+        .await
+        .unwrap()
+        .len()
+}
+
+#[tokio::test]
+async fn test_soft_deleted_contexts_can_be_restored() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let context = context_service
+        .store_context(
+            "Kafka consumers lag behind".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    context_service
+        .soft_delete_context(context.id)
+        .await
+        .unwrap();
+
+    // A soft-deleted context can't be read, listed or found by a search
+    assert!(matches!(
+        context_service.get_context(context.id).await,
+        Err(McpError::ContextNotFound(_))
+    ));
+    assert_eq!(
+        context_service
+            .count_contexts(ContextFilter::default())
+            .await
+            .unwrap(),
+        0
+    );
+    assert_eq!(kafka_matches(&embedding_service).await, 0);
+
+    // Nor can the chunks it keeps for a restore
+    let chunk_id = context_repository
+        .find_chunks_by_context_id(context.id)
+        .await
+        .unwrap()[0]
+        .chunk_id;
+    assert!(matches!(
+        context_service.get_chunk(chunk_id).await,
+        Err(McpError::ChunkNotFound(id)) if id == chunk_id
+    ));
+
+    // Restoring brings it back, searchable again from the chunks it kept
+    let restored = context_service.restore_context(context.id).await.unwrap();
+    assert!(context_service.get_chunk(chunk_id).await.is_ok());
+    assert!(restored.deleted_at.is_none());
+    assert_eq!(
+        context_service
+            .get_context(context.id)
+            .await
+            .unwrap()
+            .content,
+        "Kafka consumers lag behind"
+    );
+    assert_eq!(kafka_matches(&embedding_service).await, 1);
+
+    // Chunks dropped while it was deleted are made and embedded again
+    context_service
+        .soft_delete_context(context.id)
+        .await
+        .unwrap();
+    context_repository
+        .delete_chunks_by_context_id(context.id)
+        .await
+        .unwrap();
+    context_service.restore_context(context.id).await.unwrap();
+    let chunks = context_service.get_chunks(context.id).await.unwrap();
+    assert_eq!(chunks.len(), 1);
+    assert!(chunks[0].embedding.is_some());
+    assert_eq!(kafka_matches(&embedding_service).await, 1);
+}
+
+#[tokio::test]
+async fn test_soft_deleted_contexts_can_be_deleted_for_good() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_deleted_retention(chrono::Duration::hours(1));
+
+    let mut ids = Vec::new();
+    for content in ["Deleted by hand", "Purged", "Kept"] {
+        let context = context_service
+            .store_context(
+                content.to_string(),
+                ContextMetadata::default(),
+                StoreOptions::default(),
+            )
+            .await
+            .unwrap();
+        context_service
+            .soft_delete_context(context.id)
+            .await
+            .unwrap();
+        ids.push(context.id);
+    }
+
+    // A hard delete takes a soft-deleted context with it, so there's nothing to restore
+    context_service.delete_context(ids[0]).await.unwrap();
+    assert!(!context_repository.exists(ids[0]).await.unwrap());
+    assert!(matches!(
+        context_service.restore_context(ids[0]).await,
+        Err(McpError::ContextNotFound(_))
+    ));
+
+    // Purging only deletes contexts deleted longer than the retention ago
+    assert_eq!(context_service.purge_deleted(Utc::now()).await.unwrap(), 0);
+    context_repository
+        .soft_delete(ids[1], Utc::now() - chrono::Duration::hours(2))
+        .await
+        .unwrap();
+    assert_eq!(context_service.purge_deleted(Utc::now()).await.unwrap(), 1);
+    assert!(!context_repository.exists(ids[1]).await.unwrap());
+    assert!(context_repository.exists(ids[2]).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_sweeps_page_past_contexts_they_cant_delete() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap()
+    .with_child_policy(ChildPolicy::Reject)
+    .with_deleted_retention(chrono::Duration::hours(1));
+    let now = Utc::now();
+    let store = |content: String, expires_at, parent_id| {
+        context_service.store_context(
+            content,
+            ContextMetadata::default(),
+            StoreOptions {
+                expires_at,
+                parent_id,
+                ..StoreOptions::default()
+            },
+        )
+    };
+
+    // The oldest contexts list after a full delete page of parents that can't be deleted
+    let expiring = store(
+        "Expiring note".to_string(),
+        Some(now + chrono::Duration::hours(1)),
+        None,
+    )
+    .await
+    .unwrap();
+    let binned = store("Binned note".to_string(), None, None).await.unwrap();
+    context_repository
+        .soft_delete(binned.id, now - chrono::Duration::hours(2))
+        .await
+        .unwrap();
+    for index in 0..500 {
+        let expiring_parent = store(
+            format!("Expiring parent {}", index),
+            Some(now + chrono::Duration::hours(1)),
+            None,
+        )
+        .await
+        .unwrap();
+        store(
+            format!("Expiring child {}", index),
+            None,
+            Some(expiring_parent.id),
+        )
+        .await
+        .unwrap();
+        let binned_parent = store(format!("Binned parent {}", index), None, None)
+            .await
+            .unwrap();
+        store(
+            format!("Binned child {}", index),
+            None,
+            Some(binned_parent.id),
+        )
+        .await
+        .unwrap();
+        context_repository
+            .soft_delete(binned_parent.id, now - chrono::Duration::hours(2))
+            .await
+            .unwrap();
+    }
+
+    let later = now + chrono::Duration::hours(2);
+    assert_eq!(context_service.sweep_expired(later).await.unwrap(), 1);
+    assert!(!context_repository.exists(expiring.id).await.unwrap());
+    assert_eq!(context_service.purge_deleted(now).await.unwrap(), 1);
+    assert!(!context_repository.exists(binned.id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_relations_allow_cycles_and_go_with_their_target() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+
+    let mut ids = Vec::new();
+    for content in [
+        "Meeting notes, part one",
+        "Meeting notes, part two",
+        "Summary",
+    ] {
+        let context = context_service
+            .store_context(
+                content.to_string(),
+                ContextMetadata::default(),
+                StoreOptions::default(),
+            )
+            .await
+            .unwrap();
+        ids.push(context.id);
+    }
+    let relation = |target_id: Uuid, relation_type: &str| ContextRelation {
+        target_id,
+        relation_type: relation_type.to_string(),
+    };
+
+    // The two parts of a thread point at each other, and the summary at both
+    context_service
+        .add_relation(ids[0], relation(ids[1], "same_thread"))
+        .await
+        .unwrap();
+    context_service
+        .add_relation(ids[1], relation(ids[0], "same_thread"))
+        .await
+        .unwrap();
+    for part in &ids[..2] {
+        context_service
+            .add_relation(ids[2], relation(*part, "summarizes"))
+            .await
+            .unwrap();
+    }
+    // Adding a relation twice keeps one
+    let summary = context_service
+        .add_relation(ids[2], relation(ids[0], "summarizes"))
+        .await
+        .unwrap();
+    assert_eq!(summary.relations.len(), 2);
+
+    let related = context_service
+        .related_contexts(ids[1], None)
+        .await
+        .unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0].1.content, "Meeting notes, part one");
+    let summarized = context_service
+        .related_contexts(ids[2], Some("summarizes".to_string()))
+        .await
+        .unwrap();
+    let summarized: Vec<Uuid> = summarized.iter().map(|(_, context)| context.id).collect();
+    assert_eq!(summarized, ids[..2].to_vec());
+    assert!(context_service
+        .related_contexts(ids[2], Some("same_thread".to_string()))
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Relations must lead somewhere else
+    assert!(matches!(
+        context_service
+            .add_relation(ids[0], relation(ids[0], "same_thread"))
+            .await,
+        Err(McpError::ValidationError(_))
+    ));
+    assert!(matches!(
+        context_service
+            .add_relation(ids[0], relation(Uuid::new_v4(), "same_thread"))
+            .await,
+        Err(McpError::ValidationError(_))
+    ));
+
+    // Deleting the first part removes every relation to it
+    context_service.delete_context(ids[0]).await.unwrap();
+    let second = context_service.get_context(ids[1]).await.unwrap();
+    assert!(second.relations.is_empty());
+    let summary = context_service.get_context(ids[2]).await.unwrap();
+    assert_eq!(summary.relations, vec![relation(ids[1], "summarizes")]);
+
+    // Removing a relation that isn't there is an error
+    context_service
+        .remove_relations(ids[2], ids[1], None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        context_service.remove_relations(ids[2], ids[1], None).await,
+        Err(McpError::RelationNotFound { .. })
+    ));
+}
+
+#[tokio::test]
+async fn test_children_are_listed_and_deleted_by_the_child_policy() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let service = |policy| {
+        ContextManagementService::new(
+            context_repository.clone(),
+            embedding_service.clone(),
+            embedding_service.clone(),
+            1000, // max_chunk_size
+            200,  // chunk_overlap
+        )
+        .unwrap()
+        .with_child_policy(policy)
+    };
+    let context_service = service(ChildPolicy::Orphan);
+    let store = |content: &str, parent_id| {
+        context_service.store_context(
+            content.to_string(),
+            ContextMetadata::default(),
+            StoreOptions {
+                parent_id,
+                ..StoreOptions::default()
+            },
+        )
+    };
+
+    // A parent must exist
+    assert!(matches!(
+        store("Lost chapter", Some(Uuid::new_v4())).await,
+        Err(McpError::InvalidContextReference(_))
+    ));
+
+    let book = store("Book", None).await.unwrap();
+    let first = store("Chapter one", Some(book.id)).await.unwrap();
+    let second = store("Chapter two", Some(book.id)).await.unwrap();
+    let section = store("Section 1.1", Some(first.id)).await.unwrap();
+    assert_eq!(first.parent_id, Some(book.id));
+
+    let ids = |contexts: Vec<Context>| contexts.iter().map(|c| c.id).collect::<Vec<_>>();
+    let children = ids(context_service
+        .list_children(book.id, false, 10, 0)
+        .await
+        .unwrap());
+    assert_eq!(children.len(), 2);
+    assert!(children.contains(&first.id) && children.contains(&second.id));
+    let page = ids(context_service
+        .list_children(book.id, false, 1, 1)
+        .await
+        .unwrap());
+    assert_eq!(page, children[1..].to_vec());
+
+    // Descendants come after the children, breadth first
+    let descendants = ids(context_service
+        .list_children(book.id, true, 10, 0)
+        .await
+        .unwrap());
+    assert_eq!(descendants[..2], children[..]);
+    assert_eq!(descendants[2..], [section.id]);
+    let page = ids(context_service
+        .list_children(book.id, true, 2, 1)
+        .await
+        .unwrap());
+    assert_eq!(page, descendants[1..].to_vec());
+
+    // By default, deleting a parent orphans its children
+    context_service.delete_context(first.id).await.unwrap();
+    let section = context_service.get_context(section.id).await.unwrap();
+    assert_eq!(section.parent_id, None);
+
+    // Rejecting leaves a parent with children in place
+    let rejecting = service(ChildPolicy::Reject);
+    assert!(matches!(
+        rejecting.delete_context(book.id).await,
+        Err(McpError::HasChildren(id)) if id == book.id
+    ));
+    assert_eq!(
+        rejecting.delete_contexts(vec![book.id]).await.unwrap(),
+        vec![(book.id, DeleteOutcome::HasChildren)]
+    );
+    rejecting.delete_context(section.id).await.unwrap();
+
+    // Cascading deletes the children along with their parent
+    let cascading = service(ChildPolicy::Cascade);
+    let appendix = store("Appendix", Some(second.id)).await.unwrap();
+    cascading.delete_context(book.id).await.unwrap();
+    for id in [book.id, second.id, appendix.id] {
+        assert!(!context_repository.exists(id).await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn test_filtered_searches_find_contexts_past_the_first_candidates() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let collection_service = CollectionService::new(context_repository.clone());
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    let search_service = ContextSearchService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        10,
+    );
+
+    let large = collection_service
+        .create_collection("large".to_string(), None)
+        .await
+        .unwrap();
+    let small = collection_service
+        .create_collection("small".to_string(), None)
+        .await
+        .unwrap();
+
+    // Far more contexts closer to the query than the small collection's one
+    for i in 0..30 {
+        context_service
+            .store_context(
+                format!("Kafka consumer lag runbook {}", i),
+                ContextMetadata {
+                    tags: vec!["noise".to_string()],
+                    ..ContextMetadata::default()
+                },
+                StoreOptions {
+                    collection_id: Some(large.id),
+                    ..StoreOptions::default()
+                },
+            )
+            .await
+            .unwrap();
+    }
+    let wanted = context_service
+        .store_context(
+            "Kafka partitions were rebalanced overnight".to_string(),
+            ContextMetadata {
+                source: Some("wiki".to_string()),
+                ..ContextMetadata::default()
+            },
+            StoreOptions {
+                collection_id: Some(small.id),
+                ..StoreOptions::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    for options in [
+        SearchOptions {
+            collection: Some(small.id),
+            ..SearchOptions::default()
+        },
+        SearchOptions {
+            exclude_tags: vec!["noise".to_string()],
+            ..SearchOptions::default()
+        },
+        SearchOptions {
+            source: Some("wiki".to_string()),
+            ..SearchOptions::default()
+        },
+    ] {
+        let result = search_service
+            .search("kafka consumer lag runbook".to_string(), 1, options)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = result.matches.iter().map(|m| m.context.id).collect();
+        assert_eq!(ids, [wanted.id]);
+        assert_eq!(result.total_matches, 1);
+    }
+}
+
+#[tokio::test]
+async fn test_total_matches_counts_past_the_candidates_fetched() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    let search_service = ContextSearchService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        10,
+    );
+
+    // Far more matches than the five candidates fetched for a limit of one
+    for i in 0..12 {
+        let tags = if i % 3 == 0 {
+            vec!["draft".to_string()]
+        } else {
+            Vec::new()
+        };
+        context_service
+            .store_context(
+                format!("Kafka consumer lag runbook {}", i),
+                ContextMetadata {
+                    tags,
+                    ..ContextMetadata::default()
+                },
+                StoreOptions::default(),
+            )
+            .await
+            .unwrap();
+    }
+
+    let search = |query: &str, options| search_service.search(query.to_string(), 1, options);
+    let result = search("kafka consumer lag", SearchOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(result.matches.len(), 1);
+    assert_eq!(result.total_matches, 12);
+    assert!(!result.total_matches_is_lower_bound);
+
+    let result = search(
+        "kafka consumer lag",
+        SearchOptions {
+            exclude_tags: vec!["draft".to_string()],
+            ..SearchOptions::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.total_matches, 8);
+    assert!(!result.total_matches_is_lower_bound);
+
+    // A score threshold or quoted phrase can only be checked on the candidates scored
+    for (query, options) in [
+        (
+            "kafka consumer lag",
+            SearchOptions {
+                min_score: Some(0.0),
+                ..SearchOptions::default()
+            },
+        ),
+        ("\"consumer lag\"", SearchOptions::default()),
+    ] {
+        let result = search(query, options).await.unwrap();
+        assert_eq!(result.matches.len(), 1);
+        assert!(result.total_matches >= 1 && result.total_matches < 12);
+        assert!(result.total_matches_is_lower_bound);
+    }
+}
+
+#[tokio::test]
+async fn test_contexts_stay_in_their_collection() {
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let collection_service = CollectionService::new(context_repository.clone());
+    let context_service = ContextManagementService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        1000, // max_chunk_size
+        200,  // chunk_overlap
+    )
+    .unwrap();
+    let search_service = ContextSearchService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service.clone(),
+        10,
+    );
+    let store = |content: &str, parent_id, collection_id| {
+        context_service.store_context(
+            content.to_string(),
+            ContextMetadata::default(),
+            StoreOptions {
+                on_duplicate: OnDuplicate::Skip,
+                parent_id,
+                collection_id,
+                ..StoreOptions::default()
+            },
+        )
+    };
+
+    let alpha = collection_service
+        .create_collection("alpha".to_string(), None)
+        .await
+        .unwrap();
+    let beta = collection_service
+        .create_collection(" beta ".to_string(), Some("Second team".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(beta.name, "beta");
+
+    // Names are unique, and can't be taken from the default collection
+    for name in ["alpha", "default"] {
+        assert!(matches!(
+            collection_service
+                .create_collection(name.to_string(), None)
+                .await,
+            Err(McpError::CollectionExists(_))
+        ));
+    }
+    let names: Vec<String> = collection_service
+        .list_collections()
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|collection| collection.name)
+        .collect();
+    assert_eq!(names, ["default", "alpha", "beta"]);
+
+    // A collection must exist
+    assert!(matches!(
+        store("Stray notes", None, Some(Uuid::new_v4())).await,
+        Err(McpError::InvalidContextReference(_))
+    ));
+
+    let in_alpha = store("Kafka consumer lag runbook", None, Some(alpha.id))
+        .await
+        .unwrap();
+    let in_beta = store("Kafka consumer lag runbook", None, Some(beta.id))
+        .await
+        .unwrap();
+    let untagged = store("Kafka broker upgrade notes", None, None)
+        .await
+        .unwrap();
+    let child = store("Kafka lag dashboard", Some(in_beta.id), None)
+        .await
+        .unwrap();
+
+    // Duplicates are only looked for within a collection, and children join their parent's
+    assert_ne!(in_alpha.id, in_beta.id);
+    assert_eq!(untagged.collection(), Collection::default_collection().id);
+    assert_eq!(child.collection_id, Some(beta.id));
+
+    let listed = |collection| {
+        let context_service = &context_service;
+        async move {
+            let filter = ContextFilter::in_collection(collection);
+            let contexts = context_service
+                .list_contexts(filter.clone(), 10, 0)
+                .await
+                .unwrap();
+            assert_eq!(
+                context_service.count_contexts(filter).await.unwrap(),
+                contexts.len()
+            );
+            contexts.iter().map(|c| c.id).collect::<Vec<_>>()
+        }
+    };
+    assert_eq!(listed(alpha.id).await, [in_alpha.id]);
+    assert_eq!(listed(untagged.collection()).await, [untagged.id]);
+    let beta_ids = listed(beta.id).await;
+    assert_eq!(beta_ids.len(), 2);
+    assert!(beta_ids.contains(&in_beta.id) && beta_ids.contains(&child.id));
+
+    for (collection, expected) in [
+        (alpha.id, vec![in_alpha.id]),
+        (untagged.collection(), vec![untagged.id]),
+    ] {
+        let options = SearchOptions {
+            collection: Some(collection),
+            ..SearchOptions::default()
+        };
+        let result = search_service
+            .search("kafka".to_string(), 10, options)
+            .await
+            .unwrap();
+        let ids: Vec<Uuid> = result.matches.iter().map(|m| m.context.id).collect();
+        assert_eq!(ids, expected);
+    }
+
+    // Names and IDs resolve to the same collection
+    assert_eq!(
+        collection_service.resolve_collection("beta").await.unwrap(),
+        beta.id
+    );
+    assert_eq!(
+        collection_service
+            .resolve_collection(&beta.id.to_string())
+            .await
+            .unwrap(),
+        beta.id
+    );
+    assert!(matches!(
+        collection_service.resolve_collection("gamma").await,
+        Err(McpError::ValidationError(_))
+    ));
+
+    // A collection holding contexts, even deleted ones, can't be deleted
+    context_service
+        .soft_delete_context(in_alpha.id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        collection_service.delete_collection(alpha.id).await,
+        Err(McpError::CollectionNotEmpty(id)) if id == alpha.id
+    ));
+    context_service.delete_context(in_alpha.id).await.unwrap();
+    collection_service
+        .delete_collection(alpha.id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        collection_service.get_collection(alpha.id).await,
+        Err(McpError::CollectionNotFound(_))
+    ));
+
+    // The default collection is always there and can't be changed
+    assert!(collection_service
+        .get_collection(untagged.collection())
+        .await
+        .unwrap()
+        .is_default());
+    assert!(matches!(
+        collection_service
+            .delete_collection(untagged.collection())
+            .await,
+        Err(McpError::ValidationError(_))
+    ));
 }
@@ -1,19 +1,150 @@
+use chrono::{TimeZone, Utc};
 use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::io::{
+    AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf, WriteHalf,
+};
 use tokio::net::TcpListener;
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 use uuid::Uuid;
 
-use mcp::adapter::in_adapters::{create_router, AppState};
-use mcp::adapter::out_adapters::{InMemoryContextRepository, SimpleEmbeddingService};
-use mcp::application::{ContextManagementService, ContextSearchService};
-use mcp::domain::ContextMetadata;
+use axum_server::tls_rustls::RustlsConfig;
+use futures::{SinkExt, StreamExt};
+use mcp::adapter::in_adapters::api::models::ExportRecord;
+use mcp::adapter::in_adapters::{
+    create_router, tls_from_config, ApiKeyAuth, AppState, Authenticator, JwtAuth, McpServer,
+    RateLimiter, RouteRateLimits, ShareLinkService, SseTransport,
+};
+use mcp::adapter::out_adapters::{
+    create_repository, BroadcastEventPublisher, HttpContentFetcher, OpenAiEmbeddingService,
+    SimpleEmbeddingService, TfIdfEmbeddingService,
+};
+use mcp::application::{
+    CollectionService, ContextManagementService, ContextSearchService, EvaluationService,
+    IngestionService, ReadinessService,
+};
+use mcp::config::{AppConfig, TlsConfig};
+use mcp::domain::{
+    Context, ContextChunk, ContextMetadata, HeuristicTokenizer, Highlighter, SearchOptions,
+    StoreOptions, TagPolicy,
+};
+use mcp::ports::out_ports::{ContextRepositoryPort, EmbeddingPort, VectorStorePort};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// Default configuration, with the storage backend overridable through
+/// `MCP_TEST_STORAGE_BACKEND` to run the suite against other backends
+fn test_config() -> AppConfig {
+    let mut config = AppConfig::load_defaults().unwrap();
+
+    if let Ok(backend) = std::env::var("MCP_TEST_STORAGE_BACKEND") {
+        // Give every test server its own storage
+        let id = Uuid::new_v4().simple().to_string();
+        config.storage.backend = backend;
+        config.storage.rocksdb_path = std::env::temp_dir()
+            .join(format!("mcp-test-{}", id))
+            .to_string_lossy()
+            .into_owned();
+        config.storage.mongodb_database = format!("mcp_test_{}", id);
+        if let Ok(uri) = std::env::var("MCP_TEST_MONGODB_URI") {
+            config.storage.mongodb_uri = uri;
+        }
+    }
+
+    config
+}
 
 /// Setup a test server on a random port for testing
 async fn setup_test_server() -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    setup_test_server_with(embedding_service.clone(), embedding_service).await
+}
+
+/// Setup a test server embedding with the given service and storing chunks in `vector_store`
+async fn setup_test_server_with(
+    embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    vector_store: Arc<dyn VectorStorePort + Send + Sync>,
+) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    // Initialize adapters through the same factory as the server
+    let context_repository = create_repository(&test_config()).await.unwrap();
+    setup_test_server_on(context_repository, embedding_service, vector_store).await
+}
+
+/// Setup a test server on top of `context_repository`, for tests that write to it directly
+async fn setup_test_server_on(
+    context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
+    embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    vector_store: Arc<dyn VectorStorePort + Send + Sync>,
+) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    start_test_server(
+        context_repository,
+        embedding_service,
+        vector_store,
+        TestServerOptions::default(),
+    )
+    .await
+}
+
+/// Settings of a test server that differ from the defaults
+#[derive(Default)]
+struct TestServerOptions {
+    /// Credentials required on the protected routes
+    auth: Option<Authenticator>,
+
+    /// Limits on reads, searches and writes
+    rate_limits: Option<RouteRateLimits>,
+
+    /// Largest request body accepted, instead of the configured default
+    max_body_bytes: Option<usize>,
+
+    /// Longest context content accepted
+    max_content_bytes: Option<usize>,
+
+    /// Most matches a search returns and most contexts a listing returns, instead of the
+    /// configured defaults
+    max_results: Option<usize>,
+    max_page_size: Option<usize>,
+
+    /// Certificate to serve HTTPS with, instead of plain HTTP
+    tls: Option<RustlsConfig>,
+
+    /// Whether the unversioned paths are served, instead of the configured default
+    legacy_routes: Option<bool>,
+
+    /// Also serve MCP over this end of an in-memory stream, as `mcp-server stdio` serves it
+    /// over stdin and stdout, sharing the server's services and events
+    mcp_stdio: Option<DuplexStream>,
+
+    /// Refuse to ingest URLs on private addresses, as the server does by default; tests
+    /// otherwise ingest from local stub servers
+    block_private_addresses: bool,
+}
+
+/// Setup a test server with `options`
+async fn setup_test_server_with_options(
+    options: TestServerOptions,
+) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
+    let context_repository = create_repository(&test_config()).await.unwrap();
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    start_test_server(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service,
+        options,
+    )
+    .await
+}
+
+/// Start a test server wired to the given adapters
+async fn start_test_server(
+    context_repository: Arc<dyn ContextRepositoryPort + Send + Sync>,
+    embedding_service: Arc<dyn EmbeddingPort + Send + Sync>,
+    vector_store: Arc<dyn VectorStorePort + Send + Sync>,
+    options: TestServerOptions,
+) -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>) {
     // Set up a random available port for the server
     let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
     let server_addr = listener.local_addr().unwrap();
@@ -21,28 +152,96 @@ async fn setup_test_server() -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>
     // Set up channels for shutting down the server
     let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
-    // Initialize adapters
-    let context_repository = Arc::new(InMemoryContextRepository::new());
-    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
-
     // Initialize application services
-    let context_manager = Arc::new(ContextManagementService::new(
+    let events = Arc::new(BroadcastEventPublisher::new(64));
+    let mut context_manager = ContextManagementService::new(
         context_repository.clone(),
         embedding_service.clone(),
+        vector_store.clone(),
         1000, // max_chunk_size
         200,  // chunk_overlap
-    ));
+    )
+    .unwrap()
+    .with_max_delete_batch(test_config().context.max_delete_batch)
+    .with_event_publisher(events.clone());
+    if let Some(max_content_bytes) = options.max_content_bytes {
+        context_manager = context_manager.with_max_content_bytes(max_content_bytes);
+    }
+    let context_manager = Arc::new(context_manager);
 
     let context_search = Arc::new(ContextSearchService::new(
         context_repository.clone(),
         embedding_service.clone(),
+        vector_store.clone(),
         10, // max_results
     ));
 
+    let evaluation = Arc::new(EvaluationService::new(
+        context_manager.clone(),
+        context_search.clone(),
+        "test".to_string(),
+    ));
+
+    let readiness = Arc::new(ReadinessService::new(
+        context_repository.clone(),
+        embedding_service.clone(),
+    ));
+
+    let collections = Arc::new(CollectionService::new(context_repository.clone()));
+
+    let tag_policy = Arc::new(TagPolicy {
+        max_tags: test_config().tags.max_per_context,
+        ..TagPolicy::default()
+    });
+    let mcp_server = Arc::new(
+        McpServer::new(context_manager.clone(), context_search.clone())
+            .with_tag_policy(tag_policy.clone())
+            .with_events(events.clone()),
+    );
+    if let Some(stream) = options.mcp_stdio {
+        let mcp_server = mcp_server.clone();
+        tokio::spawn(async move {
+            let (reader, writer) = tokio::io::split(stream);
+            let _ = mcp_server.serve(reader, writer).await;
+        });
+    }
+    let mcp = Arc::new(SseTransport::new(mcp_server));
+
     // Set up the app state
     let app_state = AppState {
         context_manager,
         context_search,
+        share_links: Arc::new(ShareLinkService::new(
+            "test-secret",
+            chrono::Duration::hours(1),
+            chrono::Duration::days(1),
+        )),
+        share_rate_limiter: Arc::new(RateLimiter::new(100.0, 100)),
+        rate_limits: options.rate_limits,
+        auth: options.auth.map(Arc::new),
+        max_body_bytes: options
+            .max_body_bytes
+            .unwrap_or(test_config().context.max_body_bytes),
+        max_results: options
+            .max_results
+            .unwrap_or(test_config().context.max_results),
+        max_page_size: options
+            .max_page_size
+            .unwrap_or(test_config().context.max_page_size),
+        legacy_routes: options.legacy_routes.unwrap_or(true),
+        tag_policy,
+        highlighter: Arc::new(Highlighter::default()),
+        tokenizer: Arc::new(HeuristicTokenizer),
+        evaluation,
+        ingestion: Arc::new(IngestionService::new(Arc::new(
+            HttpContentFetcher::new(Duration::from_secs(5), 1024 * 1024)
+                .with_private_addresses(!options.block_private_addresses),
+        ))),
+        readiness,
+        collections,
+        events,
+        mcp,
+        started_at: Instant::now(),
     };
 
     // Create the router
@@ -50,6 +249,21 @@ async fn setup_test_server() -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>
 
     // Start the server in a separate task
     let server_handle = tokio::spawn(async move {
+        if let Some(tls) = options.tls {
+            let handle = axum_server::Handle::new();
+            let shutdown = handle.clone();
+            tokio::spawn(async move {
+                shutdown_rx.await.ok();
+                shutdown.graceful_shutdown(None);
+            });
+            axum_server::from_tcp_rustls(listener.into_std().unwrap(), tls)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await
+                .unwrap();
+            return;
+        }
+
         let server = axum::serve(listener, app).with_graceful_shutdown(async {
             shutdown_rx.await.ok();
         });
@@ -67,7 +281,7 @@ async fn setup_test_server() -> (SocketAddr, oneshot::Sender<()>, JoinHandle<()>
 async fn test_client_server_interaction() {
     // Start a test server
     let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
-    let base_url = format!("http://{}", server_addr);
+    let base_url = format!("http://{}/v1", server_addr);
 
     // Create an HTTP client
     let client = reqwest::Client::builder()
@@ -156,8 +370,6 @@ async fn test_client_server_interaction() {
     assert!(found, "Created context should be in the list response");
 
     // Test 4: Search contexts
-    // NOTE: The SimpleEmbeddingService used in tests may not return meaningful search results
-    // So we'll handle empty results gracefully
     let search_query = "integration test";
     let response = client
         .post(&format!("{}/search", base_url))
@@ -173,19 +385,13 @@ async fn test_client_server_interaction() {
 
     let search_response: serde_json::Value = response.json().await.unwrap();
     let matches = search_response["matches"].as_array().unwrap();
-
-    // In a real environment, this would return matches, but for testing purposes
-    // we're just checking that the API call succeeded and returned the expected format
     println!("Search matches: {:?}", matches);
 
-    // Skip the empty matches assertion that was failing
-    // assert!(!matches.is_empty());
-
-    if !matches.is_empty() {
-        // If we got matches, verify they have scores
-        let score = matches[0]["score"].as_f64().unwrap();
-        assert!(score > 0.0);
-    }
+    assert!(!matches.is_empty());
+    assert_eq!(matches[0]["context"]["id"].as_str().unwrap(), context_id);
+    let score = matches[0]["score"].as_f64().unwrap();
+    assert!(score > 0.0);
+    assert!(search_response["total_matches"].as_u64().unwrap() >= matches.len() as u64);
 
     // Test 5: Update a context
     let updated_content = "This is an updated test context for the integration test";
@@ -256,58 +462,95 @@ async fn test_client_server_interaction() {
 }
 
 #[tokio::test]
-async fn test_client_error_handling() {
-    // Start a test server
+async fn test_health_reports_status_and_version() {
     let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
-    let base_url = format!("http://{}", server_addr);
-
-    // Create HTTP client
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
-        .unwrap();
+    let client = reqwest::Client::new();
 
-    // Test 1: Get a non-existent context
-    let non_existent_id = Uuid::new_v4().to_string();
     let response = client
-        .get(&format!("{}/contexts/{}", base_url, non_existent_id))
+        .get(&format!("http://{}/v1/health", server_addr))
         .send()
         .await
         .unwrap();
+    assert_eq!(response.status(), 200);
 
-    assert_eq!(response.status(), 404); // Not Found
+    let health: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(health["status"], "ok");
+    assert_eq!(health["version"], env!("CARGO_PKG_VERSION"));
+    assert!(health["uptime_seconds"].is_u64());
 
-    let error_response: serde_json::Value = response.json().await.unwrap();
-    assert_eq!(error_response["code"], "CONTEXT_NOT_FOUND");
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_ready_when_every_dependency_passes() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
 
-    // Test 2: Invalid search request (missing query)
     let response = client
-        .post(&format!("{}/search", base_url))
-        .json(&serde_json::json!({
-            // Missing required "query" field
-            "limit": 5
-        }))
+        .get(&format!("http://{}/v1/ready", server_addr))
         .send()
         .await
         .unwrap();
+    assert_eq!(response.status(), 200);
 
-    assert!(response.status().is_client_error());
+    let readiness: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(readiness["status"], "ready");
+    let dependencies = readiness["dependencies"].as_array().unwrap();
+    assert_eq!(dependencies.len(), 2);
+    assert!(dependencies
+        .iter()
+        .all(|dependency| dependency["status"] == "ok"));
 
-    // Test 3: Update a non-existent context
-    let non_existent_id = Uuid::new_v4().to_string();
-    let update_request = serde_json::json!({
-        "content": "This won't work",
-        "tags": ["test"],
-    });
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_not_ready_names_the_failing_dependency() {
+    // An embedding provider rejecting the configured credentials
+    let provider = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(
+            wiremock::ResponseTemplate::new(401).set_body_json(serde_json::json!({
+                "error": { "message": "Incorrect API key provided" },
+            })),
+        )
+        .mount(&provider)
+        .await;
+    let embedding_service =
+        OpenAiEmbeddingService::new("wrong-key", "text-embedding-3-small", &provider.uri())
+            .unwrap();
+
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server_with(
+        Arc::new(embedding_service),
+        Arc::new(SimpleEmbeddingService::new(128)),
+    )
+    .await;
+    let client = reqwest::Client::new();
 
     let response = client
-        .put(&format!("{}/contexts/{}", base_url, non_existent_id))
-        .json(&update_request)
+        .get(&format!("http://{}/v1/ready", server_addr))
         .send()
         .await
         .unwrap();
+    assert_eq!(response.status(), 503);
 
-    assert_eq!(response.status(), 404); // Not Found
+    let readiness: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(readiness["status"], "unavailable");
+    let dependencies = readiness["dependencies"].as_array().unwrap();
+    let failing: Vec<&serde_json::Value> = dependencies
+        .iter()
+        .filter(|dependency| dependency["status"] == "error")
+        .collect();
+    assert_eq!(failing.len(), 1);
+    assert_eq!(failing[0]["name"], "embedding");
+    assert!(failing[0]["error"]
+        .as_str()
+        .unwrap()
+        .contains("Incorrect API key"));
 
     // Shutdown the server
     shutdown_tx.send(()).unwrap();
@@ -315,238 +558,4911 @@ async fn test_client_error_handling() {
 }
 
 #[tokio::test]
-async fn test_context_search_functionality() {
-    // Start a test server
-    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
-    let base_url = format!("http://{}", server_addr);
-
-    // Create an HTTP client
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
-        .unwrap();
-
-    // Store multiple contexts with different content and tags
-    let contexts = vec![
-        (
-            "Neural networks are a class of machine learning models inspired by the human brain.",
-            vec!["ai", "machine_learning"],
-        ),
-        (
-            "Transformers have revolutionized natural language processing and understanding.",
-            vec!["ai", "nlp", "transformers"],
-        ),
-        (
-            "Claude is an AI assistant created by Anthropic to be helpful, harmless, and honest.",
-            vec!["ai", "assistant", "anthropic"],
-        ),
-        (
-            "Rust is a systems programming language focused on safety, speed, and concurrency.",
-            vec!["programming", "systems", "rust"],
-        ),
-        (
-            "Python is a popular high-level programming language known for its readability.",
-            vec!["programming", "python"],
-        ),
-    ];
-
-    // Store all the contexts
-    let mut context_ids = Vec::new();
-
-    for (content, tags) in contexts {
-        let store_request = serde_json::json!({
-            "content": content,
-            "tags": tags,
-        });
-
-        let response = client
-            .post(&format!("{}/contexts", base_url))
-            .json(&store_request)
-            .send()
-            .await
-            .unwrap();
+async fn test_api_key_is_required_when_configured() {
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            auth: Some(Authenticator::ApiKey(ApiKeyAuth::new("test-api-key"))),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
 
-        assert_eq!(response.status(), 201);
+    // Missing key
+    let response = client.get(&contexts_url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "AUTH_ERROR");
 
-        let context_response: serde_json::Value = response.json().await.unwrap();
-        context_ids.push(context_response["id"].as_str().unwrap().to_string());
-    }
+    // Wrong key, in either header
+    let response = client
+        .get(&contexts_url)
+        .bearer_auth("wrong-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let response = client
+        .get(&contexts_url)
+        .header("X-Api-Key", "wrong-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
 
-    // Test 1: Search by content - modified to handle empty results
+    // Correct key, in either header
     let response = client
-        .post(&format!("{}/search", base_url))
-        .json(&serde_json::json!({
-            "query": "neural networks transformers claude",
-            "limit": 10
-        }))
+        .get(&contexts_url)
+        .bearer_auth("test-api-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .get(&contexts_url)
+        .header("X-Api-Key", "test-api-key")
         .send()
         .await
         .unwrap();
+    assert_eq!(response.status(), 200);
 
+    // The health check stays open
+    let response = client
+        .get(&format!("http://{}/v1/health", server_addr))
+        .send()
+        .await
+        .unwrap();
     assert_eq!(response.status(), 200);
 
-    let search_response: serde_json::Value = response.json().await.unwrap();
-    let matches = search_response["matches"].as_array().unwrap();
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
 
-    // Add debug print to see what's coming back
-    println!("Search response: {:?}", search_response);
+/// A bearer token signed with `secret` for `audience`, expiring `expires_in` seconds from now
+fn signed_token(secret: &[u8], audience: &str, expires_in: i64, scope: &str) -> String {
+    let claims = serde_json::json!({
+        "sub": "alice",
+        "iss": "https://issuer.test",
+        "aud": audience,
+        "exp": Utc::now().timestamp() + expires_in,
+        "scope": scope,
+    });
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::HS256),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(secret),
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_bearer_tokens_are_verified() {
+    let secret = b"test-signing-key";
+    let auth = JwtAuth::new(
+        jsonwebtoken::DecodingKey::from_secret(secret),
+        jsonwebtoken::Algorithm::HS256,
+        "https://issuer.test",
+        "mcp",
+    )
+    .with_required_scope(Some("contexts".to_string()))
+    .with_leeway(0);
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            auth: Some(Authenticator::Jwt(auth)),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
+
+    let status = |token: Option<String>| {
+        let mut request = client.get(&contexts_url);
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        async move { request.send().await.unwrap().status() }
+    };
+
+    assert_eq!(status(None).await, 401);
+    assert_eq!(
+        status(Some(signed_token(secret, "mcp", 300, "contexts"))).await,
+        200
+    );
 
-    // No assertion on matches being non-empty - SimpleEmbeddingService may not work in tests
+    // Expired, for another audience, or signed with another key
+    assert_eq!(
+        status(Some(signed_token(secret, "mcp", -60, "contexts"))).await,
+        401
+    );
+    assert_eq!(
+        status(Some(signed_token(secret, "other", 300, "contexts"))).await,
+        401
+    );
+    assert_eq!(
+        status(Some(signed_token(b"another-key", "mcp", 300, "contexts"))).await,
+        401
+    );
 
-    // Test 2: Search with tag filtering - modified for test environment
+    // Valid but without the required scope
     let response = client
-        .post(&format!("{}/search", base_url))
-        .json(&serde_json::json!({
-            "query": "programming",
-            "tags": ["rust"],
-            "limit": 10
-        }))
+        .get(&contexts_url)
+        .bearer_auth(signed_token(secret, "mcp", 300, "other"))
         .send()
         .await
         .unwrap();
+    assert_eq!(response.status(), 403);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "FORBIDDEN");
 
-    assert_eq!(response.status(), 200);
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
 
-    let search_response: serde_json::Value = response.json().await.unwrap();
-    let matches = search_response["matches"].as_array().unwrap();
+#[tokio::test]
+async fn test_rate_limited_requests_get_429_until_the_bucket_refills() {
+    // Searches are limited far more tightly than reads
+    let rate_limits = RouteRateLimits {
+        reads: Arc::new(RateLimiter::new(100.0, 100)),
+        search: Arc::new(RateLimiter::new(5.0, 3)),
+        writes: Arc::new(RateLimiter::new(100.0, 100)),
+        failed_auth: Arc::new(RateLimiter::new(100.0, 100)),
+    };
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            rate_limits: Some(rate_limits),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let client = reqwest::Client::new();
+    let search_url = format!("http://{}/v1/search?q=anything", server_addr);
 
-    // Add debug print to see what's coming back
-    println!("Tag-filtered search response: {:?}", search_response);
+    for _ in 0..3 {
+        let response = client.get(&search_url).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+    }
 
-    // The SimpleEmbeddingService implementation might return empty results in tests
-    // For now, let's skip these assertions
-    /*
-    assert!(!matches.is_empty());
-    let matched_content = matches[0]["context"]["content"].as_str().unwrap();
-    assert!(matched_content.contains("Rust"));
-    */
+    let response = client.get(&search_url).send().await.unwrap();
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "RATE_LIMIT");
 
-    // Test 3: List contexts filtered by tags
+    // Reads have their own bucket
     let response = client
-        .get(&format!("{}/contexts?tags=ai,nlp&limit=10", base_url))
+        .get(&format!("http://{}/v1/contexts", server_addr))
         .send()
         .await
         .unwrap();
-
     assert_eq!(response.status(), 200);
 
-    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
-    println!("Tag-filtered list response: {:?}", list_response);
+    // A token comes back every 200ms
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let response = client.get(&search_url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
 
-    // There should be at least one context with both "ai" and "nlp" tags
-    assert!(!list_response.is_empty());
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
 
-    // Check each returned context has both required tags
-    for ctx in &list_response {
-        let tags = ctx["tags"].as_array().unwrap();
-        let has_ai = tags.iter().any(|tag| tag.as_str().unwrap() == "ai");
-        let has_nlp = tags.iter().any(|tag| tag.as_str().unwrap() == "nlp");
-        assert!(
-            has_ai && has_nlp,
-            "Context should have both 'ai' and 'nlp' tags"
-        );
-    }
+#[tokio::test]
+async fn test_failed_authentications_are_rate_limited() {
+    let rate_limits = RouteRateLimits {
+        reads: Arc::new(RateLimiter::new(100.0, 100)),
+        search: Arc::new(RateLimiter::new(100.0, 100)),
+        writes: Arc::new(RateLimiter::new(100.0, 100)),
+        failed_auth: Arc::new(RateLimiter::new(5.0, 2)),
+    };
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            auth: Some(Authenticator::ApiKey(ApiKeyAuth::new("test-api-key"))),
+            rate_limits: Some(rate_limits),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
 
-    // Check the specific context we expect
-    if !list_response.is_empty() {
-        let matched_content = list_response[0]["content"].as_str().unwrap();
-        assert!(matched_content.contains("Transformers"));
+    // Authenticated requests don't count against the failures
+    for _ in 0..3 {
+        let response = client
+            .get(&contexts_url)
+            .bearer_auth("test-api-key")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
     }
 
-    // Clean up - delete all created contexts
-    for id in context_ids {
+    for _ in 0..2 {
         let response = client
-            .delete(&format!("{}/contexts/{}", base_url, id))
+            .get(&contexts_url)
+            .bearer_auth("wrong-key")
             .send()
             .await
             .unwrap();
+        assert_eq!(response.status(), 401);
+    }
 
-        assert_eq!(response.status(), 204); // No Content
+    // Once its failures are used up the address is refused before its key is checked
+    let response = client
+        .get(&contexts_url)
+        .bearer_auth("wrong-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 429);
+    assert!(response.headers().contains_key("retry-after"));
+    let response = client
+        .get(&contexts_url)
+        .bearer_auth("test-api-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 429);
+
+    // A failure comes back every 200ms
+    tokio::time::sleep(Duration::from_millis(250)).await;
+    let response = client
+        .get(&contexts_url)
+        .bearer_auth("test-api-key")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_content_and_body_limits() {
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            max_body_bytes: Some(1024),
+            max_content_bytes: Some(100),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
+
+    // Content of exactly the limit is stored, a byte more is rejected
+    let response = client
+        .post(&contexts_url)
+        .json(&serde_json::json!({ "content": "x".repeat(100) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let response = client
+        .post(&contexts_url)
+        .json(&serde_json::json!({ "content": "x".repeat(101) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "VALIDATION_ERROR");
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("content exceeds 100 bytes"));
+
+    // A body of exactly the limit is read, a byte more is refused with the standard error body
+    let body = |size: usize| {
+        let json = r#"{"content": "short"}"#;
+        format!("{}{}", json, " ".repeat(size - json.len()))
+    };
+    let response = client
+        .post(&contexts_url)
+        .header("content-type", "application/json")
+        .body(body(1024))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let response = client
+        .post(&contexts_url)
+        .header("content-type", "application/json")
+        .body(body(1025))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "PAYLOAD_TOO_LARGE");
+    assert!(error["message"].as_str().unwrap().contains("1024 bytes"));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+/// Assert a response is a 400 `VALIDATION_ERROR` whose message starts with `field`
+async fn assert_rejected_field(response: reqwest::Response, field: &str) {
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "VALIDATION_ERROR");
+    let message = error["message"].as_str().unwrap();
+    assert!(message.starts_with(field), "{}", message);
+}
+
+#[tokio::test]
+async fn test_blank_content_is_rejected() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
+
+    for content in ["", "   \n\t "] {
+        let response = client
+            .post(&contexts_url)
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .unwrap();
+        assert_rejected_field(response, "content").await;
     }
 
+    // Updates can't blank out a context either
+    let stored: serde_json::Value = client
+        .post(&contexts_url)
+        .json(&serde_json::json!({ "content": "Kept content" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .put(&format!(
+            "{}/{}",
+            contexts_url,
+            stored["id"].as_str().unwrap()
+        ))
+        .json(&serde_json::json!({ "content": " " }))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "content").await;
+
+    // Nothing blank was listed
+    let listed: Vec<serde_json::Value> = client
+        .get(&contexts_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0]["content"], "Kept content");
+
     // Shutdown the server
     shutdown_tx.send(()).unwrap();
     let _ = server_handle.await;
 }
 
-// Additional helper test that focuses only on the tag filtering functionality
 #[tokio::test]
-async fn test_tag_filtering() {
-    // Start a test server
+async fn test_blank_and_excess_tags_are_rejected() {
     let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
-    let base_url = format!("http://{}", server_addr);
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
+    let max_tags = test_config().tags.max_per_context.unwrap();
 
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(5))
-        .build()
+    let response = client
+        .post(&contexts_url)
+        .json(&serde_json::json!({ "content": "Tagged", "tags": ["rust", " "] }))
+        .send()
+        .await
         .unwrap();
+    assert_rejected_field(response, "tags").await;
 
-    // Create contexts with specific tags for testing
-    let contexts = [
-        ("Context 1", vec!["tag1", "tag2"]),
-        ("Context 2", vec!["tag2", "tag3"]),
-        ("Context 3", vec!["tag1", "tag3"]),
-        ("Context 4", vec!["tag1", "tag2", "tag3"]),
-    ];
+    // The limit itself is fine, one more is not
+    let tags: Vec<String> = (0..=max_tags).map(|n| format!("tag-{}", n)).collect();
+    let response = client
+        .post(&contexts_url)
+        .json(&serde_json::json!({ "content": "Tagged", "tags": &tags[..max_tags] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let response = client
+        .post(&contexts_url)
+        .json(&serde_json::json!({ "content": "Tagged", "tags": tags }))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "tags").await;
 
-    // Store all contexts
-    for (content, tags) in &contexts {
-        let store_request = serde_json::json!({
-            "content": content,
-            "tags": tags,
-        });
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_every_problem_with_a_context_is_reported_at_once() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+
+    let too_many_tags: Vec<String> = (0..=test_config().tags.max_per_context)
+        .map(|i| format!("tag-{}", i))
+        .collect();
+    let response = client
+        .post(&format!("http://{}/v1/contexts", server_addr))
+        .json(&serde_json::json!({ "content": "  ", "tags": too_many_tags }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "VALIDATION_ERROR");
+    let fields: Vec<&str> = error["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|detail| detail["field"].as_str().unwrap())
+        .collect();
+    assert_eq!(fields, ["content", "tags"]);
+    assert!(error["details"][1]["message"]
+        .as_str()
+        .unwrap()
+        .starts_with("tags"));
+
+    // So are bad timestamps alongside bad tag filters when listing
+    let response = client
+        .get(&format!(
+            "http://{}/v1/contexts?tags=%20ok,&tag_mode=some&created_after=later",
+            server_addr
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["details"].as_array().unwrap().len(), 2);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_blank_search_query_is_rejected() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
 
+    for query in ["", "   ", r#""""#] {
         let response = client
-            .post(&format!("{}/contexts", base_url))
-            .json(&store_request)
+            .post(&format!("http://{}/v1/search", server_addr))
+            .json(&serde_json::json!({ "query": query }))
             .send()
             .await
             .unwrap();
-
-        assert_eq!(response.status(), 201);
+        assert_rejected_field(response, "query").await;
     }
 
-    // Test filtering by single tag
     let response = client
-        .get(&format!("{}/contexts?tags=tag1&limit=10", base_url))
+        .get(&format!("http://{}/v1/search", server_addr))
+        .query(&[("q", "  ")])
         .send()
         .await
         .unwrap();
+    assert_rejected_field(response, "query").await;
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_batch_delete() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
 
+    let mut ids = Vec::new();
+    for content in [
+        "First imported context",
+        "Second imported context",
+        "Kept context",
+    ] {
+        let stored: serde_json::Value = client
+            .post(&contexts_url)
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(stored["id"].as_str().unwrap().to_string());
+    }
+
+    let missing = Uuid::new_v4().to_string();
+    let response = client
+        .post(&format!("{}/delete", contexts_url))
+        .json(&serde_json::json!({ "ids": [ids[0], missing, ids[1]] }))
+        .send()
+        .await
+        .unwrap();
     assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["deleted"], 2);
+    assert_eq!(
+        body["results"],
+        serde_json::json!([
+            { "id": ids[0], "status": "deleted" },
+            { "id": missing, "status": "not_found" },
+            { "id": ids[1], "status": "deleted" },
+        ])
+    );
 
-    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
-    println!("Single tag filter response: {:?}", list_response);
-    assert_eq!(list_response.len(), 3); // Should return 3 contexts with tag1
+    // The deleted contexts are gone and can't be found by searching
+    let response = client
+        .get(&format!("{}/{}", contexts_url, ids[0]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let results: serde_json::Value = client
+        .post(&format!("http://{}/v1/search", server_addr))
+        .json(&serde_json::json!({ "query": "imported context" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let matches = results["matches"].as_array().unwrap();
+    assert!(matches.iter().all(|found| found["context"]["id"] == ids[2]));
 
-    // Test filtering by multiple tags
+    // Batches over the configured size are rejected whole
+    let too_many: Vec<String> = std::iter::repeat(ids[2].clone())
+        .take(test_config().context.max_delete_batch + 1)
+        .collect();
     let response = client
-        .get(&format!("{}/contexts?tags=tag1,tag3&limit=10", base_url))
+        .post(&format!("{}/delete", contexts_url))
+        .json(&serde_json::json!({ "ids": too_many }))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "ids").await;
+    let response = client
+        .get(&format!("{}/{}", contexts_url, ids[2]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_delete_by_tags() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let contexts_url = format!("http://{}/v1/contexts", server_addr);
+
+    for (content, tags) in [
+        ("Run 42 first result", vec!["run-42"]),
+        ("Run 42 second result", vec!["run-42", "gpu"]),
+        ("Run 43 result", vec!["run-43"]),
+        ("Untagged note", vec![]),
+    ] {
+        let response = client
+            .post(&contexts_url)
+            .json(&serde_json::json!({ "content": content, "tags": tags }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+    }
+
+    // Without confirm=true nothing is deleted
+    for query in ["tags=run-42", "tags=run-42&confirm=false"] {
+        let response = client
+            .delete(&format!("{}?{}", contexts_url, query))
+            .send()
+            .await
+            .unwrap();
+        assert_rejected_field(response, "confirm").await;
+    }
+    let listed: Vec<serde_json::Value> = client
+        .get(&contexts_url)
         .send()
         .await
+        .unwrap()
+        .json()
+        .await
         .unwrap();
+    assert_eq!(listed.len(), 4);
 
+    let response = client
+        .delete(&format!("{}?tags=run-42&confirm=true", contexts_url))
+        .send()
+        .await
+        .unwrap();
     assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["deleted"], 2);
 
-    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
-    println!("Multiple tag filter response: {:?}", list_response);
-    assert_eq!(list_response.len(), 2); // Should return contexts 3 and 4
+    // Contexts without the tag are untouched
+    let listed: Vec<serde_json::Value> = client
+        .get(&contexts_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let mut remaining: Vec<&str> = listed
+        .iter()
+        .map(|context| context["content"].as_str().unwrap())
+        .collect();
+    remaining.sort();
+    assert_eq!(remaining, ["Run 43 result", "Untagged note"]);
 
-    // Test filtering by all three tags
+    // A tag is required, so confirm=true alone can't delete everything
     let response = client
-        .get(&format!(
-            "{}/contexts?tags=tag1,tag2,tag3&limit=10",
-            base_url
-        ))
+        .delete(&format!("{}?confirm=true", contexts_url))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "tags").await;
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_get_chunk_by_id() {
+    let context_repository = create_repository(&test_config()).await.unwrap();
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server_on(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service,
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let stored: serde_json::Value = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Chunked content to look up" }))
         .send()
         .await
+        .unwrap()
+        .json()
+        .await
         .unwrap();
+    let context_id = Uuid::parse_str(stored["id"].as_str().unwrap()).unwrap();
+    let chunk = context_repository
+        .find_chunks_by_context_id(context_id)
+        .await
+        .unwrap()
+        .remove(0);
 
+    let response = client
+        .get(&format!("{}/chunks/{}", base_url, chunk.chunk_id))
+        .send()
+        .await
+        .unwrap();
     assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        body,
+        serde_json::json!({
+            "id": chunk.chunk_id,
+            "context_id": context_id,
+            "content": "Chunked content to look up",
+            "position": 0,
+        })
+    );
 
-    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
-    println!("All tags filter response: {:?}", list_response);
-    assert_eq!(list_response.len(), 1); // Should return only context 4
+    let response = client
+        .get(&format!("{}/chunks/{}", base_url, Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "CHUNK_NOT_FOUND");
+
+    // Updating regenerates the chunks, so the old ID no longer resolves
+    let response = client
+        .put(&format!("{}/contexts/{}", base_url, context_id))
+        .json(&serde_json::json!({ "content": "Rewritten content" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = client
+        .get(&format!("{}/chunks/{}", base_url, chunk.chunk_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    let regenerated = context_repository
+        .find_chunks_by_context_id(context_id)
+        .await
+        .unwrap()
+        .remove(0);
+    let body: serde_json::Value = client
+        .get(&format!("{}/chunks/{}", base_url, regenerated.chunk_id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(body["content"], "Rewritten content");
+
+    // The chunks a deleted context keeps for a restore can't be read
+    let response = client
+        .delete(&format!("{}/contexts/{}", base_url, context_id))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let response = client
+        .get(&format!("{}/chunks/{}", base_url, regenerated.chunk_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "CHUNK_NOT_FOUND");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_store_chunks_by_the_requested_strategy() {
+    let context_repository = create_repository(&test_config()).await.unwrap();
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server_on(
+        context_repository.clone(),
+        embedding_service.clone(),
+        embedding_service,
+    )
+    .await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    // Two paragraphs that fit in one 1000 byte chunk together only in part
+    let first = format!("{}\r\n\r\n", "Paragraph one. ".repeat(40).trim_end());
+    let second = "Paragraph two. ".repeat(40).trim_end().to_string();
+    let content = format!("{}{}", first, second);
+
+    let mut chunk_contents = Vec::new();
+    for chunking in [serde_json::Value::Null, serde_json::json!("paragraph")] {
+        let stored: serde_json::Value = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content, "chunking": chunking }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let context_id = Uuid::parse_str(stored["id"].as_str().unwrap()).unwrap();
+        let chunks = context_repository
+            .find_chunks_by_context_id(context_id)
+            .await
+            .unwrap();
+        chunk_contents.push(
+            chunks
+                .into_iter()
+                .map(|chunk| (chunk.position, chunk.content))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    // The configured fixed windows cut into the second paragraph
+    assert_eq!(chunk_contents[0][0], (0, content[..1000].to_string()));
+    assert_eq!(
+        chunk_contents[1],
+        vec![(0, first.clone()), (first.len(), second.clone())]
+    );
+
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": content, "chunking": "chapters" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_store_handles_duplicate_content_as_requested() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+    let content = "Quarterly planning notes";
+
+    let store = |on_duplicate: Option<&str>| {
+        let mut request = serde_json::json!({ "content": content });
+        if let Some(on_duplicate) = on_duplicate {
+            request["on_duplicate"] = serde_json::json!(on_duplicate);
+        }
+        client
+            .post(&format!("{}/contexts", base_url))
+            .json(&request)
+            .send()
+    };
+
+    // Duplicates are allowed unless the request says otherwise
+    let response = store(None).await.unwrap();
+    assert_eq!(response.status(), 201);
+    let original: serde_json::Value = response.json().await.unwrap();
+    let hash = original["content_hash"].as_str().unwrap().to_string();
+    assert_eq!(hash.len(), 64);
+
+    let response = store(Some("allow")).await.unwrap();
+    assert_eq!(response.status(), 201);
+    let allowed: serde_json::Value = response.json().await.unwrap();
+    assert_ne!(allowed["id"], original["id"]);
+    assert_eq!(allowed["content_hash"], hash.as_str());
+
+    // Skipping returns the first stored context with that content
+    let response = store(Some("skip")).await.unwrap();
+    let skipped: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(skipped["id"], original["id"]);
+
+    let response = store(Some("error")).await.unwrap();
+    assert_eq!(response.status(), 409);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "CONTEXT_EXISTS");
+
+    let response = store(Some("replace")).await.unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = client
+        .get(&format!("{}/contexts/count", base_url))
+        .send()
+        .await
+        .unwrap();
+    let count: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(count["count"], 2);
+
+    // Updating the content updates its hash
+    let response = client
+        .put(&format!(
+            "{}/contexts/{}",
+            base_url,
+            allowed["id"].as_str().unwrap()
+        ))
+        .json(&serde_json::json!({ "content": "Quarterly planning notes, revised" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_ne!(updated["content_hash"], hash.as_str());
+    assert_eq!(updated["content_hash"].as_str().unwrap().len(), 64);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_context_count_and_tag_counts() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    for (content, tags) in [
+        ("Transformers overview", vec!["ai", "nlp"]),
+        ("Diffusion models", vec!["ai", "vision"]),
+        ("Tokenizer notes", vec!["ai", "nlp"]),
+        ("Gardening tips", vec!["home"]),
+        ("Untagged note", vec![]),
+    ] {
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content, "tags": tags }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+    }
+
+    let count = |query: &'static str| {
+        let request = client.get(&format!("{}/contexts/count{}", base_url, query));
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            let body: serde_json::Value = response.json().await.unwrap();
+            body["count"].as_u64().unwrap()
+        }
+    };
+    assert_eq!(count("").await, 5);
+    assert_eq!(count("?tags=ai").await, 3);
+    assert_eq!(count("?tags=ai,nlp").await, 2);
+    assert_eq!(count("?tags=nlp,home&tag_mode=any").await, 3);
+    assert_eq!(count("?tags=ai&exclude_tags=vision").await, 2);
+    // Paging doesn't change the count
+    assert_eq!(count("?tags=ai&limit=1").await, 3);
+
+    // Most used tags first, ties in tag order
+    let response = client
+        .get(&format!("{}/tags", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let tags: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        tags,
+        serde_json::json!([
+            { "tag": "ai", "count": 3 },
+            { "tag": "nlp", "count": 2 },
+            { "tag": "home", "count": 1 },
+            { "tag": "vision", "count": 1 },
+        ])
+    );
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_tag_counts_leave_out_deleted_and_expired_contexts() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let store = |body: serde_json::Value| {
+        let request = client.post(format!("{}/contexts", base_url)).json(&body);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 201);
+            let context: serde_json::Value = response.json().await.unwrap();
+            context["id"].as_str().unwrap().to_string()
+        }
+    };
+    store(serde_json::json!({ "content": "Kept note", "tags": ["ai", "kept"] })).await;
+    let deleted =
+        store(serde_json::json!({ "content": "Deleted note", "tags": ["ai", "gone"] })).await;
+    store(serde_json::json!({
+        "content": "Expiring note",
+        "tags": ["ai", "ephemeral"],
+        "ttl_seconds": 1,
+    }))
+    .await;
+
+    let response = client
+        .delete(format!("{}/contexts/{}", base_url, deleted))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    // Only the live context is counted, and tags only hidden contexts carry are left out
+    let response = client
+        .get(&format!("{}/tags", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let tags: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        tags,
+        serde_json::json!([
+            { "tag": "ai", "count": 1 },
+            { "tag": "kept", "count": 1 },
+        ])
+    );
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_context_reads_honor_etags() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+
+    let stored: serde_json::Value = client
+        .post(&format!("http://{}/v1/contexts", server_addr))
+        .json(&serde_json::json!({ "content": "Polled content", "tags": ["poll"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let context_url = format!(
+        "http://{}/v1/contexts/{}",
+        server_addr,
+        stored["id"].as_str().unwrap()
+    );
+    let get = |if_none_match: Option<&str>| {
+        let mut request = client.get(&context_url);
+        if let Some(etag) = if_none_match {
+            request = request.header("If-None-Match", etag);
+        }
+        request.send()
+    };
+
+    let response = get(None).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    assert!(etag.starts_with('"') && etag.ends_with('"'));
+
+    // An unchanged context isn't sent again; weak and listed tags match too
+    for if_none_match in [
+        etag.clone(),
+        format!("W/{}", etag),
+        format!("\"other\", {}", etag),
+        "*".to_string(),
+    ] {
+        let response = get(Some(&if_none_match)).await.unwrap();
+        assert_eq!(response.status(), 304);
+        assert_eq!(response.headers()["etag"], etag.as_str());
+        assert!(response.bytes().await.unwrap().is_empty());
+    }
+    let response = get(Some("\"other\"")).await.unwrap();
+    assert_eq!(response.status(), 200);
+
+    // Updating the context changes its ETag
+    let response = client
+        .put(&context_url)
+        .json(&serde_json::json!({ "content": "Polled content", "tags": ["poll", "updated"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let response = get(Some(&etag)).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let new_etag = response.headers()["etag"].to_str().unwrap().to_string();
+    assert_ne!(new_etag, etag);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["tags"], serde_json::json!(["poll", "updated"]));
+    assert_eq!(get(Some(&new_etag)).await.unwrap().status(), 304);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_stale_if_match_is_a_version_conflict() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+
+    let stored: serde_json::Value = client
+        .post(&format!("http://{}/v1/contexts", server_addr))
+        .json(&serde_json::json!({ "content": "First draft" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stored["version"], 1);
+    let context_url = format!(
+        "http://{}/v1/contexts/{}",
+        server_addr,
+        stored["id"].as_str().unwrap()
+    );
+    let put = |content: &str, if_match: Option<&str>| {
+        let mut request = client
+            .put(&context_url)
+            .json(&serde_json::json!({ "content": content }));
+        if let Some(version) = if_match {
+            request = request.header("If-Match", version);
+        }
+        request.send()
+    };
+
+    // Two writers read version 1; the first update wins
+    let response = put("Second draft", Some("1")).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["version"], 2);
+
+    // The second one is rejected instead of overwriting it
+    let response = put("Lost update", Some("\"1\"")).await.unwrap();
+    assert_eq!(response.status(), 409);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "VERSION_CONFLICT");
+    let current: serde_json::Value = client
+        .get(&context_url)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(current["content"], "Second draft");
+    assert_eq!(current["version"], 2);
+
+    // A version that isn't a number is a bad request
+    let response = put("Third draft", Some("latest")).await.unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Without If-Match the update applies unconditionally
+    let response = put("Third draft", None).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["version"], 3);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_updates_can_be_listed_and_restored_as_revisions() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+
+    let stored: serde_json::Value = client
+        .post(&format!("http://{}/v1/contexts", server_addr))
+        .json(&serde_json::json!({ "content": "First draft", "tags": ["draft"] }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let context_url = format!(
+        "http://{}/v1/contexts/{}",
+        server_addr,
+        stored["id"].as_str().unwrap()
+    );
+    let response = client
+        .put(&context_url)
+        .json(&serde_json::json!({ "content": "Second draft", "tags": ["final"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // The update kept the state it replaced
+    let revisions: serde_json::Value = client
+        .get(&format!("{}/revisions", context_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let revisions = revisions.as_array().unwrap();
+    assert_eq!(revisions.len(), 1);
+    assert_eq!(revisions[0]["version"], 1);
+    assert_eq!(revisions[0]["content"], "First draft");
+    assert!(revisions[0]["archived_at"].is_string());
+
+    let revision: serde_json::Value = client
+        .get(&format!("{}/revisions/1", context_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(revision["tags"], serde_json::json!(["draft"]));
+
+    // Restoring brings the old content and tags back as a new version
+    let response = client
+        .post(&format!("{}/revisions/1/restore", context_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let restored: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(restored["content"], "First draft");
+    assert_eq!(restored["tags"], serde_json::json!(["draft"]));
+    assert_eq!(restored["version"], 3);
+
+    // ...and keeps the state it replaced
+    let revisions: serde_json::Value = client
+        .get(&format!("{}/revisions", context_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let versions: Vec<&serde_json::Value> = revisions
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|revision| &revision["version"])
+        .collect();
+    assert_eq!(versions, vec![1, 2]);
+    assert_eq!(revisions[1]["content"], "Second draft");
+
+    // Versions that were never kept are not found
+    let response = client
+        .get(&format!("{}/revisions/7", context_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "REVISION_NOT_FOUND");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_deleted_contexts_can_be_restored_until_hard_deleted() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let stored: serde_json::Value = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Rotate the signing keys every quarter" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let context_url = format!("{}/contexts/{}", base_url, stored["id"].as_str().unwrap());
+    let search_hits = || async {
+        let response: serde_json::Value = client
+            .post(&format!("{}/search", base_url))
+            .json(&serde_json::json!({ "query": "signing keys" }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        response["matches"].as_array().unwrap().len()
+    };
+
+    // Deleting hides the context from reads and searches
+    let response = client.delete(&context_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client.get(&context_url).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(search_hits().await, 0);
+
+    // Restoring brings it back as it was
+    let response = client
+        .post(&format!("{}/restore", context_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let restored: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(restored["content"], "Rotate the signing keys every quarter");
+    let response = client.get(&context_url).send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(search_hits().await, 1);
+
+    // A soft-deleted context can still be deleted for good, after which it's gone
+    let response = client.delete(&context_url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client
+        .delete(&format!("{}?hard=true", context_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client
+        .post(&format!("{}/restore", context_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "CONTEXT_NOT_FOUND");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_relations_can_be_added_followed_and_removed() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let mut ids = Vec::new();
+    for content in ["Standup, Monday", "Standup, Tuesday"] {
+        let context: serde_json::Value = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(context["id"].as_str().unwrap().to_string());
+    }
+    let relate = |from: &str, to: &str, relation_type: &str| {
+        client
+            .post(format!("{}/contexts/{}/relations", base_url, from))
+            .json(&serde_json::json!({ "target_id": to, "relation_type": relation_type }))
+            .send()
+    };
+
+    // Both notes belong to the same thread, which makes a cycle
+    let response = relate(&ids[0], &ids[1], "same_thread").await.unwrap();
+    assert_eq!(response.status(), 201);
+    let relation: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(relation["target_id"], ids[1].as_str());
+    let response = relate(&ids[1], &ids[0], "same_thread").await.unwrap();
+    assert_eq!(response.status(), 201);
+    let response = relate(&ids[0], &ids[1], "follows_up").await.unwrap();
+    assert_eq!(response.status(), 201);
+
+    let related: serde_json::Value = client
+        .get(format!("{}/contexts/{}/related", base_url, ids[0]))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let types: Vec<&str> = related
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|related| related["relation_type"].as_str().unwrap())
+        .collect();
+    assert_eq!(types, vec!["same_thread", "follows_up"]);
+    assert_eq!(related[0]["context"]["content"], "Standup, Tuesday");
+    let related: serde_json::Value = client
+        .get(format!(
+            "{}/contexts/{}/related?relation_type=follows_up",
+            base_url, ids[0]
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(related.as_array().unwrap().len(), 1);
+
+    // Removing one type of relation leaves the other
+    let relation_url = format!("{}/contexts/{}/relations/{}", base_url, ids[0], ids[1]);
+    let response = client
+        .delete(format!("{}?relation_type=follows_up", relation_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client
+        .delete(format!("{}?relation_type=follows_up", relation_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "RELATION_NOT_FOUND");
+
+    // Relations to a missing context are rejected
+    let response = relate(&ids[0], &Uuid::new_v4().to_string(), "same_thread")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Deleting a context for good removes the relations to it
+    let response = client
+        .delete(format!("{}/contexts/{}?hard=true", base_url, ids[1]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client.delete(&relation_url).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_children_can_be_paged_and_outlive_their_parent() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+    let store = |content: &str, parent_id: Option<&str>| {
+        client
+            .post(format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content, "parent_id": parent_id }))
+            .send()
+    };
+    let children = |parent_id: &str, query: &str| {
+        let url = format!("{}/contexts/{}/children?{}", base_url, parent_id, query);
+        let client = client.clone();
+        async move {
+            let children: serde_json::Value =
+                client.get(url).send().await.unwrap().json().await.unwrap();
+            children
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|child| child["content"].as_str().unwrap().to_string())
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // The parent must exist
+    let response = store("Orphaned section", Some(&Uuid::new_v4().to_string()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "INVALID_REFERENCE");
+
+    let manual: serde_json::Value = store("Operations manual", None)
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let manual_id = manual["id"].as_str().unwrap().to_string();
+    let mut chapter_ids = Vec::new();
+    for chapter in ["Deploying", "Monitoring", "Recovering from a failover"] {
+        let response = store(chapter, Some(&manual_id)).await.unwrap();
+        assert_eq!(response.status(), 201);
+        let chapter: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(chapter["parent_id"], manual_id.as_str());
+        chapter_ids.push(chapter["id"].as_str().unwrap().to_string());
+    }
+    let response = store("Promoting the replica", Some(&chapter_ids[2]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    // Children page like a listing, and descendants follow them
+    let all = children(&manual_id, "").await;
+    assert_eq!(all.len(), 3);
+    assert_eq!(children(&manual_id, "limit=2").await, all[..2]);
+    assert_eq!(children(&manual_id, "limit=2&offset=2").await, all[2..]);
+    let descendants = children(&manual_id, "include_descendants=true").await;
+    assert_eq!(descendants[..3], all[..]);
+    assert_eq!(descendants[3..], ["Promoting the replica"]);
+
+    // Search matches name their parent, however compact
+    let response: serde_json::Value = client
+        .post(format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "promoting the replica",
+            "response_mode": "ids_only",
+        }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let promoting = response["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|m| m["parent_id"] == chapter_ids[2].as_str());
+    assert!(promoting.is_some());
+
+    // Deleting the parent for good leaves its children without one
+    let response = client
+        .delete(format!("{}/contexts/{}?hard=true", base_url, manual_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    let chapter: serde_json::Value = client
+        .get(format!("{}/contexts/{}", base_url, chapter_ids[0]))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert!(chapter["parent_id"].is_null());
+    let response = client
+        .get(format!("{}/contexts/{}/children", base_url, manual_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_contexts_never_leak_into_another_collection() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+    let create = |name: &str| {
+        client
+            .post(format!("{}/collections", base_url))
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+    };
+    let store = |content: &str, collection_id: Option<&str>| {
+        client
+            .post(format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content, "collection_id": collection_id }))
+            .send()
+    };
+    let contents = |matches: &serde_json::Value| {
+        matches
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| {
+                m.get("context").unwrap_or(m)["content"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let response = create("billing").await.unwrap();
+    assert_eq!(response.status(), 201);
+    let billing: serde_json::Value = response.json().await.unwrap();
+    let billing_id = billing["id"].as_str().unwrap().to_string();
+    let support: serde_json::Value = create("support").await.unwrap().json().await.unwrap();
+    let support_id = support["id"].as_str().unwrap().to_string();
+
+    // Names are unique
+    let response = create("billing").await.unwrap();
+    assert_eq!(response.status(), 409);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "COLLECTION_EXISTS");
+
+    // Contexts stored without a collection are in the default one, as before
+    let response = store("Refund policy for the legacy plan", None)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let legacy: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(legacy["collection_id"], Uuid::nil().to_string());
+    for (content, collection_id) in [
+        ("Refund policy for annual invoices", &billing_id),
+        ("Refund policy for support tickets", &support_id),
+    ] {
+        let context: serde_json::Value = store(content, Some(collection_id))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(context["collection_id"], collection_id.as_str());
+    }
+    let response = store(
+        "Refund policy nobody owns",
+        Some(&Uuid::new_v4().to_string()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Listings, counts and both kinds of search only see the collection they are scoped to
+    for (collection, expected) in [
+        ("billing", "Refund policy for annual invoices"),
+        (support_id.as_str(), "Refund policy for support tickets"),
+        ("default", "Refund policy for the legacy plan"),
+    ] {
+        let listed: serde_json::Value = client
+            .get(format!("{}/contexts?collection={}", base_url, collection))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(contents(&listed), [expected]);
+
+        let count: serde_json::Value = client
+            .get(format!(
+                "{}/contexts/count?collection={}",
+                base_url, collection
+            ))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(count["count"], 1);
+
+        let searched: serde_json::Value = client
+            .post(format!("{}/search", base_url))
+            .json(&serde_json::json!({ "query": "refund policy", "collection": collection }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(contents(&searched["matches"]), [expected]);
+
+        let searched: serde_json::Value = client
+            .get(format!("{}/search", base_url))
+            .query(&[("q", "refund policy"), ("collection", collection)])
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(contents(&searched["matches"]), [expected]);
+    }
+
+    // Unscoped requests still see every collection
+    let count: serde_json::Value = client
+        .get(format!("{}/contexts/count", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(count["count"], 3);
+
+    // Unknown collections are refused rather than matching nothing
+    let response = client
+        .get(format!("{}/contexts?collection=marketing", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let response = client
+        .get(format!(
+            "{}/contexts?collection={}",
+            base_url,
+            Uuid::new_v4()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "COLLECTION_NOT_FOUND");
+
+    // Collections are listed by name after the default one, and can be renamed
+    let collections: serde_json::Value = client
+        .get(format!("{}/collections", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let names: Vec<&str> = collections
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|collection| collection["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["default", "billing", "support"]);
+    let response = client
+        .put(format!("{}/collections/{}", base_url, support_id))
+        .json(&serde_json::json!({ "name": "helpdesk" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let renamed: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(renamed["name"], "helpdesk");
+
+    // A collection holding contexts can't be deleted, nor can the default one
+    let response = client
+        .delete(format!("{}/collections/{}", base_url, billing_id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 409);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "COLLECTION_NOT_EMPTY");
+    let response = client
+        .delete(format!("{}/collections/{}", base_url, Uuid::nil()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let empty: serde_json::Value = create("archive").await.unwrap().json().await.unwrap();
+    let url = format!("{}/collections/{}", base_url, empty["id"].as_str().unwrap());
+    let response = client.delete(&url).send().await.unwrap();
+    assert_eq!(response.status(), 204);
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 404);
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_tls_round_trip_with_a_self_signed_certificate() {
+    let dir = std::env::temp_dir().join(format!("mcp-test-tls-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let cert_pem = generated.cert.pem();
+    let cert_path = dir.join("cert.pem");
+    let key_path = dir.join("key.pem");
+    std::fs::write(&cert_path, &cert_pem).unwrap();
+    std::fs::write(&key_path, generated.key_pair.serialize_pem()).unwrap();
+
+    let tls = tls_from_config(&TlsConfig {
+        cert_path: Some(cert_path.to_string_lossy().into_owned()),
+        key_path: Some(key_path.to_string_lossy().into_owned()),
+    })
+    .unwrap();
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            tls,
+            ..TestServerOptions::default()
+        })
+        .await;
+    let base_url = format!("https://localhost:{}/v1", server_addr.port());
+
+    // A client that doesn't trust the certificate can't connect
+    assert!(reqwest::get(format!("{}/health", base_url)).await.is_err());
+
+    // Trusting it, as `--ca-cert` does, a context makes the round trip
+    let client = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(cert_pem.as_bytes()).unwrap())
+        .build()
+        .unwrap();
+    let stored: serde_json::Value = client
+        .post(format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Sent over TLS" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let fetched: serde_json::Value = client
+        .get(format!(
+            "{}/contexts/{}",
+            base_url,
+            stored["id"].as_str().unwrap()
+        ))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(fetched["content"], "Sent over TLS");
+
+    // Plain HTTP isn't answered
+    let plain = reqwest::get(format!("http://{}/v1/health", server_addr)).await;
+    assert!(plain.map_or(true, |response| !response.status().is_success()));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_request_ids_correlate_headers_and_errors() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let missing_url = format!("http://{}/v1/contexts/{}", server_addr, Uuid::new_v4());
+
+    // A request id the client sends comes back in the header and the error body
+    let response = client
+        .get(&missing_url)
+        .header("X-Request-Id", "trace-1234")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    assert_eq!(response.headers()["x-request-id"], "trace-1234");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "CONTEXT_NOT_FOUND");
+    assert_eq!(body["request_id"], "trace-1234");
+
+    // Without one, or with one unfit to echo, the server makes one up
+    for sent in [None, Some("not ok")] {
+        let mut request = client.get(&missing_url);
+        if let Some(id) = sent {
+            request = request.header("X-Request-Id", id);
+        }
+        let response = request.send().await.unwrap();
+        let header = response.headers()["x-request-id"]
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(Uuid::parse_str(&header).is_ok());
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["request_id"], header.as_str());
+    }
+
+    // Successful responses carry the header too
+    let response = client
+        .get(&format!("http://{}/v1/health", server_addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().contains_key("x-request-id"));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_expired_contexts_are_gone_everywhere() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let store = |body: serde_json::Value| {
+        client
+            .post(format!("{}/contexts", base_url))
+            .json(&body)
+            .send()
+    };
+    let expiring: serde_json::Value = store(serde_json::json!({
+        "content": "Ephemeral retention note",
+        "tags": ["ttl"],
+        "ttl_seconds": 1,
+    }))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    assert!(expiring["expires_at"].is_string());
+    let lasting: serde_json::Value = store(serde_json::json!({
+        "content": "Permanent retention note",
+        "tags": ["ttl"],
+    }))
+    .await
+    .unwrap()
+    .json()
+    .await
+    .unwrap();
+    let expiring_url = format!("{}/contexts/{}", base_url, expiring["id"].as_str().unwrap());
+    assert_eq!(
+        client.get(&expiring_url).send().await.unwrap().status(),
+        200
+    );
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    // Reads and updates no longer find it
+    assert_eq!(
+        client.get(&expiring_url).send().await.unwrap().status(),
+        404
+    );
+    let response = client
+        .put(&expiring_url)
+        .json(&serde_json::json!({ "content": "Revived" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    // Listings, counts and searches leave it out
+    let listed: Vec<serde_json::Value> = client
+        .get(format!("{}/contexts?tags=ttl", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let listed: Vec<&str> = listed
+        .iter()
+        .map(|context| context["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(listed, [lasting["id"].as_str().unwrap()]);
+    let count: serde_json::Value = client
+        .get(format!("{}/contexts/count?tags=ttl", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(count["count"], 1);
+    let search: serde_json::Value = client
+        .post(format!("{}/search", base_url))
+        .json(&serde_json::json!({ "query": "retention note", "limit": 10 }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let found: Vec<&str> = search["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["context"]["id"].as_str().unwrap())
+        .collect();
+    assert!(found.contains(&lasting["id"].as_str().unwrap()));
+    assert!(!found.contains(&expiring["id"].as_str().unwrap()));
+
+    // An expiry must be in the future, and given one way only
+    for (body, field) in [
+        (
+            serde_json::json!({ "content": "x", "expires_at": "2020-01-01T00:00:00Z" }),
+            "expires_at",
+        ),
+        (
+            serde_json::json!({ "content": "x", "ttl_seconds": 0 }),
+            "ttl_seconds",
+        ),
+        (
+            serde_json::json!({
+                "content": "x",
+                "ttl_seconds": 60,
+                "expires_at": "2999-01-01T00:00:00Z",
+            }),
+            "expires_at",
+        ),
+    ] {
+        assert_rejected_field(store(body).await.unwrap(), field).await;
+    }
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_export_streams_every_context_as_ndjson() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let mut stored = Vec::new();
+    for (content, tags) in [
+        ("Backup of the billing runbook", vec!["runbook"]),
+        ("Backup of the paging policy", vec!["policy", "oncall"]),
+        ("Backup of the release checklist", vec![]),
+    ] {
+        let context: serde_json::Value = client
+            .post(format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content, "tags": tags }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        stored.push(context["id"].as_str().unwrap().to_string());
+    }
+
+    let export = |include_chunks: bool| {
+        client
+            .get(format!("{}/export", base_url))
+            .query(&[("include_chunks", include_chunks.to_string())])
+            .send()
+    };
+    let response = export(false).await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+    let body = response.text().await.unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines.len(), 3);
+
+    // Every line is a record on its own, and reads back as the same JSON
+    let mut exported = Vec::new();
+    for line in &lines {
+        let record: ExportRecord = serde_json::from_str(line).unwrap();
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::from_str::<serde_json::Value>(line).unwrap()
+        );
+        match record {
+            ExportRecord::Context(context) => exported.push(context.id.to_string()),
+            ExportRecord::Chunk(_) => panic!("chunks were not asked for"),
+        }
+    }
+    exported.sort();
+    stored.sort();
+    assert_eq!(exported, stored);
+
+    // Each short context is one chunk, exported with its embedding
+    let body = export(true).await.unwrap().text().await.unwrap();
+    let records: Vec<ExportRecord> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 6);
+    let chunks: Vec<_> = records
+        .iter()
+        .filter_map(|record| match record {
+            ExportRecord::Chunk(chunk) => Some(chunk),
+            ExportRecord::Context(_) => None,
+        })
+        .collect();
+    assert_eq!(chunks.len(), 3);
+    for chunk in chunks {
+        assert!(stored.contains(&chunk.context_id.to_string()));
+        assert!(chunk.embedding.as_ref().is_some_and(|e| !e.is_empty()));
+    }
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_events_stream_context_changes_in_order() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let mut events = client
+        .get(format!("{}/events", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(events.status(), 200);
+    assert!(events.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/event-stream"));
+
+    let context: serde_json::Value = client
+        .post(format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Watched context" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = context["id"].as_str().unwrap();
+    let response = client
+        .delete(format!("{}/contexts/{}", base_url, id))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+
+    // Events are separated by blank lines
+    let mut received = String::new();
+    while received.matches("\n\n").count() < 2 {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), events.chunk())
+            .await
+            .expect("no event within 5 seconds")
+            .unwrap()
+            .expect("the stream ended");
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    let received: Vec<(&str, serde_json::Value)> = received
+        .split_terminator("\n\n")
+        .map(|event| {
+            let field = |name: &str| {
+                event
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .unwrap()
+            };
+            (
+                field("event: "),
+                serde_json::from_str(field("data: ")).unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(received[0].0, "context.created");
+    assert_eq!(received[1].0, "context.deleted");
+    for (name, data) in &received {
+        assert_eq!(data["type"], *name);
+        assert_eq!(data["context_id"], id);
+        assert!(data["timestamp"].is_string());
+    }
+
+    // The stream stays open until the client leaves, which graceful shutdown waits for
+    drop(events);
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+/// The name and data of the next server-sent event on `stream`, keeping what's read past it
+/// in `received`
+async fn next_sse_event(stream: &mut reqwest::Response, received: &mut String) -> (String, String) {
+    while !received.contains("\n\n") {
+        let chunk = tokio::time::timeout(Duration::from_secs(5), stream.chunk())
+            .await
+            .expect("no event within 5 seconds")
+            .unwrap()
+            .expect("the stream ended");
+        received.push_str(std::str::from_utf8(&chunk).unwrap());
+    }
+    let end = received.find("\n\n").unwrap();
+    let event: String = received.drain(..end + 2).collect();
+    let field = |name: &str| {
+        event
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .unwrap_or_default()
+            .to_string()
+    };
+    (field("event: "), field("data: "))
+}
+
+#[tokio::test]
+async fn test_mcp_sessions_over_server_sent_events() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let origin = format!("http://{}", server_addr);
+
+    // The first event says where to post the session's messages
+    let mut stream = client
+        .get(format!("{}/v1/mcp/sse", origin))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(stream.status(), 200);
+    let mut received = String::new();
+    let (name, endpoint) = next_sse_event(&mut stream, &mut received).await;
+    assert_eq!(name, "endpoint");
+    assert!(endpoint.starts_with("/v1/mcp/messages?sessionId="));
+    let messages = format!("{}{}", origin, endpoint);
+
+    // Each message is accepted, and answered on the stream
+    let post = |message: serde_json::Value| client.post(&messages).json(&message).send();
+    let response = post(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": { "protocolVersion": "2024-11-05", "capabilities": {} }
+    }))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 202);
+    let (name, data) = next_sse_event(&mut stream, &mut received).await;
+    assert_eq!(name, "message");
+    let initialized: serde_json::Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(initialized["id"], 1);
+    assert_eq!(initialized["result"]["serverInfo"]["name"], "mcp");
+
+    // Notifications get no response
+    let response = post(serde_json::json!({
+        "jsonrpc": "2.0",
+        "method": "notifications/initialized"
+    }))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 202);
+
+    let response = post(serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "store_context",
+            "arguments": { "content": "Stored over MCP", "tags": ["mcp"] }
+        }
+    }))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 202);
+    let (_, data) = next_sse_event(&mut stream, &mut received).await;
+    let stored: serde_json::Value = serde_json::from_str(&data).unwrap();
+    assert_eq!(stored["id"], 2);
+    assert_eq!(stored["result"]["isError"], false);
+
+    // The context is there for the REST API too
+    let count: serde_json::Value = client
+        .get(format!("{}/v1/contexts/count?tags=mcp", origin))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(count["count"], 1);
+
+    // Closing the stream ends the session
+    drop(stream);
+    let mut status = 202;
+    for _ in 0..50 {
+        status = post(serde_json::json!({ "jsonrpc": "2.0", "id": 3, "method": "ping" }))
+            .await
+            .unwrap()
+            .status()
+            .as_u16();
+        if status == 404 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(status, 404);
+    let response = client
+        .post(format!(
+            "{}/v1/mcp/messages?sessionId={}",
+            origin,
+            Uuid::new_v4()
+        ))
+        .json(&serde_json::json!({ "jsonrpc": "2.0", "id": 4, "method": "ping" }))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body["code"], "SESSION_NOT_FOUND");
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+/// Write `message` as a line of an MCP stdio stream
+async fn send_mcp_line(writer: &mut WriteHalf<DuplexStream>, message: serde_json::Value) {
+    let mut line = message.to_string();
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await.unwrap();
+}
+
+/// The next message read from an MCP stdio stream
+async fn next_mcp_line(lines: &mut Lines<BufReader<ReadHalf<DuplexStream>>>) -> serde_json::Value {
+    let line = tokio::time::timeout(Duration::from_secs(5), lines.next_line())
+        .await
+        .expect("no message within 5 seconds")
+        .unwrap()
+        .expect("the stream ended");
+    serde_json::from_str(&line).unwrap()
+}
+
+#[tokio::test]
+async fn test_mcp_subscriptions_are_notified_of_changes_made_through_the_api() {
+    let (client, transport) = tokio::io::duplex(64 * 1024);
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            mcp_stdio: Some(transport),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let http = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+    let (reader, mut writer) = tokio::io::split(client);
+    let mut lines = BufReader::new(reader).lines();
+
+    let context: serde_json::Value = http
+        .post(format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Watched over MCP" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let id = context["id"].as_str().unwrap().to_string();
+    let uri = format!("context://{}", id);
+
+    send_mcp_line(
+        &mut writer,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {} }
+        }),
+    )
+    .await;
+    let initialized = next_mcp_line(&mut lines).await;
+    assert_eq!(
+        initialized["result"]["capabilities"]["resources"]["subscribe"],
+        true
+    );
+    send_mcp_line(
+        &mut writer,
+        serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+    )
+    .await;
+    send_mcp_line(
+        &mut writer,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "resources/subscribe",
+            "params": { "uri": uri }
+        }),
+    )
+    .await;
+    let subscribed = next_mcp_line(&mut lines).await;
+    assert_eq!(subscribed["id"], 2);
+    assert_eq!(subscribed["result"], serde_json::json!({}));
+
+    // Updating the context through the API updates the subscribed resource
+    let response = http
+        .put(format!("{}/contexts/{}", base_url, id))
+        .json(&serde_json::json!({ "content": "Changed through the API" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let updated = next_mcp_line(&mut lines).await;
+    assert_eq!(updated["method"], "notifications/resources/updated");
+    assert_eq!(updated["params"]["uri"], uri.as_str());
+    assert!(updated.get("id").is_none());
+
+    // Other contexts only change the list
+    let response = http
+        .post(format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Not watched" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let changed = next_mcp_line(&mut lines).await;
+    assert_eq!(changed["method"], "notifications/resources/list_changed");
+
+    // Deleting the subscribed context updates it one last time
+    let response = http
+        .delete(format!("{}/contexts/{}", base_url, id))
+        .send()
+        .await
+        .unwrap();
+    assert!(response.status().is_success());
+    let updated = next_mcp_line(&mut lines).await;
+    assert_eq!(updated["method"], "notifications/resources/updated");
+    let changed = next_mcp_line(&mut lines).await;
+    assert_eq!(changed["method"], "notifications/resources/list_changed");
+
+    drop(writer);
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+type TestSocket =
+    tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// The next frame a test socket receives
+async fn next_ws_message(socket: &mut TestSocket) -> WsMessage {
+    tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("no frame within 5 seconds")
+        .expect("the socket closed")
+        .unwrap()
+}
+
+/// The next text frame a test socket receives, as JSON
+async fn next_ws_json(socket: &mut TestSocket) -> serde_json::Value {
+    loop {
+        match next_ws_message(socket).await {
+            WsMessage::Text(text) => return serde_json::from_str(&text).unwrap(),
+            WsMessage::Ping(_) | WsMessage::Pong(_) => {}
+            other => panic!("expected a text frame, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_websocket_subscriptions_filter_changes() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+    let ws_url = format!("ws://{}/v1/ws", server_addr);
+
+    let (mut socket, _) = tokio_tungstenite::connect_async(&ws_url).await.unwrap();
+    let subscription =
+        serde_json::json!({ "tags": ["project-x"], "events": ["created", "deleted"] });
+    socket
+        .send(WsMessage::Text(subscription.to_string()))
+        .await
+        .unwrap();
+    let ack = next_ws_json(&mut socket).await;
+    assert_eq!(
+        ack,
+        serde_json::json!({ "type": "subscribed", "tags": ["project-x"] })
+    );
+
+    let store = |content: &str, tag: &str| {
+        client
+            .post(format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content, "tags": [tag] }))
+            .send()
+    };
+    let watched: serde_json::Value = store("Project X notes", "project-x")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let other: serde_json::Value = store("Project Y notes", "project-y")
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let watched_url = format!("{}/contexts/{}", base_url, watched["id"].as_str().unwrap());
+    let other_url = format!("{}/contexts/{}", base_url, other["id"].as_str().unwrap());
+
+    // Updates weren't asked for, and the other context lacks the tag
+    client
+        .put(&watched_url)
+        .json(&serde_json::json!({ "content": "Project X notes, revised", "tags": ["project-x"] }))
+        .send()
+        .await
+        .unwrap();
+    client.delete(&other_url).send().await.unwrap();
+    client.delete(&watched_url).send().await.unwrap();
+
+    let created = next_ws_json(&mut socket).await;
+    assert_eq!(created["type"], "context.created");
+    assert_eq!(created["context_id"], watched["id"]);
+    let deleted = next_ws_json(&mut socket).await;
+    assert_eq!(deleted["type"], "context.deleted");
+    assert_eq!(deleted["context_id"], watched["id"]);
+    assert_eq!(deleted["tags"], serde_json::json!(["project-x"]));
+
+    // Closing the socket ends the subscription, with the server answering the close
+    socket.close(None).await.unwrap();
+    assert!(matches!(
+        next_ws_message(&mut socket).await,
+        WsMessage::Close(_)
+    ));
+    assert!(socket.next().await.is_none());
+    assert_eq!(
+        store("Project X later", "project-x")
+            .await
+            .unwrap()
+            .status(),
+        201
+    );
+
+    // An invalid subscription gets an error and a policy close
+    let (mut socket, _) = tokio_tungstenite::connect_async(&ws_url).await.unwrap();
+    socket
+        .send(WsMessage::Text(r#"{"events": ["exploded"]}"#.to_string()))
+        .await
+        .unwrap();
+    let error = next_ws_json(&mut socket).await;
+    assert_eq!(error["code"], "VALIDATION_ERROR");
+    match next_ws_message(&mut socket).await {
+        WsMessage::Close(Some(frame)) => assert_eq!(frame.code, CloseCode::Policy),
+        other => panic!("expected a close frame, got {:?}", other),
+    }
+    drop(socket);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_import_restores_an_export() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let mut ids = Vec::new();
+    for (content, tags) in [
+        ("Restore drill for the ledger database", vec!["drill"]),
+        (
+            "Restore drill for the search cluster",
+            vec!["drill", "search"],
+        ),
+    ] {
+        let context: serde_json::Value = client
+            .post(format!("{}/contexts", base_url))
+            .json(&serde_json::json!({
+                "content": content,
+                "tags": tags,
+                "metadata": { "owner": "sre" },
+                "ttl_seconds": 3600,
+            }))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        ids.push(context["id"].as_str().unwrap().to_string());
+    }
+
+    let export = || async {
+        let body = client
+            .get(format!("{}/export?include_chunks=true", base_url))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        let mut lines: Vec<String> = body.lines().map(str::to_string).collect();
+        lines.sort();
+        lines
+    };
+    let import = |on_conflict: &str, body: String| {
+        client
+            .post(format!("{}/import?on_conflict={}", base_url, on_conflict))
+            .header("content-type", "application/x-ndjson")
+            .body(body)
+            .send()
+    };
+    let before = export().await;
+    assert_eq!(before.len(), 4);
+
+    // Wipe everything, then import the export with its chunks ahead of their contexts
+    let response = client
+        .post(format!("{}/contexts/delete", base_url))
+        .json(&serde_json::json!({ "ids": ids }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(export().await.is_empty());
+
+    let mut dump = before.clone();
+    dump.sort_by_key(|line| !line.contains(r#""type":"chunk""#));
+    let response = import("error", dump.join("\n")).await.unwrap();
+    assert_eq!(response.status(), 200);
+    let counts: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(counts, serde_json::json!({ "imported": 2, "skipped": 0 }));
+    assert_eq!(export().await, before);
+
+    // The imported embeddings are searchable right away
+    let search: serde_json::Value = client
+        .get(format!("{}/search?q=ledger", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(search["matches"][0]["context"]["id"], ids[0].as_str());
+
+    // Importing the same ids again follows on_conflict
+    let dump = before.join("\n");
+    let response = import("error", dump.clone()).await.unwrap();
+    assert_eq!(response.status(), 409);
+    let counts: serde_json::Value = import("skip", dump.clone())
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(counts, serde_json::json!({ "imported": 0, "skipped": 2 }));
+    let counts: serde_json::Value = import("overwrite", dump)
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(counts, serde_json::json!({ "imported": 2, "skipped": 0 }));
+    assert_eq!(export().await, before);
+
+    // A malformed line fails the whole import, naming the line
+    let response = import("overwrite", format!("{}\nnot json", before[0]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert!(error["message"].as_str().unwrap().starts_with("Line 2:"));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_client_error_handling() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    // Create HTTP client
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Test 1: Get a non-existent context
+    let non_existent_id = Uuid::new_v4().to_string();
+    let response = client
+        .get(&format!("{}/contexts/{}", base_url, non_existent_id))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 404); // Not Found
+
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "CONTEXT_NOT_FOUND");
+
+    // Test 2: Invalid search request (missing query)
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            // Missing required "query" field
+            "limit": 5
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert!(response.status().is_client_error());
+
+    // Test 3: Update a non-existent context
+    let non_existent_id = Uuid::new_v4().to_string();
+    let update_request = serde_json::json!({
+        "content": "This won't work",
+        "tags": ["test"],
+    });
+
+    let response = client
+        .put(&format!("{}/contexts/{}", base_url, non_existent_id))
+        .json(&update_request)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 404); // Not Found
+
+    // Test 4: An id that isn't a UUID gets the standard error body
+    let response = client
+        .get(&format!("{}/contexts/not-a-uuid", base_url))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "INVALID_ID");
+    assert!(error_response["message"].is_string());
+
+    // Test 5: A body that isn't JSON
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .header("content-type", "application/json")
+        .body(r#"{"content": "#)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "MALFORMED_BODY");
+
+    // Test 6: JSON of the wrong shape
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": 42 }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 400);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "VALIDATION_ERROR");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_context_search_functionality() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    // Create an HTTP client
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Store multiple contexts with different content and tags
+    let contexts = vec![
+        (
+            "Neural networks are a class of machine learning models inspired by the human brain.",
+            vec!["ai", "machine_learning"],
+        ),
+        (
+            "Transformers have revolutionized natural language processing and understanding.",
+            vec!["ai", "nlp", "transformers"],
+        ),
+        (
+            "Claude is an AI assistant created by Anthropic to be helpful, harmless, and honest.",
+            vec!["ai", "assistant", "anthropic"],
+        ),
+        (
+            "Rust is a systems programming language focused on safety, speed, and concurrency.",
+            vec!["programming", "systems", "rust"],
+        ),
+        (
+            "Python is a popular high-level programming language known for its readability.",
+            vec!["programming", "python"],
+        ),
+    ];
+
+    // Store all the contexts
+    let mut context_ids = Vec::new();
+
+    for (content, tags) in contexts {
+        let store_request = serde_json::json!({
+            "content": content,
+            "tags": tags,
+        });
+
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&store_request)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 201);
+
+        let context_response: serde_json::Value = response.json().await.unwrap();
+        context_ids.push(context_response["id"].as_str().unwrap().to_string());
+    }
+
+    // Test 1: Search by content
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "neural networks transformers claude",
+            "limit": 10
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    let matches = search_response["matches"].as_array().unwrap();
+
+    // Add debug print to see what's coming back
+    println!("Search response: {:?}", search_response);
+
+    assert!(!matches.is_empty());
+    for context_match in matches {
+        let id = context_match["context"]["id"].as_str().unwrap();
+        assert!(context_ids.iter().any(|stored| stored == id));
+    }
+
+    // Searching "Rust programming" finds the Rust context first
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "Rust programming",
+            "limit": 10
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    let matches = search_response["matches"].as_array().unwrap();
+    assert!(!matches.is_empty());
+    assert_eq!(
+        matches[0]["context"]["id"].as_str().unwrap(),
+        context_ids[3]
+    );
+
+    // Test 2: Search with tag filtering
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "programming",
+            "tags": ["rust"],
+            "limit": 10
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    let matches = search_response["matches"].as_array().unwrap();
+
+    // Add debug print to see what's coming back
+    println!("Tag-filtered search response: {:?}", search_response);
+
+    assert!(!matches.is_empty());
+    let matched_content = matches[0]["context"]["content"].as_str().unwrap();
+    assert!(matched_content.contains("Rust"));
+
+    // Test 3: List contexts filtered by tags
+    let response = client
+        .get(&format!("{}/contexts?tags=ai,nlp&limit=10", base_url))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
+    println!("Tag-filtered list response: {:?}", list_response);
+
+    // There should be at least one context with both "ai" and "nlp" tags
+    assert!(!list_response.is_empty());
+
+    // Check each returned context has both required tags
+    for ctx in &list_response {
+        let tags = ctx["tags"].as_array().unwrap();
+        let has_ai = tags.iter().any(|tag| tag.as_str().unwrap() == "ai");
+        let has_nlp = tags.iter().any(|tag| tag.as_str().unwrap() == "nlp");
+        assert!(
+            has_ai && has_nlp,
+            "Context should have both 'ai' and 'nlp' tags"
+        );
+    }
+
+    // Check the specific context we expect
+    if !list_response.is_empty() {
+        let matched_content = list_response[0]["content"].as_str().unwrap();
+        assert!(matched_content.contains("Transformers"));
+    }
+
+    // Clean up - delete all created contexts
+    for id in context_ids {
+        let response = client
+            .delete(&format!("{}/contexts/{}", base_url, id))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 204); // No Content
+    }
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+// Additional helper test that focuses only on the tag filtering functionality
+#[tokio::test]
+async fn test_tag_filtering() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Create contexts with specific tags for testing
+    let contexts = [
+        ("Context 1", vec!["tag1", "tag2"]),
+        ("Context 2", vec!["tag2", "tag3"]),
+        ("Context 3", vec!["tag1", "tag3"]),
+        ("Context 4", vec!["tag1", "tag2", "tag3"]),
+    ];
+
+    // Store all contexts
+    for (content, tags) in &contexts {
+        let store_request = serde_json::json!({
+            "content": content,
+            "tags": tags,
+        });
+
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&store_request)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 201);
+    }
+
+    // Test filtering by single tag
+    let response = client
+        .get(&format!("{}/contexts?tags=tag1&limit=10", base_url))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
+    println!("Single tag filter response: {:?}", list_response);
+    assert_eq!(list_response.len(), 3); // Should return 3 contexts with tag1
+
+    // Test filtering by multiple tags
+    let response = client
+        .get(&format!("{}/contexts?tags=tag1,tag3&limit=10", base_url))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
+    println!("Multiple tag filter response: {:?}", list_response);
+    assert_eq!(list_response.len(), 2); // Should return contexts 3 and 4
+
+    // Test filtering by all three tags
+    let response = client
+        .get(&format!(
+            "{}/contexts?tags=tag1,tag2,tag3&limit=10",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+
+    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
+    println!("All tags filter response: {:?}", list_response);
+    assert_eq!(list_response.len(), 1); // Should return only context 4
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_tag_mode_any() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Create contexts with specific tags for testing
+    let contexts = [
+        ("Context 1", vec!["tag1", "tag2"]),
+        ("Context 2", vec!["tag2", "tag3"]),
+        ("Context 3", vec!["tag1", "tag3"]),
+        ("Context 4", vec!["tag1", "tag2", "tag3"]),
+        ("Context 5", vec!["tag4"]),
+    ];
+
+    // Store all contexts
+    for (content, tags) in &contexts {
+        let store_request = serde_json::json!({
+            "content": content,
+            "tags": tags,
+        });
+
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&store_request)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 201);
+    }
+
+    // With tag_mode=any, any one of the tags will do
+    let response = client
+        .get(&format!(
+            "{}/contexts?tags=tag1,tag3&tag_mode=any&limit=10",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["x-total-count"], "4");
+
+    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
+    let mut contents: Vec<&str> = list_response
+        .iter()
+        .map(|context| context["content"].as_str().unwrap())
+        .collect();
+    contents.sort();
+    assert_eq!(
+        contents,
+        ["Context 1", "Context 2", "Context 3", "Context 4"]
+    );
+
+    // Searches take the same mode
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "Context",
+            "tags": ["tag2", "tag4"],
+            "tag_mode": "any",
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    let mut contents: Vec<&str> = search_response["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["context"]["content"].as_str().unwrap())
+        .collect();
+    contents.sort();
+    assert_eq!(
+        contents,
+        ["Context 1", "Context 2", "Context 4", "Context 5"]
+    );
+
+    let response = client
+        .get(&format!("{}/contexts?tags=tag1&tag_mode=either", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_excluded_tags() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Create contexts with specific tags for testing
+    let contexts = [
+        ("Context 1", vec!["tag1", "tag2"]),
+        ("Context 2", vec!["tag2", "tag3"]),
+        ("Context 3", vec!["tag1", "tag3"]),
+        ("Context 4", vec!["tag1", "tag2", "tag3"]),
+    ];
+
+    // Store all contexts
+    for (content, tags) in &contexts {
+        let store_request = serde_json::json!({
+            "content": content,
+            "tags": tags,
+        });
+
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&store_request)
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), 201);
+    }
+
+    // Excluded tags hide a context even when it has the required ones, whatever their case
+    let response = client
+        .get(&format!(
+            "{}/contexts?tags=tag1&exclude_tags=TAG3&limit=10",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let list_response: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(list_response.len(), 1);
+    assert_eq!(list_response[0]["content"], "Context 1");
+
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "Context",
+            "tags": ["tag2"],
+            "exclude_tags": ["Tag1"],
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 200);
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    let matches = search_response["matches"].as_array().unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0]["context"]["content"], "Context 2");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_context_sharing_links() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Store a context to share
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({
+            "content": "Shared context content",
+            "tags": ["shared"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    let context_id = context["id"].as_str().unwrap().to_string();
+
+    // Create a sharing link
+    let response = client
+        .post(&format!("{}/contexts/{}/share", base_url, context_id))
+        .json(&serde_json::json!({ "ttl_seconds": 600 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let share: serde_json::Value = response.json().await.unwrap();
+    let url = share["url"].as_str().unwrap().to_string();
+    let token = share["token"].as_str().unwrap().to_string();
+    let token_id = share["token_id"].as_str().unwrap().to_string();
+    assert_eq!(share["context_id"].as_str().unwrap(), context_id);
+
+    // The link serves the context
+    let response = client
+        .get(&format!("http://{}{}", server_addr, url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let shared: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(shared["id"].as_str().unwrap(), context_id);
+    assert_eq!(
+        shared["content"].as_str().unwrap(),
+        "Shared context content"
+    );
+
+    // A tampered token is rejected
+    let tampered = format!("{}00", token);
+    let response = client
+        .get(&format!("{}/shared/{}", base_url, tampered))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "AUTH_ERROR");
+
+    // Sharing a non-existent context fails
+    let response = client
+        .post(&format!("{}/contexts/{}/share", base_url, Uuid::new_v4()))
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    // A link can't be revoked through another context
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "Unshared context content" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let other: serde_json::Value = response.json().await.unwrap();
+    let response = client
+        .delete(&format!(
+            "{}/contexts/{}/share/{}",
+            base_url,
+            other["id"].as_str().unwrap(),
+            token_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "SHARE_LINK_NOT_FOUND");
+
+    // Revoke the link
+    let response = client
+        .delete(&format!(
+            "{}/contexts/{}/share/{}",
+            base_url, context_id, token_id
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+
+    // The revoked link no longer works
+    let response = client
+        .get(&format!("{}{}", base_url, url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_tag_normalization() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Store a context with messy tags
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({
+            "content": "Rust ownership and borrowing explained",
+            "tags": [" Rust ", "RUST", "rust", "Machine   Learning"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    let context_id = context["id"].as_str().unwrap().to_string();
+    assert_eq!(
+        context["tags"],
+        serde_json::json!(["rust", "machine-learning"])
+    );
+
+    // List filters normalize the same way
+    for filter in [
+        "RUST",
+        " Rust ",
+        "machine learning",
+        "Rust,MACHINE learning",
+    ] {
+        let response = client
+            .get(&format!("{}/contexts", base_url))
+            .query(&[("tags", filter)])
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let contexts: Vec<serde_json::Value> = response.json().await.unwrap();
+        assert!(
+            contexts.iter().any(|c| c["id"] == context_id),
+            "Filter {:?} should match the stored context",
+            filter
+        );
+    }
+
+    // Search filters normalize the same way
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "Rust ownership",
+            "tags": ["  RUST"],
+            "limit": 5,
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let search: serde_json::Value = response.json().await.unwrap();
+    let matches = search["matches"].as_array().unwrap();
+    assert!(matches.iter().any(|m| m["context"]["id"] == context_id));
+
+    // Updates normalize too
+    let response = client
+        .put(&format!("{}/contexts/{}", base_url, context_id))
+        .json(&serde_json::json!({
+            "content": "Rust ownership and borrowing explained",
+            "tags": ["Systems Programming"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let updated: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(updated["tags"], serde_json::json!(["systems-programming"]));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_query_string_search() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Store one current and one archived runbook
+    let mut ids = Vec::new();
+    for (content, tags, source) in [
+        ("Database outage runbook", vec!["runbook"], "pagerduty"),
+        (
+            "Old outage runbook",
+            vec!["runbook", "archived"],
+            "pagerduty",
+        ),
+        ("Outage postmortem notes", vec!["runbook"], "wiki"),
+    ] {
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({
+                "content": content,
+                "tags": tags,
+                "source": source,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        let context: serde_json::Value = response.json().await.unwrap();
+        ids.push(context["id"].as_str().unwrap().to_string());
+    }
+
+    // Directives filter the ranked matches
+    let response = client
+        .get(&format!("{}/search", base_url))
+        .query(&[("q", "outage tag:Runbook -tag:archived source:pagerduty")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let search: serde_json::Value = response.json().await.unwrap();
+    let matched: Vec<&str> = search["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["context"]["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(matched, vec![ids[0].as_str()]);
+
+    // Unknown directives are rejected with the offending token in the message
+    let response = client
+        .get(&format!("{}/search", base_url))
+        .query(&[("q", "outage colour:red")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert!(error["message"].as_str().unwrap().contains("colour:red"));
+
+    // Filters alone are not a search
+    let response = client
+        .get(&format!("{}/search", base_url))
+        .query(&[("q", "tag:runbook")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Matches can be rendered for pasting into a prompt
+    let response = client
+        .get(&format!("{}/search", base_url))
+        .query(&[("q", "outage tag:runbook -tag:archived source:pagerduty")])
+        .header("Accept", "application/xml")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("application/xml"));
+    let xml = response.text().await.unwrap();
+    assert!(xml.contains(&format!("<context id=\"{}\" source=\"pagerduty\"", ids[0])));
+
+    let response = client
+        .get(&format!("{}/search", base_url))
+        .query(&[("q", "outage tag:runbook"), ("format", "markdown")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let markdown = response.text().await.unwrap();
+    assert!(markdown.contains("## 1. Context "));
+    assert!(markdown.contains("- Tags: runbook"));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_compact_response_modes_shrink_search_payloads() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    // Store a long document so its content dominates the payload
+    let content = "Kubernetes rollout checklist for the payments service. ".repeat(200);
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({
+            "content": content,
+            "tags": ["runbook"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    let context_id = context["id"].as_str().unwrap().to_string();
+
+    let mut sizes = Vec::new();
+    for mode in ["full", "chunks_only", "ids_only"] {
+        let response = client
+            .post(&format!("{}/search", base_url))
+            .json(&serde_json::json!({
+                "query": "rollout checklist",
+                "response_mode": mode,
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = response.bytes().await.unwrap();
+        sizes.push(body.len());
+
+        let search: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let m = &search["matches"][0];
+        assert_eq!(m["id"], context_id.as_str());
+        assert!(m["score"].is_number());
+        match mode {
+            "full" => assert_eq!(m["context"]["content"], content.as_str()),
+            "chunks_only" => {
+                assert_eq!(m["context"]["id"], context_id.as_str());
+                assert!(m["context"].get("content").is_none());
+                for chunk in m["chunks"].as_array().unwrap() {
+                    assert!(chunk["token_count"].as_u64().unwrap() > 0);
+                }
+            }
+            _ => {
+                assert!(m.get("context").is_none());
+                assert!(m.get("chunks").is_none());
+            }
+        }
+    }
+    assert!(
+        sizes[0] > sizes[1] && sizes[1] > sizes[2],
+        "Payloads should shrink from full to chunks_only to ids_only: {:?}",
+        sizes
+    );
+
+    // Reference retrieval takes the same modes
+    let response = client
+        .post(&format!("{}/references", base_url))
+        .json(&serde_json::json!({
+            "references": [{ "context_id": context_id }],
+            "response_mode": "chunks_only",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let retrieved: serde_json::Value = response.json().await.unwrap();
+    let m = &retrieved["matches"][0];
+    assert_eq!(m["context"]["id"], context_id.as_str());
+    assert!(m["context"].get("content").is_none());
+
+    // Unknown modes are rejected
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "rollout",
+            "response_mode": "everything",
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_requests_do_not_fail() {
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()
+        .unwrap();
+
+    // Each task stores, lists, reads back, and deletes its own context
+    let tasks: Vec<_> = (0..300)
+        .map(|i| {
+            let client = client.clone();
+            let base_url = base_url.clone();
+            tokio::spawn(async move {
+                let response = client
+                    .post(&format!("{}/contexts", base_url))
+                    .json(&serde_json::json!({
+                        "content": format!("Concurrent context {}", i),
+                        "tags": [format!("batch{}", i % 7)],
+                    }))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), 201);
+                let context: serde_json::Value = response.json().await.unwrap();
+                let id = context["id"].as_str().unwrap().to_string();
+
+                let response = client
+                    .get(&format!("{}/contexts", base_url))
+                    .query(&[("tags", format!("batch{}", i % 7))])
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), 200);
+
+                let response = client
+                    .get(&format!("{}/contexts/{}", base_url, id))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), 200);
+
+                let response = client
+                    .delete(&format!("{}/contexts/{}", base_url, id))
+                    .send()
+                    .await
+                    .unwrap();
+                assert_eq!(response.status(), 204);
+            })
+        })
+        .collect();
+
+    for task in tasks {
+        task.await.unwrap();
+    }
+
+    // Every context was deleted by the task that stored it
+    let response = client
+        .get(&format!("{}/contexts", base_url))
+        .send()
+        .await
+        .unwrap();
+    let contexts: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert!(contexts.is_empty());
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_evaluation_runs() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({
+            "content": "Restart the primary database and check replication",
+            "tags": ["runbook"],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    // Cases must expect something
+    let response = client
+        .post(&format!("{}/admin/eval/datasets", base_url))
+        .json(&serde_json::json!({
+            "name": "runbooks",
+            "cases": [{ "query": "restart the database" }],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = client
+        .post(&format!("{}/admin/eval/datasets", base_url))
+        .json(&serde_json::json!({
+            "name": "runbooks",
+            "cases": [{ "query": "restart the database", "expected_tags": ["Runbook"] }],
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+
+    for label in ["baseline", "candidate"] {
+        let response = client
+            .post(&format!("{}/admin/eval/run", base_url))
+            .json(&serde_json::json!({ "dataset": "runbooks", "k": 5, "label": label }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        let run: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(run["queries"], 1);
+        let recall = run["recall_at_k"].as_f64().unwrap();
+        assert!((0.0..=1.0).contains(&recall));
+    }
+
+    let response = client
+        .post(&format!("{}/admin/eval/run", base_url))
+        .json(&serde_json::json!({ "dataset": "missing" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+
+    let response = client
+        .get(&format!("{}/admin/eval/runs", base_url))
+        .query(&[("dataset", "runbooks")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let runs: Vec<serde_json::Value> = response.json().await.unwrap();
+    let labels: Vec<_> = runs
+        .iter()
+        .map(|run| run["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["baseline", "candidate"]);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_list_and_search_filter_by_creation_time() {
+    let context_repository = create_repository(&test_config()).await.unwrap();
+    let embedding_service = Arc::new(SimpleEmbeddingService::new(128));
+
+    // Meeting notes back-dated to midnight on the 1st to the 4th, written to the repository
+    // directly since the API always stamps the current time
+    let day = |day: u32| Utc.with_ymd_and_hms(2024, 3, day, 0, 0, 0).unwrap();
+    let mut ids = Vec::new();
+    for i in 1..=4 {
+        let content = format!("Meeting notes from day {}", i);
+        let context = Context {
+            created_at: day(i),
+            ..Context::new(content.clone(), ContextMetadata::default())
+        };
+        let chunk = ContextChunk {
+            chunk_id: Uuid::new_v4(),
+            context_id: context.id,
+            content: content.clone(),
+            position: 0,
+            embedding: embedding_service
+                .embed_texts(&[content])
+                .await
+                .unwrap()
+                .pop(),
+        };
+        context_repository
+            .save_context_with_chunks(context.clone(), vec![chunk.clone()])
+            .await
+            .unwrap();
+        embedding_service.upsert(&[chunk], &[]).await.unwrap();
+        ids.push(context.id.to_string());
+    }
+
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server_on(
+        context_repository,
+        embedding_service.clone(),
+        embedding_service,
+    )
+    .await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let list = |query: Vec<(&'static str, &'static str)>| {
+        let request = client.get(&format!("{}/contexts", base_url)).query(&query);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            let total = response.headers()["x-total-count"]
+                .to_str()
+                .unwrap()
+                .to_string();
+            let contexts: Vec<serde_json::Value> = response.json().await.unwrap();
+            let mut ids: Vec<String> = contexts
+                .iter()
+                .map(|context| context["id"].as_str().unwrap().to_string())
+                .collect();
+            ids.sort();
+            (total, ids)
+        }
+    };
+    let sorted = |mut ids: Vec<String>| {
+        ids.sort();
+        ids
+    };
+
+    // created_after is inclusive and created_before exclusive
+    let (total, found) = list(vec![
+        ("created_after", "2024-03-02T00:00:00Z"),
+        ("created_before", "2024-03-04T00:00:00Z"),
+    ])
+    .await;
+    assert_eq!(total, "2");
+    assert_eq!(found, sorted(vec![ids[1].clone(), ids[2].clone()]));
+
+    let (total, found) = list(vec![("created_after", "2024-03-03T01:00:00+01:00")]).await;
+    assert_eq!(total, "2");
+    assert_eq!(found, sorted(vec![ids[2].clone(), ids[3].clone()]));
+
+    let response = client
+        .get(&format!("{}/contexts", base_url))
+        .query(&[("created_before", "last tuesday")])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "VALIDATION_ERROR");
+    assert!(error_response["message"]
+        .as_str()
+        .unwrap()
+        .contains("created_before"));
+
+    // Search takes the same bounds
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "meeting notes",
+            "created_after": "2024-03-04T00:00:00Z"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    let matches = search_response["matches"].as_array().unwrap();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0]["context"]["id"].as_str().unwrap(), ids[3]);
+
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({
+            "query": "meeting notes",
+            "created_before": "the 4th"
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error_response["code"], "VALIDATION_ERROR");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_list_reports_total_count() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let mut ids = Vec::new();
+    for i in 0..5 {
+        let tag = if i % 2 == 0 { "even" } else { "odd" };
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({
+                "content": format!("Paged context {}", i),
+                "tags": [tag],
+            }))
+            .send()
+            .await
+            .unwrap();
+        let context: serde_json::Value = response.json().await.unwrap();
+        ids.push(context["id"].as_str().unwrap().to_string());
+    }
+
+    let list = |query: Vec<(&'static str, &'static str)>| {
+        let request = client.get(&format!("{}/contexts", base_url)).query(&query);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            let total: usize = response.headers()["x-total-count"]
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            let contexts: Vec<serde_json::Value> = response.json().await.unwrap();
+            (total, contexts.len())
+        }
+    };
+
+    // The total ignores pagination but follows the tag filter
+    assert_eq!(list(vec![("limit", "2")]).await, (5, 2));
+    assert_eq!(list(vec![("tags", "even"), ("limit", "1")]).await, (3, 1));
+    assert_eq!(list(vec![("tags", "odd"), ("offset", "1")]).await, (2, 1));
+
+    let response = client
+        .delete(&format!("{}/contexts/{}", base_url, ids[0]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 204);
+    assert_eq!(list(vec![("tags", "even")]).await, (2, 2));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_list_envelope_reports_the_next_page() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    for i in 0..5 {
+        client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": format!("Enveloped context {}", i) }))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let page = |offset: &'static str| {
+        let request = client.get(&format!("{}/contexts", base_url)).query(&[
+            ("envelope", "true"),
+            ("limit", "2"),
+            ("offset", offset),
+        ]);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            response.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+
+    let first = page("0").await;
+    assert_eq!(first["items"].as_array().unwrap().len(), 2);
+    assert_eq!(first["total"], 5);
+    assert_eq!(first["limit"], 2);
+    assert_eq!(first["offset"], 0);
+    assert_eq!(first["next_offset"], 2);
+
+    let last = page("4").await;
+    assert_eq!(last["items"].as_array().unwrap().len(), 1);
+    assert!(last["next_offset"].is_null());
+
+    // Past the end the page is empty, but the total still counts every match
+    let beyond = page("10").await;
+    assert!(beyond["items"].as_array().unwrap().is_empty());
+    assert_eq!(beyond["total"], 5);
+    assert!(beyond["next_offset"].is_null());
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_over_limit_requests_are_clamped_to_the_maximum() {
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            max_results: Some(3),
+            max_page_size: Some(4),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    for i in 0..6 {
+        client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": format!("Clamped replica note {}", i) }))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    // Searches default to the maximum and never return more
+    for limit in [None, Some(1_000_000)] {
+        let response = client
+            .post(&format!("{}/search", base_url))
+            .json(&serde_json::json!({ "query": "replica note", "limit": limit }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let results: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(results["matches"].as_array().unwrap().len(), 3);
+    }
+    let response = client
+        .get(&format!("{}/search?q=replica&limit=1000000", base_url))
+        .send()
+        .await
+        .unwrap();
+    let results: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(results["matches"].as_array().unwrap().len(), 3);
+
+    // Listings report the limit they applied
+    let response = client
+        .get(&format!(
+            "{}/contexts?envelope=true&limit=1000000",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let page: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(page["items"].as_array().unwrap().len(), 4);
+    assert_eq!(page["limit"], 4);
+    assert_eq!(page["total"], 6);
+    assert_eq!(page["next_offset"], 4);
+
+    let response = client
+        .get(&format!("{}/contexts", base_url))
+        .send()
+        .await
+        .unwrap();
+    let contexts: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(contexts.len(), 4);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_filtered_searches_fill_the_default_limit() {
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            max_results: Some(3),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    // Wiki pages, followed by more blog posts than the limit that match the query more closely
+    let mut wiki_ids = Vec::new();
+    for i in 0..3 {
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({
+                "content": format!("Replica failover notes kept on the team wiki, page {}", i),
+                "source": "wiki",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+        let context: serde_json::Value = response.json().await.unwrap();
+        wiki_ids.push(context["id"].as_str().unwrap().to_string());
+    }
+    for i in 0..4 {
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({
+                "content": format!("Replica failover: replica failover, post {}", i),
+                "source": "blog",
+            }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 201);
+    }
+
+    // Filtering by source still fills the default limit with wiki pages
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({ "query": "replica failover", "source": "wiki" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    let results: serde_json::Value = response.json().await.unwrap();
+    let mut ids: Vec<String> = results["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap().to_string())
+        .collect();
+    ids.sort();
+    wiki_ids.sort();
+    assert_eq!(ids, wiki_ids);
+    assert_eq!(results["total_matches"], 3);
+
+    // As does the query string syntax
+    let response = client
+        .get(&format!(
+            "{}/search?q=replica+failover+source:wiki",
+            base_url
+        ))
+        .send()
+        .await
+        .unwrap();
+    let results: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(results["matches"].as_array().unwrap().len(), 3);
+    assert_eq!(results["total_matches"], 3);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_search_min_score_leaves_out_irrelevant_matches() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let mut ids = Vec::new();
+    for content in [
+        "Rust is a systems programming language focused on safety",
+        "Python is a popular programming language known for its readability",
+    ] {
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .unwrap();
+        let context: serde_json::Value = response.json().await.unwrap();
+        ids.push(context["id"].as_str().unwrap().to_string());
+    }
+
+    // Without a threshold every stored context comes back, however weak the match
+    let search = |body: serde_json::Value| {
+        let request = client.post(&format!("{}/search", base_url)).json(&body);
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            response.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+    let response = search(serde_json::json!({ "query": "quantum chromodynamics" })).await;
+    assert_eq!(response["matches"].as_array().unwrap().len(), 2);
+
+    let response = search(serde_json::json!({
+        "query": "quantum chromodynamics",
+        "min_score": 0.3
+    }))
+    .await;
+    assert_eq!(response["matches"], serde_json::json!([]));
+    assert_eq!(response["total_matches"], 0);
+
+    // The query syntax applies the same threshold
+    let response = client
+        .get(&format!("{}/search", base_url))
+        .query(&[("q", "quantum chromodynamics min_score:0.3")])
+        .send()
+        .await
+        .unwrap();
+    let response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(response["matches"], serde_json::json!([]));
+
+    // Relevant matches clear it
+    let response = search(serde_json::json!({
+        "query": "Rust safety",
+        "min_score": 0.3
+    }))
+    .await;
+    let matches = response["matches"].as_array().unwrap();
+    assert_eq!(matches[0]["context"]["id"].as_str().unwrap(), ids[0]);
+    assert_eq!(response["total_matches"], matches.len());
+    assert!(matches.iter().all(|m| m["score"].as_f64().unwrap() >= 0.3));
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_search_with_injected_embedding_service() {
+    let tfidf = Arc::new(TfIdfEmbeddingService::new(256));
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with(tfidf.clone(), tfidf).await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    let mut ids = Vec::new();
+    for content in [
+        "The database is down again",
+        "The cache is down again",
+        "The kafka broker is rebalancing",
+    ] {
+        let response = client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .unwrap();
+        let context: serde_json::Value = response.json().await.unwrap();
+        ids.push(context["id"].as_str().unwrap().to_string());
+    }
+
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({ "query": "kafka down", "limit": 3 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+
+    // The rare term outweighs the one shared by the other contexts
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(search_response["matches"][0]["context"]["id"], ids[2]);
+
+    // A smaller limit returns fewer matches, but still counts every one
+    let response = client
+        .post(&format!("{}/search", base_url))
+        .json(&serde_json::json!({ "query": "kafka down", "limit": 1 }))
+        .send()
+        .await
+        .unwrap();
+    let search_response: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(search_response["matches"].as_array().unwrap().len(), 1);
+    assert_eq!(search_response["matches"][0]["context"]["id"], ids[2]);
+    assert_eq!(search_response["total_matches"], 3);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+/// Needs the model files, which are downloaded into `embedding.fastembed.model_path` on the first run
+#[cfg(feature = "fastembed")]
+#[tokio::test]
+async fn test_fastembed_ranks_relevant_context_first() {
+    use mcp::adapter::out_adapters::{create_embedding_backend, InMemoryContextRepository};
+    use mcp::config::EmbeddingProvider;
+    use mcp::ports::in_ports::{ContextManagementPort, ContextSearchPort};
+
+    let mut config = AppConfig::load_defaults().unwrap();
+    config.embedding.provider = EmbeddingProvider::FastEmbed;
+    let embedding = create_embedding_backend(&config).await.unwrap();
+
+    let context_repository = Arc::new(InMemoryContextRepository::new());
+    let context_manager = ContextManagementService::new(
+        context_repository.clone(),
+        embedding.embedding_service.clone(),
+        embedding.vector_store.clone(),
+        1000,
+        200,
+    )
+    .unwrap();
+    let context_search = ContextSearchService::new(
+        context_repository,
+        embedding.embedding_service,
+        embedding.vector_store,
+        10,
+    );
+
+    let revenue = context_manager
+        .store_context(
+            "Quarterly revenue grew by twelve percent after the pricing change".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+    let cat = context_manager
+        .store_context(
+            "The cat curled up on the windowsill and purred in the sun".to_string(),
+            ContextMetadata::default(),
+            StoreOptions::default(),
+        )
+        .await
+        .unwrap();
+
+    // The query shares no words with either context, so only the embeddings can rank them
+    let result = context_search
+        .search("sleepy kitten".to_string(), 2, SearchOptions::default())
+        .await
+        .unwrap();
+
+    let ids: Vec<_> = result.matches.iter().map(|m| m.context.id).collect();
+    assert_eq!(ids, [cat.id, revenue.id]);
+}
+
+#[tokio::test]
+async fn test_search_highlights_matched_terms() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    client
+        .post(&format!("{}/contexts", base_url))
+        .json(&serde_json::json!({
+            "content": "The payments service had an outage after the database failover"
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let search = |highlight: bool| {
+        let request = client
+            .post(&format!("{}/search", base_url))
+            .json(&serde_json::json!({ "query": "payments outage", "highlight": highlight }));
+        async move {
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            response.json::<serde_json::Value>().await.unwrap()
+        }
+    };
+
+    let response = search(true).await;
+    let snippets = response["matches"][0]["snippets"].as_array().unwrap();
+    assert_eq!(snippets.len(), 1);
+    let snippet = snippets[0].as_str().unwrap();
+    assert!(snippet.contains("<em>payments</em>"), "{}", snippet);
+    assert!(snippet.contains("<em>outage</em>"), "{}", snippet);
+
+    // Snippets are left out unless asked for
+    let response = search(false).await;
+    assert!(response["matches"][0].get("snippets").is_none());
+
+    shutdown_tx.send(()).unwrap();
+    server_handle.await.unwrap();
+}
+
+#[tokio::test]
+async fn test_search_matches_quoted_phrases_exactly() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let base_url = format!("http://{}/v1", server_addr);
+    let client = reqwest::Client::new();
+
+    for content in [
+        "Long prompts overflow the context window of the model",
+        "The window shows the context of the selected item",
+    ] {
+        client
+            .post(&format!("{}/contexts", base_url))
+            .json(&serde_json::json!({ "content": content }))
+            .send()
+            .await
+            .unwrap();
+    }
+
+    let search = |query: &str| {
+        client
+            .post(&format!("{}/search", base_url))
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+    };
+
+    // Without quotes both contexts have the terms
+    let response = search("context window").await.unwrap();
+    assert_eq!(response.status(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["matches"].as_array().unwrap().len(), 2);
+
+    let response = search("\"context window\"").await.unwrap();
+    assert_eq!(response.status(), 200);
+    let result: serde_json::Value = response.json().await.unwrap();
+    let matches = result["matches"].as_array().unwrap();
+    assert_eq!(matches.len(), 1);
+    assert!(matches[0]["context"]["content"]
+        .as_str()
+        .unwrap()
+        .contains("context window"));
+
+    // A query of nothing but empty phrases has nothing to search for
+    let response = search("\"\" \"  \"").await.unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "VALIDATION_ERROR");
+
+    shutdown_tx.send(()).unwrap();
+    server_handle.await.unwrap();
+}
+
+/// An upload form holding `bytes` as a file named `file_name`
+fn upload_form(file_name: &str, bytes: Vec<u8>) -> reqwest::multipart::Form {
+    reqwest::multipart::Form::new().part(
+        "file",
+        reqwest::multipart::Part::bytes(bytes).file_name(file_name.to_string()),
+    )
+}
+
+#[tokio::test]
+async fn test_upload_stores_a_text_file() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let upload_url = format!("http://{}/v1/contexts/upload", server_addr);
+
+    let response = client
+        .post(&upload_url)
+        .multipart(
+            upload_form("runbook.md", b"# Failover\n\nPromote the replica.".to_vec())
+                .text("tags", "Runbook, database"),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(context["content"], "# Failover\n\nPromote the replica.");
+    assert_eq!(context["source"], "runbook.md");
+    assert_eq!(context["content_type"], "text/markdown");
+    assert_eq!(context["tags"], serde_json::json!(["runbook", "database"]));
+
+    // Fields given with the file win over what the file name suggests
+    let response = client
+        .post(&upload_url)
+        .multipart(
+            upload_form("notes.txt", b"Rotate the keys monthly".to_vec())
+                .text("source", "wiki")
+                .text("content_type", "text/x-wiki"),
+        )
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(context["source"], "wiki");
+    assert_eq!(context["content_type"], "text/x-wiki");
+
+    // The uploaded context is stored like any other
+    let id = context["id"].as_str().unwrap();
+    let stored: serde_json::Value = client
+        .get(format!("http://{}/v1/contexts/{}", server_addr, id))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(stored["content"], "Rotate the keys monthly");
+
+    // A form without a file is rejected
+    let response = client
+        .post(&upload_url)
+        .multipart(reqwest::multipart::Form::new().text("tags", "runbook"))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "file").await;
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_upload_of_non_utf8_file_needs_lossy() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let upload_url = format!("http://{}/v1/contexts/upload", server_addr);
+    let latin1 = b"caf\xe9 au lait".to_vec();
+
+    let response = client
+        .post(&upload_url)
+        .multipart(upload_form("menu.txt", latin1.clone()))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "file").await;
+
+    let response = client
+        .post(&upload_url)
+        .multipart(upload_form("menu.txt", latin1).text("lossy", "true"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(context["content"], "caf\u{fffd} au lait");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_upload_of_oversized_file_is_refused() {
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            max_body_bytes: Some(4096),
+            max_content_bytes: Some(100),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let client = reqwest::Client::new();
+    let upload_url = format!("http://{}/v1/contexts/upload", server_addr);
+
+    // A file over the content limit but inside the body limit fails validation
+    let response = client
+        .post(&upload_url)
+        .multipart(upload_form("big.txt", vec![b'x'; 101]))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "content").await;
+
+    // A file over the body limit is refused while it's being read
+    let response = client
+        .post(&upload_url)
+        .multipart(upload_form("huge.txt", vec![b'x'; 8192]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 413);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "PAYLOAD_TOO_LARGE");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_ingest_url_stores_the_page() {
+    let site = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::path("/runbook"))
+        .respond_with(
+            wiremock::ResponseTemplate::new(200)
+                .insert_header("content-type", "text/html; charset=utf-8")
+                .set_body_string(
+                    "<html><head><title>Failover</title></head>\
+                     <body><p>Promote the <b>replica</b> &amp; update DNS.</p></body></html>",
+                ),
+        )
+        .mount(&site)
+        .await;
+    let page_url = format!("{}/runbook", site.uri());
+
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let ingest_url = format!("http://{}/v1/ingest/url", server_addr);
+
+    let response = client
+        .post(&ingest_url)
+        .json(&serde_json::json!({ "url": page_url, "tags": ["Runbook"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(
+        context["content"],
+        "Failover\n\nPromote the replica & update DNS."
+    );
+    assert_eq!(context["source"], page_url);
+    assert_eq!(context["content_type"], "text/plain");
+    assert_eq!(context["tags"], serde_json::json!(["runbook"]));
+
+    // The page is searchable like any other context
+    let results: serde_json::Value = client
+        .get(format!("http://{}/v1/search?q=replica", server_addr))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(results["matches"][0]["context"]["id"], context["id"]);
+
+    // Without stripping the markup is kept, labelled with the page's content type
+    let response = client
+        .post(&ingest_url)
+        .json(&serde_json::json!({ "url": page_url, "strip_html": false }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    let context: serde_json::Value = response.json().await.unwrap();
+    assert!(context["content"].as_str().unwrap().starts_with("<html>"));
+    assert_eq!(context["content_type"], "text/html");
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_ingest_url_failures() {
+    let site = wiremock::MockServer::start().await;
+    wiremock::Mock::given(wiremock::matchers::path("/broken"))
+        .respond_with(wiremock::ResponseTemplate::new(500))
+        .mount(&site)
+        .await;
+
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let ingest_url = format!("http://{}/v1/ingest/url", server_addr);
+
+    // A page that can't be fetched is the site's failure, not ours
+    let response = client
+        .post(&ingest_url)
+        .json(&serde_json::json!({ "url": format!("{}/broken", site.uri()) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 502);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "UPSTREAM_ERROR");
+    assert!(error["message"].as_str().unwrap().contains("500"));
+
+    let response = client
+        .post(&ingest_url)
+        .json(&serde_json::json!({ "url": "ftp://example.com/notes.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_rejected_field(response, "url").await;
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+
+    // By default, URLs on internal addresses are refused before anything is fetched
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            block_private_addresses: true,
+            ..TestServerOptions::default()
+        })
+        .await;
+    let response = client
+        .post(format!("http://{}/v1/ingest/url", server_addr))
+        .json(&serde_json::json!({ "url": format!("{}/broken", site.uri()) }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 400);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert!(error["message"]
+        .as_str()
+        .unwrap()
+        .contains("private address"));
+    assert_eq!(site.received_requests().await.unwrap().len(), 1);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_unversioned_paths_are_deprecated_aliases() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+
+    let response = client
+        .post(format!("http://{}/v1/contexts", server_addr))
+        .json(&serde_json::json!({ "content": "Stored through the versioned API" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 201);
+    assert!(response.headers().get("deprecation").is_none());
+    let context: serde_json::Value = response.json().await.unwrap();
+    let id = context["id"].as_str().unwrap();
+
+    // The old path still serves the same context, flagged with its successor
+    let response = client
+        .get(format!("http://{}/contexts/{}", server_addr, id))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["deprecation"], "true");
+    assert_eq!(
+        response.headers()["link"],
+        format!("</v1/contexts/{}>; rel=\"successor-version\"", id).as_str()
+    );
+    let legacy: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(legacy["content"], "Stored through the versioned API");
+
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+
+    // With the aliases turned off only the versioned paths exist
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            legacy_routes: Some(false),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let legacy = client
+        .get(format!("http://{}/health", server_addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(legacy.status(), 404);
+    let versioned = client
+        .get(format!("http://{}/v1/health", server_addr))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(versioned.status(), 200);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[tokio::test]
+async fn test_context_content_can_be_read_as_text() {
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = reqwest::Client::new();
+    let base_url = format!("http://{}/v1", server_addr);
+
+    let content = "# Failover\n\n1. Promote the replica\n2. Update DNS\n";
+    let context: serde_json::Value = client
+        .post(format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": content, "content_type": "text/markdown" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let context_url = format!("{}/contexts/{}", base_url, context["id"].as_str().unwrap());
+
+    // Asking for plain text gets just the content
+    let response = client
+        .get(&context_url)
+        .header("Accept", "text/plain")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(response.headers()["vary"], "accept");
+    assert_eq!(response.text().await.unwrap(), content);
+
+    // JSON stays the default, for no Accept header as for one preferring JSON
+    for accept in [None, Some("*/*"), Some("application/json, text/plain")] {
+        let mut request = client.get(&context_url);
+        if let Some(accept) = accept {
+            request = request.header("Accept", accept);
+        }
+        let response = request.send().await.unwrap();
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("application/json"));
+        let body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(body["content"], content);
+    }
+
+    // The raw route returns the content with the context's own content type
+    let response = client
+        .get(format!("{}/raw", context_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "text/markdown");
+    let etag = response.headers()["etag"].to_str().unwrap().to_string();
+    assert_eq!(response.text().await.unwrap(), content);
+    let response = client
+        .get(format!("{}/raw", context_url))
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 304);
+
+    // Without a content type the raw content is plain text
+    let plain: serde_json::Value = client
+        .post(format!("{}/contexts", base_url))
+        .json(&serde_json::json!({ "content": "no type given" }))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let response = client
+        .get(format!(
+            "{}/contexts/{}/raw",
+            base_url,
+            plain["id"].as_str().unwrap()
+        ))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/plain; charset=utf-8"
+    );
+    assert_eq!(response.text().await.unwrap(), "no type given");
+
+    let response = client
+        .get(format!("{}/contexts/{}/raw", base_url, Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_typed_client_round_trip() {
+    use mcp::adapter::in_adapters::api::models::{
+        ContextReferenceDto, ListContextsParams, ReferenceRequest, SearchRequest,
+        StoreContextRequest, UpdateContextRequest,
+    };
+    use mcp::client::{ClientError, McpClient};
+
+    // Start a test server
+    let (server_addr, shutdown_tx, server_handle) = setup_test_server().await;
+    let client = McpClient::new(format!("http://{}", server_addr));
+
+    // Store
+    let stored = client
+        .store(&StoreContextRequest {
+            content: "Typed clients keep request bodies in step with the server".to_string(),
+            source: Some("client-test".to_string()),
+            tags: Some(vec!["client".to_string()]),
+            ..StoreContextRequest::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(stored.source.as_deref(), Some("client-test"));
+    assert_eq!(stored.tags, vec!["client"]);
+
+    // Get
+    let fetched = client.get(stored.id).await.unwrap();
+    assert_eq!(fetched.content, stored.content);
+
+    // List
+    let page = client
+        .list(ListContextsParams {
+            tags: Some("client".to_string()),
+            ..ListContextsParams::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(page.total, 1);
+    assert_eq!(page.items[0].id, stored.id);
+
+    // Search
+    let search = client
+        .search(&SearchRequest {
+            query: "typed clients".to_string(),
+            limit: Some(5),
+            ..SearchRequest::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(search.matches[0].id, stored.id);
+
+    // Update
+    let updated = client
+        .update(
+            stored.id,
+            &UpdateContextRequest {
+                content: "Typed clients share the server's DTOs".to_string(),
+                tags: Some(vec!["client".to_string(), "dto".to_string()]),
+                ..UpdateContextRequest::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(updated.tags, vec!["client", "dto"]);
+    assert_eq!(updated.version, stored.version + 1);
+
+    // Retrieve by reference
+    let retrieved = client
+        .retrieve_by_references(&ReferenceRequest {
+            references: vec![ContextReferenceDto {
+                context_id: stored.id,
+                chunk_ids: None,
+                weight: None,
+            }],
+            response_mode: Default::default(),
+        })
+        .await
+        .unwrap();
+    let context = retrieved.matches[0].context.as_ref().unwrap();
+    assert_eq!(context.content.as_deref(), Some(updated.content.as_str()));
+
+    // Delete, after which the server's error body comes back typed
+    client.delete(stored.id).await.unwrap();
+    let err = client.get(stored.id).await.unwrap_err();
+    assert_eq!(err.status(), Some(reqwest::StatusCode::NOT_FOUND));
+    assert_eq!(err.code(), Some("CONTEXT_NOT_FOUND"));
+    match err {
+        ClientError::Api { error, .. } => assert!(error.request_id.is_some()),
+        other => panic!("Expected a server error, got {:?}", other),
+    }
+
+    // Shutdown the server
+    shutdown_tx.send(()).unwrap();
+    let _ = server_handle.await;
+
+    // Without a server there is no response, which is a transport error
+    let err = client.get(stored.id).await.unwrap_err();
+    assert!(matches!(err, ClientError::Transport(_)));
+    assert_eq!(err.status(), None);
+}
+
+#[cfg(feature = "client")]
+#[tokio::test]
+async fn test_typed_client_sends_the_api_key() {
+    use mcp::client::McpClient;
+
+    let (server_addr, shutdown_tx, server_handle) =
+        setup_test_server_with_options(TestServerOptions {
+            auth: Some(Authenticator::ApiKey(ApiKeyAuth::new("test-api-key"))),
+            ..TestServerOptions::default()
+        })
+        .await;
+    let base_url = format!("http://{}", server_addr);
+
+    let err = McpClient::new(&base_url)
+        .list(Default::default())
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Some("AUTH_ERROR"));
+
+    let page = McpClient::new(&base_url)
+        .with_api_key("test-api-key")
+        .list(Default::default())
+        .await
+        .unwrap();
+    assert_eq!(page.offset, 0);
 
     // Shutdown the server
     shutdown_tx.send(()).unwrap();